@@ -21,6 +21,14 @@ pub enum CheckApiKeyError {
     UnknownValue(serde_json::Value),
 }
 
+/// struct for typed errors of method [`internal_get_account_report`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InternalGetAccountReportError {
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
 /// struct for typed errors of method [`internal_get_account_state`]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -29,6 +37,28 @@ pub enum InternalGetAccountStateError {
     UnknownValue(serde_json::Value),
 }
 
+/// struct for typed errors of method [`internal_get_webhook_deliveries`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InternalGetWebhookDeliveriesError {
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`internal_post_api_key_revoked`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InternalPostApiKeyRevokedError {
+    UnknownValue(serde_json::Value),
+}
+
+/// struct for typed errors of method [`post_migrate_json`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PostMigrateJsonError {
+    Status500(),
+    UnknownValue(serde_json::Value),
+}
+
 pub async fn check_api_key(
     configuration: &configuration::Configuration,
     api_key: crate::models::ApiKey,
@@ -70,6 +100,46 @@ pub async fn check_api_key(
     }
 }
 
+/// Registrations per sign in provider, setup completion rate and daily and weekly active accounts.
+pub async fn internal_get_account_report(
+    configuration: &configuration::Configuration,
+) -> Result<crate::models::AccountReport, Error<InternalGetAccountReportError>> {
+    let local_var_configuration = configuration;
+
+    let local_var_client = &local_var_configuration.client;
+
+    let local_var_uri_str = format!(
+        "{}/internal/reports/accounts",
+        local_var_configuration.base_path
+    );
+    let mut local_var_req_builder =
+        local_var_client.request(reqwest::Method::GET, local_var_uri_str.as_str());
+
+    if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
+        local_var_req_builder =
+            local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        serde_json::from_str(&local_var_content).map_err(Error::from)
+    } else {
+        let local_var_entity: Option<InternalGetAccountReportError> =
+            serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent {
+            status: local_var_status,
+            content: local_var_content,
+            entity: local_var_entity,
+        };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
+
 pub async fn internal_get_account_state(
     configuration: &configuration::Configuration,
     account_id: &str,
@@ -110,3 +180,125 @@ pub async fn internal_get_account_state(
         Err(Error::ResponseError(local_var_error))
     }
 }
+
+/// Status of the latest account lifecycle event webhook deliveries.
+pub async fn internal_get_webhook_deliveries(
+    configuration: &configuration::Configuration,
+) -> Result<crate::models::WebhookDeliveryList, Error<InternalGetWebhookDeliveriesError>> {
+    let local_var_configuration = configuration;
+
+    let local_var_client = &local_var_configuration.client;
+
+    let local_var_uri_str = format!(
+        "{}/internal/webhook_deliveries",
+        local_var_configuration.base_path
+    );
+    let mut local_var_req_builder =
+        local_var_client.request(reqwest::Method::GET, local_var_uri_str.as_str());
+
+    if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
+        local_var_req_builder =
+            local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        serde_json::from_str(&local_var_content).map_err(Error::from)
+    } else {
+        let local_var_entity: Option<InternalGetWebhookDeliveriesError> =
+            serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent {
+            status: local_var_status,
+            content: local_var_content,
+            entity: local_var_entity,
+        };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
+
+/// Account server calls this when an API key is revoked, so that the key is removed from the cache of keys checked from the account server. Request body is the SHA-256 hash of the API key.
+pub async fn internal_post_api_key_revoked(
+    configuration: &configuration::Configuration,
+    token_hash: &str,
+) -> Result<(), Error<InternalPostApiKeyRevokedError>> {
+    let local_var_configuration = configuration;
+
+    let local_var_client = &local_var_configuration.client;
+
+    let local_var_uri_str = format!(
+        "{}/internal/api_key_revoked",
+        local_var_configuration.base_path
+    );
+    let mut local_var_req_builder =
+        local_var_client.request(reqwest::Method::POST, local_var_uri_str.as_str());
+
+    if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
+        local_var_req_builder =
+            local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+    local_var_req_builder = local_var_req_builder.json(&token_hash);
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        Ok(())
+    } else {
+        let local_var_entity: Option<InternalPostApiKeyRevokedError> =
+            serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent {
+            status: local_var_status,
+            content: local_var_content,
+            entity: local_var_entity,
+        };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
+
+/// Rewrite all stored JSON data (Account, AccountSetup, NotificationSettings) with the current JSON data version. Reading older data migrates it also lazily, so this is only needed when all data should be up to date.
+pub async fn post_migrate_json(
+    configuration: &configuration::Configuration,
+) -> Result<crate::models::JsonMigrationResult, Error<PostMigrateJsonError>> {
+    let local_var_configuration = configuration;
+
+    let local_var_client = &local_var_configuration.client;
+
+    let local_var_uri_str = format!(
+        "{}/internal/migrate_json",
+        local_var_configuration.base_path
+    );
+    let mut local_var_req_builder =
+        local_var_client.request(reqwest::Method::POST, local_var_uri_str.as_str());
+
+    if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
+        local_var_req_builder =
+            local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+
+    let local_var_req = local_var_req_builder.build()?;
+    let local_var_resp = local_var_client.execute(local_var_req).await?;
+
+    let local_var_status = local_var_resp.status();
+    let local_var_content = local_var_resp.text().await?;
+
+    if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
+        serde_json::from_str(&local_var_content).map_err(Error::from)
+    } else {
+        let local_var_entity: Option<PostMigrateJsonError> =
+            serde_json::from_str(&local_var_content).ok();
+        let local_var_error = ResponseContent {
+            status: local_var_status,
+            content: local_var_content,
+            entity: local_var_entity,
+        };
+        Err(Error::ResponseError(local_var_error))
+    }
+}
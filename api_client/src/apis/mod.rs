@@ -94,7 +94,14 @@ pub fn parse_deep_object(prefix: &str, value: &serde_json::Value) -> Vec<(String
 
 pub mod account_api;
 pub mod accountinternal_api;
+pub mod admin_api;
+pub mod cache_api;
 pub mod calculator_api;
+pub mod clock_api;
 pub mod common_api;
+pub mod metrics_api;
+pub mod organization_api;
+pub mod reload_api;
+pub mod standby_api;
 
 pub mod configuration;
@@ -41,3 +41,33 @@ pub async fn api_available(configuration: &configuration::Configuration) -> Resu
 
     Ok(())
 }
+
+/// Returns Ok(true) if `GET /health` reports that the server is ready.
+pub async fn server_ready(configuration: &configuration::Configuration) -> Result<bool, ()> {
+    let local_var_configuration = configuration;
+
+    let local_var_client = &local_var_configuration.client;
+
+    let local_var_uri_str = format!("{}/health", local_var_configuration.base_path);
+    let mut local_var_req_builder =
+        local_var_client.request(reqwest::Method::GET, local_var_uri_str.as_str());
+
+    if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
+        local_var_req_builder =
+            local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
+    }
+
+    let local_var_req = local_var_req_builder.build().map_err(|_| ())?;
+    let local_var_resp = local_var_client
+        .execute(local_var_req)
+        .await
+        .map_err(|_| ())?;
+    let local_var_content = local_var_resp.text().await.map_err(|_| ())?;
+
+    let ready = serde_json::from_str::<serde_json::Value>(&local_var_content)
+        .ok()
+        .and_then(|value| value.get("ready").and_then(|ready| ready.as_bool()))
+        .unwrap_or(false);
+
+    Ok(ready)
+}
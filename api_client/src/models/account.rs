@@ -10,12 +10,32 @@
 
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct Account {
+    /// Registration time. None if the account was registered before registration time was saved.
+    #[serde(
+        rename = "created_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_unix_time: Option<Option<i64>>,
+    /// None if the account has not logged in after login time was saved.
+    #[serde(
+        rename = "last_login_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_login_unix_time: Option<Option<i64>>,
     #[serde(rename = "state")]
     pub state: crate::models::AccountState,
 }
 
 impl Account {
     pub fn new(state: crate::models::AccountState) -> Account {
-        Account { state }
+        Account {
+            created_unix_time: None,
+            last_login_unix_time: None,
+            state,
+        }
     }
 }
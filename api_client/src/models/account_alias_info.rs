@@ -0,0 +1,27 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountAliasInfo {
+    /// None if the account was registered before aliases were added.
+    #[serde(
+        rename = "alias",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub alias: Option<Option<String>>,
+}
+
+impl AccountAliasInfo {
+    pub fn new() -> AccountAliasInfo {
+        AccountAliasInfo { alias: None }
+    }
+}
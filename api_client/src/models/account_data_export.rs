@@ -0,0 +1,73 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountDataExport : All data which is stored for the account. Data is included only from enabled server components.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountDataExport {
+    #[serde(
+        rename = "account",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account: Option<Option<Box<crate::models::Account>>>,
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+    #[serde(
+        rename = "account_setup",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account_setup: Option<Option<Box<crate::models::AccountSetup>>>,
+    /// Calculator history entries in creation order. None if the account does not have history entries.
+    #[serde(
+        rename = "calculator_history",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub calculator_history: Option<Option<Vec<crate::models::HistoryEntry>>>,
+    #[serde(
+        rename = "calculator_state",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub calculator_state: Option<Option<Box<crate::models::CalculatorState>>>,
+    #[serde(rename = "created_unix_time")]
+    pub created_unix_time: i64,
+    #[serde(
+        rename = "sign_in_with",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sign_in_with: Option<Option<Box<crate::models::SignInWithExport>>>,
+}
+
+impl AccountDataExport {
+    /// All data which is stored for the account. Data is included only from enabled server components.
+    pub fn new(
+        account_id: crate::models::AccountIdLight,
+        created_unix_time: i64,
+    ) -> AccountDataExport {
+        AccountDataExport {
+            account: None,
+            account_id: Box::new(account_id),
+            account_setup: None,
+            calculator_history: None,
+            calculator_state: None,
+            created_unix_time,
+            sign_in_with: None,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountEventType : Account lifecycle event which is sent to the configured webhooks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum AccountEventType {
+    #[serde(rename = "created")]
+    Created,
+    #[serde(rename = "setup_completed")]
+    SetupCompleted,
+    #[serde(rename = "banned")]
+    Banned,
+    #[serde(rename = "deleted")]
+    Deleted,
+    #[serde(rename = "deletion_canceled")]
+    DeletionCanceled,
+    #[serde(rename = "purged")]
+    Purged,
+    #[serde(rename = "sessions_revoked")]
+    SessionsRevoked,
+    #[serde(rename = "inactive")]
+    Inactive,
+}
+
+impl ToString for AccountEventType {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Created => String::from("created"),
+            Self::SetupCompleted => String::from("setup_completed"),
+            Self::Banned => String::from("banned"),
+            Self::Deleted => String::from("deleted"),
+            Self::DeletionCanceled => String::from("deletion_canceled"),
+            Self::Purged => String::from("purged"),
+            Self::SessionsRevoked => String::from("sessions_revoked"),
+            Self::Inactive => String::from("inactive"),
+        }
+    }
+}
+
+impl Default for AccountEventType {
+    fn default() -> AccountEventType {
+        Self::Created
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountExport : Account data export bundle. Data is included only from enabled server components.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountExport {
+    #[serde(
+        rename = "account",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account: Option<Option<Box<crate::models::Account>>>,
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+    #[serde(
+        rename = "account_setup",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account_setup: Option<Option<Box<crate::models::AccountSetup>>>,
+    #[serde(
+        rename = "calculation_schedules",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub calculation_schedules: Option<Option<Vec<crate::models::CalculationSchedule>>>,
+    #[serde(
+        rename = "calculator_settings",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub calculator_settings: Option<Option<Box<crate::models::CalculatorSettings>>>,
+    #[serde(
+        rename = "calculator_slots",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub calculator_slots: Option<Option<Vec<crate::models::CalculatorSlot>>>,
+    #[serde(
+        rename = "calculator_state",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub calculator_state: Option<Option<Box<crate::models::CalculatorState>>>,
+    #[serde(rename = "created_unix_time")]
+    pub created_unix_time: i64,
+    /// Bundle can not be downloaded after this time.
+    #[serde(rename = "expires_unix_time")]
+    pub expires_unix_time: i64,
+    #[serde(
+        rename = "notification_settings",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub notification_settings: Option<Option<Box<crate::models::NotificationSettings>>>,
+    /// Data residency region of the account. None if the account does not have a region.
+    #[serde(
+        rename = "region",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub region: Option<Option<String>>,
+}
+
+impl AccountExport {
+    /// Account data export bundle. Data is included only from enabled server components.
+    pub fn new(
+        account_id: crate::models::AccountIdLight,
+        created_unix_time: i64,
+        expires_unix_time: i64,
+    ) -> AccountExport {
+        AccountExport {
+            account: None,
+            account_id: Box::new(account_id),
+            account_setup: None,
+            calculation_schedules: None,
+            calculator_settings: None,
+            calculator_slots: None,
+            calculator_state: None,
+            created_unix_time,
+            expires_unix_time,
+            notification_settings: None,
+            region: None,
+        }
+    }
+}
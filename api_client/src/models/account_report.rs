@@ -0,0 +1,61 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountReport : Account registration and activity summary.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountReport {
+    /// Accounts active during the last 24 hours.
+    #[serde(rename = "daily_active_accounts")]
+    pub daily_active_accounts: i64,
+    #[serde(rename = "generated_unix_time")]
+    pub generated_unix_time: i64,
+    /// Count of all accounts.
+    #[serde(rename = "registrations")]
+    pub registrations: i64,
+    #[serde(rename = "registrations_per_provider")]
+    pub registrations_per_provider: Vec<crate::models::ProviderRegistrations>,
+    #[serde(rename = "registrations_per_region")]
+    pub registrations_per_region: Vec<crate::models::RegionRegistrations>,
+    /// Count of accounts which are not in initial setup state.
+    #[serde(rename = "setup_completed")]
+    pub setup_completed: i64,
+    /// Value `setup_completed / registrations`. Zero if there are no accounts.
+    #[serde(rename = "setup_completion_rate")]
+    pub setup_completion_rate: f64,
+    /// Accounts active during the last 7 days.
+    #[serde(rename = "weekly_active_accounts")]
+    pub weekly_active_accounts: i64,
+}
+
+impl AccountReport {
+    /// Account registration and activity summary.
+    pub fn new(
+        daily_active_accounts: i64,
+        generated_unix_time: i64,
+        registrations: i64,
+        registrations_per_provider: Vec<crate::models::ProviderRegistrations>,
+        registrations_per_region: Vec<crate::models::RegionRegistrations>,
+        setup_completed: i64,
+        setup_completion_rate: f64,
+        weekly_active_accounts: i64,
+    ) -> AccountReport {
+        AccountReport {
+            daily_active_accounts,
+            generated_unix_time,
+            registrations,
+            registrations_per_provider,
+            registrations_per_region,
+            setup_completed,
+            setup_completion_rate,
+            weekly_active_accounts,
+        }
+    }
+}
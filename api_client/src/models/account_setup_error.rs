@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountSetupError : AccountSetup validation failed.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountSetupError {
+    #[serde(rename = "code")]
+    pub code: crate::models::AccountSetupErrorCode,
+}
+
+impl AccountSetupError {
+    /// AccountSetup validation failed.
+    pub fn new(code: crate::models::AccountSetupErrorCode) -> AccountSetupError {
+        AccountSetupError { code }
+    }
+}
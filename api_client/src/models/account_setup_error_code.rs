@@ -0,0 +1,38 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountSetupErrorCode : Reason why AccountSetup was rejected.
+
+/// Reason why AccountSetup was rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum AccountSetupErrorCode {
+    #[serde(rename = "EmailEmpty")]
+    EmailEmpty,
+    #[serde(rename = "EmailTooLong")]
+    EmailTooLong,
+    #[serde(rename = "EmailInvalid")]
+    EmailInvalid,
+}
+
+impl ToString for AccountSetupErrorCode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::EmailEmpty => String::from("EmailEmpty"),
+            Self::EmailTooLong => String::from("EmailTooLong"),
+            Self::EmailInvalid => String::from("EmailInvalid"),
+        }
+    }
+}
+
+impl Default for AccountSetupErrorCode {
+    fn default() -> AccountSetupErrorCode {
+        Self::EmailEmpty
+    }
+}
@@ -15,6 +15,10 @@ pub enum AccountState {
     InitialSetup,
     #[serde(rename = "Normal")]
     Normal,
+    #[serde(rename = "Banned")]
+    Banned,
+    #[serde(rename = "PendingDeletion")]
+    PendingDeletion,
 }
 
 impl ToString for AccountState {
@@ -22,6 +26,8 @@ impl ToString for AccountState {
         match self {
             Self::InitialSetup => String::from("InitialSetup"),
             Self::Normal => String::from("Normal"),
+            Self::Banned => String::from("Banned"),
+            Self::PendingDeletion => String::from("PendingDeletion"),
         }
     }
 }
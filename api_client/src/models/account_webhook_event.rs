@@ -0,0 +1,41 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AccountWebhookEvent : Webhook request body.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AccountWebhookEvent {
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+    #[serde(rename = "event")]
+    pub event: crate::models::AccountEventType,
+    /// Same for every delivery attempt of the event.
+    #[serde(rename = "event_id")]
+    pub event_id: uuid::Uuid,
+    #[serde(rename = "unix_time")]
+    pub unix_time: i64,
+}
+
+impl AccountWebhookEvent {
+    /// Webhook request body.
+    pub fn new(
+        account_id: crate::models::AccountIdLight,
+        event: crate::models::AccountEventType,
+        event_id: uuid::Uuid,
+        unix_time: i64,
+    ) -> AccountWebhookEvent {
+        AccountWebhookEvent {
+            account_id: Box::new(account_id),
+            event,
+            event_id,
+            unix_time,
+        }
+    }
+}
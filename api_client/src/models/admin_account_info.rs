@@ -0,0 +1,56 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AdminAccountInfo {
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+    /// None if the account was registered before registration time was saved.
+    #[serde(
+        rename = "created_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_unix_time: Option<Option<i64>>,
+    /// None if the account has not logged in after login time was saved.
+    #[serde(
+        rename = "last_login_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_login_unix_time: Option<Option<i64>>,
+    /// Data residency region. None if the account does not have a region.
+    #[serde(
+        rename = "region",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub region: Option<Option<String>>,
+    #[serde(rename = "state")]
+    pub state: crate::models::AccountState,
+}
+
+impl AdminAccountInfo {
+    pub fn new(
+        account_id: crate::models::AccountIdLight,
+        state: crate::models::AccountState,
+    ) -> AdminAccountInfo {
+        AdminAccountInfo {
+            account_id: Box::new(account_id),
+            created_unix_time: None,
+            last_login_unix_time: None,
+            region: None,
+            state,
+        }
+    }
+}
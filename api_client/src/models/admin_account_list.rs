@@ -0,0 +1,36 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AdminAccountList : Accounts in registration order.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct AdminAccountList {
+    #[serde(rename = "accounts")]
+    pub accounts: Vec<crate::models::AdminAccountInfo>,
+    #[serde(rename = "page")]
+    pub page: i32,
+    #[serde(rename = "page_size")]
+    pub page_size: i32,
+}
+
+impl AdminAccountList {
+    /// Accounts in registration order.
+    pub fn new(
+        accounts: Vec<crate::models::AdminAccountInfo>,
+        page: i32,
+        page_size: i32,
+    ) -> AdminAccountList {
+        AdminAccountList {
+            accounts,
+            page,
+            page_size,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// AngleMode : Unit of trigonometric function arguments.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum AngleMode {
+    #[serde(rename = "Radians")]
+    Radians,
+    #[serde(rename = "Degrees")]
+    Degrees,
+}
+
+impl ToString for AngleMode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Radians => String::from("Radians"),
+            Self::Degrees => String::from("Degrees"),
+        }
+    }
+}
+
+impl Default for AngleMode {
+    fn default() -> AngleMode {
+        Self::Radians
+    }
+}
@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct BackendVersion {
+    #[serde(rename = "backend_version")]
+    pub backend_version: String,
+}
+
+impl BackendVersion {
+    pub fn new(backend_version: String) -> BackendVersion {
+        BackendVersion { backend_version }
+    }
+}
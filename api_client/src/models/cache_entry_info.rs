@@ -0,0 +1,60 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CacheEntryInfo : Account's cache entry. Access token is not included.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CacheEntryInfo {
+    #[serde(rename = "access_token_exists")]
+    pub access_token_exists: bool,
+    #[serde(
+        rename = "account",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account: Option<Option<Box<crate::models::Account>>>,
+    /// Cached account data equals to data in SQLite. None if account data is not cached.
+    #[serde(
+        rename = "account_matches_database",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account_matches_database: Option<Option<bool>>,
+    #[serde(
+        rename = "client_version",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub client_version: Option<Option<Box<crate::models::ClientVersion>>>,
+    /// IP address and port of the current WebSocket connection.
+    #[serde(
+        rename = "current_connection",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub current_connection: Option<Option<String>>,
+}
+
+impl CacheEntryInfo {
+    /// Account's cache entry. Access token is not included.
+    pub fn new(access_token_exists: bool) -> CacheEntryInfo {
+        CacheEntryInfo {
+            access_token_exists,
+            account: None,
+            account_matches_database: None,
+            client_version: None,
+            current_connection: None,
+        }
+    }
+}
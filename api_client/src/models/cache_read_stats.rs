@@ -0,0 +1,35 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CacheReadStats : Counters of cached JSON data reads. Counters are totals since server start.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CacheReadStats {
+    /// Reads which failed.
+    #[serde(rename = "failures")]
+    pub failures: i64,
+    /// Reads which did not find the data from the cache, so the data was read from the database and added to the cache.
+    #[serde(rename = "fallbacks")]
+    pub fallbacks: i64,
+    /// Reads which found the data from the cache.
+    #[serde(rename = "hits")]
+    pub hits: i64,
+}
+
+impl CacheReadStats {
+    /// Counters of cached JSON data reads. Counters are totals since server start.
+    pub fn new(failures: i64, fallbacks: i64, hits: i64) -> CacheReadStats {
+        CacheReadStats {
+            failures,
+            fallbacks,
+            hits,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculationRequest : Expression which server evaluates.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculationRequest {
+    #[serde(rename = "expression")]
+    pub expression: String,
+}
+
+impl CalculationRequest {
+    /// Expression which server evaluates.
+    pub fn new(expression: String) -> CalculationRequest {
+        CalculationRequest { expression }
+    }
+}
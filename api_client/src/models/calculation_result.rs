@@ -0,0 +1,37 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculationResult : Successful evaluation. Formatted result is also saved as the calculator state.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculationResult {
+    /// Result formatted with account's calculator settings.
+    #[serde(rename = "formatted_result")]
+    pub formatted_result: String,
+    #[serde(rename = "result")]
+    pub result: f64,
+    #[serde(rename = "warnings")]
+    pub warnings: Vec<serde_json::Value>,
+}
+
+impl CalculationResult {
+    /// Successful evaluation. Formatted result is also saved as the calculator state.
+    pub fn new(
+        formatted_result: String,
+        result: f64,
+        warnings: Vec<serde_json::Value>,
+    ) -> CalculationResult {
+        CalculationResult {
+            formatted_result,
+            result,
+            warnings,
+        }
+    }
+}
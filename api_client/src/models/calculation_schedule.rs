@@ -0,0 +1,41 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculationSchedule {
+    #[serde(rename = "expression")]
+    pub expression: String,
+    #[serde(rename = "next_run_unix_time")]
+    pub next_run_unix_time: i64,
+    #[serde(
+        rename = "repeat_interval_seconds",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub repeat_interval_seconds: Option<Option<i64>>,
+    #[serde(rename = "schedule_id")]
+    pub schedule_id: i64,
+}
+
+impl CalculationSchedule {
+    pub fn new(
+        expression: String,
+        next_run_unix_time: i64,
+        schedule_id: i64,
+    ) -> CalculationSchedule {
+        CalculationSchedule {
+            expression,
+            next_run_unix_time,
+            repeat_interval_seconds: None,
+            schedule_id,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculationScheduleList {
+    #[serde(rename = "schedules")]
+    pub schedules: Vec<crate::models::CalculationSchedule>,
+}
+
+impl CalculationScheduleList {
+    pub fn new(schedules: Vec<crate::models::CalculationSchedule>) -> CalculationScheduleList {
+        CalculationScheduleList { schedules }
+    }
+}
@@ -0,0 +1,25 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculatorBroadcast : Broadcast mode of the current account.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculatorBroadcast {
+    /// Send calculator state changes to the WebSocket connections of the organization members.
+    #[serde(rename = "enabled")]
+    pub enabled: bool,
+}
+
+impl CalculatorBroadcast {
+    /// Broadcast mode of the current account.
+    pub fn new(enabled: bool) -> CalculatorBroadcast {
+        CalculatorBroadcast { enabled }
+    }
+}
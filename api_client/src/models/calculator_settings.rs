@@ -0,0 +1,47 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculatorSettings : Account's calculator settings. Settings affect evaluation and result formatting.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculatorSettings {
+    #[serde(rename = "angle_mode")]
+    pub angle_mode: crate::models::AngleMode,
+    /// Digits after the decimal separator in formatted results. If None, the shortest exact representation is used.
+    #[serde(
+        rename = "display_precision",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_precision: Option<Option<i32>>,
+    /// BCP 47 language tag, for example "en-US".
+    #[serde(rename = "locale")]
+    pub locale: String,
+    /// Group integer digits with the locale's thousands separator.
+    #[serde(rename = "thousands_separator")]
+    pub thousands_separator: bool,
+}
+
+impl CalculatorSettings {
+    /// Account's calculator settings. Settings affect evaluation and result formatting.
+    pub fn new(
+        angle_mode: crate::models::AngleMode,
+        locale: String,
+        thousands_separator: bool,
+    ) -> CalculatorSettings {
+        CalculatorSettings {
+            angle_mode,
+            display_precision: None,
+            locale,
+            thousands_separator,
+        }
+    }
+}
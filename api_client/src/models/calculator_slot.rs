@@ -0,0 +1,32 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculatorSlot : Named calculator state which is stored in addition to the current calculator state.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculatorSlot {
+    #[serde(rename = "slot_name")]
+    pub slot_name: String,
+    #[serde(rename = "state")]
+    pub state: String,
+    #[serde(rename = "updated_unix_time")]
+    pub updated_unix_time: i64,
+}
+
+impl CalculatorSlot {
+    /// Named calculator state which is stored in addition to the current calculator state.
+    pub fn new(slot_name: String, state: String, updated_unix_time: i64) -> CalculatorSlot {
+        CalculatorSlot {
+            slot_name,
+            state,
+            updated_unix_time,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculatorSlotList : Slots are ordered by name.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculatorSlotList {
+    #[serde(rename = "slots")]
+    pub slots: Vec<crate::models::CalculatorSlot>,
+}
+
+impl CalculatorSlotList {
+    /// Slots are ordered by name.
+    pub fn new(slots: Vec<crate::models::CalculatorSlot>) -> CalculatorSlotList {
+        CalculatorSlotList { slots }
+    }
+}
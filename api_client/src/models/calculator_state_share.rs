@@ -0,0 +1,26 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CalculatorStateShare : Account which can read the calculator state of the current account.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CalculatorStateShare {
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+}
+
+impl CalculatorStateShare {
+    /// Account which can read the calculator state of the current account.
+    pub fn new(account_id: crate::models::AccountIdLight) -> CalculatorStateShare {
+        CalculatorStateShare {
+            account_id: Box::new(account_id),
+        }
+    }
+}
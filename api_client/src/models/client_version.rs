@@ -0,0 +1,29 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// ClientVersion : Client sends this as JSON Text when WebSocket connection starts.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ClientVersion {
+    #[serde(rename = "app_version")]
+    pub app_version: String,
+    #[serde(rename = "protocol_version")]
+    pub protocol_version: i32,
+}
+
+impl ClientVersion {
+    /// Client sends this as JSON Text when WebSocket connection starts.
+    pub fn new(app_version: String, protocol_version: i32) -> ClientVersion {
+        ClientVersion {
+            app_version,
+            protocol_version,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ClockAdvance {
+    #[serde(rename = "seconds")]
+    pub seconds: i64,
+}
+
+impl ClockAdvance {
+    pub fn new(seconds: i64) -> ClockAdvance {
+        ClockAdvance { seconds }
+    }
+}
@@ -0,0 +1,28 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ClockTime {
+    /// Total time which the clock has been moved forward.
+    #[serde(rename = "offset_seconds")]
+    pub offset_seconds: i64,
+    /// Current server time.
+    #[serde(rename = "unix_time")]
+    pub unix_time: i64,
+}
+
+impl ClockTime {
+    pub fn new(offset_seconds: i64, unix_time: i64) -> ClockTime {
+        ClockTime {
+            offset_seconds,
+            unix_time,
+        }
+    }
+}
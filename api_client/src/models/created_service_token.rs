@@ -0,0 +1,29 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// CreatedServiceToken : Service token which was just created. The token value is not available after this.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct CreatedServiceToken {
+    #[serde(rename = "token")]
+    pub token: Box<crate::models::ApiKey>,
+    #[serde(rename = "token_id")]
+    pub token_id: i64,
+}
+
+impl CreatedServiceToken {
+    /// Service token which was just created. The token value is not available after this.
+    pub fn new(token: crate::models::ApiKey, token_id: i64) -> CreatedServiceToken {
+        CreatedServiceToken {
+            token: Box::new(token),
+            token_id,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Diagnostic {
+    #[serde(
+        rename = "code",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub code: Option<Option<crate::models::DiagnosticCode>>,
+    /// Tokens which would have been valid at the error position.
+    #[serde(rename = "expected")]
+    pub expected: Vec<String>,
+    /// Message in English or in account's language if the diagnostic is localized.
+    #[serde(rename = "message")]
+    pub message: String,
+    #[serde(rename = "severity")]
+    pub severity: crate::models::Severity,
+    #[serde(rename = "span")]
+    pub span: Box<crate::models::Span>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        expected: Vec<String>,
+        message: String,
+        severity: crate::models::Severity,
+        span: crate::models::Span,
+    ) -> Diagnostic {
+        Diagnostic {
+            code: None,
+            expected,
+            message,
+            severity,
+            span: Box::new(span),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// DiagnosticCode : Machine readable error code for errors which clients might want to handle differently than other errors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum DiagnosticCode {
+    #[serde(rename = "ExpressionTooLong")]
+    ExpressionTooLong,
+    #[serde(rename = "ExpressionTooDeep")]
+    ExpressionTooDeep,
+    #[serde(rename = "StepLimitExceeded")]
+    StepLimitExceeded,
+    #[serde(rename = "TimeLimitExceeded")]
+    TimeLimitExceeded,
+}
+
+impl ToString for DiagnosticCode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::ExpressionTooLong => String::from("ExpressionTooLong"),
+            Self::ExpressionTooDeep => String::from("ExpressionTooDeep"),
+            Self::StepLimitExceeded => String::from("StepLimitExceeded"),
+            Self::TimeLimitExceeded => String::from("TimeLimitExceeded"),
+        }
+    }
+}
+
+impl Default for DiagnosticCode {
+    fn default() -> DiagnosticCode {
+        Self::ExpressionTooLong
+    }
+}
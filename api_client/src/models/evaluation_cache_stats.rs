@@ -0,0 +1,41 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct EvaluationCacheStats {
+    #[serde(rename = "capacity")]
+    pub capacity: i64,
+    #[serde(rename = "enabled")]
+    pub enabled: bool,
+    #[serde(rename = "entries")]
+    pub entries: i64,
+    #[serde(rename = "hits")]
+    pub hits: i64,
+    #[serde(rename = "misses")]
+    pub misses: i64,
+}
+
+impl EvaluationCacheStats {
+    pub fn new(
+        capacity: i64,
+        enabled: bool,
+        entries: i64,
+        hits: i64,
+        misses: i64,
+    ) -> EvaluationCacheStats {
+        EvaluationCacheStats {
+            capacity,
+            enabled,
+            entries,
+            hits,
+            misses,
+        }
+    }
+}
@@ -8,17 +8,31 @@
  * Generated by: https://openapi-generator.tech
  */
 
-///
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+/// EventToClient : Event which server sends to the WebSocket connection as JSON text. Client should fetch the changed data using the API.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum EventToClient {
     #[serde(rename = "AccountStateChanged")]
     AccountStateChanged,
+    #[serde(rename = "CalculatorStateChanged")]
+    CalculatorStateChanged,
+    #[serde(rename = "ScheduledCalculationCompleted")]
+    ScheduledCalculationCompleted,
+    /// Account logged in from another device. Sent to the WebSocket connection of the previous session.
+    #[serde(rename = "NewSessionCreated")]
+    NewSessionCreated(Box<crate::models::NewSessionInfo>),
+    /// Replaced refresh token of the account was used, so all sessions are revoked and the user must login again. Sent to the WebSocket connection of the current session before it is closed.
+    #[serde(rename = "SessionsRevoked")]
+    SessionsRevoked(Box<crate::models::SessionsRevokedInfo>),
 }
 
 impl ToString for EventToClient {
     fn to_string(&self) -> String {
         match self {
             Self::AccountStateChanged => String::from("AccountStateChanged"),
+            Self::CalculatorStateChanged => String::from("CalculatorStateChanged"),
+            Self::ScheduledCalculationCompleted => String::from("ScheduledCalculationCompleted"),
+            Self::NewSessionCreated(_) => String::from("NewSessionCreated"),
+            Self::SessionsRevoked(_) => String::from("SessionsRevoked"),
         }
     }
 }
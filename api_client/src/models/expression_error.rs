@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// ExpressionError : Expression parsing or evaluation failed.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ExpressionError {
+    #[serde(rename = "diagnostics")]
+    pub diagnostics: Vec<crate::models::Diagnostic>,
+}
+
+impl ExpressionError {
+    /// Expression parsing or evaluation failed.
+    pub fn new(diagnostics: Vec<crate::models::Diagnostic>) -> ExpressionError {
+        ExpressionError { diagnostics }
+    }
+}
@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct HealthStatus {
+    #[serde(rename = "ready")]
+    pub ready: bool,
+}
+
+impl HealthStatus {
+    pub fn new(ready: bool) -> HealthStatus {
+        HealthStatus { ready }
+    }
+}
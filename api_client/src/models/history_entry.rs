@@ -0,0 +1,56 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Error message if evaluation failed.
+    #[serde(
+        rename = "error",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub error: Option<Option<String>>,
+    #[serde(rename = "expression")]
+    pub expression: String,
+    /// Result formatted with account's calculator settings.
+    #[serde(
+        rename = "formatted_result",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub formatted_result: Option<Option<String>>,
+    #[serde(rename = "history_id")]
+    pub history_id: i64,
+    /// None if evaluation failed.
+    #[serde(
+        rename = "result",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub result: Option<Option<f64>>,
+    #[serde(rename = "unix_time")]
+    pub unix_time: i64,
+}
+
+impl HistoryEntry {
+    pub fn new(expression: String, history_id: i64, unix_time: i64) -> HistoryEntry {
+        HistoryEntry {
+            error: None,
+            expression,
+            formatted_result: None,
+            history_id,
+            result: None,
+            unix_time,
+        }
+    }
+}
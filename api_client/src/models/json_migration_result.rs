@@ -0,0 +1,22 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct JsonMigrationResult {
+    /// Count of rewritten JSON objects.
+    #[serde(rename = "rewritten")]
+    pub rewritten: i64,
+}
+
+impl JsonMigrationResult {
+    pub fn new(rewritten: i64) -> JsonMigrationResult {
+        JsonMigrationResult { rewritten }
+    }
+}
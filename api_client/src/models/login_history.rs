@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// LoginHistory : Login history entries starting from the most recent.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct LoginHistory {
+    #[serde(rename = "entries")]
+    pub entries: Vec<crate::models::LoginHistoryEntry>,
+}
+
+impl LoginHistory {
+    /// Login history entries starting from the most recent.
+    pub fn new(entries: Vec<crate::models::LoginHistoryEntry>) -> LoginHistory {
+        LoginHistory { entries }
+    }
+}
@@ -0,0 +1,35 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct LoginHistoryEntry {
+    /// None if the address was not available, for example when an admin ended the session.
+    #[serde(
+        rename = "ip_address",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ip_address: Option<Option<String>>,
+    #[serde(rename = "method")]
+    pub method: crate::models::LoginMethod,
+    #[serde(rename = "unix_time")]
+    pub unix_time: i64,
+}
+
+impl LoginHistoryEntry {
+    pub fn new(method: crate::models::LoginMethod, unix_time: i64) -> LoginHistoryEntry {
+        LoginHistoryEntry {
+            ip_address: None,
+            method,
+            unix_time,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// LoginMethod : How the session was started, continued or ended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum LoginMethod {
+    #[serde(rename = "Login")]
+    Login,
+    #[serde(rename = "SignInWithLogin")]
+    SignInWithLogin,
+    #[serde(rename = "TokenRefresh")]
+    TokenRefresh,
+    #[serde(rename = "TokenExchange")]
+    TokenExchange,
+    #[serde(rename = "Logout")]
+    Logout,
+}
+
+impl ToString for LoginMethod {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Login => String::from("Login"),
+            Self::SignInWithLogin => String::from("SignInWithLogin"),
+            Self::TokenRefresh => String::from("TokenRefresh"),
+            Self::TokenExchange => String::from("TokenExchange"),
+            Self::Logout => String::from("Logout"),
+        }
+    }
+}
+
+impl Default for LoginMethod {
+    fn default() -> LoginMethod {
+        Self::Login
+    }
+}
@@ -1,22 +1,164 @@
 pub mod account;
 pub use self::account::Account;
+pub mod account_alias_info;
+pub use self::account_alias_info::AccountAliasInfo;
+pub mod account_data_export;
+pub use self::account_data_export::AccountDataExport;
+pub mod account_event_type;
+pub use self::account_event_type::AccountEventType;
+pub mod account_export;
+pub use self::account_export::AccountExport;
 pub mod account_id_light;
 pub use self::account_id_light::AccountIdLight;
+pub mod account_report;
+pub use self::account_report::AccountReport;
 pub mod account_setup;
 pub use self::account_setup::AccountSetup;
+pub mod account_setup_error;
+pub use self::account_setup_error::AccountSetupError;
+pub mod account_setup_error_code;
+pub use self::account_setup_error_code::AccountSetupErrorCode;
 pub mod account_state;
 pub use self::account_state::AccountState;
+pub mod account_webhook_event;
+pub use self::account_webhook_event::AccountWebhookEvent;
+pub mod admin_account_info;
+pub use self::admin_account_info::AdminAccountInfo;
+pub mod admin_account_list;
+pub use self::admin_account_list::AdminAccountList;
+pub mod angle_mode;
+pub use self::angle_mode::AngleMode;
 pub mod api_key;
 pub use self::api_key::ApiKey;
 pub mod auth_pair;
 pub use self::auth_pair::AuthPair;
+pub mod backend_version;
+pub use self::backend_version::BackendVersion;
+pub mod cache_entry_info;
+pub use self::cache_entry_info::CacheEntryInfo;
+pub mod cache_read_stats;
+pub use self::cache_read_stats::CacheReadStats;
+pub mod calculation_request;
+pub use self::calculation_request::CalculationRequest;
+pub mod calculation_result;
+pub use self::calculation_result::CalculationResult;
+pub mod calculation_schedule;
+pub use self::calculation_schedule::CalculationSchedule;
+pub mod calculation_schedule_list;
+pub use self::calculation_schedule_list::CalculationScheduleList;
+pub mod calculator_broadcast;
+pub use self::calculator_broadcast::CalculatorBroadcast;
+pub mod calculator_settings;
+pub use self::calculator_settings::CalculatorSettings;
+pub mod calculator_slot;
+pub use self::calculator_slot::CalculatorSlot;
+pub mod calculator_slot_list;
+pub use self::calculator_slot_list::CalculatorSlotList;
 pub mod calculator_state;
 pub use self::calculator_state::CalculatorState;
+pub mod calculator_state_share;
+pub use self::calculator_state_share::CalculatorStateShare;
+pub mod client_version;
+pub use self::client_version::ClientVersion;
+pub mod clock_advance;
+pub use self::clock_advance::ClockAdvance;
+pub mod clock_time;
+pub use self::clock_time::ClockTime;
+pub mod created_service_token;
+pub use self::created_service_token::CreatedServiceToken;
+pub mod diagnostic;
+pub use self::diagnostic::Diagnostic;
+pub mod diagnostic_code;
+pub use self::diagnostic_code::DiagnosticCode;
+pub mod evaluation_cache_stats;
+pub use self::evaluation_cache_stats::EvaluationCacheStats;
 pub mod event_to_client;
 pub use self::event_to_client::EventToClient;
+pub mod expression_error;
+pub use self::expression_error::ExpressionError;
+pub mod health_status;
+pub use self::health_status::HealthStatus;
+pub mod history_entry;
+pub use self::history_entry::HistoryEntry;
+pub mod json_migration_result;
+pub use self::json_migration_result::JsonMigrationResult;
+pub mod login_history;
+pub use self::login_history::LoginHistory;
+pub mod login_history_entry;
+pub use self::login_history_entry::LoginHistoryEntry;
+pub mod login_method;
+pub use self::login_method::LoginMethod;
 pub mod login_result;
 pub use self::login_result::LoginResult;
+pub mod new_calculation_schedule;
+pub use self::new_calculation_schedule::NewCalculationSchedule;
+pub mod new_organization;
+pub use self::new_organization::NewOrganization;
+pub mod new_organization_member;
+pub use self::new_organization_member::NewOrganizationMember;
+pub mod new_service_token;
+pub use self::new_service_token::NewServiceToken;
+pub mod new_session_info;
+pub use self::new_session_info::NewSessionInfo;
+pub mod notification_delivery;
+pub use self::notification_delivery::NotificationDelivery;
+pub mod notification_settings;
+pub use self::notification_settings::NotificationSettings;
+pub mod operation_set;
+pub use self::operation_set::OperationSet;
+pub mod organization;
+pub use self::organization::Organization;
+pub mod organization_id;
+pub use self::organization_id::OrganizationId;
+pub mod organization_member;
+pub use self::organization_member::OrganizationMember;
+pub mod organization_member_list;
+pub use self::organization_member_list::OrganizationMemberList;
+pub mod organization_membership;
+pub use self::organization_membership::OrganizationMembership;
+pub mod organization_settings;
+pub use self::organization_settings::OrganizationSettings;
+pub mod provider_registrations;
+pub use self::provider_registrations::ProviderRegistrations;
+pub mod readiness_check;
+pub use self::readiness_check::ReadinessCheck;
+pub mod readiness_status;
+pub use self::readiness_status::ReadinessStatus;
 pub mod refresh_token;
 pub use self::refresh_token::RefreshToken;
+pub mod region_registrations;
+pub use self::region_registrations::RegionRegistrations;
+pub mod schedule_id;
+pub use self::schedule_id::ScheduleId;
+pub mod server_metrics;
+pub use self::server_metrics::ServerMetrics;
+pub mod service_token_info;
+pub use self::service_token_info::ServiceTokenInfo;
+pub mod service_token_list;
+pub use self::service_token_list::ServiceTokenList;
+pub mod service_token_scope;
+pub use self::service_token_scope::ServiceTokenScope;
+pub mod session_info;
+pub use self::session_info::SessionInfo;
+pub mod sessions_revoked_info;
+pub use self::sessions_revoked_info::SessionsRevokedInfo;
+pub mod severity;
+pub use self::severity::Severity;
+pub mod sign_in_with_export;
+pub use self::sign_in_with_export::SignInWithExport;
 pub mod sign_in_with_login_info;
 pub use self::sign_in_with_login_info::SignInWithLoginInfo;
+pub mod span;
+pub use self::span::Span;
+pub mod stale_account_cleanup_request;
+pub use self::stale_account_cleanup_request::StaleAccountCleanupRequest;
+pub mod stale_account_cleanup_status;
+pub use self::stale_account_cleanup_status::StaleAccountCleanupStatus;
+pub mod webhook_delivery;
+pub use self::webhook_delivery::WebhookDelivery;
+pub mod webhook_delivery_list;
+pub use self::webhook_delivery_list::WebhookDeliveryList;
+pub mod webhook_delivery_status;
+pub use self::webhook_delivery_status::WebhookDeliveryStatus;
+pub mod write_queue_stats;
+pub use self::write_queue_stats::WriteQueueStats;
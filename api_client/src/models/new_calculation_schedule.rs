@@ -0,0 +1,39 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// NewCalculationSchedule : Expression which is evaluated at a future time and optionally repeated.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct NewCalculationSchedule {
+    #[serde(rename = "expression")]
+    pub expression: String,
+    /// Repeat evaluation with this fixed interval from the previous run. If None the schedule is removed after the evaluation.
+    #[serde(
+        rename = "repeat_interval_seconds",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub repeat_interval_seconds: Option<Option<i64>>,
+    /// Unix timestamp of the first evaluation.
+    #[serde(rename = "run_at_unix_time")]
+    pub run_at_unix_time: i64,
+}
+
+impl NewCalculationSchedule {
+    /// Expression which is evaluated at a future time and optionally repeated.
+    pub fn new(expression: String, run_at_unix_time: i64) -> NewCalculationSchedule {
+        NewCalculationSchedule {
+            expression,
+            repeat_interval_seconds: None,
+            run_at_unix_time,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct NewOrganization {
+    /// Maximum length is 100 characters.
+    #[serde(rename = "name")]
+    pub name: String,
+}
+
+impl NewOrganization {
+    pub fn new(name: String) -> NewOrganization {
+        NewOrganization { name }
+    }
+}
@@ -0,0 +1,26 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct NewOrganizationMember {
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+    #[serde(rename = "is_admin")]
+    pub is_admin: bool,
+}
+
+impl NewOrganizationMember {
+    pub fn new(account_id: crate::models::AccountIdLight, is_admin: bool) -> NewOrganizationMember {
+        NewOrganizationMember {
+            account_id: Box::new(account_id),
+            is_admin,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct NewServiceToken {
+    /// Name for identifying the token in the token list. Maximum length is 100 characters.
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "scope")]
+    pub scope: crate::models::ServiceTokenScope,
+}
+
+impl NewServiceToken {
+    pub fn new(name: String, scope: crate::models::ServiceTokenScope) -> NewServiceToken {
+        NewServiceToken { name, scope }
+    }
+}
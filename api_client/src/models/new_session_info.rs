@@ -0,0 +1,47 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// NewSessionInfo : Device info of a new session.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct NewSessionInfo {
+    /// IP address of the login request.
+    #[serde(rename = "address")]
+    pub address: String,
+    /// Country code of the address if GeoIP database is configured.
+    #[serde(
+        rename = "country",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub country: Option<Option<String>>,
+    #[serde(rename = "unix_time")]
+    pub unix_time: i64,
+    #[serde(
+        rename = "user_agent",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub user_agent: Option<Option<String>>,
+}
+
+impl NewSessionInfo {
+    /// Device info of a new session.
+    pub fn new(address: String, unix_time: i64) -> NewSessionInfo {
+        NewSessionInfo {
+            address,
+            country: None,
+            unix_time,
+            user_agent: None,
+        }
+    }
+}
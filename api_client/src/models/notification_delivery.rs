@@ -0,0 +1,39 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// NotificationDelivery : How event is delivered to the client. Events which are not disabled are always sent to the WebSocket connection if it exists.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum NotificationDelivery {
+    #[serde(rename = "WebSocketOnly")]
+    WebSocketOnly,
+    #[serde(rename = "Push")]
+    Push,
+    #[serde(rename = "Email")]
+    Email,
+    #[serde(rename = "Disabled")]
+    Disabled,
+}
+
+impl ToString for NotificationDelivery {
+    fn to_string(&self) -> String {
+        match self {
+            Self::WebSocketOnly => String::from("WebSocketOnly"),
+            Self::Push => String::from("Push"),
+            Self::Email => String::from("Email"),
+            Self::Disabled => String::from("Disabled"),
+        }
+    }
+}
+
+impl Default for NotificationDelivery {
+    fn default() -> NotificationDelivery {
+        Self::WebSocketOnly
+    }
+}
@@ -0,0 +1,36 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// NotificationSettings : Delivery setting for every event type.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    #[serde(rename = "account_state_changed")]
+    pub account_state_changed: crate::models::NotificationDelivery,
+    #[serde(rename = "new_session_created")]
+    pub new_session_created: crate::models::NotificationDelivery,
+    #[serde(rename = "scheduled_calculation_completed")]
+    pub scheduled_calculation_completed: crate::models::NotificationDelivery,
+}
+
+impl NotificationSettings {
+    /// Delivery setting for every event type.
+    pub fn new(
+        account_state_changed: crate::models::NotificationDelivery,
+        new_session_created: crate::models::NotificationDelivery,
+        scheduled_calculation_completed: crate::models::NotificationDelivery,
+    ) -> NotificationSettings {
+        NotificationSettings {
+            account_state_changed,
+            new_session_created,
+            scheduled_calculation_completed,
+        }
+    }
+}
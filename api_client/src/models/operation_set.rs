@@ -0,0 +1,39 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum OperationSet {
+    #[serde(rename = "basic")]
+    Basic,
+    #[serde(rename = "scientific")]
+    Scientific,
+    #[serde(rename = "statistics")]
+    Statistics,
+    #[serde(rename = "currency")]
+    Currency,
+}
+
+impl ToString for OperationSet {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Basic => String::from("basic"),
+            Self::Scientific => String::from("scientific"),
+            Self::Statistics => String::from("statistics"),
+            Self::Currency => String::from("currency"),
+        }
+    }
+}
+
+impl Default for OperationSet {
+    fn default() -> OperationSet {
+        Self::Basic
+    }
+}
@@ -0,0 +1,29 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Organization {
+    #[serde(rename = "created_unix_time")]
+    pub created_unix_time: i64,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "org_id")]
+    pub org_id: i64,
+}
+
+impl Organization {
+    pub fn new(created_unix_time: i64, name: String, org_id: i64) -> Organization {
+        Organization {
+            created_unix_time,
+            name,
+            org_id,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrganizationId {
+    #[serde(rename = "org_id")]
+    pub org_id: i64,
+}
+
+impl OrganizationId {
+    pub fn new(org_id: i64) -> OrganizationId {
+        OrganizationId { org_id }
+    }
+}
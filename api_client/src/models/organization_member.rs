@@ -0,0 +1,50 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// OrganizationMember : Organization member and its usage.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrganizationMember {
+    #[serde(rename = "account_id")]
+    pub account_id: Box<crate::models::AccountIdLight>,
+    /// Count of calculator history entries.
+    #[serde(rename = "history_entries")]
+    pub history_entries: i64,
+    #[serde(rename = "is_admin")]
+    pub is_admin: bool,
+    #[serde(rename = "joined_unix_time")]
+    pub joined_unix_time: i64,
+    /// Latest saved activity time. None if the account has not been active.
+    #[serde(
+        rename = "last_activity_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_activity_unix_time: Option<Option<i64>>,
+}
+
+impl OrganizationMember {
+    /// Organization member and its usage.
+    pub fn new(
+        account_id: crate::models::AccountIdLight,
+        history_entries: i64,
+        is_admin: bool,
+        joined_unix_time: i64,
+    ) -> OrganizationMember {
+        OrganizationMember {
+            account_id: Box::new(account_id),
+            history_entries,
+            is_admin,
+            joined_unix_time,
+            last_activity_unix_time: None,
+        }
+    }
+}
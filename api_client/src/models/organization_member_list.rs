@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrganizationMemberList {
+    #[serde(rename = "members")]
+    pub members: Vec<crate::models::OrganizationMember>,
+}
+
+impl OrganizationMemberList {
+    pub fn new(members: Vec<crate::models::OrganizationMember>) -> OrganizationMemberList {
+        OrganizationMemberList { members }
+    }
+}
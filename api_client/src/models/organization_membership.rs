@@ -0,0 +1,33 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// OrganizationMembership : Organization of the current account.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrganizationMembership {
+    /// Organization admins can view the member list.
+    #[serde(rename = "is_admin")]
+    pub is_admin: bool,
+    #[serde(rename = "organization")]
+    pub organization: Box<crate::models::Organization>,
+}
+
+impl OrganizationMembership {
+    /// Organization of the current account.
+    pub fn new(
+        is_admin: bool,
+        organization: crate::models::Organization,
+    ) -> OrganizationMembership {
+        OrganizationMembership {
+            is_admin,
+            organization: Box::new(organization),
+        }
+    }
+}
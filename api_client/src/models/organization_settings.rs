@@ -0,0 +1,41 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// OrganizationSettings : Organization specific overrides for the server config. Unset values use the server config.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrganizationSettings {
+    /// Calculator history entries older than this are deleted. Minimum is 1 and maximum is 36500.
+    #[serde(
+        rename = "history_retention_days",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub history_retention_days: Option<Option<i64>>,
+    /// Enabled calculator operation sets. Basic operations are always enabled.
+    #[serde(
+        rename = "operation_sets",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub operation_sets: Option<Option<Vec<crate::models::OperationSet>>>,
+}
+
+impl OrganizationSettings {
+    /// Organization specific overrides for the server config. Unset values use the server config.
+    pub fn new() -> OrganizationSettings {
+        OrganizationSettings {
+            history_retention_days: None,
+            operation_sets: None,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ProviderRegistrations {
+    /// Sign in provider like `google`, `apple` or `none` if account does not use sign in with login.
+    #[serde(rename = "provider")]
+    pub provider: String,
+    #[serde(rename = "registrations")]
+    pub registrations: i64,
+}
+
+impl ProviderRegistrations {
+    pub fn new(provider: String, registrations: i64) -> ProviderRegistrations {
+        ProviderRegistrations {
+            provider,
+            registrations,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ReadinessCheck {
+    /// Check name like `database`.
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "ok")]
+    pub ok: bool,
+}
+
+impl ReadinessCheck {
+    pub fn new(name: String, ok: bool) -> ReadinessCheck {
+        ReadinessCheck { name, ok }
+    }
+}
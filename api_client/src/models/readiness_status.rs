@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ReadinessStatus {
+    #[serde(rename = "checks")]
+    pub checks: Vec<crate::models::ReadinessCheck>,
+    /// True if all checks succeeded.
+    #[serde(rename = "ready")]
+    pub ready: bool,
+}
+
+impl ReadinessStatus {
+    pub fn new(checks: Vec<crate::models::ReadinessCheck>, ready: bool) -> ReadinessStatus {
+        ReadinessStatus { checks, ready }
+    }
+}
@@ -0,0 +1,32 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct RegionRegistrations {
+    /// Data residency region. None for accounts without region.
+    #[serde(
+        rename = "region",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub region: Option<Option<String>>,
+    #[serde(rename = "registrations")]
+    pub registrations: i64,
+}
+
+impl RegionRegistrations {
+    pub fn new(registrations: i64) -> RegionRegistrations {
+        RegionRegistrations {
+            region: None,
+            registrations,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ScheduleId {
+    #[serde(rename = "schedule_id")]
+    pub schedule_id: i64,
+}
+
+impl ScheduleId {
+    pub fn new(schedule_id: i64) -> ScheduleId {
+        ScheduleId { schedule_id }
+    }
+}
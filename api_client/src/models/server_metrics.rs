@@ -0,0 +1,29 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ServerMetrics {
+    #[serde(rename = "cache_reads")]
+    pub cache_reads: Box<crate::models::CacheReadStats>,
+    #[serde(rename = "write_queue")]
+    pub write_queue: Box<crate::models::WriteQueueStats>,
+}
+
+impl ServerMetrics {
+    pub fn new(
+        cache_reads: crate::models::CacheReadStats,
+        write_queue: crate::models::WriteQueueStats,
+    ) -> ServerMetrics {
+        ServerMetrics {
+            cache_reads: Box::new(cache_reads),
+            write_queue: Box::new(write_queue),
+        }
+    }
+}
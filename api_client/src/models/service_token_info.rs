@@ -0,0 +1,40 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// ServiceTokenInfo : Service token without the token value.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ServiceTokenInfo {
+    #[serde(rename = "created_unix_time")]
+    pub created_unix_time: i64,
+    #[serde(rename = "name")]
+    pub name: String,
+    #[serde(rename = "scope")]
+    pub scope: crate::models::ServiceTokenScope,
+    #[serde(rename = "token_id")]
+    pub token_id: i64,
+}
+
+impl ServiceTokenInfo {
+    /// Service token without the token value.
+    pub fn new(
+        created_unix_time: i64,
+        name: String,
+        scope: crate::models::ServiceTokenScope,
+        token_id: i64,
+    ) -> ServiceTokenInfo {
+        ServiceTokenInfo {
+            created_unix_time,
+            name,
+            scope,
+            token_id,
+        }
+    }
+}
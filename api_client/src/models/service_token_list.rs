@@ -0,0 +1,21 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ServiceTokenList {
+    #[serde(rename = "tokens")]
+    pub tokens: Vec<crate::models::ServiceTokenInfo>,
+}
+
+impl ServiceTokenList {
+    pub fn new(tokens: Vec<crate::models::ServiceTokenInfo>) -> ServiceTokenList {
+        ServiceTokenList { tokens }
+    }
+}
@@ -0,0 +1,33 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// ServiceTokenScope : Routes which a service token can access. Service tokens can not access account routes, so the token owner must manage the tokens with an interactive session.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum ServiceTokenScope {
+    #[serde(rename = "CalculatorRead")]
+    CalculatorRead,
+    #[serde(rename = "CalculatorWrite")]
+    CalculatorWrite,
+}
+
+impl ToString for ServiceTokenScope {
+    fn to_string(&self) -> String {
+        match self {
+            Self::CalculatorRead => String::from("CalculatorRead"),
+            Self::CalculatorWrite => String::from("CalculatorWrite"),
+        }
+    }
+}
+
+impl Default for ServiceTokenScope {
+    fn default() -> ServiceTokenScope {
+        Self::CalculatorRead
+    }
+}
@@ -0,0 +1,59 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// SessionInfo : Current session of the account.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct SessionInfo {
+    /// IP address and port of the current WebSocket connection.
+    #[serde(
+        rename = "connection_address",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub connection_address: Option<Option<String>>,
+    /// Unix timestamp of the latest login or request made with the access token.
+    #[serde(
+        rename = "last_activity_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_activity_unix_time: Option<Option<i64>>,
+    /// IP address and port of the latest request made with the access token.
+    #[serde(
+        rename = "last_seen_address",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_seen_address: Option<Option<String>>,
+    /// Country code of the last seen address. Available if server has GeoIP database configured.
+    #[serde(
+        rename = "last_seen_country",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_seen_country: Option<Option<String>>,
+}
+
+impl SessionInfo {
+    /// Current session of the account.
+    pub fn new() -> SessionInfo {
+        SessionInfo {
+            connection_address: None,
+            last_activity_unix_time: None,
+            last_seen_address: None,
+            last_seen_country: None,
+        }
+    }
+}
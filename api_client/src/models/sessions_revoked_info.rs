@@ -0,0 +1,39 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// SessionsRevokedInfo : Connection attempt which used the replaced refresh token.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct SessionsRevokedInfo {
+    /// IP address of the connection attempt.
+    #[serde(rename = "address")]
+    pub address: String,
+    /// Country code of the address if GeoIP database is configured.
+    #[serde(
+        rename = "country",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub country: Option<Option<String>>,
+    #[serde(rename = "unix_time")]
+    pub unix_time: i64,
+}
+
+impl SessionsRevokedInfo {
+    /// Connection attempt which used the replaced refresh token.
+    pub fn new(address: String, unix_time: i64) -> SessionsRevokedInfo {
+        SessionsRevokedInfo {
+            address,
+            country: None,
+            unix_time,
+        }
+    }
+}
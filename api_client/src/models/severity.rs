@@ -0,0 +1,33 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    #[serde(rename = "Error")]
+    Error,
+    #[serde(rename = "Warning")]
+    Warning,
+}
+
+impl ToString for Severity {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Error => String::from("Error"),
+            Self::Warning => String::from("Warning"),
+        }
+    }
+}
+
+impl Default for Severity {
+    fn default() -> Severity {
+        Self::Error
+    }
+}
@@ -0,0 +1,39 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// SignInWithExport : Sign in with account IDs of the account.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct SignInWithExport {
+    #[serde(
+        rename = "apple_account_id",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub apple_account_id: Option<Option<String>>,
+    #[serde(
+        rename = "google_account_id",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub google_account_id: Option<Option<String>>,
+}
+
+impl SignInWithExport {
+    /// Sign in with account IDs of the account.
+    pub fn new() -> SignInWithExport {
+        SignInWithExport {
+            apple_account_id: None,
+            google_account_id: None,
+        }
+    }
+}
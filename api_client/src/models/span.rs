@@ -0,0 +1,28 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// Span : Position in the expression. Positions are character indexes, so those can be used directly for highlighting the expression text.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Span {
+    /// Character after the last character.
+    #[serde(rename = "end")]
+    pub end: i32,
+    /// First character.
+    #[serde(rename = "start")]
+    pub start: i32,
+}
+
+impl Span {
+    /// Position in the expression. Positions are character indexes, so those can be used directly for highlighting the expression text.
+    pub fn new(end: i32, start: i32) -> Span {
+        Span { end, start }
+    }
+}
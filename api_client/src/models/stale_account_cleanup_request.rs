@@ -0,0 +1,46 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct StaleAccountCleanupRequest {
+    /// Accounts handled between progress updates. Default is 100 and maximum is 1000.
+    #[serde(
+        rename = "batch_size",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub batch_size: Option<Option<i32>>,
+    /// Accounts which have not been active, logged in or registered during this many days are stale. Default is the `stale_account_days` config value.
+    #[serde(
+        rename = "inactive_days",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub inactive_days: Option<Option<i32>>,
+    /// Send the `inactive` webhook event for every stale account.
+    #[serde(rename = "notify", skip_serializing_if = "Option::is_none")]
+    pub notify: Option<bool>,
+    /// Request deletion of every stale account. Deleted accounts have the normal deletion grace period.
+    #[serde(rename = "schedule_deletion", skip_serializing_if = "Option::is_none")]
+    pub schedule_deletion: Option<bool>,
+}
+
+impl StaleAccountCleanupRequest {
+    pub fn new() -> StaleAccountCleanupRequest {
+        StaleAccountCleanupRequest {
+            batch_size: None,
+            inactive_days: None,
+            notify: None,
+            schedule_deletion: None,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// StaleAccountCleanupStatus : Progress of the latest stale account cleanup.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct StaleAccountCleanupStatus {
+    #[serde(rename = "deletion_scheduled")]
+    pub deletion_scheduled: i64,
+    #[serde(rename = "failed")]
+    pub failed: i64,
+    #[serde(
+        rename = "finished_unix_time",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub finished_unix_time: Option<Option<i64>>,
+    /// Stale accounts handled so far.
+    #[serde(rename = "found")]
+    pub found: i64,
+    /// Accounts which latest activity is before this are stale.
+    #[serde(rename = "inactive_before_unix_time")]
+    pub inactive_before_unix_time: i64,
+    #[serde(rename = "notified")]
+    pub notified: i64,
+    #[serde(rename = "notify")]
+    pub notify: bool,
+    #[serde(rename = "running")]
+    pub running: bool,
+    #[serde(rename = "schedule_deletion")]
+    pub schedule_deletion: bool,
+    /// Stale accounts which are not in normal state. Banned accounts and accounts in initial setup are not changed.
+    #[serde(rename = "skipped")]
+    pub skipped: i64,
+    #[serde(rename = "started_unix_time")]
+    pub started_unix_time: i64,
+}
+
+impl StaleAccountCleanupStatus {
+    /// Progress of the latest stale account cleanup.
+    pub fn new(
+        deletion_scheduled: i64,
+        failed: i64,
+        found: i64,
+        inactive_before_unix_time: i64,
+        notified: i64,
+        notify: bool,
+        running: bool,
+        schedule_deletion: bool,
+        skipped: i64,
+        started_unix_time: i64,
+    ) -> StaleAccountCleanupStatus {
+        StaleAccountCleanupStatus {
+            deletion_scheduled,
+            failed,
+            finished_unix_time: None,
+            found,
+            inactive_before_unix_time,
+            notified,
+            notify,
+            running,
+            schedule_deletion,
+            skipped,
+            started_unix_time,
+        }
+    }
+}
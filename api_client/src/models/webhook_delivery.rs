@@ -0,0 +1,50 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    #[serde(rename = "attempts")]
+    pub attempts: i32,
+    #[serde(rename = "event")]
+    pub event: Box<crate::models::AccountWebhookEvent>,
+    /// HTTP status of the latest attempt. None if the request failed before receiving a response.
+    #[serde(
+        rename = "last_status_code",
+        default,
+        with = "::serde_with::rust::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_status_code: Option<Option<i32>>,
+    #[serde(rename = "status")]
+    pub status: crate::models::WebhookDeliveryStatus,
+    #[serde(rename = "updated_unix_time")]
+    pub updated_unix_time: i64,
+    #[serde(rename = "url")]
+    pub url: String,
+}
+
+impl WebhookDelivery {
+    pub fn new(
+        attempts: i32,
+        event: crate::models::AccountWebhookEvent,
+        status: crate::models::WebhookDeliveryStatus,
+        updated_unix_time: i64,
+        url: String,
+    ) -> WebhookDelivery {
+        WebhookDelivery {
+            attempts,
+            event: Box::new(event),
+            last_status_code: None,
+            status,
+            updated_unix_time,
+            url,
+        }
+    }
+}
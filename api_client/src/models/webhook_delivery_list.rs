@@ -0,0 +1,24 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// WebhookDeliveryList : Latest webhook deliveries. The newest delivery is first.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct WebhookDeliveryList {
+    #[serde(rename = "deliveries")]
+    pub deliveries: Vec<crate::models::WebhookDelivery>,
+}
+
+impl WebhookDeliveryList {
+    /// Latest webhook deliveries. The newest delivery is first.
+    pub fn new(deliveries: Vec<crate::models::WebhookDelivery>) -> WebhookDeliveryList {
+        WebhookDeliveryList { deliveries }
+    }
+}
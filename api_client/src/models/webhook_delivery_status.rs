@@ -0,0 +1,36 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub enum WebhookDeliveryStatus {
+    #[serde(rename = "Pending")]
+    Pending,
+    #[serde(rename = "Delivered")]
+    Delivered,
+    #[serde(rename = "Failed")]
+    Failed,
+}
+
+impl ToString for WebhookDeliveryStatus {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Pending => String::from("Pending"),
+            Self::Delivered => String::from("Delivered"),
+            Self::Failed => String::from("Failed"),
+        }
+    }
+}
+
+impl Default for WebhookDeliveryStatus {
+    fn default() -> WebhookDeliveryStatus {
+        Self::Pending
+    }
+}
@@ -0,0 +1,54 @@
+/*
+ * calculator-backend
+ *
+ * Calculator backend API
+ *
+ * The version of the OpenAPI document: 0.1.0
+ *
+ * Generated by: https://openapi-generator.tech
+ */
+
+/// WriteQueueStats : Write command queue counters. Counters are totals since server start, so clients calculate rates from the difference of two reads.
+
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct WriteQueueStats {
+    /// Commands which were handled in batch transactions.
+    #[serde(rename = "batched_commands")]
+    pub batched_commands: i64,
+    /// Transactions which contained multiple calculator state updates.
+    #[serde(rename = "batches")]
+    pub batches: i64,
+    /// Handled commands.
+    #[serde(rename = "commands")]
+    pub commands: i64,
+    /// Total time which the write command runner spent handling commands.
+    #[serde(rename = "processing_micros")]
+    pub processing_micros: i64,
+    /// Commands which are waiting for the write command runner.
+    #[serde(rename = "queue_depth")]
+    pub queue_depth: i64,
+    /// Total time which handled commands waited in the queue. Waiting ends when the write command runner takes the command from the queue.
+    #[serde(rename = "queue_wait_micros")]
+    pub queue_wait_micros: i64,
+}
+
+impl WriteQueueStats {
+    /// Write command queue counters. Counters are totals since server start, so clients calculate rates from the difference of two reads.
+    pub fn new(
+        batched_commands: i64,
+        batches: i64,
+        commands: i64,
+        processing_micros: i64,
+        queue_depth: i64,
+        queue_wait_micros: i64,
+    ) -> WriteQueueStats {
+        WriteQueueStats {
+            batched_commands,
+            batches,
+            commands,
+            processing_micros,
+            queue_depth,
+            queue_wait_micros,
+        }
+    }
+}
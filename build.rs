@@ -2,4 +2,6 @@
 fn main() {
     // trigger recompilation when a new migration is added
     println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=migrations_history");
+    println!("cargo:rerun-if-changed=migrations_postgres");
 }
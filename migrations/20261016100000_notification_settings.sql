@@ -0,0 +1,10 @@
+-- Which events are sent to the account.
+
+CREATE TABLE IF NOT EXISTS NotificationSettings(
+    account_row_id  INTEGER PRIMARY KEY,
+    json_text       TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
@@ -0,0 +1,29 @@
+-- Scheduled calculations and the calculation history.
+
+CREATE TABLE IF NOT EXISTS CalculationSchedule(
+    schedule_id             INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_row_id          INTEGER NOT NULL,
+    expression              TEXT    NOT NULL,
+    next_run_unix_time      INTEGER NOT NULL,
+    repeat_interval_seconds INTEGER,          -- Can be null
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS CalculationScheduleNextRunIndex
+    ON CalculationSchedule (next_run_unix_time);
+
+CREATE TABLE IF NOT EXISTS CalculatorHistory(
+    history_id      INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_row_id  INTEGER NOT NULL,
+    expression      TEXT    NOT NULL,
+    result          REAL,             -- Can be null
+    error           TEXT,             -- Can be null
+    unix_time       INTEGER NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
@@ -0,0 +1,10 @@
+-- Calculator settings of the account.
+
+CREATE TABLE IF NOT EXISTS CalculatorSettings(
+    account_row_id  INTEGER PRIMARY KEY,
+    json_text       TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
@@ -0,0 +1,15 @@
+
+-- Latest activity time of the account. Activity is saved periodically from
+-- the cache, so this can be behind the real activity a little.
+
+CREATE TABLE IF NOT EXISTS AccountActivity(
+    account_row_id          INTEGER PRIMARY KEY,
+    last_activity_unix_time INTEGER NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS AccountActivityTimeIndex
+    ON AccountActivity (last_activity_unix_time);
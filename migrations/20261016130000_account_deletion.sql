@@ -0,0 +1,15 @@
+-- Accounts which are waiting for the deletion grace period to end. Account
+-- state is PendingDeletion when the account has a row here.
+
+CREATE TABLE IF NOT EXISTS AccountDeletion(
+    account_row_id          INTEGER PRIMARY KEY,
+    requested_unix_time     INTEGER NOT NULL,
+    delete_after_unix_time  INTEGER NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS AccountDeletionTimeIndex
+    ON AccountDeletion (delete_after_unix_time);
@@ -0,0 +1,3 @@
+-- Access token expiration time. Null if the access token does not expire.
+
+ALTER TABLE ApiKey ADD COLUMN expires_unix_time INTEGER;
@@ -0,0 +1,7 @@
+-- Apple account ID (sub claim of Apple identity token). Can be null. SQLite
+-- can not add a column with UNIQUE constraint, so uniqueness is an index.
+
+ALTER TABLE SignInWithInfo ADD COLUMN apple_account_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS SignInWithInfoAppleAccountIdIndex
+    ON SignInWithInfo (apple_account_id);
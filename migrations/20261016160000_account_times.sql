@@ -0,0 +1,7 @@
+-- Registration and latest login time. Null for accounts which were
+-- registered before these columns existed and for accounts which have not
+-- logged in after that.
+
+ALTER TABLE AccountId ADD COLUMN created_unix_time INTEGER;
+
+ALTER TABLE AccountId ADD COLUMN last_login_unix_time INTEGER;
@@ -0,0 +1,15 @@
+-- Accounts which can read the calculator state of the owner account.
+
+CREATE TABLE IF NOT EXISTS CalculatorStateShare(
+    owner_account_row_id    INTEGER NOT NULL,
+    reader_account_row_id   INTEGER NOT NULL,
+    PRIMARY KEY (owner_account_row_id, reader_account_row_id),
+    FOREIGN KEY (owner_account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE,
+    FOREIGN KEY (reader_account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
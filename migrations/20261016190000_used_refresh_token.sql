@@ -0,0 +1,13 @@
+-- Refresh tokens which are replaced with a new token. Using these again is
+-- a sign of a stolen token.
+
+CREATE TABLE IF NOT EXISTS UsedRefreshToken(
+    account_row_id  INTEGER NOT NULL,
+    refresh_token   BLOB    NOT NULL,
+    used_unix_time  INTEGER NOT NULL,
+    PRIMARY KEY (account_row_id, refresh_token),
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
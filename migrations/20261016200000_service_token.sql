@@ -0,0 +1,15 @@
+-- Long-lived access tokens for scripts and integrations. These are separate
+-- from the interactive session tokens in the ApiKey table.
+
+CREATE TABLE IF NOT EXISTS ServiceToken(
+    token_id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_row_id      INTEGER NOT NULL,
+    token               TEXT    NOT NULL UNIQUE,
+    name                TEXT    NOT NULL,
+    scope               TEXT    NOT NULL,
+    created_unix_time   INTEGER NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
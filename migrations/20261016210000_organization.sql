@@ -0,0 +1,26 @@
+-- Organizations for team and classroom deployments. Account can belong to
+-- one organization.
+
+CREATE TABLE IF NOT EXISTS Organization(
+    org_id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    name                TEXT    NOT NULL,
+    created_unix_time   INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS OrganizationMember(
+    account_row_id      INTEGER PRIMARY KEY,
+    org_id              INTEGER NOT NULL,
+    is_admin            BOOLEAN NOT NULL,
+    joined_unix_time    INTEGER NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE,
+    FOREIGN KEY (org_id)
+        REFERENCES Organization (org_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS OrganizationMemberOrgIndex
+    ON OrganizationMember (org_id);
@@ -0,0 +1,13 @@
+-- Named calculator states. Rows are removed with the account.
+
+CREATE TABLE IF NOT EXISTS CalculatorSlot(
+    account_row_id      INTEGER NOT NULL,
+    slot_name           TEXT    NOT NULL,
+    state               TEXT    NOT NULL,
+    updated_unix_time   INTEGER NOT NULL,
+    PRIMARY KEY (account_row_id, slot_name),
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
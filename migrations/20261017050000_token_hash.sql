@@ -0,0 +1,37 @@
+-- Access and refresh tokens are saved as SHA-256 hashes of the tokens.
+-- Existing plaintext tokens can not be hashed with SQLite, so the tables
+-- are created again without the tokens and the accounts must login again.
+
+ALTER TABLE ApiKey RENAME TO ApiKeyPlaintext;
+
+CREATE TABLE IF NOT EXISTS ApiKey(
+    account_row_id      INTEGER PRIMARY KEY,
+    api_key_hash        BLOB                UNIQUE,  -- Can be null
+    expires_unix_time   INTEGER,                     -- Can be null
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+INSERT INTO ApiKey (account_row_id)
+SELECT account_row_id FROM ApiKeyPlaintext;
+
+DROP TABLE ApiKeyPlaintext;
+
+ALTER TABLE RefreshToken RENAME TO RefreshTokenPlaintext;
+
+CREATE TABLE IF NOT EXISTS RefreshToken(
+    account_row_id              INTEGER PRIMARY KEY,
+    refresh_token_hash          BLOB                UNIQUE,  -- Can be null
+    refresh_token_generation    INTEGER NOT NULL    DEFAULT 0,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+INSERT INTO RefreshToken (account_row_id, refresh_token_generation)
+SELECT account_row_id, refresh_token_generation FROM RefreshTokenPlaintext;
+
+DROP TABLE RefreshTokenPlaintext;
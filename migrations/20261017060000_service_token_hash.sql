@@ -0,0 +1,18 @@
+-- Service tokens are saved as SHA-256 hashes of the tokens. Existing
+-- plaintext tokens can not be hashed with SQLite, so the table is created
+-- again without the tokens and the service tokens must be created again.
+
+DROP TABLE ServiceToken;
+
+CREATE TABLE IF NOT EXISTS ServiceToken(
+    token_id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_row_id      INTEGER NOT NULL,
+    token_hash          BLOB    NOT NULL UNIQUE,
+    name                TEXT    NOT NULL,
+    scope               TEXT    NOT NULL,
+    created_unix_time   INTEGER NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
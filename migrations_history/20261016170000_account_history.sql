@@ -0,0 +1,13 @@
+-- Immutable account history records. Account data is in the current
+-- database, so the account is identified with the account UUID and history
+-- remains after the account is deleted.
+
+CREATE TABLE IF NOT EXISTS AccountHistory(
+    history_row_id  INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id      BLOB    NOT NULL,
+    event_json      TEXT    NOT NULL,
+    unix_time       INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS AccountHistoryAccountIdIndex
+    ON AccountHistory (account_id, unix_time);
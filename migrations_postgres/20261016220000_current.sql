@@ -0,0 +1,200 @@
+-- Current data tables for the PostgreSQL backend. These match the tables
+-- which the SQLite migrations create.
+
+CREATE TABLE IF NOT EXISTS AccountId(
+    account_row_id          BIGSERIAL   PRIMARY KEY,
+    account_id              UUID        NOT NULL    UNIQUE,
+    created_unix_time       BIGINT,     -- Can be null
+    last_login_unix_time    BIGINT      -- Can be null
+);
+
+CREATE TABLE IF NOT EXISTS ApiKey(
+    account_row_id      BIGINT  PRIMARY KEY,
+    api_key             TEXT                UNIQUE,  -- Can be null
+    expires_unix_time   BIGINT,                      -- Can be null
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS RefreshToken(
+    account_row_id  BIGINT  PRIMARY KEY,
+    refresh_token   BYTEA               UNIQUE,  -- Can be null
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS SignInWithInfo(
+    account_row_id      BIGINT  PRIMARY KEY,
+    google_account_id   TEXT                UNIQUE,  -- Can be null
+    apple_account_id    TEXT                UNIQUE,  -- Can be null
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS Account(
+    account_row_id  BIGINT  PRIMARY KEY,
+    json_text       TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS AccountSetup(
+    account_row_id  BIGINT  PRIMARY KEY,
+    json_text       TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS NotificationSettings(
+    account_row_id  BIGINT  PRIMARY KEY,
+    json_text       TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS AccountActivity(
+    account_row_id          BIGINT  PRIMARY KEY,
+    last_activity_unix_time BIGINT  NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS AccountActivityTimeIndex
+    ON AccountActivity (last_activity_unix_time);
+
+CREATE TABLE IF NOT EXISTS AccountDeletion(
+    account_row_id          BIGINT  PRIMARY KEY,
+    requested_unix_time     BIGINT  NOT NULL,
+    delete_after_unix_time  BIGINT  NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS AccountDeletionTimeIndex
+    ON AccountDeletion (delete_after_unix_time);
+
+CREATE TABLE IF NOT EXISTS UsedRefreshToken(
+    account_row_id  BIGINT  NOT NULL,
+    refresh_token   BYTEA   NOT NULL,
+    used_unix_time  BIGINT  NOT NULL,
+    PRIMARY KEY (account_row_id, refresh_token),
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS ServiceToken(
+    token_id            BIGSERIAL   PRIMARY KEY,
+    account_row_id      BIGINT      NOT NULL,
+    token               TEXT        NOT NULL UNIQUE,
+    name                TEXT        NOT NULL,
+    scope               TEXT        NOT NULL,
+    created_unix_time   BIGINT      NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS Organization(
+    org_id              BIGSERIAL   PRIMARY KEY,
+    name                TEXT        NOT NULL,
+    created_unix_time   BIGINT      NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS OrganizationMember(
+    account_row_id      BIGINT  PRIMARY KEY,
+    org_id              BIGINT  NOT NULL,
+    is_admin            BOOLEAN NOT NULL,
+    joined_unix_time    BIGINT  NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE,
+    FOREIGN KEY (org_id)
+        REFERENCES Organization (org_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS OrganizationMemberOrgIndex
+    ON OrganizationMember (org_id);
+
+-- Tables for calculator features
+
+CREATE TABLE IF NOT EXISTS CurrentState(
+    account_row_id  BIGINT  PRIMARY KEY,
+    calculation     TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS CalculatorSettings(
+    account_row_id  BIGINT  PRIMARY KEY,
+    json_text       TEXT    NOT NULL    DEFAULT '',
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS CalculationSchedule(
+    schedule_id             BIGSERIAL   PRIMARY KEY,
+    account_row_id          BIGINT      NOT NULL,
+    expression              TEXT        NOT NULL,
+    next_run_unix_time      BIGINT      NOT NULL,
+    repeat_interval_seconds BIGINT,     -- Can be null
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS CalculationScheduleNextRunIndex
+    ON CalculationSchedule (next_run_unix_time);
+
+CREATE TABLE IF NOT EXISTS CalculatorHistory(
+    history_id      BIGSERIAL           PRIMARY KEY,
+    account_row_id  BIGINT              NOT NULL,
+    expression      TEXT                NOT NULL,
+    result          DOUBLE PRECISION,   -- Can be null
+    error           TEXT,               -- Can be null
+    unix_time       BIGINT              NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS CalculatorStateShare(
+    owner_account_row_id    BIGINT  NOT NULL,
+    reader_account_row_id   BIGINT  NOT NULL,
+    PRIMARY KEY (owner_account_row_id, reader_account_row_id),
+    FOREIGN KEY (owner_account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE,
+    FOREIGN KEY (reader_account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
@@ -0,0 +1,12 @@
+-- Organization specific overrides for the server config. NULL uses the
+-- server config value.
+
+CREATE TABLE IF NOT EXISTS OrganizationSettings(
+    org_id                  BIGINT  PRIMARY KEY,
+    history_retention_days  BIGINT,     -- Can be null
+    operation_sets          TEXT,       -- JSON array. Can be null.
+    FOREIGN KEY (org_id)
+        REFERENCES Organization (org_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
@@ -0,0 +1,7 @@
+-- Data residency region of the account. Null for accounts which were
+-- registered before regions were saved or when region is not configured.
+
+ALTER TABLE AccountId ADD COLUMN region TEXT;
+
+CREATE INDEX IF NOT EXISTS AccountIdRegionIndex
+    ON AccountId (region);
@@ -0,0 +1,17 @@
+-- Audit log of logins, token refreshes and logouts. Rows are removed with
+-- the account.
+
+CREATE TABLE IF NOT EXISTS LoginHistory(
+    login_history_id    BIGSERIAL   PRIMARY KEY,
+    account_row_id      BIGINT      NOT NULL,
+    unix_time           BIGINT      NOT NULL,
+    ip_address          TEXT,
+    method              TEXT        NOT NULL,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS LoginHistoryAccountIndex
+    ON LoginHistory (account_row_id, login_history_id);
@@ -0,0 +1,11 @@
+-- Short account ID aliases for manual account references. Accounts which
+-- were registered before aliases were added do not have an alias.
+
+CREATE TABLE IF NOT EXISTS AccountAlias(
+    account_row_id  BIGINT  PRIMARY KEY,
+    alias           TEXT    NOT NULL    UNIQUE,
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
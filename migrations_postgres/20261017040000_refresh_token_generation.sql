@@ -0,0 +1,20 @@
+-- Refresh token generation is incremented every time the refresh token
+-- changes. Replaced refresh tokens are saved as SHA-256 hashes with the
+-- generation of the token. Previously saved plaintext tokens are removed.
+
+ALTER TABLE RefreshToken
+    ADD COLUMN refresh_token_generation BIGINT NOT NULL DEFAULT 0;
+
+DROP TABLE IF EXISTS UsedRefreshToken;
+
+CREATE TABLE IF NOT EXISTS UsedRefreshToken(
+    account_row_id      BIGINT  NOT NULL,
+    refresh_token_hash  BYTEA   NOT NULL,
+    generation          BIGINT  NOT NULL,
+    used_unix_time      BIGINT  NOT NULL,
+    PRIMARY KEY (account_row_id, refresh_token_hash),
+    FOREIGN KEY (account_row_id)
+        REFERENCES AccountId (account_row_id)
+            ON DELETE CASCADE
+            ON UPDATE CASCADE
+);
@@ -0,0 +1,19 @@
+-- Access and refresh tokens are saved as SHA-256 hashes of the tokens.
+-- Tokens are hashed like the server hashes them, so existing sessions
+-- continue to work. Refresh tokens are hashed as Base64 strings.
+
+ALTER TABLE ApiKey ADD COLUMN api_key_hash BYTEA UNIQUE;
+
+UPDATE ApiKey
+SET api_key_hash = sha256(convert_to(api_key, 'UTF8'))
+WHERE api_key IS NOT NULL;
+
+ALTER TABLE ApiKey DROP COLUMN api_key;
+
+ALTER TABLE RefreshToken ADD COLUMN refresh_token_hash BYTEA UNIQUE;
+
+UPDATE RefreshToken
+SET refresh_token_hash = sha256(convert_to(encode(refresh_token, 'base64'), 'UTF8'))
+WHERE refresh_token IS NOT NULL;
+
+ALTER TABLE RefreshToken DROP COLUMN refresh_token;
@@ -0,0 +1,12 @@
+-- Service tokens are saved as SHA-256 hashes of the tokens. Tokens are
+-- hashed like the server hashes them, so existing service tokens continue
+-- to work.
+
+ALTER TABLE ServiceToken ADD COLUMN token_hash BYTEA UNIQUE;
+
+UPDATE ServiceToken
+SET token_hash = sha256(convert_to(token, 'UTF8'));
+
+ALTER TABLE ServiceToken ALTER COLUMN token_hash SET NOT NULL;
+
+ALTER TABLE ServiceToken DROP COLUMN token;
@@ -2,24 +2,42 @@
 
 // Routes
 pub mod account;
+pub mod admin;
+pub mod cache;
 pub mod calculator;
+pub mod clock;
 pub mod common;
+pub mod metrics;
+pub mod organization;
+pub mod reload;
+pub mod standby;
 
+pub mod access;
+pub mod context;
+pub mod doc;
 pub mod model;
 pub mod utils;
 
 use utoipa::{Modify, OpenApi};
 
 use crate::{
+    calculator::{EvaluationCache, OperationRegistry},
     config::Config,
+    i18n::Translations,
     server::{
-        app::sign_in_with::SignInWithManager,
+        analytics::Analytics,
+        app::{sign_in_with::SignInWithProvider, stale::StaleAccountCleanup},
         database::{
             commands::WriteCommandRunnerHandle,
+            export::ExportStorage,
             read::ReadCommands,
             utils::{AccountIdManager, ApiKeyManager},
         },
+        event::EventManager,
+        geoip::GeoIpLookup,
         internal::InternalApiManager,
+        status::ServerStatus,
+        webhook::WebhookManager,
     },
 };
 
@@ -27,34 +45,120 @@ use utils::SecurityApiTokenDefault;
 
 // API docs
 
+/// Public API document.
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        common::get_version,
+        common::get_health,
+        common::get_health_live,
+        common::get_health_ready,
         common::get_connect_websocket,
         account::post_register,
         account::post_login,
+        account::post_refresh,
+        account::post_token_exchange,
         account::post_sign_in_with_login,
         account::post_account_setup,
         account::post_complete_setup,
         account::post_delete,
+        account::post_cancel_delete,
+        account::get_deletion_export,
+        account::get_export,
         account::get_account_state,
-        account::internal::check_api_key,
-        account::internal::internal_get_account_state,
+        account::get_session,
+        account::get_login_history,
+        account::get_service_tokens,
+        account::post_service_token,
+        account::delete_service_token,
+        account::get_account_alias,
+        organization::get_organization,
+        organization::get_organization_members,
+        account::get_notification_settings,
+        account::put_notification_settings,
         calculator::get_calculator_state,
         calculator::post_calculator_state,
+        calculator::post_calculate,
+        calculator::get_calculation_schedules,
+        calculator::post_calculation_schedule,
+        calculator::put_calculation_schedule,
+        calculator::delete_calculation_schedule,
+        calculator::get_calculator_slots,
+        calculator::put_calculator_slot,
+        calculator::delete_calculator_slot,
+        calculator::get_calculator_settings,
+        calculator::put_calculator_settings,
+        calculator::get_history_entry,
+        calculator::post_rerun_history_entry,
+        calculator::get_shared_calculator_state,
+        calculator::post_calculator_state_share,
+        calculator::delete_calculator_state_share,
+        calculator::put_calculator_broadcast,
     ),
     components(schemas(
+        common::BackendVersion,
+        common::HealthStatus,
+        common::ReadinessStatus,
+        common::ReadinessCheck,
         common::EventToClient,
+        common::NewSessionInfo,
+        common::SessionsRevokedInfo,
+        common::ClientVersion,
+        common::ClientVersionRequirement,
         account::data::AccountIdLight,
         account::data::ApiKey,
         account::data::Account,
         account::data::AccountState,
         account::data::AccountSetup,
+        account::data::AccountSetupError,
+        account::data::AccountSetupErrorCode,
         account::data::SignInWithLoginInfo,
         account::data::LoginResult,
         account::data::RefreshToken,
         account::data::AuthPair,
+        account::data::SessionInfo,
+        account::data::ServiceTokenScope,
+        account::data::ServiceTokenId,
+        account::data::NewServiceToken,
+        account::data::CreatedServiceToken,
+        account::data::ServiceTokenInfo,
+        account::data::ServiceTokenList,
+        account::data::AccountAliasInfo,
+        account::data::LoginMethod,
+        account::data::LoginHistoryEntry,
+        account::data::LoginHistory,
+        account::data::Organization,
+        account::data::OrganizationMembership,
+        account::data::OrganizationMember,
+        account::data::OrganizationMemberList,
+        account::data::AccountExport,
+        account::data::AccountDataExport,
+        account::data::SignInWithExport,
+        account::data::AccountDeletionInfo,
+        account::data::NotificationSettings,
+        account::data::NotificationDelivery,
         calculator::data::CalculatorState,
+        calculator::data::CalculationRequest,
+        calculator::data::CalculationResult,
+        calculator::data::ScheduleId,
+        calculator::data::NewCalculationSchedule,
+        calculator::data::CalculationSchedule,
+        calculator::data::CalculationScheduleList,
+        calculator::data::CalculatorSlotName,
+        calculator::data::CalculatorSlot,
+        calculator::data::CalculatorSlotList,
+        calculator::data::ExpressionError,
+        calculator::data::HistoryId,
+        calculator::data::HistoryEntry,
+        calculator::data::CalculatorSettings,
+        calculator::data::CalculatorStateShare,
+        calculator::data::CalculatorBroadcast,
+        calculator::data::CalculatorStateBroadcast,
+        crate::calculator::AngleMode,
+        crate::calculator::Diagnostic,
+        crate::calculator::Severity,
+        crate::calculator::DiagnosticCode,
+        crate::calculator::Span,
     )),
     modifiers(&SecurityApiTokenDefault),
     info(
@@ -65,6 +169,90 @@ use utils::SecurityApiTokenDefault;
 )]
 pub struct ApiDoc;
 
+/// Internal server to server API document. Internal routes do not use the
+/// API key.
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        account::internal::check_api_key,
+        account::internal::internal_post_api_key_revoked,
+        account::internal::internal_get_account_state,
+        account::internal::post_migrate_json,
+        account::internal::internal_get_webhook_deliveries,
+        account::internal::internal_get_account_report,
+        admin::internal_get_admin_accounts,
+        admin::internal_get_admin_account,
+        admin::internal_post_admin_logout,
+        admin::internal_post_admin_ban,
+        admin::internal_post_admin_unban,
+        admin::internal_post_admin_service_token,
+        admin::internal_post_stale_account_cleanup,
+        admin::internal_get_stale_account_cleanup,
+        organization::internal_post_organization,
+        organization::internal_get_organization_members,
+        organization::internal_put_organization_member,
+        organization::internal_delete_organization_member,
+        organization::internal_get_organization_settings,
+        organization::internal_put_organization_settings,
+        calculator::internal_get_evaluation_cache_stats,
+        metrics::internal_get_metrics,
+        reload::internal_post_reload_config,
+        clock::internal_post_clock_advance,
+        standby::post_standby_snapshot,
+        common::get_health_live,
+        common::get_health_ready,
+        cache::internal_get_cache_entry,
+        cache::internal_post_refresh_cache_entry,
+        cache::internal_post_evict_cache_entry,
+    ),
+    components(schemas(
+        account::data::AccountIdLight,
+        account::data::ApiKey,
+        account::data::TokenHash,
+        account::data::Account,
+        account::data::AccountState,
+        account::data::JsonMigrationResult,
+        account::data::AccountEventType,
+        account::data::AccountWebhookEvent,
+        account::data::WebhookDeliveryStatus,
+        account::data::WebhookDelivery,
+        account::data::WebhookDeliveryList,
+        account::data::AccountReport,
+        account::data::ProviderRegistrations,
+        account::data::RegionRegistrations,
+        account::data::ServiceTokenScope,
+        account::data::NewServiceToken,
+        account::data::CreatedServiceToken,
+        account::data::OrganizationId,
+        account::data::NewOrganization,
+        account::data::NewOrganizationMember,
+        account::data::OrganizationMember,
+        account::data::OrganizationMemberList,
+        account::data::OrganizationSettings,
+        crate::calculator::OperationSet,
+        admin::AdminAccountInfo,
+        admin::AdminAccountList,
+        admin::StaleAccountCleanupRequest,
+        admin::StaleAccountCleanupStatus,
+        clock::ClockAdvance,
+        clock::ClockTime,
+        crate::calculator::EvaluationCacheStats,
+        cache::CacheEntryInfo,
+        common::ClientVersion,
+        metrics::ServerMetrics,
+        common::ReadinessStatus,
+        common::ReadinessCheck,
+        crate::server::database::commands::WriteQueueStats,
+        crate::server::database::cache::CacheReadStats,
+    )),
+    info(
+        title = "calculator-backend-internal",
+        description = "Calculator backend internal API",
+        version = "0.1.0"
+    )
+)]
+pub struct InternalApiDoc;
+
 // App state getters
 
 pub trait GetApiKeys {
@@ -72,6 +260,11 @@ pub trait GetApiKeys {
     fn api_keys(&self) -> ApiKeyManager<'_>;
 }
 
+pub trait GetEventManager {
+    /// Events to WebSocket connections.
+    fn event_manager(&self) -> EventManager<'_>;
+}
+
 pub trait GetUsers {
     /// All users registered in the service.
     fn users(&self) -> AccountIdManager<'_>;
@@ -86,7 +279,7 @@ pub trait ReadDatabase {
 }
 
 pub trait SignInWith {
-    fn sign_in_with_manager(&self) -> &SignInWithManager;
+    fn sign_in_with(&self) -> &dyn SignInWithProvider;
 }
 
 pub trait GetInternalApi {
@@ -96,3 +289,46 @@ pub trait GetInternalApi {
 pub trait GetConfig {
     fn config(&self) -> &Config;
 }
+
+pub trait GetOperations {
+    /// Calculator operations which are enabled.
+    fn operations(&self) -> &OperationRegistry;
+}
+
+pub trait GetEvaluationCache {
+    fn evaluation_cache(&self) -> &EvaluationCache;
+}
+
+pub trait GetTranslations {
+    /// Translations for server generated messages.
+    fn translations(&self) -> &Translations;
+}
+
+pub trait GetGeoIp {
+    fn geoip(&self) -> &dyn GeoIpLookup;
+}
+
+pub trait GetExports {
+    /// Account data export bundles.
+    fn exports(&self) -> &ExportStorage;
+}
+
+pub trait GetWebhooks {
+    /// Account lifecycle event webhooks.
+    fn webhooks(&self) -> &WebhookManager;
+}
+
+pub trait GetAnalytics {
+    /// Product analytics events.
+    fn analytics(&self) -> &Analytics;
+}
+
+pub trait GetServerStatus {
+    /// Startup progress of the server.
+    fn server_status(&self) -> &ServerStatus;
+}
+
+pub trait GetStaleAccountCleanup {
+    /// Background cleanup of inactive accounts.
+    fn stale_account_cleanup(&self) -> &StaleAccountCleanup;
+}
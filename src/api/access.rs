@@ -0,0 +1,90 @@
+//! Route access based on account state
+//!
+//! Access is checked in the authentication middleware of private routes, so
+//! handlers do not need to check the account state. Routes which check the
+//! access token by themselves (WebSocket connection) are not included.
+//!
+//! Service tokens are additionally limited to the routes which the token
+//! scope allows.
+
+use hyper::Method;
+
+use super::{
+    account::{
+        self,
+        data::{AccountState, ServiceTokenScope},
+    },
+    calculator,
+};
+
+/// Routes which an account can access.
+#[derive(Debug, Clone, Copy)]
+pub enum AllowedRoutes {
+    All,
+    AllExcept(&'static [&'static str]),
+    Only(&'static [&'static str]),
+}
+
+impl AllowedRoutes {
+    /// `route` is a route path like the paths used in the router.
+    pub fn contains(&self, route: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::AllExcept(routes) => !routes.contains(&route),
+            Self::Only(routes) => routes.contains(&route),
+        }
+    }
+}
+
+/// Route access matrix.
+pub fn allowed_routes(state: AccountState) -> AllowedRoutes {
+    match state {
+        AccountState::InitialSetup => AllowedRoutes::Only(&[
+            account::PATH_ACCOUNT_STATE,
+            account::PATH_ACCOUNT_SETUP,
+            account::PATH_ACCOUNT_COMPLETE_SETUP,
+            account::PATH_GET_EXPORT,
+        ]),
+        AccountState::Normal => AllowedRoutes::AllExcept(&[
+            account::PATH_ACCOUNT_SETUP,
+            account::PATH_ACCOUNT_COMPLETE_SETUP,
+            account::PATH_POST_CANCEL_DELETE,
+        ]),
+        AccountState::Banned => AllowedRoutes::Only(&[account::PATH_ACCOUNT_STATE]),
+        AccountState::PendingDeletion => AllowedRoutes::Only(&[
+            account::PATH_ACCOUNT_STATE,
+            account::PATH_POST_CANCEL_DELETE,
+            account::PATH_GET_DELETION_EXPORT,
+            account::PATH_GET_EXPORT,
+        ]),
+    }
+}
+
+/// Routes which service tokens can access. Calculator state sharing changes
+/// are not included as those give other accounts access to the data.
+const SERVICE_TOKEN_ROUTES: &[&str] = &[
+    calculator::PATH_GET_CALCULATOR_STATE,
+    calculator::PATH_POST_CALCULATOR_STATE,
+    calculator::PATH_POST_CALCULATE,
+    calculator::PATH_GET_CALCULATION_SCHEDULES,
+    calculator::PATH_PUT_CALCULATION_SCHEDULE,
+    calculator::PATH_GET_CALCULATOR_SLOTS,
+    calculator::PATH_PUT_CALCULATOR_SLOT,
+    calculator::PATH_GET_HISTORY_ENTRY,
+    calculator::PATH_POST_RERUN_HISTORY_ENTRY,
+    calculator::PATH_CALCULATOR_SETTINGS,
+    calculator::PATH_GET_SHARED_CALCULATOR_STATE,
+];
+
+/// Service token scope matrix. This is checked in addition to the account
+/// state.
+pub fn service_token_allows(scope: ServiceTokenScope, method: &Method, route: &str) -> bool {
+    if !SERVICE_TOKEN_ROUTES.contains(&route) {
+        return false;
+    }
+
+    match scope {
+        ServiceTokenScope::CalculatorRead => method == Method::GET,
+        ServiceTokenScope::CalculatorWrite => true,
+    }
+}
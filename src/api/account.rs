@@ -1,56 +1,113 @@
 pub mod data;
 pub mod internal;
 
-use axum::{Extension, Json, TypedHeader};
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query},
+    headers::UserAgent,
+    response::{IntoResponse, Response},
+    Extension, Json, TypedHeader,
+};
 
 use futures::FutureExt;
-use hyper::StatusCode;
+use hyper::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
 
 use self::data::{
-    Account, AccountIdInternal, AccountIdLight, AccountSetup, AccountState, ApiKey, AuthPair,
-    GoogleAccountId, LoginResult, RefreshToken, SignInWithInfo, SignInWithLoginInfo,
+    Account, AccountAliasInfo, AccountDataExport, AccountDeletionInfo, AccountEventType,
+    AccountExport, AccountIdInternal, AccountIdLight, AccountSetup, AccountSetupError,
+    AccountState, ApiKey, AppleAccountId, AuthPair, CreatedServiceToken, GoogleAccountId,
+    LoginHistory, LoginMethod, LoginResult, NewServiceToken, NotificationSettings, RefreshToken,
+    ServiceTokenId, ServiceTokenList, SessionInfo, SignInWithInfo, SignInWithLoginInfo,
 };
 
-use super::{GetConfig, GetInternalApi, SignInWith};
+use super::{
+    common::NewSessionInfo, context::RequestContext, GetAnalytics, GetConfig, GetExports, GetGeoIp,
+    GetInternalApi, GetWebhooks, SignInWith,
+};
 
 use tracing::error;
 
-use super::{utils::ApiKeyHeader, GetApiKeys, GetUsers, ReadDatabase, WriteDatabase};
+use super::{utils::write_error_status, GetApiKeys, GetUsers, ReadDatabase, WriteDatabase};
 
 use tokio_stream::StreamExt;
 
+use crate::server::{
+    analytics::{
+        EVENT_ACCOUNT_REGISTERED, EVENT_ACCOUNT_SETUP_COMPLETED, EVENT_ACCOUNT_SETUP_SAVED,
+    },
+    database::utils::current_unix_time,
+};
+
 pub const PATH_REGISTER: &str = "/account_api/register";
 
+#[derive(Debug, Clone, Deserialize, Serialize, IntoParams, PartialEq, Eq)]
+#[into_params(parameter_in = Query)]
+pub struct RegisterQuery {
+    /// Data residency region for the new account. Must be one of the
+    /// regions which the server allows. Default region is used if not set.
+    pub region: Option<String>,
+}
+
 /// Register new account. Returns new account ID which is UUID.
 #[utoipa::path(
     post,
     path = "/account_api/register",
+    params(RegisterQuery),
     security(),
     responses(
         (status = 200, description = "New account created.", body = AccountIdLight),
+        (status = 400, description = "Region is not allowed."),
         (status = 500, description = "Internal server error."),
     )
 )]
-pub async fn post_register<S: WriteDatabase + GetConfig>(
+pub async fn post_register<S: WriteDatabase + GetConfig + GetWebhooks + GetAnalytics>(
+    Query(query): Query<RegisterQuery>,
     state: S,
 ) -> Result<Json<AccountIdLight>, StatusCode> {
-    register_impl(&state, SignInWithInfo::default())
+    register_impl(&state, SignInWithInfo::default(), query.region)
         .await
         .map(|id| id.into())
 }
 
-pub async fn register_impl<S: WriteDatabase + GetConfig>(
+pub async fn register_impl<S: WriteDatabase + GetConfig + GetWebhooks + GetAnalytics>(
     state: &S,
     sign_in_with: SignInWithInfo,
+    region: Option<String>,
 ) -> Result<AccountIdLight, StatusCode> {
+    let region = match region {
+        Some(region) if state.config().is_allowed_region(&region) => Some(region),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+        None => state.config().default_region().map(ToString::to_string),
+    };
+
     // New unique UUID is generated every time so no special handling needed
     // to avoid database collisions.
     let id = AccountIdLight::new(uuid::Uuid::new_v4());
+    let sign_in_provider = if sign_in_with.google_account_id.is_some() {
+        "google"
+    } else if sign_in_with.apple_account_id.is_some() {
+        "apple"
+    } else {
+        "none"
+    };
 
     let a = state.write_database().account();
-    let register = a.register(id, sign_in_with);
+    let register = a.register(id, sign_in_with, region);
     match register.await {
-        Ok(id) => Ok(id.as_light().into()),
+        Ok(id) => {
+            state
+                .webhooks()
+                .send(AccountEventType::Created, id.as_light());
+            state.analytics().track(
+                EVENT_ACCOUNT_REGISTERED,
+                Some(id.as_light()),
+                serde_json::json!({ "sign_in_provider": sign_in_provider }),
+            );
+            Ok(id.as_light().into())
+        }
         Err(e) => {
             error!("Error: {e:?}");
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -71,15 +128,23 @@ pub const PATH_LOGIN: &str = "/account_api/login";
         (status = 500, description = "Internal server error."),
     ),
 )]
-pub async fn post_login<S: GetApiKeys + WriteDatabase + GetUsers>(
+pub async fn post_login<S: GetApiKeys + WriteDatabase + GetUsers + GetGeoIp>(
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
     Json(id): Json<AccountIdLight>,
     state: S,
 ) -> Result<Json<LoginResult>, StatusCode> {
-    login_impl(id, state).await.map(|d| d.into())
+    let session = new_session_info(&state, address, user_agent);
+    login_impl(id, LoginMethod::Login, address.ip(), session, state)
+        .await
+        .map(|d| d.into())
 }
 
 async fn login_impl<S: GetApiKeys + WriteDatabase + GetUsers>(
     id: AccountIdLight,
+    method: LoginMethod,
+    address: IpAddr,
+    session: NewSessionInfo,
     state: S,
 ) -> Result<LoginResult, StatusCode> {
     let access = ApiKey::generate_new();
@@ -94,13 +159,15 @@ async fn login_impl<S: GetApiKeys + WriteDatabase + GetUsers>(
 
     state
         .write_database()
-        .set_new_auth_pair(id, account.clone(), None)
+        .login(id, account.clone(), session)
         .await
         .map_err(|e| {
             error!("Login error: {e:?}");
-            StatusCode::INTERNAL_SERVER_ERROR // Database writing failed.
+            write_error_status(&e) // Database writing failed.
         })?;
 
+    record_login_history(&state, id, method, Some(address)).await;
+
     // TODO: microservice support
 
     let result = LoginResult {
@@ -110,6 +177,161 @@ async fn login_impl<S: GetApiKeys + WriteDatabase + GetUsers>(
     Ok(result.into())
 }
 
+fn new_session_info<S: GetGeoIp>(
+    state: &S,
+    address: SocketAddr,
+    user_agent: Option<TypedHeader<UserAgent>>,
+) -> NewSessionInfo {
+    NewSessionInfo {
+        address: address.ip().to_string(),
+        country: state
+            .geoip()
+            .lookup(address.ip())
+            .map(|location| location.country),
+        user_agent: user_agent.map(|TypedHeader(user_agent)| user_agent.as_str().to_string()),
+        unix_time: current_unix_time(),
+    }
+}
+
+/// Save login history entry. Errors are only logged, so the already
+/// changed session is not reported as failed.
+pub async fn record_login_history<S: WriteDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+    method: LoginMethod,
+    address: Option<IpAddr>,
+) {
+    let result = state
+        .write_database()
+        .account()
+        .insert_login_history(id, method, address)
+        .await;
+    if let Err(e) = result {
+        error!("Saving login history failed: {e:?}");
+    }
+}
+
+pub const PATH_POST_REFRESH: &str = "/account_api/refresh";
+
+/// Get new access and refresh tokens without WebSocket connection. Current
+/// refresh token is required. Current WebSocket connection is not changed.
+///
+/// Use this when the access token has expired.
+#[utoipa::path(
+    post,
+    path = "/account_api/refresh",
+    security(),
+    request_body = RefreshToken,
+    responses(
+        (status = 200, description = "New tokens.", body = AuthPair),
+        (status = 401, description = "Invalid refresh token."),
+        (status = 500, description = "Internal server error."),
+    ),
+)]
+pub async fn post_refresh<S: ReadDatabase + WriteDatabase>(
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Json(refresh_token): Json<RefreshToken>,
+    state: S,
+) -> Result<Json<AuthPair>, StatusCode> {
+    let id = state
+        .read_database()
+        .account_with_refresh_token(&refresh_token)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let pair = AuthPair {
+        access: ApiKey::generate_new(),
+        refresh: RefreshToken::generate_new(),
+    };
+
+    let refreshed = state
+        .write_database()
+        .refresh_auth_pair(id, refresh_token, pair.clone())
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    if refreshed {
+        record_login_history(&state, id, LoginMethod::TokenRefresh, Some(address.ip())).await;
+        Ok(pair.into())
+    } else {
+        // Refresh token was used concurrently.
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+pub const PATH_POST_TOKEN_EXCHANGE: &str = "/account_api/token_exchange";
+
+/// Get new access and refresh tokens for a non-connected session. This is
+/// an alternative for clients which can not keep WebSocket connection open.
+///
+/// Refresh token is rotated like when connecting the WebSocket. The new
+/// access token works only from the IP address of this request and it
+/// expires after `http_session_access_token_ttl_seconds` from the server
+/// config. Get new tokens with the new refresh token from this route.
+///
+/// Available only if `http_token_exchange` is enabled from the server
+/// config.
+#[utoipa::path(
+    post,
+    path = "/account_api/token_exchange",
+    security(),
+    request_body = RefreshToken,
+    responses(
+        (status = 200, description = "New tokens.", body = AuthPair),
+        (status = 401, description = "Invalid refresh token."),
+        (status = 404, description = "HTTP token exchange is disabled."),
+        (status = 500, description = "Internal server error."),
+    ),
+)]
+pub async fn post_token_exchange<S: ReadDatabase + WriteDatabase + GetConfig>(
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Json(refresh_token): Json<RefreshToken>,
+    state: S,
+) -> Result<Json<AuthPair>, StatusCode> {
+    if !state.config().http_token_exchange() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let id = state
+        .read_database()
+        .account_with_refresh_token(&refresh_token)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let pair = AuthPair {
+        access: ApiKey::generate_new(),
+        refresh: RefreshToken::generate_new(),
+    };
+
+    let exchanged = state
+        .write_database()
+        .exchange_auth_pair(id, refresh_token, pair.clone(), address.ip())
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    if exchanged {
+        record_login_history(&state, id, LoginMethod::TokenExchange, Some(address.ip())).await;
+        Ok(pair.into())
+    } else {
+        // Refresh token was used concurrently.
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
 pub const PATH_SIGN_IN_WITH_LOGIN: &str = "/account_api/sign_in_with_login";
 
 /// Start new session with sign in with Apple or Google. Creates new account if
@@ -121,18 +343,29 @@ pub const PATH_SIGN_IN_WITH_LOGIN: &str = "/account_api/sign_in_with_login";
     request_body = SignInWithLoginInfo,
     responses(
         (status = 200, description = "Login or account creation successful.", body = LoginResult),
+        (status = 400, description = "Region is not allowed."),
         (status = 500, description = "Internal server error."),
     ),
 )]
 pub async fn post_sign_in_with_login<
-    S: GetApiKeys + WriteDatabase + GetUsers + SignInWith + GetConfig,
+    S: GetApiKeys
+        + WriteDatabase
+        + GetUsers
+        + SignInWith
+        + GetConfig
+        + GetWebhooks
+        + GetAnalytics
+        + GetGeoIp,
 >(
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
     Json(tokens): Json<SignInWithLoginInfo>,
     state: S,
 ) -> Result<Json<LoginResult>, StatusCode> {
+    let session = new_session_info(&state, address, user_agent);
     if let Some(google) = tokens.google_token {
         let info = state
-            .sign_in_with_manager()
+            .sign_in_with()
             .validate_google_token(google)
             .await
             .map_err(|e| {
@@ -149,45 +382,78 @@ pub async fn post_sign_in_with_login<
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
 
-        if let Some(already_existing_account) = already_existing_account {
-            login_impl(already_existing_account.as_light(), state)
-                .await
-                .map(|d| d.into())
-        } else {
-            let id = register_impl(
-                &state,
-                SignInWithInfo {
-                    google_account_id: Some(google_id),
-                },
-            )
-            .await?;
-            login_impl(id, state).await.map(|d| d.into())
-        }
+        sign_in_with_login_impl(
+            already_existing_account,
+            SignInWithInfo {
+                google_account_id: Some(google_id),
+                ..SignInWithInfo::default()
+            },
+            tokens.region,
+            address.ip(),
+            session,
+            state,
+        )
+        .await
     } else if let Some(apple) = tokens.apple_token {
-        let _info = state
-            .sign_in_with_manager()
+        let info = state
+            .sign_in_with()
             .validate_apple_token(apple)
             .await
             .map_err(|e| {
                 error!("{e:?}");
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
+        let apple_id = AppleAccountId(info.id);
+        let already_existing_account = state
+            .users()
+            .get_account_with_apple_account_id(apple_id.clone())
+            .await
+            .map_err(|e| {
+                error!("{e:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
 
-        // if validate_sign_in_with_apple_token(apple).await.unwrap() {
-        //     let key = ApiKey::generate_new();
-        //     Ok(key.into())
-        // } else {
-        //     Err(StatusCode::INTERNAL_SERVER_ERROR)
-        // }
-        Err(StatusCode::INTERNAL_SERVER_ERROR)
+        sign_in_with_login_impl(
+            already_existing_account,
+            SignInWithInfo {
+                apple_account_id: Some(apple_id),
+                ..SignInWithInfo::default()
+            },
+            tokens.region,
+            address.ip(),
+            session,
+            state,
+        )
+        .await
     } else {
         Err(StatusCode::INTERNAL_SERVER_ERROR)
     }
 }
 
+/// Login to existing account or register new account with the sign in
+/// with login info. Region is used only for a new account.
+async fn sign_in_with_login_impl<
+    S: GetApiKeys + WriteDatabase + GetUsers + GetConfig + GetWebhooks + GetAnalytics,
+>(
+    already_existing_account: Option<AccountIdInternal>,
+    sign_in_with: SignInWithInfo,
+    region: Option<String>,
+    address: IpAddr,
+    session: NewSessionInfo,
+    state: S,
+) -> Result<Json<LoginResult>, StatusCode> {
+    let id = match already_existing_account {
+        Some(id) => id.as_light(),
+        None => register_impl(&state, sign_in_with, region).await?,
+    };
+    login_impl(id, LoginMethod::SignInWithLogin, address, session, state)
+        .await
+        .map(|d| d.into())
+}
+
 pub const PATH_ACCOUNT_STATE: &str = "/account_api/state";
 
-/// Get current account state.
+/// Get current account state, registration time and latest login time.
 #[utoipa::path(
     get,
     path = "/account_api/state",
@@ -198,70 +464,382 @@ pub const PATH_ACCOUNT_STATE: &str = "/account_api/state";
     ),
     security(("api_key" = [])),
 )]
-pub async fn get_account_state<S: GetApiKeys + ReadDatabase>(
-    TypedHeader(api_key): TypedHeader<ApiKeyHeader>,
+pub async fn get_account_state<S: ReadDatabase>(
+    Extension(context): Extension<RequestContext>,
     state: S,
 ) -> Result<Json<Account>, StatusCode> {
-    let id = state
-        .api_keys()
-        .api_key_exists(api_key.key())
+    let times = state
+        .read_database()
+        .account_times(context.account_id)
         .await
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+        .map_err(|e| {
+            error!("Get account state: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Account::new_from(context.account_state)
+        .with_times(times)
+        .into())
+}
 
+pub const PATH_SESSION: &str = "/account_api/session";
+
+/// Get current session info. Last seen address and activity time are
+/// updated when access token is used.
+#[utoipa::path(
+    get,
+    path = "/account_api/session",
+    responses(
+        (status = 200, description = "Request successfull.", body = SessionInfo),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_session<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<SessionInfo>, StatusCode> {
     state
         .read_database()
-        .read_json::<Account>(id)
+        .session_info(id.as_light())
         .await
-        .map(|account| account.into())
+        .map(|info| info.into())
         .map_err(|e| {
-            error!("Get account state: {e:?}");
+            error!("Get session: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const DEFAULT_LOGIN_HISTORY_LIMIT: u32 = 20;
+pub const MAX_LOGIN_HISTORY_LIMIT: u32 = 100;
+
+#[derive(Debug, Clone, Deserialize, Serialize, IntoParams, PartialEq, Eq)]
+#[into_params(parameter_in = Query)]
+pub struct LoginHistoryQuery {
+    /// Count of returned entries. Default is 20 and maximum is 100.
+    pub limit: Option<u32>,
+}
+
+pub const PATH_LOGIN_HISTORY: &str = "/account_api/login_history";
+
+/// Get the most recent logins, token refreshes and logouts of the account.
+/// The most recent entry is first.
+#[utoipa::path(
+    get,
+    path = "/account_api/login_history",
+    params(LoginHistoryQuery),
+    responses(
+        (status = 200, description = "Request successfull.", body = LoginHistory),
+        (status = 400, description = "Invalid limit."),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_login_history<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    Query(query): Query<LoginHistoryQuery>,
+    state: S,
+) -> Result<Json<LoginHistory>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_LOGIN_HISTORY_LIMIT);
+    if limit == 0 || limit > MAX_LOGIN_HISTORY_LIMIT {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    state
+        .read_database()
+        .login_history(id, limit.into())
+        .await
+        .map(|entries| LoginHistory { entries }.into())
+        .map_err(|e| {
+            error!("Get login history: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const MAX_SERVICE_TOKENS_PER_ACCOUNT: i64 = 10;
+pub const MAX_SERVICE_TOKEN_NAME_LENGTH: usize = 100;
+
+pub const PATH_GET_SERVICE_TOKENS: &str = "/account_api/session/service_tokens";
+
+/// Get service tokens of the account. Token values are not included.
+#[utoipa::path(
+    get,
+    path = "/account_api/session/service_tokens",
+    responses(
+        (status = 200, description = "Request successfull.", body = ServiceTokenList),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_service_tokens<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<ServiceTokenList>, StatusCode> {
+    state
+        .read_database()
+        .service_tokens(id)
+        .await
+        .map(|tokens| ServiceTokenList { tokens }.into())
+        .map_err(|e| {
+            error!("Get service tokens: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_GET_ACCOUNT_ALIAS: &str = "/account_api/alias";
+
+/// Get short-code alias of the account ID. The alias can be used instead of
+/// the account ID in routes which have the account ID in the path.
+#[utoipa::path(
+    get,
+    path = "/account_api/alias",
+    responses(
+        (status = 200, description = "Request successfull.", body = AccountAliasInfo),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_account_alias<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<AccountAliasInfo>, StatusCode> {
+    state
+        .read_database()
+        .account_alias(id)
+        .await
+        .map(|alias| {
+            AccountAliasInfo {
+                alias: alias.map(|a| a.as_str().to_string()),
+            }
+            .into()
+        })
+        .map_err(|e| {
+            error!("Get account alias: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_POST_SERVICE_TOKEN: &str = "/account_api/session/service_tokens";
+
+/// Create a long-lived service token for scripts and integrations.
+///
+/// The token is used like an access token, but it does not expire and it
+/// does not require the WebSocket connection. The token scope limits the
+/// token to calculator routes. The token value is only in this response.
+///
+/// Account can have at most 10 service tokens.
+#[utoipa::path(
+    post,
+    path = "/account_api/session/service_tokens",
+    request_body = NewServiceToken,
+    responses(
+        (status = 200, description = "Service token created.", body = CreatedServiceToken),
+        (status = 400, description = "Name is empty or too long."),
+        (status = 401, description = "Unauthorized."),
+        (status = 406, description = "Service token limit reached."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn post_service_token<S: WriteDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    Json(token): Json<NewServiceToken>,
+    state: S,
+) -> Result<Json<CreatedServiceToken>, StatusCode> {
+    create_service_token(&state, id, token)
+        .await
+        .map(|t| t.into())
+}
+
+/// Shared with the admin API.
+pub async fn create_service_token<S: WriteDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+    token: NewServiceToken,
+) -> Result<CreatedServiceToken, StatusCode> {
+    let name_length = token.name.chars().count();
+    if name_length == 0 || name_length > MAX_SERVICE_TOKEN_NAME_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    state
+        .write_database()
+        .account()
+        .create_service_token(id, token, MAX_SERVICE_TOKENS_PER_ACCOUNT)
+        .await
+        .map_err(|e| {
+            error!("Create service token: {e:?}");
+            write_error_status(&e)
+        })?
+        .ok_or(StatusCode::NOT_ACCEPTABLE)
+}
+
+pub const PATH_DELETE_SERVICE_TOKEN: &str = "/account_api/session/service_tokens/:token_id";
+
+/// Revoke service token. Requests with the token fail after this.
+#[utoipa::path(
+    delete,
+    path = "/account_api/session/service_tokens/{token_id}",
+    params(ServiceTokenId),
+    responses(
+        (status = 200, description = "Service token revoked."),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Service token not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn delete_service_token<S: WriteDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    Path(token_id): Path<ServiceTokenId>,
+    state: S,
+) -> Result<(), StatusCode> {
+    let revoked = state
+        .write_database()
+        .account()
+        .revoke_service_token(id, token_id)
+        .await
+        .map_err(|e| {
+            error!("Revoke service token: {e:?}");
+            write_error_status(&e)
+        })?;
+
+    if revoked {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub const PATH_NOTIFICATION_SETTINGS: &str = "/account_api/notification_settings";
+
+/// Get notification settings.
+#[utoipa::path(
+    get,
+    path = "/account_api/notification_settings",
+    responses(
+        (status = 200, description = "Request successfull.", body = NotificationSettings),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_notification_settings<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<NotificationSettings>, StatusCode> {
+    state
+        .read_database()
+        .read_json::<NotificationSettings>(id)
+        .await
+        .map(|settings| settings.into())
+        .map_err(|e| {
+            error!("Get notification settings: {e:?}");
             StatusCode::INTERNAL_SERVER_ERROR // Database reading failed.
         })
 }
 
+/// Replace notification settings.
+#[utoipa::path(
+    put,
+    path = "/account_api/notification_settings",
+    request_body(content = NotificationSettings),
+    responses(
+        (status = 200, description = "Request successfull."),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn put_notification_settings<S: WriteDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    Json(settings): Json<NotificationSettings>,
+    state: S,
+) -> Result<(), StatusCode> {
+    state
+        .write_database()
+        .account()
+        .update_notification_settings(id, settings)
+        .await
+        .map_err(|e| {
+            error!("Put notification settings: {e:?}");
+            write_error_status(&e) // Database writing failed.
+        })
+}
+
 pub const PATH_ACCOUNT_SETUP: &str = "/account_api/setup";
 
 /// Setup non-changeable user information during `initial setup` state.
+///
+/// Invalid setup is rejected with status code 400 and error code in the
+/// response body.
 #[utoipa::path(
     post,
     path = "/account_api/setup",
     request_body(content = AccountSetup),
     responses(
         (status = 200, description = "Request successfull."),
-        (status = 406, description = "Current state is not initial setup."),
+        (status = 400, description = "Invalid account setup.", body = AccountSetupError),
         (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Current state is not initial setup."),
         (
             status = 500,
             description = "Internal server error."),
     ),
     security(("api_key" = [])),
 )]
-pub async fn post_account_setup<S: GetApiKeys + ReadDatabase + WriteDatabase>(
+pub async fn post_account_setup<
+    S: GetApiKeys + ReadDatabase + WriteDatabase + GetAnalytics + GetConfig,
+>(
     Extension(id): Extension<AccountIdInternal>,
     Json(data): Json<AccountSetup>,
     state: S,
-) -> Result<(), StatusCode> {
-    let account = state
-        .read_database()
-        .read_json::<Account>(id)
+) -> Result<(), AccountSetupRequestError> {
+    data.validate(state.config().required_setup_fields())
+        .map_err(|code| AccountSetupRequestError::Invalid(AccountSetupError { code }))?;
+
+    state
+        .write_database()
+        .account()
+        .update_account_setup(id, data)
         .await
         .map_err(|e| {
-            error!("error: {e:?}");
-            StatusCode::INTERNAL_SERVER_ERROR // Database reading failed.
+            error!("Write database error: {e:?}");
+            write_error_status(&e) // Database writing failed.
         })?;
 
-    if account.state() == AccountState::InitialSetup {
-        state
-            .write_database()
-            .account()
-            .update_account_setup(id, data)
-            .await
-            .map_err(|e| {
-                error!("Write database error: {e:?}");
-                StatusCode::INTERNAL_SERVER_ERROR // Database writing failed.
-            })
-    } else {
-        Err(StatusCode::NOT_ACCEPTABLE)
+    state.analytics().track(
+        EVENT_ACCOUNT_SETUP_SAVED,
+        Some(id.as_light()),
+        serde_json::json!({}),
+    );
+
+    Ok(())
+}
+
+/// Error for account setup handler. Invalid setup is reported with status
+/// code 400 and error code in the response body.
+pub enum AccountSetupRequestError {
+    Status(StatusCode),
+    Invalid(AccountSetupError),
+}
+
+impl From<StatusCode> for AccountSetupRequestError {
+    fn from(value: StatusCode) -> Self {
+        Self::Status(value)
+    }
+}
+
+impl IntoResponse for AccountSetupRequestError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status) => status.into_response(),
+            Self::Invalid(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
+        }
     }
 }
 
@@ -269,7 +847,7 @@ pub const PATH_ACCOUNT_COMPLETE_SETUP: &str = "/account_api/complete_setup";
 
 /// Complete initial setup.
 ///
-/// Request to this handler will complete if client is in `initial setup`,
+/// Request to this handler will complete if client is in `initial setup` and
 /// setup information is set.
 ///
 #[utoipa::path(
@@ -277,18 +855,26 @@ pub const PATH_ACCOUNT_COMPLETE_SETUP: &str = "/account_api/complete_setup";
     path = "/account_api/complete_setup",
     responses(
         (status = 200, description = "Request successfull."),
-        (status = 406, description = "Current state is not initial setup or AccountSetup is empty."),
+        (status = 406, description = "AccountSetup does not have the required fields."),
         (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Current state is not initial setup."),
         (status = 500, description = "Internal server error."),
     ),
     security(("api_key" = [])),
 )]
 pub async fn post_complete_setup<
-    S: GetApiKeys + ReadDatabase + WriteDatabase + GetInternalApi + GetConfig,
+    S: GetApiKeys
+        + ReadDatabase
+        + WriteDatabase
+        + GetInternalApi
+        + GetConfig
+        + GetWebhooks
+        + GetAnalytics,
 >(
-    Extension(id): Extension<AccountIdInternal>,
+    Extension(context): Extension<RequestContext>,
     state: S,
 ) -> Result<(), StatusCode> {
+    let id = context.account_id;
     let account_setup = state
         .read_database()
         .read_json::<AccountSetup>(id)
@@ -298,52 +884,374 @@ pub async fn post_complete_setup<
             StatusCode::INTERNAL_SERVER_ERROR // Database reading failed.
         })?;
 
-    if account_setup.email().is_empty() {
+    if account_setup
+        .validate(state.config().required_setup_fields())
+        .is_err()
+    {
         return Err(StatusCode::NOT_ACCEPTABLE);
     }
 
-    let mut account = state
-        .read_database()
-        .read_json::<Account>(id)
+    let mut account = Account::new_from(context.account_state);
+    account.complete_setup();
+
+    state
+        .write_database()
+        .account()
+        .update_account(id, account.clone())
         .await
         .map_err(|e| {
-            error!("Complete setup error: {e:?}");
-            StatusCode::INTERNAL_SERVER_ERROR // Database reading failed.
+            error!("Write database error: {e:?}");
+            write_error_status(&e) // Database writing failed.
         })?;
 
-    if account.state() == AccountState::InitialSetup {
-        account.complete_setup();
-
-        state
-            .write_database()
-            .account()
-            .update_account(id, account)
-            .await
-            .map_err(|e| {
-                error!("Write database error: {e:?}");
-                StatusCode::INTERNAL_SERVER_ERROR // Database writing failed.
-            })
-    } else {
-        Err(StatusCode::NOT_ACCEPTABLE)
+    if let Some(event) = AccountEventType::from_state_change(context.account_state, account.state())
+    {
+        state.webhooks().send(event, id.as_light());
     }
+
+    state.analytics().track(
+        EVENT_ACCOUNT_SETUP_COMPLETED,
+        Some(id.as_light()),
+        serde_json::json!({}),
+    );
+
+    Ok(())
 }
 
 pub const PATH_POST_DELETE: &str = "/account_api/delete";
 
-/// Delete account.
+/// Request account deletion.
+///
+/// Account state changes to `PendingDeletion` and account data is removed
+/// after the deletion grace period. Export bundle of the account data is
+/// created, so the data can be downloaded during the grace period.
 #[utoipa::path(
-    put,
+    post,
     path = "/account_api/delete",
     responses(
-        (status = 200, description = "All account data is now deleted."),
+        (status = 200, description = "Account deletion is scheduled.", body = AccountDeletionInfo),
+        (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Current state is not normal."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn post_delete<S: WriteDatabase + ReadDatabase + GetExports + GetConfig + GetWebhooks>(
+    Extension(context): Extension<RequestContext>,
+    state: S,
+) -> Result<Json<AccountDeletionInfo>, StatusCode> {
+    request_deletion_impl(&state, context.account_id, context.account_state)
+        .await
+        .map(|info| info.into())
+}
+
+/// Saves the export bundle, schedules the deletion and sends the webhook
+/// event. Also used from the stale account cleanup.
+pub async fn request_deletion_impl<
+    S: WriteDatabase + ReadDatabase + GetExports + GetConfig + GetWebhooks,
+>(
+    state: &S,
+    id: AccountIdInternal,
+    old_state: AccountState,
+) -> Result<AccountDeletionInfo, StatusCode> {
+    let unix_time = state.config().unix_time();
+    let export = state
+        .read_database()
+        .account_export(id, state.config(), unix_time)
+        .await
+        .map_err(|e| {
+            error!("Delete account: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    state.exports().save(&export).await.map_err(|e| {
+        error!("Delete account: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let info = AccountDeletionInfo {
+        requested_unix_time: unix_time,
+        delete_after_unix_time: unix_time + state.config().deletion_grace_period_seconds(),
+    };
+    let mut account = Account::new_from(old_state);
+    account.request_deletion();
+
+    state
+        .write_database()
+        .account()
+        .request_deletion(id, account.clone(), info)
+        .await
+        .map_err(|e| {
+            error!("Delete account: {e:?}");
+            write_error_status(&e)
+        })?;
+
+    if let Some(event) = AccountEventType::from_state_change(old_state, account.state()) {
+        state.webhooks().send(event, id.as_light());
+    }
+
+    Ok(info)
+}
+
+pub const PATH_POST_CANCEL_DELETE: &str = "/account_api/cancel_delete";
+
+/// Cancel account deletion during the deletion grace period.
+#[utoipa::path(
+    post,
+    path = "/account_api/cancel_delete",
+    responses(
+        (status = 200, description = "Account deletion is canceled."),
         (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Current state is not pending deletion."),
         (status = 500, description = "Internal server error."),
     ),
     security(("api_key" = [])),
 )]
-pub async fn post_delete<S: GetApiKeys + WriteDatabase + ReadDatabase>(
-    _state: S,
+pub async fn post_cancel_delete<S: WriteDatabase + GetWebhooks>(
+    Extension(context): Extension<RequestContext>,
+    state: S,
 ) -> Result<(), StatusCode> {
-    // TODO: implement
-    Err(StatusCode::INTERNAL_SERVER_ERROR)
+    let id = context.account_id;
+    let mut account = Account::new_from(context.account_state);
+    account.cancel_deletion();
+
+    state
+        .write_database()
+        .account()
+        .cancel_deletion(id, account.clone())
+        .await
+        .map_err(|e| {
+            error!("Cancel account deletion: {e:?}");
+            write_error_status(&e)
+        })?;
+
+    if let Some(event) = AccountEventType::from_state_change(context.account_state, account.state())
+    {
+        state.webhooks().send(event, id.as_light());
+    }
+
+    Ok(())
+}
+
+pub const PATH_GET_DELETION_EXPORT: &str = "/account_api/delete/export";
+
+/// Download account data export bundle which was created when account
+/// deletion was requested.
+#[utoipa::path(
+    get,
+    path = "/account_api/delete/export",
+    responses(
+        (status = 200, description = "Request successfull.", body = AccountExport),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Export bundle does not exist or it is expired."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_deletion_export<S: GetExports + GetConfig>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<AccountExport>, StatusCode> {
+    state
+        .exports()
+        .load(id.as_light(), state.config().unix_time())
+        .await
+        .map_err(|e| {
+            error!("Get deletion export: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub const PATH_GET_EXPORT: &str = "/account_api/export";
+
+/// Download all data which is stored for the account.
+///
+/// Response is a JSON file attachment. Data is read from the database when
+/// the request is made.
+#[utoipa::path(
+    get,
+    path = "/account_api/export",
+    responses(
+        (status = 200, description = "Request successfull.", body = AccountDataExport,
+            headers(("Content-Disposition" = String, description = "Attachment file name."))),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_export<S: ReadDatabase + GetConfig>(
+    Extension(context): Extension<RequestContext>,
+    state: S,
+) -> Result<([(header::HeaderName, String); 1], Json<AccountDataExport>), StatusCode> {
+    let id = context.account_id;
+    let export = state
+        .read_database()
+        .account_data_export(id, state.config(), state.config().unix_time())
+        .await
+        .map_err(|e| {
+            error!("Get export: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let disposition = format!("attachment; filename=\"account-{}.json\"", id.as_uuid());
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(export)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header, StatusCode};
+
+    use super::{
+        PATH_ACCOUNT_STATE, PATH_GET_EXPORT, PATH_LOGIN_HISTORY, PATH_POST_SERVICE_TOKEN,
+        PATH_SIGN_IN_WITH_LOGIN,
+    };
+    use crate::{
+        api::{
+            calculator::PATH_GET_CALCULATOR_SLOTS,
+            model::{
+                Account, AccountDataExport, AccountState, CreatedServiceToken, GoogleAccountId,
+                LoginHistory, LoginMethod, LoginResult, NewServiceToken, ServiceTokenScope,
+            },
+            GetUsers, ReadDatabase,
+        },
+        server::{
+            app::sign_in_with::mock::MockSignInWith,
+            test_support::{response_json, TestApp},
+        },
+    };
+
+    #[tokio::test]
+    async fn login_is_saved_to_login_history() {
+        let app = TestApp::new().await;
+        let (_, login) = app.register_and_login().await;
+        let access = Some(&login.account.access);
+
+        let response = app.get(PATH_ACCOUNT_STATE, access).await;
+        let _: Account = response_json(response, StatusCode::OK).await;
+
+        let response = app.get(PATH_LOGIN_HISTORY, access).await;
+        let history: LoginHistory = response_json(response, StatusCode::OK).await;
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].method, LoginMethod::Login);
+        assert_eq!(history.entries[0].ip_address.as_deref(), Some("127.0.0.1"));
+
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn sign_in_with_google_creates_account_once() {
+        let sign_in_with =
+            MockSignInWith::default().with_google_account("token", "google-id", "a@example.com");
+        let app = TestApp::with_sign_in_with(sign_in_with).await;
+        let body = serde_json::json!({ "google_token": "token" });
+
+        for _ in 0..2 {
+            let response = app.post_json(PATH_SIGN_IN_WITH_LOGIN, None, &body).await;
+            let login: LoginResult = response_json(response, StatusCode::OK).await;
+            let response = app
+                .get(PATH_ACCOUNT_STATE, Some(&login.account.access))
+                .await;
+            let account: Account = response_json(response, StatusCode::OK).await;
+            assert_eq!(account.state(), AccountState::InitialSetup);
+        }
+
+        let state = app.state();
+        let id = state
+            .users()
+            .get_account_with_google_account_id(GoogleAccountId("google-id".to_string()))
+            .await
+            .unwrap()
+            .expect("Account not found");
+        let history = state.read_database().login_history(id, 10).await.unwrap();
+        assert_eq!(history.len(), 2);
+
+        let body = serde_json::json!({ "google_token": "invalid" });
+        let response = app.post_json(PATH_SIGN_IN_WITH_LOGIN, None, &body).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        drop(state);
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn account_data_export_is_attachment() {
+        let app = TestApp::new().await;
+        let (id, login) = app.register_and_login().await;
+
+        let response = app.get(PATH_GET_EXPORT, Some(&login.account.access)).await;
+        let disposition = response
+            .headers()
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        assert_eq!(
+            disposition.as_deref(),
+            Some(format!("attachment; filename=\"account-{}.json\"", id.as_uuid()).as_str())
+        );
+        let export: AccountDataExport = response_json(response, StatusCode::OK).await;
+        assert_eq!(export.account_id, id);
+        // TestApp::register_and_login completes the account setup.
+        assert_eq!(
+            export.account.map(|account| account.state()),
+            Some(AccountState::Normal)
+        );
+        assert!(export.account_setup.is_some());
+        assert!(export.calculator_state.is_some());
+        assert_eq!(export.calculator_history, None);
+
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn skip_initial_setup_creates_normal_accounts() {
+        let app = TestApp::with_config(|config| {
+            config
+                .account
+                .get_or_insert_with(Default::default)
+                .skip_initial_setup = Some(true);
+        })
+        .await;
+        let (_, login) = app.register_and_login_without_setup().await;
+
+        let response = app
+            .get(PATH_ACCOUNT_STATE, Some(&login.account.access))
+            .await;
+        let account: Account = response_json(response, StatusCode::OK).await;
+        assert_eq!(account.state(), AccountState::Normal);
+
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn hashed_service_token_can_be_used_and_revoked() {
+        let app = TestApp::new().await;
+        let (_, login) = app.register_and_login().await;
+
+        let new = NewServiceToken {
+            name: "script".to_string(),
+            scope: ServiceTokenScope::CalculatorRead,
+        };
+        let response = app
+            .post_json(PATH_POST_SERVICE_TOKEN, Some(&login.account.access), &new)
+            .await;
+        let created: CreatedServiceToken = response_json(response, StatusCode::OK).await;
+
+        let response = app
+            .get(PATH_GET_CALCULATOR_SLOTS, Some(&created.token))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let path = format!("/account_api/session/service_tokens/{}", created.token_id);
+        let response = app.delete(&path, Some(&login.account.access)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .get(PATH_GET_CALCULATOR_SLOTS, Some(&created.token))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        app.close().await;
+    }
 }
@@ -1,7 +1,20 @@
 use base64::Engine;
-use serde::{Deserialize, Serialize};
+use rand::Rng;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 use utoipa::{IntoParams, ToSchema};
 
+use crate::{
+    api::{
+        calculator::data::{
+            CalculationSchedule, CalculatorSettings, CalculatorSlot, CalculatorState,
+            CalculatorStateInternal, HistoryEntry,
+        },
+        common::EventToClient,
+    },
+    calculator::OperationSet,
+};
+
 /// Used with database
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Eq, Hash, PartialEq, Copy)]
 pub struct AccountIdInternal {
@@ -64,6 +77,56 @@ impl From<AccountIdLight> for uuid::Uuid {
     }
 }
 
+/// Path parameter `account_id` of routes which accept an account ID or an
+/// account alias.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AccountIdOrAlias {
+    /// Account ID or account alias.
+    pub account_id: String,
+}
+
+/// Short account ID alias for manual account references, like support
+/// requests. Aliases are random, so those can not be guessed from the
+/// account ID.
+#[derive(Debug, Clone, sqlx::Type, PartialEq, Eq)]
+#[sqlx(transparent)]
+pub struct AccountAlias(String);
+
+impl AccountAlias {
+    /// Crockford base32 alphabet in lowercase.
+    const ALPHABET: &'static [u8] = b"0123456789abcdefghjkmnpqrstvwxyz";
+    pub const LENGTH: usize = 12;
+
+    pub fn generate() -> Self {
+        let mut rng = rand::thread_rng();
+        let alias = (0..Self::LENGTH)
+            .map(|_| Self::ALPHABET[rng.gen_range(0..Self::ALPHABET.len())] as char)
+            .collect();
+        Self(alias)
+    }
+
+    /// Aliases are case insensitive. Returns None if `text` is not a valid
+    /// alias.
+    pub fn parse(text: &str) -> Option<Self> {
+        let alias = text.to_ascii_lowercase();
+        if alias.len() == Self::LENGTH && alias.bytes().all(|c| Self::ALPHABET.contains(&c)) {
+            Some(Self(alias))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct AccountAliasInfo {
+    /// None if the account was registered before aliases were added.
+    pub alias: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, ToSchema, Clone, Eq, Hash, PartialEq)]
 pub struct LoginResult {
     pub account: AuthPair,
@@ -97,6 +160,10 @@ impl ApiKey {
     pub fn as_str(&self) -> &str {
         &self.api_key
     }
+
+    pub fn hash(&self) -> TokenHash {
+        TokenHash::new(&self.api_key)
+    }
 }
 
 /// This is just a really long random number which is Base64 encoded.
@@ -147,6 +214,50 @@ impl RefreshToken {
     pub fn bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
         base64::engine::general_purpose::STANDARD.decode(&self.token)
     }
+
+    pub fn hash(&self) -> TokenHash {
+        TokenHash::new(&self.token)
+    }
+}
+
+/// SHA-256 hash of an access, refresh or service token. Database has only
+/// the token hashes, so the plaintext tokens are only in the memory.
+/// Serialized as a hex string.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, ToSchema)]
+#[schema(value_type = String)]
+pub struct TokenHash(Vec<u8>);
+
+impl Serialize for TokenHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        hex::decode(&text)
+            .map(Self)
+            .map_err(|_| de::Error::custom(format!("Invalid token hash {text}")))
+    }
+}
+
+impl TokenHash {
+    fn new(token: &str) -> Self {
+        Self(Sha256::digest(token.as_bytes()).to_vec())
+    }
+
+    pub fn from_bytes(hash: Vec<u8>) -> Self {
+        Self(hash)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
 }
 
 /// AccessToken and RefreshToken
@@ -162,20 +273,37 @@ impl AuthPair {
     }
 }
 
+/// Times are not saved to the account JSON. They are added to API
+/// responses from [AccountTimes].
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
 pub struct Account {
     state: AccountState,
+    /// Registration time. None if the account was registered before
+    /// registration time was saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_unix_time: Option<i64>,
+    /// None if the account has not logged in after login time was saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_login_unix_time: Option<i64>,
 }
 
 impl Account {
     pub fn new() -> Self {
+        Self::new_from(AccountState::InitialSetup)
+    }
+
+    pub fn new_from(state: AccountState) -> Self {
         Self {
-            state: AccountState::InitialSetup,
+            state,
+            created_unix_time: None,
+            last_login_unix_time: None,
         }
     }
 
-    pub fn new_from(state: AccountState) -> Self {
-        Self { state }
+    pub fn with_times(mut self, times: AccountTimes) -> Self {
+        self.created_unix_time = times.created_unix_time;
+        self.last_login_unix_time = times.last_login_unix_time;
+        self
     }
 
     pub fn state(&self) -> AccountState {
@@ -187,20 +315,55 @@ impl Account {
             self.state = AccountState::Normal;
         }
     }
+
+    pub fn request_deletion(&mut self) {
+        if self.state == AccountState::Normal {
+            self.state = AccountState::PendingDeletion;
+        }
+    }
+
+    pub fn cancel_deletion(&mut self) {
+        if self.state == AccountState::PendingDeletion {
+            self.state = AccountState::Normal;
+        }
+    }
+
+    pub fn ban(&mut self) {
+        self.state = AccountState::Banned;
+    }
+
+    /// Unbanned account is in normal state even if it was banned before
+    /// completing the initial setup.
+    pub fn unban(&mut self) {
+        if self.state == AccountState::Banned {
+            self.state = AccountState::Normal;
+        }
+    }
 }
 
 impl Default for Account {
     fn default() -> Self {
-        Self {
-            state: AccountState::InitialSetup,
-        }
+        Self::new()
     }
 }
 
+/// Registration and latest login time from the database.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountTimes {
+    pub created_unix_time: Option<i64>,
+    pub last_login_unix_time: Option<i64>,
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
 pub enum AccountState {
     InitialSetup,
     Normal,
+    /// Only the account state route is available. Accounts are banned and
+    /// unbanned using the internal admin API.
+    Banned,
+    /// Account data is removed after the deletion grace period. Deletion can
+    /// be canceled during the grace period.
+    PendingDeletion,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema, Default, PartialEq, Eq)]
@@ -209,22 +372,634 @@ pub struct AccountSetup {
 }
 
 impl AccountSetup {
+    pub const MAX_EMAIL_LENGTH: usize = 254;
+
     pub fn email(&self) -> &str {
         &self.email
     }
+
+    /// Fields in `required` must be set. Other fields are validated only
+    /// if those are set.
+    pub fn validate(&self, required: &[AccountSetupField]) -> Result<(), AccountSetupErrorCode> {
+        if self.email.is_empty() {
+            if required.contains(&AccountSetupField::Email) {
+                return Err(AccountSetupErrorCode::EmailEmpty);
+            } else {
+                return Ok(());
+            }
+        }
+        if self.email.chars().count() > Self::MAX_EMAIL_LENGTH {
+            return Err(AccountSetupErrorCode::EmailTooLong);
+        }
+        let valid_email = match self.email.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.is_empty()
+                    && !domain.contains('@')
+                    && !self.email.chars().any(char::is_whitespace)
+            }
+            None => false,
+        };
+        if valid_email {
+            Ok(())
+        } else {
+            Err(AccountSetupErrorCode::EmailInvalid)
+        }
+    }
+}
+
+/// AccountSetup field which can be configured to be required.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountSetupField {
+    Email,
+}
+
+/// Reason why AccountSetup was rejected.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub enum AccountSetupErrorCode {
+    EmailEmpty,
+    /// Email is longer than 254 characters.
+    EmailTooLong,
+    EmailInvalid,
+}
+
+/// AccountSetup validation failed.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct AccountSetupError {
+    pub code: AccountSetupErrorCode,
+}
+
+/// How event is delivered to the client. Events which are not disabled are
+/// always sent to the WebSocket connection if it exists.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq, Default)]
+pub enum NotificationDelivery {
+    #[default]
+    WebSocketOnly,
+    /// Send push notification if WebSocket is not connected.
+    Push,
+    /// Send email if WebSocket is not connected.
+    Email,
+    /// Event is not sent.
+    Disabled,
+}
+
+/// Delivery setting for every event type.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq, Default)]
+pub struct NotificationSettings {
+    pub account_state_changed: NotificationDelivery,
+    pub scheduled_calculation_completed: NotificationDelivery,
+    pub new_session_created: NotificationDelivery,
+}
+
+impl NotificationSettings {
+    /// Delivery method which event delivery should use when WebSocket is not
+    /// connected.
+    pub fn delivery(&self, event: &EventToClient) -> NotificationDelivery {
+        match event {
+            EventToClient::AccountStateChanged => self.account_state_changed,
+            // Calculator state is fetched when the client connects.
+            EventToClient::CalculatorStateChanged => NotificationDelivery::WebSocketOnly,
+            EventToClient::ScheduledCalculationCompleted => self.scheduled_calculation_completed,
+            EventToClient::NewSessionCreated(_) => self.new_session_created,
+            EventToClient::CalculatorStateBroadcast(_) => NotificationDelivery::WebSocketOnly,
+            // Security notification can not be disabled.
+            EventToClient::SessionsRevoked(_) => NotificationDelivery::WebSocketOnly,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct JsonMigrationResult {
+    /// Count of rewritten JSON objects.
+    pub rewritten: u64,
+}
+
+/// Account registration and activity summary.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct AccountReport {
+    pub generated_unix_time: i64,
+    /// Count of all accounts.
+    pub registrations: i64,
+    pub registrations_per_provider: Vec<ProviderRegistrations>,
+    pub registrations_per_region: Vec<RegionRegistrations>,
+    /// Count of accounts which are not in initial setup state.
+    pub setup_completed: i64,
+    /// Value `setup_completed / registrations`. Zero if there are no
+    /// accounts.
+    pub setup_completion_rate: f64,
+    /// Accounts active during the last 24 hours.
+    pub daily_active_accounts: i64,
+    /// Accounts active during the last 7 days.
+    pub weekly_active_accounts: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ProviderRegistrations {
+    /// Sign in provider like `google`, `apple` or `none` if account does not
+    /// use sign in with login.
+    pub provider: String,
+    pub registrations: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct RegionRegistrations {
+    /// Data residency region. None for accounts without region.
+    pub region: Option<String>,
+    pub registrations: i64,
+}
+
+/// Scheduled account deletion.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct AccountDeletionInfo {
+    pub requested_unix_time: i64,
+    /// Account data is removed after this time.
+    pub delete_after_unix_time: i64,
+}
+
+/// Current session of the account.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// IP address and port of the current WebSocket connection.
+    pub connection_address: Option<String>,
+    /// IP address and port of the latest request made with the access token.
+    pub last_seen_address: Option<String>,
+    /// Country code of the last seen address. Available if server has
+    /// GeoIP database configured.
+    pub last_seen_country: Option<String>,
+    /// Unix timestamp of the latest login or request made with the access
+    /// token.
+    pub last_activity_unix_time: Option<i64>,
+}
+
+/// Routes which a service token can access. Service tokens can not access
+/// account routes, so the token owner must manage the tokens with an
+/// interactive session.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub enum ServiceTokenScope {
+    /// Only GET requests to the calculator routes.
+    CalculatorRead,
+    /// All calculator routes.
+    CalculatorWrite,
+}
+
+impl ServiceTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CalculatorRead => "calculator_read",
+            Self::CalculatorWrite => "calculator_write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "calculator_read" => Some(Self::CalculatorRead),
+            "calculator_write" => Some(Self::CalculatorWrite),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, IntoParams, PartialEq, Eq)]
+pub struct ServiceTokenId {
+    pub token_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct NewServiceToken {
+    /// Name for identifying the token in the token list. Maximum length is
+    /// 100 characters.
+    pub name: String,
+    pub scope: ServiceTokenScope,
+}
+
+/// Service token which was just created. The token value is not available
+/// after this.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CreatedServiceToken {
+    pub token_id: i64,
+    /// Use this like an access token. The token does not expire.
+    pub token: ApiKey,
+}
+
+/// Service token without the token value.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ServiceTokenInfo {
+    pub token_id: i64,
+    pub name: String,
+    pub scope: ServiceTokenScope,
+    pub created_unix_time: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ServiceTokenList {
+    pub tokens: Vec<ServiceTokenInfo>,
+}
+
+/// How the session was started, continued or ended.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub enum LoginMethod {
+    Login,
+    SignInWithLogin,
+    /// New tokens from the WebSocket connection or the refresh route.
+    TokenRefresh,
+    /// New tokens for a non-connected session.
+    TokenExchange,
+    Logout,
+}
+
+impl LoginMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::SignInWithLogin => "sign_in_with_login",
+            Self::TokenRefresh => "token_refresh",
+            Self::TokenExchange => "token_exchange",
+            Self::Logout => "logout",
+        }
+    }
+
+    pub fn parse(method: &str) -> Option<Self> {
+        match method {
+            "login" => Some(Self::Login),
+            "sign_in_with_login" => Some(Self::SignInWithLogin),
+            "token_refresh" => Some(Self::TokenRefresh),
+            "token_exchange" => Some(Self::TokenExchange),
+            "logout" => Some(Self::Logout),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct LoginHistoryEntry {
+    pub unix_time: i64,
+    /// None if the address was not available, for example when an admin
+    /// ended the session.
+    pub ip_address: Option<String>,
+    pub method: LoginMethod,
+}
+
+/// Login history entries starting from the most recent.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct LoginHistory {
+    pub entries: Vec<LoginHistoryEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, IntoParams, PartialEq, Eq)]
+pub struct OrganizationId {
+    pub org_id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct NewOrganization {
+    /// Maximum length is 100 characters.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct Organization {
+    pub org_id: i64,
+    pub name: String,
+    pub created_unix_time: i64,
+}
+
+/// Organization of the current account.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct OrganizationMembership {
+    pub organization: Organization,
+    /// Organization admins can view the member list.
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct NewOrganizationMember {
+    pub account_id: AccountIdLight,
+    pub is_admin: bool,
+}
+
+/// Organization member and its usage.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct OrganizationMember {
+    pub account_id: AccountIdLight,
+    pub is_admin: bool,
+    pub joined_unix_time: i64,
+    /// Latest saved activity time. None if the account has not been active.
+    pub last_activity_unix_time: Option<i64>,
+    /// Count of calculator history entries.
+    pub history_entries: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct OrganizationMemberList {
+    pub members: Vec<OrganizationMember>,
+}
+
+/// Organization specific overrides for the server config. Unset values use
+/// the server config.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct OrganizationSettings {
+    /// Calculator history entries older than this are deleted. Minimum is
+    /// 1 and maximum is 36500.
+    pub history_retention_days: Option<i64>,
+    /// Enabled calculator operation sets. Basic operations are always
+    /// enabled.
+    pub operation_sets: Option<Vec<OperationSet>>,
+}
+
+/// Account data export bundle. Data is included only from enabled server
+/// components.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct AccountExport {
+    pub account_id: AccountIdLight,
+    pub created_unix_time: i64,
+    /// Bundle can not be downloaded after this time.
+    pub expires_unix_time: i64,
+    /// Data residency region of the account. None if the account does not
+    /// have a region.
+    pub region: Option<String>,
+    pub account: Option<Account>,
+    pub account_setup: Option<AccountSetup>,
+    pub notification_settings: Option<NotificationSettings>,
+    pub calculator_state: Option<CalculatorState>,
+    pub calculator_settings: Option<CalculatorSettings>,
+    pub calculation_schedules: Option<Vec<CalculationSchedule>>,
+    pub calculator_slots: Option<Vec<CalculatorSlot>>,
+}
+
+/// All data which is stored for the account. Data is included only from
+/// enabled server components.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct AccountDataExport {
+    pub account_id: AccountIdLight,
+    pub created_unix_time: i64,
+    pub account: Option<Account>,
+    pub account_setup: Option<AccountSetup>,
+    pub sign_in_with: Option<SignInWithExport>,
+    pub calculator_state: Option<CalculatorState>,
+    /// Calculator history entries in creation order. None if the account
+    /// does not have history entries.
+    pub calculator_history: Option<Vec<HistoryEntry>>,
+}
+
+/// Sign in with account IDs of the account.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct SignInWithExport {
+    pub google_account_id: Option<String>,
+    pub apple_account_id: Option<String>,
+}
+
+impl From<SignInWithInfo> for SignInWithExport {
+    fn from(value: SignInWithInfo) -> Self {
+        Self {
+            google_account_id: value.google_account_id.map(|id| id.0),
+            apple_account_id: value.apple_account_id.map(|id| id.0),
+        }
+    }
+}
+
+/// Account data from the tables which have at most one row for each
+/// account. Values are None if the table does not have a row for the
+/// account.
+#[derive(Debug, Clone)]
+pub struct AccountDataInternal {
+    pub account: Option<Account>,
+    pub account_setup: Option<AccountSetup>,
+    pub sign_in_with: Option<SignInWithInfo>,
+    pub calculator_state: Option<CalculatorStateInternal>,
+}
+
+/// Account lifecycle event which is sent to the configured webhooks.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountEventType {
+    Created,
+    SetupCompleted,
+    Banned,
+    /// Account deletion is requested.
+    Deleted,
+    DeletionCanceled,
+    /// Account data is removed after the deletion grace period.
+    Purged,
+    /// Replaced refresh token was used, so the token might be stolen. All
+    /// sessions of the account are revoked.
+    SessionsRevoked,
+    /// Account has been inactive longer than the stale account period.
+    /// Sent from the stale account cleanup.
+    Inactive,
+}
+
+impl AccountEventType {
+    /// Event for account state change if the change is a lifecycle event.
+    pub fn from_state_change(old: AccountState, new: AccountState) -> Option<Self> {
+        match (old, new) {
+            (AccountState::InitialSetup, AccountState::Normal) => Some(Self::SetupCompleted),
+            (AccountState::Banned, AccountState::Banned) => None,
+            (_, AccountState::Banned) => Some(Self::Banned),
+            (AccountState::Normal, AccountState::PendingDeletion) => Some(Self::Deleted),
+            (AccountState::PendingDeletion, AccountState::Normal) => Some(Self::DeletionCanceled),
+            _ => None,
+        }
+    }
+}
+
+/// Account history record which is saved to the history database.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountHistoryEvent {
+    Registered,
+    Login,
+    StateChanged {
+        old: AccountState,
+        new: AccountState,
+    },
+    /// Account data is removed from the current database.
+    Purged,
+    /// Replaced refresh token was used and all sessions were revoked.
+    RefreshTokenReused,
+}
+
+/// Webhook request body.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct AccountWebhookEvent {
+    /// Same for every delivery attempt of the event.
+    pub event_id: uuid::Uuid,
+    pub event: AccountEventType,
+    pub account_id: AccountIdLight,
+    pub unix_time: i64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub enum WebhookDeliveryStatus {
+    /// Delivery is in progress or waiting for retry.
+    Pending,
+    Delivered,
+    /// All attempts failed.
+    Failed,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct WebhookDelivery {
+    pub event: AccountWebhookEvent,
+    pub url: String,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    /// HTTP status of the latest attempt. None if the request failed
+    /// before receiving a response.
+    pub last_status_code: Option<u16>,
+    pub updated_unix_time: i64,
+}
+
+/// Latest webhook deliveries. The newest delivery is first.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct WebhookDeliveryList {
+    pub deliveries: Vec<WebhookDelivery>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
 pub struct SignInWithLoginInfo {
     pub apple_token: Option<String>,
     pub google_token: Option<String>,
+    /// Data residency region for a new account. Must be one of the regions
+    /// which the server allows. Default region is used if not set.
+    #[serde(default)]
+    pub region: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct SignInWithInfo {
     pub google_account_id: Option<GoogleAccountId>,
+    pub apple_account_id: Option<AppleAccountId>,
 }
 
 #[derive(Debug, Clone, sqlx::Type, PartialEq)]
 #[sqlx(transparent)]
 pub struct GoogleAccountId(pub String);
+
+#[derive(Debug, Clone, sqlx::Type, PartialEq)]
+#[sqlx(transparent)]
+pub struct AppleAccountId(pub String);
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    use super::{
+        Account, AccountAlias, AccountIdInternal, AccountIdLight, AccountSetup,
+        AccountSetupErrorCode, AccountSetupField, AccountState, RefreshToken,
+    };
+
+    fn account_state() -> impl Strategy<Value = AccountState> {
+        prop_oneof![
+            Just(AccountState::InitialSetup),
+            Just(AccountState::Normal),
+            Just(AccountState::Banned),
+            Just(AccountState::PendingDeletion),
+        ]
+    }
+
+    fn uuid() -> impl Strategy<Value = uuid::Uuid> {
+        any::<u128>().prop_map(uuid::Uuid::from_u128)
+    }
+
+    proptest! {
+        #[test]
+        fn refresh_token_bytes_round_trip(bytes in proptest::collection::vec(any::<u8>(), 0..128)) {
+            let token = RefreshToken::from_bytes(&bytes);
+            prop_assert_eq!(token.bytes().unwrap(), bytes);
+        }
+
+        #[test]
+        fn refresh_token_string_round_trip(bytes in proptest::collection::vec(any::<u8>(), 0..128)) {
+            let token = RefreshToken::from_bytes(&bytes);
+            let from_string = RefreshToken::from_string(token.as_str().to_string());
+            prop_assert_eq!(from_string.bytes().unwrap(), bytes);
+            prop_assert_eq!(from_string, token);
+        }
+
+        #[test]
+        fn refresh_token_json_is_base64_string(bytes in proptest::collection::vec(any::<u8>(), 0..128)) {
+            let token = RefreshToken::from_bytes(&bytes);
+            let value = serde_json::to_value(&token).unwrap();
+            prop_assert_eq!(&value, &json!({ "token": token.as_str() }));
+            prop_assert_eq!(serde_json::from_value::<RefreshToken>(value).unwrap(), token);
+        }
+
+        /// Account JSON is saved to the database, so field names and enum
+        /// variant names must not change.
+        #[test]
+        fn account_json_is_stable(
+            state in account_state(),
+            created_unix_time in any::<Option<i64>>(),
+            last_login_unix_time in any::<Option<i64>>(),
+        ) {
+            let account = Account {
+                state,
+                created_unix_time,
+                last_login_unix_time,
+            };
+            let mut expected = json!({ "state": format!("{state:?}") });
+            if let Some(time) = created_unix_time {
+                expected["created_unix_time"] = json!(time);
+            }
+            if let Some(time) = last_login_unix_time {
+                expected["last_login_unix_time"] = json!(time);
+            }
+
+            let value = serde_json::to_value(&account).unwrap();
+            prop_assert_eq!(&value, &expected);
+            prop_assert_eq!(serde_json::from_value::<Account>(value).unwrap(), account);
+        }
+
+        #[test]
+        fn account_id_json_round_trip(account_id in uuid(), account_row_id in any::<i64>()) {
+            let id = AccountIdInternal { account_id, account_row_id };
+            let json = serde_json::to_string(&id).unwrap();
+            prop_assert_eq!(serde_json::from_str::<AccountIdInternal>(&json).unwrap(), id);
+
+            let light = id.as_light();
+            prop_assert_eq!(light.as_uuid(), account_id);
+            let value = serde_json::to_value(light).unwrap();
+            prop_assert_eq!(&value, &json!({ "account_id": light.to_string() }));
+            prop_assert_eq!(serde_json::from_value::<AccountIdLight>(value).unwrap(), light);
+        }
+    }
+
+    #[test]
+    fn generated_alias_can_be_parsed() {
+        let alias = AccountAlias::generate();
+        assert_eq!(alias.as_str().len(), AccountAlias::LENGTH);
+        assert_eq!(AccountAlias::parse(alias.as_str()), Some(alias.clone()));
+        assert_eq!(
+            AccountAlias::parse(&alias.as_str().to_ascii_uppercase()),
+            Some(alias)
+        );
+        assert_eq!(AccountAlias::parse("0123456789ui"), None);
+        assert_eq!(AccountAlias::parse("0123"), None);
+    }
+
+    #[test]
+    fn account_json_without_times_is_accepted() {
+        let account: Account = serde_json::from_str(r#"{"state":"Normal"}"#).unwrap();
+        assert_eq!(account, Account::new_from(AccountState::Normal));
+    }
+
+    #[test]
+    fn optional_email_is_validated_only_if_set() {
+        let empty = AccountSetup::default();
+        assert_eq!(
+            empty.validate(&[AccountSetupField::Email]),
+            Err(AccountSetupErrorCode::EmailEmpty)
+        );
+        assert_eq!(empty.validate(&[]), Ok(()));
+
+        let invalid = AccountSetup {
+            email: "a".to_string(),
+        };
+        assert_eq!(
+            invalid.validate(&[]),
+            Err(AccountSetupErrorCode::EmailInvalid)
+        );
+    }
+}
@@ -1,13 +1,21 @@
 //! Handlers for internal from Server to Server state transfers and messages
 
-use axum::{extract::Path, Json};
+use axum::Json;
 
 use hyper::StatusCode;
 
-use crate::api::{GetUsers, ReadDatabase};
+use crate::{
+    api::{
+        utils::AccountIdPath, GetInternalApi, GetUsers, GetWebhooks, ReadDatabase, WriteDatabase,
+    },
+    server::database::utils::current_unix_time,
+};
 
 use super::{
-    data::{Account, AccountIdLight, ApiKey},
+    data::{
+        Account, AccountIdLight, AccountIdOrAlias, AccountReport, ApiKey, JsonMigrationResult,
+        TokenHash, WebhookDeliveryList,
+    },
     GetApiKeys,
 };
 
@@ -37,30 +45,53 @@ pub async fn check_api_key<S: GetApiKeys>(
         .map(|id| id.as_light().into())
 }
 
+pub const PATH_INTERNAL_POST_API_KEY_REVOKED: &str = "/internal/api_key_revoked";
+
+/// Account server calls this when an API key is revoked, so that the key is
+/// removed from the cache of keys checked from the account server. Request
+/// body is the SHA-256 hash of the API key.
+#[utoipa::path(
+    post,
+    path = "/internal/api_key_revoked",
+    request_body(content = TokenHash),
+    responses(
+        (status = 200, description = "API key is removed from the cache"),
+    ),
+    security(),
+)]
+pub async fn internal_post_api_key_revoked<S: GetInternalApi>(
+    Json(hash): Json<TokenHash>,
+    state: S,
+) {
+    state.internal_api().api_key_revoked(&hash);
+}
+
 pub const PATH_INTERNAL_GET_ACCOUNT_STATE: &str = "/internal/get_account_state/:account_id";
 
 #[utoipa::path(
     get,
     path = "/internal/get_account_state/{account_id}",
-    params(AccountIdLight),
+    params(AccountIdOrAlias),
     responses(
         (status = 200, description = "Get current account state", body = Account),
-        (status = 500, description = "Internal server error or account ID was invalid"),
+        (status = 404, description = "Account not found"),
+        (status = 500, description = "Internal server error"),
     ),
     security(),
 )]
 pub async fn internal_get_account_state<S: ReadDatabase + GetUsers>(
-    Path(account_id): Path<AccountIdLight>,
+    account_id: AccountIdPath,
     state: S,
 ) -> Result<Json<Account>, StatusCode> {
     let internal_id = state
         .users()
-        .get_internal_id(account_id)
+        .get_internal_id_from_path(account_id)
         .await
         .map_err(|e| {
             error!("Internal get account state error: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
 
     state
         .read_database()
@@ -72,3 +103,88 @@ pub async fn internal_get_account_state<S: ReadDatabase + GetUsers>(
             StatusCode::INTERNAL_SERVER_ERROR
         })
 }
+
+pub const PATH_INTERNAL_MIGRATE_JSON: &str = "/internal/migrate_json";
+
+/// Rewrite all stored JSON data (Account, AccountSetup, NotificationSettings)
+/// with the current JSON data version. Reading older data migrates it also
+/// lazily, so this is only needed when all data should be up to date.
+#[utoipa::path(
+    post,
+    path = "/internal/migrate_json",
+    responses(
+        (status = 200, description = "Migration completed", body = JsonMigrationResult),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(),
+)]
+pub async fn post_migrate_json<S: WriteDatabase>(
+    state: S,
+) -> Result<Json<JsonMigrationResult>, StatusCode> {
+    state
+        .write_database()
+        .migrate_json()
+        .await
+        .map(|count| JsonMigrationResult { rewritten: count }.into())
+        .map_err(|e| {
+            error!("Internal migrate JSON error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_INTERNAL_GET_WEBHOOK_DELIVERIES: &str = "/internal/webhook_deliveries";
+
+/// Status of the latest account lifecycle event webhook deliveries.
+#[utoipa::path(
+    get,
+    path = "/internal/webhook_deliveries",
+    responses(
+        (status = 200, description = "Latest deliveries", body = WebhookDeliveryList),
+    ),
+    security(),
+)]
+pub async fn internal_get_webhook_deliveries<S: GetWebhooks>(
+    state: S,
+) -> Json<WebhookDeliveryList> {
+    WebhookDeliveryList {
+        deliveries: state.webhooks().deliveries().await,
+    }
+    .into()
+}
+
+pub const PATH_INTERNAL_GET_ACCOUNT_REPORT: &str = "/internal/reports/accounts";
+
+/// Registrations per sign in provider, setup completion rate and daily and
+/// weekly active accounts.
+#[utoipa::path(
+    get,
+    path = "/internal/reports/accounts",
+    responses(
+        (status = 200, description = "Account report", body = AccountReport),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(),
+)]
+pub async fn internal_get_account_report<S: ReadDatabase + WriteDatabase>(
+    state: S,
+) -> Result<Json<AccountReport>, StatusCode> {
+    // Include activity which is only in the cache.
+    state
+        .write_database()
+        .save_account_activity()
+        .await
+        .map_err(|e| {
+            error!("Internal get account report error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    state
+        .read_database()
+        .account_report(current_unix_time())
+        .await
+        .map(|report| report.into())
+        .map_err(|e| {
+            error!("Internal get account report error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
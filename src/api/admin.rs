@@ -0,0 +1,430 @@
+//! Internal routes for account administration and moderation
+
+pub mod cli;
+
+use axum::{extract::Query, Json};
+
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{
+    account::record_login_history,
+    model::{
+        Account, AccountEventType, AccountIdInternal, AccountIdLight, AccountIdOrAlias,
+        AccountState, AccountTimes, CreatedServiceToken, LoginMethod, NewServiceToken,
+    },
+    utils::AccountIdPath,
+    GetConfig, GetExports, GetStaleAccountCleanup, GetUsers, GetWebhooks, ReadDatabase,
+    WriteDatabase,
+};
+
+use crate::server::app::stale::StaleAccountCleanupError;
+
+pub const DEFAULT_ACCOUNT_PAGE_SIZE: u32 = 100;
+pub const MAX_ACCOUNT_PAGE_SIZE: u32 = 1000;
+pub const DEFAULT_STALE_ACCOUNT_BATCH_SIZE: u32 = 100;
+pub const MAX_STALE_ACCOUNT_BATCH_SIZE: u32 = 1000;
+
+#[derive(Debug, Clone, Deserialize, Serialize, IntoParams, PartialEq, Eq)]
+#[into_params(parameter_in = Query)]
+pub struct AccountListQuery {
+    /// Page number starting from zero. Default is zero.
+    pub page: Option<u32>,
+    /// Default is 100 and maximum is 1000.
+    pub page_size: Option<u32>,
+    /// List only accounts of this data residency region.
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct AdminAccountInfo {
+    pub account_id: AccountIdLight,
+    pub state: AccountState,
+    /// None if the account was registered before registration time was
+    /// saved.
+    pub created_unix_time: Option<i64>,
+    /// None if the account has not logged in after login time was saved.
+    pub last_login_unix_time: Option<i64>,
+    /// Data residency region. None if the account does not have a region.
+    pub region: Option<String>,
+}
+
+/// Accounts in registration order.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct AdminAccountList {
+    pub accounts: Vec<AdminAccountInfo>,
+    pub page: u32,
+    pub page_size: u32,
+}
+
+pub const PATH_INTERNAL_GET_ADMIN_ACCOUNTS: &str = "/internal/admin/accounts";
+
+/// List accounts with paging. Accounts can be filtered by region.
+#[utoipa::path(
+    get,
+    path = "/internal/admin/accounts",
+    params(AccountListQuery),
+    responses(
+        (status = 200, description = "Accounts on the requested page.", body = AdminAccountList),
+        (status = 400, description = "Page size is too large."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_get_admin_accounts<S: ReadDatabase>(
+    Query(query): Query<AccountListQuery>,
+    state: S,
+) -> Result<Json<AdminAccountList>, StatusCode> {
+    let page = query.page.unwrap_or(0);
+    let page_size = query.page_size.unwrap_or(DEFAULT_ACCOUNT_PAGE_SIZE);
+    if page_size > MAX_ACCOUNT_PAGE_SIZE {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let ids = state
+        .read_database()
+        .account_ids_page(page, page_size, query.region.as_deref())
+        .await
+        .map_err(|e| {
+            error!("Admin get accounts error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut accounts = Vec::with_capacity(ids.len());
+    for id in ids {
+        let account = read_account(&state, id).await?;
+        let times = read_account_times(&state, id).await?;
+        let region = state
+            .read_database()
+            .account_region(id)
+            .await
+            .map_err(|e| {
+                error!("Admin get accounts error: {e:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        accounts.push(AdminAccountInfo {
+            account_id: id.as_light(),
+            state: account.state(),
+            created_unix_time: times.created_unix_time,
+            last_login_unix_time: times.last_login_unix_time,
+            region,
+        });
+    }
+
+    Ok(AdminAccountList {
+        accounts,
+        page,
+        page_size,
+    }
+    .into())
+}
+
+pub const PATH_INTERNAL_GET_ADMIN_ACCOUNT: &str = "/internal/admin/accounts/:account_id";
+
+#[utoipa::path(
+    get,
+    path = "/internal/admin/accounts/{account_id}",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Current account state, registration time and latest login time.", body = Account),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_get_admin_account<S: ReadDatabase + GetUsers>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<Json<Account>, StatusCode> {
+    let id = internal_id(&state, account_id).await?;
+    let account = read_account(&state, id).await?;
+    let times = read_account_times(&state, id).await?;
+    Ok(account.with_times(times).into())
+}
+
+pub const PATH_INTERNAL_POST_ADMIN_LOGOUT: &str = "/internal/admin/accounts/:account_id/logout";
+
+/// Remove access and refresh tokens of the account. The account can login
+/// again.
+#[utoipa::path(
+    post,
+    path = "/internal/admin/accounts/{account_id}/logout",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Account is logged out."),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_post_admin_logout<S: WriteDatabase + GetUsers>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<(), StatusCode> {
+    let id = internal_id(&state, account_id).await?;
+    logout(&state, id).await
+}
+
+pub const PATH_INTERNAL_POST_ADMIN_BAN: &str = "/internal/admin/accounts/:account_id/ban";
+
+/// Ban the account and log it out. Banned account can only get the account
+/// state from private routes.
+#[utoipa::path(
+    post,
+    path = "/internal/admin/accounts/{account_id}/ban",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Account is banned."),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_post_admin_ban<S: ReadDatabase + WriteDatabase + GetUsers + GetWebhooks>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<(), StatusCode> {
+    let id = internal_id(&state, account_id).await?;
+    change_account_state(&state, id, Account::ban).await?;
+    logout(&state, id).await
+}
+
+pub const PATH_INTERNAL_POST_ADMIN_UNBAN: &str = "/internal/admin/accounts/:account_id/unban";
+
+/// Move banned account to normal state. Does nothing if the account is not
+/// banned.
+#[utoipa::path(
+    post,
+    path = "/internal/admin/accounts/{account_id}/unban",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Account is not banned."),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_post_admin_unban<S: ReadDatabase + WriteDatabase + GetUsers + GetWebhooks>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<(), StatusCode> {
+    let id = internal_id(&state, account_id).await?;
+    change_account_state(&state, id, Account::unban).await
+}
+
+pub const PATH_INTERNAL_POST_ADMIN_SERVICE_TOKEN: &str =
+    "/internal/admin/accounts/:account_id/service_tokens";
+
+/// Create a service token for the account. The account can list and revoke
+/// the token like its own service tokens.
+#[utoipa::path(
+    post,
+    path = "/internal/admin/accounts/{account_id}/service_tokens",
+    params(AccountIdOrAlias),
+    request_body = NewServiceToken,
+    responses(
+        (status = 200, description = "Service token created.", body = CreatedServiceToken),
+        (status = 400, description = "Name is empty or too long."),
+        (status = 406, description = "Service token limit reached."),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_post_admin_service_token<S: WriteDatabase + GetUsers>(
+    account_id: AccountIdPath,
+    Json(token): Json<NewServiceToken>,
+    state: S,
+) -> Result<Json<CreatedServiceToken>, StatusCode> {
+    let id = internal_id(&state, account_id).await?;
+    super::account::create_service_token(&state, id, token)
+        .await
+        .map(|t| t.into())
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct StaleAccountCleanupRequest {
+    /// Accounts which have not been active, logged in or registered during
+    /// this many days are stale. Default is the `stale_account_days` config
+    /// value.
+    pub inactive_days: Option<u32>,
+    /// Send the `inactive` webhook event for every stale account.
+    #[serde(default)]
+    pub notify: bool,
+    /// Request deletion of every stale account. Deleted accounts have the
+    /// normal deletion grace period.
+    #[serde(default)]
+    pub schedule_deletion: bool,
+    /// Accounts handled between progress updates. Default is 100 and
+    /// maximum is 1000.
+    pub batch_size: Option<u32>,
+}
+
+/// Progress of the latest stale account cleanup.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct StaleAccountCleanupStatus {
+    pub running: bool,
+    pub started_unix_time: i64,
+    pub finished_unix_time: Option<i64>,
+    /// Accounts which latest activity is before this are stale.
+    pub inactive_before_unix_time: i64,
+    pub notify: bool,
+    pub schedule_deletion: bool,
+    /// Stale accounts handled so far.
+    pub found: u64,
+    pub notified: u64,
+    pub deletion_scheduled: u64,
+    /// Stale accounts which are not in normal state. Banned accounts and
+    /// accounts in initial setup are not changed.
+    pub skipped: u64,
+    pub failed: u64,
+}
+
+pub const PATH_INTERNAL_ADMIN_STALE_ACCOUNT_CLEANUP: &str =
+    "/internal/admin/stale_accounts/cleanup";
+
+/// Start stale account cleanup in the background. Accounts are handled in
+/// batches and deletion requests go through the write command runner.
+#[utoipa::path(
+    post,
+    path = "/internal/admin/stale_accounts/cleanup",
+    request_body = StaleAccountCleanupRequest,
+    responses(
+        (status = 200, description = "Cleanup started.", body = StaleAccountCleanupStatus),
+        (status = 400, description = "Batch size is too large or zero."),
+        (status = 409, description = "Cleanup is already running."),
+    ),
+    security(),
+)]
+pub async fn internal_post_stale_account_cleanup<
+    S: GetStaleAccountCleanup
+        + ReadDatabase
+        + WriteDatabase
+        + GetExports
+        + GetConfig
+        + GetWebhooks
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+>(
+    Json(request): Json<StaleAccountCleanupRequest>,
+    state: S,
+) -> Result<Json<StaleAccountCleanupStatus>, StatusCode> {
+    let batch_size = request
+        .batch_size
+        .unwrap_or(DEFAULT_STALE_ACCOUNT_BATCH_SIZE);
+    if batch_size == 0 || batch_size > MAX_STALE_ACCOUNT_BATCH_SIZE {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    state
+        .stale_account_cleanup()
+        .start(state.clone(), request)
+        .await
+        .map(|status| status.into())
+        .map_err(|e| match e {
+            StaleAccountCleanupError::AlreadyRunning => StatusCode::CONFLICT,
+        })
+}
+
+/// Progress of the running or the latest finished stale account cleanup.
+#[utoipa::path(
+    get,
+    path = "/internal/admin/stale_accounts/cleanup",
+    responses(
+        (status = 200, description = "Cleanup progress.", body = StaleAccountCleanupStatus),
+        (status = 404, description = "Cleanup has not been started."),
+    ),
+    security(),
+)]
+pub async fn internal_get_stale_account_cleanup<S: GetStaleAccountCleanup>(
+    state: S,
+) -> Result<Json<StaleAccountCleanupStatus>, StatusCode> {
+    state
+        .stale_account_cleanup()
+        .status()
+        .await
+        .map(|status| status.into())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn internal_id<S: GetUsers>(
+    state: &S,
+    account_id: AccountIdPath,
+) -> Result<AccountIdInternal, StatusCode> {
+    state
+        .users()
+        .get_internal_id_from_path(account_id)
+        .await
+        .map_err(|e| {
+            error!("Admin API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn read_account<S: ReadDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+) -> Result<Account, StatusCode> {
+    state
+        .read_database()
+        .read_json::<Account>(id)
+        .await
+        .map_err(|e| {
+            error!("Admin API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn read_account_times<S: ReadDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+) -> Result<AccountTimes, StatusCode> {
+    state.read_database().account_times(id).await.map_err(|e| {
+        error!("Admin API error: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn change_account_state<S: ReadDatabase + WriteDatabase + GetWebhooks>(
+    state: &S,
+    id: AccountIdInternal,
+    change: fn(&mut Account),
+) -> Result<(), StatusCode> {
+    let mut account = read_account(state, id).await?;
+    let old_state = account.state();
+    change(&mut account);
+    if account.state() == old_state {
+        return Ok(());
+    }
+
+    state
+        .write_database()
+        .account()
+        .update_account(id, account.clone())
+        .await
+        .map_err(|e| {
+            error!("Admin API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if let Some(event) = AccountEventType::from_state_change(old_state, account.state()) {
+        state.webhooks().send(event, id.as_light());
+    }
+
+    Ok(())
+}
+
+async fn logout<S: WriteDatabase>(state: &S, id: AccountIdInternal) -> Result<(), StatusCode> {
+    state.write_database().logout(id).await.map_err(|e| {
+        error!("Admin API error: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    record_login_history(state, id, LoginMethod::Logout, None).await;
+    Ok(())
+}
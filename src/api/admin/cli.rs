@@ -0,0 +1,83 @@
+//! Command line client for the stale account cleanup
+
+use std::time::Duration;
+
+use error_stack::{IntoReport, Result};
+use reqwest::StatusCode;
+
+use crate::{config::args::StaleAccountCleanupCommand, utils::IntoReportExt};
+
+use super::{StaleAccountCleanupStatus, PATH_INTERNAL_ADMIN_STALE_ACCOUNT_CLEANUP};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(thiserror::Error, Debug)]
+pub enum StaleAccountCleanupCliError {
+    #[error("Invalid internal API URL")]
+    Url,
+    #[error("Request to the internal API failed")]
+    Request,
+    #[error("Cleanup is already running")]
+    AlreadyRunning,
+}
+
+/// Start the cleanup using the internal API of a running server and print
+/// progress to stdout until the cleanup is finished.
+///
+/// The `client` should be the internal API client from the config, so the
+/// client certificate is used if it is configured.
+pub async fn run_stale_account_cleanup_command(
+    command: StaleAccountCleanupCommand,
+    client: reqwest::Client,
+) -> Result<(), StaleAccountCleanupCliError> {
+    let url = command
+        .url_internal
+        .join(PATH_INTERNAL_ADMIN_STALE_ACCOUNT_CLEANUP)
+        .into_error(StaleAccountCleanupCliError::Url)?;
+
+    let response = client
+        .post(url.clone())
+        .json(&command.request)
+        .send()
+        .await
+        .into_error(StaleAccountCleanupCliError::Request)?;
+    if response.status() == StatusCode::CONFLICT {
+        return Err(StaleAccountCleanupCliError::AlreadyRunning).into_report();
+    }
+    let mut status: StaleAccountCleanupStatus = response
+        .error_for_status()
+        .into_error(StaleAccountCleanupCliError::Request)?
+        .json()
+        .await
+        .into_error(StaleAccountCleanupCliError::Request)?;
+
+    println!(
+        "Cleanup started, inactive before unix time {}",
+        status.inactive_before_unix_time
+    );
+
+    while status.running {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        status = client
+            .get(url.clone())
+            .send()
+            .await
+            .into_error(StaleAccountCleanupCliError::Request)?
+            .error_for_status()
+            .into_error(StaleAccountCleanupCliError::Request)?
+            .json()
+            .await
+            .into_error(StaleAccountCleanupCliError::Request)?;
+        print_progress(&status);
+    }
+
+    println!("Cleanup finished");
+    Ok(())
+}
+
+fn print_progress(status: &StaleAccountCleanupStatus) {
+    println!(
+        "found: {}, notified: {}, deletion scheduled: {}, skipped: {}, failed: {}",
+        status.found, status.notified, status.deletion_scheduled, status.skipped, status.failed,
+    );
+}
@@ -0,0 +1,203 @@
+//! Internal routes for debugging DatabaseCache
+
+use axum::Json;
+
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use tracing::error;
+
+use super::{
+    common::ClientVersion,
+    model::{Account, AccountIdOrAlias},
+    utils::AccountIdPath,
+    GetUsers, ReadDatabase, WriteDatabase,
+};
+
+/// Account's cache entry. Access token is not included.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct CacheEntryInfo {
+    /// Cached account data. None if account data is not cached.
+    pub account: Option<Account>,
+    /// Cached account data equals to data in SQLite. None if account data
+    /// is not cached.
+    pub account_matches_database: Option<bool>,
+    /// IP address and port of the current WebSocket connection.
+    pub current_connection: Option<String>,
+    /// Client version of the current WebSocket connection.
+    pub client_version: Option<ClientVersion>,
+    pub access_token_exists: bool,
+}
+
+pub const PATH_INTERNAL_GET_CACHE_ENTRY: &str = "/internal/cache/:account_id";
+
+#[utoipa::path(
+    get,
+    path = "/internal/cache/{account_id}",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Cache entry info", body = CacheEntryInfo),
+        (status = 404, description = "Account not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(),
+)]
+pub async fn internal_get_cache_entry<S: ReadDatabase + GetUsers>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<Json<CacheEntryInfo>, StatusCode> {
+    let internal_id = state
+        .users()
+        .get_internal_id_from_path(account_id)
+        .await
+        .map_err(|e| {
+            error!("Internal get cache entry error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (account, current_connection, client_version, access_token_exists) = state
+        .read_database()
+        .cache_entry_info(internal_id.as_light())
+        .await
+        .map_err(|e| {
+            error!("Internal get cache entry error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let account_matches_database = match &account {
+        Some(account) => {
+            let database_account = state
+                .read_database()
+                .read_json_from_database::<Account>(internal_id)
+                .await
+                .map_err(|e| {
+                    error!("Internal get cache entry error: {e:?}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            Some(*account == database_account)
+        }
+        None => None,
+    };
+
+    Ok(CacheEntryInfo {
+        account,
+        account_matches_database,
+        current_connection: current_connection.map(|a| a.to_string()),
+        client_version,
+        access_token_exists,
+    }
+    .into())
+}
+
+pub const PATH_INTERNAL_POST_REFRESH_CACHE_ENTRY: &str = "/internal/cache/:account_id/refresh";
+
+/// Replace cached data with data from SQLite.
+#[utoipa::path(
+    post,
+    path = "/internal/cache/{account_id}/refresh",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Cache entry refreshed"),
+        (status = 404, description = "Account not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(),
+)]
+pub async fn internal_post_refresh_cache_entry<S: WriteDatabase + GetUsers>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<(), StatusCode> {
+    let internal_id = state
+        .users()
+        .get_internal_id_from_path(account_id)
+        .await
+        .map_err(|e| {
+            error!("Internal refresh cache entry error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state
+        .write_database()
+        .refresh_cache_entry(internal_id)
+        .await
+        .map_err(|e| {
+            error!("Internal refresh cache entry error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_INTERNAL_POST_EVICT_CACHE_ENTRY: &str = "/internal/cache/:account_id/evict";
+
+/// Remove cached data. Account ID and access token stay in the cache.
+#[utoipa::path(
+    post,
+    path = "/internal/cache/{account_id}/evict",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Cached data removed"),
+        (status = 404, description = "Account not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(),
+)]
+pub async fn internal_post_evict_cache_entry<S: WriteDatabase + GetUsers>(
+    account_id: AccountIdPath,
+    state: S,
+) -> Result<(), StatusCode> {
+    let internal_id = state
+        .users()
+        .get_internal_id_from_path(account_id)
+        .await
+        .map_err(|e| {
+            error!("Internal evict cache entry error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state
+        .write_database()
+        .evict_cache_entry(internal_id)
+        .await
+        .map_err(|e| {
+            error!("Internal evict cache entry error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::CacheEntryInfo;
+    use crate::{
+        api::{account::PATH_GET_ACCOUNT_ALIAS, model::AccountAliasInfo},
+        server::test_support::{response_json, TestApp},
+    };
+
+    #[tokio::test]
+    async fn account_alias_can_be_used_in_internal_routes() {
+        let app = TestApp::new().await;
+        let (_, login) = app.register_and_login().await;
+
+        let response = app
+            .get(PATH_GET_ACCOUNT_ALIAS, Some(&login.account.access))
+            .await;
+        let info: AccountAliasInfo = response_json(response, StatusCode::OK).await;
+        let alias = info.alias.unwrap();
+
+        let response = app
+            .get(&format!("/internal/cache/{}", alias.to_uppercase()), None)
+            .await;
+        let _: CacheEntryInfo = response_json(response, StatusCode::OK).await;
+
+        let response = app.get("/internal/cache/000000000000", None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app.get("/internal/cache/not-an-alias", None).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        app.close().await;
+    }
+}
@@ -1,16 +1,43 @@
 pub mod data;
 
-use axum::{Extension, Json};
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
 
 use hyper::StatusCode;
 
-use self::data::{CalculatorState, CalculatorStateInternal};
+use crate::{
+    calculator::{self, EvaluationCacheStats, OperationRegistry},
+    server::{
+        analytics::{
+            EVENT_CALCULATION_EVALUATED, EVENT_CALCULATION_SCHEDULE_CREATED,
+            EVENT_CALCULATOR_STATE_SAVED, EVENT_HISTORY_ENTRY_RERUN,
+        },
+        database::utils::current_unix_time,
+    },
+};
 
-use super::{model::AccountIdInternal, GetInternalApi, GetUsers};
+use self::data::{
+    CalculationRequest, CalculationResult, CalculationScheduleList, CalculatorBroadcast,
+    CalculatorSettings, CalculatorSlotList, CalculatorSlotName, CalculatorState,
+    CalculatorStateInternal, CalculatorStateShare, ExpressionError, HistoryEntry,
+    HistoryEntryInternal, HistoryId, NewCalculationSchedule, NewHistoryEntry, ScheduleId,
+};
+
+use super::{
+    model::{AccountIdInternal, AccountIdOrAlias},
+    organization, GetAnalytics, GetConfig, GetEvaluationCache, GetInternalApi, GetOperations,
+    GetTranslations, GetUsers,
+};
 
 use tracing::error;
 
-use super::{GetApiKeys, ReadDatabase, WriteDatabase};
+use super::{
+    utils::{write_error_status, AccountIdPath, BodyFormat, MsgPackOrJson, Negotiated},
+    GetApiKeys, ReadDatabase, WriteDatabase,
+};
 
 // TODO: Add timeout for database commands
 
@@ -18,11 +45,17 @@ pub const PATH_GET_CALCULATOR_STATE: &str = "/calculator_api/state";
 
 /// Get account's current calculator state.
 ///
+/// Response is MessagePack if Accept header contains `application/msgpack`.
 #[utoipa::path(
     get,
     path = "/calculator_api/state",
     responses(
-        (status = 200, description = "Get current state.", body = CalculatorState),
+        (
+            status = 200,
+            description = "Get current state.",
+            body = CalculatorState,
+            content_type = ["application/json", "application/msgpack"],
+        ),
         (status = 401, description = "Unauthorized."),
         (
             status = 500,
@@ -35,16 +68,14 @@ pub async fn get_calculator_state<
     S: ReadDatabase + GetUsers + GetApiKeys + GetInternalApi + WriteDatabase,
 >(
     Extension(account_id): Extension<AccountIdInternal>,
+    format: BodyFormat,
     state: S,
-) -> Result<Json<CalculatorState>, StatusCode> {
+) -> Result<Negotiated<CalculatorState>, StatusCode> {
     state
         .read_database()
         .read_json::<CalculatorStateInternal>(account_id)
         .await
-        .map(|state| {
-            let state: CalculatorState = state.into();
-            state.into()
-        })
+        .map(|state| Negotiated::new(format, state.into()))
         .map_err(|e| {
             error!("{e:?}");
             StatusCode::INTERNAL_SERVER_ERROR
@@ -54,6 +85,8 @@ pub async fn get_calculator_state<
 pub const PATH_POST_CALCULATOR_STATE: &str = "/calculator_api/state";
 
 /// Update calculator state.
+///
+/// Request body can be MessagePack if Content-Type is `application/msgpack`.
 #[utoipa::path(
     post,
     path = "/calculator_api/state",
@@ -68,9 +101,9 @@ pub const PATH_POST_CALCULATOR_STATE: &str = "/calculator_api/state";
     ),
     security(("api_key" = [])),
 )]
-pub async fn post_calculator_state<S: GetApiKeys + WriteDatabase + ReadDatabase>(
+pub async fn post_calculator_state<S: GetApiKeys + WriteDatabase + ReadDatabase + GetAnalytics>(
     Extension(account_id): Extension<AccountIdInternal>,
-    Json(calculator_state): Json<CalculatorState>,
+    MsgPackOrJson(calculator_state): MsgPackOrJson<CalculatorState>,
     state: S,
 ) -> Result<(), StatusCode> {
     let new = CalculatorStateInternal {
@@ -84,8 +117,944 @@ pub async fn post_calculator_state<S: GetApiKeys + WriteDatabase + ReadDatabase>
         .await
         .map_err(|e| {
             error!("{e:?}");
-            StatusCode::INTERNAL_SERVER_ERROR // Database writing failed.
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    state.analytics().track(
+        EVENT_CALCULATOR_STATE_SAVED,
+        Some(account_id.as_light()),
+        serde_json::json!({}),
+    );
+
+    Ok(())
+}
+
+pub const PATH_POST_CALCULATE: &str = "/calculator_api/calculate";
+
+/// Evaluate expression with account's calculator settings. Formatted result
+/// is saved as the calculator state.
+#[utoipa::path(
+    post,
+    path = "/calculator_api/calculate",
+    request_body = CalculationRequest,
+    responses(
+        (status = 200, description = "Evaluation result.", body = CalculationResult),
+        (status = 400, description = "Invalid expression or expression limit exceeded.", body = ExpressionError),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn post_calculate<
+    S: ReadDatabase
+        + WriteDatabase
+        + GetConfig
+        + GetOperations
+        + GetEvaluationCache
+        + GetTranslations
+        + GetAnalytics,
+>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Json(request): Json<CalculationRequest>,
+    state: S,
+) -> Result<Json<CalculationResult>, ExpressionRequestError> {
+    let settings = read_calculator_settings(account_id, &state).await?;
+    let organization_operations = organization_operations(account_id, &state).await?;
+    let evaluation = state.evaluation_cache().evaluate(
+        &request.expression,
+        organization_operations
+            .as_ref()
+            .unwrap_or_else(|| state.operations()),
+        &state.config().evaluation_limits(),
+        settings.angle_mode,
+    );
+
+    let mut evaluation = match evaluation {
+        Ok(evaluation) => evaluation,
+        Err(mut diagnostics) => {
+            for diagnostic in &mut diagnostics {
+                diagnostic.localize(state.translations(), &settings.locale);
+            }
+            return Err(ExpressionRequestError::InvalidExpression(ExpressionError {
+                diagnostics,
+            }));
+        }
+    };
+    for warning in &mut evaluation.warnings {
+        warning.localize(state.translations(), &settings.locale);
+    }
+
+    let formatted_result = settings.number_format().format(evaluation.value);
+    let new = CalculatorStateInternal {
+        state: formatted_result.clone(),
+    };
+
+    state
+        .write_database()
+        .calculator()
+        .update_calculator_state(account_id, new)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
         })?;
 
+    state.analytics().track(
+        EVENT_CALCULATION_EVALUATED,
+        Some(account_id.as_light()),
+        serde_json::json!({}),
+    );
+
+    Ok(CalculationResult {
+        result: evaluation.value,
+        formatted_result,
+        warnings: evaluation.warnings,
+    }
+    .into())
+}
+
+/// Error for handlers which parse expressions. Invalid expression is
+/// reported with status code 400 and diagnostics in the response body.
+pub enum ExpressionRequestError {
+    Status(StatusCode),
+    InvalidExpression(ExpressionError),
+}
+
+impl From<StatusCode> for ExpressionRequestError {
+    fn from(value: StatusCode) -> Self {
+        Self::Status(value)
+    }
+}
+
+impl IntoResponse for ExpressionRequestError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Status(status) => status.into_response(),
+            Self::InvalidExpression(e) => (StatusCode::BAD_REQUEST, Json(e)).into_response(),
+        }
+    }
+}
+
+/// Shortest allowed interval for repeating calculation schedules.
+pub const MIN_SCHEDULE_REPEAT_INTERVAL_SECONDS: i64 = 60;
+
+async fn validate_schedule<S: ReadDatabase + GetConfig + GetOperations + GetTranslations>(
+    account_id: AccountIdInternal,
+    schedule: &NewCalculationSchedule,
+    state: &S,
+) -> Result<(), ExpressionRequestError> {
+    let valid_interval = schedule
+        .repeat_interval_seconds
+        .map(|interval| interval >= MIN_SCHEDULE_REPEAT_INTERVAL_SECONDS)
+        .unwrap_or(true);
+    if !valid_interval {
+        return Err(StatusCode::NOT_ACCEPTABLE.into());
+    }
+
+    let organization_operations = organization_operations(account_id, state).await?;
+    let result = calculator::parse(
+        &schedule.expression,
+        organization_operations
+            .as_ref()
+            .unwrap_or_else(|| state.operations()),
+        &state.config().evaluation_limits(),
+        &mut vec![],
+    );
+    if let Err(mut diagnostics) = result {
+        let settings = read_calculator_settings(account_id, state).await?;
+        for diagnostic in &mut diagnostics {
+            diagnostic.localize(state.translations(), &settings.locale);
+        }
+        return Err(ExpressionRequestError::InvalidExpression(ExpressionError {
+            diagnostics,
+        }));
+    }
+
     Ok(())
 }
+
+pub const PATH_GET_CALCULATION_SCHEDULES: &str = "/calculator_api/schedules";
+
+/// Get account's calculation schedules.
+#[utoipa::path(
+    get,
+    path = "/calculator_api/schedules",
+    responses(
+        (status = 200, description = "Get schedules.", body = CalculationScheduleList),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_calculation_schedules<S: ReadDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<CalculationScheduleList>, StatusCode> {
+    state
+        .read_database()
+        .calculation_schedules(account_id)
+        .await
+        .map(|schedules| CalculationScheduleList { schedules }.into())
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_POST_CALCULATION_SCHEDULE: &str = "/calculator_api/schedules";
+
+/// Schedule expression evaluation. Evaluation result is stored to the
+/// calculator history.
+///
+/// Repeat interval must be at least 60 seconds. Only fixed interval repeats
+/// are supported, cron expressions are not.
+#[utoipa::path(
+    post,
+    path = "/calculator_api/schedules",
+    request_body = NewCalculationSchedule,
+    responses(
+        (status = 200, description = "Schedule created.", body = ScheduleId),
+        (status = 400, description = "Invalid expression or expression limit exceeded.", body = ExpressionError),
+        (status = 401, description = "Unauthorized."),
+        (status = 406, description = "Invalid repeat interval or schedule limit reached."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn post_calculation_schedule<
+    S: ReadDatabase + WriteDatabase + GetConfig + GetOperations + GetTranslations + GetAnalytics,
+>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Json(schedule): Json<NewCalculationSchedule>,
+    state: S,
+) -> Result<Json<ScheduleId>, ExpressionRequestError> {
+    validate_schedule(account_id, &schedule, &state).await?;
+
+    let id = state
+        .write_database()
+        .calculator()
+        .create_calculation_schedule(
+            account_id,
+            schedule,
+            state.config().max_schedules_per_account(),
+        )
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?
+        .ok_or(StatusCode::NOT_ACCEPTABLE)?;
+
+    state.analytics().track(
+        EVENT_CALCULATION_SCHEDULE_CREATED,
+        Some(account_id.as_light()),
+        serde_json::json!({}),
+    );
+
+    Ok(id.into())
+}
+
+pub const PATH_PUT_CALCULATION_SCHEDULE: &str = "/calculator_api/schedules/:schedule_id";
+
+/// Replace calculation schedule.
+#[utoipa::path(
+    put,
+    path = "/calculator_api/schedules/{schedule_id}",
+    params(ScheduleId),
+    request_body = NewCalculationSchedule,
+    responses(
+        (status = 200, description = "Schedule updated."),
+        (status = 400, description = "Invalid expression or expression limit exceeded.", body = ExpressionError),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Schedule not found."),
+        (status = 406, description = "Invalid repeat interval."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn put_calculation_schedule<
+    S: ReadDatabase + WriteDatabase + GetConfig + GetOperations + GetTranslations,
+>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Path(schedule_id): Path<ScheduleId>,
+    Json(schedule): Json<NewCalculationSchedule>,
+    state: S,
+) -> Result<(), ExpressionRequestError> {
+    validate_schedule(account_id, &schedule, &state).await?;
+
+    let updated = state
+        .write_database()
+        .calculator()
+        .update_calculation_schedule(account_id, schedule_id, schedule)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    if updated {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND.into())
+    }
+}
+
+pub const PATH_DELETE_CALCULATION_SCHEDULE: &str = "/calculator_api/schedules/:schedule_id";
+
+/// Delete calculation schedule.
+#[utoipa::path(
+    delete,
+    path = "/calculator_api/schedules/{schedule_id}",
+    params(ScheduleId),
+    responses(
+        (status = 200, description = "Schedule deleted."),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Schedule not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn delete_calculation_schedule<S: WriteDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Path(schedule_id): Path<ScheduleId>,
+    state: S,
+) -> Result<(), StatusCode> {
+    let deleted = state
+        .write_database()
+        .calculator()
+        .delete_calculation_schedule(account_id, schedule_id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    if deleted {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub const PATH_GET_CALCULATOR_SLOTS: &str = "/calculator_api/slots";
+
+/// Get account's named calculator states.
+#[utoipa::path(
+    get,
+    path = "/calculator_api/slots",
+    responses(
+        (status = 200, description = "Get slots.", body = CalculatorSlotList),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_calculator_slots<S: ReadDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<CalculatorSlotList>, StatusCode> {
+    state
+        .read_database()
+        .calculator_slots(account_id)
+        .await
+        .map(|slots| CalculatorSlotList { slots }.into())
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_PUT_CALCULATOR_SLOT: &str = "/calculator_api/slots/:slot_name";
+
+/// Save calculator state to a named slot. Existing slot is replaced.
+///
+/// Slot count is limited with `max_slots_per_account`.
+#[utoipa::path(
+    put,
+    path = "/calculator_api/slots/{slot_name}",
+    params(CalculatorSlotName),
+    request_body = CalculatorState,
+    responses(
+        (status = 200, description = "Slot saved."),
+        (status = 400, description = "Invalid slot name."),
+        (status = 401, description = "Unauthorized."),
+        (status = 406, description = "Slot limit reached."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn put_calculator_slot<S: WriteDatabase + GetConfig>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Path(slot_name): Path<CalculatorSlotName>,
+    Json(calculator_state): Json<CalculatorState>,
+    state: S,
+) -> Result<(), StatusCode> {
+    if !slot_name.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let saved = state
+        .write_database()
+        .calculator()
+        .put_calculator_slot(
+            account_id,
+            slot_name,
+            calculator_state.state,
+            state.config().max_slots_per_account(),
+        )
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    if saved {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_ACCEPTABLE)
+    }
+}
+
+pub const PATH_DELETE_CALCULATOR_SLOT: &str = "/calculator_api/slots/:slot_name";
+
+/// Delete named calculator state.
+#[utoipa::path(
+    delete,
+    path = "/calculator_api/slots/{slot_name}",
+    params(CalculatorSlotName),
+    responses(
+        (status = 200, description = "Slot deleted."),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Slot not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn delete_calculator_slot<S: WriteDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Path(slot_name): Path<CalculatorSlotName>,
+    state: S,
+) -> Result<(), StatusCode> {
+    let deleted = state
+        .write_database()
+        .calculator()
+        .delete_calculator_slot(account_id, slot_name)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    if deleted {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub const PATH_GET_HISTORY_ENTRY: &str = "/calculator_api/history/:history_id";
+
+/// Get calculator history entry.
+///
+/// Response is MessagePack if Accept header contains `application/msgpack`.
+#[utoipa::path(
+    get,
+    path = "/calculator_api/history/{history_id}",
+    params(HistoryId),
+    responses(
+        (
+            status = 200,
+            description = "Get history entry.",
+            body = HistoryEntry,
+            content_type = ["application/json", "application/msgpack"],
+        ),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "History entry not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_history_entry<S: ReadDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Path(history_id): Path<HistoryId>,
+    format: BodyFormat,
+    state: S,
+) -> Result<Negotiated<HistoryEntry>, StatusCode> {
+    let entry = state
+        .read_database()
+        .history_entry(account_id, history_id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let settings = read_calculator_settings(account_id, &state).await?;
+    Ok(Negotiated::new(
+        format,
+        HistoryEntry::new(entry, &settings.number_format()),
+    ))
+}
+
+pub const PATH_POST_RERUN_HISTORY_ENTRY: &str = "/calculator_api/history/:history_id/rerun";
+
+/// Evaluate history entry's expression again with current settings. Result
+/// is stored as a new history entry, which is returned. Failed evaluation
+/// is also stored to history.
+///
+/// Response is MessagePack if Accept header contains `application/msgpack`.
+#[utoipa::path(
+    post,
+    path = "/calculator_api/history/{history_id}/rerun",
+    params(HistoryId),
+    responses(
+        (
+            status = 200,
+            description = "New history entry.",
+            body = HistoryEntry,
+            content_type = ["application/json", "application/msgpack"],
+        ),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "History entry not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn post_rerun_history_entry<
+    S: ReadDatabase + WriteDatabase + GetConfig + GetOperations + GetEvaluationCache + GetAnalytics,
+>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Path(history_id): Path<HistoryId>,
+    format: BodyFormat,
+    state: S,
+) -> Result<Negotiated<HistoryEntry>, StatusCode> {
+    let entry = state
+        .read_database()
+        .history_entry(account_id, history_id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let settings = read_calculator_settings(account_id, &state).await?;
+    let organization_operations = organization_operations(account_id, &state).await?;
+    let evaluation = state.evaluation_cache().evaluate(
+        &entry.expression,
+        organization_operations
+            .as_ref()
+            .unwrap_or_else(|| state.operations()),
+        &state.config().evaluation_limits(),
+        settings.angle_mode,
+    );
+    let new_entry =
+        NewHistoryEntry::from_evaluation(entry.expression, evaluation, current_unix_time());
+
+    let new_id = state
+        .write_database()
+        .calculator()
+        .insert_history_entry(account_id, new_entry.clone())
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })?;
+
+    state.analytics().track(
+        EVENT_HISTORY_ENTRY_RERUN,
+        Some(account_id.as_light()),
+        serde_json::json!({ "evaluation_failed": new_entry.error.is_some() }),
+    );
+
+    let new_entry = HistoryEntryInternal {
+        history_id: new_id.history_id,
+        expression: new_entry.expression,
+        result: new_entry.result,
+        error: new_entry.error,
+        unix_time: new_entry.unix_time,
+    };
+    Ok(Negotiated::new(
+        format,
+        HistoryEntry::new(new_entry, &settings.number_format()),
+    ))
+}
+
+/// Operations of the organization of the account. None if the organization
+/// does not override the operation sets of the server config.
+async fn organization_operations<S: ReadDatabase>(
+    account_id: AccountIdInternal,
+    state: &S,
+) -> Result<Option<OperationRegistry>, StatusCode> {
+    Ok(organization::account_settings(state, account_id)
+        .await?
+        .operation_sets
+        .map(|sets| OperationRegistry::with_builtin_operations(&sets)))
+}
+
+async fn read_calculator_settings<S: ReadDatabase>(
+    account_id: AccountIdInternal,
+    state: &S,
+) -> Result<CalculatorSettings, StatusCode> {
+    state
+        .read_database()
+        .read_json::<CalculatorSettings>(account_id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_CALCULATOR_SETTINGS: &str = "/calculator_api/settings";
+
+/// Get calculator settings.
+#[utoipa::path(
+    get,
+    path = "/calculator_api/settings",
+    responses(
+        (status = 200, description = "Get calculator settings.", body = CalculatorSettings),
+        (status = 401, description = "Unauthorized."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_calculator_settings<S: ReadDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<CalculatorSettings>, StatusCode> {
+    read_calculator_settings(account_id, &state)
+        .await
+        .map(|settings| settings.into())
+}
+
+/// Replace calculator settings.
+#[utoipa::path(
+    put,
+    path = "/calculator_api/settings",
+    request_body = CalculatorSettings,
+    responses(
+        (status = 200, description = "Settings updated."),
+        (status = 401, description = "Unauthorized."),
+        (status = 406, description = "Invalid display precision or locale."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn put_calculator_settings<S: WriteDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Json(settings): Json<CalculatorSettings>,
+    state: S,
+) -> Result<(), StatusCode> {
+    if !settings.is_valid() {
+        return Err(StatusCode::NOT_ACCEPTABLE);
+    }
+
+    state
+        .write_database()
+        .calculator()
+        .update_calculator_settings(account_id, settings)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })
+}
+
+pub const PATH_POST_CALCULATOR_STATE_SHARE: &str = "/calculator_api/share";
+
+/// Allow another account to read the calculator state of the current
+/// account. Accounts must belong to the same organization or neither of
+/// them can belong to an organization.
+#[utoipa::path(
+    post,
+    path = "/calculator_api/share",
+    request_body = CalculatorStateShare,
+    responses(
+        (status = 200, description = "Calculator state is shared with the account."),
+        (status = 400, description = "Account ID is the current account."),
+        (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Account belongs to a different organization."),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn post_calculator_state_share<S: ReadDatabase + WriteDatabase + GetUsers>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Json(share): Json<CalculatorStateShare>,
+    state: S,
+) -> Result<(), StatusCode> {
+    if share.account_id == account_id.as_light() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let reader = share_account_internal_id(&state, AccountIdPath::Id(share.account_id)).await?;
+    if !organization::same_organization(&state, account_id, reader).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    state
+        .write_database()
+        .calculator()
+        .share_calculator_state(account_id, reader)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })
+}
+
+pub const PATH_DELETE_CALCULATOR_STATE_SHARE: &str = "/calculator_api/share/:account_id";
+
+/// Remove read access of another account to the calculator state of the
+/// current account. Does nothing if the state is not shared with the
+/// account.
+#[utoipa::path(
+    delete,
+    path = "/calculator_api/share/{account_id}",
+    params(AccountIdOrAlias),
+    responses(
+        (status = 200, description = "Calculator state is not shared with the account."),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn delete_calculator_state_share<S: WriteDatabase + GetUsers>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    reader: AccountIdPath,
+    state: S,
+) -> Result<(), StatusCode> {
+    let reader = share_account_internal_id(&state, reader).await?;
+
+    state
+        .write_database()
+        .calculator()
+        .remove_calculator_state_share(account_id, reader)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })
+}
+
+pub const PATH_GET_SHARED_CALCULATOR_STATE: &str = "/calculator_api/state/:account_id";
+
+/// Get calculator state of another account which has shared the state with
+/// the current account. Shares between organizations are ignored. State of
+/// an organization admin which broadcasts the state is readable by the
+/// organization members.
+///
+/// Response is MessagePack if Accept header contains `application/msgpack`.
+#[utoipa::path(
+    get,
+    path = "/calculator_api/state/{account_id}",
+    params(AccountIdOrAlias),
+    responses(
+        (
+            status = 200,
+            description = "Calculator state of the account.",
+            body = CalculatorState,
+            content_type = ["application/json", "application/msgpack"],
+        ),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Account not found or state is not shared with the current account."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_shared_calculator_state<S: ReadDatabase + GetUsers>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    owner: AccountIdPath,
+    format: BodyFormat,
+    state: S,
+) -> Result<Negotiated<CalculatorState>, StatusCode> {
+    let owner = share_account_internal_id(&state, owner).await?;
+
+    if owner != account_id {
+        let shared = state
+            .read_database()
+            .calculator_state_shared_with(owner.as_light(), account_id.as_light())
+            .await
+            .map_err(|e| {
+                error!("{e:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+        // Organization membership might have changed after sharing.
+        let shared = shared && organization::same_organization(&state, owner, account_id).await?;
+        let shared = shared || broadcast_visible(&state, owner, account_id).await?;
+
+        // Same status as for missing account, so that existence of the
+        // account is not revealed.
+        if !shared {
+            return Err(StatusCode::NOT_FOUND);
+        }
+    }
+
+    state
+        .read_database()
+        .read_json::<CalculatorStateInternal>(owner)
+        .await
+        .map(|state| Negotiated::new(format, state.into()))
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// True if `owner` is an admin which broadcasts the calculator state to the
+/// organization of `reader`.
+async fn broadcast_visible<S: ReadDatabase>(
+    state: &S,
+    owner: AccountIdInternal,
+    reader: AccountIdInternal,
+) -> Result<bool, StatusCode> {
+    let broadcast = state
+        .read_database()
+        .calculator_broadcast(owner.as_light())
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let org_id = match broadcast {
+        Some(org_id) => org_id,
+        None => return Ok(false),
+    };
+
+    Ok(
+        organization::admin_organization(state, owner).await? == Some(org_id)
+            && organization::same_organization(state, owner, reader).await?,
+    )
+}
+
+pub const PATH_PUT_CALCULATOR_BROADCAST: &str = "/calculator_api/broadcast";
+
+/// Enable or disable broadcasting of calculator state changes to the
+/// organization of the current account. Only organization admins can
+/// broadcast. Members receive the state with the `CalculatorStateBroadcast`
+/// event. Broadcasting ends when the server restarts.
+#[utoipa::path(
+    put,
+    path = "/calculator_api/broadcast",
+    request_body = CalculatorBroadcast,
+    responses(
+        (status = 200, description = "Broadcast mode updated."),
+        (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Account is not an organization admin."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn put_calculator_broadcast<S: ReadDatabase + WriteDatabase>(
+    Extension(account_id): Extension<AccountIdInternal>,
+    Json(broadcast): Json<CalculatorBroadcast>,
+    state: S,
+) -> Result<(), StatusCode> {
+    let org_id = if broadcast.enabled {
+        match organization::admin_organization(&state, account_id).await? {
+            Some(org_id) => Some(org_id),
+            None => return Err(StatusCode::FORBIDDEN),
+        }
+    } else {
+        None
+    };
+
+    state
+        .write_database()
+        .calculator()
+        .set_calculator_broadcast(account_id, org_id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            write_error_status(&e) // Database writing failed.
+        })
+}
+
+/// Unknown account is the only error which is not an internal error.
+async fn share_account_internal_id<S: GetUsers>(
+    state: &S,
+    id: AccountIdPath,
+) -> Result<AccountIdInternal, StatusCode> {
+    state
+        .users()
+        .get_internal_id_from_path(id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub const PATH_INTERNAL_GET_EVALUATION_CACHE_STATS: &str = "/internal/evaluation_cache";
+
+/// Get evaluation result cache size and hit and miss counts.
+#[utoipa::path(
+    get,
+    path = "/internal/evaluation_cache",
+    responses(
+        (status = 200, description = "Evaluation cache stats.", body = EvaluationCacheStats),
+    ),
+    security(),
+)]
+pub async fn internal_get_evaluation_cache_stats<S: GetEvaluationCache>(
+    state: S,
+) -> Json<EvaluationCacheStats> {
+    state.evaluation_cache().stats().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::PATH_GET_CALCULATOR_SLOTS;
+    use crate::{
+        api::model::{CalculatorSlotList, CalculatorState},
+        server::test_support::{response_json, TestApp},
+    };
+
+    #[tokio::test]
+    async fn calculator_slot_can_be_saved_and_deleted() {
+        let app = TestApp::with_config(|config| config.limits.max_slots_per_account = 1).await;
+        let (_, login) = app.register_and_login().await;
+        let access = Some(&login.account.access);
+        let state = CalculatorState {
+            state: "1".to_string(),
+        };
+
+        let response = app
+            .put_json("/calculator_api/slots/a", access, &state)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .put_json("/calculator_api/slots/a", access, &state)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .put_json("/calculator_api/slots/b", access, &state)
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+        let response = app
+            .put_json("/calculator_api/slots/a.b", access, &state)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.get(PATH_GET_CALCULATOR_SLOTS, access).await;
+        let list: CalculatorSlotList = response_json(response, StatusCode::OK).await;
+        assert_eq!(list.slots.len(), 1);
+        assert_eq!(list.slots[0].slot_name, "a");
+        assert_eq!(list.slots[0].state, "1");
+
+        let response = app.delete("/calculator_api/slots/a", access).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.delete("/calculator_api/slots/a", access).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        app.close().await;
+    }
+}
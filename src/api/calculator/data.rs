@@ -1,8 +1,14 @@
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
-/// Calculator's database data
-#[derive(Debug, Clone)]
+use crate::{
+    api::model::AccountIdLight,
+    calculator::{AngleMode, Diagnostic, Evaluation, NumberFormat},
+};
+
+/// Calculator's database data. Default is the empty state of a new
+/// account.
+#[derive(Debug, Clone, Default)]
 pub struct CalculatorStateInternal {
     pub state: String,
 }
@@ -24,3 +30,270 @@ impl From<CalculatorStateInternal> for CalculatorState {
         Self { state: value.state }
     }
 }
+
+/// Expression which server evaluates.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculationRequest {
+    pub expression: String,
+}
+
+/// Successful evaluation. Formatted result is also saved as the calculator
+/// state.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct CalculationResult {
+    pub result: f64,
+    /// Result formatted with account's calculator settings.
+    pub formatted_result: String,
+    pub warnings: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, IntoParams, PartialEq, Eq)]
+pub struct ScheduleId {
+    pub schedule_id: i64,
+}
+
+/// Expression which is evaluated at a future time and optionally repeated.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct NewCalculationSchedule {
+    pub expression: String,
+    /// Unix timestamp of the first evaluation.
+    pub run_at_unix_time: i64,
+    /// Repeat evaluation with this fixed interval from the previous run.
+    /// If None the schedule is removed after the evaluation.
+    pub repeat_interval_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculationSchedule {
+    pub schedule_id: i64,
+    pub expression: String,
+    pub next_run_unix_time: i64,
+    pub repeat_interval_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculationScheduleList {
+    pub schedules: Vec<CalculationSchedule>,
+}
+
+/// Name of a calculator memory slot.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, IntoParams, PartialEq, Eq)]
+pub struct CalculatorSlotName {
+    /// 1-64 ASCII letters, digits, `-` or `_` characters.
+    pub slot_name: String,
+}
+
+impl CalculatorSlotName {
+    pub const MAX_LENGTH: usize = 64;
+
+    pub fn is_valid(&self) -> bool {
+        !self.slot_name.is_empty()
+            && self.slot_name.len() <= Self::MAX_LENGTH
+            && self
+                .slot_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+}
+
+/// Named calculator state which is stored in addition to the current
+/// calculator state.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculatorSlot {
+    pub slot_name: String,
+    pub state: String,
+    pub updated_unix_time: i64,
+}
+
+/// Slots are ordered by name.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculatorSlotList {
+    pub slots: Vec<CalculatorSlot>,
+}
+
+/// Account which can read the calculator state of the current account.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculatorStateShare {
+    pub account_id: AccountIdLight,
+}
+
+/// Broadcast mode of the current account.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculatorBroadcast {
+    /// Send calculator state changes to the WebSocket connections of the
+    /// organization members.
+    pub enabled: bool,
+}
+
+/// Calculator state of an organization admin which broadcasts the state.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculatorStateBroadcast {
+    pub account_id: AccountIdLight,
+    pub state: String,
+}
+
+/// Evaluated expression stored to calculator history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewHistoryEntry {
+    pub expression: String,
+    /// None if evaluation failed.
+    pub result: Option<f64>,
+    /// Error message if evaluation failed.
+    pub error: Option<String>,
+    pub unix_time: i64,
+}
+
+impl NewHistoryEntry {
+    /// Failed evaluation is stored with all error messages.
+    pub fn from_evaluation(
+        expression: String,
+        evaluation: Result<Evaluation, Vec<Diagnostic>>,
+        unix_time: i64,
+    ) -> Self {
+        let (result, error) = match evaluation {
+            Ok(evaluation) => (Some(evaluation.value), None),
+            Err(errors) => {
+                let message = errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                (None, Some(message))
+            }
+        };
+
+        Self {
+            expression,
+            result,
+            error,
+            unix_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, IntoParams, PartialEq, Eq)]
+pub struct HistoryId {
+    pub history_id: i64,
+}
+
+/// History entry's database data
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntryInternal {
+    pub history_id: i64,
+    pub expression: String,
+    pub result: Option<f64>,
+    pub error: Option<String>,
+    pub unix_time: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq)]
+pub struct HistoryEntry {
+    pub history_id: i64,
+    pub expression: String,
+    /// None if evaluation failed.
+    pub result: Option<f64>,
+    /// Result formatted with account's calculator settings.
+    pub formatted_result: Option<String>,
+    /// Error message if evaluation failed.
+    pub error: Option<String>,
+    pub unix_time: i64,
+}
+
+impl HistoryEntry {
+    pub fn new(entry: HistoryEntryInternal, format: &NumberFormat) -> Self {
+        Self {
+            history_id: entry.history_id,
+            expression: entry.expression,
+            result: entry.result,
+            formatted_result: entry.result.map(|result| format.format(result)),
+            error: entry.error,
+            unix_time: entry.unix_time,
+        }
+    }
+}
+
+/// Account's calculator settings. Settings affect evaluation and result
+/// formatting.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CalculatorSettings {
+    pub angle_mode: AngleMode,
+    /// Digits after the decimal separator in formatted results. If None, the
+    /// shortest exact representation is used.
+    pub display_precision: Option<u8>,
+    /// Group integer digits with the locale's thousands separator.
+    pub thousands_separator: bool,
+    /// BCP 47 language tag, for example "en-US".
+    pub locale: String,
+}
+
+impl CalculatorSettings {
+    pub const MAX_DISPLAY_PRECISION: u8 = 15;
+    pub const MAX_LOCALE_LENGTH: usize = 35;
+
+    pub fn is_valid(&self) -> bool {
+        let valid_precision = self
+            .display_precision
+            .map(|precision| precision <= Self::MAX_DISPLAY_PRECISION)
+            .unwrap_or(true);
+        let valid_locale = !self.locale.is_empty()
+            && self.locale.len() <= Self::MAX_LOCALE_LENGTH
+            && self
+                .locale
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid_precision && valid_locale
+    }
+
+    pub fn number_format(&self) -> NumberFormat {
+        NumberFormat::new(
+            &self.locale,
+            self.display_precision,
+            self.thousands_separator,
+        )
+    }
+}
+
+impl Default for CalculatorSettings {
+    fn default() -> Self {
+        Self {
+            angle_mode: AngleMode::default(),
+            display_precision: None,
+            thousands_separator: false,
+            locale: "en".to_string(),
+        }
+    }
+}
+
+/// Expression parsing or evaluation failed.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ExpressionError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    use super::{CalculatorState, CalculatorStateInternal};
+
+    proptest! {
+        /// Calculator state string is saved to the database and returned to
+        /// clients as is.
+        #[test]
+        fn calculator_state_json_is_stable(state in any::<String>()) {
+            let calculator_state = CalculatorState { state: state.clone() };
+            let value = serde_json::to_value(&calculator_state).unwrap();
+            prop_assert_eq!(&value, &json!({ "state": state }));
+            prop_assert_eq!(
+                serde_json::from_value::<CalculatorState>(value).unwrap(),
+                calculator_state.clone()
+            );
+
+            let from_internal = CalculatorState::from(CalculatorStateInternal {
+                state: calculator_state.state.clone(),
+            });
+            prop_assert_eq!(from_internal.into_update(), calculator_state);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Internal route for moving the mock clock forward in tests
+
+use axum::Json;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::GetConfig;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ClockAdvance {
+    pub seconds: i64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ClockTime {
+    /// Current server time.
+    pub unix_time: i64,
+    /// Total time which the clock has been moved forward.
+    pub offset_seconds: i64,
+}
+
+pub const PATH_INTERNAL_POST_CLOCK_ADVANCE: &str = "/internal/clock/advance";
+
+/// Move server time forward. Available only if the mock clock is enabled.
+#[utoipa::path(
+    post,
+    path = "/internal/clock/advance",
+    request_body = ClockAdvance,
+    responses(
+        (status = 200, description = "Clock moved forward.", body = ClockTime),
+        (status = 400, description = "Seconds is negative."),
+        (status = 404, description = "Mock clock is not enabled."),
+    ),
+    security(),
+)]
+pub async fn internal_post_clock_advance<S: GetConfig>(
+    Json(advance): Json<ClockAdvance>,
+    state: S,
+) -> Result<Json<ClockTime>, StatusCode> {
+    let clock = state.config().mock_clock().ok_or(StatusCode::NOT_FOUND)?;
+    if advance.seconds < 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let unix_time = clock.advance(advance.seconds);
+    Ok(ClockTime {
+        unix_time,
+        offset_seconds: clock.offset_seconds(),
+    }
+    .into())
+}
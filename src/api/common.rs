@@ -1,16 +1,19 @@
 //! Common routes to all microservices
 
-// TODO: add app version route
-
-use std::net::SocketAddr;
+use std::{
+    fmt::Debug,
+    future::Future,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
 use axum::{
     extract::{
-        ws::{Message, WebSocket},
+        ws::{CloseFrame, Message, WebSocket},
         ConnectInfo, WebSocketUpgrade,
     },
     response::IntoResponse,
-    TypedHeader,
+    Json, TypedHeader,
 };
 
 use futures::StreamExt;
@@ -20,29 +23,168 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::{
-    server::app::{connection::WebSocketManager, AppState},
+    config::Config,
+    server::{
+        app::{connection::WebSocketManager, AppState},
+        database::utils::current_unix_time,
+        security::SecurityAuditEvent,
+    },
     utils::IntoReportExt,
 };
 
-use super::model::{AccountIdInternal, ApiKey, AuthPair, RefreshToken};
+use super::{
+    account::record_login_history,
+    calculator::data::CalculatorStateBroadcast,
+    model::{AccountEventType, AccountIdInternal, ApiKey, AuthPair, LoginMethod, RefreshToken},
+};
 
-use tracing::error;
+use tokio::time::Interval;
+use tracing::{error, info};
 
-use super::{utils::ApiKeyHeader, GetApiKeys, ReadDatabase, WriteDatabase};
+use super::{
+    utils::ApiKeyHeader, GetApiKeys, GetConfig, GetEventManager, GetGeoIp, GetInternalApi,
+    GetServerStatus, GetWebhooks, ReadDatabase, WriteDatabase,
+};
 
 use error_stack::{IntoReport, Result, ResultExt};
 
+pub const PATH_GET_VERSION: &str = "/common_api/version";
+
+/// Get backend version.
+///
+/// Response can be cached for a short time.
+#[utoipa::path(
+    get,
+    path = "/common_api/version",
+    responses(
+        (status = 200, description = "Backend version.", body = BackendVersion),
+    ),
+)]
+pub async fn get_version() -> Json<BackendVersion> {
+    Json(BackendVersion {
+        backend_version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
+
+pub const PATH_GET_HEALTH: &str = "/health";
+
+/// Get server readiness.
+///
+/// Server is ready when the database cache is loaded, API servers are
+/// listening and background tasks are running.
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Server is ready.", body = HealthStatus),
+        (status = 503, description = "Server is starting.", body = HealthStatus),
+    ),
+)]
+pub async fn get_health<S: GetServerStatus>(state: S) -> (StatusCode, Json<HealthStatus>) {
+    let ready = state.server_status().is_ready();
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, HealthStatus { ready }.into())
+}
+
+/// Readiness checks which do not finish in this time fail.
+const READINESS_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub const PATH_GET_HEALTH_LIVE: &str = "/health/live";
+
+/// Get server liveness.
+///
+/// Server responds if the process is running. Use the readiness route to
+/// check if the server can handle requests.
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    responses(
+        (status = 200, description = "Server is running."),
+    ),
+)]
+pub async fn get_health_live() {}
+
+pub const PATH_GET_HEALTH_READY: &str = "/health/ready";
+
+/// Get server readiness with check results.
+///
+/// Checks are startup completion, database read pool, write command
+/// runners and, if the account component is on another server, the account
+/// server internal API.
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "All checks succeeded.", body = ReadinessStatus),
+        (status = 503, description = "Some check failed.", body = ReadinessStatus),
+    ),
+)]
+pub async fn get_health_ready<
+    S: GetServerStatus + ReadDatabase + WriteDatabase + GetInternalApi + GetConfig,
+>(
+    state: S,
+) -> (StatusCode, Json<ReadinessStatus>) {
+    let mut checks = vec![
+        ReadinessCheck::new("startup", state.server_status().is_ready()),
+        ReadinessCheck::new(
+            "database",
+            readiness_check(state.read_database().check_connection()).await,
+        ),
+        ReadinessCheck::new("write_commands", state.write_database().is_running()),
+    ];
+    if !state.config().components().account {
+        checks.push(ReadinessCheck::new(
+            "account_internal_api",
+            readiness_check(state.internal_api().check_account_server()).await,
+        ));
+    }
+
+    let ready = checks.iter().all(|check| check.ok);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, ReadinessStatus { ready, checks }.into())
+}
+
+async fn readiness_check<E: Debug>(
+    check: impl Future<Output = std::result::Result<(), E>>,
+) -> bool {
+    match tokio::time::timeout(READINESS_CHECK_TIMEOUT, check).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            error!("Readiness check failed: {e:?}");
+            false
+        }
+        Err(_) => {
+            error!("Readiness check timeout");
+            false
+        }
+    }
+}
+
 pub const PATH_CONNECT: &str = "/common_api/connect";
 
 /// Connect to server using WebSocket after getting refresh and access tokens.
 /// Connection is required as API access is allowed for connected clients.
 ///
-/// Send the current refersh token as Binary. The server will send the next
-/// refresh token (Binary) and after that the new access token (Text). After
-/// that API can be used.
+/// Send [ClientVersion] as JSON Text and after that the current refersh
+/// token as Binary. The server will send the next refresh token (Binary) and
+/// after that the new access token (Text). After that API can be used.
+///
+/// If the server does not support the client version, the server closes
+/// the connection with close code 4000 and [ClientVersionRequirement] as
+/// JSON close reason. Clients which send the refresh token first are
+/// protocol version 0.
 ///
-/// The access token is valid until this WebSocket is closed. Server might send
-/// events as Text which is JSON.
+/// The access token is valid until this WebSocket is closed or the access
+/// token expires. Expired access token can be replaced using
+/// `/account_api/refresh`. Server might send events as Text which is JSON.
 ///
 #[utoipa::path(
     get,
@@ -80,12 +222,32 @@ async fn handle_socket(
     state: AppState,
     mut ws_manager: WebSocketManager,
 ) {
+    let close_connection = match state
+        .read_database()
+        .connection_close_notifier(id.as_light())
+        .await
+    {
+        Ok(notifier) => notifier,
+        Err(e) => {
+            error!("WebSocket: {e:?}");
+            return;
+        }
+    };
+
     tokio::select! {
         _ = ws_manager.server_quit_watcher.recv() => (),
+        // Account is deleted or sessions are revoked, so there is no session
+        // to end.
+        _ = close_connection.notified() => (),
         r = handle_socket_result(socket, address, id, &state) => {
             match r {
-                Ok(()) => {
-                    match state.write_database().end_connection_session(id).await {
+                Ok(end) => {
+                    let remove_access_token = end == ConnectionEnd::Unresponsive;
+                    match state
+                        .write_database()
+                        .end_connection_session(id, remove_access_token)
+                        .await
+                    {
                         Ok(()) => (),
                         Err(e) => {
                             error!("WebSocket: {e:?}");
@@ -96,7 +258,10 @@ async fn handle_socket(
                     error!("WebSocket: {e:?}");
 
                     match state.write_database().logout(id).await {
-                        Ok(()) => (),
+                        Ok(()) => {
+                            let address = Some(address.ip());
+                            record_login_history(&state, id, LoginMethod::Logout, address).await
+                        }
                         Err(e) => {
                             error!("WebSocket: {e:?}");
                         }
@@ -115,18 +280,36 @@ pub enum WebSocketError {
     Receive,
     #[error("Received something else than refresh token")]
     ReceiveMissingRefreshToken,
+    #[error("Client version parsing failed")]
+    ClientVersionParsing,
     #[error("Send error")]
     Send,
 
     // Database errors
     #[error("Database: No refresh token")]
     DatabaseNoRefreshToken,
-    #[error("Invalid refresh token in database")]
-    InvalidRefreshTokenInDatabase,
     #[error("Database: account logout failed")]
     DatabaseLogoutFailed,
+    #[error("Database: used refresh token check failed")]
+    DatabaseUsedRefreshTokenCheck,
+    #[error("Database: revoking sessions failed")]
+    DatabaseRevokeSessionsFailed,
     #[error("Database: saving new tokens failed")]
     DatabaseSaveTokens,
+
+    // Event errors
+    #[error("Creating event channel failed")]
+    EventChannel,
+    #[error("Event serialization failed")]
+    EventSerialization,
+}
+
+/// How WebSocket connection ended without errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectionEnd {
+    Closed,
+    /// Client did not respond to pings.
+    Unresponsive,
 }
 
 async fn handle_socket_result(
@@ -134,34 +317,99 @@ async fn handle_socket_result(
     address: SocketAddr,
     id: AccountIdInternal,
     state: &AppState,
-) -> Result<(), WebSocketError> {
+) -> Result<ConnectionEnd, WebSocketError> {
     // TODO: add close server notification select? Or probably not needed as
     // server should shutdown after main future?
 
-    let current_refresh_token = state
+    let current_refresh_token_hash = state
         .read_database()
-        .account_refresh_token(id)
+        .account_refresh_token_hash(id)
         .await
         .change_context(WebSocketError::DatabaseNoRefreshToken)?
-        .ok_or(WebSocketError::DatabaseNoRefreshToken)?
-        .bytes()
-        .into_error(WebSocketError::InvalidRefreshTokenInDatabase)?;
+        .ok_or(WebSocketError::DatabaseNoRefreshToken)?;
+
+    let (client_version, message) = match receive_message(&mut socket).await? {
+        Message::Text(version) => {
+            let version: ClientVersion =
+                serde_json::from_str(&version).into_error(WebSocketError::ClientVersionParsing)?;
+            (Some(version), receive_message(&mut socket).await?)
+        }
+        // Protocol version 0 clients send the refresh token first.
+        message => (None, message),
+    };
+
+    let requirement = ClientVersionRequirement::new(state.config());
+    if !requirement.accepts(client_version.as_ref()) {
+        let reason =
+            serde_json::to_string(&requirement).into_error(WebSocketError::EventSerialization)?;
+        socket
+            .send(Message::Close(Some(CloseFrame {
+                code: CLOSE_CODE_UNSUPPORTED_CLIENT_VERSION,
+                reason: reason.into(),
+            })))
+            .await
+            .into_error(WebSocketError::Send)?;
+        return Ok(ConnectionEnd::Closed);
+    }
 
     // Refresh token check.
-    match socket
-        .recv()
-        .await
-        .ok_or(WebSocketError::Receive)?
-        .into_error(WebSocketError::Receive)?
-    {
+    match message {
         Message::Binary(refresh_token) => {
-            if refresh_token != current_refresh_token {
-                state
-                    .write_database()
-                    .logout(id)
+            let refresh_token = RefreshToken::from_bytes(&refresh_token);
+            if refresh_token.hash() != current_refresh_token_hash {
+                let reused = state
+                    .read_database()
+                    .used_refresh_token_generation(id, &refresh_token)
                     .await
-                    .change_context(WebSocketError::DatabaseLogoutFailed)?;
-                return Ok(());
+                    .change_context(WebSocketError::DatabaseUsedRefreshTokenCheck)?;
+                if let Some(generation) = reused {
+                    // Replaced token is used again, so either the client or
+                    // an attacker has a stolen token.
+                    SecurityAuditEvent::RefreshTokenReused {
+                        account: id,
+                        address: address.ip(),
+                        generation,
+                    }
+                    .log();
+                    // Current session of the account gets the event before
+                    // its WebSocket connection is closed.
+                    let info = SessionsRevokedInfo {
+                        address: address.ip().to_string(),
+                        country: state
+                            .geoip()
+                            .lookup(address.ip())
+                            .map(|location| location.country),
+                        unix_time: current_unix_time(),
+                    };
+                    state
+                        .event_manager()
+                        .send_connected_event(id.as_light(), EventToClient::SessionsRevoked(info))
+                        .await;
+                    state
+                        .write_database()
+                        .revoke_sessions(id)
+                        .await
+                        .change_context(WebSocketError::DatabaseRevokeSessionsFailed)?;
+                    record_login_history(state, id, LoginMethod::Logout, Some(address.ip())).await;
+                    state
+                        .webhooks()
+                        .send(AccountEventType::SessionsRevoked, id.as_light());
+                    socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: CLOSE_CODE_REFRESH_TOKEN_REUSED,
+                            reason: "".into(),
+                        })))
+                        .await
+                        .into_error(WebSocketError::Send)?;
+                } else {
+                    state
+                        .write_database()
+                        .logout(id)
+                        .await
+                        .change_context(WebSocketError::DatabaseLogoutFailed)?;
+                    record_login_history(state, id, LoginMethod::Logout, Some(address.ip())).await;
+                }
+                return Ok(ConnectionEnd::Closed);
             }
         }
         _ => return Err(WebSocketError::ReceiveMissingRefreshToken).into_report(),
@@ -186,31 +434,283 @@ async fn handle_socket_result(
                 refresh: new_refresh_token,
             },
             Some(address),
+            client_version,
         )
         .await
         .change_context(WebSocketError::DatabaseSaveTokens)?;
 
+    record_login_history(state, id, LoginMethod::TokenRefresh, Some(address.ip())).await;
+
     socket
         .send(Message::Text(new_access_token.into_string()))
         .await
         .into_error(WebSocketError::Send)?;
 
+    let mut events = state
+        .event_manager()
+        .connect(id.as_light())
+        .await
+        .change_context(WebSocketError::EventChannel)?;
+
+    let max_missed_pongs = state.config().websocket_max_missed_pongs();
+    let mut ping_timer = state
+        .config()
+        .websocket_ping_interval()
+        .map(|interval| tokio::time::interval_at(tokio::time::Instant::now() + interval, interval));
+    let mut missed_pongs = 0;
+    let mut last_activity = Instant::now();
+
     loop {
         tokio::select! {
             result = socket.recv() => {
                 match result {
                     Some(Err(_)) | None => break,
-                    Some(Ok(_)) => continue,
+                    // Every message shows that the client is connected.
+                    Some(Ok(_)) => {
+                        missed_pongs = 0;
+                        last_activity = Instant::now();
+                    }
+                }
+            }
+            _ = next_ping(&mut ping_timer) => {
+                if missed_pongs >= max_missed_pongs {
+                    info!(
+                        "WebSocket: closing unresponsive connection, idle {} seconds",
+                        last_activity.elapsed().as_secs()
+                    );
+                    // Client is probably not there, so sending might fail.
+                    let _ = socket.send(Message::Close(None)).await;
+                    return Ok(ConnectionEnd::Unresponsive);
                 }
+                socket
+                    .send(Message::Ping(Vec::new()))
+                    .await
+                    .into_error(WebSocketError::Send)?;
+                missed_pongs += 1;
+            }
+            Some(event) = events.recv() => {
+                let event = serde_json::to_string(&event)
+                    .into_error(WebSocketError::EventSerialization)?;
+                socket
+                    .send(Message::Text(event))
+                    .await
+                    .into_error(WebSocketError::Send)?;
             }
-            // TODO: event sending at some point?
         }
     }
 
-    Ok(())
+    Ok(ConnectionEnd::Closed)
+}
+
+/// Waits forever if pings are disabled.
+async fn next_ping(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn receive_message(socket: &mut WebSocket) -> Result<Message, WebSocketError> {
+    socket
+        .recv()
+        .await
+        .ok_or(WebSocketError::Receive)?
+        .into_error(WebSocketError::Receive)
+}
+
+/// Latest WebSocket protocol version which the server supports.
+pub const WEBSOCKET_PROTOCOL_VERSION: u16 = 1;
+
+/// WebSocket close code for client version which the server does not
+/// support.
+pub const CLOSE_CODE_UNSUPPORTED_CLIENT_VERSION: u16 = 4000;
+
+/// WebSocket close code for replaced refresh token. All sessions of the
+/// account are revoked, so the user must login again.
+pub const CLOSE_CODE_REFRESH_TOKEN_REUSED: u16 = 4001;
+
+/// Client sends this as JSON Text when WebSocket connection starts.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ClientVersion {
+    pub protocol_version: u16,
+    #[schema(value_type = String, example = "1.2.3")]
+    pub app_version: AppVersion,
+}
+
+/// Client versions which the server accepts.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ClientVersionRequirement {
+    pub min_protocol_version: u16,
+    pub max_protocol_version: u16,
+    #[schema(value_type = Option<String>, example = "1.2.3")]
+    pub min_app_version: Option<AppVersion>,
+}
+
+impl ClientVersionRequirement {
+    pub fn new(config: &Config) -> Self {
+        let websocket = config.websocket();
+        Self {
+            min_protocol_version: websocket.map(|c| c.min_protocol_version).unwrap_or(0),
+            max_protocol_version: WEBSOCKET_PROTOCOL_VERSION,
+            min_app_version: websocket.and_then(|c| c.min_app_version),
+        }
+    }
+
+    /// Client without version is protocol version 0 and it is accepted only
+    /// if minimum app version is not configured.
+    pub fn accepts(&self, version: Option<&ClientVersion>) -> bool {
+        let protocol_version = version.map(|v| v.protocol_version).unwrap_or(0);
+        if protocol_version < self.min_protocol_version
+            || protocol_version > self.max_protocol_version
+        {
+            return false;
+        }
+
+        match (self.min_app_version, version) {
+            (None, _) => true,
+            (Some(min), Some(version)) => version.app_version >= min,
+            (Some(_), None) => false,
+        }
+    }
 }
 
+/// App version with format `major.minor.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AppVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl TryFrom<String> for AppVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        let mut numbers = value.split('.').map(|n| n.parse::<u32>());
+        match (
+            numbers.next(),
+            numbers.next(),
+            numbers.next(),
+            numbers.next(),
+        ) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(format!("Invalid app version '{value}'")),
+        }
+    }
+}
+
+impl From<AppVersion> for String {
+    fn from(value: AppVersion) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for AppVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Event which server sends to the WebSocket connection as JSON text. Client
+/// should fetch the changed data using the API.
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub enum EventToClient {
     AccountStateChanged,
+    CalculatorStateChanged,
+    ScheduledCalculationCompleted,
+    /// Account logged in from another device. Sent to the WebSocket
+    /// connection of the previous session.
+    NewSessionCreated(NewSessionInfo),
+    /// Calculator state of an organization admin changed. Sent to the
+    /// organization members when the admin has enabled broadcasting.
+    CalculatorStateBroadcast(CalculatorStateBroadcast),
+    /// Replaced refresh token of the account was used, so all sessions are
+    /// revoked and the user must login again. Sent to the WebSocket
+    /// connection of the current session before it is closed.
+    SessionsRevoked(SessionsRevokedInfo),
+}
+
+/// Device info of a new session.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct NewSessionInfo {
+    /// IP address of the login request.
+    pub address: String,
+    /// Country code of the address if GeoIP database is configured.
+    pub country: Option<String>,
+    pub user_agent: Option<String>,
+    pub unix_time: i64,
+}
+
+/// Connection attempt which used the replaced refresh token.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct SessionsRevokedInfo {
+    /// IP address of the connection attempt.
+    pub address: String,
+    /// Country code of the address if GeoIP database is configured.
+    pub country: Option<String>,
+    pub unix_time: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct BackendVersion {
+    pub backend_version: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct HealthStatus {
+    pub ready: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ReadinessStatus {
+    /// True if all checks succeeded.
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ReadinessCheck {
+    /// Check name like `database`.
+    pub name: String,
+    pub ok: bool,
+}
+
+impl ReadinessCheck {
+    fn new(name: &str, ok: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            ok,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::{ReadinessStatus, PATH_GET_HEALTH_LIVE, PATH_GET_HEALTH_READY};
+    use crate::server::test_support::{response_json, TestApp};
+
+    #[tokio::test]
+    async fn health_routes_report_ready_server() {
+        let app = TestApp::new().await;
+
+        let response = app.get(PATH_GET_HEALTH_LIVE, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.get(PATH_GET_HEALTH_READY, None).await;
+        let status: ReadinessStatus = response_json(response, StatusCode::OK).await;
+        assert!(status.ready);
+        let names: Vec<_> = status.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["startup", "database", "write_commands"]);
+
+        app.close().await;
+    }
 }
@@ -0,0 +1,68 @@
+//! Request context for private routes
+
+use hyper::Method;
+use uuid::Uuid;
+
+use super::{
+    access::{self, AllowedRoutes},
+    model::{AccountIdInternal, AccountState, ServiceTokenScope},
+};
+
+/// Random ID for identifying one request in logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestId(Uuid);
+
+impl RequestId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Authenticated account info which the authentication middleware adds to
+/// request extensions. Handlers can use this instead of reading the same
+/// data again.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestContext {
+    pub account_id: AccountIdInternal,
+    pub account_state: AccountState,
+    pub permissions: AllowedRoutes,
+    pub request_id: RequestId,
+    /// Set if the request uses a service token instead of an access token.
+    pub service_token: Option<ServiceTokenScope>,
+}
+
+impl RequestContext {
+    pub fn new(
+        account_id: AccountIdInternal,
+        account_state: AccountState,
+        request_id: RequestId,
+    ) -> Self {
+        Self {
+            account_id,
+            account_state,
+            permissions: access::allowed_routes(account_state),
+            request_id,
+            service_token: None,
+        }
+    }
+
+    pub fn with_service_token(mut self, scope: Option<ServiceTokenScope>) -> Self {
+        self.service_token = scope;
+        self
+    }
+
+    /// Account state and service token scope must allow the route.
+    pub fn allows(&self, method: &Method, route: &str) -> bool {
+        let service_token_allows = self
+            .service_token
+            .map(|scope| access::service_token_allows(scope, method, route))
+            .unwrap_or(true);
+        self.permissions.contains(route) && service_token_allows
+    }
+}
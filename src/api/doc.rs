@@ -0,0 +1,218 @@
+//! OpenAPI documents for code generation
+//!
+//! The generated `api_client` crate is used by the bots and by the internal
+//! API client in `server/internal.rs`. The client's README is included at
+//! build time, so [check_api_client] checks the client which the binary was
+//! built with.
+
+use error_stack::{Report, Result};
+use utoipa::{
+    openapi::{OpenApi as OpenApiDoc, PathItemType},
+    OpenApi,
+};
+
+use crate::config::{
+    args::{ApiDocType, OpenApiCommand},
+    Config,
+};
+
+use super::{ApiDoc, InternalApiDoc};
+
+pub const API_DOC_FILE_NAME: &str = "calculator_api.json";
+pub const INTERNAL_API_DOC_FILE_NAME: &str = "calculator_internal_api.json";
+
+/// Endpoint table of the generated client.
+const API_CLIENT_README: &str = include_str!("../../api_client/README.md");
+
+/// Path prefixes of routes which exist only if the account component is
+/// enabled. WebSocket sessions are managed by the account server.
+const ACCOUNT_ROUTE_PREFIXES: &[&str] = &[
+    "/account_api/",
+    "/common_api/connect",
+    "/internal/admin/",
+    "/internal/check_api_key",
+    "/internal/get_account_state/",
+    "/internal/migrate_json",
+    "/internal/reports/",
+    "/internal/webhook_deliveries",
+];
+
+/// Path prefixes of routes which exist only if the account component is
+/// disabled.
+const REMOTE_ACCOUNT_ROUTE_PREFIXES: &[&str] = &["/internal/api_key_revoked"];
+
+/// Path prefixes of routes which exist only if the calculator component is
+/// enabled.
+const CALCULATOR_ROUTE_PREFIXES: &[&str] = &["/calculator_api/", "/internal/evaluation_cache"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ApiDocError {
+    #[error("Serialization error")]
+    Serialize,
+    #[error("Generated api_client does not match the internal API document")]
+    ApiClientMismatch,
+}
+
+/// Public and internal API in one document. The `api_client` crate is
+/// generated from this.
+pub fn api_client_doc() -> OpenApiDoc {
+    let mut doc = ApiDoc::openapi();
+    doc.merge(InternalApiDoc::openapi());
+    doc
+}
+
+pub fn api_doc(doc_type: ApiDocType) -> OpenApiDoc {
+    match doc_type {
+        ApiDocType::Public => ApiDoc::openapi(),
+        ApiDocType::Internal => InternalApiDoc::openapi(),
+        ApiDocType::Client => api_client_doc(),
+    }
+}
+
+pub fn api_doc_json(doc_type: ApiDocType) -> Result<String, ApiDocError> {
+    api_doc(doc_type)
+        .to_pretty_json()
+        .map_err(|e| Report::new(ApiDocError::Serialize).attach_printable(e.to_string()))
+}
+
+/// Remove routes which the server does not have with the current config, so
+/// that the served documents contain only available routes.
+pub fn remove_unavailable_routes(doc: &mut OpenApiDoc, config: &Config) {
+    doc.paths
+        .paths
+        .retain(|path, _| is_route_available(path, config));
+}
+
+fn is_route_available(path: &str, config: &Config) -> bool {
+    let components = config.components();
+    let has_prefix = |prefixes: &[&str]| prefixes.iter().any(|p| path.starts_with(p));
+    if has_prefix(ACCOUNT_ROUTE_PREFIXES) {
+        components.account
+    } else if has_prefix(REMOTE_ACCOUNT_ROUTE_PREFIXES) {
+        !components.account
+    } else if has_prefix(CALCULATOR_ROUTE_PREFIXES) {
+        components.calculator
+    } else if path.starts_with("/internal/standby/") {
+        config.hot_standby_receiver()
+    } else if path.starts_with("/internal/clock/") {
+        config.mock_clock().is_some()
+    } else {
+        true
+    }
+}
+
+/// Check that the internal API endpoints of the generated client and the
+/// internal API document are the same.
+pub fn check_api_client() -> Result<(), ApiDocError> {
+    let doc = InternalApiDoc::openapi();
+    let client_endpoints = api_client_endpoints();
+
+    let mismatches: Vec<String> = client_endpoints
+        .iter()
+        .filter(|endpoint| endpoint.path.starts_with("/internal/"))
+        .filter(|endpoint| !doc_contains(&doc, &endpoint.method, &endpoint.path))
+        .map(|endpoint| format!("{} {}", endpoint.method, endpoint.path))
+        .collect();
+
+    if !mismatches.is_empty() {
+        return Err(
+            Report::new(ApiDocError::ApiClientMismatch).attach_printable(format!(
+                "Client endpoints not in internal API document: {}. Regenerate api_client.",
+                mismatches.join(", ")
+            )),
+        );
+    }
+
+    let mut missing = vec![];
+    for (path, item) in &doc.paths.paths {
+        for operation_type in item.operations.keys() {
+            let method = method_name(operation_type);
+            if !client_endpoints
+                .iter()
+                .any(|e| e.method == method && &e.path == path)
+            {
+                missing.push(format!("{method} {path}"));
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(
+            Report::new(ApiDocError::ApiClientMismatch).attach_printable(format!(
+                "Internal API document endpoints not in client: {}. Regenerate api_client.",
+                missing.join(", ")
+            )),
+        );
+    }
+
+    Ok(())
+}
+
+struct ClientEndpoint {
+    /// Upper case HTTP method.
+    method: String,
+    path: String,
+}
+
+/// Parse rows like
+/// `*AccountinternalApi* | [**check_api_key**](docs/...) | **GET** /internal/check_api_key | `
+fn api_client_endpoints() -> Vec<ClientEndpoint> {
+    API_CLIENT_README
+        .lines()
+        .filter(|line| line.starts_with('*'))
+        .filter_map(|line| {
+            let column = line.split('|').nth(2)?.trim();
+            let (method, path) = column.split_once(' ')?;
+            Some(ClientEndpoint {
+                method: method.trim_matches('*').to_string(),
+                path: path.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn doc_contains(doc: &OpenApiDoc, method: &str, path: &str) -> bool {
+    doc.paths
+        .paths
+        .get(path)
+        .map(|item| item.operations.keys().any(|t| method_name(t) == method))
+        .unwrap_or(false)
+}
+
+fn method_name(operation_type: &PathItemType) -> &'static str {
+    match operation_type {
+        PathItemType::Get => "GET",
+        PathItemType::Post => "POST",
+        PathItemType::Put => "PUT",
+        PathItemType::Delete => "DELETE",
+        PathItemType::Options => "OPTIONS",
+        PathItemType::Head => "HEAD",
+        PathItemType::Patch => "PATCH",
+        PathItemType::Trace => "TRACE",
+        PathItemType::Connect => "CONNECT",
+    }
+}
+
+/// Print API document or check result to stdout.
+pub fn run_openapi_command(command: OpenApiCommand) -> Result<(), ApiDocError> {
+    match command {
+        OpenApiCommand::Print(doc_type) => println!("{}", api_doc_json(doc_type)?),
+        OpenApiCommand::CheckApiClient => {
+            check_api_client()?;
+            println!("api_client matches the internal API document");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_api_client;
+
+    #[test]
+    fn api_client_matches_internal_api_doc() {
+        if let Err(e) = check_api_client() {
+            panic!("{e:?}");
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Internal routes for server metrics
+
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::server::database::{cache::CacheReadStats, commands::WriteQueueStats};
+
+use super::{ReadDatabase, WriteDatabase};
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct ServerMetrics {
+    pub write_queue: WriteQueueStats,
+    pub cache_reads: CacheReadStats,
+}
+
+pub const PATH_INTERNAL_GET_METRICS: &str = "/internal/metrics";
+
+/// Get write command queue and cache read metrics.
+#[utoipa::path(
+    get,
+    path = "/internal/metrics",
+    responses(
+        (status = 200, description = "Server metrics.", body = ServerMetrics),
+    ),
+    security(),
+)]
+pub async fn internal_get_metrics<S: WriteDatabase + ReadDatabase>(
+    state: S,
+) -> Json<ServerMetrics> {
+    ServerMetrics {
+        write_queue: state.write_database().write_queue_stats(),
+        cache_reads: state.read_database().cache_read_stats(),
+    }
+    .into()
+}
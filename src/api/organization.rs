@@ -0,0 +1,370 @@
+//! Organizations for team and classroom deployments
+//!
+//! Server admins manage organizations using the internal API. Organization
+//! admins can view the member list of their own organization. The
+//! organization ID is always read from the membership of the current
+//! account, so accounts can not access data of other organizations.
+
+use axum::{extract::Path, Extension, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::IntoParams;
+
+use super::{
+    model::{
+        AccountIdInternal, AccountIdLight, NewOrganization, NewOrganizationMember, OrganizationId,
+        OrganizationMemberList, OrganizationMembership, OrganizationSettings,
+    },
+    GetUsers, ReadDatabase, WriteDatabase,
+};
+
+pub const MAX_ORGANIZATION_NAME_LENGTH: usize = 100;
+pub const MAX_HISTORY_RETENTION_DAYS: i64 = 36500;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, IntoParams, PartialEq, Eq)]
+pub struct OrganizationMemberPath {
+    pub org_id: i64,
+    pub account_id: uuid::Uuid,
+}
+
+pub const PATH_GET_ORGANIZATION: &str = "/account_api/organization";
+
+/// Get organization of the current account.
+#[utoipa::path(
+    get,
+    path = "/account_api/organization",
+    responses(
+        (status = 200, description = "Request successfull.", body = OrganizationMembership),
+        (status = 401, description = "Unauthorized."),
+        (status = 404, description = "Account does not belong to an organization."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_organization<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<OrganizationMembership>, StatusCode> {
+    membership(&state, id)
+        .await?
+        .map(|membership| membership.into())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub const PATH_GET_ORGANIZATION_MEMBERS: &str = "/account_api/organization/members";
+
+/// Get members of the organization of the current account and their usage.
+/// Only organization admins can access this.
+#[utoipa::path(
+    get,
+    path = "/account_api/organization/members",
+    responses(
+        (status = 200, description = "Request successfull.", body = OrganizationMemberList),
+        (status = 401, description = "Unauthorized."),
+        (status = 403, description = "Account is not an organization admin."),
+        (status = 404, description = "Account does not belong to an organization."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(("api_key" = [])),
+)]
+pub async fn get_organization_members<S: ReadDatabase>(
+    Extension(id): Extension<AccountIdInternal>,
+    state: S,
+) -> Result<Json<OrganizationMemberList>, StatusCode> {
+    let membership = membership(&state, id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if !membership.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let org_id = OrganizationId {
+        org_id: membership.organization.org_id,
+    };
+    members(&state, org_id).await.map(|list| list.into())
+}
+
+pub const PATH_INTERNAL_POST_ORGANIZATION: &str = "/internal/admin/organizations";
+
+#[utoipa::path(
+    post,
+    path = "/internal/admin/organizations",
+    request_body = NewOrganization,
+    responses(
+        (status = 200, description = "Organization created.", body = OrganizationId),
+        (status = 400, description = "Name is empty or too long."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_post_organization<S: WriteDatabase>(
+    Json(org): Json<NewOrganization>,
+    state: S,
+) -> Result<Json<OrganizationId>, StatusCode> {
+    let name_length = org.name.chars().count();
+    if name_length == 0 || name_length > MAX_ORGANIZATION_NAME_LENGTH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    state
+        .write_database()
+        .account()
+        .create_organization(org)
+        .await
+        .map(|id| id.into())
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub const PATH_INTERNAL_ORGANIZATION_MEMBERS: &str =
+    "/internal/admin/organizations/:org_id/members";
+
+#[utoipa::path(
+    get,
+    path = "/internal/admin/organizations/{org_id}/members",
+    params(OrganizationId),
+    responses(
+        (status = 200, description = "Organization members.", body = OrganizationMemberList),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_get_organization_members<S: ReadDatabase>(
+    Path(org_id): Path<OrganizationId>,
+    state: S,
+) -> Result<Json<OrganizationMemberList>, StatusCode> {
+    members(&state, org_id).await.map(|list| list.into())
+}
+
+/// Add account to the organization or change its admin status. Account is
+/// moved if it belongs to another organization.
+#[utoipa::path(
+    put,
+    path = "/internal/admin/organizations/{org_id}/members",
+    params(OrganizationId),
+    request_body = NewOrganizationMember,
+    responses(
+        (status = 200, description = "Account is a member of the organization."),
+        (status = 404, description = "Organization or account not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_put_organization_member<S: WriteDatabase + GetUsers>(
+    Path(org_id): Path<OrganizationId>,
+    Json(member): Json<NewOrganizationMember>,
+    state: S,
+) -> Result<(), StatusCode> {
+    let id = internal_id(&state, member.account_id).await?;
+
+    let updated = state
+        .write_database()
+        .account()
+        .set_organization_member(org_id, id, member.is_admin)
+        .await
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if updated {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub const PATH_INTERNAL_DELETE_ORGANIZATION_MEMBER: &str =
+    "/internal/admin/organizations/:org_id/members/:account_id";
+
+#[utoipa::path(
+    delete,
+    path = "/internal/admin/organizations/{org_id}/members/{account_id}",
+    params(OrganizationMemberPath),
+    responses(
+        (status = 200, description = "Account is removed from the organization."),
+        (status = 404, description = "Account is not a member of the organization."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_delete_organization_member<S: WriteDatabase + GetUsers>(
+    Path(path): Path<OrganizationMemberPath>,
+    state: S,
+) -> Result<(), StatusCode> {
+    let id = internal_id(&state, AccountIdLight::new(path.account_id)).await?;
+    let org_id = OrganizationId {
+        org_id: path.org_id,
+    };
+
+    let removed = state
+        .write_database()
+        .account()
+        .remove_organization_member(org_id, id)
+        .await
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if removed {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub const PATH_INTERNAL_ORGANIZATION_SETTINGS: &str =
+    "/internal/admin/organizations/:org_id/settings";
+
+#[utoipa::path(
+    get,
+    path = "/internal/admin/organizations/{org_id}/settings",
+    params(OrganizationId),
+    responses(
+        (status = 200, description = "Organization settings.", body = OrganizationSettings),
+        (status = 404, description = "Organization not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_get_organization_settings<S: ReadDatabase>(
+    Path(org_id): Path<OrganizationId>,
+    state: S,
+) -> Result<Json<OrganizationSettings>, StatusCode> {
+    state
+        .read_database()
+        .organization_settings(org_id)
+        .await
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(|settings| settings.into())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Replace organization settings. Unset values use the server config.
+#[utoipa::path(
+    put,
+    path = "/internal/admin/organizations/{org_id}/settings",
+    params(OrganizationId),
+    request_body = OrganizationSettings,
+    responses(
+        (status = 200, description = "Organization settings updated."),
+        (status = 400, description = "History retention is out of range."),
+        (status = 404, description = "Organization not found."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn internal_put_organization_settings<S: WriteDatabase>(
+    Path(org_id): Path<OrganizationId>,
+    Json(settings): Json<OrganizationSettings>,
+    state: S,
+) -> Result<(), StatusCode> {
+    if let Some(days) = settings.history_retention_days {
+        if !(1..=MAX_HISTORY_RETENTION_DAYS).contains(&days) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let updated = state
+        .write_database()
+        .account()
+        .set_organization_settings(org_id, settings)
+        .await
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if updated {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Settings of the organization of the account. Default settings if the
+/// account does not belong to an organization.
+pub async fn account_settings<S: ReadDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+) -> Result<OrganizationSettings, StatusCode> {
+    state
+        .read_database()
+        .account_organization_settings(id)
+        .await
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// True if both accounts belong to the same organization or neither of them
+/// belongs to an organization.
+pub async fn same_organization<S: ReadDatabase>(
+    state: &S,
+    a: AccountIdInternal,
+    b: AccountIdInternal,
+) -> Result<bool, StatusCode> {
+    let a = membership(state, a).await?;
+    let b = membership(state, b).await?;
+    Ok(a.map(|m| m.organization.org_id) == b.map(|m| m.organization.org_id))
+}
+
+/// Organization where the account is an admin.
+pub async fn admin_organization<S: ReadDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+) -> Result<Option<OrganizationId>, StatusCode> {
+    Ok(membership(state, id)
+        .await?
+        .filter(|m| m.is_admin)
+        .map(|m| OrganizationId {
+            org_id: m.organization.org_id,
+        }))
+}
+
+async fn membership<S: ReadDatabase>(
+    state: &S,
+    id: AccountIdInternal,
+) -> Result<Option<OrganizationMembership>, StatusCode> {
+    state
+        .read_database()
+        .organization_membership(id)
+        .await
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn members<S: ReadDatabase>(
+    state: &S,
+    org_id: OrganizationId,
+) -> Result<OrganizationMemberList, StatusCode> {
+    state
+        .read_database()
+        .organization_members(org_id)
+        .await
+        .map(|members| OrganizationMemberList { members })
+        .map_err(|e| {
+            error!("Organization API error: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Unknown account ID is the only possible error.
+async fn internal_id<S: GetUsers>(
+    state: &S,
+    id: AccountIdLight,
+) -> Result<AccountIdInternal, StatusCode> {
+    state
+        .users()
+        .get_internal_id(id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,28 @@
+//! Internal route for reloading the config file
+
+use std::sync::Arc;
+
+use hyper::StatusCode;
+use tracing::error;
+
+use crate::server::config_reload::ConfigReloader;
+
+pub const PATH_INTERNAL_POST_RELOAD_CONFIG: &str = "/internal/reload_config";
+
+/// Load the config file again and apply log level, rate limit and TLS
+/// certificate changes. Same as sending SIGHUP to the server.
+#[utoipa::path(
+    post,
+    path = "/internal/reload_config",
+    responses(
+        (status = 200, description = "Config reloaded."),
+        (status = 500, description = "Config loading failed. Current config is not changed."),
+    ),
+    security(),
+)]
+pub async fn internal_post_reload_config(reloader: Arc<ConfigReloader>) -> Result<(), StatusCode> {
+    reloader.reload().map_err(|e| {
+        error!("Config reload failed: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
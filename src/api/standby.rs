@@ -0,0 +1,49 @@
+//! Internal routes for hot standby replication
+
+use bytes::Bytes;
+use hyper::StatusCode;
+
+use tracing::error;
+
+use crate::server::database::standby::{DB_STANDBY_DIR_NAME, STANDBY_SNAPSHOT_FILE_NAME};
+
+use super::GetConfig;
+
+pub const PATH_INTERNAL_STANDBY_SNAPSHOT: &str = "/internal/standby/snapshot";
+
+const SNAPSHOT_RECEIVE_TMP_FILE_NAME: &str = "receive_tmp.db";
+
+/// Replace standby server's database snapshot. Only available when server
+/// is in hot standby mode `standby`.
+#[utoipa::path(
+    post,
+    path = "/internal/standby/snapshot",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Snapshot saved."),
+        (status = 500, description = "Internal server error."),
+    ),
+    security(),
+)]
+pub async fn post_standby_snapshot<S: GetConfig>(
+    snapshot: Bytes,
+    state: S,
+) -> Result<(), StatusCode> {
+    let dir = state.config().database_dir().join(DB_STANDBY_DIR_NAME);
+    let tmp_file = dir.join(SNAPSHOT_RECEIVE_TMP_FILE_NAME);
+
+    let save = async {
+        if !dir.exists() {
+            tokio::fs::create_dir_all(&dir).await?;
+        }
+        // Write to temporary file first so that the previous snapshot stays
+        // valid if writing fails.
+        tokio::fs::write(&tmp_file, &snapshot).await?;
+        tokio::fs::rename(&tmp_file, dir.join(STANDBY_SNAPSHOT_FILE_NAME)).await
+    };
+
+    save.await.map_err(|e| {
+        error!("Saving standby snapshot failed: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
@@ -1,44 +1,161 @@
-use std::net::SocketAddr;
+use std::{convert::Infallible, net::SocketAddr, time::Instant};
 
-use axum::{extract::ConnectInfo, middleware::Next, response::Response};
+use axum::{
+    async_trait,
+    body::{Bytes, HttpBody},
+    extract::{ConnectInfo, FromRequest, FromRequestParts, MatchedPath, Path},
+    http::request::Parts,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    BoxError, Json,
+};
 use headers::{Header, HeaderValue};
-use hyper::{header, Request, StatusCode};
+use hyper::{header, HeaderMap, Request, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::error;
 
 use utoipa::{
     openapi::security::{ApiKeyValue, SecurityScheme},
     Modify,
 };
 
-use super::{model::ApiKey, GetApiKeys};
+use crate::server::{
+    database::DatabaseError,
+    latency::{self, Phase},
+    security::check_access_token_address_change,
+};
+
+use super::{
+    context::{RequestContext, RequestId},
+    model::{AccountAlias, AccountIdLight, AccountIdOrAlias, ApiKey},
+    GetApiKeys, GetConfig, GetGeoIp, GetInternalApi,
+};
 
 pub const API_KEY_HEADER_STR: &str = "x-api-key";
 pub static API_KEY_HEADER: header::HeaderName = header::HeaderName::from_static(API_KEY_HEADER_STR);
 
-pub async fn authenticate_with_api_key<T, S: GetApiKeys>(
+/// Checks the access token and updates last seen address of the account.
+/// If `bind_access_token_to_ip` config option is enabled, the request must
+/// come from the same IP address as the current WebSocket connection.
+///
+/// Service tokens are accepted also. Those do not require the WebSocket
+/// connection and do not update the session info. The token scope must
+/// allow the route.
+///
+/// Account state must allow accessing the route. See [super::access].
+///
+/// If the account component is on another server, the access token and
+/// the account state are checked from the account server, so the address
+/// is not updated. Service tokens are not supported in that case.
+///
+/// Adds [RequestContext] and [AccountIdInternal] to request extensions.
+/// Missing or invalid access token is 401 and route which the account state
+/// does not allow is 403.
+pub async fn authenticate_with_api_key<T, S: GetApiKeys + GetConfig + GetGeoIp + GetInternalApi>(
     state: S,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     mut req: Request<T>,
     next: Next<T>,
 ) -> Result<Response, StatusCode> {
-    let header = req
+    let auth_start = Instant::now();
+    let key_str = req
         .headers()
         .get(API_KEY_HEADER_STR)
-        .ok_or(StatusCode::BAD_REQUEST)?;
-    let key_str = header.to_str().map_err(|_| StatusCode::BAD_REQUEST)?;
+        .and_then(|header| header.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
     let key = ApiKey::new(key_str.to_string());
 
-    if let Some(id) = state
-        .api_keys()
-        .api_key_and_connection_exists(&key, addr)
-        .await
-    {
-        req.extensions_mut().insert(id);
-        Ok(next.run(req).await)
+    let location = state.geoip().lookup(addr.ip());
+
+    let (id, service_token) = if state.config().components().account {
+        let access_token = state
+            .api_keys()
+            .api_key_used(
+                &key,
+                addr,
+                location.clone(),
+                state.config().bind_access_token_to_ip(),
+            )
+            .await;
+        match access_token {
+            Some((id, previous)) => {
+                check_access_token_address_change(id, previous, (addr, location));
+                (id, None)
+            }
+            None => {
+                let (id, scope) = state
+                    .api_keys()
+                    .service_token_used(&key)
+                    .await
+                    .ok_or(StatusCode::UNAUTHORIZED)?;
+                (id, Some(scope))
+            }
+        }
     } else {
-        Err(StatusCode::UNAUTHORIZED)
+        // Access tokens are stored on the account server.
+        let id = state
+            .internal_api()
+            .check_api_key(key)
+            .await
+            .map_err(|e| {
+                error!("{e:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        (id, None)
+    };
+
+    let account = state
+        .internal_api()
+        .get_account_state(id)
+        .await
+        .map_err(|e| {
+            error!("{e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    // Request ID is missing if the router does not have the request ID
+    // middleware.
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .copied()
+        .unwrap_or_else(RequestId::new);
+    let context =
+        RequestContext::new(id, account.state(), request_id).with_service_token(service_token);
+
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| route_without_base_path(state.config().api_base_path(), path.as_str()))
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !context.allows(req.method(), route) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    req.extensions_mut().insert(context);
+    req.extensions_mut().insert(id);
+    latency::record_phase(Phase::Auth, auth_start.elapsed());
+    Ok(next.run(req).await)
+}
+
+/// Status code for failed database write. Writes are rejected with status
+/// 429 if the account has too many pending write commands and with status
+/// 503 if the write command queue of the account's shard is full.
+pub fn write_error_status(e: &error_stack::Report<DatabaseError>) -> StatusCode {
+    match e.current_context() {
+        DatabaseError::TooManyPendingWrites => StatusCode::TOO_MANY_REQUESTS,
+        DatabaseError::WriteQueueBusy => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
 
+/// Matched path contains the API base path if it is configured.
+pub fn route_without_base_path<'a>(api_base_path: Option<&str>, matched_path: &'a str) -> &'a str {
+    api_base_path
+        .and_then(|base_path| matched_path.strip_prefix(base_path))
+        .unwrap_or(matched_path)
+}
+
 pub struct ApiKeyHeader(ApiKey);
 
 impl ApiKeyHeader {
@@ -68,6 +185,151 @@ impl Header for ApiKeyHeader {
     }
 }
 
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Body format for endpoints which support MessagePack in addition to JSON.
+/// Extracting this selects the response format using the Accept header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFormat {
+    Json,
+    MsgPack,
+}
+
+impl BodyFormat {
+    fn from_accept_header(headers: &HeaderMap) -> Self {
+        let msgpack_accepted = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(is_msgpack);
+        if msgpack_accepted {
+            Self::MsgPack
+        } else {
+            Self::Json
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for BodyFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_accept_header(&parts.headers))
+    }
+}
+
+/// Path parameter `account_id` which is an account ID or an account alias.
+/// Other values are rejected with status 400. Use
+/// `AccountIdManager::get_internal_id_from_path` to find the account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountIdPath {
+    Id(AccountIdLight),
+    Alias(AccountAlias),
+}
+
+impl AccountIdPath {
+    fn parse(text: &str) -> Option<Self> {
+        match uuid::Uuid::parse_str(text) {
+            Ok(id) => Some(Self::Id(AccountIdLight::new(id))),
+            Err(_) => AccountAlias::parse(text).map(Self::Alias),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AccountIdPath {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(path) = Path::<AccountIdOrAlias>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        Self::parse(&path.account_id).ok_or(StatusCode::BAD_REQUEST)
+    }
+}
+
+fn is_msgpack(media_type: &str) -> bool {
+    let media_type = media_type.split(';').next().unwrap_or_default().trim();
+    media_type.eq_ignore_ascii_case(MSGPACK_CONTENT_TYPE)
+}
+
+/// Request body extractor which accepts JSON and MessagePack. Format is
+/// selected using the Content-Type header.
+pub struct MsgPackOrJson<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for MsgPackOrJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let msgpack_body = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(is_msgpack)
+            .unwrap_or(false);
+
+        if msgpack_body {
+            let bytes = Bytes::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            rmp_serde::from_slice(&bytes)
+                .map(Self)
+                .map_err(|_| StatusCode::BAD_REQUEST.into_response())
+        } else {
+            Json::<T>::from_request(req, state)
+                .await
+                .map(|Json(data)| Self(data))
+                .map_err(IntoResponse::into_response)
+        }
+    }
+}
+
+/// Response body which is serialized using the format from [BodyFormat].
+pub struct Negotiated<T> {
+    pub format: BodyFormat,
+    pub data: T,
+}
+
+impl<T> Negotiated<T> {
+    pub fn new(format: BodyFormat, data: T) -> Self {
+        Self { format, data }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        match self.format {
+            BodyFormat::Json => Json(self.data).into_response(),
+            // Use maps with field names, so the data has the same structure
+            // as JSON.
+            BodyFormat::MsgPack => match rmp_serde::to_vec_named(&self.data) {
+                Ok(bytes) => (
+                    [(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(MSGPACK_CONTENT_TYPE),
+                    )],
+                    bytes,
+                )
+                    .into_response(),
+                Err(e) => {
+                    error!("MessagePack serialization failed: {e:?}");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+        }
+    }
+}
+
 /// Utoipa API doc security config
 pub struct SecurityApiTokenDefault;
 
@@ -83,3 +345,92 @@ impl Modify for SecurityApiTokenDefault {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use headers::{Header, HeaderValue};
+    use proptest::prelude::*;
+
+    use super::{ApiKeyHeader, API_KEY_HEADER};
+    use crate::{
+        api::{account::PATH_ACCOUNT_STATE, model::ApiKey, GetConfig},
+        server::test_support::TestApp,
+    };
+
+    fn encode(key: ApiKey) -> Vec<HeaderValue> {
+        let mut values = Vec::new();
+        ApiKeyHeader(key).encode(&mut values);
+        values
+    }
+
+    #[test]
+    fn generated_api_key_is_valid_header_value() {
+        let key = ApiKey::generate_new();
+        let values = encode(key.clone());
+        assert_eq!(values.len(), 1);
+        let decoded = ApiKeyHeader::decode(&mut values.iter()).unwrap();
+        assert_eq!(decoded.key(), &key);
+        assert_eq!(ApiKeyHeader::name(), &API_KEY_HEADER);
+    }
+
+    proptest! {
+        #[test]
+        fn api_key_header_round_trip(key in "[!-~]{1,64}") {
+            let key = ApiKey::new(key);
+            let values = encode(key.clone());
+            prop_assert_eq!(values.len(), 1);
+            prop_assert_eq!(values[0].to_str().unwrap(), key.as_str());
+            let decoded = ApiKeyHeader::decode(&mut values.iter()).unwrap();
+            prop_assert_eq!(decoded.key(), &key);
+        }
+
+        #[test]
+        fn non_visible_ascii_api_key_is_rejected(
+            prefix in "[!-~]{0,8}",
+            byte in 0x80u8..=0xff,
+        ) {
+            let mut bytes = prefix.into_bytes();
+            bytes.push(byte);
+            let value = HeaderValue::from_bytes(&bytes).unwrap();
+            prop_assert!(ApiKeyHeader::decode(&mut std::iter::once(&value)).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn access_token_expires_when_mock_clock_passes_ttl() {
+        let app = TestApp::with_config(|config| {
+            config.mock_clock = Some(true);
+            config
+                .account
+                .get_or_insert_with(Default::default)
+                .access_token_ttl_seconds = Some(60);
+        })
+        .await;
+        let (_, login) = app.register_and_login().await;
+        let access = Some(&login.account.access);
+        let state = app.state();
+        let clock = state.config().mock_clock().unwrap();
+
+        clock.advance(30);
+        let response = app.get(PATH_ACCOUNT_STATE, access).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        clock.advance(31);
+        let response = app.get(PATH_ACCOUNT_STATE, access).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        drop(state);
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn account_state_requires_access_token() {
+        let app = TestApp::new().await;
+
+        let response = app.get(PATH_ACCOUNT_STATE, None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        app.close().await;
+    }
+}
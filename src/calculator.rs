@@ -0,0 +1,242 @@
+//! Calculator expression evaluation
+//!
+//! Supported syntax: decimal numbers, `+`, `-`, `*`, `/`, `^`, unary minus,
+//! parentheses and function calls. See [parser] for the grammar.
+//!
+//! Operators and functions are operations in [OperationRegistry], so the
+//! evaluator does not need changes when new operations are added.
+//!
+//! Errors and warnings are reported as [Diagnostic]s which contain the
+//! position of the problem in the expression.
+//!
+//! Parsing and evaluation are limited with [EvaluationLimits].
+
+pub mod cache;
+pub mod diagnostic;
+pub mod format;
+pub mod limits;
+pub mod operation;
+pub mod operations;
+pub mod parser;
+pub mod tokenizer;
+
+pub use self::cache::{EvaluationCache, EvaluationCacheStats};
+pub use self::diagnostic::{Diagnostic, DiagnosticCode, Severity, Span};
+pub use self::format::NumberFormat;
+pub use self::limits::EvaluationLimits;
+pub use self::operation::{AngleMode, OperationRegistry, OperationSet};
+
+use crate::i18n::Message;
+
+use self::{
+    limits::Budget,
+    operation::OperationContext,
+    operations::basic,
+    parser::{BinaryOperator, Expression, UnaryOperator},
+    tokenizer::Token,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub value: f64,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Parse and evaluate expression. Returns all errors if evaluation fails.
+pub fn evaluate(
+    expression: &str,
+    operations: &OperationRegistry,
+    limits: &EvaluationLimits,
+    angle_mode: AngleMode,
+) -> Result<Evaluation, Vec<Diagnostic>> {
+    let mut warnings = vec![];
+    let tokens = tokenize(expression, limits, &mut warnings)?;
+    let value = evaluate_tokens(&tokens, operations, limits, angle_mode)?;
+    Ok(Evaluation { value, warnings })
+}
+
+/// Parse expression and check that all functions exist and have correct
+/// argument count. Expression is not evaluated.
+pub fn parse(
+    expression: &str,
+    operations: &OperationRegistry,
+    limits: &EvaluationLimits,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Expression, Vec<Diagnostic>> {
+    let tokens = tokenize(expression, limits, warnings)?;
+    parse_tokens(&tokens, operations, limits)
+}
+
+/// Tokenize expression if it is not too long.
+fn tokenize(
+    expression: &str,
+    limits: &EvaluationLimits,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Vec<Token>, Vec<Diagnostic>> {
+    let length = expression.chars().count();
+    if length > limits.max_expression_length {
+        return Err(vec![Diagnostic::error(
+            Span::new(limits.max_expression_length, length),
+            Message::new("Expression is longer than {0} characters")
+                .arg(limits.max_expression_length),
+        )
+        .with_code(DiagnosticCode::ExpressionTooLong)]);
+    }
+
+    tokenizer::tokenize(expression, warnings)
+}
+
+fn parse_tokens(
+    tokens: &[Token],
+    operations: &OperationRegistry,
+    limits: &EvaluationLimits,
+) -> Result<Expression, Vec<Diagnostic>> {
+    let ast = parser::parse(tokens, limits.max_depth).map_err(|e| vec![e])?;
+
+    // Parser limits only nesting, so long operator chains must be checked
+    // separately.
+    if ast.depth() > limits.max_depth {
+        return Err(vec![Diagnostic::error(
+            ast.full_span(),
+            "Expression is nested too deeply",
+        )
+        .with_code(DiagnosticCode::ExpressionTooDeep)]);
+    }
+
+    let mut errors = vec![];
+    check_calls(&ast, operations, &mut errors);
+    if errors.is_empty() {
+        Ok(ast)
+    } else {
+        Err(errors)
+    }
+}
+
+fn evaluate_tokens(
+    tokens: &[Token],
+    operations: &OperationRegistry,
+    limits: &EvaluationLimits,
+    angle_mode: AngleMode,
+) -> Result<f64, Vec<Diagnostic>> {
+    let ast = parse_tokens(tokens, operations, limits)?;
+    let mut evaluator = Evaluator {
+        operations,
+        context: OperationContext { angle_mode },
+        budget: Budget::new(limits),
+    };
+    evaluator.evaluate(&ast).map_err(|e| vec![e])
+}
+
+fn check_calls(
+    expression: &Expression,
+    operations: &OperationRegistry,
+    errors: &mut Vec<Diagnostic>,
+) {
+    match expression {
+        Expression::Number { .. } => (),
+        Expression::Unary { operand, .. } => check_calls(operand, operations, errors),
+        Expression::Binary { left, right, .. } => {
+            check_calls(left, operations, errors);
+            check_calls(right, operations, errors);
+        }
+        Expression::Call {
+            name,
+            arguments,
+            span,
+        } => {
+            match operations.get(name) {
+                None => errors.push(Diagnostic::error(
+                    *span,
+                    Message::new("Unknown function '{0}'").arg(name),
+                )),
+                Some(operation) if !operation.arity.accepts(arguments.len()) => {
+                    errors.push(Diagnostic::error(
+                        *span,
+                        Message::new("Function '{0}' takes {1} arguments but {2} were given")
+                            .arg(name)
+                            .arg(operation.arity)
+                            .arg(arguments.len()),
+                    ))
+                }
+                Some(_) => (),
+            }
+            for argument in arguments {
+                check_calls(argument, operations, errors);
+            }
+        }
+    }
+}
+
+fn operator_name(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => basic::ADD,
+        BinaryOperator::Subtract => basic::SUBTRACT,
+        BinaryOperator::Multiply => basic::MULTIPLY,
+        BinaryOperator::Divide => basic::DIVIDE,
+        BinaryOperator::Power => basic::POWER,
+    }
+}
+
+struct Evaluator<'a> {
+    operations: &'a OperationRegistry,
+    context: OperationContext,
+    budget: Budget,
+}
+
+impl Evaluator<'_> {
+    fn apply(&self, name: &str, arguments: &[f64], span: Span) -> Result<f64, Diagnostic> {
+        let operation = self.operations.get(name).ok_or_else(|| {
+            Diagnostic::error(span, Message::new("Unknown function '{0}'").arg(name))
+        })?;
+        (operation.implementation)(arguments, &self.context)
+            .map_err(|message| Diagnostic::error(span, message))
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Result<f64, Diagnostic> {
+        self.budget.use_steps(1, expression.full_span())?;
+
+        let value = match expression {
+            Expression::Number { value, .. } => *value,
+            Expression::Unary {
+                operator: UnaryOperator::Negate,
+                operand,
+                span,
+            } => {
+                let operand = self.evaluate(operand)?;
+                self.apply(basic::NEGATE, &[operand], *span)?
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+                span,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.apply(operator_name(*operator), &[left, right], *span)?
+            }
+            Expression::Call {
+                name,
+                arguments,
+                span,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|a| self.evaluate(a))
+                    .collect::<Result<Vec<f64>, Diagnostic>>()?;
+                // Functions iterate over their arguments.
+                self.budget.use_steps(arguments.len() as u64, *span)?;
+                self.apply(name, &arguments, *span)?
+            }
+        };
+
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(Diagnostic::error(
+                expression.full_span(),
+                "Result is not a finite number",
+            ))
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Evaluation result cache
+//!
+//! All operations are pure, so the result depends only on the expression and
+//! the angle mode. Cache key contains the expression tokens in normalized
+//! form, so whitespace and number formatting differences do not cause cache
+//! misses. Only successful evaluations are cached because for example the
+//! evaluation time limit can fail differently between evaluations.
+//!
+//! Limits can change when the config is reloaded, so the limits are part of
+//! the cache key. Cached result is then used only if the expression passed
+//! the same depth and step limits when it was evaluated.
+//!
+//! Registries with different operation sets share the cache. Cached result
+//! is used only if the registry has all the functions of the expression.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    evaluate_tokens, tokenize,
+    tokenizer::{Token, TokenKind},
+    AngleMode, Diagnostic, Evaluation, EvaluationLimits, OperationRegistry,
+};
+
+type CacheKey = (AngleMode, EvaluationLimits, String);
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct EvaluationCacheStats {
+    pub enabled: bool,
+    pub capacity: u64,
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Bounded LRU cache for evaluation results.
+pub struct EvaluationCache {
+    /// None if cache is disabled.
+    cache: Option<Mutex<LruCache<CacheKey, f64>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EvaluationCache {
+    /// Cache is disabled if `capacity` is None or zero.
+    pub fn new(capacity: Option<usize>) -> Self {
+        Self {
+            cache: capacity
+                .and_then(NonZeroUsize::new)
+                .map(|capacity| Mutex::new(LruCache::new(capacity))),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Same as [super::evaluate] but result is read from cache if possible.
+    pub fn evaluate(
+        &self,
+        expression: &str,
+        operations: &OperationRegistry,
+        limits: &EvaluationLimits,
+        angle_mode: AngleMode,
+    ) -> Result<Evaluation, Vec<Diagnostic>> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return super::evaluate(expression, operations, limits, angle_mode),
+        };
+
+        // Warnings depend on the exact expression text, so tokenizing is
+        // not cached.
+        let mut warnings = vec![];
+        let tokens = tokenize(expression, limits, &mut warnings)?;
+        let key = (angle_mode, *limits, cache_key(&tokens));
+
+        let cached = Self::lock(cache).get(&key).copied();
+        let cached = cached.filter(|_| has_operations(&tokens, operations));
+        if let Some(value) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Evaluation { value, warnings });
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Evaluate without holding the lock.
+        let value = evaluate_tokens(&tokens, operations, limits, angle_mode)?;
+        Self::lock(cache).put(key, value);
+        Ok(Evaluation { value, warnings })
+    }
+
+    pub fn stats(&self) -> EvaluationCacheStats {
+        let (enabled, capacity, entries) = match &self.cache {
+            Some(cache) => {
+                let cache = Self::lock(cache);
+                (true, cache.cap().get() as u64, cache.len() as u64)
+            }
+            None => (false, 0, 0),
+        };
+
+        EvaluationCacheStats {
+            enabled,
+            capacity,
+            entries,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn lock(
+        cache: &Mutex<LruCache<CacheKey, f64>>,
+    ) -> std::sync::MutexGuard<'_, LruCache<CacheKey, f64>> {
+        // Cache is always in valid state, so poisoning can be ignored.
+        cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn cache_key(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match &token.kind {
+            TokenKind::Number(value) => value.to_string(),
+            TokenKind::Identifier(name) => name.clone(),
+            other => other.describe(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn has_operations(tokens: &[Token], operations: &OperationRegistry) -> bool {
+    tokens.iter().all(|token| match &token.kind {
+        TokenKind::Identifier(name) => operations.get(name).is_some(),
+        _ => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EvaluationCache;
+    use crate::calculator::{
+        AngleMode, DiagnosticCode, EvaluationLimits, OperationRegistry, OperationSet,
+    };
+
+    #[test]
+    fn cached_result_is_not_used_with_lower_limits() {
+        let cache = EvaluationCache::new(Some(10));
+        let operations = OperationRegistry::with_builtin_operations(OperationSet::ALL);
+        let limits = EvaluationLimits::default();
+        let expression = "((((1 + 2))))";
+
+        for _ in 0..2 {
+            let evaluation = cache
+                .evaluate(expression, &operations, &limits, AngleMode::Radians)
+                .unwrap();
+            assert_eq!(evaluation.value, 3.0);
+        }
+        assert_eq!(cache.stats().hits, 1);
+
+        let limits = EvaluationLimits {
+            max_depth: 2,
+            ..limits
+        };
+        let errors = cache
+            .evaluate(expression, &operations, &limits, AngleMode::Radians)
+            .unwrap_err();
+        assert_eq!(errors[0].code, Some(DiagnosticCode::ExpressionTooDeep));
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
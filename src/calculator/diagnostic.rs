@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::i18n::{Message, Translations};
+
+/// Position in the expression. Positions are character indexes, so those
+/// can be used directly for highlighting the expression text.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct Span {
+    /// First character.
+    pub start: usize,
+    /// Character after the last character.
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Span which covers both spans.
+    pub fn join(self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Machine readable error code for errors which clients might want to
+/// handle differently than other errors.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    ExpressionTooLong,
+    ExpressionTooDeep,
+    StepLimitExceeded,
+    TimeLimitExceeded,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    /// Message in English or in account's language if the diagnostic is
+    /// localized.
+    pub message: String,
+    /// Tokens which would have been valid at the error position.
+    pub expected: Vec<String>,
+    pub code: Option<DiagnosticCode>,
+    /// Untranslated message for localization.
+    #[serde(skip)]
+    pub source_message: Option<Message>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: impl Into<Message>) -> Self {
+        Self::new(Severity::Error, span, message.into())
+    }
+
+    pub fn warning(span: Span, message: impl Into<Message>) -> Self {
+        Self::new(Severity::Warning, span, message.into())
+    }
+
+    fn new(severity: Severity, span: Span, message: Message) -> Self {
+        Self {
+            severity,
+            span,
+            message: message.english(),
+            expected: vec![],
+            code: None,
+            source_message: Some(message),
+        }
+    }
+
+    /// Translate message to `locale`.
+    pub fn localize(&mut self, translations: &Translations, locale: &str) {
+        if let Some(message) = &self.source_message {
+            self.message = translations.translate(locale, message);
+        }
+    }
+
+    pub fn with_expected(mut self, expected: &[&str]) -> Self {
+        self.expected = expected.iter().map(|e| e.to_string()).collect();
+        self
+    }
+
+    pub fn with_code(mut self, code: DiagnosticCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (position {}-{})",
+            self.message, self.span.start, self.span.end
+        )?;
+        if !self.expected.is_empty() {
+            write!(f, ", expected: {}", self.expected.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! Number formatting for displaying results
+
+/// How result numbers are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Digits after the decimal separator. If None, the shortest
+    /// representation which converts back to the same number is used.
+    pub precision: Option<u8>,
+    pub decimal_separator: char,
+    /// Separator for groups of three integer digits.
+    pub thousands_separator: Option<char>,
+}
+
+impl NumberFormat {
+    /// Separators are selected using language of `locale`. Unknown languages
+    /// use English separators.
+    pub fn new(locale: &str, precision: Option<u8>, group_thousands: bool) -> Self {
+        let language = locale
+            .split(|c| c == '-' || c == '_')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let (decimal_separator, thousands_separator) = match language.as_str() {
+            "da" | "de" | "es" | "id" | "it" | "nl" | "pt" | "tr" => (',', '.'),
+            "cs" | "fi" | "fr" | "nb" | "no" | "pl" | "ru" | "sv" | "uk" => (',', '\u{a0}'),
+            _ => ('.', ','),
+        };
+
+        Self {
+            precision,
+            decimal_separator,
+            thousands_separator: group_thousands.then_some(thousands_separator),
+        }
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        let text = match self.precision {
+            Some(precision) => format!("{:.*}", precision as usize, value),
+            None => value.to_string(),
+        };
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+
+        let mut result = sign.to_string();
+        for (i, c) in integer.chars().enumerate() {
+            if let Some(separator) = self.thousands_separator {
+                if i > 0 && (integer.len() - i) % 3 == 0 {
+                    result.push(separator);
+                }
+            }
+            result.push(c);
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+}
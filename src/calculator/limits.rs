@@ -0,0 +1,150 @@
+//! Execution limits
+//!
+//! Limits prevent expressions from using too much server resources.
+
+use std::time::{Duration, Instant};
+
+use super::diagnostic::{Diagnostic, DiagnosticCode, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EvaluationLimits {
+    /// Max expression length in characters.
+    pub max_expression_length: usize,
+    /// Max nesting depth of the parsed expression.
+    pub max_depth: usize,
+    /// Max count of evaluation steps. Every evaluated expression node and
+    /// every function argument is one step.
+    pub max_steps: u64,
+    /// Max evaluation wall time.
+    pub max_time: Duration,
+}
+
+impl Default for EvaluationLimits {
+    fn default() -> Self {
+        Self {
+            max_expression_length: 10_000,
+            max_depth: 64,
+            max_steps: 100_000,
+            max_time: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Steps and time left for one evaluation.
+pub struct Budget {
+    steps_left: u64,
+    deadline: Instant,
+}
+
+impl Budget {
+    pub fn new(limits: &EvaluationLimits) -> Self {
+        Self {
+            steps_left: limits.max_steps,
+            deadline: Instant::now() + limits.max_time,
+        }
+    }
+
+    /// Use `count` steps. Error is returned if steps or time runs out.
+    pub fn use_steps(&mut self, count: u64, span: Span) -> Result<(), Diagnostic> {
+        if count > self.steps_left {
+            self.steps_left = 0;
+            return Err(Diagnostic::error(span, "Evaluation step limit exceeded")
+                .with_code(DiagnosticCode::StepLimitExceeded));
+        }
+        self.steps_left -= count;
+
+        if Instant::now() > self.deadline {
+            return Err(Diagnostic::error(span, "Evaluation time limit exceeded")
+                .with_code(DiagnosticCode::TimeLimitExceeded));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Budget, EvaluationLimits};
+    use crate::calculator::{
+        evaluate, AngleMode, DiagnosticCode, OperationRegistry, OperationSet, Span,
+    };
+
+    fn error_code(expression: &str, limits: EvaluationLimits) -> Option<DiagnosticCode> {
+        let operations = OperationRegistry::with_builtin_operations(OperationSet::ALL);
+        let errors = evaluate(expression, &operations, &limits, AngleMode::Radians).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        errors[0].code
+    }
+
+    #[test]
+    fn expression_length_limit() {
+        let limits = EvaluationLimits {
+            max_expression_length: 5,
+            ..EvaluationLimits::default()
+        };
+        let operations = OperationRegistry::with_builtin_operations(OperationSet::ALL);
+        assert!(evaluate("1 + 2", &operations, &limits, AngleMode::Radians).is_ok());
+
+        let errors = evaluate("1 + 20", &operations, &limits, AngleMode::Radians).unwrap_err();
+        assert_eq!(errors[0].code, Some(DiagnosticCode::ExpressionTooLong));
+        assert_eq!(errors[0].span, Span::new(5, 6));
+    }
+
+    #[test]
+    fn nesting_depth_limit() {
+        let limits = EvaluationLimits {
+            max_depth: 3,
+            ..EvaluationLimits::default()
+        };
+        assert_eq!(
+            error_code("((((1))))", limits),
+            Some(DiagnosticCode::ExpressionTooDeep)
+        );
+        // Operator chains are not nested, but those make the tree deep.
+        assert_eq!(
+            error_code("1 + 1 + 1 + 1", limits),
+            Some(DiagnosticCode::ExpressionTooDeep)
+        );
+    }
+
+    #[test]
+    fn step_limit() {
+        let limits = EvaluationLimits {
+            max_steps: 4,
+            ..EvaluationLimits::default()
+        };
+        let operations = OperationRegistry::with_builtin_operations(OperationSet::ALL);
+        // Three expression nodes.
+        assert!(evaluate("1 + 2", &operations, &limits, AngleMode::Radians).is_ok());
+        // Four expression nodes and three function arguments.
+        assert_eq!(
+            error_code("sum(1, 2, 3)", limits),
+            Some(DiagnosticCode::StepLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn budget_step_limit() {
+        let mut budget = Budget::new(&EvaluationLimits {
+            max_steps: 2,
+            ..EvaluationLimits::default()
+        });
+        let span = Span::new(0, 1);
+        assert!(budget.use_steps(2, span).is_ok());
+        let error = budget.use_steps(1, span).unwrap_err();
+        assert_eq!(error.code, Some(DiagnosticCode::StepLimitExceeded));
+    }
+
+    #[test]
+    fn budget_time_limit() {
+        let mut budget = Budget::new(&EvaluationLimits {
+            max_time: Duration::ZERO,
+            ..EvaluationLimits::default()
+        });
+        std::thread::sleep(Duration::from_millis(1));
+        let error = budget.use_steps(1, Span::new(0, 1)).unwrap_err();
+        assert_eq!(error.code, Some(DiagnosticCode::TimeLimitExceeded));
+    }
+}
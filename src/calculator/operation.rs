@@ -0,0 +1,205 @@
+//! Operation registry
+//!
+//! Every operator and function which the evaluator supports is an operation
+//! in the registry. Operations belong to an operation set and only
+//! operations from enabled sets are available.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::i18n::Message;
+
+use super::operations;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationSet {
+    /// Operators. Always enabled.
+    Basic,
+    Scientific,
+    Statistics,
+    Currency,
+}
+
+impl OperationSet {
+    pub const ALL: &'static [OperationSet] = &[
+        OperationSet::Basic,
+        OperationSet::Scientific,
+        OperationSet::Statistics,
+        OperationSet::Currency,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Self::Exact(n) => count == n,
+            Self::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+impl std::fmt::Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(n) => write!(f, "{}", n),
+            Self::AtLeast(n) => write!(f, "at least {}", n),
+        }
+    }
+}
+
+/// Unit of trigonometric function arguments.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema, PartialEq, Eq, Hash, Default)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+/// Settings which affect operation results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OperationContext {
+    pub angle_mode: AngleMode,
+}
+
+impl OperationContext {
+    pub fn angle_to_radians(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+        }
+    }
+}
+
+/// Returns error message if operation fails.
+pub type OperationFn = fn(&[f64], &OperationContext) -> Result<f64, Message>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Operation {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub set: OperationSet,
+    pub implementation: OperationFn,
+}
+
+#[derive(Debug)]
+pub struct OperationRegistry {
+    enabled_sets: Vec<OperationSet>,
+    operations: HashMap<&'static str, Operation>,
+}
+
+impl OperationRegistry {
+    /// Empty registry which accepts operations from `enabled_sets`.
+    /// Basic operation set is always enabled.
+    pub fn new(enabled_sets: &[OperationSet]) -> Self {
+        let mut enabled_sets = enabled_sets.to_vec();
+        if !enabled_sets.contains(&OperationSet::Basic) {
+            enabled_sets.push(OperationSet::Basic);
+        }
+        Self {
+            enabled_sets,
+            operations: HashMap::new(),
+        }
+    }
+
+    /// Registry with all built-in operations from `enabled_sets`.
+    pub fn with_builtin_operations(enabled_sets: &[OperationSet]) -> Self {
+        let mut registry = Self::new(enabled_sets);
+        operations::register_builtin_operations(&mut registry);
+        registry
+    }
+
+    /// Operation is ignored if its operation set is not enabled.
+    ///
+    /// Panics if operation with the same name is already registered.
+    pub fn register(&mut self, operation: Operation) {
+        if !self.enabled_sets.contains(&operation.set) {
+            return;
+        }
+
+        if self.operations.insert(operation.name, operation).is_some() {
+            panic!("Operation '{}' is already registered", operation.name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Operation> {
+        self.operations.get(name)
+    }
+
+    pub fn enabled_sets(&self) -> &[OperationSet] {
+        &self.enabled_sets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arity, Operation, OperationRegistry, OperationSet};
+    use crate::calculator::{evaluate, operations::basic, AngleMode, EvaluationLimits};
+
+    fn double() -> Operation {
+        Operation {
+            name: "double",
+            arity: Arity::Exact(1),
+            set: OperationSet::Scientific,
+            implementation: |a, _| Ok(a[0] * 2.0),
+        }
+    }
+
+    #[test]
+    fn basic_set_is_always_enabled() {
+        let registry = OperationRegistry::with_builtin_operations(&[]);
+        assert_eq!(registry.enabled_sets(), &[OperationSet::Basic]);
+        assert!(registry.get(basic::ADD).is_some());
+        assert!(registry.get("sqrt").is_none());
+        assert!(registry.get("sum").is_none());
+    }
+
+    #[test]
+    fn only_operations_from_enabled_sets_are_registered() {
+        let registry = OperationRegistry::with_builtin_operations(&[OperationSet::Statistics]);
+        assert!(registry.get("sum").is_some());
+        assert!(registry.get("sqrt").is_none());
+
+        let mut registry = OperationRegistry::new(&[OperationSet::Basic]);
+        registry.register(double());
+        assert!(registry.get("double").is_none());
+    }
+
+    #[test]
+    fn registered_operation_is_evaluated() {
+        let mut registry = OperationRegistry::with_builtin_operations(&[OperationSet::Scientific]);
+        registry.register(double());
+        let evaluation = evaluate(
+            "double(1 + 2)",
+            &registry,
+            &EvaluationLimits::default(),
+            AngleMode::Radians,
+        )
+        .unwrap();
+        assert_eq!(evaluation.value, 6.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Operation 'double' is already registered")]
+    fn registering_same_name_twice_panics() {
+        let mut registry = OperationRegistry::new(&[OperationSet::Scientific]);
+        registry.register(double());
+        registry.register(double());
+    }
+
+    #[test]
+    fn arity() {
+        assert!(Arity::Exact(2).accepts(2));
+        assert!(!Arity::Exact(2).accepts(1));
+        assert!(Arity::AtLeast(1).accepts(3));
+        assert!(!Arity::AtLeast(1).accepts(0));
+    }
+}
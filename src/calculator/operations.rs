@@ -0,0 +1,15 @@
+//! Built-in operations
+
+pub mod basic;
+pub mod currency;
+pub mod scientific;
+pub mod statistics;
+
+use super::operation::OperationRegistry;
+
+pub fn register_builtin_operations(registry: &mut OperationRegistry) {
+    basic::register(registry);
+    scientific::register(registry);
+    statistics::register(registry);
+    currency::register(registry);
+}
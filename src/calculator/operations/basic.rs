@@ -0,0 +1,41 @@
+//! Operators
+
+use crate::calculator::operation::{
+    Arity, Operation, OperationFn, OperationRegistry, OperationSet,
+};
+
+pub const ADD: &str = "add";
+pub const SUBTRACT: &str = "subtract";
+pub const MULTIPLY: &str = "multiply";
+pub const DIVIDE: &str = "divide";
+pub const POWER: &str = "power";
+pub const NEGATE: &str = "negate";
+
+fn binary(name: &'static str, implementation: OperationFn) -> Operation {
+    Operation {
+        name,
+        arity: Arity::Exact(2),
+        set: OperationSet::Basic,
+        implementation,
+    }
+}
+
+pub fn register(registry: &mut OperationRegistry) {
+    registry.register(binary(ADD, |a, _| Ok(a[0] + a[1])));
+    registry.register(binary(SUBTRACT, |a, _| Ok(a[0] - a[1])));
+    registry.register(binary(MULTIPLY, |a, _| Ok(a[0] * a[1])));
+    registry.register(binary(DIVIDE, |a, _| {
+        if a[1] == 0.0 {
+            Err("Division by zero".into())
+        } else {
+            Ok(a[0] / a[1])
+        }
+    }));
+    registry.register(binary(POWER, |a, _| Ok(a[0].powf(a[1]))));
+    registry.register(Operation {
+        name: NEGATE,
+        arity: Arity::Exact(1),
+        set: OperationSet::Basic,
+        implementation: |a, _| Ok(-a[0]),
+    });
+}
@@ -0,0 +1,27 @@
+//! Currency calculation functions
+
+use crate::calculator::operation::{Arity, Operation, OperationRegistry, OperationSet};
+
+pub fn register(registry: &mut OperationRegistry) {
+    // Round to cents. Halfway cases are rounded away from zero.
+    registry.register(Operation {
+        name: "round_money",
+        arity: Arity::Exact(1),
+        set: OperationSet::Currency,
+        implementation: |a, _| Ok((a[0] * 100.0).round() / 100.0),
+    });
+    // percent_of(amount, percent)
+    registry.register(Operation {
+        name: "percent_of",
+        arity: Arity::Exact(2),
+        set: OperationSet::Currency,
+        implementation: |a, _| Ok(a[0] * a[1] / 100.0),
+    });
+    // add_percent(amount, percent), for example adding VAT.
+    registry.register(Operation {
+        name: "add_percent",
+        arity: Arity::Exact(2),
+        set: OperationSet::Currency,
+        implementation: |a, _| Ok(a[0] * (1.0 + a[1] / 100.0)),
+    });
+}
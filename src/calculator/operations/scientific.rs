@@ -0,0 +1,43 @@
+//! Scientific functions
+
+use crate::calculator::operation::{
+    Arity, Operation, OperationFn, OperationRegistry, OperationSet,
+};
+
+fn unary(name: &'static str, implementation: OperationFn) -> Operation {
+    Operation {
+        name,
+        arity: Arity::Exact(1),
+        set: OperationSet::Scientific,
+        implementation,
+    }
+}
+
+pub fn register(registry: &mut OperationRegistry) {
+    registry.register(unary("abs", |a, _| Ok(a[0].abs())));
+    registry.register(unary("sqrt", |a, _| {
+        if a[0] < 0.0 {
+            Err("Square root of negative number".into())
+        } else {
+            Ok(a[0].sqrt())
+        }
+    }));
+    registry.register(unary("exp", |a, _| Ok(a[0].exp())));
+    registry.register(unary("ln", |a, _| {
+        if a[0] <= 0.0 {
+            Err("Logarithm of non-positive number".into())
+        } else {
+            Ok(a[0].ln())
+        }
+    }));
+    registry.register(unary("log10", |a, _| {
+        if a[0] <= 0.0 {
+            Err("Logarithm of non-positive number".into())
+        } else {
+            Ok(a[0].log10())
+        }
+    }));
+    registry.register(unary("sin", |a, c| Ok(c.angle_to_radians(a[0]).sin())));
+    registry.register(unary("cos", |a, c| Ok(c.angle_to_radians(a[0]).cos())));
+    registry.register(unary("tan", |a, c| Ok(c.angle_to_radians(a[0]).tan())));
+}
@@ -0,0 +1,44 @@
+//! Statistics functions
+
+use crate::calculator::operation::{
+    Arity, Operation, OperationFn, OperationRegistry, OperationSet,
+};
+
+fn variadic(name: &'static str, implementation: OperationFn) -> Operation {
+    Operation {
+        name,
+        arity: Arity::AtLeast(1),
+        set: OperationSet::Statistics,
+        implementation,
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub fn register(registry: &mut OperationRegistry) {
+    registry.register(variadic("sum", |a, _| Ok(a.iter().sum())));
+    registry.register(variadic("mean", |a, _| Ok(mean(a))));
+    registry.register(variadic("min", |a, _| {
+        Ok(a.iter().copied().fold(f64::INFINITY, f64::min))
+    }));
+    registry.register(variadic("max", |a, _| {
+        Ok(a.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+    }));
+    registry.register(variadic("median", |a, _| {
+        let mut sorted = a.to_vec();
+        sorted.sort_by(|x, y| x.total_cmp(y));
+        let middle = sorted.len() / 2;
+        if sorted.len() % 2 == 0 {
+            Ok((sorted[middle - 1] + sorted[middle]) / 2.0)
+        } else {
+            Ok(sorted[middle])
+        }
+    }));
+    registry.register(variadic("stddev", |a, _| {
+        let mean = mean(a);
+        let variance = a.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / a.len() as f64;
+        Ok(variance.sqrt())
+    }));
+}
@@ -0,0 +1,391 @@
+//! Recursive descent parser
+//!
+//! ```text
+//! expression = term (("+" | "-") term)*
+//! term       = unary (("*" | "/") unary)*
+//! unary      = "-" unary | power
+//! power      = primary ("^" unary)?
+//! primary    = number | identifier "(" arguments? ")" | "(" expression ")"
+//! arguments  = expression ("," expression)*
+//! ```
+
+use crate::i18n::Message;
+
+use super::{
+    diagnostic::{Diagnostic, DiagnosticCode, Span},
+    tokenizer::{Token, TokenKind},
+};
+
+const EXPECTED_OPERAND: &[&str] = &["number", "function name", "'('", "'-'"];
+const EXPECTED_OPERATOR: &[&str] = &["'+'", "'-'", "'*'", "'/'", "'^'", "end of expression"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number {
+        value: f64,
+        span: Span,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+        span: Span,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+        /// Span of the operator.
+        span: Span,
+    },
+    Call {
+        name: String,
+        arguments: Vec<Expression>,
+        /// Span of the function name.
+        span: Span,
+    },
+}
+
+impl Expression {
+    /// Span of the whole expression.
+    pub fn full_span(&self) -> Span {
+        match self {
+            Self::Number { span, .. } | Self::Call { span, .. } => *span,
+            Self::Unary { operand, span, .. } => span.join(operand.full_span()),
+            Self::Binary { left, right, .. } => left.full_span().join(right.full_span()),
+        }
+    }
+
+    /// Nesting depth of the expression tree. Single number has depth 1.
+    pub fn depth(&self) -> usize {
+        match self {
+            Self::Number { .. } => 1,
+            Self::Unary { operand, .. } => 1 + operand.depth(),
+            Self::Binary { left, right, .. } => 1 + left.depth().max(right.depth()),
+            Self::Call { arguments, .. } => {
+                1 + arguments.iter().map(|a| a.depth()).max().unwrap_or(0)
+            }
+        }
+    }
+}
+
+/// Parse tokens to expression tree.
+///
+/// Parsing stops with error if nesting of parentheses, function calls,
+/// unary minus and exponents gets deeper than `max_depth`, so hostile input
+/// can not overflow the stack.
+pub fn parse(tokens: &[Token], max_depth: usize) -> Result<Expression, Diagnostic> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+        max_depth,
+    };
+    let expression = parser.expression()?;
+    match parser.peek() {
+        TokenKind::End => Ok(expression),
+        TokenKind::RightParen => Err(Diagnostic::error(
+            parser.current().span,
+            "Unmatched closing parenthesis",
+        )),
+        _ => Err(parser.unexpected(EXPECTED_OPERATOR)),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Parser<'_> {
+    fn current(&self) -> &Token {
+        // Last token is always End.
+        &self.tokens[self.position.min(self.tokens.len() - 1)]
+    }
+
+    fn peek(&self) -> &TokenKind {
+        &self.current().kind
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.current().clone();
+        self.position += 1;
+        token
+    }
+
+    fn unexpected(&self, expected: &[&str]) -> Diagnostic {
+        let token = self.current();
+        Diagnostic::error(
+            token.span,
+            Message::new("Unexpected {0}").arg(token.kind.describe()),
+        )
+        .with_expected(expected)
+    }
+
+    /// Run `parse` one nesting level deeper.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        if self.depth >= self.max_depth {
+            return Err(
+                Diagnostic::error(self.current().span, "Expression is nested too deeply")
+                    .with_code(DiagnosticCode::ExpressionTooDeep),
+            );
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn expression(&mut self) -> Result<Expression, Diagnostic> {
+        let mut left = self.term()?;
+        loop {
+            let operator = match self.peek() {
+                TokenKind::Plus => BinaryOperator::Add,
+                TokenKind::Minus => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+            let span = self.next().span;
+            let right = self.term()?;
+            left = binary(operator, left, right, span);
+        }
+    }
+
+    fn term(&mut self) -> Result<Expression, Diagnostic> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                TokenKind::Star => BinaryOperator::Multiply,
+                TokenKind::Slash => BinaryOperator::Divide,
+                _ => return Ok(left),
+            };
+            let span = self.next().span;
+            let right = self.unary()?;
+            left = binary(operator, left, right, span);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, Diagnostic> {
+        if self.peek() == &TokenKind::Minus {
+            let span = self.next().span;
+            let operand = self.nested(Self::unary)?;
+            Ok(Expression::Unary {
+                operator: UnaryOperator::Negate,
+                operand: Box::new(operand),
+                span,
+            })
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expression, Diagnostic> {
+        let base = self.primary()?;
+        if self.peek() == &TokenKind::Caret {
+            let span = self.next().span;
+            let exponent = self.nested(Self::unary)?;
+            Ok(binary(BinaryOperator::Power, base, exponent, span))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, Diagnostic> {
+        match self.peek().clone() {
+            TokenKind::Number(value) => {
+                let span = self.next().span;
+                Ok(Expression::Number { value, span })
+            }
+            TokenKind::Identifier(name) => {
+                let span = self.next().span;
+                if self.peek() != &TokenKind::LeftParen {
+                    return Err(self.unexpected(&["'('"]));
+                }
+                let open = self.next().span;
+                let arguments = self.nested(|p| p.arguments(open))?;
+                Ok(Expression::Call {
+                    name,
+                    arguments,
+                    span,
+                })
+            }
+            TokenKind::LeftParen => {
+                let open = self.next().span;
+                let expression = self.nested(Self::expression)?;
+                self.close_paren(open, &["')'"])?;
+                Ok(expression)
+            }
+            _ => Err(self.unexpected(EXPECTED_OPERAND)),
+        }
+    }
+
+    fn arguments(&mut self, open: Span) -> Result<Vec<Expression>, Diagnostic> {
+        let mut arguments = vec![];
+        if self.peek() == &TokenKind::RightParen {
+            self.next();
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.expression()?);
+            if self.peek() == &TokenKind::Comma {
+                self.next();
+            } else {
+                self.close_paren(open, &["')'", "','"])?;
+                return Ok(arguments);
+            }
+        }
+    }
+
+    fn close_paren(&mut self, open: Span, expected: &[&str]) -> Result<(), Diagnostic> {
+        match self.peek() {
+            TokenKind::RightParen => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::End => {
+                Err(Diagnostic::error(open, "Unclosed parenthesis").with_expected(expected))
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression, span: Span) -> Expression {
+    Expression::Binary {
+        operator,
+        left: Box::new(left),
+        right: Box::new(right),
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, BinaryOperator, Expression, UnaryOperator};
+    use crate::calculator::{
+        diagnostic::{Diagnostic, DiagnosticCode, Span},
+        tokenizer::tokenize,
+    };
+
+    fn parse_str(expression: &str, max_depth: usize) -> Result<Expression, Diagnostic> {
+        let tokens = tokenize(expression, &mut vec![]).unwrap();
+        parse(&tokens, max_depth)
+    }
+
+    fn number(value: f64, start: usize) -> Box<Expression> {
+        let end = start + value.to_string().len();
+        Box::new(Expression::Number {
+            value,
+            span: Span::new(start, end),
+        })
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let expression = parse_str("1+2*3", 64).unwrap();
+        assert_eq!(
+            expression,
+            Expression::Binary {
+                operator: BinaryOperator::Add,
+                left: number(1.0, 0),
+                right: Box::new(Expression::Binary {
+                    operator: BinaryOperator::Multiply,
+                    left: number(2.0, 2),
+                    right: number(3.0, 4),
+                    span: Span::new(3, 4),
+                }),
+                span: Span::new(1, 2),
+            }
+        );
+        assert_eq!(expression.full_span(), Span::new(0, 5));
+    }
+
+    #[test]
+    fn power_binds_tighter_than_unary_minus() {
+        let expression = parse_str("-2^2", 64).unwrap();
+        assert_eq!(
+            expression,
+            Expression::Unary {
+                operator: UnaryOperator::Negate,
+                operand: Box::new(Expression::Binary {
+                    operator: BinaryOperator::Power,
+                    left: number(2.0, 1),
+                    right: number(2.0, 3),
+                    span: Span::new(2, 3),
+                }),
+                span: Span::new(0, 1),
+            }
+        );
+    }
+
+    #[test]
+    fn function_call_arguments() {
+        let expression = parse_str("max(1, (2))", 64).unwrap();
+        assert_eq!(
+            expression,
+            Expression::Call {
+                name: "max".to_string(),
+                arguments: vec![*number(1.0, 4), *number(2.0, 8)],
+                span: Span::new(0, 3),
+            }
+        );
+        assert!(matches!(
+            parse_str("pi()", 64).unwrap(),
+            Expression::Call { arguments, .. } if arguments.is_empty()
+        ));
+    }
+
+    #[test]
+    fn unexpected_token_position_and_expected_tokens() {
+        let error = parse_str("1 + * 2", 64).unwrap_err();
+        assert_eq!(error.span, Span::new(4, 5));
+        assert_eq!(error.message, "Unexpected '*'");
+        assert_eq!(
+            error.expected,
+            vec!["number", "function name", "'('", "'-'"]
+        );
+
+        let error = parse_str("1 2", 64).unwrap_err();
+        assert_eq!(error.span, Span::new(2, 3));
+        assert_eq!(error.message, "Unexpected number");
+    }
+
+    #[test]
+    fn parenthesis_errors() {
+        let error = parse_str("(1 + 2", 64).unwrap_err();
+        assert_eq!(error.span, Span::new(0, 1));
+        assert_eq!(error.message, "Unclosed parenthesis");
+
+        let error = parse_str("1 + 2)", 64).unwrap_err();
+        assert_eq!(error.span, Span::new(5, 6));
+        assert_eq!(error.message, "Unmatched closing parenthesis");
+
+        let error = parse_str("sqrt 4", 64).unwrap_err();
+        assert_eq!(error.span, Span::new(5, 6));
+        assert_eq!(error.expected, vec!["'('"]);
+    }
+
+    #[test]
+    fn missing_operand_at_end() {
+        let error = parse_str("1 +", 64).unwrap_err();
+        assert_eq!(error.span, Span::new(3, 3));
+        assert_eq!(error.message, "Unexpected end of expression");
+    }
+}
@@ -0,0 +1,238 @@
+use crate::i18n::Message;
+
+use super::diagnostic::{Diagnostic, Span};
+
+/// Numbers with more significant digits than this can not be represented
+/// exactly with f64.
+const MAX_EXACT_SIGNIFICANT_DIGITS: usize = 15;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number(f64),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LeftParen,
+    RightParen,
+    Comma,
+    End,
+}
+
+impl TokenKind {
+    /// Token name for diagnostics.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Number(_) => "number".to_string(),
+            Self::Identifier(name) => format!("'{}'", name),
+            Self::Plus => "'+'".to_string(),
+            Self::Minus => "'-'".to_string(),
+            Self::Star => "'*'".to_string(),
+            Self::Slash => "'/'".to_string(),
+            Self::Caret => "'^'".to_string(),
+            Self::LeftParen => "'('".to_string(),
+            Self::RightParen => "')'".to_string(),
+            Self::Comma => "','".to_string(),
+            Self::End => "end of expression".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Split expression to tokens. Last token is always `TokenKind::End`.
+///
+/// Warnings are added to `warnings`.
+pub fn tokenize(
+    expression: &str,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Vec<Token>, Vec<Diagnostic>> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        let kind = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '^' => TokenKind::Caret,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            ',' => TokenKind::Comma,
+            c if c.is_ascii_digit() || c == '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let span = Span::new(start, i);
+                match number(&text, span, warnings) {
+                    Ok(value) => tokens.push(Token {
+                        kind: TokenKind::Number(value),
+                        span,
+                    }),
+                    Err(e) => errors.push(e),
+                }
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Identifier(chars[start..i].iter().collect()),
+                    span: Span::new(start, i),
+                });
+                continue;
+            }
+            c => {
+                errors.push(Diagnostic::error(
+                    Span::new(start, start + 1),
+                    Message::new("Unexpected character '{0}'").arg(c),
+                ));
+                i += 1;
+                continue;
+            }
+        };
+
+        i += 1;
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, i),
+        });
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        span: Span::new(chars.len(), chars.len()),
+    });
+
+    Ok(tokens)
+}
+
+fn number(text: &str, span: Span, warnings: &mut Vec<Diagnostic>) -> Result<f64, Diagnostic> {
+    let value: f64 = text
+        .parse()
+        .map_err(|_| Diagnostic::error(span, Message::new("Invalid number '{0}'").arg(text)))?;
+
+    if !value.is_finite() {
+        return Err(Diagnostic::error(span, "Number is too large"));
+    }
+
+    let significant_digits = text
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .skip_while(|c| *c == '0')
+        .count();
+    if significant_digits > MAX_EXACT_SIGNIFICANT_DIGITS {
+        warnings.push(Diagnostic::warning(
+            span,
+            "Number has too many digits to be represented exactly; precision is lost",
+        ));
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Token, TokenKind};
+    use crate::calculator::diagnostic::{Severity, Span};
+
+    fn kinds(tokens: &[Token]) -> Vec<TokenKind> {
+        tokens.iter().map(|t| t.kind.clone()).collect()
+    }
+
+    #[test]
+    fn tokens_have_character_spans() {
+        let mut warnings = vec![];
+        let tokens = tokenize("sqrt(2.5) * -x_1", &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            kinds(&tokens),
+            vec![
+                TokenKind::Identifier("sqrt".to_string()),
+                TokenKind::LeftParen,
+                TokenKind::Number(2.5),
+                TokenKind::RightParen,
+                TokenKind::Star,
+                TokenKind::Minus,
+                TokenKind::Identifier("x_1".to_string()),
+                TokenKind::End,
+            ]
+        );
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 4),
+                Span::new(4, 5),
+                Span::new(5, 8),
+                Span::new(8, 9),
+                Span::new(10, 11),
+                Span::new(12, 13),
+                Span::new(13, 16),
+                Span::new(16, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_are_character_indexes() {
+        let mut warnings = vec![];
+        let tokens = tokenize("π + 1", &mut warnings).unwrap();
+        assert_eq!(tokens[1].span, Span::new(2, 3));
+        assert_eq!(tokens[3].span, Span::new(5, 5));
+    }
+
+    #[test]
+    fn all_unexpected_characters_are_reported() {
+        let mut warnings = vec![];
+        let errors = tokenize("1 # 2 $", &mut warnings).unwrap_err();
+        let spans: Vec<Span> = errors.iter().map(|e| e.span).collect();
+        assert_eq!(spans, vec![Span::new(2, 3), Span::new(6, 7)]);
+        assert_eq!(errors[0].message, "Unexpected character '#'");
+    }
+
+    #[test]
+    fn invalid_number_is_error() {
+        let mut warnings = vec![];
+        let errors = tokenize("1 + 1.2.3", &mut warnings).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, Span::new(4, 9));
+        assert_eq!(errors[0].message, "Invalid number '1.2.3'");
+    }
+
+    #[test]
+    fn number_with_too_many_digits_is_warning() {
+        let mut warnings = vec![];
+        let tokens = tokenize("1234567890.1234567", &mut warnings).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+        assert_eq!(warnings[0].span, Span::new(0, 18));
+
+        let mut warnings = vec![];
+        tokenize("0.000000000000001", &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+    }
+}
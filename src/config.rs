@@ -5,22 +5,47 @@ use std::{
     io::BufReader,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
     vec,
 };
 
 use error_stack::{IntoReport, Result, ResultExt};
+use http::HeaderValue;
 use reqwest::Url;
-use rustls_pemfile::{certs, rsa_private_keys};
-use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use tokio_rustls::rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use tracing_subscriber::EnvFilter;
 
-use crate::utils::IntoReportExt;
+use crate::{
+    api::account::data::AccountSetupField,
+    calculator::{EvaluationLimits, OperationSet},
+    server::{
+        clock::{Clock, MockClock, SystemClock},
+        database::disk::DiskSpaceLimits,
+    },
+    utils::IntoReportExt,
+};
 
 use self::{
-    args::TestMode,
-    file::{Components, ConfigFile, ExternalServices, SignInWithGoogleConfig, SocketConfig},
+    args::{
+        ArgsConfig, MigrateMode, OpenApiCommand, RestoreBackup, StaleAccountCleanupCommand,
+        TestMode,
+    },
+    file::{
+        AnalyticsConfig, AnalyticsSinkType, ApiKeyCacheConfig, BackupConfig, Components,
+        ConfigFile, DatabaseBackend, ExternalServices, HotStandbyConfig, HotStandbyMode,
+        LatencyBudgetConfig, Limits, LoggingConfig, RateLimitConfig, ReplicationConfig,
+        SignInWithAppleConfig, SignInWithGoogleConfig, SocketConfig, TelemetryConfig, TlsConfig,
+        TracingSamplingConfig, WebSocketConfig,
+    },
 };
 
-pub const DATABASE_MESSAGE_CHANNEL_BUFFER: usize = 32;
+pub const DEFAULT_DELETION_GRACE_PERIOD_DAYS: u32 = 30;
+pub const DEFAULT_STALE_ACCOUNT_DAYS: u32 = 365;
+pub const DEFAULT_REQUIRED_SETUP_FIELDS: &[AccountSetupField] = &[AccountSetupField::Email];
+pub const DEFAULT_HTTP_SESSION_ACCESS_TOKEN_TTL_SECONDS: i64 = 15 * 60;
 
 #[derive(thiserror::Error, Debug)]
 pub enum GetConfigError {
@@ -44,6 +69,48 @@ pub enum GetConfigError {
     TlsConfigMissing,
     #[error("TLS config creation error")]
     CreateTlsConfig,
+    #[error("Internal API client creation error")]
+    CreateInternalApiClient,
+
+    #[error("Hot standby primary mode requires standby internal API URL")]
+    HotStandbyUrlMissing,
+    #[error("Replication requires either dir or s3 but not both")]
+    ReplicationDestination,
+    #[error("PostgreSQL backend requires database URL")]
+    PostgresUrlMissing,
+    #[error("Feature is not supported with PostgreSQL backend")]
+    PostgresUnsupported,
+
+    #[error("Invalid API base path")]
+    InvalidApiBasePath,
+    #[error("Invalid CORS origin")]
+    InvalidCorsOrigin,
+    #[error("Reading webhook secret failed")]
+    WebhookSecret,
+    #[error("Analytics sink config is incomplete")]
+    AnalyticsConfig,
+    #[error("Tracing sampling rate must be from 0.0 to 1.0")]
+    InvalidSamplingRate,
+    #[error("Limit value must be greater than zero")]
+    InvalidLimit,
+    #[error("Rate limit burst and requests per second must be greater than zero")]
+    InvalidRateLimit,
+    #[error("Invalid log level")]
+    InvalidLogLevel,
+    #[error("Region must be 1-64 characters from a-z, 0-9, '-' and '_'")]
+    InvalidRegion,
+    #[error("Loading test config file failed")]
+    LoadTestConfig,
+    #[error("Loading bot script failed")]
+    LoadBotScript,
+    #[error("Invalid test TLS configuration")]
+    InvalidTestTls,
+    #[error("Isolated QA tests require test server instances")]
+    InvalidTestQaIsolated,
+    #[error("Write ratio must be from 0 to 100")]
+    InvalidTestWriteRatio,
+    #[error("Mock clock requires debug mode")]
+    MockClockWithoutDebug,
 }
 
 #[derive(Debug)]
@@ -55,13 +122,29 @@ pub struct Config {
     external_services: ExternalServices,
     client_api_urls: InternalApiUrls,
     sign_in_with_urls: SignInWithUrls,
+    api_base_path: Option<String>,
+    cors_allowed_origins: Vec<HeaderValue>,
+    webhooks: Vec<WebhookTarget>,
 
     // Other configs
     test_mode: Option<TestMode>,
+    restore_backup: Option<RestoreBackup>,
+    migrate: Option<MigrateMode>,
+    openapi: Option<OpenApiCommand>,
+    check_config: bool,
+    replay: bool,
+    stale_account_cleanup: Option<StaleAccountCleanupCommand>,
+
+    // Time
+    clock: Arc<dyn Clock>,
+    /// Same as `clock` if mock clock is enabled.
+    mock_clock: Option<Arc<MockClock>>,
 
     // TLS
     public_api_tls_config: Option<Arc<ServerConfig>>,
     internal_api_tls_config: Option<Arc<ServerConfig>>,
+    /// Uses the internal API client certificate if it is configured.
+    internal_api_http_client: reqwest::Client,
 }
 
 impl Config {
@@ -69,6 +152,28 @@ impl Config {
         &self.database
     }
 
+    pub fn database_backend(&self) -> DatabaseBackend {
+        self.file.database.backend.unwrap_or_default()
+    }
+
+    /// PostgreSQL connection URL. Always exists when the backend is
+    /// PostgreSQL.
+    pub fn postgres_url(&self) -> Option<&str> {
+        self.file.database.url.as_deref()
+    }
+
+    pub fn disk_space_limits(&self) -> DiskSpaceLimits {
+        DiskSpaceLimits {
+            warning_mib: self.file.limits.disk_space_warning_mib,
+            minimum_mib: self.file.limits.disk_space_minimum_mib,
+        }
+    }
+
+    /// Buffer sizes and other tunables.
+    pub fn limits(&self) -> &Limits {
+        &self.file.limits
+    }
+
     pub fn components(&self) -> &Components {
         &self.file.components
     }
@@ -87,6 +192,190 @@ impl Config {
         self.file.debug.unwrap_or(false)
     }
 
+    /// API requests must come from the same IP address as the current
+    /// WebSocket connection.
+    pub fn bind_access_token_to_ip(&self) -> bool {
+        self.file.bind_access_token_to_ip.unwrap_or(true)
+    }
+
+    /// Time which in-flight requests have for completing when the server
+    /// quits.
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.file.limits.shutdown_timeout_seconds)
+    }
+
+    /// Time between account deletion request and removing the account data.
+    pub fn deletion_grace_period_seconds(&self) -> i64 {
+        let days = self
+            .file
+            .account
+            .as_ref()
+            .and_then(|c| c.deletion_grace_period_days)
+            .unwrap_or(DEFAULT_DELETION_GRACE_PERIOD_DAYS);
+        i64::from(days) * 24 * 60 * 60
+    }
+
+    /// Default inactivity period of the stale account cleanup.
+    pub fn stale_account_days(&self) -> u32 {
+        self.file
+            .account
+            .as_ref()
+            .and_then(|c| c.stale_account_days)
+            .unwrap_or(DEFAULT_STALE_ACCOUNT_DAYS)
+    }
+
+    /// New accounts are created in Normal state.
+    pub fn skip_initial_setup(&self) -> bool {
+        self.file
+            .account
+            .as_ref()
+            .and_then(|c| c.skip_initial_setup)
+            .unwrap_or(false)
+    }
+
+    /// Account setup fields which must be set.
+    pub fn required_setup_fields(&self) -> &[AccountSetupField] {
+        self.file
+            .account
+            .as_ref()
+            .and_then(|c| c.required_setup_fields.as_deref())
+            .unwrap_or(DEFAULT_REQUIRED_SETUP_FIELDS)
+    }
+
+    /// Access token lifetime. If None, access tokens do not expire.
+    pub fn access_token_ttl_seconds(&self) -> Option<i64> {
+        self.file
+            .account
+            .as_ref()
+            .and_then(|c| c.access_token_ttl_seconds)
+            .map(i64::from)
+    }
+
+    pub fn http_token_exchange(&self) -> bool {
+        self.file
+            .account
+            .as_ref()
+            .and_then(|c| c.http_token_exchange)
+            .unwrap_or(false)
+    }
+
+    /// Access token lifetime for sessions created with HTTP token exchange.
+    /// Never longer than the normal access token lifetime.
+    pub fn http_session_access_token_ttl_seconds(&self) -> i64 {
+        let ttl = self
+            .file
+            .account
+            .as_ref()
+            .and_then(|c| c.http_session_access_token_ttl_seconds)
+            .map(i64::from)
+            .unwrap_or(DEFAULT_HTTP_SESSION_ACCESS_TOKEN_TTL_SECONDS);
+        match self.access_token_ttl_seconds() {
+            Some(normal_ttl) => ttl.min(normal_ttl),
+            None => ttl,
+        }
+    }
+
+    /// Data residency region for new accounts.
+    pub fn default_region(&self) -> Option<&str> {
+        self.file
+            .account
+            .as_ref()
+            .and_then(|c| c.default_region.as_deref())
+    }
+
+    /// True if registration request can select the region.
+    pub fn is_allowed_region(&self, region: &str) -> bool {
+        self.default_region() == Some(region)
+            || self
+                .file
+                .account
+                .as_ref()
+                .and_then(|c| c.allowed_regions.as_ref())
+                .map(|regions| regions.iter().any(|r| r == region))
+                .unwrap_or(false)
+    }
+
+    pub fn max_schedules_per_account(&self) -> i64 {
+        self.file.limits.max_schedules_per_account as i64
+    }
+
+    pub fn max_slots_per_account(&self) -> i64 {
+        self.file.limits.max_slots_per_account as i64
+    }
+
+    /// Enabled calculator operation sets.
+    pub fn operation_sets(&self) -> &[OperationSet] {
+        self.file
+            .calculator
+            .as_ref()
+            .and_then(|c| c.operation_sets.as_deref())
+            .unwrap_or(OperationSet::ALL)
+    }
+
+    /// Limits for parsing and evaluating calculator expressions.
+    pub fn evaluation_limits(&self) -> EvaluationLimits {
+        let mut limits = EvaluationLimits::default();
+        if let Some(c) = &self.file.calculator {
+            if let Some(value) = c.max_expression_length {
+                limits.max_expression_length = value;
+            }
+            if let Some(value) = c.max_expression_depth {
+                limits.max_depth = value;
+            }
+            if let Some(value) = c.max_evaluation_steps {
+                limits.max_steps = value;
+            }
+            if let Some(value) = c.max_evaluation_time_milliseconds {
+                limits.max_time = Duration::from_millis(value);
+            }
+        }
+        limits
+    }
+
+    /// Capacity of evaluation result cache. None if cache is disabled.
+    pub fn evaluation_cache_capacity(&self) -> Option<usize> {
+        let calculator = self.file.calculator.as_ref();
+        if calculator.and_then(|c| c.evaluation_cache).unwrap_or(true) {
+            Some(self.file.limits.evaluation_cache_capacity)
+        } else {
+            None
+        }
+    }
+
+    /// Default calculator history retention. None if history entries are
+    /// kept.
+    pub fn history_retention_days(&self) -> Option<i64> {
+        self.file
+            .calculator
+            .as_ref()
+            .and_then(|c| c.history_retention_days)
+            .map(i64::from)
+    }
+
+    /// Minimum time between calculator state broadcasts of one account.
+    pub fn calculator_broadcast_min_interval(&self) -> Duration {
+        Duration::from_millis(self.file.limits.broadcast_min_interval_milliseconds)
+    }
+
+    pub fn geoip_database(&self) -> Option<&Path> {
+        self.file.geoip_database.as_deref()
+    }
+
+    /// Normalized path prefix for all routes. Starts with '/' and does not
+    /// end with '/'. None if routes are not prefixed.
+    pub fn api_base_path(&self) -> Option<&str> {
+        self.api_base_path.as_deref()
+    }
+
+    pub fn cors_allowed_origins(&self) -> &[HeaderValue] {
+        &self.cors_allowed_origins
+    }
+
+    /// Account lifecycle event webhooks.
+    pub fn webhooks(&self) -> &[WebhookTarget] {
+        &self.webhooks
+    }
+
     pub fn external_services(&self) -> &ExternalServices {
         &self.external_services
     }
@@ -95,6 +384,11 @@ impl Config {
         &self.client_api_urls
     }
 
+    /// None if the access token cache uses the default settings.
+    pub fn api_key_cache(&self) -> Option<&ApiKeyCacheConfig> {
+        self.file.api_key_cache.as_ref()
+    }
+
     pub fn sign_in_with_urls(&self) -> &SignInWithUrls {
         &self.sign_in_with_urls
     }
@@ -103,11 +397,59 @@ impl Config {
         self.file.sign_in_with_google.as_ref()
     }
 
+    pub fn sign_in_with_apple_config(&self) -> Option<&SignInWithAppleConfig> {
+        self.file.sign_in_with_apple.as_ref()
+    }
+
     /// Launch testing and benchmark mode instead of the server mode.
     pub fn test_mode(&self) -> Option<TestMode> {
         self.test_mode.clone()
     }
 
+    pub fn restore_backup(&self) -> Option<&RestoreBackup> {
+        self.restore_backup.as_ref()
+    }
+
+    pub fn migrate(&self) -> Option<MigrateMode> {
+        self.migrate
+    }
+
+    pub fn openapi(&self) -> Option<OpenApiCommand> {
+        self.openapi
+    }
+
+    /// Only validate the config.
+    pub fn check_config(&self) -> bool {
+        self.check_config
+    }
+
+    /// Rebuild the current database from shipped WAL segments before
+    /// starting the server.
+    pub fn replay(&self) -> bool {
+        self.replay
+    }
+
+    /// Time source for token expiration, retention and scheduling.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// Current time from the config's clock.
+    pub fn unix_time(&self) -> i64 {
+        self.clock.unix_time()
+    }
+
+    /// Available if the config enables the mock clock.
+    pub fn mock_clock(&self) -> Option<&Arc<MockClock>> {
+        self.mock_clock.as_ref()
+    }
+
+    /// Run stale account cleanup using the internal API of a running
+    /// server instead of the server mode.
+    pub fn stale_account_cleanup(&self) -> Option<&StaleAccountCleanupCommand> {
+        self.stale_account_cleanup.as_ref()
+    }
+
     pub fn public_api_tls_config(&self) -> Option<&Arc<ServerConfig>> {
         self.public_api_tls_config.as_ref()
     }
@@ -115,14 +457,146 @@ impl Config {
     pub fn internal_api_tls_config(&self) -> Option<&Arc<ServerConfig>> {
         self.internal_api_tls_config.as_ref()
     }
+
+    /// HTTP client for requests to internal APIs of other servers.
+    pub fn internal_api_http_client(&self) -> &reqwest::Client {
+        &self.internal_api_http_client
+    }
+
+    /// None if analytics is disabled.
+    pub fn analytics(&self) -> Option<&AnalyticsConfig> {
+        self.file.analytics.as_ref()
+    }
+
+    /// None if all requests are traced in debug mode and none in production.
+    pub fn tracing_sampling(&self) -> Option<&TracingSamplingConfig> {
+        self.file.tracing_sampling.as_ref()
+    }
+
+    /// None if latency budget warnings are disabled.
+    pub fn latency_budget(&self) -> Option<&LatencyBudgetConfig> {
+        self.file.latency_budget.as_ref()
+    }
+
+    /// None if logging settings are not configured.
+    pub fn logging(&self) -> Option<&LoggingConfig> {
+        self.file.logging.as_ref()
+    }
+
+    /// None if WebSocket client versions are not configured.
+    pub fn websocket(&self) -> Option<&WebSocketConfig> {
+        self.file.websocket.as_ref()
+    }
+
+    /// None if WebSocket pings are disabled.
+    pub fn websocket_ping_interval(&self) -> Option<Duration> {
+        let seconds = self.file.limits.websocket_ping_interval_seconds;
+        if seconds == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(seconds))
+        }
+    }
+
+    pub fn websocket_max_missed_pongs(&self) -> u32 {
+        self.file.limits.websocket_max_missed_pongs
+    }
+
+    /// None if rate limiting is disabled.
+    pub fn rate_limit(&self) -> Option<&RateLimitConfig> {
+        self.file.rate_limit.as_ref()
+    }
+
+    /// None if telemetry is disabled.
+    pub fn telemetry(&self) -> Option<&TelemetryConfig> {
+        self.file.telemetry.as_ref()
+    }
+
+    pub fn backup(&self) -> Option<&BackupConfig> {
+        self.file.backup.as_ref()
+    }
+
+    pub fn replication(&self) -> Option<&ReplicationConfig> {
+        self.file.replication.as_ref()
+    }
+
+    pub fn hot_standby(&self) -> Option<&HotStandbyConfig> {
+        self.file.hot_standby.as_ref()
+    }
+
+    /// Server accepts database snapshots from the primary server.
+    pub fn hot_standby_receiver(&self) -> bool {
+        self.hot_standby()
+            .map(|c| c.mode == HotStandbyMode::Standby)
+            .unwrap_or(false)
+    }
 }
 
 pub fn get_config() -> Result<Config, GetConfigError> {
+    // Parse arguments first, so that the completions command does not
+    // require config file.
+    let args_config = args::get_config()?;
+    let current_dir = std::env::current_dir().into_error(GetConfigError::GetWorkingDir)?;
+    let file_config =
+        file::ConfigFile::load(current_dir).change_context(GetConfigError::LoadFileError)?;
+
+    create_config(args_config, file_config)
+}
+
+/// Load the config file again. Command line argument values and the
+/// database directory are the same as in the current config.
+pub fn reload_config(current: &Config) -> Result<Config, GetConfigError> {
+    let args_config = ArgsConfig {
+        database_dir: Some(current.database.clone()),
+        test_mode: current.test_mode.clone(),
+        restore_backup: current.restore_backup.clone(),
+        migrate: current.migrate,
+        openapi: current.openapi,
+        check_config: current.check_config,
+        replay: current.replay,
+        stale_account_cleanup: current.stale_account_cleanup.clone(),
+    };
     let current_dir = std::env::current_dir().into_error(GetConfigError::GetWorkingDir)?;
-    let mut file_config =
+    let file_config =
         file::ConfigFile::load(current_dir).change_context(GetConfigError::LoadFileError)?;
-    let args_config = args::get_config();
 
+    // Time must not jump when the config is reloaded.
+    let mut config = create_config(args_config, file_config)?;
+    config.clock = current.clock.clone();
+    config.mock_clock = current.mock_clock.clone();
+    Ok(config)
+}
+
+/// Config for in-process tests. The default config file is used with debug
+/// mode and `modify` changes. The config file is not read from the working
+/// directory.
+#[cfg(test)]
+pub fn test_config(
+    database_dir: PathBuf,
+    modify: impl FnOnce(&mut ConfigFile),
+) -> Result<Config, GetConfigError> {
+    let mut file_config: ConfigFile =
+        toml::from_str(file::DEFAULT_CONFIG_FILE_TEXT).into_error(GetConfigError::LoadFileError)?;
+    file_config.debug = Some(true);
+    modify(&mut file_config);
+
+    let args_config = ArgsConfig {
+        database_dir: Some(database_dir),
+        test_mode: None,
+        restore_backup: None,
+        migrate: None,
+        openapi: None,
+        check_config: false,
+        replay: false,
+        stale_account_cleanup: None,
+    };
+    create_config(args_config, file_config)
+}
+
+fn create_config(
+    args_config: ArgsConfig,
+    mut file_config: ConfigFile,
+) -> Result<Config, GetConfigError> {
     let database = if let Some(database) = args_config.database_dir {
         database
     } else {
@@ -137,6 +611,7 @@ pub fn get_config() -> Result<Config, GetConfigError> {
         Some(tls_config) => Some(Arc::new(generate_server_config(
             tls_config.public_api_key.as_path(),
             tls_config.public_api_cert.as_path(),
+            None,
         )?)),
         None => None,
     };
@@ -145,28 +620,221 @@ pub fn get_config() -> Result<Config, GetConfigError> {
         Some(tls_config) => Some(Arc::new(generate_server_config(
             tls_config.internal_api_key.as_path(),
             tls_config.internal_api_cert.as_path(),
+            tls_config.internal_api_ca_cert.as_deref(),
         )?)),
         None => None,
     };
 
+    let internal_api_http_client = create_internal_api_http_client(file_config.tls.as_ref())?;
+
     if public_api_tls_config.is_none() && !file_config.debug.unwrap_or_default() {
         return Err(GetConfigError::TlsConfigMissing)
             .into_report()
             .attach_printable("TLS must be configured when debug mode is false");
     }
 
+    if let Some(HotStandbyConfig {
+        mode: HotStandbyMode::Primary,
+        standby_internal_api: None,
+        ..
+    }) = &file_config.hot_standby
+    {
+        return Err(GetConfigError::HotStandbyUrlMissing).into_report();
+    }
+
+    if let Some(replication) = &file_config.replication {
+        if replication.dir.is_some() == replication.s3.is_some() {
+            return Err(GetConfigError::ReplicationDestination).into_report();
+        }
+    }
+
+    if file_config.database.backend == Some(DatabaseBackend::Postgres) {
+        if file_config.database.url.is_none() {
+            return Err(GetConfigError::PostgresUrlMissing).into_report();
+        }
+
+        let unsupported = [
+            ("backup", file_config.backup.is_some()),
+            ("replication", file_config.replication.is_some()),
+            ("hot_standby", file_config.hot_standby.is_some()),
+            ("--replay", args_config.replay),
+            ("backup restore", args_config.restore_backup.is_some()),
+            ("migrate", args_config.migrate.is_some()),
+        ];
+        for (name, enabled) in unsupported {
+            if enabled {
+                return Err(GetConfigError::PostgresUnsupported)
+                    .into_report()
+                    .attach_printable(format!("Unsupported feature {name}"));
+            }
+        }
+    }
+
+    if let Some(analytics) = &file_config.analytics {
+        let missing = match analytics.sink {
+            AnalyticsSinkType::Log => None,
+            AnalyticsSinkType::File => analytics.file.is_none().then_some("file"),
+            AnalyticsSinkType::Http => analytics.url.is_none().then_some("url"),
+        };
+        if let Some(missing) = missing {
+            return Err(GetConfigError::AnalyticsConfig)
+                .into_report()
+                .attach_printable(format!("Analytics config value '{missing}' is missing"));
+        }
+    }
+
+    if let Err(name) = file_config.limits.validate() {
+        return Err(GetConfigError::InvalidLimit)
+            .into_report()
+            .attach_printable(format!("Invalid limit {name}"));
+    }
+
+    if let Some(sampling) = &file_config.tracing_sampling {
+        let rates = std::iter::once(("default_rate", &sampling.default_rate)).chain(
+            sampling
+                .route_class_rates
+                .iter()
+                .map(|(class, rate)| (class.as_str(), rate)),
+        );
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(rate) {
+                return Err(GetConfigError::InvalidSamplingRate)
+                    .into_report()
+                    .attach_printable(format!("Invalid sampling rate {rate} for {name}"));
+            }
+        }
+    }
+
+    if let Some(level) = file_config
+        .logging
+        .as_ref()
+        .and_then(|logging| logging.level.as_ref())
+    {
+        if let Err(e) = EnvFilter::try_new(level) {
+            return Err(GetConfigError::InvalidLogLevel)
+                .into_report()
+                .attach_printable(format!("Invalid log level {level}: {e}"));
+        }
+    }
+
+    if let Some(account) = &file_config.account {
+        let regions = account
+            .default_region
+            .iter()
+            .chain(account.allowed_regions.iter().flatten());
+        for region in regions {
+            if !is_valid_region(region) {
+                return Err(GetConfigError::InvalidRegion)
+                    .into_report()
+                    .attach_printable(format!("Invalid region {region}"));
+            }
+        }
+    }
+
+    if let Some(rate_limit) = &file_config.rate_limit {
+        for (name, bucket) in [
+            ("access_token", rate_limit.access_token),
+            ("ip", rate_limit.ip),
+        ] {
+            if bucket.burst == 0
+                || !bucket.requests_per_second.is_finite()
+                || bucket.requests_per_second <= 0.0
+            {
+                return Err(GetConfigError::InvalidRateLimit)
+                    .into_report()
+                    .attach_printable(format!("Invalid rate limit {name}"));
+            }
+        }
+    }
+
+    let mock_clock = if file_config.mock_clock.unwrap_or(false) {
+        if !file_config.debug.unwrap_or_default() {
+            return Err(GetConfigError::MockClockWithoutDebug).into_report();
+        }
+        Some(Arc::new(MockClock::default()))
+    } else {
+        None
+    };
+    let clock: Arc<dyn Clock> = match &mock_clock {
+        Some(mock_clock) => mock_clock.clone(),
+        None => Arc::new(SystemClock),
+    };
+
+    let api_base_path = match file_config.api_base_path.as_deref() {
+        Some(path) => normalize_api_base_path(path)?,
+        None => None,
+    };
+
+    let cors_allowed_origins = file_config
+        .cors_allowed_origins
+        .iter()
+        .flatten()
+        .map(|origin| {
+            HeaderValue::from_str(origin.trim_end_matches('/'))
+                .into_error_with_info_lazy(GetConfigError::InvalidCorsOrigin, || origin.clone())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let webhooks = file_config
+        .webhooks
+        .iter()
+        .flatten()
+        .map(|webhook| {
+            let secret = std::fs::read_to_string(&webhook.secret_file)
+                .into_error_with_info_lazy(GetConfigError::WebhookSecret, || {
+                    webhook.secret_file.display().to_string()
+                })?;
+            Ok(WebhookTarget {
+                url: webhook.url.clone(),
+                secret: secret.trim().as_bytes().to_vec(),
+                max_attempts: webhook
+                    .max_attempts
+                    .unwrap_or(file_config.limits.webhook_max_attempts)
+                    .max(1),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     Ok(Config {
         file: file_config,
         database,
         external_services,
         client_api_urls,
+        api_base_path,
+        cors_allowed_origins,
+        webhooks,
         test_mode: args_config.test_mode,
+        restore_backup: args_config.restore_backup,
+        migrate: args_config.migrate,
+        openapi: args_config.openapi,
+        check_config: args_config.check_config,
+        replay: args_config.replay,
+        stale_account_cleanup: args_config.stale_account_cleanup,
+        clock,
+        mock_clock,
         sign_in_with_urls: SignInWithUrls::new()?,
         public_api_tls_config,
         internal_api_tls_config,
+        internal_api_http_client,
     })
 }
 
+#[derive(Clone)]
+pub struct WebhookTarget {
+    pub url: Url,
+    pub secret: Vec<u8>,
+    pub max_attempts: u32,
+}
+
+impl std::fmt::Debug for WebhookTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookTarget")
+            .field("url", &self.url)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InternalApiUrls {
     pub account_base_url: Option<Url>,
@@ -198,12 +866,44 @@ pub fn create_client_api_urls(
     })
 }
 
+fn is_valid_region(region: &str) -> bool {
+    (1..=64).contains(&region.len())
+        && region
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Returns None if `path` does not add a prefix (empty or "/").
+fn normalize_api_base_path(path: &str) -> Result<Option<String>, GetConfigError> {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        return Ok(None);
+    }
+
+    let valid = path.split('/').all(|segment| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+    });
+    if !valid {
+        return Err(GetConfigError::InvalidApiBasePath)
+            .into_report()
+            .attach_printable(format!("Invalid path: {path}"));
+    }
+
+    Ok(Some(format!("/{path}")))
+}
+
 const GOOGLE_PUBLIC_KEY_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+const APPLE_PUBLIC_KEY_URL: &str = "https://appleid.apple.com/auth/keys";
 
 #[derive(Debug, Clone)]
 pub struct SignInWithUrls {
     /// Request to this should return JwkSet.
     pub google_public_keys: Url,
+    /// Request to this should return JwkSet.
+    pub apple_public_keys: Url,
 }
 
 impl SignInWithUrls {
@@ -211,17 +911,25 @@ impl SignInWithUrls {
         Ok(Self {
             google_public_keys: Url::parse(GOOGLE_PUBLIC_KEY_URL)
                 .into_error(GetConfigError::ConstUrlParsingFailed)?,
+            apple_public_keys: Url::parse(APPLE_PUBLIC_KEY_URL)
+                .into_error(GetConfigError::ConstUrlParsingFailed)?,
         })
     }
 }
 
+/// Client certificates are required if `client_ca_cert_path` is set.
 fn generate_server_config(
     key_path: &Path,
     cert_path: &Path,
+    client_ca_cert_path: Option<&Path>,
 ) -> Result<ServerConfig, GetConfigError> {
-    let mut key_reader =
-        BufReader::new(std::fs::File::open(key_path).into_error(GetConfigError::CreateTlsConfig)?);
-    let all_keys = rsa_private_keys(&mut key_reader).into_error(GetConfigError::CreateTlsConfig)?;
+    let key_file = std::fs::read(key_path).into_error(GetConfigError::CreateTlsConfig)?;
+    let mut all_keys =
+        rsa_private_keys(&mut key_file.as_slice()).into_error(GetConfigError::CreateTlsConfig)?;
+    if all_keys.is_empty() {
+        all_keys = pkcs8_private_keys(&mut key_file.as_slice())
+            .into_error(GetConfigError::CreateTlsConfig)?;
+    }
 
     let key = if let [key] = &all_keys[..] {
         PrivateKey(key.clone())
@@ -250,11 +958,81 @@ fn generate_server_config(
             .attach_printable("Only one cert supported");
     };
 
-    let config = ServerConfig::builder()
-        .with_safe_defaults()
-        .with_no_client_auth() // TODO: configure at some point
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match client_ca_cert_path {
+        Some(ca_cert_path) => {
+            let mut roots = RootCertStore::empty();
+            for ca_cert in load_certs(ca_cert_path)? {
+                roots
+                    .add(&ca_cert)
+                    .into_error(GetConfigError::CreateTlsConfig)?;
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
         .with_single_cert(vec![cert], key)
         .into_error(GetConfigError::CreateTlsConfig)?;
 
     Ok(config)
 }
+
+fn load_certs(cert_path: &Path) -> Result<Vec<Certificate>, GetConfigError> {
+    let mut cert_reader =
+        BufReader::new(std::fs::File::open(cert_path).into_error(GetConfigError::CreateTlsConfig)?);
+    let all_certs = certs(&mut cert_reader).into_error(GetConfigError::CreateTlsConfig)?;
+    if all_certs.is_empty() {
+        return Err(GetConfigError::CreateTlsConfig)
+            .into_report()
+            .attach_printable(format!("No cert found from {}", cert_path.display()));
+    }
+    Ok(all_certs.into_iter().map(Certificate).collect())
+}
+
+/// Client which uses the internal API client certificate and trusts the
+/// internal API CA if those are configured.
+fn create_internal_api_http_client(
+    tls_config: Option<&TlsConfig>,
+) -> Result<reqwest::Client, GetConfigError> {
+    let mut builder = reqwest::Client::builder();
+
+    let (ca_cert, client_cert, client_key) = match tls_config {
+        Some(tls) => (
+            tls.internal_api_ca_cert.as_deref(),
+            tls.internal_api_client_cert.as_deref(),
+            tls.internal_api_client_key.as_deref(),
+        ),
+        None => (None, None, None),
+    };
+
+    if let Some(ca_cert) = ca_cert {
+        let pem = std::fs::read(ca_cert).into_error(GetConfigError::CreateInternalApiClient)?;
+        let ca_cert = reqwest::Certificate::from_pem(&pem)
+            .into_error(GetConfigError::CreateInternalApiClient)?;
+        builder = builder.use_rustls_tls().add_root_certificate(ca_cert);
+    }
+
+    match (client_cert, client_key) {
+        (Some(cert), Some(key)) => {
+            let mut pem =
+                std::fs::read(cert).into_error(GetConfigError::CreateInternalApiClient)?;
+            pem.extend(std::fs::read(key).into_error(GetConfigError::CreateInternalApiClient)?);
+            let identity = reqwest::Identity::from_pem(&pem)
+                .into_error(GetConfigError::CreateInternalApiClient)?;
+            builder = builder.use_rustls_tls().identity(identity);
+        }
+        (None, None) => (),
+        _ => {
+            return Err(GetConfigError::CreateInternalApiClient)
+                .into_report()
+                .attach_printable(
+                    "Both internal_api_client_cert and internal_api_client_key are required",
+                )
+        }
+    }
+
+    builder
+        .build()
+        .into_error(GetConfigError::CreateInternalApiClient)
+}
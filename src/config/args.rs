@@ -1,130 +1,516 @@
 use std::{
-    convert::{TryFrom, TryInto},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
-use clap::{arg, command, value_parser, Command, PossibleValue};
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use error_stack::{IntoReport, Result, ResultExt};
 use reqwest::Url;
+use serde::Deserialize;
 
-use crate::test::client::PublicApiUrls;
+use crate::{
+    api::admin::StaleAccountCleanupRequest,
+    test::{
+        bot_script::BotScript,
+        client::PublicApiUrls,
+        tls::{CertificatePin, TestCertificate},
+    },
+    utils::IntoReportExt,
+};
+
+use super::GetConfigError;
+
+const DEFAULT_URL_REGISTER: &str = "http://127.0.0.1:3001";
+const DEFAULT_URL_ACCOUNT: &str = "http://127.0.0.1:3000";
+const DEFAULT_URL_CALCULATOR: &str = "http://127.0.0.1:3000";
+const DEFAULT_URL_INTERNAL: &str = "http://127.0.0.1:3001";
+const DEFAULT_TEST_DATABASE_DIR: &str = "tmp_databases";
+const DEFAULT_QA_TEST_TIMEOUT_SECONDS: u64 = 180;
+const DEFAULT_WRITE_RATIO: u8 = 50;
 
 // Config given as command line arguments
 pub struct ArgsConfig {
     pub database_dir: Option<PathBuf>,
     pub test_mode: Option<TestMode>,
+    pub restore_backup: Option<RestoreBackup>,
+    pub migrate: Option<MigrateMode>,
+    pub openapi: Option<OpenApiCommand>,
+    pub check_config: bool,
+    pub replay: bool,
+    pub stale_account_cleanup: Option<StaleAccountCleanupCommand>,
 }
 
-pub fn get_config() -> ArgsConfig {
-    let matches = command!()
-        .arg(
-            arg!(--database <DIR> "Set database directory. Overrides config file value.")
-                .required(false)
-                .value_parser(value_parser!(PathBuf)),
-        )
-        .subcommand(
-            Command::new("test")
-                .about("Run tests and benchmarks")
-                .arg(
-                    arg!(--bots <COUNT> "Bot count per task")
-                        .value_parser(value_parser!(u32))
-                        .default_value("1")
-                        .required(false),
-                )
-                .arg(
-                    arg!(--tasks <COUNT> "Task count")
-                        .value_parser(value_parser!(u32))
-                        .default_value("1")
-                        .required(false),
-                )
-                .arg(
-                    arg!(--"url-register" <URL> "Base URL for account API for register and login")
-                        .value_parser(value_parser!(Url))
-                        .default_value("http://127.0.0.1:3001")
-                        .required(false),
-                )
-                .arg(
-                    arg!(--"url-account" <URL> "Base URL for account API")
-                        .value_parser(value_parser!(Url))
-                        .default_value("http://127.0.0.1:3000")
-                        .required(false),
-                )
-                .arg(
-                    arg!(--"url-calculator" <URL> "Base URL for calculator API")
-                        .value_parser(value_parser!(Url))
-                        .default_value("http://127.0.0.1:3000")
-                        .required(false),
-                )
-                .arg(
-                    arg!(--"test-database" <DIR> "Directory for test database")
-                        .value_parser(value_parser!(PathBuf))
-                        .default_value("tmp_databases")
-                        .required(false),
-                )
-                .arg(arg!(--"microservice-calculator" "Start calculator API as microservice"))
-                .arg(arg!(--"no-sleep" "Make bots to make requests constantly"))
-                .arg(arg!(--"no-clean" "Do not remove created database files"))
-                .arg(arg!(--"no-servers" "Do not start new server instances"))
-                .arg(arg!(--"save-state" "Save and load state"))
-                .arg(arg!(--"update-calculator" "Update calculator state continuously"))
-                .arg(arg!(--"print-speed" "Print some speed information"))
-                .arg(arg!(--"log-debug" "Enable debug logging for server instances"))
-                .arg(arg!(--"early-quit" "First error quits"))
-                .arg(
-                    arg!(--"test" <NAME> "Select custom test")
-                        .value_parser(value_parser!(Test))
-                        .required(false)
-                        .default_value(TEST_NAME_QA),
-                )
-                .arg(arg!(--forever "Run tests forever")),
-        )
-        .get_matches();
-
-    let test_mode = match matches.subcommand() {
-        Some(("test", sub_matches)) => {
-            let api_urls = PublicApiUrls::new(
-                sub_matches.get_one::<Url>("url-register").unwrap().clone(),
-                sub_matches.get_one::<Url>("url-account").unwrap().clone(),
-                sub_matches
-                    .get_one::<Url>("url-calculator")
-                    .unwrap()
-                    .clone(),
-            );
-
-            Some(TestMode {
-                bot_count: *sub_matches.get_one::<u32>("bots").unwrap(),
-                task_count: *sub_matches.get_one::<u32>("tasks").unwrap(),
-                forever: sub_matches.is_present("forever"),
-                no_sleep: sub_matches.is_present("no-sleep"),
-                no_clean: sub_matches.is_present("no-clean"),
-                no_servers: sub_matches.is_present("no-servers"),
-                update_calculator_state: sub_matches.is_present("update-calculator"),
-                save_state: sub_matches.is_present("save-state"),
-                print_speed: sub_matches.is_present("print-speed"),
-                early_quit: sub_matches.is_present("early-quit"),
-                test: sub_matches
-                    .get_one::<Test>("test")
-                    .map(ToOwned::to_owned)
-                    .unwrap(),
-                server: ServerConfig {
-                    api_urls,
-                    test_database_dir: sub_matches
-                        .get_one::<PathBuf>("test-database")
-                        .map(ToOwned::to_owned)
-                        .unwrap(),
-                    microservice_calculator: sub_matches.is_present("microservice-calculator"),
-                    log_debug: sub_matches.is_present("log-debug"),
-                },
-            })
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Cli {
+    /// Set database directory. Overrides config file value.
+    #[arg(long, value_name = "DIR", global = true)]
+    database: Option<PathBuf>,
+
+    /// Rebuild the current database from shipped WAL segments before
+    /// starting the server. Requires replication config.
+    #[arg(long)]
+    replay: bool,
+
+    /// Server is started if subcommand is not given.
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum CliCommand {
+    /// Run the server
+    Serve,
+    /// Load and validate the config file
+    CheckConfig,
+    /// Run database migrations. Server must not be running.
+    Migrate(MigrateArgs),
+    /// Database backup commands
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Same as `backup restore`
+    #[command(hide = true)]
+    RestoreBackup(RestoreBackupArgs),
+    /// Print OpenAPI document or check generated api_client
+    Openapi(OpenApiArgs),
+    /// Run tests and benchmarks
+    Test(TestArgs),
+    /// Find accounts which have been inactive longer than the stale account
+    /// period and notify them or schedule their deletion. Uses the internal
+    /// API of a running server.
+    StaleAccounts(StaleAccountsArgs),
+    /// Print shell completion script to stdout
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommand {
+    /// Replace current database with a backup. Server must not be running.
+    Restore(RestoreBackupArgs),
+}
+
+#[derive(Args, Debug)]
+struct RestoreBackupArgs {
+    /// Backup file name. Default is the newest backup.
+    #[arg(long)]
+    name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("mode").args(["dry_run", "apply"]).required(true)))]
+struct MigrateArgs {
+    /// Print pending migrations and schema diff
+    #[arg(long)]
+    dry_run: bool,
+    /// Apply pending migrations
+    #[arg(long)]
+    apply: bool,
+}
+
+#[derive(Args, Debug)]
+struct StaleAccountsArgs {
+    /// Base URL for account server internal API
+    #[arg(long, value_name = "URL", default_value = DEFAULT_URL_INTERNAL)]
+    url_internal: Url,
+    /// Inactivity period. Default is the `stale_account_days` config value.
+    #[arg(long, value_name = "DAYS")]
+    inactive_days: Option<u32>,
+    /// Send the `inactive` webhook event for every stale account
+    #[arg(long)]
+    notify: bool,
+    /// Request deletion of every stale account
+    #[arg(long)]
+    schedule_deletion: bool,
+    /// Accounts handled between progress updates [default: 100]
+    #[arg(long, value_name = "COUNT")]
+    batch_size: Option<u32>,
+}
+
+#[derive(Args, Debug)]
+struct OpenApiArgs {
+    /// Document to print. Client document contains public and internal API.
+    #[arg(long, value_enum, default_value_t = ApiDocType::Client)]
+    doc: ApiDocType,
+    /// Check that api_client matches the internal API document
+    #[arg(long)]
+    check_api_client: bool,
+}
+
+#[derive(Args, Debug)]
+struct TestArgs {
+    /// TOML file with test settings. Keys are the argument names with
+    /// underscores. Command line arguments override file values.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Bot count per task [default: 1]
+    #[arg(long, value_name = "COUNT")]
+    bots: Option<u32>,
+    /// Task count [default: 1]
+    #[arg(long, value_name = "COUNT")]
+    tasks: Option<u32>,
+    /// Base URL for account API for register and login [default: http://127.0.0.1:3001]
+    #[arg(long, value_name = "URL")]
+    url_register: Option<Url>,
+    /// Base URL for account API [default: http://127.0.0.1:3000]
+    #[arg(long, value_name = "URL")]
+    url_account: Option<Url>,
+    /// Base URL for calculator API [default: http://127.0.0.1:3000]
+    #[arg(long, value_name = "URL")]
+    url_calculator: Option<Url>,
+    /// Base URL for account server internal API. Test servers use the port
+    /// of this URL for the internal API. [default: http://127.0.0.1:3001]
+    #[arg(long, value_name = "URL")]
+    url_internal: Option<Url>,
+    /// Directory for test database [default: tmp_databases]
+    #[arg(long, value_name = "DIR")]
+    test_database: Option<PathBuf>,
+    /// Start calculator API as microservice
+    #[arg(long)]
+    microservice_calculator: bool,
+    /// Make bots to make requests constantly
+    #[arg(long)]
+    no_sleep: bool,
+    /// Do not remove created database files
+    #[arg(long)]
+    no_clean: bool,
+    /// Do not start new server instances
+    #[arg(long)]
+    no_servers: bool,
+    /// Save and load state
+    #[arg(long)]
+    save_state: bool,
+    /// Update calculator state continuously
+    #[arg(long)]
+    update_calculator: bool,
+    /// Print some speed information
+    #[arg(long)]
+    print_speed: bool,
+    /// Enable debug logging for server instances
+    #[arg(long)]
+    log_debug: bool,
+    /// First error quits
+    #[arg(long)]
+    early_quit: bool,
+    /// Select custom test [default: qa]
+    #[arg(long, value_name = "NAME", value_enum)]
+    test: Option<Test>,
+    /// Run tests forever
+    #[arg(long)]
+    forever: bool,
+    /// Default timeout for one QA test. Execution time of the test's
+    /// actions is counted. [default: 180]
+    #[arg(long, value_name = "SECONDS")]
+    qa_test_timeout: Option<u64>,
+    /// Run also OpenAPI conformance tests in QA mode. Server must serve the
+    /// OpenAPI document, so debug mode is required.
+    #[arg(long)]
+    qa_openapi: bool,
+    /// Start new server instances for every QA test group, so that
+    /// destructive tests of one group can not affect other groups.
+    #[arg(long)]
+    qa_isolated: bool,
+    /// Timeout for the whole test run. Test run is stopped when the timeout
+    /// is reached. [default: no timeout]
+    #[arg(long, value_name = "SECONDS")]
+    run_timeout: Option<u64>,
+    /// Retry count for idempotent bot requests which fail with connect
+    /// error, timeout or server error [default: 0]
+    #[arg(long, value_name = "COUNT")]
+    request_retries: Option<u32>,
+    /// Use HTTPS and WSS. Test servers use a generated self-signed
+    /// certificate which bots pin.
+    #[arg(long)]
+    tls: bool,
+    /// SHA-256 fingerprint of the server certificate as hex. Overrides the
+    /// generated certificate pin. Without a pin the system root
+    /// certificates are used.
+    #[arg(long, value_name = "SHA256")]
+    certificate_pin: Option<String>,
+    /// TOML or JSON file with bot actions for the `bot` test
+    #[arg(long, value_name = "FILE")]
+    bot_config: Option<PathBuf>,
+    /// Write benchmark results to a file when the test run ends. CSV is
+    /// used if the file extension is `csv` and otherwise JSON. Relative
+    /// path is relative to the test database directory.
+    #[arg(long, value_name = "FILE")]
+    benchmark_output: Option<PathBuf>,
+    /// Percentage of write requests in the `benchmark-mixed` test
+    /// [default: 50]
+    #[arg(long, value_name = "PERCENT")]
+    write_ratio: Option<u8>,
+}
+
+/// Test settings file for the test command. Flags can only be enabled
+/// from the command line, so file value is used if the flag is not given.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestConfigFile {
+    bots: Option<u32>,
+    tasks: Option<u32>,
+    url_register: Option<Url>,
+    url_account: Option<Url>,
+    url_calculator: Option<Url>,
+    url_internal: Option<Url>,
+    test_database: Option<PathBuf>,
+    microservice_calculator: Option<bool>,
+    no_sleep: Option<bool>,
+    no_clean: Option<bool>,
+    no_servers: Option<bool>,
+    save_state: Option<bool>,
+    update_calculator: Option<bool>,
+    print_speed: Option<bool>,
+    log_debug: Option<bool>,
+    early_quit: Option<bool>,
+    test: Option<Test>,
+    forever: Option<bool>,
+    qa_test_timeout: Option<u64>,
+    qa_openapi: Option<bool>,
+    qa_isolated: Option<bool>,
+    run_timeout: Option<u64>,
+    request_retries: Option<u32>,
+    tls: Option<bool>,
+    certificate_pin: Option<String>,
+    bot_config: Option<PathBuf>,
+    benchmark_output: Option<PathBuf>,
+    write_ratio: Option<u8>,
+}
+
+impl TestConfigFile {
+    fn load(file: &Path) -> Result<Self, GetConfigError> {
+        let text = std::fs::read_to_string(file)
+            .into_error(GetConfigError::LoadTestConfig)
+            .attach_printable_lazy(|| file.display().to_string())?;
+        toml::from_str(&text)
+            .into_error(GetConfigError::LoadTestConfig)
+            .attach_printable_lazy(|| file.display().to_string())
+    }
+}
+
+fn default_url(url: &str) -> Result<Url, GetConfigError> {
+    Url::parse(url).into_error(GetConfigError::ConstUrlParsingFailed)
+}
+
+impl TestArgs {
+    fn into_test_mode(self) -> Result<TestMode, GetConfigError> {
+        let file = match &self.config {
+            Some(path) => TestConfigFile::load(path)?,
+            None => TestConfigFile::default(),
+        };
+
+        let flag = |arg: bool, file_value: Option<bool>| arg || file_value.unwrap_or(false);
+
+        let mut url_register = match self.url_register.or(file.url_register) {
+            Some(url) => url,
+            None => default_url(DEFAULT_URL_REGISTER)?,
+        };
+        let mut url_account = match self.url_account.or(file.url_account) {
+            Some(url) => url,
+            None => default_url(DEFAULT_URL_ACCOUNT)?,
+        };
+        let mut url_calculator = match self.url_calculator.or(file.url_calculator) {
+            Some(url) => url,
+            None => default_url(DEFAULT_URL_CALCULATOR)?,
+        };
+        let mut url_internal = match self.url_internal.or(file.url_internal) {
+            Some(url) => url,
+            None => default_url(DEFAULT_URL_INTERNAL)?,
+        };
+
+        let no_servers = flag(self.no_servers, file.no_servers);
+        let qa_isolated = flag(self.qa_isolated, file.qa_isolated);
+        if qa_isolated && no_servers {
+            return Err(GetConfigError::InvalidTestQaIsolated)
+                .into_report()
+                .attach_printable("Option no_servers can not be used with qa_isolated");
         }
-        _ => None,
+        let write_ratio = self
+            .write_ratio
+            .or(file.write_ratio)
+            .unwrap_or(DEFAULT_WRITE_RATIO);
+        if write_ratio > 100 {
+            return Err(GetConfigError::InvalidTestWriteRatio).into_report();
+        }
+        let tls = flag(self.tls, file.tls);
+        let (tls_certificate, certificate_pin) = if tls {
+            for url in [
+                &mut url_register,
+                &mut url_account,
+                &mut url_calculator,
+                &mut url_internal,
+            ] {
+                url.set_scheme("https")
+                    .map_err(|()| GetConfigError::InvalidTestTls)
+                    .into_report()
+                    .attach_printable("Changing URL scheme to https failed")?;
+            }
+
+            let certificate = if no_servers {
+                None
+            } else {
+                Some(TestCertificate::generate())
+            };
+            let pin = match self.certificate_pin.or(file.certificate_pin) {
+                Some(pin) => Some(
+                    CertificatePin::from_hex(&pin)
+                        .ok_or(GetConfigError::InvalidTestTls)
+                        .into_report()?,
+                ),
+                None => certificate.as_ref().map(|c| c.pin.clone()),
+            };
+            (certificate, pin)
+        } else {
+            (None, None)
+        };
+
+        let bot_script = match self.bot_config.or(file.bot_config) {
+            Some(path) => Some(BotScript::load(&path)?),
+            None => None,
+        };
+
+        Ok(TestMode {
+            bot_count: self.bots.or(file.bots).unwrap_or(1),
+            task_count: self.tasks.or(file.tasks).unwrap_or(1),
+            forever: flag(self.forever, file.forever),
+            no_sleep: flag(self.no_sleep, file.no_sleep),
+            no_clean: flag(self.no_clean, file.no_clean),
+            no_servers,
+            update_calculator_state: flag(self.update_calculator, file.update_calculator),
+            save_state: flag(self.save_state, file.save_state),
+            print_speed: flag(self.print_speed, file.print_speed),
+            early_quit: flag(self.early_quit, file.early_quit),
+            test: self.test.or(file.test).unwrap_or(Test::Qa),
+            qa_test_timeout: Duration::from_secs(
+                self.qa_test_timeout
+                    .or(file.qa_test_timeout)
+                    .unwrap_or(DEFAULT_QA_TEST_TIMEOUT_SECONDS),
+            ),
+            qa_openapi: flag(self.qa_openapi, file.qa_openapi),
+            qa_isolated,
+            run_timeout: self
+                .run_timeout
+                .or(file.run_timeout)
+                .map(Duration::from_secs),
+            request_retries: self.request_retries.or(file.request_retries).unwrap_or(0),
+            bot_script,
+            benchmark_output: self.benchmark_output.or(file.benchmark_output),
+            write_ratio,
+            server: ServerConfig {
+                api_urls: PublicApiUrls::new(url_register, url_account, url_calculator),
+                internal_api_url: url_internal,
+                test_database_dir: self
+                    .test_database
+                    .or(file.test_database)
+                    .unwrap_or_else(|| DEFAULT_TEST_DATABASE_DIR.into()),
+                microservice_calculator: flag(
+                    self.microservice_calculator,
+                    file.microservice_calculator,
+                ),
+                log_debug: flag(self.log_debug, file.log_debug),
+                tls_certificate,
+                certificate_pin,
+            },
+        })
+    }
+}
+
+/// Parse command line arguments. Completions command prints the completion
+/// script and exits the process, so config file is not needed for it.
+pub fn get_config() -> Result<ArgsConfig, GetConfigError> {
+    let cli = Cli::parse();
+
+    let mut config = ArgsConfig {
+        database_dir: cli.database,
+        test_mode: None,
+        restore_backup: None,
+        migrate: None,
+        openapi: None,
+        check_config: false,
+        replay: cli.replay,
+        stale_account_cleanup: None,
     };
 
-    ArgsConfig {
-        database_dir: matches
-            .get_one::<PathBuf>("database")
-            .map(ToOwned::to_owned),
-        test_mode,
+    match cli.command {
+        None | Some(CliCommand::Serve) => (),
+        Some(CliCommand::CheckConfig) => config.check_config = true,
+        Some(CliCommand::Migrate(args)) => {
+            config.migrate = Some(if args.apply {
+                MigrateMode::Apply
+            } else {
+                MigrateMode::DryRun
+            })
+        }
+        Some(CliCommand::Backup {
+            command: BackupCommand::Restore(args),
+        })
+        | Some(CliCommand::RestoreBackup(args)) => {
+            config.restore_backup = Some(RestoreBackup { name: args.name })
+        }
+        Some(CliCommand::Openapi(args)) => {
+            config.openapi = Some(if args.check_api_client {
+                OpenApiCommand::CheckApiClient
+            } else {
+                OpenApiCommand::Print(args.doc)
+            })
+        }
+        Some(CliCommand::Test(args)) => config.test_mode = Some(args.into_test_mode()?),
+        Some(CliCommand::StaleAccounts(args)) => {
+            config.stale_account_cleanup = Some(StaleAccountCleanupCommand {
+                url_internal: args.url_internal,
+                request: StaleAccountCleanupRequest {
+                    inactive_days: args.inactive_days,
+                    notify: args.notify,
+                    schedule_deletion: args.schedule_deletion,
+                    batch_size: args.batch_size,
+                },
+            })
+        }
+        Some(CliCommand::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+            std::process::exit(0);
+        }
     }
+
+    Ok(config)
+}
+
+#[derive(Debug, Clone)]
+pub struct StaleAccountCleanupCommand {
+    pub url_internal: Url,
+    pub request: StaleAccountCleanupRequest,
+}
+
+#[derive(Debug, Clone)]
+pub struct RestoreBackup {
+    /// Restore the newest backup if None.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ApiDocType {
+    Public,
+    Internal,
+    /// Public and internal API.
+    Client,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenApiCommand {
+    Print(ApiDocType),
+    CheckApiClient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrateMode {
+    DryRun,
+    Apply,
 }
 
 #[derive(Debug, Clone)]
@@ -140,86 +526,78 @@ pub struct TestMode {
     pub print_speed: bool,
     pub early_quit: bool,
     pub test: Test,
+    /// Default timeout for one QA test.
+    pub qa_test_timeout: Duration,
+    /// Run also OpenAPI conformance tests in QA mode.
+    pub qa_openapi: bool,
+    /// Every QA test group has its own server instances.
+    pub qa_isolated: bool,
+    /// Timeout for the whole test run.
+    pub run_timeout: Option<Duration>,
+    /// Retry count for idempotent bot requests.
+    pub request_retries: u32,
+    /// Actions for the `bot` test. Default actions are used if None.
+    pub bot_script: Option<BotScript>,
+    /// Benchmark report file. Relative path is relative to the test
+    /// database directory.
+    pub benchmark_output: Option<PathBuf>,
+    /// Percentage of write requests in the `benchmark-mixed` test.
+    pub write_ratio: u8,
     pub server: ServerConfig,
 }
 
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub api_urls: PublicApiUrls,
+    /// Account server internal API.
+    pub internal_api_url: Url,
     pub test_database_dir: PathBuf,
     pub microservice_calculator: bool,
     pub log_debug: bool,
+    /// Certificate for test servers if TLS is enabled.
+    pub tls_certificate: Option<TestCertificate>,
+    /// Bots accept only the server certificate which matches the pin.
+    pub certificate_pin: Option<CertificatePin>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Test {
     Qa,
     BenchmarkGetCalculatorState,
+    /// Compare cache and SQLite read path latencies.
+    BenchmarkCacheVsDatabase,
+    /// Internal API routes which microservices use on every request.
+    BenchmarkInternalApi,
+    /// Calculator state update throughput.
+    BenchmarkUpdateCalculatorState,
+    /// Calculator state reads and writes in the ratio which
+    /// `--write-ratio` sets.
+    BenchmarkMixed,
     Bot,
 }
 
-const TEST_NAME_QA: &str = "qa";
-const TEST_NAME_BENCHMARK_GET_CALCUALTOR_STATE: &str = "benchmark-get-calculator-state";
-const TEST_NAME_BOT: &str = "bot";
-
 impl Test {
     pub fn as_str(&self) -> &'static str {
         match self {
-            Self::Qa => TEST_NAME_QA,
-            Self::BenchmarkGetCalculatorState => TEST_NAME_BENCHMARK_GET_CALCUALTOR_STATE,
-            Self::Bot => TEST_NAME_BOT,
+            Self::Qa => "qa",
+            Self::BenchmarkGetCalculatorState => "benchmark-get-calculator-state",
+            Self::BenchmarkCacheVsDatabase => "benchmark-cache-vs-database",
+            Self::BenchmarkInternalApi => "benchmark-internal-api",
+            Self::BenchmarkUpdateCalculatorState => "benchmark-update-calculator-state",
+            Self::BenchmarkMixed => "benchmark-mixed",
+            Self::Bot => "bot",
         }
     }
-}
 
-impl TryFrom<&str> for Test {
-    type Error = ();
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Ok(match value {
-            TEST_NAME_QA => Self::Qa,
-            TEST_NAME_BENCHMARK_GET_CALCUALTOR_STATE => Self::BenchmarkGetCalculatorState,
-            TEST_NAME_BOT => Self::Bot,
-            _ => return Err(()),
-        })
-    }
-}
-
-impl clap::builder::ValueParserFactory for Test {
-    type Parser = TestNameParser;
-    fn value_parser() -> Self::Parser {
-        TestNameParser
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct TestNameParser;
-
-impl clap::builder::TypedValueParser for TestNameParser {
-    type Value = Test;
-
-    fn parse_ref(
-        &self,
-        _cmd: &clap::Command,
-        _arg: Option<&clap::Arg>,
-        value: &std::ffi::OsStr,
-    ) -> Result<Self::Value, clap::Error> {
-        value
-            .to_str()
-            .ok_or(clap::Error::raw(
-                clap::ErrorKind::InvalidUtf8,
-                "Text was not UTF-8.",
-            ))?
-            .try_into()
-            .map_err(|_| clap::Error::raw(clap::ErrorKind::InvalidValue, "Unknown test"))
-    }
-
-    fn possible_values(
-        &self,
-    ) -> Option<Box<dyn Iterator<Item = clap::PossibleValue<'static>> + '_>> {
-        Some(Box::new(
-            [Test::Qa, Test::BenchmarkGetCalculatorState, Test::Bot]
-                .iter()
-                .map(|value| PossibleValue::new(value.as_str())),
-        ))
+    pub fn is_benchmark(&self) -> bool {
+        match self {
+            Self::BenchmarkGetCalculatorState
+            | Self::BenchmarkCacheVsDatabase
+            | Self::BenchmarkInternalApi
+            | Self::BenchmarkUpdateCalculatorState
+            | Self::BenchmarkMixed => true,
+            Self::Qa | Self::Bot => false,
+        }
     }
 }
@@ -0,0 +1,138 @@
+//! Localization of server generated text
+//!
+//! Messages are written in English in the code. English text with argument
+//! placeholders (`{0}`, `{1}`, ...) is also the translation key, so a missing
+//! translation falls back to English. Translations are TOML files in the
+//! `translations` directory which are bundled into the binary.
+
+use std::collections::HashMap;
+
+/// Language of the messages in the code.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Bundled translations. File name is the language tag.
+const BUNDLED_TRANSLATIONS: &[(&str, &str)] = &[
+    ("de", include_str!("../translations/de.toml")),
+    ("fi", include_str!("../translations/fi.toml")),
+];
+
+/// Translatable message with arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    template: &'static str,
+    args: Vec<String>,
+}
+
+impl Message {
+    pub fn new(template: &'static str) -> Self {
+        Self {
+            template,
+            args: vec![],
+        }
+    }
+
+    /// Replaces the next placeholder.
+    pub fn arg(mut self, arg: impl ToString) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    /// Message in the default language.
+    pub fn english(&self) -> String {
+        fill_template(self.template, &self.args)
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(template: &'static str) -> Self {
+        Self::new(template)
+    }
+}
+
+pub struct Translations {
+    /// Language tag -> (English template -> translated template)
+    languages: HashMap<String, HashMap<String, String>>,
+}
+
+impl Translations {
+    /// Load translations which are bundled into the binary.
+    ///
+    /// Panics if bundled translation file is invalid.
+    pub fn bundled() -> Self {
+        let languages = BUNDLED_TRANSLATIONS
+            .iter()
+            .map(|(language, text)| {
+                let messages: HashMap<String, String> = toml::from_str(text)
+                    .unwrap_or_else(|e| panic!("Invalid translation file {}: {}", language, e));
+                (language.to_string(), messages)
+            })
+            .collect();
+        Self { languages }
+    }
+
+    /// Translate message using the first language from `locale`'s fallback
+    /// chain which has a translation for the message.
+    pub fn translate(&self, locale: &str, message: &Message) -> String {
+        let translation = fallback_chain(locale).iter().find_map(|language| {
+            self.languages
+                .get(language)
+                .and_then(|messages| messages.get(message.template))
+        });
+        match translation {
+            Some(template) => fill_template(template, &message.args),
+            None => message.english(),
+        }
+    }
+}
+
+/// Languages which are tried when translating to `locale`. For example
+/// "fi-FI" is tried as "fi-fi" and "fi". The default language is always the
+/// last one.
+pub fn fallback_chain(locale: &str) -> Vec<String> {
+    let locale = locale.replace('_', "-").to_ascii_lowercase();
+    let mut chain = vec![];
+    let mut tag = locale.as_str();
+    loop {
+        if !tag.is_empty() && !chain.iter().any(|l| l == tag) {
+            chain.push(tag.to_string());
+        }
+        match tag.rfind('-') {
+            Some(i) => tag = &tag[..i],
+            None => break,
+        }
+    }
+    if !chain.iter().any(|l| l == DEFAULT_LANGUAGE) {
+        chain.push(DEFAULT_LANGUAGE.to_string());
+    }
+    chain
+}
+
+/// Replace placeholders in one pass, so placeholders inside arguments are
+/// not replaced.
+fn fill_template(template: &str, args: &[String]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let arg = placeholder.find('}').and_then(|end| {
+            placeholder[1..end]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| args.get(i))
+                .map(|arg| (arg, end))
+        });
+        match arg {
+            Some((arg, end)) => {
+                text.push_str(arg);
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
@@ -1,5 +1,7 @@
 pub mod api;
+pub mod calculator;
 pub mod config;
+pub mod i18n;
 pub mod server;
 pub mod test;
 pub mod utils;
@@ -11,10 +13,38 @@ fn main() {
     // TODO: print commit ID to logs if build directory was clean
     let config = config::get_config().unwrap();
 
+    if config.check_config() {
+        println!("Config is valid");
+        return;
+    }
+
+    if let Some(command) = config.openapi() {
+        if let Err(e) = api::doc::run_openapi_command(command) {
+            eprintln!("{e:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let runtime = tokio::runtime::Runtime::new().unwrap();
 
     if let Some(test_mode_config) = config.test_mode() {
         runtime.block_on(async { TestRunner::new(config, test_mode_config).run().await })
+    } else if let Some(command) = config.stale_account_cleanup().cloned() {
+        runtime
+            .block_on(api::admin::cli::run_stale_account_cleanup_command(
+                command,
+                config.internal_api_http_client().clone(),
+            ))
+            .unwrap();
+    } else if let Some(restore) = config.restore_backup().cloned() {
+        runtime.block_on(async {
+            CalculatorServer::new(config)
+                .restore_backup(restore.name.as_deref())
+                .await
+        })
+    } else if let Some(mode) = config.migrate() {
+        runtime.block_on(async { CalculatorServer::new(config).migrate(mode).await })
     } else {
         runtime.block_on(async { CalculatorServer::new(config).run().await })
     }
@@ -1,10 +1,26 @@
+pub mod analytics;
 pub mod app;
+pub mod clock;
+pub mod config_reload;
 pub mod database;
+pub mod event;
+pub mod geoip;
 pub mod internal;
+pub mod latency;
+pub mod logging;
+pub mod request_id;
+pub mod response_headers;
+pub mod security;
+pub mod status;
+pub mod telemetry;
+#[cfg(test)]
+pub mod test_support;
+pub mod trace_sampling;
+pub mod webhook;
 
 use std::{net::SocketAddr, pin::Pin, sync::Arc};
 
-use axum::Router;
+use axum::{middleware, Router};
 use futures::future::poll_fn;
 use hyper::server::{
     accept::Accept,
@@ -20,17 +36,38 @@ use tokio_rustls::rustls::ServerConfig;
 use tokio_rustls::TlsAcceptor;
 use tower::MakeService;
 use tower_http::trace::TraceLayer;
-use tracing::{error, info};
-use utoipa::OpenApi;
+use tracing::{error, info, warn};
+use utoipa::{openapi::Server, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
-    api::ApiDoc,
-    config::Config,
+    api::{
+        doc::{
+            check_api_client, remove_unavailable_routes, API_DOC_FILE_NAME,
+            INTERNAL_API_DOC_FILE_NAME,
+        },
+        ApiDoc, GetServerStatus, InternalApiDoc,
+    },
+    config::{args::MigrateMode, Config},
     server::{
-        app::{connection::WebSocketManager, App},
-        database::DatabaseManager,
+        app::{
+            activity::AccountActivitySaver, connection::WebSocketManager, deletion::AccountPurger,
+            rate_limit, retention::HistoryPurger, scheduler::CalculationScheduler,
+            sign_in_with::SignInWithManager, App,
+        },
+        config_reload::{ConfigHandle, ConfigReloadSignalListener, ConfigReloader},
+        database::{
+            backup::restore_backup,
+            migrate::{migrate_apply, migrate_dry_run},
+            replication::replay_wal,
+            DatabaseManager,
+        },
+        geoip::load_geoip,
         internal::InternalApp,
+        latency::LatencyBudgets,
+        logging::init_logging,
+        telemetry::TelemetrySender,
+        trace_sampling::TraceSampler,
     },
 };
 
@@ -47,8 +84,32 @@ impl CalculatorServer {
         }
     }
 
+    /// Restore database backup instead of running the server.
+    pub async fn restore_backup(self, name: Option<&str>) {
+        init_logging(&self.config);
+
+        restore_backup(&self.config, name)
+            .await
+            .expect("Backup restore failed");
+    }
+
+    /// Run database migrations instead of running the server.
+    pub async fn migrate(self, mode: MigrateMode) {
+        init_logging(&self.config);
+
+        match mode {
+            MigrateMode::DryRun => migrate_dry_run(&self.config).await,
+            MigrateMode::Apply => migrate_apply(&self.config).await,
+        }
+        .expect("Migration failed");
+    }
+
     pub async fn run(self) {
-        tracing_subscriber::fmt::init();
+        let log_level = init_logging(&self.config);
+
+        if self.config.replay() {
+            replay_wal(&self.config).await.expect("WAL replay failed");
+        }
 
         let (database_manager, router_database_handle) = DatabaseManager::new(
             self.config.database_dir().to_path_buf(),
@@ -57,24 +118,80 @@ impl CalculatorServer {
         .await
         .expect("Database init failed");
 
+        if self.config.debug_mode() {
+            if let Err(e) = check_api_client() {
+                error!("{e:?}");
+            }
+        }
+
         let (server_quit_handle, server_quit_watcher) = broadcast::channel(1);
         let (ws_manager, mut ws_quit_ready) =
             WebSocketManager::new(server_quit_watcher.resubscribe());
 
-        let mut app = App::new(router_database_handle, self.config.clone(), ws_manager).await;
+        let geoip =
+            load_geoip(self.config.geoip_database()).expect("GeoIP database loading failed");
 
-        let server_task = self
-            .create_public_api_server_task(&mut app, server_quit_watcher.resubscribe())
-            .await;
-        let internal_server_task = if self.config.debug_mode() {
+        let mut app = App::new(
+            router_database_handle,
+            self.config.clone(),
+            ws_manager,
+            geoip,
+            Box::new(SignInWithManager::new(self.config.clone())),
+        )
+        .await;
+
+        let config_reloader = Arc::new(ConfigReloader::new(
+            self.config.clone(),
+            log_level,
+            app.rate_limiter(),
+        ));
+        let config_reload_listener = ConfigReloadSignalListener::new_task(config_reloader.clone());
+
+        let telemetry = match (self.config.telemetry(), app.request_counters()) {
+            (Some(config), Some(counters)) => Some(TelemetrySender::new_task(counters, config)),
+            _ => None,
+        };
+
+        let activity_saver = AccountActivitySaver::new_task(app.state());
+
+        let account_purger = if self.config.components().account {
+            Some(AccountPurger::new_task(app.state()))
+        } else {
             None
+        };
+
+        let scheduler = if self.config.components().calculator {
+            Some(CalculationScheduler::new_task(app.state()))
         } else {
-            Some(
-                self.create_internal_api_server_task(&app, server_quit_watcher.resubscribe())
-                    .await,
-            )
+            None
         };
 
+        let history_purger = if self.config.components().calculator {
+            Some(HistoryPurger::new_task(app.state()))
+        } else {
+            None
+        };
+
+        let server_task = self
+            .create_public_api_server_task(
+                &mut app,
+                &config_reloader,
+                server_quit_watcher.resubscribe(),
+            )
+            .await;
+        // Internal routes are also in the public API in debug mode, but
+        // microservices and test bots use the internal API address.
+        let internal_server_task = self
+            .create_internal_api_server_task(
+                &app,
+                &config_reloader,
+                server_quit_watcher.resubscribe(),
+            )
+            .await;
+
+        app.state().server_status().set_ready();
+        info!("Server is ready");
+
         match signal::ctrl_c().await {
             Ok(()) => (),
             Err(e) => error!("Failed to listen CTRL+C. Error: {}", e),
@@ -88,11 +205,9 @@ impl CalculatorServer {
         server_task
             .await
             .expect("Public API server task panic detected");
-        if let Some(handle) = internal_server_task {
-            handle
-                .await
-                .expect("Internal API server task panic detected");
-        }
+        internal_server_task
+            .await
+            .expect("Internal API server task panic detected");
 
         loop {
             match ws_quit_ready.recv().await {
@@ -101,6 +216,26 @@ impl CalculatorServer {
             }
         }
 
+        if let Some(scheduler) = scheduler {
+            scheduler.quit().await;
+        }
+
+        if let Some(history_purger) = history_purger {
+            history_purger.quit().await;
+        }
+
+        if let Some(telemetry) = telemetry {
+            telemetry.quit().await;
+        }
+
+        if let Some(account_purger) = account_purger {
+            account_purger.quit().await;
+        }
+
+        activity_saver.quit().await;
+
+        config_reload_listener.quit().await;
+
         drop(app);
         database_manager.close().await;
 
@@ -111,24 +246,10 @@ impl CalculatorServer {
     pub async fn create_public_api_server_task(
         &self,
         app: &mut App,
+        config_reloader: &Arc<ConfigReloader>,
         quit_notification: ServerQuitWatcher,
     ) -> JoinHandle<()> {
-        let router = {
-            let router = self.create_public_router(app);
-            let router = if self.config.debug_mode() {
-                router
-                    .merge(Self::create_swagger_ui())
-                    .merge(self.create_internal_router(&app))
-            } else {
-                router
-            };
-            let router = if self.config.debug_mode() {
-                router.route_layer(TraceLayer::new_for_http())
-            } else {
-                router
-            };
-            router
-        };
+        let router = self.create_public_api_router(app, config_reloader);
 
         let addr = self.config.socket().public_api;
         info!("Public API is available on {}", addr);
@@ -137,18 +258,57 @@ impl CalculatorServer {
         }
 
         if let Some(tls_config) = self.config.public_api_tls_config() {
-            self.create_server_task_with_tls(addr, router, tls_config.clone(), quit_notification)
-                .await
+            self.create_server_task_with_tls(
+                addr,
+                router,
+                tls_config.clone(),
+                config_reloader.handle(),
+                Config::public_api_tls_config,
+                quit_notification,
+            )
+            .await
+        } else {
+            self.create_server_task_no_tls(router, addr, "Public API", quit_notification)
+        }
+    }
+
+    /// Public API routes with all middleware. Internal routes are included
+    /// in debug mode.
+    pub fn create_public_api_router(
+        &self,
+        app: &mut App,
+        config_reloader: &Arc<ConfigReloader>,
+    ) -> Router {
+        let router = self.create_public_router(app);
+        let router = if self.config.debug_mode() {
+            router.merge(self.create_internal_router(app, config_reloader))
         } else {
-            self.create_server_task_no_tls(router, addr, "Public API")
+            router
+        };
+        let router = self.add_latency_budgets(router);
+        let router = self.add_tracing(router);
+        let router = self.add_request_counting(app, router);
+        let router = self.add_rate_limiting(app, router);
+        let router = self.add_api_base_path(self.add_response_headers(router));
+        let router = self.add_request_id(router);
+        if self.config.debug_mode() {
+            router.merge(self.create_swagger_ui())
+        } else {
+            router
         }
     }
 
+    /// TLS certificates are selected from the latest config with
+    /// `select_tls_config` when a connection is accepted, so reloaded
+    /// certificates are used for new connections. The `tls_config` is used
+    /// if the latest config does not have TLS enabled.
     pub async fn create_server_task_with_tls(
         &self,
         addr: SocketAddr,
         router: Router,
         tls_config: Arc<ServerConfig>,
+        config_handle: ConfigHandle,
+        select_tls_config: fn(&Config) -> Option<&Arc<ServerConfig>>,
         mut quit_notification: ServerQuitWatcher,
     ) -> JoinHandle<()> {
         let listener = TcpListener::bind(addr)
@@ -160,6 +320,7 @@ impl CalculatorServer {
 
         let protocol = Arc::new(Http::new());
         let acceptor = TlsAcceptor::from(tls_config);
+        let shutdown_timeout = self.config.shutdown_timeout();
 
         let mut app_service = router.into_make_service_with_connect_info::<SocketAddr>();
 
@@ -190,23 +351,40 @@ impl CalculatorServer {
                     }
                 };
 
-                let acceptor = acceptor.clone();
+                let acceptor = match select_tls_config(&config_handle.current()) {
+                    Some(tls_config) => TlsAcceptor::from(tls_config.clone()),
+                    None => acceptor.clone(),
+                };
                 let protocol = protocol.clone();
                 let service = app_service.make_service(&stream);
 
                 let mut quit_notification = quit_notification.resubscribe();
                 let drop_on_quit = drop_after_connection.clone();
                 tokio::spawn(async move {
-                    tokio::select! {
-                        _ = quit_notification.recv() => {} // Graceful shutdown for connections?
-                        connection = acceptor.accept(stream) => {
-                            match connection {
-                                Ok(connection) => {
-                                    if let Ok(service) = service.await {
-                                        let _ = protocol.serve_connection(connection, service).with_upgrades().await;
-                                    }
+                    let connection = tokio::select! {
+                        _ = quit_notification.recv() => None,
+                        connection = acceptor.accept(stream) => connection.ok(),
+                    };
+
+                    let service = match connection {
+                        Some(connection) => service.await.ok().map(|s| (connection, s)),
+                        None => None,
+                    };
+
+                    if let Some((connection, service)) = service {
+                        let connection = protocol
+                            .serve_connection(connection, service)
+                            .with_upgrades();
+                        tokio::pin!(connection);
+                        tokio::select! {
+                            _ = connection.as_mut() => (),
+                            _ = quit_notification.recv() => {
+                                // Complete in-flight requests and close the
+                                // connection. HTTP/2 clients get GOAWAY.
+                                connection.as_mut().graceful_shutdown();
+                                if tokio::time::timeout(shutdown_timeout, connection).await.is_err() {
+                                    warn!("Connection closing timeout. Dropping the connection.");
                                 }
-                                Err(_) => {},
                             }
                         }
                     }
@@ -231,26 +409,37 @@ impl CalculatorServer {
         router: Router,
         addr: SocketAddr,
         name_for_log_message: &'static str,
+        mut quit_notification: ServerQuitWatcher,
     ) -> JoinHandle<()> {
         let normal_api_server = {
             axum::Server::bind(&addr)
                 .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         };
+        let shutdown_timeout = self.config.shutdown_timeout();
 
         tokio::spawn(async move {
-            let shutdown_handle = normal_api_server.with_graceful_shutdown(async {
-                match signal::ctrl_c().await {
-                    Ok(()) => (),
-                    Err(e) => error!("Failed to listen CTRL+C. Error: {}", e),
-                }
+            let mut quit_for_timeout = quit_notification.resubscribe();
+            // Graceful shutdown stops accepting new connections and waits
+            // that in-flight requests complete.
+            let shutdown_handle = normal_api_server.with_graceful_shutdown(async move {
+                let _ = quit_notification.recv().await;
             });
+            let shutdown_timeout = async move {
+                let _ = quit_for_timeout.recv().await;
+                tokio::time::sleep(shutdown_timeout).await;
+            };
 
-            match shutdown_handle.await {
-                Ok(()) => {
-                    info!("{name_for_log_message} server future returned Ok()");
-                }
-                Err(e) => {
-                    error!("{name_for_log_message} server future returned error: {}", e);
+            tokio::select! {
+                result = shutdown_handle => match result {
+                    Ok(()) => {
+                        info!("{name_for_log_message} server future returned Ok()");
+                    }
+                    Err(e) => {
+                        error!("{name_for_log_message} server future returned error: {}", e);
+                    }
+                },
+                _ = shutdown_timeout => {
+                    warn!("{name_for_log_message} server shutdown timeout. Dropping connections.");
                 }
             }
         })
@@ -260,11 +449,19 @@ impl CalculatorServer {
     pub async fn create_internal_api_server_task(
         &self,
         app: &App,
+        config_reloader: &Arc<ConfigReloader>,
         quit_notification: ServerQuitWatcher,
     ) -> JoinHandle<()> {
-        let router = self.create_internal_router(&app);
+        let router = self
+            .create_internal_router(&app, config_reloader)
+            .merge(InternalApp::create_health_router(app.state()));
+        let router = self.add_latency_budgets(router);
+        let router = self.add_tracing(router);
+        let router = self.add_request_counting(app, router);
+        let router = self.add_api_base_path(self.add_response_headers(router));
+        let router = self.add_request_id(router);
         let router = if self.config.debug_mode() {
-            router.merge(Self::create_swagger_ui())
+            router.merge(self.create_swagger_ui())
         } else {
             router
         };
@@ -272,10 +469,17 @@ impl CalculatorServer {
         let addr = self.config.socket().internal_api;
         info!("Internal API is available on {}", addr);
         if let Some(tls_config) = self.config.internal_api_tls_config() {
-            self.create_server_task_with_tls(addr, router, tls_config.clone(), quit_notification)
-                .await
+            self.create_server_task_with_tls(
+                addr,
+                router,
+                tls_config.clone(),
+                config_reloader.handle(),
+                Config::internal_api_tls_config,
+                quit_notification,
+            )
+            .await
         } else {
-            self.create_server_task_no_tls(router, addr, "Internal API")
+            self.create_server_task_no_tls(router, addr, "Internal API", quit_notification)
         }
     }
 
@@ -293,16 +497,138 @@ impl CalculatorServer {
         router
     }
 
-    pub fn create_internal_router(&self, app: &App) -> Router {
-        let mut router = Router::new();
+    pub fn create_internal_router(
+        &self,
+        app: &App,
+        config_reloader: &Arc<ConfigReloader>,
+    ) -> Router {
+        let mut router = InternalApp::create_cache_router(app.state())
+            .merge(InternalApp::create_metrics_router(app.state()))
+            .merge(InternalApp::create_reload_router(config_reloader.clone()));
         if self.config.components().account {
-            router = router.merge(InternalApp::create_account_server_router(app.state()))
+            router = router
+                .merge(InternalApp::create_account_server_router(app.state()))
+                .merge(InternalApp::create_admin_router(app.state()))
+        } else {
+            router = router.merge(InternalApp::create_api_key_cache_router(app.state()))
+        }
+
+        if self.config.components().calculator {
+            router = router.merge(InternalApp::create_calculator_server_router(app.state()))
+        }
+
+        if self.config.hot_standby_receiver() {
+            router = router.merge(InternalApp::create_standby_router(app.state()))
+        }
+
+        if self.config.mock_clock().is_some() {
+            router = router.merge(InternalApp::create_clock_router(app.state()))
         }
 
         router
     }
 
-    pub fn create_swagger_ui() -> SwaggerUi {
-        SwaggerUi::new("/swagger-ui").url("/api-doc/calculator_api.json", ApiDoc::openapi())
+    /// Trace sampled requests if sampling is configured. Otherwise trace all
+    /// requests in debug mode.
+    pub fn add_tracing(&self, router: Router) -> Router {
+        match self.config.tracing_sampling() {
+            Some(sampling) => {
+                let sampler = TraceSampler::new(sampling);
+                router.route_layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(sampler.clone())
+                        .on_request(sampler.clone())
+                        .on_response(sampler.clone())
+                        .on_eos(sampler.clone())
+                        .on_failure(sampler),
+                )
+            }
+            None if self.config.debug_mode() => router.route_layer(TraceLayer::new_for_http()),
+            None => router,
+        }
+    }
+
+    /// Log requests which exceed their latency budget if latency budgets
+    /// are configured.
+    pub fn add_latency_budgets(&self, router: Router) -> Router {
+        match LatencyBudgets::new(&self.config) {
+            Some(budgets) => router.route_layer(middleware::from_fn_with_state(
+                Arc::new(budgets),
+                latency::check_latency_budget,
+            )),
+            None => router,
+        }
+    }
+
+    /// Count requests for telemetry if it is enabled.
+    pub fn add_request_counting(&self, app: &App, router: Router) -> Router {
+        match app.request_counters() {
+            Some(counters) => router.route_layer(middleware::from_fn_with_state(
+                counters,
+                telemetry::count_request,
+            )),
+            None => router,
+        }
+    }
+
+    /// Limit requests per client IP address and access token if rate
+    /// limiting is enabled. This should be done before
+    /// [Self::add_response_headers], so that CORS preflight requests are
+    /// not limited and limited responses have CORS headers.
+    pub fn add_rate_limiting(&self, app: &App, router: Router) -> Router {
+        match app.rate_limiter() {
+            Some(limiter) => router.layer(middleware::from_fn_with_state(
+                (limiter, app.state()),
+                rate_limit::rate_limit,
+            )),
+            None => router,
+        }
+    }
+
+    /// Add OPTIONS handling, CORS and Cache-Control headers. This should be
+    /// done before [Self::add_api_base_path], so that route paths are
+    /// without the base path.
+    pub fn add_response_headers(&self, router: Router) -> Router {
+        let router = router.layer(middleware::from_fn(response_headers::add_response_headers));
+        match response_headers::cors_layer(&self.config) {
+            Some(cors) => router.layer(cors),
+            None => router,
+        }
+    }
+
+    /// Add request ID to request extensions, log span and response headers.
+    /// This should be done last, so that the tracing span and all error
+    /// responses have the request ID.
+    pub fn add_request_id(&self, router: Router) -> Router {
+        router.layer(middleware::from_fn(request_id::add_request_id))
+    }
+
+    /// Move all routes under the configured API base path.
+    pub fn add_api_base_path(&self, router: Router) -> Router {
+        match self.config.api_base_path() {
+            Some(base_path) => Router::new().nest(base_path, router),
+            None => router,
+        }
+    }
+
+    /// Swagger UI routes include the API base path, so this router should
+    /// not be given to [Self::add_api_base_path]. The base path is also
+    /// the server URL in the OpenAPI document.
+    pub fn create_swagger_ui(&self) -> SwaggerUi {
+        let mut api_doc = ApiDoc::openapi();
+        let mut internal_api_doc = InternalApiDoc::openapi();
+        remove_unavailable_routes(&mut api_doc, &self.config);
+        remove_unavailable_routes(&mut internal_api_doc, &self.config);
+        let base_path = self.config.api_base_path().unwrap_or_default();
+        if !base_path.is_empty() {
+            api_doc.servers = Some(vec![Server::new(base_path)]);
+            internal_api_doc.servers = Some(vec![Server::new(base_path)]);
+        }
+        SwaggerUi::new(format!("{base_path}/swagger-ui"))
+            .url(format!("{base_path}/api-doc/{API_DOC_FILE_NAME}"), api_doc)
+            .url(
+                format!("{base_path}/api-doc/{INTERNAL_API_DOC_FILE_NAME}"),
+                internal_api_doc,
+            )
     }
 }
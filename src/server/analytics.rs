@@ -0,0 +1,249 @@
+//! Product analytics events
+//!
+//! Request handlers record events with [Analytics::track]. Events are
+//! buffered and written to the configured [AnalyticsSink] in batches by a
+//! background task, so handlers do not wait for the sink. Nothing is
+//! recorded if analytics is not configured.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use error_stack::Result;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::{
+    api::model::AccountIdLight,
+    config::{file::AnalyticsSinkType, Config},
+    server::database::utils::current_unix_time,
+    utils::IntoReportExt,
+};
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_FLUSH_INTERVAL_SECONDS: u64 = 10;
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Event names
+pub const EVENT_ACCOUNT_REGISTERED: &str = "account_registered";
+pub const EVENT_ACCOUNT_SETUP_SAVED: &str = "account_setup_saved";
+pub const EVENT_ACCOUNT_SETUP_COMPLETED: &str = "account_setup_completed";
+pub const EVENT_CALCULATOR_STATE_SAVED: &str = "calculator_state_saved";
+pub const EVENT_CALCULATION_EVALUATED: &str = "calculation_evaluated";
+pub const EVENT_CALCULATION_SCHEDULE_CREATED: &str = "calculation_schedule_created";
+pub const EVENT_HISTORY_ENTRY_RERUN: &str = "history_entry_rerun";
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnalyticsError {
+    #[error("Serialization error")]
+    Serialize,
+    #[error("Writing analytics file failed")]
+    File,
+    #[error("Sending analytics events failed")]
+    Http,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsEvent {
+    pub name: &'static str,
+    pub unix_time: i64,
+    pub account_id: Option<AccountIdLight>,
+    /// JSON object.
+    pub properties: Value,
+}
+
+/// Destination for analytics events.
+#[async_trait]
+pub trait AnalyticsSink: Send + Sync {
+    async fn write(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError>;
+}
+
+/// Write events to the server log.
+pub struct LogSink;
+
+#[async_trait]
+impl AnalyticsSink for LogSink {
+    async fn write(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError> {
+        for event in events {
+            let event = serde_json::to_string(event).into_error(AnalyticsError::Serialize)?;
+            info!("Analytics event: {}", event);
+        }
+        Ok(())
+    }
+}
+
+/// Append events to a file. One JSON object per line (NDJSON).
+pub struct FileSink {
+    file: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(file: PathBuf) -> Self {
+        Self { file }
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for FileSink {
+    async fn write(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError> {
+        let mut data = vec![];
+        for event in events {
+            serde_json::to_writer(&mut data, event).into_error(AnalyticsError::Serialize)?;
+            data.push(b'\n');
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)
+            .await
+            .into_error(AnalyticsError::File)?;
+        file.write_all(&data)
+            .await
+            .into_error(AnalyticsError::File)?;
+        file.flush().await.into_error(AnalyticsError::File)
+    }
+}
+
+/// POST events as JSON array.
+pub struct HttpBatchSink {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl HttpBatchSink {
+    pub fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(HTTP_REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for HttpBatchSink {
+    async fn write(&self, events: &[AnalyticsEvent]) -> Result<(), AnalyticsError> {
+        self.client
+            .post(self.url.clone())
+            .json(events)
+            .send()
+            .await
+            .into_error(AnalyticsError::Http)?
+            .error_for_status()
+            .into_error(AnalyticsError::Http)?;
+        Ok(())
+    }
+}
+
+pub struct Analytics {
+    /// None if analytics is disabled.
+    sender: Option<mpsc::Sender<AnalyticsEvent>>,
+}
+
+impl Analytics {
+    pub fn disabled() -> Self {
+        Self { sender: None }
+    }
+
+    /// Starts the writer task. The task quits when this is dropped.
+    pub fn new(config: &Config) -> Self {
+        let event_buffer = config.limits().analytics_event_buffer;
+        let config = match config.analytics() {
+            Some(config) => config,
+            None => return Self::disabled(),
+        };
+
+        let sink: Arc<dyn AnalyticsSink> = match (&config.sink, &config.file, &config.url) {
+            (AnalyticsSinkType::Log, _, _) => Arc::new(LogSink),
+            (AnalyticsSinkType::File, Some(file), _) => Arc::new(FileSink::new(file.clone())),
+            (AnalyticsSinkType::Http, _, Some(url)) => Arc::new(HttpBatchSink::new(url.clone())),
+            // Config loading checks that file and URL exist.
+            _ => return Self::disabled(),
+        };
+
+        let (sender, receiver) = mpsc::channel(event_buffer);
+        tokio::spawn(run_writer(
+            receiver,
+            sink,
+            config.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+            Duration::from_secs(
+                config
+                    .flush_interval_seconds
+                    .unwrap_or(DEFAULT_FLUSH_INTERVAL_SECONDS),
+            ),
+        ));
+
+        Self {
+            sender: Some(sender),
+        }
+    }
+
+    /// Record event. Event is dropped if the buffer is full. Properties
+    /// should be a JSON object.
+    pub fn track(&self, name: &'static str, account_id: Option<AccountIdLight>, properties: Value) {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let event = AnalyticsEvent {
+            name,
+            unix_time: current_unix_time(),
+            account_id,
+            properties,
+        };
+
+        if let Err(e) = sender.try_send(event) {
+            warn!("Analytics event dropped: {}", e);
+        }
+    }
+}
+
+async fn run_writer(
+    mut receiver: mpsc::Receiver<AnalyticsEvent>,
+    sink: Arc<dyn AnalyticsSink>,
+    batch_size: usize,
+    flush_interval: Duration,
+) {
+    let mut timer = tokio::time::interval(flush_interval);
+    let mut batch = Vec::with_capacity(batch_size);
+
+    loop {
+        tokio::select! {
+            event = receiver.recv() => {
+                match event {
+                    Some(event) => batch.push(event),
+                    None => break,
+                }
+                if batch.len() < batch_size {
+                    continue;
+                }
+            }
+            _ = timer.tick() => {
+                if batch.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        flush(sink.as_ref(), &mut batch).await;
+    }
+
+    if !batch.is_empty() {
+        flush(sink.as_ref(), &mut batch).await;
+    }
+}
+
+/// Events are dropped if writing fails, so that a broken sink does not
+/// grow the memory usage.
+async fn flush(sink: &dyn AnalyticsSink, batch: &mut Vec<AnalyticsEvent>) {
+    if let Err(e) = sink.write(batch).await {
+        error!("Writing {} analytics events failed: {e:?}", batch.len());
+    }
+    batch.clear();
+}
@@ -1,6 +1,12 @@
+pub mod activity;
 pub mod connected_routes;
 pub mod connection;
+pub mod deletion;
+pub mod rate_limit;
+pub mod retention;
+pub mod scheduler;
 pub mod sign_in_with;
+pub mod stale;
 
 use std::sync::Arc;
 
@@ -11,32 +17,52 @@ use axum::{
 
 use crate::{
     api::{
-        self, GetApiKeys, GetConfig, GetInternalApi, GetUsers, ReadDatabase, SignInWith,
-        WriteDatabase,
+        self, GetAnalytics, GetApiKeys, GetConfig, GetEvaluationCache, GetEventManager, GetExports,
+        GetGeoIp, GetInternalApi, GetOperations, GetServerStatus, GetStaleAccountCleanup,
+        GetTranslations, GetUsers, GetWebhooks, ReadDatabase, SignInWith, WriteDatabase,
     },
+    calculator::{EvaluationCache, OperationRegistry},
     config::Config,
+    i18n::Translations,
 };
 
 use self::{
-    connected_routes::ConnectedApp, connection::WebSocketManager, sign_in_with::SignInWithManager,
+    connected_routes::ConnectedApp, connection::WebSocketManager, rate_limit::RateLimiter,
+    sign_in_with::SignInWithProvider, stale::StaleAccountCleanup,
 };
 
 use super::{
+    analytics::Analytics,
     database::{
         commands::WriteCommandRunnerHandle,
+        export::ExportStorage,
         read::ReadCommands,
         utils::{AccountIdManager, ApiKeyManager},
         RouterDatabaseReadHandle,
     },
-    internal::{InternalApiClient, InternalApiManager},
+    event::EventManager,
+    geoip::GeoIpLookup,
+    internal::{api_key_cache::RemoteApiKeyCache, InternalApiClient, InternalApiManager},
+    status::ServerStatus,
+    telemetry::RequestCounters,
+    webhook::WebhookManager,
 };
 
 #[derive(Clone)]
 pub struct AppState {
     database: Arc<RouterDatabaseReadHandle>,
     internal_api: Arc<InternalApiClient>,
+    api_key_cache: Arc<RemoteApiKeyCache>,
     config: Arc<Config>,
-    sign_in_with: Arc<SignInWithManager>,
+    sign_in_with: Arc<dyn SignInWithProvider>,
+    geoip: Arc<dyn GeoIpLookup>,
+    operations: Arc<OperationRegistry>,
+    evaluation_cache: Arc<EvaluationCache>,
+    translations: Arc<Translations>,
+    webhooks: Arc<WebhookManager>,
+    analytics: Arc<Analytics>,
+    status: Arc<ServerStatus>,
+    stale_account_cleanup: Arc<StaleAccountCleanup>,
 }
 
 impl GetApiKeys for AppState {
@@ -45,6 +71,12 @@ impl GetApiKeys for AppState {
     }
 }
 
+impl GetEventManager for AppState {
+    fn event_manager(&self) -> EventManager<'_> {
+        self.database.event_manager()
+    }
+}
+
 impl GetUsers for AppState {
     fn users(&self) -> AccountIdManager<'_> {
         self.database.account_id_manager()
@@ -64,8 +96,8 @@ impl WriteDatabase for AppState {
 }
 
 impl SignInWith for AppState {
-    fn sign_in_with_manager(&self) -> &SignInWithManager {
-        &self.sign_in_with
+    fn sign_in_with(&self) -> &dyn SignInWithProvider {
+        self.sign_in_with.as_ref()
     }
 }
 
@@ -74,6 +106,7 @@ impl GetInternalApi for AppState {
         InternalApiManager::new(
             &self.config,
             &self.internal_api,
+            &self.api_key_cache,
             self.api_keys(),
             self.read_database(),
             self.write_database(),
@@ -88,9 +121,67 @@ impl GetConfig for AppState {
     }
 }
 
+impl GetOperations for AppState {
+    fn operations(&self) -> &OperationRegistry {
+        &self.operations
+    }
+}
+
+impl GetEvaluationCache for AppState {
+    fn evaluation_cache(&self) -> &EvaluationCache {
+        &self.evaluation_cache
+    }
+}
+
+impl GetTranslations for AppState {
+    fn translations(&self) -> &Translations {
+        &self.translations
+    }
+}
+
+impl GetGeoIp for AppState {
+    fn geoip(&self) -> &dyn GeoIpLookup {
+        self.geoip.as_ref()
+    }
+}
+
+impl GetExports for AppState {
+    fn exports(&self) -> &ExportStorage {
+        self.database.exports()
+    }
+}
+
+impl GetWebhooks for AppState {
+    fn webhooks(&self) -> &WebhookManager {
+        &self.webhooks
+    }
+}
+
+impl GetAnalytics for AppState {
+    fn analytics(&self) -> &Analytics {
+        &self.analytics
+    }
+}
+
+impl GetServerStatus for AppState {
+    fn server_status(&self) -> &ServerStatus {
+        &self.status
+    }
+}
+
+impl GetStaleAccountCleanup for AppState {
+    fn stale_account_cleanup(&self) -> &StaleAccountCleanup {
+        &self.stale_account_cleanup
+    }
+}
+
 pub struct App {
     state: AppState,
     ws_manager: Option<WebSocketManager>,
+    /// Available if telemetry is enabled.
+    request_counters: Option<Arc<RequestCounters>>,
+    /// Available if rate limiting is enabled.
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl App {
@@ -98,17 +189,41 @@ impl App {
         database_handle: RouterDatabaseReadHandle,
         config: Arc<Config>,
         ws_manager: WebSocketManager,
+        geoip: Box<dyn GeoIpLookup>,
+        sign_in_with: Box<dyn SignInWithProvider>,
     ) -> Self {
         let state = AppState {
             config: config.clone(),
             database: Arc::new(database_handle),
-            internal_api: InternalApiClient::new(config.external_service_urls().clone()).into(),
-            sign_in_with: SignInWithManager::new(config).into(),
+            internal_api: InternalApiClient::new(
+                config.external_service_urls().clone(),
+                config.internal_api_http_client().clone(),
+            )
+            .into(),
+            api_key_cache: RemoteApiKeyCache::new(&config).into(),
+            sign_in_with: sign_in_with.into(),
+            geoip: geoip.into(),
+            operations: OperationRegistry::with_builtin_operations(config.operation_sets()).into(),
+            evaluation_cache: EvaluationCache::new(config.evaluation_cache_capacity()).into(),
+            translations: Translations::bundled().into(),
+            webhooks: WebhookManager::new(&config).into(),
+            analytics: Analytics::new(&config).into(),
+            status: ServerStatus::default().into(),
+            stale_account_cleanup: StaleAccountCleanup::default().into(),
         };
 
+        let request_counters = config
+            .telemetry()
+            .map(|_| Arc::new(RequestCounters::new(&config)));
+        let rate_limiter = config
+            .rate_limit()
+            .map(|rate_limit| Arc::new(RateLimiter::new(rate_limit.clone())));
+
         Self {
             state,
             ws_manager: Some(ws_manager),
+            request_counters,
+            rate_limiter,
         }
     }
 
@@ -116,8 +231,44 @@ impl App {
         self.state.clone()
     }
 
+    pub fn request_counters(&self) -> Option<Arc<RequestCounters>> {
+        self.request_counters.clone()
+    }
+
+    pub fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        self.rate_limiter.clone()
+    }
+
     pub fn create_common_server_router(&mut self) -> Router {
-        Router::new().route(
+        let router = Router::new()
+            .route(api::common::PATH_GET_VERSION, get(api::common::get_version))
+            .route(
+                api::common::PATH_GET_HEALTH,
+                get({
+                    let state = self.state.clone();
+                    move || api::common::get_health(state)
+                }),
+            )
+            .route(
+                api::common::PATH_GET_HEALTH_LIVE,
+                get(api::common::get_health_live),
+            )
+            .route(
+                api::common::PATH_GET_HEALTH_READY,
+                get({
+                    let state = self.state.clone();
+                    move || api::common::get_health_ready(state)
+                }),
+            );
+
+        // WebSocket sessions use the access and refresh tokens of the
+        // account component, so calculator only servers do not have this
+        // route.
+        if !self.state.config.components().account {
+            return router;
+        }
+
+        router.route(
             api::common::PATH_CONNECT,
             get({
                 let state = self.state.clone();
@@ -136,21 +287,39 @@ impl App {
                 api::account::PATH_REGISTER,
                 post({
                     let state = self.state.clone();
-                    move || api::account::post_register(state)
+                    move |param1| api::account::post_register(param1, state)
                 }),
             )
             .route(
                 api::account::PATH_LOGIN,
                 post({
                     let state = self.state.clone();
-                    move |body| api::account::post_login(body, state)
+                    move |addr, user_agent, body| {
+                        api::account::post_login(addr, user_agent, body, state)
+                    }
+                }),
+            )
+            .route(
+                api::account::PATH_POST_REFRESH,
+                post({
+                    let state = self.state.clone();
+                    move |addr, body| api::account::post_refresh(addr, body, state)
+                }),
+            )
+            .route(
+                api::account::PATH_POST_TOKEN_EXCHANGE,
+                post({
+                    let state = self.state.clone();
+                    move |addr, body| api::account::post_token_exchange(addr, body, state)
                 }),
             )
             .route(
                 api::account::PATH_SIGN_IN_WITH_LOGIN,
                 post({
                     let state = self.state.clone();
-                    move |body| api::account::post_sign_in_with_login(body, state)
+                    move |addr, user_agent, body| {
+                        api::account::post_sign_in_with_login(addr, user_agent, body, state)
+                    }
                 }),
             );
 
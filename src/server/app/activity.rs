@@ -0,0 +1,76 @@
+//! Saves account activity times from the cache to the database and removes
+//! expired access tokens from the cache
+
+use std::time::Duration;
+
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    api::{GetApiKeys, WriteDatabase},
+    utils::{QuitReceiver, QuitSender},
+};
+
+use super::AppState;
+
+const SAVE_INTERVAL_SECONDS: u64 = 5 * 60;
+
+#[derive(Debug)]
+pub struct AccountActivitySaverQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl AccountActivitySaverQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Account activity saver task join failed: {}", e),
+        }
+    }
+}
+
+pub struct AccountActivitySaver {
+    state: AppState,
+}
+
+impl AccountActivitySaver {
+    pub fn new_task(state: AppState) -> AccountActivitySaverQuitHandle {
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(Self { state }.run(quit_receiver));
+        AccountActivitySaverQuitHandle { task, quit }
+    }
+
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(Duration::from_secs(SAVE_INTERVAL_SECONDS));
+        // First tick completes immediately.
+        timer.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    self.save().await;
+                    self.remove_expired_access_tokens().await;
+                }
+            }
+        }
+
+        // Save activity from the last interval.
+        self.save().await;
+    }
+
+    async fn save(&self) {
+        if let Err(e) = self.state.write_database().save_account_activity().await {
+            error!("Saving account activity failed: {e:?}");
+        }
+    }
+
+    async fn remove_expired_access_tokens(&self) {
+        let count = self.state.api_keys().remove_expired_api_keys().await;
+        if count > 0 {
+            info!("Removed {count} expired access tokens from the cache");
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
     middleware,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
@@ -29,7 +29,73 @@ impl ConnectedApp {
                 api::account::PATH_ACCOUNT_STATE,
                 get({
                     let state = self.state.clone();
-                    move |body| api::account::get_account_state(body, state)
+                    move |param1| api::account::get_account_state(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_SESSION,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::account::get_session(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_LOGIN_HISTORY,
+                get({
+                    let state = self.state.clone();
+                    move |param1, param2| api::account::get_login_history(param1, param2, state)
+                }),
+            )
+            .route(
+                api::account::PATH_GET_SERVICE_TOKENS,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::account::get_service_tokens(param1, state)
+                })
+                .post({
+                    let state = self.state.clone();
+                    move |param1, param2| api::account::post_service_token(param1, param2, state)
+                }),
+            )
+            .route(
+                api::account::PATH_GET_ACCOUNT_ALIAS,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::account::get_account_alias(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_DELETE_SERVICE_TOKEN,
+                delete({
+                    let state = self.state.clone();
+                    move |param1, param2| api::account::delete_service_token(param1, param2, state)
+                }),
+            )
+            .route(
+                api::organization::PATH_GET_ORGANIZATION,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::organization::get_organization(param1, state)
+                }),
+            )
+            .route(
+                api::organization::PATH_GET_ORGANIZATION_MEMBERS,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::organization::get_organization_members(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_NOTIFICATION_SETTINGS,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::account::get_notification_settings(param1, state)
+                })
+                .put({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::account::put_notification_settings(param1, param2, state)
+                    }
                 }),
             )
             .route(
@@ -39,6 +105,34 @@ impl ConnectedApp {
                     move |arg1, arg2| api::account::post_account_setup(arg1, arg2, state)
                 }),
             )
+            .route(
+                api::account::PATH_POST_DELETE,
+                post({
+                    let state = self.state.clone();
+                    move |param1| api::account::post_delete(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_POST_CANCEL_DELETE,
+                post({
+                    let state = self.state.clone();
+                    move |param1| api::account::post_cancel_delete(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_GET_DELETION_EXPORT,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::account::get_deletion_export(param1, state)
+                }),
+            )
+            .route(
+                api::account::PATH_GET_EXPORT,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::account::get_export(param1, state)
+                }),
+            )
             .route(
                 api::account::PATH_ACCOUNT_COMPLETE_SETUP,
                 post({
@@ -64,7 +158,9 @@ impl ConnectedApp {
                 api::calculator::PATH_GET_CALCULATOR_STATE,
                 get({
                     let state = self.state.clone();
-                    move |param1| api::calculator::get_calculator_state(param1, state)
+                    move |param1, param2| {
+                        api::calculator::get_calculator_state(param1, param2, state)
+                    }
                 }),
             )
             .route(
@@ -74,6 +170,130 @@ impl ConnectedApp {
                     move |header, body| api::calculator::post_calculator_state(header, body, state)
                 }),
             )
+            .route(
+                api::calculator::PATH_POST_CALCULATE,
+                post({
+                    let state = self.state.clone();
+                    move |param1, param2| api::calculator::post_calculate(param1, param2, state)
+                }),
+            )
+            .route(
+                api::calculator::PATH_GET_CALCULATION_SCHEDULES,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::calculator::get_calculation_schedules(param1, state)
+                })
+                .post({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::post_calculation_schedule(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_PUT_CALCULATION_SCHEDULE,
+                put({
+                    let state = self.state.clone();
+                    move |param1, param2, param3| {
+                        api::calculator::put_calculation_schedule(param1, param2, param3, state)
+                    }
+                })
+                .delete({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::delete_calculation_schedule(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_GET_CALCULATOR_SLOTS,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::calculator::get_calculator_slots(param1, state)
+                }),
+            )
+            .route(
+                api::calculator::PATH_PUT_CALCULATOR_SLOT,
+                put({
+                    let state = self.state.clone();
+                    move |param1, param2, param3| {
+                        api::calculator::put_calculator_slot(param1, param2, param3, state)
+                    }
+                })
+                .delete({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::delete_calculator_slot(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_CALCULATOR_SETTINGS,
+                get({
+                    let state = self.state.clone();
+                    move |param1| api::calculator::get_calculator_settings(param1, state)
+                })
+                .put({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::put_calculator_settings(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_GET_HISTORY_ENTRY,
+                get({
+                    let state = self.state.clone();
+                    move |param1, param2, param3| {
+                        api::calculator::get_history_entry(param1, param2, param3, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_POST_RERUN_HISTORY_ENTRY,
+                post({
+                    let state = self.state.clone();
+                    move |param1, param2, param3| {
+                        api::calculator::post_rerun_history_entry(param1, param2, param3, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_GET_SHARED_CALCULATOR_STATE,
+                get({
+                    let state = self.state.clone();
+                    move |param1, param2, param3| {
+                        api::calculator::get_shared_calculator_state(param1, param2, param3, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_POST_CALCULATOR_STATE_SHARE,
+                post({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::post_calculator_state_share(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_DELETE_CALCULATOR_STATE_SHARE,
+                delete({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::delete_calculator_state_share(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::calculator::PATH_PUT_CALCULATOR_BROADCAST,
+                put({
+                    let state = self.state.clone();
+                    move |param1, param2| {
+                        api::calculator::put_calculator_broadcast(param1, param2, state)
+                    }
+                }),
+            )
             .route_layer({
                 middleware::from_fn({
                     let state = self.state.clone();
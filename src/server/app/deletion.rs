@@ -0,0 +1,89 @@
+//! Deletes accounts which deletion grace period has ended
+
+use std::time::Duration;
+
+use error_stack::Result;
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    api::{model::AccountEventType, GetConfig, GetWebhooks, ReadDatabase, WriteDatabase},
+    server::database::DatabaseError,
+    utils::{QuitReceiver, QuitSender},
+};
+
+use super::AppState;
+
+const PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+#[derive(Debug)]
+pub struct AccountPurgerQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl AccountPurgerQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Account purger task join failed: {}", e),
+        }
+    }
+}
+
+pub struct AccountPurger {
+    state: AppState,
+}
+
+impl AccountPurger {
+    pub fn new_task(state: AppState) -> AccountPurgerQuitHandle {
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(Self { state }.run(quit_receiver));
+        AccountPurgerQuitHandle { task, quit }
+    }
+
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL_SECONDS));
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    match self.purge_expired_accounts().await {
+                        Ok(()) => (),
+                        Err(e) => error!("Purging deleted accounts failed: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn purge_expired_accounts(&self) -> Result<(), DatabaseError> {
+        let accounts = self
+            .state
+            .read_database()
+            .expired_account_deletions(self.state.config().unix_time())
+            .await?;
+
+        for account_id in accounts {
+            match self
+                .state
+                .write_database()
+                .account()
+                .delete_account(account_id)
+                .await
+            {
+                Ok(()) => {
+                    info!("Account {} deleted", account_id.as_light());
+                    self.state
+                        .webhooks()
+                        .send(AccountEventType::Purged, account_id.as_light());
+                }
+                Err(e) => error!("Deleting account failed: {e:?}"),
+            }
+        }
+
+        Ok(())
+    }
+}
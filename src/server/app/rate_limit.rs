@@ -0,0 +1,220 @@
+//! Token bucket rate limiting for the public API
+//!
+//! Every request is limited per client IP address. Requests with a valid
+//! access token are also limited per access token. Only access tokens which
+//! exist in the access token cache get a bucket, so invalid tokens can not
+//! be used for bypassing the IP address limit or for growing the bucket
+//! map.
+//!
+//! Bucket sizes and refill rates can be changed when the config is
+//! reloaded. Existing buckets keep their current token count.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use headers::{HeaderMapExt, RetryAfter};
+use hyper::{Request, StatusCode};
+
+use crate::{
+    api::{
+        model::{ApiKey, TokenHash},
+        utils::API_KEY_HEADER_STR,
+        GetApiKeys,
+    },
+    config::file::{RateLimitConfig, TokenBucketConfig},
+};
+
+/// Full buckets are removed when bucket count reaches this, so that the
+/// bucket map does not grow without limit.
+const BUCKET_CLEANUP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RateLimitKey {
+    AccessToken(TokenHash),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: TokenBucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, config: TokenBucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.requests_per_second).min(config.burst as f64);
+        self.updated = now;
+    }
+
+    /// Take one token. Returns time until next token is available if the
+    /// bucket is empty.
+    fn take(&mut self, config: TokenBucketConfig, now: Instant) -> Result<(), Duration> {
+        self.refill(config, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - self.tokens) / config.requests_per_second;
+            Err(Duration::from_secs_f64(wait))
+        }
+    }
+
+    fn is_full(&self, config: TokenBucketConfig, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * config.requests_per_second >= config.burst as f64
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RwLock<RateLimitConfig>,
+    buckets: Mutex<HashMap<RateLimitKey, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn update_config(&self, config: RateLimitConfig) {
+        match self.config.write() {
+            Ok(mut current) => *current = config,
+            Err(poisoned) => *poisoned.into_inner() = config,
+        }
+    }
+
+    fn config(&self) -> RateLimitConfig {
+        match self.config.read() {
+            Ok(config) => config.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn bucket_config(config: &RateLimitConfig, key: &RateLimitKey) -> TokenBucketConfig {
+        match key {
+            RateLimitKey::AccessToken(_) => config.access_token,
+            RateLimitKey::Ip(_) => config.ip,
+        }
+    }
+
+    /// Returns time until the request would be allowed if the request is
+    /// limited.
+    fn check(&self, key: RateLimitKey) -> Result<(), Duration> {
+        let now = Instant::now();
+        let limits = self.config();
+        let config = Self::bucket_config(&limits, &key);
+        let mut buckets = match self.buckets.lock() {
+            Ok(buckets) => buckets,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        if buckets.len() >= BUCKET_CLEANUP_THRESHOLD {
+            buckets.retain(|key, bucket| !bucket.is_full(Self::bucket_config(&limits, key), now));
+        }
+
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(config, now))
+            .take(config, now)
+    }
+}
+
+/// Middleware which responds with 429 and Retry-After header when the
+/// request is limited.
+pub async fn rate_limit<B, S: GetApiKeys>(
+    State((limiter, state)): State<(Arc<RateLimiter>, S)>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let access_token = req
+        .headers()
+        .get(API_KEY_HEADER_STR)
+        .and_then(|header| header.to_str().ok())
+        .map(|token| ApiKey::new(token.to_string()));
+    let access_token = match access_token {
+        Some(token) if state.api_keys().api_key_exists(&token).await.is_some() => Some(token),
+        _ => None,
+    };
+
+    let result = limiter
+        .check(RateLimitKey::Ip(addr.ip()))
+        .and_then(|()| match access_token {
+            Some(token) => limiter.check(RateLimitKey::AccessToken(token.hash())),
+            None => Ok(()),
+        });
+
+    match result {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            // Retry-After has second precision, so round up.
+            let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
+            response
+                .headers_mut()
+                .typed_insert(RetryAfter::delay(Duration::from_secs(seconds)));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        api::{account::PATH_ACCOUNT_STATE, model::ApiKey},
+        config::file::{RateLimitConfig, TokenBucketConfig},
+        server::test_support::TestApp,
+    };
+
+    #[tokio::test]
+    async fn invalid_access_token_is_limited_per_ip_address() {
+        let app = TestApp::with_config(|config| {
+            config.rate_limit = Some(RateLimitConfig {
+                access_token: TokenBucketConfig {
+                    burst: 100,
+                    requests_per_second: 100.0,
+                },
+                ip: TokenBucketConfig {
+                    burst: 2,
+                    requests_per_second: 0.001,
+                },
+            })
+        })
+        .await;
+
+        for i in 0..3 {
+            let token = ApiKey::new(format!("invalid-{i}"));
+            let response = app.get(PATH_ACCOUNT_STATE, Some(&token)).await;
+            let expected = if i < 2 {
+                StatusCode::UNAUTHORIZED
+            } else {
+                StatusCode::TOO_MANY_REQUESTS
+            };
+            assert_eq!(response.status(), expected);
+        }
+
+        app.close().await;
+    }
+}
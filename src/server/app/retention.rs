@@ -0,0 +1,70 @@
+//! Deletes calculator history entries which are older than the history
+//! retention
+
+use std::time::Duration;
+
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    api::{GetConfig, WriteDatabase},
+    utils::{QuitReceiver, QuitSender},
+};
+
+use super::AppState;
+
+const PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+#[derive(Debug)]
+pub struct HistoryPurgerQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl HistoryPurgerQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("History purger task join failed: {}", e),
+        }
+    }
+}
+
+pub struct HistoryPurger {
+    state: AppState,
+}
+
+impl HistoryPurger {
+    pub fn new_task(state: AppState) -> HistoryPurgerQuitHandle {
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(Self { state }.run(quit_receiver));
+        HistoryPurgerQuitHandle { task, quit }
+    }
+
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL_SECONDS));
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => self.purge_expired_history_entries().await,
+            }
+        }
+    }
+
+    async fn purge_expired_history_entries(&self) {
+        let retention_days = self.state.config().history_retention_days();
+        match self
+            .state
+            .write_database()
+            .calculator()
+            .delete_expired_history_entries(retention_days)
+            .await
+        {
+            Ok(0) => (),
+            Ok(count) => info!("{count} expired history entries deleted"),
+            Err(e) => error!("Purging history entries failed: {e:?}"),
+        }
+    }
+}
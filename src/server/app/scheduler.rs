@@ -0,0 +1,128 @@
+//! Evaluates scheduled calculations
+
+use std::time::Duration;
+
+use error_stack::Result;
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::error;
+
+use crate::{
+    api::{
+        model::{CalculatorSettings, NewHistoryEntry},
+        GetConfig, GetEvaluationCache, GetOperations, ReadDatabase, WriteDatabase,
+    },
+    calculator::OperationRegistry,
+    server::database::DatabaseError,
+    utils::{QuitReceiver, QuitSender},
+};
+
+use super::AppState;
+
+const SCHEDULER_INTERVAL_SECONDS: u64 = 5;
+
+#[derive(Debug)]
+pub struct CalculationSchedulerQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl CalculationSchedulerQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Calculation scheduler task join failed: {}", e),
+        }
+    }
+}
+
+pub struct CalculationScheduler {
+    state: AppState,
+}
+
+impl CalculationScheduler {
+    pub fn new_task(state: AppState) -> CalculationSchedulerQuitHandle {
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(Self { state }.run(quit_receiver));
+        CalculationSchedulerQuitHandle { task, quit }
+    }
+
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(Duration::from_secs(SCHEDULER_INTERVAL_SECONDS));
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    match self.run_due_schedules().await {
+                        Ok(()) => (),
+                        Err(e) => error!("Running scheduled calculations failed: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_due_schedules(&self) -> Result<(), DatabaseError> {
+        let now = self.state.config().unix_time();
+        let schedules = self
+            .state
+            .read_database()
+            .due_calculation_schedules(now)
+            .await?;
+
+        let limits = self.state.config().evaluation_limits();
+        for (account_id, schedule) in schedules {
+            let settings = match self
+                .state
+                .read_database()
+                .read_json::<CalculatorSettings>(account_id)
+                .await
+            {
+                Ok(settings) => settings,
+                Err(e) => {
+                    error!("Reading calculator settings failed: {e:?}");
+                    continue;
+                }
+            };
+
+            let organization_operations = match self
+                .state
+                .read_database()
+                .account_organization_settings(account_id)
+                .await
+            {
+                Ok(settings) => settings
+                    .operation_sets
+                    .map(|sets| OperationRegistry::with_builtin_operations(&sets)),
+                Err(e) => {
+                    error!("Reading organization settings failed: {e:?}");
+                    continue;
+                }
+            };
+
+            let evaluation = self.state.evaluation_cache().evaluate(
+                &schedule.expression,
+                organization_operations
+                    .as_ref()
+                    .unwrap_or_else(|| self.state.operations()),
+                &limits,
+                settings.angle_mode,
+            );
+            let entry =
+                NewHistoryEntry::from_evaluation(schedule.expression.clone(), evaluation, now);
+
+            let result = self
+                .state
+                .write_database()
+                .calculator()
+                .complete_scheduled_calculation(account_id, schedule, entry)
+                .await;
+            if let Err(e) = result {
+                error!("Completing scheduled calculation failed: {e:?}");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,16 +1,36 @@
 pub mod apple;
 pub mod google;
+#[cfg(test)]
+pub mod mock;
 
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use error_stack::Result;
 
 use crate::config::Config;
 
 use self::{
-    apple::{AppleAccountId, SignInWithAppleError, SignInWithAppleManager},
+    apple::{AppleAccountInfo, SignInWithAppleError, SignInWithAppleManager},
     google::{GoogleAccountInfo, SignInWithGoogleError, SignInWithGoogleManager},
 };
+
+/// Validates tokens which clients got from Google or Apple sign in.
+#[async_trait]
+pub trait SignInWithProvider: Send + Sync {
+    async fn validate_google_token(
+        &self,
+        token: String,
+    ) -> Result<GoogleAccountInfo, SignInWithGoogleError>;
+
+    async fn validate_apple_token(
+        &self,
+        token: String,
+    ) -> Result<AppleAccountInfo, SignInWithAppleError>;
+}
+
+/// Validates tokens with the public keys which are downloaded from Google
+/// and Apple.
 pub struct SignInWithManager {
     google: SignInWithGoogleManager,
     apple: SignInWithAppleManager,
@@ -24,18 +44,21 @@ impl SignInWithManager {
             apple: SignInWithAppleManager::new(config.clone(), client.clone()),
         }
     }
+}
 
-    pub async fn validate_google_token(
+#[async_trait]
+impl SignInWithProvider for SignInWithManager {
+    async fn validate_google_token(
         &self,
         token: String,
     ) -> Result<GoogleAccountInfo, SignInWithGoogleError> {
         self.google.validate_google_token(token).await
     }
 
-    pub async fn validate_apple_token(
+    async fn validate_apple_token(
         &self,
         token: String,
-    ) -> Result<AppleAccountId, SignInWithAppleError> {
+    ) -> Result<AppleAccountInfo, SignInWithAppleError> {
         self.apple.validate_apple_token(token).await
     }
 }
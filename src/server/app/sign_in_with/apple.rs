@@ -1,39 +1,225 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use error_stack::{IntoReport, Result};
+use error_stack::Result;
 
-use tracing::{error, info};
+use headers::{CacheControl, HeaderMapExt};
+use hyper::Method;
+
+use jsonwebtoken::{
+    jwk::{Jwk, JwkSet},
+    DecodingKey, Validation,
+};
+use serde::Deserialize;
+
+use tokio::sync::RwLock;
 
 use crate::{config::Config, utils::IntoReportExt};
 
+/// Apple identity token iss field (issuer) value.
+const ISS_VALUE_APPLE: &str = "https://appleid.apple.com";
+
+/// Apple public keys are cached this long if the key download response does
+/// not have max age in cache control header.
+const DEFAULT_PUBLIC_KEY_CACHE_TIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Apple rotates keys, so unknown kid downloads the keys again. Minimum time
+/// between downloads prevents downloading keys for every invalid token.
+const MIN_PUBLIC_KEY_DOWNLOAD_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(thiserror::Error, Debug)]
 pub enum SignInWithAppleError {
     #[error("Token (from client) header parsing failed")]
     InvalidTokenHeader,
 
-    #[error("Token was invalid")]
+    #[error("Token from client was invalid")]
     InvalidToken,
+
+    #[error("Couldn't download Apple public key")]
+    PublicKeyDownloadFailed,
+
+    #[error("Token kid property not found from token received from client")]
+    MissingJwtKid,
+
+    #[error("Parsing HTTP GET for Apple public key response cache control header failed.")]
+    ParsingCacheControlHeader,
+
+    #[error("Max age related time calculation failed")]
+    CacheCalculation,
+
+    #[error("HTTP GET for Apple public keys didn't contain valid JwkSet")]
+    JwkSetParsingFailed,
+
+    #[error("Requested Jwk was not found")]
+    JwkNotFound,
+
+    #[error("Decoding key generation failed")]
+    DecodingKeyGenerationFailed,
+
+    #[error("Apple public key missing algorithm field")]
+    ApplePublicKeyMissingAlgorithmField,
+
+    #[error("Sign in with Apple is not enabled from server settings file")]
+    NotEnabled,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppleTokenClaims {
+    /// Apple account ID
+    sub: String,
 }
 
-pub struct AppleAccountId(String);
+#[derive(Debug, Clone)]
+pub struct AppleAccountInfo {
+    pub id: String,
+}
+
+struct ApplePublicKeys {
+    keys: JwkSet,
+    valid_until_this: Instant,
+    downloaded: Instant,
+}
+
+enum KeyStatus {
+    Found(Jwk),
+    KeyRefreshNeeded,
+}
 
 pub struct SignInWithAppleManager {
     client: reqwest::Client,
     config: Arc<Config>,
+    apple_public_keys: RwLock<Option<ApplePublicKeys>>,
 }
 
 impl SignInWithAppleManager {
     pub fn new(config: Arc<Config>, client: reqwest::Client) -> Self {
-        Self { client, config }
+        Self {
+            client,
+            config,
+            apple_public_keys: RwLock::new(None),
+        }
     }
+
     pub async fn validate_apple_token(
         &self,
         token: String,
-    ) -> Result<AppleAccountId, SignInWithAppleError> {
+    ) -> Result<AppleAccountInfo, SignInWithAppleError> {
+        let apple_config = self
+            .config
+            .sign_in_with_apple_config()
+            .ok_or(SignInWithAppleError::NotEnabled)?;
+
         let not_validated_header = jsonwebtoken::decode_header(&token)
             .into_error(SignInWithAppleError::InvalidTokenHeader)?;
-        info!("{:?}", &not_validated_header);
+        let wanted_kid = not_validated_header
+            .kid
+            .ok_or(SignInWithAppleError::MissingJwtKid)?;
+
+        let apple_public_key = self.get_apple_public_key(&wanted_kid).await?;
+
+        let key = DecodingKey::from_jwk(&apple_public_key)
+            .into_error(SignInWithAppleError::DecodingKeyGenerationFailed)?;
+
+        let mut v = Validation::new(
+            apple_public_key
+                .common
+                .algorithm
+                .ok_or(SignInWithAppleError::ApplePublicKeyMissingAlgorithmField)?,
+        );
+        v.set_required_spec_claims(&["exp", "aud", "iss"]);
+        v.set_issuer(&[ISS_VALUE_APPLE]);
+        let mut audience = vec![apple_config.app_bundle_id.as_str()];
+        if let Some(service_id) = &apple_config.service_id {
+            audience.push(service_id.as_str());
+        }
+        v.set_audience(&audience);
+
+        let data = jsonwebtoken::decode::<AppleTokenClaims>(&token, &key, &v)
+            .into_error(SignInWithAppleError::InvalidToken)?;
+
+        Ok(AppleAccountInfo {
+            id: data.claims.sub,
+        })
+    }
+
+    async fn get_apple_public_key(&self, wanted_kid: &str) -> Result<Jwk, SignInWithAppleError> {
+        match self
+            .get_apple_public_key_from_local_keys(wanted_kid)
+            .await?
+        {
+            KeyStatus::Found(key) => Ok(key),
+            KeyStatus::KeyRefreshNeeded => {
+                self.download_apple_public_keys_and_get_key(wanted_kid)
+                    .await
+            }
+        }
+    }
+
+    async fn get_apple_public_key_from_local_keys(
+        &self,
+        wanted_kid: &str,
+    ) -> Result<KeyStatus, SignInWithAppleError> {
+        let keys = self.apple_public_keys.read().await;
+        match keys.as_ref() {
+            None => Ok(KeyStatus::KeyRefreshNeeded),
+            Some(keys) => {
+                if Instant::now() >= keys.valid_until_this {
+                    return Ok(KeyStatus::KeyRefreshNeeded);
+                }
+                match keys.keys.find(wanted_kid) {
+                    Some(jwk) => Ok(KeyStatus::Found(jwk.clone())),
+                    None if keys.downloaded.elapsed() >= MIN_PUBLIC_KEY_DOWNLOAD_INTERVAL => {
+                        Ok(KeyStatus::KeyRefreshNeeded)
+                    }
+                    None => Err(SignInWithAppleError::JwkNotFound.into()),
+                }
+            }
+        }
+    }
+
+    async fn download_apple_public_keys_and_get_key(
+        &self,
+        wanted_kid: &str,
+    ) -> Result<Jwk, SignInWithAppleError> {
+        let download_request = reqwest::Request::new(
+            Method::GET,
+            self.config.sign_in_with_urls().apple_public_keys.clone(),
+        );
+
+        let r = self
+            .client
+            .execute(download_request)
+            .await
+            .into_error(SignInWithAppleError::PublicKeyDownloadFailed)?;
+
+        let max_age = r
+            .headers()
+            .typed_try_get::<CacheControl>()
+            .into_error(SignInWithAppleError::ParsingCacheControlHeader)?
+            .and_then(|header| header.max_age())
+            .unwrap_or(DEFAULT_PUBLIC_KEY_CACHE_TIME);
+        let downloaded = Instant::now();
+        let valid_until_this = downloaded
+            .checked_add(max_age)
+            .ok_or(SignInWithAppleError::CacheCalculation)?;
+
+        let jwk_set: JwkSet = r
+            .json()
+            .await
+            .into_error(SignInWithAppleError::JwkSetParsingFailed)?;
+        let mut key_store = self.apple_public_keys.write().await;
+        *key_store = Some(ApplePublicKeys {
+            keys: jwk_set.clone(),
+            valid_until_this,
+            downloaded,
+        });
 
-        Err(SignInWithAppleError::InvalidToken).into_report()
+        let jwk = jwk_set
+            .find(wanted_kid)
+            .ok_or(SignInWithAppleError::JwkNotFound)?
+            .clone();
+        Ok(jwk)
     }
 }
@@ -77,6 +77,7 @@ struct GoogleTokenClaims {
     email_verified: bool,
 }
 
+#[derive(Debug, Clone)]
 pub struct GoogleAccountInfo {
     pub id: String,
     pub email: String,
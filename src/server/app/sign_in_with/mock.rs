@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, Result};
+
+use super::{
+    apple::{AppleAccountInfo, SignInWithAppleError},
+    google::{GoogleAccountInfo, SignInWithGoogleError},
+    SignInWithProvider,
+};
+
+/// Accepts only the tokens which are added to it. Other tokens are
+/// invalid.
+#[derive(Default)]
+pub struct MockSignInWith {
+    google: HashMap<String, GoogleAccountInfo>,
+    apple: HashMap<String, AppleAccountInfo>,
+}
+
+impl MockSignInWith {
+    pub fn with_google_account(mut self, token: &str, id: &str, email: &str) -> Self {
+        let info = GoogleAccountInfo {
+            id: id.to_string(),
+            email: email.to_string(),
+        };
+        self.google.insert(token.to_string(), info);
+        self
+    }
+
+    pub fn with_apple_account(mut self, token: &str, id: &str) -> Self {
+        let info = AppleAccountInfo { id: id.to_string() };
+        self.apple.insert(token.to_string(), info);
+        self
+    }
+}
+
+#[async_trait]
+impl SignInWithProvider for MockSignInWith {
+    async fn validate_google_token(
+        &self,
+        token: String,
+    ) -> Result<GoogleAccountInfo, SignInWithGoogleError> {
+        self.google
+            .get(&token)
+            .cloned()
+            .ok_or(SignInWithGoogleError::InvalidToken)
+            .into_report()
+    }
+
+    async fn validate_apple_token(
+        &self,
+        token: String,
+    ) -> Result<AppleAccountInfo, SignInWithAppleError> {
+        self.apple
+            .get(&token)
+            .cloned()
+            .ok_or(SignInWithAppleError::InvalidToken)
+            .into_report()
+    }
+}
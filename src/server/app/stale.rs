@@ -0,0 +1,204 @@
+//! Finds accounts which have been inactive longer than the stale account
+//! period and notifies them or schedules their deletion in batches
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::api::{
+    account::request_deletion_impl,
+    admin::{
+        StaleAccountCleanupRequest, StaleAccountCleanupStatus, DEFAULT_STALE_ACCOUNT_BATCH_SIZE,
+    },
+    model::{Account, AccountEventType, AccountIdInternal, AccountState},
+    GetConfig, GetExports, GetWebhooks, ReadDatabase, WriteDatabase,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum StaleAccountCleanupError {
+    #[error("Stale account cleanup is already running")]
+    AlreadyRunning,
+}
+
+/// Only one cleanup runs at a time. Status of the latest cleanup is kept
+/// until the next cleanup starts.
+#[derive(Debug, Default)]
+pub struct StaleAccountCleanup {
+    status: Arc<Mutex<Option<StaleAccountCleanupStatus>>>,
+}
+
+impl StaleAccountCleanup {
+    pub async fn status(&self) -> Option<StaleAccountCleanupStatus> {
+        self.status.lock().await.clone()
+    }
+
+    /// Start cleanup task. Returns the initial status.
+    pub async fn start<
+        S: ReadDatabase + WriteDatabase + GetExports + GetConfig + GetWebhooks + Send + Sync + 'static,
+    >(
+        &self,
+        state: S,
+        request: StaleAccountCleanupRequest,
+    ) -> Result<StaleAccountCleanupStatus, StaleAccountCleanupError> {
+        let mut current = self.status.lock().await;
+        if current.as_ref().map(|s| s.running).unwrap_or(false) {
+            return Err(StaleAccountCleanupError::AlreadyRunning);
+        }
+
+        let unix_time = state.config().unix_time();
+        let inactive_days = request
+            .inactive_days
+            .unwrap_or(state.config().stale_account_days());
+        let status = StaleAccountCleanupStatus {
+            running: true,
+            started_unix_time: unix_time,
+            finished_unix_time: None,
+            inactive_before_unix_time: unix_time - i64::from(inactive_days) * 24 * 60 * 60,
+            notify: request.notify,
+            schedule_deletion: request.schedule_deletion,
+            found: 0,
+            notified: 0,
+            deletion_scheduled: 0,
+            skipped: 0,
+            failed: 0,
+        };
+        *current = Some(status.clone());
+
+        info!(
+            "Stale account cleanup started, inactive_days: {inactive_days}, notify: {}, schedule_deletion: {}",
+            request.notify, request.schedule_deletion,
+        );
+
+        let task = CleanupTask {
+            state,
+            status: self.status.clone(),
+            batch_size: request
+                .batch_size
+                .unwrap_or(DEFAULT_STALE_ACCOUNT_BATCH_SIZE),
+        };
+        tokio::spawn(task.run());
+
+        Ok(status)
+    }
+}
+
+struct CleanupTask<S> {
+    state: S,
+    status: Arc<Mutex<Option<StaleAccountCleanupStatus>>>,
+    batch_size: u32,
+}
+
+impl<S: ReadDatabase + WriteDatabase + GetExports + GetConfig + GetWebhooks> CleanupTask<S> {
+    async fn run(self) {
+        let (inactive_before, notify, schedule_deletion) = match self.status.lock().await.as_ref() {
+            Some(s) => (s.inactive_before_unix_time, s.notify, s.schedule_deletion),
+            None => return,
+        };
+
+        let mut after_row_id = 0;
+        loop {
+            let ids = match self
+                .state
+                .read_database()
+                .stale_accounts(inactive_before, after_row_id, i64::from(self.batch_size))
+                .await
+            {
+                Ok(ids) => ids,
+                Err(e) => {
+                    error!("Stale account cleanup failed: {e:?}");
+                    break;
+                }
+            };
+
+            let last = match ids.last() {
+                Some(last) => *last,
+                None => break,
+            };
+            after_row_id = last.row_id();
+
+            let mut batch = BatchCounts::default();
+            for id in ids {
+                self.handle_account(id, notify, schedule_deletion, &mut batch)
+                    .await;
+            }
+
+            let mut status = self.status.lock().await;
+            if let Some(status) = status.as_mut() {
+                batch.add_to(status);
+                info!(
+                    "Stale account cleanup progress, found: {}, notified: {}, deletion_scheduled: {}, skipped: {}, failed: {}",
+                    status.found,
+                    status.notified,
+                    status.deletion_scheduled,
+                    status.skipped,
+                    status.failed,
+                );
+            }
+        }
+
+        if let Some(status) = self.status.lock().await.as_mut() {
+            status.running = false;
+            status.finished_unix_time = Some(self.state.config().unix_time());
+        }
+        info!("Stale account cleanup finished");
+    }
+
+    async fn handle_account(
+        &self,
+        id: AccountIdInternal,
+        notify: bool,
+        schedule_deletion: bool,
+        batch: &mut BatchCounts,
+    ) {
+        batch.found += 1;
+
+        let account = match self.state.read_database().read_json::<Account>(id).await {
+            Ok(account) => account,
+            Err(e) => {
+                error!("Stale account cleanup: {e:?}");
+                batch.failed += 1;
+                return;
+            }
+        };
+        if account.state() != AccountState::Normal {
+            batch.skipped += 1;
+            return;
+        }
+
+        if notify {
+            self.state
+                .webhooks()
+                .send(AccountEventType::Inactive, id.as_light());
+            batch.notified += 1;
+        }
+
+        if schedule_deletion {
+            match request_deletion_impl(&self.state, id, account.state()).await {
+                Ok(_) => batch.deletion_scheduled += 1,
+                Err(_) => batch.failed += 1,
+            }
+        }
+    }
+}
+
+/// Counts of one batch, so the status lock is not held while accounts are
+/// handled.
+#[derive(Debug, Default)]
+struct BatchCounts {
+    found: u64,
+    notified: u64,
+    deletion_scheduled: u64,
+    skipped: u64,
+    failed: u64,
+}
+
+impl BatchCounts {
+    fn add_to(&self, status: &mut StaleAccountCleanupStatus) {
+        status.found += self.found;
+        status.notified += self.notified;
+        status.deletion_scheduled += self.deletion_scheduled;
+        status.skipped += self.skipped;
+        status.failed += self.failed;
+    }
+}
@@ -0,0 +1,162 @@
+//! Time source for expiration and scheduling decisions
+//!
+//! Code which compares times to token lifetimes, retention periods or
+//! schedules gets the time from the [Clock] of the config, so tests can move
+//! the time forward with [MockClock] instead of waiting.
+//!
+//! Saved times are UTC seconds since the Unix epoch. [UtcTimestamp] is
+//! stored to the database as an integer and serialized as RFC 3339 text to
+//! logs and files.
+
+use std::{
+    fmt::{self, Debug, Display},
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+pub trait Clock: Debug + Send + Sync {
+    fn unix_time(&self) -> i64;
+
+    fn now(&self) -> UtcTimestamp {
+        UtcTimestamp::from_unix_time(self.unix_time())
+    }
+}
+
+/// System time.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn unix_time(&self) -> i64 {
+        OffsetDateTime::now_utc().unix_timestamp()
+    }
+}
+
+/// System time with an offset which tests can change. Time still passes
+/// normally between the changes.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    offset_seconds: AtomicI64,
+}
+
+impl MockClock {
+    /// Move the time forward. Returns the new time.
+    pub fn advance(&self, seconds: i64) -> i64 {
+        self.offset_seconds.fetch_add(seconds, Ordering::Relaxed);
+        self.unix_time()
+    }
+
+    pub fn offset_seconds(&self) -> i64 {
+        self.offset_seconds.load(Ordering::Relaxed)
+    }
+}
+
+impl Clock for MockClock {
+    fn unix_time(&self) -> i64 {
+        SystemClock.unix_time() + self.offset_seconds()
+    }
+}
+
+/// UTC time with second precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct UtcTimestamp(i64);
+
+impl UtcTimestamp {
+    /// System time. Use [Clock::now] for expiration and scheduling
+    /// decisions.
+    pub fn now() -> Self {
+        SystemClock.now()
+    }
+
+    pub const fn from_unix_time(unix_time: i64) -> Self {
+        Self(unix_time)
+    }
+
+    /// Seconds since the Unix epoch.
+    pub const fn unix_time(self) -> i64 {
+        self.0
+    }
+
+    /// None if the time is outside the range of [OffsetDateTime].
+    fn to_offset_date_time(self) -> Option<OffsetDateTime> {
+        OffsetDateTime::from_unix_timestamp(self.0).ok()
+    }
+
+    pub fn parse_rfc3339(text: &str) -> Option<Self> {
+        OffsetDateTime::parse(text, &Rfc3339)
+            .ok()
+            .map(|time| Self(time.unix_timestamp()))
+    }
+}
+
+impl From<UtcTimestamp> for i64 {
+    fn from(value: UtcTimestamp) -> Self {
+        value.unix_time()
+    }
+}
+
+/// RFC 3339 text like `2023-04-01T12:00:00Z`. Times which can not be
+/// formatted are displayed as Unix time.
+impl Display for UtcTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self
+            .to_offset_date_time()
+            .and_then(|time| time.format(&Rfc3339).ok())
+        {
+            Some(text) => f.write_str(&text),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Serialize for UtcTimestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for UtcTimestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Self::parse_rfc3339(&text)
+            .ok_or_else(|| de::Error::custom(format!("Invalid RFC 3339 time {text}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::UtcTimestamp;
+
+    // Years 0000-9999 can be formatted as RFC 3339.
+    const MIN_UNIX_TIME: i64 = -62_167_219_200;
+    const MAX_UNIX_TIME: i64 = 253_402_300_799;
+
+    #[test]
+    fn display_is_rfc3339_in_utc() {
+        let time = UtcTimestamp::from_unix_time(1_680_350_400);
+        assert_eq!(time.to_string(), "2023-04-01T12:00:00Z");
+    }
+
+    proptest! {
+        #[test]
+        fn json_round_trip(unix_time in MIN_UNIX_TIME..=MAX_UNIX_TIME) {
+            let time = UtcTimestamp::from_unix_time(unix_time);
+            let json = serde_json::to_string(&time).unwrap();
+            prop_assert_eq!(serde_json::from_str::<UtcTimestamp>(&json).unwrap(), time);
+        }
+
+        #[test]
+        fn text_order_matches_time_order(
+            a in MIN_UNIX_TIME..=MAX_UNIX_TIME,
+            b in MIN_UNIX_TIME..=MAX_UNIX_TIME,
+        ) {
+            let (a, b) = (UtcTimestamp::from_unix_time(a), UtcTimestamp::from_unix_time(b));
+            prop_assert_eq!(a.to_string().cmp(&b.to_string()), a.cmp(&b));
+        }
+    }
+}
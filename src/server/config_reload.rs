@@ -0,0 +1,162 @@
+//! Config reloading without server restart
+//!
+//! The config file is loaded again when the server receives SIGHUP or when
+//! the reload route is called. Only log level, rate limits and TLS
+//! certificates are applied to the running server. Other changes, like
+//! enabling or disabling rate limiting or TLS, require a restart.
+
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use tokio::{
+    sync::{oneshot, watch},
+    task::JoinHandle,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{reload_config, Config},
+    utils::{QuitReceiver, QuitSender},
+};
+
+use super::{app::rate_limit::RateLimiter, logging::LogLevelHandle};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigReloadError {
+    #[error("Loading config failed")]
+    LoadConfig,
+}
+
+/// Latest successfully loaded config.
+#[derive(Debug, Clone)]
+pub struct ConfigHandle {
+    receiver: watch::Receiver<Arc<Config>>,
+}
+
+impl ConfigHandle {
+    pub fn current(&self) -> Arc<Config> {
+        self.receiver.borrow().clone()
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigReloader {
+    sender: watch::Sender<Arc<Config>>,
+    log_level: LogLevelHandle,
+    /// Available if rate limiting is enabled.
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config: Arc<Config>,
+        log_level: LogLevelHandle,
+        rate_limiter: Option<Arc<RateLimiter>>,
+    ) -> Self {
+        let (sender, _) = watch::channel(config);
+        Self {
+            sender,
+            log_level,
+            rate_limiter,
+        }
+    }
+
+    pub fn handle(&self) -> ConfigHandle {
+        ConfigHandle {
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    /// Load the config file and apply the supported changes. The current
+    /// config is not changed if loading fails.
+    pub fn reload(&self) -> Result<(), ConfigReloadError> {
+        let current = self.sender.borrow().clone();
+        let new = reload_config(&current).change_context(ConfigReloadError::LoadConfig)?;
+
+        self.log_level.set_level(&new);
+
+        if current.logging().map(|logging| logging.format)
+            != new.logging().map(|logging| logging.format)
+        {
+            warn!("Changing log format requires restart");
+        }
+
+        match (&self.rate_limiter, new.rate_limit()) {
+            (Some(limiter), Some(rate_limit)) => limiter.update_config(rate_limit.clone()),
+            (None, None) => (),
+            _ => warn!("Enabling or disabling rate limiting requires restart"),
+        }
+
+        if current.public_api_tls_config().is_some() != new.public_api_tls_config().is_some() {
+            warn!("Enabling or disabling TLS requires restart");
+        }
+
+        self.sender.send_replace(Arc::new(new));
+        info!("Config reloaded");
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigReloadQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl ConfigReloadQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Config reload task join failed: {}", e),
+        }
+    }
+}
+
+/// Reloads config when the server receives SIGHUP.
+pub struct ConfigReloadSignalListener {
+    reloader: Arc<ConfigReloader>,
+}
+
+impl ConfigReloadSignalListener {
+    pub fn new_task(reloader: Arc<ConfigReloader>) -> ConfigReloadQuitHandle {
+        let listener = Self { reloader };
+
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(listener.run(quit_receiver));
+
+        ConfigReloadQuitHandle { task, quit }
+    }
+
+    #[cfg(unix)]
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                error!("Failed to listen SIGHUP. Error: {}", e);
+                let _ = quit_receiver.await;
+                return;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = hangup.recv() => {
+                    info!("SIGHUP received");
+                    match self.reloader.reload() {
+                        Ok(()) => (),
+                        Err(e) => error!("Config reload failed: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn run(self, quit_receiver: QuitReceiver) {
+        let _ = quit_receiver.await;
+    }
+}
@@ -1,8 +1,18 @@
+pub mod backup;
 pub mod cache;
 pub mod commands;
 pub mod current;
+pub mod disk;
+pub mod export;
+pub mod history;
+pub mod json_migration;
+pub mod migrate;
+pub mod postgres;
 pub mod read;
+pub mod replication;
 pub mod sqlite;
+pub mod standby;
+pub mod storage;
 pub mod utils;
 pub mod write;
 
@@ -18,25 +28,45 @@ use tracing::info;
 
 use crate::{
     api::model::{AccountIdInternal, AccountIdLight, SignInWithInfo},
-    config::Config,
-    server::database::{commands::WriteCommandRunner, sqlite::print_sqlite_version},
+    config::{
+        file::{DatabaseBackend, HotStandbyMode},
+        Config,
+    },
+    server::{
+        database::{commands::WriteCommandRunner, sqlite::print_sqlite_version},
+        event::EventManager,
+    },
 };
 
 use self::{
+    backup::{BackupManager, BackupQuitHandle},
     cache::DatabaseCache,
     commands::{WriteCommandRunnerHandle, WriteCommandRunnerQuitHandle},
-    current::SqliteReadCommands,
+    current::{
+        CurrentDataCloseHandle, CurrentDataReadHandle, CurrentDataWriteHandle, CurrentReadCommands,
+    },
+    disk::{check_disk_space, DiskSpace, DiskSpaceLimits},
+    export::{ExportStorage, DB_EXPORTS_DIR_NAME},
+    history::HistoryWriteHandle,
+    migrate::check_no_pending_migrations,
+    postgres::PostgresHandle,
     read::ReadCommands,
+    replication::{ReplicationQuitHandle, WalShipper},
     sqlite::{
-        CurrentDataWriteHandle, DatabaseType, SqliteDatabasePath, SqliteReadCloseHandle,
-        SqliteReadHandle, SqliteWriteCloseHandle, SqliteWriteHandle,
+        DatabaseType, SqliteDatabasePath, SqliteReadHandle, SqliteWriteCloseHandle,
+        SqliteWriteHandle,
     },
+    standby::{HotStandbyQuitHandle, HotStandbySender, DB_STANDBY_DIR_NAME},
+    storage::LocalStorage,
     utils::{AccountIdManager, ApiKeyManager},
     write::{WriteCommands, WriteCommandsAccount},
 };
 use crate::utils::IntoReportExt;
 
 pub const DB_CURRENT_DATA_DIR_NAME: &str = "current";
+pub const DB_HISTORY_DATA_DIR_NAME: &str = "history";
+pub const DB_BACKUPS_DIR_NAME: &str = "backups";
+pub const DB_JOURNALS_DIR_NAME: &str = "journals";
 
 pub type DatabeseEntryId = String;
 
@@ -53,6 +83,10 @@ pub enum DatabaseError {
     CommandSendingFailed,
     #[error("Database command result receiving failed")]
     CommandResultReceivingFailed,
+    #[error("Account has too many pending write commands")]
+    TooManyPendingWrites,
+    #[error("Write command queue of the account's shard is full")]
+    WriteQueueBusy,
 
     // Other errors
     #[error("Database initialization error")]
@@ -64,28 +98,97 @@ pub enum DatabaseError {
 
     #[error("Command runner quit too early")]
     CommandRunnerQuit,
+
+    #[error("Hot standby error")]
+    HotStandby,
+
+    #[error("Backup error")]
+    Backup,
+
+    #[error("Replication error")]
+    Replication,
+
+    #[error("Not enough disk space")]
+    DiskSpace,
+}
+
+/// Subdirectories of the database root directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseDir {
+    /// Current SQLite database.
+    Current,
+    /// History SQLite database.
+    History,
+    /// Account data export bundles.
+    Exports,
+    Backups,
+    Journals,
+    /// Hot standby snapshots.
+    Standby,
 }
 
-/// Absolsute path to database root directory.
+impl DatabaseDir {
+    pub const ALL: &'static [DatabaseDir] = &[
+        Self::Current,
+        Self::History,
+        Self::Exports,
+        Self::Backups,
+        Self::Journals,
+        Self::Standby,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Current => DB_CURRENT_DATA_DIR_NAME,
+            Self::History => DB_HISTORY_DATA_DIR_NAME,
+            Self::Exports => DB_EXPORTS_DIR_NAME,
+            Self::Backups => DB_BACKUPS_DIR_NAME,
+            Self::Journals => DB_JOURNALS_DIR_NAME,
+            Self::Standby => DB_STANDBY_DIR_NAME,
+        }
+    }
+}
+
+/// Absolsute path to database root directory. All subdirectories are
+/// created when this is created.
 pub struct DatabaseRoot {
     root: PathBuf,
     current: SqliteDatabasePath,
+    history: SqliteDatabasePath,
+    exports: ExportStorage,
+    disk_space_limits: DiskSpaceLimits,
 }
 
 impl DatabaseRoot {
-    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self, DatabaseError> {
+    pub fn new<T: AsRef<Path>>(
+        path: T,
+        disk_space_limits: DiskSpaceLimits,
+    ) -> Result<Self, DatabaseError> {
         let root = path.as_ref().to_path_buf();
         if !root.exists() {
             fs::create_dir(&root).into_error(DatabaseError::Init)?;
         }
 
-        let current = root.join(DB_CURRENT_DATA_DIR_NAME);
-        if !current.exists() {
-            fs::create_dir(&current).into_error(DatabaseError::Init)?;
+        for dir in DatabaseDir::ALL {
+            let dir = root.join(dir.name());
+            if !dir.exists() {
+                fs::create_dir(&dir).into_error(DatabaseError::Init)?;
+            }
         }
-        let current = SqliteDatabasePath::new(current);
 
-        Ok(Self { root, current })
+        Ok(Self {
+            current: SqliteDatabasePath::new(root.join(DatabaseDir::Current.name())),
+            history: SqliteDatabasePath::new(root.join(DatabaseDir::History.name())),
+            exports: ExportStorage::new(Arc::new(LocalStorage::new(
+                root.join(DatabaseDir::Exports.name()),
+            ))),
+            root,
+            disk_space_limits,
+        })
+    }
+
+    pub fn dir(&self, dir: DatabaseDir) -> PathBuf {
+        self.root.join(dir.name())
     }
 
     /// Sqlite database path
@@ -96,13 +199,39 @@ impl DatabaseRoot {
     pub fn current_ref(&self) -> &SqliteDatabasePath {
         &self.current
     }
+
+    /// History Sqlite database path
+    pub fn history(&self) -> SqliteDatabasePath {
+        self.history.clone()
+    }
+
+    /// Account data export bundles.
+    pub fn exports(&self) -> &ExportStorage {
+        &self.exports
+    }
+
+    /// Directory for hot standby snapshots.
+    pub fn standby(&self) -> PathBuf {
+        self.dir(DatabaseDir::Standby)
+    }
+
+    /// Log warning if disk space is low. Error is returned if disk space is
+    /// less than the configured minimum. Check this before creating large
+    /// files like backups and snapshots.
+    pub fn check_disk_space(&self) -> Result<DiskSpace, DatabaseError> {
+        check_disk_space(&self.root, self.disk_space_limits)
+            .change_context(DatabaseError::DiskSpace)
+    }
 }
 
-/// Handle SQLite databases and write command runner.
+/// Handle databases and write command runner.
 pub struct DatabaseManager {
-    sqlite_write_close: SqliteWriteCloseHandle,
-    sqlite_read_close: SqliteReadCloseHandle,
+    current_close: CurrentDataCloseHandle,
+    history_write_close: SqliteWriteCloseHandle,
     write_command_runner_close: WriteCommandRunnerQuitHandle,
+    hot_standby_close: Option<HotStandbyQuitHandle>,
+    backup_close: Option<BackupQuitHandle>,
+    replication_close: Option<ReplicationQuitHandle>,
 }
 
 impl DatabaseManager {
@@ -113,42 +242,126 @@ impl DatabaseManager {
     ) -> Result<(Self, RouterDatabaseReadHandle), DatabaseError> {
         info!("Creating DatabaseManager");
 
-        let root = DatabaseRoot::new(database_dir)?;
+        let root = Arc::new(DatabaseRoot::new(database_dir, config.disk_space_limits())?);
+        let disk_space = root.check_disk_space()?;
+        info!(
+            "Available disk space in database directory: {} MiB",
+            disk_space.available_mib()
+        );
+        root.exports()
+            .remove_expired(config.unix_time())
+            .await
+            .change_context(DatabaseError::File)?;
 
-        let (sqlite_write, sqlite_write_close) =
-            SqliteWriteHandle::new(root.current(), DatabaseType::Current)
+        let (history_write, history_write_close) =
+            SqliteWriteHandle::new(root.history(), DatabaseType::History, true)
                 .await
                 .change_context(DatabaseError::Init)?;
 
-        print_sqlite_version(sqlite_write.pool())
-            .await
-            .change_context(DatabaseError::Init)?;
-
-        let (sqlite_read, sqlite_read_close) =
-            SqliteReadHandle::new(root.current(), DatabaseType::Current)
+        let mut hot_standby_close = None;
+        let mut replication_close = None;
+        let mut backup_close = None;
+
+        let (current_write, current_read, current_close) = match config.database_backend() {
+            DatabaseBackend::Sqlite => {
+                let (sqlite_write, sqlite_write_close) = SqliteWriteHandle::new(
+                    root.current(),
+                    DatabaseType::Current,
+                    config.replication().is_none(),
+                )
                 .await
                 .change_context(DatabaseError::Init)?;
 
-        let read_commands = SqliteReadCommands::new(&sqlite_read);
+                print_sqlite_version(sqlite_write.pool())
+                    .await
+                    .change_context(DatabaseError::Init)?;
+
+                check_no_pending_migrations(sqlite_write.pool())
+                    .await
+                    .change_context(DatabaseError::Init)?;
+
+                let (sqlite_read, sqlite_read_close) =
+                    SqliteReadHandle::new(root.current(), DatabaseType::Current)
+                        .await
+                        .change_context(DatabaseError::Init)?;
+
+                if let Some(standby_config) = config.hot_standby() {
+                    if standby_config.mode == HotStandbyMode::Primary {
+                        hot_standby_close = Some(
+                            HotStandbySender::new_task(
+                                sqlite_read.clone(),
+                                root.clone(),
+                                standby_config,
+                                config.internal_api_http_client().clone(),
+                            )
+                            .change_context(DatabaseError::HotStandby)?,
+                        );
+                    }
+                }
+
+                if let Some(replication_config) = config.replication() {
+                    replication_close = Some(
+                        WalShipper::new_task(
+                            sqlite_write.clone(),
+                            root.clone(),
+                            replication_config,
+                        )
+                        .change_context(DatabaseError::Replication)?,
+                    );
+                }
+
+                if let Some(backup_config) = config.backup() {
+                    backup_close = Some(
+                        BackupManager::new_task(sqlite_read.clone(), root.clone(), backup_config)
+                            .change_context(DatabaseError::Backup)?,
+                    );
+                }
+
+                (
+                    CurrentDataWriteHandle::sqlite(sqlite_write),
+                    CurrentDataReadHandle::Sqlite(sqlite_read),
+                    CurrentDataCloseHandle::Sqlite {
+                        write: sqlite_write_close,
+                        read: sqlite_read_close,
+                    },
+                )
+            }
+            DatabaseBackend::Postgres => {
+                // Config validation makes sure that the URL exists.
+                let url = config.postgres_url().unwrap_or_default();
+                let (postgres, postgres_close) = PostgresHandle::new(url)
+                    .await
+                    .change_context(DatabaseError::Init)?;
+
+                (
+                    CurrentDataWriteHandle::postgres(postgres.clone()),
+                    CurrentDataReadHandle::Postgres(postgres),
+                    CurrentDataCloseHandle::Postgres(postgres_close),
+                )
+            }
+        };
+
+        let read_commands = CurrentReadCommands::new(&current_read);
         let cache = DatabaseCache::new(read_commands, &config)
             .await
             .change_context(DatabaseError::Cache)?;
 
         let router_write_handle = RouterDatabaseWriteHandle {
-            sqlite_write: CurrentDataWriteHandle::new(sqlite_write),
-            sqlite_read,
-            root: root.into(),
+            current_write,
+            current_read,
+            history_write: HistoryWriteHandle::new(history_write),
+            root,
             cache: cache.into(),
         };
 
-        let sqlite_read = router_write_handle.sqlite_read.clone();
+        let current_read = router_write_handle.current_read.clone();
         let root = router_write_handle.root.clone();
         let cache = router_write_handle.cache.clone();
 
-        let (write_handle, receiver) = WriteCommandRunner::new_channel();
+        let (write_handle, receiver) = WriteCommandRunner::new_channel(&config);
 
         let router_read_handle = RouterDatabaseReadHandle {
-            sqlite_read,
+            current_read,
             root,
             cache,
             write_handle,
@@ -158,9 +371,12 @@ impl DatabaseManager {
             WriteCommandRunner::new(router_write_handle, receiver, config);
 
         let database_manager = DatabaseManager {
-            sqlite_write_close,
-            sqlite_read_close,
+            current_close,
+            history_write_close,
             write_command_runner_close,
+            hot_standby_close,
+            backup_close,
+            replication_close,
         };
 
         info!("DatabaseManager created");
@@ -169,44 +385,66 @@ impl DatabaseManager {
     }
 
     pub async fn close(self) {
-        self.sqlite_read_close.close().await;
-        self.sqlite_write_close.close().await;
+        if let Some(hot_standby) = self.hot_standby_close {
+            hot_standby.quit().await;
+        }
 
+        if let Some(backup) = self.backup_close {
+            backup.quit().await;
+        }
+
+        if let Some(replication) = self.replication_close {
+            replication.quit().await;
+        }
+
+        // Pending write commands are handled before the runner quits, so
+        // the runner must quit before the connection pools are closed.
         match self.write_command_runner_close.quit().await {
             Ok(()) => (),
             Err(e) => tracing::error!("Write command runner quit failed: {}", e),
         }
+
+        self.current_close.close().await;
+        self.history_write_close.close().await;
     }
 }
 
 #[derive(Clone)]
 pub struct RouterDatabaseWriteHandle {
     root: Arc<DatabaseRoot>,
-    sqlite_write: CurrentDataWriteHandle,
-    sqlite_read: SqliteReadHandle,
+    current_write: CurrentDataWriteHandle,
+    current_read: CurrentDataReadHandle,
+    history_write: HistoryWriteHandle,
     cache: Arc<DatabaseCache>,
 }
 
 impl RouterDatabaseWriteHandle {
     pub fn user_write_commands(&self) -> WriteCommands {
-        WriteCommands::new(&self.sqlite_write, &self.cache)
+        WriteCommands::new(&self.current_write, &self.history_write, &self.cache)
     }
 
     pub fn user_write_commands_account<'b>(&'b self) -> WriteCommandsAccount<'b> {
-        WriteCommandsAccount::new(&self.sqlite_write, &self.cache)
+        WriteCommandsAccount::new(&self.current_write, &self.cache)
+    }
+
+    pub fn event_manager(&self) -> EventManager<'_> {
+        EventManager::new(&self.cache)
     }
 
     pub async fn register(
         &self,
         id_light: AccountIdLight,
         sign_in_with_info: SignInWithInfo,
+        region: Option<String>,
         config: &Config,
     ) -> Result<AccountIdInternal, DatabaseError> {
         WriteCommands::register(
             id_light,
             sign_in_with_info,
+            region,
             config,
-            self.sqlite_write.clone(),
+            self.current_write.clone(),
+            &self.history_write,
             &self.cache,
         )
         .await
@@ -215,14 +453,14 @@ impl RouterDatabaseWriteHandle {
 
 pub struct RouterDatabaseReadHandle {
     root: Arc<DatabaseRoot>,
-    sqlite_read: SqliteReadHandle,
+    current_read: CurrentDataReadHandle,
     cache: Arc<DatabaseCache>,
     write_handle: WriteCommandRunnerHandle,
 }
 
 impl RouterDatabaseReadHandle {
     pub fn read(&self) -> ReadCommands<'_> {
-        ReadCommands::new(&self.sqlite_read, &self.cache)
+        ReadCommands::new(&self.current_read, &self.cache)
     }
 
     pub fn api_key_manager(&self) -> ApiKeyManager<'_> {
@@ -230,10 +468,18 @@ impl RouterDatabaseReadHandle {
     }
 
     pub fn account_id_manager(&self) -> AccountIdManager<'_> {
-        AccountIdManager::new(&self.cache, &self.sqlite_read)
+        AccountIdManager::new(&self.cache, &self.current_read)
+    }
+
+    pub fn event_manager(&self) -> EventManager<'_> {
+        EventManager::new(&self.cache)
     }
 
     pub fn write(&self) -> &WriteCommandRunnerHandle {
         &self.write_handle
     }
+
+    pub fn exports(&self) -> &ExportStorage {
+        self.root.exports()
+    }
 }
@@ -0,0 +1,376 @@
+//! Periodic database backups
+//!
+//! Backup is a SQLite database snapshot which is stored to the backups
+//! directory. If S3 is configured, the backup is also uploaded to the
+//! bucket. Only the newest backups are kept both locally and remotely.
+//! Every backup has a JSON metadata file with the account counts per data
+//! residency region. Backups are restored with the `restore-backup` command when the server is
+//! not running.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use error_stack::{Report, Result, ResultExt};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    api::model::RegionRegistrations,
+    config::{file::BackupConfig, Config},
+    server::clock::UtcTimestamp,
+    utils::{ConvertCommandError, IntoReportExt, QuitReceiver, QuitSender},
+};
+
+use super::{
+    current::{account::read::CurrentReadAccountCommands, traits::ReadAccountStorage},
+    sqlite::{create_snapshot, SqliteReadHandle, DATABASE_FILE_NAME},
+    storage::{LocalStorage, S3Storage, Storage},
+    write::NoId,
+    DatabaseDir, DatabaseRoot,
+};
+
+const BACKUP_FILE_PREFIX: &str = "backup_";
+const BACKUP_FILE_SUFFIX: &str = ".db";
+const BACKUP_METADATA_FILE_SUFFIX: &str = ".json";
+const DEFAULT_BACKUP_INTERVAL_SECONDS: u64 = 24 * 60 * 60;
+const DEFAULT_LOCAL_RETENTION_COUNT: usize = 7;
+const DEFAULT_REMOTE_RETENTION_COUNT: usize = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BackupError {
+    #[error("Creating database snapshot failed")]
+    Snapshot,
+    #[error("File error")]
+    File,
+    #[error("Local backup storage error")]
+    LocalStorage,
+    #[error("Remote backup storage error")]
+    RemoteStorage,
+    #[error("Not enough disk space")]
+    DiskSpace,
+    #[error("Database initialization error")]
+    Init,
+    #[error("Backup not found")]
+    NotFound,
+    #[error("Creating backup metadata failed")]
+    Metadata,
+}
+
+/// Backup metadata which can be checked without restoring the backup.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackupMetadata {
+    /// Same time as in the backup key.
+    pub created: UtcTimestamp,
+    /// Account counts per data residency region.
+    pub regions: Vec<RegionRegistrations>,
+}
+
+#[derive(Debug)]
+pub struct BackupQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl BackupQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Backup task join failed: {}", e),
+        }
+    }
+}
+
+/// Local and optional remote backup storage.
+struct BackupStorages {
+    local: LocalStorage,
+    remote: Option<S3Storage>,
+    local_retention_count: usize,
+    remote_retention_count: usize,
+}
+
+impl BackupStorages {
+    fn new(root: &DatabaseRoot, config: &BackupConfig) -> Result<Self, BackupError> {
+        let remote = match &config.s3 {
+            Some(s3_config) => {
+                Some(S3Storage::new(s3_config).change_context(BackupError::RemoteStorage)?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            local: LocalStorage::new(root.dir(DatabaseDir::Backups)),
+            remote,
+            local_retention_count: config
+                .local_retention_count
+                .unwrap_or(DEFAULT_LOCAL_RETENTION_COUNT),
+            remote_retention_count: config
+                .s3
+                .as_ref()
+                .and_then(|c| c.retention_count)
+                .unwrap_or(DEFAULT_REMOTE_RETENTION_COUNT),
+        })
+    }
+}
+
+/// Creates backups at configured interval.
+pub struct BackupManager {
+    read: SqliteReadHandle,
+    root: Arc<DatabaseRoot>,
+    storages: BackupStorages,
+    interval: Duration,
+}
+
+impl BackupManager {
+    pub fn new_task(
+        read: SqliteReadHandle,
+        root: Arc<DatabaseRoot>,
+        config: &BackupConfig,
+    ) -> Result<BackupQuitHandle, BackupError> {
+        let storages = BackupStorages::new(&root, config)?;
+
+        if storages.remote.is_some() {
+            info!("Backups are uploaded to S3");
+        }
+
+        let manager = Self {
+            read,
+            root,
+            storages,
+            interval: Duration::from_secs(
+                config
+                    .interval_seconds
+                    .unwrap_or(DEFAULT_BACKUP_INTERVAL_SECONDS),
+            ),
+        };
+
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(manager.run(quit_receiver));
+
+        Ok(BackupQuitHandle { task, quit })
+    }
+
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(self.interval);
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    match self.create_backup().await {
+                        Ok(()) => (),
+                        Err(e) => error!("Database backup failed: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn create_backup(&self) -> Result<(), BackupError> {
+        self.root
+            .check_disk_space()
+            .change_context(BackupError::DiskSpace)?;
+
+        let created = UtcTimestamp::now();
+        let key = backup_key(created);
+        let backups_dir = self.root.dir(DatabaseDir::Backups);
+        let file = backups_dir.join(&key);
+        // Storage does not list tmp files, so partial backup is never
+        // restored.
+        let tmp_file = backups_dir.join(format!("{key}.tmp"));
+        if tmp_file.exists() {
+            tokio::fs::remove_file(&tmp_file)
+                .await
+                .into_error(BackupError::File)?;
+        }
+
+        create_snapshot(self.read.pool(), &tmp_file)
+            .await
+            .change_context(BackupError::Snapshot)?;
+        tokio::fs::rename(&tmp_file, &file)
+            .await
+            .into_error(BackupError::File)?;
+
+        let metadata_key = backup_metadata_key(created);
+        let metadata = self.backup_metadata(created).await?;
+        self.storages
+            .local
+            .put(&metadata_key, metadata.clone())
+            .await
+            .change_context(BackupError::LocalStorage)
+            .attach_printable_lazy(|| metadata_key.clone())?;
+
+        info!("Database backup {} created", key);
+
+        if let Some(remote) = &self.storages.remote {
+            let data = tokio::fs::read(&file).await.into_error(BackupError::File)?;
+            remote
+                .put(&key, data)
+                .await
+                .change_context(BackupError::RemoteStorage)
+                .attach_printable_lazy(|| key.clone())?;
+            remote
+                .put(&metadata_key, metadata)
+                .await
+                .change_context(BackupError::RemoteStorage)
+                .attach_printable_lazy(|| metadata_key.clone())?;
+            info!("Database backup {} uploaded", key);
+
+            remove_old_backups(remote, self.storages.remote_retention_count)
+                .await
+                .change_context(BackupError::RemoteStorage)?;
+        }
+
+        remove_old_backups(&self.storages.local, self.storages.local_retention_count)
+            .await
+            .change_context(BackupError::LocalStorage)?;
+
+        Ok(())
+    }
+
+    async fn backup_metadata(&self, created: UtcTimestamp) -> Result<Vec<u8>, BackupError> {
+        let regions = CurrentReadAccountCommands::new(&self.read)
+            .account_regions()
+            .await
+            .convert(NoId)
+            .change_context(BackupError::Metadata)?;
+        let metadata = BackupMetadata { created, regions };
+        serde_json::to_vec_pretty(&metadata).into_error(BackupError::Metadata)
+    }
+}
+
+/// Keys contain Unix time, because RFC 3339 time contains characters
+/// which are not allowed in all file names.
+fn backup_key(created: UtcTimestamp) -> String {
+    format!(
+        "{BACKUP_FILE_PREFIX}{}{BACKUP_FILE_SUFFIX}",
+        created.unix_time()
+    )
+}
+
+fn backup_metadata_key(created: UtcTimestamp) -> String {
+    format!(
+        "{BACKUP_FILE_PREFIX}{}{BACKUP_METADATA_FILE_SUFFIX}",
+        created.unix_time()
+    )
+}
+
+/// Returns None if key is not a backup key.
+fn backup_time(key: &str) -> Option<UtcTimestamp> {
+    key.strip_prefix(BACKUP_FILE_PREFIX)?
+        .strip_suffix(BACKUP_FILE_SUFFIX)?
+        .parse()
+        .ok()
+        .map(UtcTimestamp::from_unix_time)
+}
+
+/// Backup keys sorted so that the newest backup is first.
+async fn list_backups(storage: &dyn Storage) -> Result<Vec<String>, BackupError> {
+    let mut backups: Vec<(UtcTimestamp, String)> = storage
+        .list()
+        .await
+        .change_context(BackupError::File)?
+        .into_iter()
+        .filter_map(|key| backup_time(&key).map(|time| (time, key)))
+        .collect();
+    backups.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(backups.into_iter().map(|(_, key)| key).collect())
+}
+
+async fn remove_old_backups(storage: &dyn Storage, keep: usize) -> Result<(), BackupError> {
+    for key in list_backups(storage).await?.iter().skip(keep) {
+        storage
+            .delete(key)
+            .await
+            .change_context(BackupError::File)
+            .attach_printable_lazy(|| key.clone())?;
+        // Backups created before metadata files do not have metadata.
+        if let Some(time) = backup_time(key) {
+            let metadata_key = backup_metadata_key(time);
+            storage
+                .delete(&metadata_key)
+                .await
+                .change_context(BackupError::File)
+                .attach_printable_lazy(|| metadata_key.clone())?;
+        }
+        info!("Old database backup {} removed", key);
+    }
+    Ok(())
+}
+
+/// Replace the current database with a backup. Server must not be running.
+///
+/// Remote backups are used if S3 is configured. If `name` is None, the
+/// newest backup is restored. The replaced database is moved to the
+/// backups directory.
+pub async fn restore_backup(config: &Config, name: Option<&str>) -> Result<(), BackupError> {
+    let root = DatabaseRoot::new(config.database_dir(), config.disk_space_limits())
+        .change_context(BackupError::Init)?;
+    let default_config = BackupConfig {
+        interval_seconds: None,
+        local_retention_count: None,
+        s3: None,
+    };
+    let storages = BackupStorages::new(&root, config.backup().unwrap_or(&default_config))?;
+    let storage: &dyn Storage = match &storages.remote {
+        Some(remote) => remote,
+        None => &storages.local,
+    };
+
+    let key = match name {
+        Some(name) => name.to_string(),
+        None => list_backups(storage)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Report::new(BackupError::NotFound))?,
+    };
+
+    let data = storage
+        .get(&key)
+        .await
+        .change_context(BackupError::File)
+        .attach_printable_lazy(|| key.clone())?
+        .ok_or_else(|| Report::new(BackupError::NotFound).attach_printable(key.clone()))?;
+
+    root.check_disk_space()
+        .change_context(BackupError::DiskSpace)?;
+
+    let current_dir = root.current_ref().path();
+    let current_file = current_dir.join(DATABASE_FILE_NAME);
+    if current_file.exists() {
+        let replaced = root.dir(DatabaseDir::Backups).join(format!(
+            "before_restore_{}.db",
+            UtcTimestamp::now().unix_time()
+        ));
+        tokio::fs::rename(&current_file, &replaced)
+            .await
+            .into_error(BackupError::File)?;
+        info!("Current database moved to {}", replaced.display());
+    }
+    for suffix in ["-wal", "-shm"] {
+        remove_if_exists(&current_dir.join(format!("{DATABASE_FILE_NAME}{suffix}"))).await?;
+    }
+
+    let tmp_file = current_dir.join(format!("{DATABASE_FILE_NAME}.tmp"));
+    tokio::fs::write(&tmp_file, data)
+        .await
+        .into_error(BackupError::File)?;
+    tokio::fs::rename(&tmp_file, &current_file)
+        .await
+        .into_error(BackupError::File)?;
+
+    info!("Database backup {} restored", key);
+
+    Ok(())
+}
+
+async fn remove_if_exists(file: &Path) -> Result<(), BackupError> {
+    match tokio::fs::remove_file(file).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => {
+            Err(e).into_error_with_info_lazy(BackupError::File, || file.display().to_string())
+        }
+    }
+}
@@ -1,24 +1,47 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use async_trait::async_trait;
-use tokio::sync::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, RwLock};
 use tokio_stream::StreamExt;
 use tracing::info;
+use utoipa::ToSchema;
 
 use crate::{
     api::{
-        calculator::data::CalculatorStateInternal,
-        model::{Account, AccountIdInternal, AccountIdLight, AccountSetup, ApiKey},
+        calculator::data::{CalculatorSettings, CalculatorStateInternal},
+        common::ClientVersion,
+        model::{
+            Account, AccountIdInternal, AccountIdLight, AccountSetup, ApiKey, NotificationSettings,
+            OrganizationId, ServiceTokenScope, TokenHash,
+        },
     },
     config::Config,
-    server::database::write::NoId,
+    server::{
+        clock::Clock,
+        database::write::NoId,
+        event::EventSender,
+        geoip::GeoLocation,
+        internal::api_key_cache::ApiKeyRevocationNotifier,
+        latency::{self, Phase},
+    },
     utils::ConvertCommandError,
 };
 
 use error_stack::{Result, ResultExt};
 
 use super::{
-    current::SqliteReadCommands, read::ReadResult, sqlite::SqliteSelectJson, write::WriteResult,
+    current::{CurrentReadCommands, SelectJson},
+    read::ReadResult,
+    write::WriteResult,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -42,20 +65,86 @@ pub enum CacheError {
 pub struct AccountEntry {
     pub account_id_internal: AccountIdInternal,
     pub cache: RwLock<CacheEntry>,
+    /// WebSocket connection of the account closes when this is notified.
+    pub close_connection: Arc<Notify>,
+}
+
+/// Access token of logged in account.
+struct AccessTokenEntry {
+    account: Arc<AccountEntry>,
+    /// None if the access token does not expire.
+    expires_unix_time: Option<i64>,
+    /// Set if the access token is from HTTP token exchange. The session is
+    /// non-connected, so the IP address of the token exchange request is
+    /// used instead of the WebSocket connection IP address.
+    non_connected_ip: Option<IpAddr>,
+}
+
+impl AccessTokenEntry {
+    fn is_expired(&self, unix_time: i64) -> bool {
+        self.expires_unix_time
+            .map(|expires| expires <= unix_time)
+            .unwrap_or(false)
+    }
+}
+
+/// Long-lived token for scripts and integrations.
+struct ServiceTokenEntry {
+    account: Arc<AccountEntry>,
+    scope: ServiceTokenScope,
+}
+
+/// Counters of cached JSON data reads. Counters are totals since server
+/// start.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct CacheReadStats {
+    /// Reads which found the data from the cache.
+    pub hits: u64,
+    /// Reads which did not find the data from the cache, so the data was
+    /// read from the database and added to the cache.
+    pub fallbacks: u64,
+    /// Reads which failed.
+    pub failures: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CacheRead {
+    Hit,
+    Fallback,
+    Failure,
+}
+
+#[derive(Debug, Default)]
+struct CacheReadCounters {
+    hits: AtomicU64,
+    fallbacks: AtomicU64,
+    failures: AtomicU64,
 }
 
 pub struct DatabaseCache {
-    /// Accounts which are logged in.
-    api_keys: RwLock<HashMap<ApiKey, Arc<AccountEntry>>>,
+    /// Accounts which are logged in. Keys are access token hashes.
+    api_keys: RwLock<HashMap<TokenHash, AccessTokenEntry>>,
+    /// Keys are service token hashes.
+    service_tokens: RwLock<HashMap<TokenHash, ServiceTokenEntry>>,
     /// All accounts registered in the service.
     accounts: RwLock<HashMap<AccountIdLight, Arc<AccountEntry>>>,
+    /// Access token expiration and activity times use this clock.
+    clock: Arc<dyn Clock>,
+    /// Removed access tokens are sent to calculator servers which cache
+    /// access tokens.
+    revocations: ApiKeyRevocationNotifier,
+    read_counters: CacheReadCounters,
 }
 
 impl DatabaseCache {
-    pub async fn new(read: SqliteReadCommands<'_>, config: &Config) -> Result<Self, CacheError> {
+    pub async fn new(read: CurrentReadCommands<'_>, config: &Config) -> Result<Self, CacheError> {
         let cache = Self {
             api_keys: RwLock::new(HashMap::new()),
+            service_tokens: RwLock::new(HashMap::new()),
             accounts: RwLock::new(HashMap::new()),
+            clock: config.clock().clone(),
+            revocations: ApiKeyRevocationNotifier::new(config),
+            read_counters: CacheReadCounters::default(),
         };
 
         // Load data from database to memory.
@@ -72,21 +161,53 @@ impl DatabaseCache {
         let read_account = cache.accounts.read().await;
         let ids = read_account.values();
         for lock_and_cache in ids {
-            let api_key = read
+            let api_key_hash = read
                 .account()
-                .access_token(lock_and_cache.account_id_internal)
+                .access_token_hash(lock_and_cache.account_id_internal)
                 .await
                 .attach(lock_and_cache.account_id_internal)
                 .change_context(CacheError::Init)?;
 
-            if let Some(key) = api_key {
+            let expires_unix_time = read
+                .account()
+                .access_token_expiration(lock_and_cache.account_id_internal)
+                .await
+                .attach(lock_and_cache.account_id_internal)
+                .change_context(CacheError::Init)?;
+
+            if let Some(key) = api_key_hash {
+                // Non-connected session information is not saved, so
+                // those clients must exchange tokens again.
+                let token = AccessTokenEntry {
+                    account: lock_and_cache.clone(),
+                    expires_unix_time,
+                    non_connected_ip: None,
+                };
                 let mut write_api_keys = cache.api_keys.write().await;
                 if write_api_keys.contains_key(&key) {
                     return Err(CacheError::AlreadyExists.into()).change_context(CacheError::Init);
-                } else {
-                    write_api_keys.insert(key, lock_and_cache.clone());
+                } else if !token.is_expired(cache.clock.unix_time()) {
+                    write_api_keys.insert(key, token);
+                }
+            }
+
+            let service_tokens = read
+                .account()
+                .service_token_hashes(lock_and_cache.account_id_internal)
+                .await
+                .attach(lock_and_cache.account_id_internal)
+                .change_context(CacheError::Init)?;
+            let mut write_service_tokens = cache.service_tokens.write().await;
+            for (token_hash, scope) in service_tokens {
+                let entry = ServiceTokenEntry {
+                    account: lock_and_cache.clone(),
+                    scope,
+                };
+                if write_service_tokens.insert(token_hash, entry).is_some() {
+                    return Err(CacheError::AlreadyExists.into()).change_context(CacheError::Init);
                 }
             }
+            drop(write_service_tokens);
 
             let mut entry = lock_and_cache.cache.write().await;
 
@@ -94,7 +215,35 @@ impl DatabaseCache {
                 let account = Account::select_json(lock_and_cache.account_id_internal, &read)
                     .await
                     .change_context(CacheError::Init)?;
-                entry.account = Some(account.clone().into())
+                entry.account = Some(account.clone().into());
+
+                let settings =
+                    NotificationSettings::select_json(lock_and_cache.account_id_internal, &read)
+                        .await
+                        .change_context(CacheError::Init)?;
+                entry.notification_settings = Some(settings.into());
+            }
+
+            if config.components().calculator {
+                let settings =
+                    CalculatorSettings::select_json(lock_and_cache.account_id_internal, &read)
+                        .await
+                        .change_context(CacheError::Init)?;
+                entry.calculator_settings = Some(settings.into());
+
+                let state =
+                    CalculatorStateInternal::select_json(lock_and_cache.account_id_internal, &read)
+                        .await
+                        .change_context(CacheError::Init)?;
+                entry.calculator_state = Some(state.into());
+
+                let readers = read
+                    .calculator()
+                    .calculator_state_readers(lock_and_cache.account_id_internal)
+                    .await
+                    .attach(lock_and_cache.account_id_internal)
+                    .change_context(CacheError::Init)?;
+                entry.calculator_state_readers = readers.into_iter().collect();
             }
         }
 
@@ -120,6 +269,7 @@ impl DatabaseCache {
                 AccountEntry {
                     cache: value,
                     account_id_internal: id,
+                    close_connection: Arc::new(Notify::new()),
                 }
                 .into(),
             );
@@ -129,12 +279,133 @@ impl DatabaseCache {
         }
     }
 
+    /// Remove account and its access token from the cache and close the
+    /// WebSocket connection of the account.
+    pub async fn delete_account(&self, id: AccountIdLight) -> WriteResult<(), CacheError> {
+        let entry = self
+            .accounts
+            .write()
+            .await
+            .remove(&id)
+            .ok_or(CacheError::KeyNotExists)?;
+
+        let mut removed = vec![];
+        self.api_keys.write().await.retain(|hash, token| {
+            let keep = token.account.account_id_internal.as_light() != id;
+            if !keep {
+                removed.push(hash.clone());
+            }
+            keep
+        });
+        self.revocations.notify(removed);
+        self.service_tokens
+            .write()
+            .await
+            .retain(|_, token| token.account.account_id_internal.as_light() != id);
+
+        // Database removes the shares when the account is deleted.
+        for other in self.accounts.read().await.values() {
+            other
+                .cache
+                .write()
+                .await
+                .calculator_state_readers
+                .remove(&id);
+        }
+
+        // Permit is stored if the connection is not waiting the notification
+        // currently.
+        entry.close_connection.notify_one();
+
+        Ok(())
+    }
+
+    /// Close the current WebSocket connection of the account. Unlike with
+    /// account deletion, the next connection is not closed.
+    pub async fn close_connection(&self, id: AccountIdLight) -> WriteResult<(), CacheError> {
+        self.accounts
+            .read()
+            .await
+            .get(&id)
+            .ok_or(CacheError::KeyNotExists)?
+            .close_connection
+            .notify_waiters();
+        Ok(())
+    }
+
+    /// Notification which closes the WebSocket connection of the account.
+    pub async fn connection_close_notifier(
+        &self,
+        id: AccountIdLight,
+    ) -> ReadResult<Arc<Notify>, CacheError> {
+        let guard = self.accounts.read().await;
+        let notifier = guard
+            .get(&id)
+            .ok_or(CacheError::KeyNotExists)?
+            .close_connection
+            .clone();
+        Ok(notifier)
+    }
+
     pub async fn update_access_token_and_connection(
         &self,
         id: AccountIdLight,
-        current_access_token: Option<ApiKey>,
+        current_access_token: Option<TokenHash>,
         new_access_token: ApiKey,
+        expires_unix_time: Option<i64>,
         address: Option<SocketAddr>,
+        client_version: Option<ClientVersion>,
+    ) -> WriteResult<(), CacheError, ApiKey> {
+        self.replace_access_token(
+            id,
+            current_access_token,
+            new_access_token,
+            expires_unix_time,
+            None,
+            |entry| {
+                entry.current_connection = address;
+                entry.client_version = client_version;
+                if address.is_some() {
+                    entry.last_seen_address = address;
+                    // Location of the new address is not known.
+                    entry.last_seen_location = None;
+                }
+            },
+        )
+        .await
+    }
+
+    /// Replace access token without changing the current connection.
+    ///
+    /// If `non_connected_ip` is set, the new access token is for a
+    /// non-connected session and works only from that IP address.
+    pub async fn update_access_token(
+        &self,
+        id: AccountIdLight,
+        current_access_token: Option<TokenHash>,
+        new_access_token: ApiKey,
+        expires_unix_time: Option<i64>,
+        non_connected_ip: Option<IpAddr>,
+    ) -> WriteResult<(), CacheError, ApiKey> {
+        self.replace_access_token(
+            id,
+            current_access_token,
+            new_access_token,
+            expires_unix_time,
+            non_connected_ip,
+            |_| (),
+        )
+        .await
+    }
+
+    async fn replace_access_token(
+        &self,
+        id: AccountIdLight,
+        current_access_token: Option<TokenHash>,
+        new_access_token: ApiKey,
+        expires_unix_time: Option<i64>,
+        non_connected_ip: Option<IpAddr>,
+        update_entry: impl FnOnce(&mut CacheEntry),
     ) -> WriteResult<(), CacheError, ApiKey> {
         let cache_entry = self
             .accounts
@@ -147,13 +418,27 @@ impl DatabaseCache {
         let mut tokens = self.api_keys.write().await;
 
         if let Some(current) = current_access_token {
-            tokens.remove(&current);
+            if tokens.remove(&current).is_some() {
+                self.revocations.notify(vec![current]);
+            }
         }
 
         // Avoid collisions.
-        if tokens.get(&new_access_token).is_none() {
-            cache_entry.cache.write().await.current_connection = address;
-            tokens.insert(new_access_token, cache_entry);
+        let new_access_token_hash = new_access_token.hash();
+        if tokens.get(&new_access_token_hash).is_none() {
+            let mut entry = cache_entry.cache.write().await;
+            update_entry(&mut entry);
+            // Login is account activity.
+            entry.last_activity_unix_time = Some(self.clock.unix_time());
+            drop(entry);
+            tokens.insert(
+                new_access_token_hash,
+                AccessTokenEntry {
+                    account: cache_entry,
+                    expires_unix_time,
+                    non_connected_ip,
+                },
+            );
             Ok(())
         } else {
             Err(CacheError::AlreadyExists.into())
@@ -163,7 +448,7 @@ impl DatabaseCache {
     pub async fn delete_access_token_and_connection(
         &self,
         id: AccountIdLight,
-        token: Option<ApiKey>,
+        token: Option<TokenHash>,
     ) -> WriteResult<(), CacheError, ApiKey> {
         let cache_entry = self
             .accounts
@@ -173,43 +458,163 @@ impl DatabaseCache {
             .ok_or(CacheError::KeyNotExists)?
             .clone();
 
-        cache_entry.cache.write().await.current_connection = None;
+        let mut entry = cache_entry.cache.write().await;
+        entry.current_connection = None;
+        entry.client_version = None;
+        drop(entry);
 
         if let Some(token) = token {
-            let mut tokens = self.api_keys.write().await;
-            let _account = tokens.remove(&token).ok_or(CacheError::KeyNotExists)?;
+            // Expired token might be already removed.
+            if self.api_keys.write().await.remove(&token).is_some() {
+                self.revocations.notify(vec![token]);
+            }
         }
 
         Ok(())
     }
 
+    /// Expired access tokens do not exist.
     pub async fn access_token_exists(&self, token: &ApiKey) -> Option<AccountIdInternal> {
         let tokens = self.api_keys.read().await;
-        if let Some(entry) = tokens.get(token) {
-            Some(entry.account_id_internal)
-        } else {
-            None
+        match tokens.get(&token.hash()) {
+            Some(entry) if !entry.is_expired(self.clock.unix_time()) => {
+                Some(entry.account.account_id_internal)
+            }
+            _ => None,
         }
     }
 
-    /// Checks that connection comes from the same IP address. WebSocket is
-    /// using the cached SocketAddr, so check the IP only.
-    pub async fn access_token_and_connection_exists(
+    /// Access token was used. Updates last seen address, location and
+    /// activity time. Returns account ID and previous last seen address and
+    /// location.
+    ///
+    /// If `require_connection_ip` is true, then the request must come from
+    /// the same IP address as the current WebSocket connection. WebSocket is
+    /// using the cached SocketAddr, so check the IP only. Access tokens of
+    /// non-connected sessions are checked against the IP address of the
+    /// token exchange request.
+    pub async fn access_token_used(
         &self,
         access_token: &ApiKey,
-        connection: SocketAddr,
-    ) -> Option<AccountIdInternal> {
+        address: SocketAddr,
+        location: Option<GeoLocation>,
+        require_connection_ip: bool,
+    ) -> Option<(AccountIdInternal, Option<(SocketAddr, Option<GeoLocation>)>)> {
         let tokens = self.api_keys.read().await;
-        if let Some(entry) = tokens.get(access_token) {
-            let r = entry.cache.read().await;
-            if r.current_connection.map(|a| a.ip()) == Some(connection.ip()) {
-                Some(entry.account_id_internal)
-            } else {
-                None
+        let token = tokens.get(&access_token.hash())?;
+        if token.is_expired(self.clock.unix_time()) {
+            return None;
+        }
+        let entry = &token.account;
+        let mut cache = entry.cache.write().await;
+        let allowed_ip = token
+            .non_connected_ip
+            .or(cache.current_connection.map(|a| a.ip()));
+        if require_connection_ip && allowed_ip != Some(address.ip()) {
+            return None;
+        }
+        let previous_location = std::mem::replace(&mut cache.last_seen_location, location);
+        let previous = cache
+            .last_seen_address
+            .replace(address)
+            .map(|address| (address, previous_location));
+        cache.last_activity_unix_time = Some(self.clock.unix_time());
+        Some((entry.account_id_internal, previous))
+    }
+
+    pub async fn insert_service_token(
+        &self,
+        id: AccountIdLight,
+        token_hash: TokenHash,
+        scope: ServiceTokenScope,
+    ) -> WriteResult<(), CacheError> {
+        let account = self
+            .accounts
+            .read()
+            .await
+            .get(&id)
+            .ok_or(CacheError::KeyNotExists)?
+            .clone();
+
+        let mut tokens = self.service_tokens.write().await;
+        if tokens.contains_key(&token_hash) {
+            return Err(CacheError::AlreadyExists.into());
+        }
+        tokens.insert(token_hash, ServiceTokenEntry { account, scope });
+        Ok(())
+    }
+
+    pub async fn remove_service_token(&self, token_hash: &TokenHash) {
+        self.service_tokens.write().await.remove(token_hash);
+    }
+
+    /// Service tokens are not bound to the WebSocket connection and they
+    /// do not update the session info of the account.
+    pub async fn service_token_used(
+        &self,
+        token: &ApiKey,
+    ) -> Option<(AccountIdInternal, ServiceTokenScope)> {
+        let tokens = self.service_tokens.read().await;
+        let entry = tokens.get(&token.hash())?;
+        Some((entry.account.account_id_internal, entry.scope))
+    }
+
+    /// Check is there an access token for the account. Iterates all access
+    /// tokens, so use this only for debugging.
+    pub async fn access_token_exists_for_account(&self, id: AccountIdLight) -> bool {
+        self.api_keys
+            .read()
+            .await
+            .values()
+            .any(|token| token.account.account_id_internal.as_light() == id)
+    }
+
+    /// Remove expired access tokens. Returns count of removed tokens.
+    pub async fn remove_expired_access_tokens(&self) -> usize {
+        let now = self.clock.unix_time();
+        let mut tokens = self.api_keys.write().await;
+        let mut removed = vec![];
+        let mut count = 0;
+        tokens.retain(|hash, token| {
+            let keep = !token.is_expired(now);
+            if !keep {
+                count += 1;
+                removed.push(hash.clone());
+            }
+            keep
+        });
+        self.revocations.notify(removed);
+        count
+    }
+
+    /// Accounts which have activity newer than the saved activity time.
+    pub async fn unsaved_activity(&self) -> Vec<(AccountIdInternal, i64)> {
+        let accounts = self.accounts.read().await;
+        let mut unsaved = vec![];
+        for entry in accounts.values() {
+            let cache = entry.cache.read().await;
+            match (
+                cache.last_activity_unix_time,
+                cache.saved_activity_unix_time,
+            ) {
+                (Some(activity), Some(saved)) if activity <= saved => (),
+                (Some(activity), _) => unsaved.push((entry.account_id_internal, activity)),
+                (None, _) => (),
             }
-        } else {
-            None
         }
+        unsaved
+    }
+
+    pub async fn activity_saved(
+        &self,
+        id: AccountIdLight,
+        unix_time: i64,
+    ) -> WriteResult<(), CacheError> {
+        self.write_cache(id, |entry| {
+            entry.saved_activity_unix_time = Some(unix_time);
+            Ok(())
+        })
+        .await
     }
 
     pub async fn to_account_id_internal(
@@ -224,11 +629,38 @@ impl DatabaseCache {
         Ok(data)
     }
 
+    pub async fn account_entry(
+        &self,
+        id: AccountIdLight,
+    ) -> ReadResult<Arc<AccountEntry>, CacheError, AccountIdLight> {
+        let guard = self.accounts.read().await;
+        let entry = guard.get(&id).ok_or(CacheError::KeyNotExists)?.clone();
+        Ok(entry)
+    }
+
+    pub fn record_read(&self, read: CacheRead) {
+        let counter = match read {
+            CacheRead::Hit => &self.read_counters.hits,
+            CacheRead::Fallback => &self.read_counters.fallbacks,
+            CacheRead::Failure => &self.read_counters.failures,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn read_stats(&self) -> CacheReadStats {
+        CacheReadStats {
+            hits: self.read_counters.hits.load(Ordering::Relaxed),
+            fallbacks: self.read_counters.fallbacks.load(Ordering::Relaxed),
+            failures: self.read_counters.failures.load(Ordering::Relaxed),
+        }
+    }
+
     pub async fn read_cache<T>(
         &self,
         id: AccountIdLight,
         cache_operation: impl Fn(&CacheEntry) -> T,
     ) -> ReadResult<T, CacheError> {
+        let start = Instant::now();
         let guard = self.accounts.read().await;
         let cache_entry = guard
             .get(&id)
@@ -236,7 +668,9 @@ impl DatabaseCache {
             .cache
             .read()
             .await;
-        Ok(cache_operation(&cache_entry))
+        let output = cache_operation(&cache_entry);
+        latency::record_phase(Phase::Cache, start.elapsed());
+        Ok(output)
     }
 
     pub async fn write_cache<T>(
@@ -293,54 +727,143 @@ impl DatabaseCache {
 #[derive(Debug)]
 pub struct CacheEntry {
     pub account: Option<Box<Account>>,
+    /// Event delivery reads these settings.
+    pub notification_settings: Option<Box<NotificationSettings>>,
+    pub calculator_settings: Option<Box<CalculatorSettings>>,
+    pub calculator_state: Option<Box<CalculatorStateInternal>>,
+    /// Accounts which can read the calculator state.
+    pub calculator_state_readers: HashSet<AccountIdLight>,
     pub current_connection: Option<SocketAddr>,
+    /// Client version of the current WebSocket connection. None if the
+    /// client did not send the version.
+    pub client_version: Option<ClientVersion>,
+    /// Address of the latest request made with the access token.
+    pub last_seen_address: Option<SocketAddr>,
+    pub last_seen_location: Option<GeoLocation>,
+    pub last_activity_unix_time: Option<i64>,
+    /// Activity time which is saved to the database.
+    pub saved_activity_unix_time: Option<i64>,
+    /// Event channel of the latest WebSocket connection.
+    pub event_sender: Option<EventSender>,
+    /// Set if the account broadcasts its calculator state to the
+    /// organization members.
+    pub calculator_broadcast: Option<CalculatorBroadcastState>,
 }
 
 impl CacheEntry {
     pub fn new() -> Self {
         Self {
             account: None,
+            notification_settings: None,
+            calculator_settings: None,
+            calculator_state: None,
+            calculator_state_readers: HashSet::new(),
             current_connection: None,
+            client_version: None,
+            last_seen_address: None,
+            last_seen_location: None,
+            last_activity_unix_time: None,
+            saved_activity_unix_time: None,
+            event_sender: None,
+            calculator_broadcast: None,
         }
     }
 }
 
+/// Broadcasting is not saved to the database, so it ends when the server
+/// restarts.
+#[derive(Debug, Clone, Copy)]
+pub struct CalculatorBroadcastState {
+    pub org_id: OrganizationId,
+    /// Time of the latest broadcast.
+    pub latest: Option<Instant>,
+}
+
 #[async_trait]
 pub trait ReadCacheJson: Sized + Send {
     const CACHED_JSON: bool = false;
 
+    /// Data from the cache entry. None if the data is not in the cache.
+    fn cached(_entry: &CacheEntry) -> Option<Self> {
+        None
+    }
+
+    /// Add data to the cache entry after it was read from the database.
+    fn fill_cache(&self, _entry: &mut CacheEntry) {}
+
     async fn read_from_cache(
-        _id: AccountIdLight,
-        _cache: &DatabaseCache,
+        id: AccountIdLight,
+        cache: &DatabaseCache,
     ) -> Result<Self, CacheError> {
-        Err(CacheError::NotInCache.into())
+        if !Self::CACHED_JSON {
+            return Err(CacheError::NotInCache.into());
+        }
+        let data_in_cache = cache.read_cache(id, Self::cached).await.attach(id)?;
+        data_in_cache.ok_or(CacheError::NotInCache.into())
     }
 }
 
 impl ReadCacheJson for AccountSetup {}
 
-#[async_trait]
+impl ReadCacheJson for NotificationSettings {
+    const CACHED_JSON: bool = true;
+
+    fn cached(entry: &CacheEntry) -> Option<Self> {
+        entry
+            .notification_settings
+            .as_ref()
+            .map(|settings| settings.as_ref().clone())
+    }
+
+    fn fill_cache(&self, entry: &mut CacheEntry) {
+        entry.notification_settings = Some(self.clone().into());
+    }
+}
+
 impl ReadCacheJson for Account {
     const CACHED_JSON: bool = true;
 
-    async fn read_from_cache(
-        id: AccountIdLight,
-        cache: &DatabaseCache,
-    ) -> Result<Self, CacheError> {
-        let data_in_cache = cache
-            .read_cache(id, |entry| {
-                entry
-                    .account
-                    .as_ref()
-                    .map(|account| account.as_ref().clone())
-            })
-            .await
-            .attach(id)?;
-        data_in_cache.ok_or(CacheError::NotInCache.into())
+    fn cached(entry: &CacheEntry) -> Option<Self> {
+        entry
+            .account
+            .as_ref()
+            .map(|account| account.as_ref().clone())
+    }
+
+    fn fill_cache(&self, entry: &mut CacheEntry) {
+        entry.account = Some(self.clone().into());
+    }
+}
+
+impl ReadCacheJson for CalculatorStateInternal {
+    const CACHED_JSON: bool = true;
+
+    fn cached(entry: &CacheEntry) -> Option<Self> {
+        entry
+            .calculator_state
+            .as_ref()
+            .map(|state| state.as_ref().clone())
+    }
+
+    fn fill_cache(&self, entry: &mut CacheEntry) {
+        entry.calculator_state = Some(self.clone().into());
     }
 }
 
-impl ReadCacheJson for CalculatorStateInternal {}
+impl ReadCacheJson for CalculatorSettings {
+    const CACHED_JSON: bool = true;
+
+    fn cached(entry: &CacheEntry) -> Option<Self> {
+        entry
+            .calculator_settings
+            .as_ref()
+            .map(|settings| settings.as_ref().clone())
+    }
+
+    fn fill_cache(&self, entry: &mut CacheEntry) {
+        entry.calculator_settings = Some(self.clone().into());
+    }
+}
 
 #[async_trait]
 pub trait WriteCacheJson: Sized + Send {
@@ -355,6 +878,27 @@ pub trait WriteCacheJson: Sized + Send {
 
 impl WriteCacheJson for AccountSetup {}
 
+#[async_trait]
+impl WriteCacheJson for NotificationSettings {
+    async fn write_to_cache(
+        &self,
+        id: AccountIdLight,
+        cache: &DatabaseCache,
+    ) -> Result<(), CacheError> {
+        cache
+            .write_cache(id, |entry| {
+                entry
+                    .notification_settings
+                    .as_mut()
+                    .map(|data| *data.as_mut() = self.clone());
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+            .attach(id)
+    }
+}
+
 #[async_trait]
 impl WriteCacheJson for Account {
     async fn write_to_cache(
@@ -376,13 +920,44 @@ impl WriteCacheJson for Account {
     }
 }
 
+#[async_trait]
+impl WriteCacheJson for CalculatorSettings {
+    async fn write_to_cache(
+        &self,
+        id: AccountIdLight,
+        cache: &DatabaseCache,
+    ) -> Result<(), CacheError> {
+        cache
+            .write_cache(id, |entry| {
+                entry
+                    .calculator_settings
+                    .as_mut()
+                    .map(|data| *data.as_mut() = self.clone());
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+            .attach(id)
+    }
+}
+
 #[async_trait]
 impl WriteCacheJson for CalculatorStateInternal {
     async fn write_to_cache(
         &self,
-        _id: AccountIdLight,
-        _cache: &DatabaseCache,
+        id: AccountIdLight,
+        cache: &DatabaseCache,
     ) -> Result<(), CacheError> {
-        Ok(())
+        cache
+            .write_cache(id, |entry| {
+                entry
+                    .calculator_state
+                    .as_mut()
+                    .map(|data| *data.as_mut() = self.clone());
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+            .attach(id)
     }
 }
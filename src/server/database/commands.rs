@@ -3,56 +3,167 @@
 
 pub mod account;
 pub mod calculator;
+pub mod dispatcher;
 
-use std::{collections::HashSet, future::Future, net::SocketAddr, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
 
-use error_stack::Result;
+use error_stack::{Result, ResultExt};
+use serde::{Deserialize, Serialize};
 
 use tokio::{
     sync::{mpsc, oneshot, OwnedSemaphorePermit, RwLock, Semaphore},
     task::JoinHandle,
 };
 use tokio_stream::StreamExt;
+use tracing::debug;
+use utoipa::ToSchema;
 
 use crate::{
-    api::model::{AccountIdInternal, AccountIdLight, AuthPair},
+    api::{
+        common::{ClientVersion, EventToClient, NewSessionInfo},
+        model::{AccountIdInternal, AccountIdLight, AuthPair, RefreshToken},
+    },
     config::Config,
-    server::database::{write::WriteCommands, DatabaseError},
+    server::{
+        database::{write::WriteCommands, DatabaseError},
+        latency::{measure_phase, Phase},
+    },
     utils::{ErrorConversion, IntoReportExt},
 };
 
 use self::{
     account::{AccountWriteCommand, AccountWriteCommandRunnerHandle},
-    calculator::{CalculatorWriteCommand, CalculatorWriteCommandRunnerHandle},
+    calculator::{
+        CalculatorStateUpdate, CalculatorWriteCommand, CalculatorWriteCommandRunnerHandle,
+    },
+    dispatcher::{AccountWriteLockHandle, AccountWriteLockManager, WriteCommandDispatcher},
 };
 
 use super::RouterDatabaseWriteHandle;
 
-const CONCURRENT_WRITE_COMMAND_LIMIT: usize = 10;
-
 pub type ResultSender<T> = oneshot::Sender<Result<T, DatabaseError>>;
 
 /// Synchronized write commands.
 #[derive(Debug)]
 pub enum WriteCommand {
+    Login {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        pair: AuthPair,
+        session: NewSessionInfo,
+    },
     SetNewAuthPair {
         s: ResultSender<()>,
         account_id: AccountIdInternal,
         pair: AuthPair,
         address: Option<SocketAddr>,
+        client_version: Option<ClientVersion>,
+    },
+    RefreshAuthPair {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        current_refresh_token: RefreshToken,
+        pair: AuthPair,
+    },
+    ExchangeAuthPair {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        current_refresh_token: RefreshToken,
+        pair: AuthPair,
+        address: IpAddr,
     },
     Logout {
         s: ResultSender<()>,
         account_id: AccountIdInternal,
     },
+    RevokeSessions {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+    },
     EndConnectionSession {
         s: ResultSender<()>,
         account_id: AccountIdInternal,
+        remove_access_token: bool,
+    },
+    MigrateJson {
+        s: ResultSender<u64>,
+    },
+    RefreshCacheEntry {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+    },
+    EvictCacheEntry {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+    },
+    SaveAccountActivity {
+        s: ResultSender<u64>,
     },
     Account(AccountWriteCommand),
     Calculator(CalculatorWriteCommand),
 }
 
+impl WriteCommand {
+    /// Command name for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Login { .. } => "login",
+            Self::SetNewAuthPair { .. } => "set_new_auth_pair",
+            Self::RefreshAuthPair { .. } => "refresh_auth_pair",
+            Self::ExchangeAuthPair { .. } => "exchange_auth_pair",
+            Self::Logout { .. } => "logout",
+            Self::RevokeSessions { .. } => "revoke_sessions",
+            Self::EndConnectionSession { .. } => "end_connection_session",
+            Self::MigrateJson { .. } => "migrate_json",
+            Self::RefreshCacheEntry { .. } => "refresh_cache_entry",
+            Self::EvictCacheEntry { .. } => "evict_cache_entry",
+            Self::SaveAccountActivity { .. } => "save_account_activity",
+            Self::Account(cmd) => cmd.name(),
+            Self::Calculator(cmd) => cmd.name(),
+        }
+    }
+
+    /// Account which pending command count is limited. Session and cache
+    /// commands are not limited, so that those do not fail because of
+    /// other requests.
+    pub fn limited_account(&self) -> Option<AccountIdLight> {
+        match self {
+            Self::Account(cmd) => cmd.limited_account(),
+            Self::Calculator(cmd) => cmd.limited_account(),
+            _ => None,
+        }
+    }
+
+    /// Account which data the command writes. Commands of the same
+    /// account are run by the same shard runner.
+    pub fn shard_account(&self) -> Option<AccountIdLight> {
+        match self {
+            Self::Login { account_id, .. }
+            | Self::SetNewAuthPair { account_id, .. }
+            | Self::RefreshAuthPair { account_id, .. }
+            | Self::ExchangeAuthPair { account_id, .. }
+            | Self::Logout { account_id, .. }
+            | Self::RevokeSessions { account_id, .. }
+            | Self::EndConnectionSession { account_id, .. }
+            | Self::RefreshCacheEntry { account_id, .. }
+            | Self::EvictCacheEntry { account_id, .. } => Some(account_id.as_light()),
+            Self::MigrateJson { .. } | Self::SaveAccountActivity { .. } => None,
+            Self::Account(cmd) => cmd.shard_account(),
+            Self::Calculator(cmd) => cmd.shard_account(),
+        }
+    }
+}
+
 impl From<AccountWriteCommand> for WriteCommand {
     fn from(value: AccountWriteCommand) -> Self {
         Self::Account(value)
@@ -74,38 +185,190 @@ pub enum ConcurrentWriteCommand {
     },
 }
 
+/// Write command queue counters. Counters are totals since server start, so
+/// clients calculate rates from the difference of two reads.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct WriteQueueStats {
+    /// Commands which are waiting for the write command runner.
+    pub queue_depth: u64,
+    /// Handled commands.
+    pub commands: u64,
+    /// Total time which handled commands waited in the queue. Waiting ends
+    /// when the write command runner takes the command from the queue.
+    pub queue_wait_micros: u64,
+    /// Total time which the write command runner spent handling commands.
+    pub processing_micros: u64,
+    /// Transactions which contained multiple calculator state updates.
+    pub batches: u64,
+    /// Commands which were handled in batch transactions.
+    pub batched_commands: u64,
+}
+
+#[derive(Debug, Default)]
+struct WriteQueueCounters {
+    queue_depth: AtomicU64,
+    commands: AtomicU64,
+    queue_wait_micros: AtomicU64,
+    processing_micros: AtomicU64,
+    batches: AtomicU64,
+    batched_commands: AtomicU64,
+}
+
+impl WriteQueueCounters {
+    fn command_received(&self, queued: Instant, started: Instant) {
+        self.queue_depth.fetch_sub(1, Ordering::Relaxed);
+        self.commands.fetch_add(1, Ordering::Relaxed);
+        self.queue_wait_micros.fetch_add(
+            started.saturating_duration_since(queued).as_micros() as u64,
+            Ordering::Relaxed,
+        );
+    }
+
+    fn commands_handled(&self, started: Instant) {
+        self.processing_micros
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn batch_handled(&self, commands: usize) {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.batched_commands
+            .fetch_add(commands as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> WriteQueueStats {
+        WriteQueueStats {
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            commands: self.commands.load(Ordering::Relaxed),
+            queue_wait_micros: self.queue_wait_micros.load(Ordering::Relaxed),
+            processing_micros: self.processing_micros.load(Ordering::Relaxed),
+            batches: self.batches.load(Ordering::Relaxed),
+            batched_commands: self.batched_commands.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Unique ID of a write command. IDs are in the order the commands were
+/// sent and those are logged when the command is sent, received and handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteCommandId(u64);
+
+impl Display for WriteCommandId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug)]
+struct QueuedWriteCommand {
+    id: WriteCommandId,
+    /// Time when sending the command started.
+    queued: Instant,
+    cmd: WriteCommand,
+    /// Dispatcher sets this before dropping the command if the queue of
+    /// the shard runner is full.
+    busy: Arc<AtomicBool>,
+}
+
+/// Count of commands which each account has in the write command queue or
+/// running.
+#[derive(Debug)]
+struct PendingWrites {
+    counts: Mutex<HashMap<AccountIdLight, usize>>,
+    max_per_account: usize,
+}
+
+impl PendingWrites {
+    fn new(max_per_account: usize) -> Self {
+        Self {
+            counts: Mutex::new(HashMap::new()),
+            max_per_account,
+        }
+    }
+
+    /// Returns None if the account already has max count of pending
+    /// commands. The command is pending until the guard is dropped.
+    fn try_add(self: &Arc<Self>, id: AccountIdLight) -> Option<PendingWriteGuard> {
+        let mut counts = self.lock();
+        let count = counts.entry(id).or_insert(0);
+        if *count >= self.max_per_account {
+            return None;
+        }
+        *count += 1;
+        Some(PendingWriteGuard {
+            pending: self.clone(),
+            id,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<AccountIdLight, usize>> {
+        // Counts are always in valid state, so poisoning can be ignored.
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct PendingWriteGuard {
+    pending: Arc<PendingWrites>,
+    id: AccountIdLight,
+}
+
+impl Drop for PendingWriteGuard {
+    fn drop(&mut self) {
+        let mut counts = self.pending.lock();
+        if let Some(count) = counts.get_mut(&self.id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                counts.remove(&self.id);
+            }
+        }
+    }
+}
+
+fn log_command_completed(id: WriteCommandId, name: &'static str, started: Instant) {
+    debug!(
+        command_id = %id,
+        command = name,
+        processing_micros = started.elapsed().as_micros() as u64,
+        "Write command completed"
+    );
+}
+
 #[derive(Debug)]
 pub struct WriteCommandRunnerQuitHandle {
     handle: tokio::task::JoinHandle<()>,
+    runner_handles: Vec<tokio::task::JoinHandle<()>>,
     handle_for_concurrent: tokio::task::JoinHandle<()>,
 }
 
 impl WriteCommandRunnerQuitHandle {
+    /// Runners quit after the dispatcher, so the dispatcher is waited first.
     pub async fn quit(self) -> Result<(), DatabaseError> {
-        let e1 = self
-            .handle
-            .await
-            .into_error(DatabaseError::CommandRunnerQuit);
-        let e2 = self
-            .handle_for_concurrent
-            .await
-            .into_error(DatabaseError::CommandRunnerQuit);
-
-        match (e1, e2) {
-            (Ok(()), Ok(())) => Ok(()),
-            (Err(e), Ok(())) | (Ok(()), Err(e)) => Err(e),
-            (Err(mut e1), Err(e2)) => {
-                e1.extend_one(e2);
-                Err(e1)
+        let handles = std::iter::once(self.handle)
+            .chain(self.runner_handles)
+            .chain(std::iter::once(self.handle_for_concurrent));
+
+        let mut result: Result<(), DatabaseError> = Ok(());
+        for handle in handles {
+            if let Err(e) = handle.await.into_error(DatabaseError::CommandRunnerQuit) {
+                result = match result {
+                    Ok(()) => Err(e),
+                    Err(mut previous) => {
+                        previous.extend_one(e);
+                        Err(previous)
+                    }
+                };
             }
         }
+        result
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct WriteCommandRunnerHandle {
-    sender: mpsc::Sender<WriteCommand>,
+    sender: mpsc::Sender<QueuedWriteCommand>,
     sender_for_concurrent: mpsc::Sender<ConcurrentMessage>,
+    queue_counters: Arc<WriteQueueCounters>,
+    next_command_id: Arc<AtomicU64>,
+    pending_writes: Arc<PendingWrites>,
 }
 
 impl WriteCommandRunnerHandle {
@@ -117,17 +380,73 @@ impl WriteCommandRunnerHandle {
         CalculatorWriteCommandRunnerHandle { handle: self }
     }
 
+    /// Set new tokens and save the login time. WebSocket connection of the
+    /// previous session receives the session info.
+    pub async fn login(
+        &self,
+        account_id: AccountIdInternal,
+        pair: AuthPair,
+        session: NewSessionInfo,
+    ) -> Result<(), DatabaseError> {
+        self.send_event(|s| WriteCommand::Login {
+            s,
+            account_id,
+            pair,
+            session,
+        })
+        .await
+    }
+
     pub async fn set_new_auth_pair(
         &self,
         account_id: AccountIdInternal,
         pair: AuthPair,
         address: Option<SocketAddr>,
+        client_version: Option<ClientVersion>,
     ) -> Result<(), DatabaseError> {
         self.send_event(|s| WriteCommand::SetNewAuthPair {
             s,
             account_id,
             pair,
             address,
+            client_version,
+        })
+        .await
+    }
+
+    /// Replace tokens if the current refresh token matches. Returns false if
+    /// the refresh token did not match.
+    pub async fn refresh_auth_pair(
+        &self,
+        account_id: AccountIdInternal,
+        current_refresh_token: RefreshToken,
+        pair: AuthPair,
+    ) -> Result<bool, DatabaseError> {
+        self.send_event(|s| WriteCommand::RefreshAuthPair {
+            s,
+            account_id,
+            current_refresh_token,
+            pair,
+        })
+        .await
+    }
+
+    /// Replace tokens like `refresh_auth_pair` but the new session is
+    /// non-connected. The access token works only from `address` and
+    /// expires sooner than access tokens of WebSocket sessions.
+    pub async fn exchange_auth_pair(
+        &self,
+        account_id: AccountIdInternal,
+        current_refresh_token: RefreshToken,
+        pair: AuthPair,
+        address: IpAddr,
+    ) -> Result<bool, DatabaseError> {
+        self.send_event(|s| WriteCommand::ExchangeAuthPair {
+            s,
+            account_id,
+            current_refresh_token,
+            pair,
+            address,
         })
         .await
     }
@@ -137,26 +456,127 @@ impl WriteCommandRunnerHandle {
             .await
     }
 
+    /// Logout and close the current WebSocket connection.
+    pub async fn revoke_sessions(
+        &self,
+        account_id: AccountIdInternal,
+    ) -> Result<(), DatabaseError> {
+        self.send_event(|s| WriteCommand::RevokeSessions { s, account_id })
+            .await
+    }
+
+    /// Access token is removed if `remove_access_token` is true, so that
+    /// the token of an unresponsive client does not stay valid.
     pub async fn end_connection_session(
         &self,
         account_id: AccountIdInternal,
+        remove_access_token: bool,
+    ) -> Result<(), DatabaseError> {
+        self.send_event(|s| WriteCommand::EndConnectionSession {
+            s,
+            account_id,
+            remove_access_token,
+        })
+        .await
+    }
+
+    /// Rewrite all stored JSON data with the current JSON version.
+    pub async fn migrate_json(&self) -> Result<u64, DatabaseError> {
+        self.send_event(|s| WriteCommand::MigrateJson { s }).await
+    }
+
+    pub async fn refresh_cache_entry(
+        &self,
+        account_id: AccountIdInternal,
     ) -> Result<(), DatabaseError> {
-        self.send_event(|s| WriteCommand::EndConnectionSession { s, account_id })
+        self.send_event(|s| WriteCommand::RefreshCacheEntry { s, account_id })
             .await
     }
 
+    pub async fn evict_cache_entry(
+        &self,
+        account_id: AccountIdInternal,
+    ) -> Result<(), DatabaseError> {
+        self.send_event(|s| WriteCommand::EvictCacheEntry { s, account_id })
+            .await
+    }
+
+    /// Save cached account activity times to the database.
+    pub async fn save_account_activity(&self) -> Result<u64, DatabaseError> {
+        self.send_event(|s| WriteCommand::SaveAccountActivity { s })
+            .await
+    }
+
+    pub fn write_queue_stats(&self) -> WriteQueueStats {
+        self.queue_counters.stats()
+    }
+
+    /// False if a write command runner has stopped, so write commands
+    /// would fail.
+    pub fn is_running(&self) -> bool {
+        !self.sender.is_closed() && !self.sender_for_concurrent.is_closed()
+    }
+
     async fn send_event<T, R: Into<WriteCommand>>(
         &self,
         get_event: impl FnOnce(ResultSender<T>) -> R,
     ) -> Result<T, DatabaseError> {
         let (result_sender, receiver) = oneshot::channel();
-        self.sender
-            .send(get_event(result_sender).into())
-            .await
-            .into_error(DatabaseError::CommandSendingFailed)?;
-        receiver
-            .await
-            .into_error(DatabaseError::CommandResultReceivingFailed)?
+        let id = WriteCommandId(self.next_command_id.fetch_add(1, Ordering::Relaxed));
+        let cmd: WriteCommand = get_event(result_sender).into();
+        let _pending = match cmd.limited_account() {
+            Some(account) => match self.pending_writes.try_add(account) {
+                Some(guard) => Some(guard),
+                None => {
+                    return Err(
+                        error_stack::Report::new(DatabaseError::TooManyPendingWrites)
+                            .attach_printable(format!(
+                                "Write command {} rejected, account: {}",
+                                cmd.name(),
+                                account
+                            )),
+                    )
+                }
+            },
+            None => None,
+        };
+        debug!(command_id = %id, command = cmd.name(), "Write command enqueued");
+
+        self.queue_counters
+            .queue_depth
+            .fetch_add(1, Ordering::Relaxed);
+        let busy = Arc::new(AtomicBool::new(false));
+        let sending_result = self
+            .sender
+            .send(QueuedWriteCommand {
+                id,
+                queued: Instant::now(),
+                cmd,
+                busy: busy.clone(),
+            })
+            .await;
+        if sending_result.is_err() {
+            self.queue_counters
+                .queue_depth
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+
+        let result = match sending_result.into_error(DatabaseError::CommandSendingFailed) {
+            Ok(()) => match measure_phase(Phase::Database, receiver).await {
+                Ok(result) => result,
+                // Result sender is dropped after the flag is set, so the
+                // flag is visible when receiving fails.
+                Err(_) if busy.load(Ordering::Relaxed) => {
+                    self.queue_counters
+                        .queue_depth
+                        .fetch_sub(1, Ordering::Relaxed);
+                    Err(error_stack::Report::new(DatabaseError::WriteQueueBusy))
+                }
+                Err(e) => Err(e).into_error(DatabaseError::CommandResultReceivingFailed),
+            },
+            Err(e) => Err(e),
+        };
+        result.attach_printable_lazy(|| format!("Write command ID: {id}"))
     }
 
     async fn send_event_to_concurrent_runner<T>(
@@ -168,32 +588,55 @@ impl WriteCommandRunnerHandle {
             .send(get_event(result_sender))
             .await
             .into_error(DatabaseError::CommandSendingFailed)?;
-        receiver
+        measure_phase(Phase::Database, receiver)
             .await
             .into_error(DatabaseError::CommandResultReceivingFailed)?
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum WriteCommandShard {
+    /// Runs commands of the accounts which hash to this shard.
+    Account(usize),
+    /// Runs commands which are not related to a single account. Those are
+    /// not run at the same time with commands of the shard runners.
+    Global,
+}
+
+/// Runs synchronized write commands of one shard.
 pub struct WriteCommandRunner {
-    receiver: mpsc::Receiver<WriteCommand>,
+    receiver: mpsc::Receiver<QueuedWriteCommand>,
+    queue_counters: Arc<WriteQueueCounters>,
     write_handle: RouterDatabaseWriteHandle,
     config: Arc<Config>,
+    shard: WriteCommandShard,
+    account_locks: AccountWriteLockManager,
+    /// Shard runners hold the read lock while handling commands and the
+    /// global runner holds the write lock.
+    global_lock: Arc<RwLock<()>>,
 }
 
 impl WriteCommandRunner {
-    pub fn new_channel() -> (WriteCommandRunnerHandle, WriteCommandReceivers) {
-        let (sender, receiver) = mpsc::channel(1);
+    pub fn new_channel(config: &Config) -> (WriteCommandRunnerHandle, WriteCommandReceivers) {
+        let (sender, receiver) = mpsc::channel(config.limits().write_command_buffer);
         let (sender_for_concurrent, receiver_for_concurrent) = mpsc::channel(1);
+        let queue_counters = Arc::new(WriteQueueCounters::default());
 
         let runner_handle = WriteCommandRunnerHandle {
             sender,
             sender_for_concurrent,
+            queue_counters: queue_counters.clone(),
+            next_command_id: Arc::new(AtomicU64::new(0)),
+            pending_writes: Arc::new(PendingWrites::new(
+                config.limits().pending_writes_per_account,
+            )),
         };
         (
             runner_handle,
             WriteCommandReceivers {
                 receiver,
                 receiver_for_concurrent,
+                queue_counters,
             },
         )
     }
@@ -203,60 +646,262 @@ impl WriteCommandRunner {
         receiver: WriteCommandReceivers,
         config: Arc<Config>,
     ) -> WriteCommandRunnerQuitHandle {
-        let runner = Self {
-            receiver: receiver.receiver,
-            write_handle: write_handle.clone(),
-            config: config.clone(),
-        };
+        let dispatcher = WriteCommandDispatcher::new(
+            receiver.receiver,
+            receiver.queue_counters,
+            write_handle.clone(),
+            config.clone(),
+        );
 
         let runner_for_concurrent = ConcurrentWriteCommandRunner::new(
             receiver.receiver_for_concurrent,
+            dispatcher.account_locks(),
             write_handle,
             config,
         );
 
-        let handle = tokio::spawn(runner.run());
+        let (handle, runner_handles) = dispatcher.spawn();
         let handle_for_concurrent = tokio::spawn(runner_for_concurrent.run());
 
         let quit_handle = WriteCommandRunnerQuitHandle {
             handle,
+            runner_handles,
             handle_for_concurrent,
         };
 
         quit_handle
     }
 
-    /// Runs until web server part of the server quits.
+    /// Runs until the dispatcher quits.
+    ///
+    /// Commands which are already waiting in the queue are received
+    /// together, so that the queue does not block senders while a
+    /// command is handled.
     pub async fn run(mut self) {
+        let max_batch_size = self.config.limits().write_command_buffer;
+        let mut batch = Vec::with_capacity(max_batch_size);
         loop {
             match self.receiver.recv().await {
-                Some(cmd) => self.handle_cmd(cmd).await,
+                Some(cmd) => {
+                    batch.push(cmd);
+                    while batch.len() < max_batch_size {
+                        match self.receiver.try_recv() {
+                            Ok(cmd) => batch.push(cmd),
+                            Err(_) => break,
+                        }
+                    }
+                    self.handle_batch(&mut batch).await;
+                }
                 None => {
-                    tracing::info!("Write command runner closed");
+                    tracing::info!(shard = ?self.shard, "Write command runner closed");
                     break;
                 }
             }
         }
     }
 
+    async fn handle_batch(&self, batch: &mut Vec<QueuedWriteCommand>) {
+        match self.shard {
+            WriteCommandShard::Account(shard) => {
+                debug!(
+                    shard,
+                    batch_size = batch.len(),
+                    "Write command batch received"
+                );
+                let _running = self.global_lock.read().await;
+                self.handle_commands(batch).await
+            }
+            WriteCommandShard::Global => {
+                debug!(
+                    batch_size = batch.len(),
+                    "Global write command batch received"
+                );
+                let _running = self.global_lock.write().await;
+                self.handle_commands(batch).await
+            }
+        }
+    }
+
+    /// Handle commands in the order they were sent. Consecutive calculator
+    /// state updates are written in a single transaction, so commands of
+    /// one account keep their order.
+    async fn handle_commands(&self, batch: &mut Vec<QueuedWriteCommand>) {
+        let started = Instant::now();
+        let mut state_updates = vec![];
+        let mut state_update_ids = vec![];
+        for QueuedWriteCommand {
+            id, queued, cmd, ..
+        } in batch.drain(..)
+        {
+            self.queue_counters.command_received(queued, started);
+            debug!(
+                command_id = %id,
+                command = cmd.name(),
+                queue_wait_micros = started.saturating_duration_since(queued).as_micros() as u64,
+                "Write command dequeued"
+            );
+            match cmd {
+                WriteCommand::Calculator(CalculatorWriteCommand::UpdateCalculatorState {
+                    s,
+                    account_id,
+                    data,
+                }) => {
+                    state_updates.push(CalculatorStateUpdate {
+                        s,
+                        account_id,
+                        data,
+                    });
+                    state_update_ids.push(id);
+                }
+                cmd => {
+                    self.handle_state_update_batch(
+                        std::mem::take(&mut state_updates),
+                        std::mem::take(&mut state_update_ids),
+                    )
+                    .await;
+                    let name = cmd.name();
+                    let lock = match cmd.shard_account() {
+                        Some(account) => {
+                            Some(self.account_locks.wait_and_set_as_running(account).await)
+                        }
+                        None => None,
+                    };
+                    let cmd_started = Instant::now();
+                    self.handle_cmd(cmd).await;
+                    log_command_completed(id, name, cmd_started);
+                    if let Some(lock) = lock {
+                        lock.release().await;
+                    }
+                }
+            }
+        }
+        self.handle_state_update_batch(state_updates, state_update_ids)
+            .await;
+        self.queue_counters.commands_handled(started);
+    }
+
+    async fn handle_state_update_batch(
+        &self,
+        updates: Vec<CalculatorStateUpdate>,
+        ids: Vec<WriteCommandId>,
+    ) {
+        if updates.is_empty() {
+            return;
+        }
+        let mut locks = vec![];
+        for account in updates
+            .iter()
+            .map(|u| u.account_id.as_light())
+            .collect::<HashSet<_>>()
+        {
+            locks.push(self.account_locks.wait_and_set_as_running(account).await);
+        }
+        let started = Instant::now();
+        self.handle_calculator_state_updates(updates).await;
+        for lock in locks {
+            lock.release().await;
+        }
+        for id in ids {
+            log_command_completed(id, "update_calculator_state", started);
+        }
+    }
+
     pub async fn handle_cmd(&self, cmd: WriteCommand) {
         match cmd {
             WriteCommand::Logout { s, account_id } => self.write().logout(account_id).await.send(s),
-            WriteCommand::EndConnectionSession { s, account_id } => self
+            WriteCommand::RevokeSessions { s, account_id } => {
+                self.write().revoke_sessions(account_id).await.send(s)
+            }
+            WriteCommand::EndConnectionSession {
+                s,
+                account_id,
+                remove_access_token,
+            } => self
                 .write()
-                .end_connection_session(account_id, false)
+                .end_connection_session(account_id, remove_access_token)
                 .await
                 .send(s),
+            WriteCommand::Login {
+                s,
+                account_id,
+                pair,
+                session,
+            } => {
+                let r = self
+                    .write()
+                    .login(account_id, pair, self.access_token_expiration())
+                    .await;
+                self.send_event_if_ok(&r, account_id, EventToClient::NewSessionCreated(session))
+                    .await;
+                r.send(s)
+            }
             WriteCommand::SetNewAuthPair {
                 s,
                 account_id,
                 pair,
                 address,
+                client_version,
+            } => self
+                .write()
+                .set_new_auth_pair(
+                    account_id,
+                    pair,
+                    self.access_token_expiration(),
+                    address,
+                    client_version,
+                )
+                .await
+                .send(s),
+            WriteCommand::RefreshAuthPair {
+                s,
+                account_id,
+                current_refresh_token,
+                pair,
+            } => self
+                .write()
+                .refresh_auth_pair(
+                    account_id,
+                    current_refresh_token,
+                    pair,
+                    self.access_token_expiration(),
+                    None,
+                )
+                .await
+                .send(s),
+            WriteCommand::ExchangeAuthPair {
+                s,
+                account_id,
+                current_refresh_token,
+                pair,
+                address,
             } => self
                 .write()
-                .set_new_auth_pair(account_id, pair, address)
+                .refresh_auth_pair(
+                    account_id,
+                    current_refresh_token,
+                    pair,
+                    Some(
+                        self.config.unix_time()
+                            + self.config.http_session_access_token_ttl_seconds(),
+                    ),
+                    Some(address),
+                )
                 .await
                 .send(s),
+            WriteCommand::MigrateJson { s } => {
+                self.write().migrate_json(&self.config).await.send(s)
+            }
+            WriteCommand::RefreshCacheEntry { s, account_id } => self
+                .write()
+                .refresh_cache_entry(account_id, &self.config)
+                .await
+                .send(s),
+            WriteCommand::EvictCacheEntry { s, account_id } => {
+                self.write().evict_cache_entry(account_id).await.send(s)
+            }
+            WriteCommand::SaveAccountActivity { s } => {
+                self.write().save_account_activity().await.send(s)
+            }
             WriteCommand::Account(cmd) => self.handle_account_cmd(cmd).await,
             WriteCommand::Calculator(cmd) => self.handle_calculator_cmd(cmd).await,
         }
@@ -265,6 +910,29 @@ impl WriteCommandRunner {
     fn write(&self) -> WriteCommands {
         self.write_handle.user_write_commands()
     }
+
+    /// Send event to the WebSocket connection of the account if the write
+    /// command succeeded.
+    async fn send_event_if_ok<T>(
+        &self,
+        result: &Result<T, DatabaseError>,
+        id: AccountIdInternal,
+        event: EventToClient,
+    ) {
+        if result.is_ok() {
+            self.write_handle
+                .event_manager()
+                .send_connected_event(id.as_light(), event)
+                .await;
+        }
+    }
+
+    /// Expiration time for a new access token.
+    fn access_token_expiration(&self) -> Option<i64> {
+        self.config
+            .access_token_ttl_seconds()
+            .map(|ttl| self.config.unix_time() + ttl)
+    }
 }
 
 trait SendBack<T>: Sized {
@@ -287,56 +955,29 @@ impl<D> SendBack<D> for Result<D, DatabaseError> {
 type ConcurrentMessage = (AccountIdLight, ConcurrentWriteCommand);
 
 pub struct WriteCommandReceivers {
-    receiver: mpsc::Receiver<WriteCommand>,
+    receiver: mpsc::Receiver<QueuedWriteCommand>,
     receiver_for_concurrent: mpsc::Receiver<ConcurrentMessage>,
+    queue_counters: Arc<WriteQueueCounters>,
 }
 
 pub struct ConcurrentWriteCommandRunner {
     receiver: mpsc::Receiver<ConcurrentMessage>,
+    account_locks: AccountWriteLockManager,
     write_handle: RouterDatabaseWriteHandle,
     config: Arc<Config>,
     task_handles: Vec<JoinHandle<()>>,
 }
 
-#[derive(Default, Clone)]
-pub struct AccountWriteLockManager {
-    locks: Arc<RwLock<HashSet<AccountIdLight>>>,
-}
-
-#[must_use]
-struct AccountWriteLockHandle {
-    locks: Arc<RwLock<HashSet<AccountIdLight>>>,
-    account: AccountIdLight,
-}
-
-impl AccountWriteLockManager {
-    #[must_use]
-    async fn set_as_running(&self, a: AccountIdLight) -> Option<AccountWriteLockHandle> {
-        if self.locks.write().await.insert(a) {
-            Some(AccountWriteLockHandle {
-                locks: self.locks.clone(),
-                account: a,
-            })
-        } else {
-            None
-        }
-    }
-}
-
-impl AccountWriteLockHandle {
-    async fn release(self) {
-        self.locks.write().await.remove(&self.account);
-    }
-}
-
 impl ConcurrentWriteCommandRunner {
     pub fn new(
         receiver: mpsc::Receiver<ConcurrentMessage>,
+        account_locks: AccountWriteLockManager,
         write_handle: RouterDatabaseWriteHandle,
         config: Arc<Config>,
     ) -> Self {
         Self {
             receiver,
+            account_locks,
             write_handle,
             config,
             task_handles: vec![],
@@ -345,14 +986,15 @@ impl ConcurrentWriteCommandRunner {
 
     /// Runs until web server part of the server quits.
     pub async fn run(mut self) {
-        let task_limiter = Arc::new(Semaphore::new(CONCURRENT_WRITE_COMMAND_LIMIT));
+        let task_limiter = Arc::new(Semaphore::new(
+            self.config.limits().concurrent_write_commands,
+        ));
         let mut skip = false;
-        let cmd_owners = AccountWriteLockManager::default();
         loop {
             match self.receiver.recv().await {
                 Some(_) if skip => (),
                 Some((cmd_owner, cmd)) => {
-                    let lock = match cmd_owners.set_as_running(cmd_owner).await {
+                    let lock = match self.account_locks.set_as_running(cmd_owner).await {
                         None => {
                             // Cmd already running. Client handles that this is
                             // not possible.
@@ -431,5 +1073,21 @@ impl ConcurrentWriteCommandRunner {
         self.write_handle.user_write_commands()
     }
 
+    /// Send event to the WebSocket connection of the account if the write
+    /// command succeeded.
+    async fn send_event_if_ok<T>(
+        &self,
+        result: &Result<T, DatabaseError>,
+        id: AccountIdInternal,
+        event: EventToClient,
+    ) {
+        if result.is_ok() {
+            self.write_handle
+                .event_manager()
+                .send_connected_event(id.as_light(), event)
+                .await;
+        }
+    }
+
     async fn handle_cmd_in_task(_cmd: ConcurrentWriteCommand) {}
 }
@@ -1,9 +1,19 @@
+use std::net::IpAddr;
+
 use super::{ResultSender, SendBack, WriteCommandRunner, WriteCommandRunnerHandle};
 
 use error_stack::Result;
 
 use crate::{
-    api::model::{Account, AccountIdInternal, AccountIdLight, AccountSetup, SignInWithInfo},
+    api::{
+        common::EventToClient,
+        model::{
+            Account, AccountDeletionInfo, AccountIdInternal, AccountIdLight, AccountSetup,
+            CreatedServiceToken, LoginMethod, NewOrganization, NewServiceToken,
+            NotificationSettings, OrganizationId, OrganizationSettings, ServiceTokenId,
+            SignInWithInfo,
+        },
+    },
     server::database::DatabaseError,
 };
 
@@ -14,6 +24,7 @@ pub enum AccountWriteCommand {
         s: ResultSender<AccountIdInternal>,
         sign_in_with_info: SignInWithInfo,
         account_id: AccountIdLight,
+        region: Option<String>,
     },
     UpdateAccount {
         s: ResultSender<()>,
@@ -25,6 +36,126 @@ pub enum AccountWriteCommand {
         account_id: AccountIdInternal,
         account_setup: AccountSetup,
     },
+    UpdateNotificationSettings {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        settings: NotificationSettings,
+    },
+    RequestDeletion {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        account: Account,
+        info: AccountDeletionInfo,
+    },
+    CancelDeletion {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        account: Account,
+    },
+    DeleteAccount {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+    },
+    CreateServiceToken {
+        s: ResultSender<Option<CreatedServiceToken>>,
+        account_id: AccountIdInternal,
+        token: NewServiceToken,
+        max_tokens: i64,
+    },
+    RevokeServiceToken {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    },
+    InsertLoginHistory {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        method: LoginMethod,
+        address: Option<IpAddr>,
+    },
+    CreateOrganization {
+        s: ResultSender<OrganizationId>,
+        org: NewOrganization,
+    },
+    SetOrganizationMember {
+        s: ResultSender<bool>,
+        org_id: OrganizationId,
+        account_id: AccountIdInternal,
+        is_admin: bool,
+    },
+    RemoveOrganizationMember {
+        s: ResultSender<bool>,
+        org_id: OrganizationId,
+        account_id: AccountIdInternal,
+    },
+    SetOrganizationSettings {
+        s: ResultSender<bool>,
+        org_id: OrganizationId,
+        settings: OrganizationSettings,
+    },
+}
+
+impl AccountWriteCommand {
+    /// Command name for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Register { .. } => "register",
+            Self::UpdateAccount { .. } => "update_account",
+            Self::UpdateAccountSetup { .. } => "update_account_setup",
+            Self::UpdateNotificationSettings { .. } => "update_notification_settings",
+            Self::RequestDeletion { .. } => "request_deletion",
+            Self::CancelDeletion { .. } => "cancel_deletion",
+            Self::DeleteAccount { .. } => "delete_account",
+            Self::CreateServiceToken { .. } => "create_service_token",
+            Self::RevokeServiceToken { .. } => "revoke_service_token",
+            Self::InsertLoginHistory { .. } => "insert_login_history",
+            Self::CreateOrganization { .. } => "create_organization",
+            Self::SetOrganizationMember { .. } => "set_organization_member",
+            Self::RemoveOrganizationMember { .. } => "remove_organization_member",
+            Self::SetOrganizationSettings { .. } => "set_organization_settings",
+        }
+    }
+
+    /// Account which pending command count is limited. Commands which the
+    /// server makes by itself are not limited.
+    pub fn limited_account(&self) -> Option<AccountIdLight> {
+        match self {
+            Self::UpdateAccount { account_id, .. }
+            | Self::UpdateAccountSetup { account_id, .. }
+            | Self::UpdateNotificationSettings { account_id, .. }
+            | Self::RequestDeletion { account_id, .. }
+            | Self::CancelDeletion { account_id, .. }
+            | Self::CreateServiceToken { account_id, .. }
+            | Self::RevokeServiceToken { account_id, .. } => Some(account_id.as_light()),
+            Self::Register { .. }
+            | Self::DeleteAccount { .. }
+            | Self::InsertLoginHistory { .. }
+            | Self::CreateOrganization { .. }
+            | Self::SetOrganizationMember { .. }
+            | Self::RemoveOrganizationMember { .. }
+            | Self::SetOrganizationSettings { .. } => None,
+        }
+    }
+
+    /// Account which data the command writes. None if the command is not
+    /// related to a single account.
+    pub fn shard_account(&self) -> Option<AccountIdLight> {
+        match self {
+            Self::Register { account_id, .. } => Some(*account_id),
+            Self::UpdateAccount { account_id, .. }
+            | Self::UpdateAccountSetup { account_id, .. }
+            | Self::UpdateNotificationSettings { account_id, .. }
+            | Self::RequestDeletion { account_id, .. }
+            | Self::CancelDeletion { account_id, .. }
+            | Self::DeleteAccount { account_id, .. }
+            | Self::CreateServiceToken { account_id, .. }
+            | Self::RevokeServiceToken { account_id, .. }
+            | Self::InsertLoginHistory { account_id, .. }
+            | Self::SetOrganizationMember { account_id, .. }
+            | Self::RemoveOrganizationMember { account_id, .. } => Some(account_id.as_light()),
+            Self::CreateOrganization { .. } | Self::SetOrganizationSettings { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,12 +168,14 @@ impl AccountWriteCommandRunnerHandle<'_> {
         &self,
         account_id: AccountIdLight,
         sign_in_with_info: SignInWithInfo,
+        region: Option<String>,
     ) -> Result<AccountIdInternal, DatabaseError> {
         self.handle
             .send_event(|s| AccountWriteCommand::Register {
                 s,
                 sign_in_with_info,
                 account_id,
+                region,
             })
             .await
     }
@@ -74,6 +207,163 @@ impl AccountWriteCommandRunnerHandle<'_> {
             })
             .await
     }
+
+    pub async fn update_notification_settings(
+        &self,
+        account_id: AccountIdInternal,
+        settings: NotificationSettings,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::UpdateNotificationSettings {
+                s,
+                account_id,
+                settings,
+            })
+            .await
+    }
+
+    pub async fn request_deletion(
+        &self,
+        account_id: AccountIdInternal,
+        account: Account,
+        info: AccountDeletionInfo,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::RequestDeletion {
+                s,
+                account_id,
+                account,
+                info,
+            })
+            .await
+    }
+
+    pub async fn cancel_deletion(
+        &self,
+        account_id: AccountIdInternal,
+        account: Account,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::CancelDeletion {
+                s,
+                account_id,
+                account,
+            })
+            .await
+    }
+
+    /// Remove all account data. WebSocket connection of the account is
+    /// closed.
+    pub async fn delete_account(&self, account_id: AccountIdInternal) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::DeleteAccount { s, account_id })
+            .await
+    }
+
+    /// Returns None if account has already `max_tokens` service tokens.
+    pub async fn create_service_token(
+        &self,
+        account_id: AccountIdInternal,
+        token: NewServiceToken,
+        max_tokens: i64,
+    ) -> Result<Option<CreatedServiceToken>, DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::CreateServiceToken {
+                s,
+                account_id,
+                token,
+                max_tokens,
+            })
+            .await
+    }
+
+    /// Returns false if account does not have the service token.
+    pub async fn revoke_service_token(
+        &self,
+        account_id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::RevokeServiceToken {
+                s,
+                account_id,
+                token_id,
+            })
+            .await
+    }
+
+    pub async fn insert_login_history(
+        &self,
+        account_id: AccountIdInternal,
+        method: LoginMethod,
+        address: Option<IpAddr>,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::InsertLoginHistory {
+                s,
+                account_id,
+                method,
+                address,
+            })
+            .await
+    }
+
+    pub async fn create_organization(
+        &self,
+        org: NewOrganization,
+    ) -> Result<OrganizationId, DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::CreateOrganization { s, org })
+            .await
+    }
+
+    /// Add account to the organization or change the admin status. Returns
+    /// false if the organization does not exist.
+    pub async fn set_organization_member(
+        &self,
+        org_id: OrganizationId,
+        account_id: AccountIdInternal,
+        is_admin: bool,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::SetOrganizationMember {
+                s,
+                org_id,
+                account_id,
+                is_admin,
+            })
+            .await
+    }
+
+    /// Returns false if the account is not a member of the organization.
+    pub async fn remove_organization_member(
+        &self,
+        org_id: OrganizationId,
+        account_id: AccountIdInternal,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::RemoveOrganizationMember {
+                s,
+                org_id,
+                account_id,
+            })
+            .await
+    }
+
+    /// Returns false if the organization does not exist.
+    pub async fn set_organization_settings(
+        &self,
+        org_id: OrganizationId,
+        settings: OrganizationSettings,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| AccountWriteCommand::SetOrganizationSettings {
+                s,
+                org_id,
+                settings,
+            })
+            .await
+    }
 }
 
 impl WriteCommandRunner {
@@ -83,16 +373,22 @@ impl WriteCommandRunner {
                 s,
                 sign_in_with_info,
                 account_id,
+                region,
             } => self
                 .write_handle
-                .register(account_id, sign_in_with_info, &self.config)
+                .register(account_id, sign_in_with_info, region, &self.config)
                 .await
                 .send(s),
             AccountWriteCommand::UpdateAccount {
                 s,
                 account_id,
                 account,
-            } => self.write().update_data(account_id, &account).await.send(s),
+            } => {
+                let r = self.write().update_account(account_id, account).await;
+                self.send_event_if_ok(&r, account_id, EventToClient::AccountStateChanged)
+                    .await;
+                r.send(s)
+            }
             AccountWriteCommand::UpdateAccountSetup {
                 s,
                 account_id,
@@ -102,6 +398,105 @@ impl WriteCommandRunner {
                 .update_data(account_id, &account_setup)
                 .await
                 .send(s),
+            AccountWriteCommand::UpdateNotificationSettings {
+                s,
+                account_id,
+                settings,
+            } => self
+                .write()
+                .update_data(account_id, &settings)
+                .await
+                .send(s),
+            AccountWriteCommand::RequestDeletion {
+                s,
+                account_id,
+                account,
+                info,
+            } => {
+                let r = self
+                    .write()
+                    .request_account_deletion(account_id, account, info)
+                    .await;
+                self.send_event_if_ok(&r, account_id, EventToClient::AccountStateChanged)
+                    .await;
+                r.send(s)
+            }
+            AccountWriteCommand::CancelDeletion {
+                s,
+                account_id,
+                account,
+            } => {
+                let r = self
+                    .write()
+                    .cancel_account_deletion(account_id, account)
+                    .await;
+                self.send_event_if_ok(&r, account_id, EventToClient::AccountStateChanged)
+                    .await;
+                r.send(s)
+            }
+            AccountWriteCommand::DeleteAccount { s, account_id } => {
+                self.write().delete_account(account_id).await.send(s)
+            }
+            AccountWriteCommand::CreateServiceToken {
+                s,
+                account_id,
+                token,
+                max_tokens,
+            } => self
+                .write()
+                .create_service_token(account_id, token, max_tokens)
+                .await
+                .send(s),
+            AccountWriteCommand::RevokeServiceToken {
+                s,
+                account_id,
+                token_id,
+            } => self
+                .write()
+                .revoke_service_token(account_id, token_id)
+                .await
+                .send(s),
+            AccountWriteCommand::InsertLoginHistory {
+                s,
+                account_id,
+                method,
+                address,
+            } => self
+                .write()
+                .insert_login_history(account_id, method, address)
+                .await
+                .send(s),
+            AccountWriteCommand::CreateOrganization { s, org } => {
+                self.write().create_organization(org).await.send(s)
+            }
+            AccountWriteCommand::SetOrganizationMember {
+                s,
+                org_id,
+                account_id,
+                is_admin,
+            } => self
+                .write()
+                .set_organization_member(org_id, account_id, is_admin)
+                .await
+                .send(s),
+            AccountWriteCommand::RemoveOrganizationMember {
+                s,
+                org_id,
+                account_id,
+            } => self
+                .write()
+                .remove_organization_member(org_id, account_id)
+                .await
+                .send(s),
+            AccountWriteCommand::SetOrganizationSettings {
+                s,
+                org_id,
+                settings,
+            } => self
+                .write()
+                .set_organization_settings(org_id, settings)
+                .await
+                .send(s),
         }
     }
 }
@@ -1,9 +1,20 @@
+use std::collections::{hash_map::Entry, HashMap};
+
 use super::{ResultSender, SendBack, WriteCommandRunner, WriteCommandRunnerHandle};
 
 use error_stack::Result;
+use tracing::error;
 
 use crate::{
-    api::{calculator::data::CalculatorStateInternal, model::AccountIdInternal},
+    api::{
+        calculator::data::{
+            CalculationSchedule, CalculatorSettings, CalculatorSlotName, CalculatorStateBroadcast,
+            CalculatorStateInternal, HistoryId, NewCalculationSchedule, NewHistoryEntry,
+            ScheduleId,
+        },
+        common::EventToClient,
+        model::{AccountIdInternal, AccountIdLight, OrganizationId},
+    },
     server::database::DatabaseError,
 };
 
@@ -15,6 +26,150 @@ pub enum CalculatorWriteCommand {
         account_id: AccountIdInternal,
         data: CalculatorStateInternal,
     },
+    UpdateCalculatorSettings {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        settings: CalculatorSettings,
+    },
+    CreateCalculationSchedule {
+        s: ResultSender<Option<ScheduleId>>,
+        account_id: AccountIdInternal,
+        schedule: NewCalculationSchedule,
+        max_schedules: i64,
+    },
+    UpdateCalculationSchedule {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        schedule_id: ScheduleId,
+        schedule: NewCalculationSchedule,
+    },
+    DeleteCalculationSchedule {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        schedule_id: ScheduleId,
+    },
+    PutCalculatorSlot {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        slot_name: CalculatorSlotName,
+        state: String,
+        max_slots: i64,
+    },
+    DeleteCalculatorSlot {
+        s: ResultSender<bool>,
+        account_id: AccountIdInternal,
+        slot_name: CalculatorSlotName,
+    },
+    CompleteScheduledCalculation {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        schedule: CalculationSchedule,
+        entry: NewHistoryEntry,
+    },
+    InsertHistoryEntry {
+        s: ResultSender<HistoryId>,
+        account_id: AccountIdInternal,
+        entry: NewHistoryEntry,
+    },
+    ShareCalculatorState {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        reader: AccountIdInternal,
+    },
+    RemoveCalculatorStateShare {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        reader: AccountIdInternal,
+    },
+    SetCalculatorBroadcast {
+        s: ResultSender<()>,
+        account_id: AccountIdInternal,
+        org_id: Option<OrganizationId>,
+    },
+    DeleteExpiredHistoryEntries {
+        s: ResultSender<u64>,
+        default_retention_days: Option<i64>,
+    },
+}
+
+impl CalculatorWriteCommand {
+    /// Command name for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UpdateCalculatorState { .. } => "update_calculator_state",
+            Self::UpdateCalculatorSettings { .. } => "update_calculator_settings",
+            Self::CreateCalculationSchedule { .. } => "create_calculation_schedule",
+            Self::UpdateCalculationSchedule { .. } => "update_calculation_schedule",
+            Self::DeleteCalculationSchedule { .. } => "delete_calculation_schedule",
+            Self::PutCalculatorSlot { .. } => "put_calculator_slot",
+            Self::DeleteCalculatorSlot { .. } => "delete_calculator_slot",
+            Self::CompleteScheduledCalculation { .. } => "complete_scheduled_calculation",
+            Self::InsertHistoryEntry { .. } => "insert_history_entry",
+            Self::ShareCalculatorState { .. } => "share_calculator_state",
+            Self::RemoveCalculatorStateShare { .. } => "remove_calculator_state_share",
+            Self::SetCalculatorBroadcast { .. } => "set_calculator_broadcast",
+            Self::DeleteExpiredHistoryEntries { .. } => "delete_expired_history_entries",
+        }
+    }
+
+    /// Account which pending command count is limited. Commands which the
+    /// server makes by itself are not limited.
+    pub fn limited_account(&self) -> Option<AccountIdLight> {
+        match self {
+            Self::UpdateCalculatorState { account_id, .. }
+            | Self::UpdateCalculatorSettings { account_id, .. }
+            | Self::CreateCalculationSchedule { account_id, .. }
+            | Self::UpdateCalculationSchedule { account_id, .. }
+            | Self::DeleteCalculationSchedule { account_id, .. }
+            | Self::PutCalculatorSlot { account_id, .. }
+            | Self::DeleteCalculatorSlot { account_id, .. }
+            | Self::InsertHistoryEntry { account_id, .. }
+            | Self::ShareCalculatorState { account_id, .. }
+            | Self::RemoveCalculatorStateShare { account_id, .. }
+            | Self::SetCalculatorBroadcast { account_id, .. } => Some(account_id.as_light()),
+            Self::CompleteScheduledCalculation { .. }
+            | Self::DeleteExpiredHistoryEntries { .. } => None,
+        }
+    }
+
+    /// Account which data the command writes. None if the command is not
+    /// related to a single account.
+    pub fn shard_account(&self) -> Option<AccountIdLight> {
+        match self {
+            Self::UpdateCalculatorState { account_id, .. }
+            | Self::UpdateCalculatorSettings { account_id, .. }
+            | Self::CreateCalculationSchedule { account_id, .. }
+            | Self::UpdateCalculationSchedule { account_id, .. }
+            | Self::DeleteCalculationSchedule { account_id, .. }
+            | Self::PutCalculatorSlot { account_id, .. }
+            | Self::DeleteCalculatorSlot { account_id, .. }
+            | Self::CompleteScheduledCalculation { account_id, .. }
+            | Self::InsertHistoryEntry { account_id, .. }
+            | Self::ShareCalculatorState { account_id, .. }
+            | Self::RemoveCalculatorStateShare { account_id, .. }
+            | Self::SetCalculatorBroadcast { account_id, .. } => Some(account_id.as_light()),
+            Self::DeleteExpiredHistoryEntries { .. } => None,
+        }
+    }
+}
+
+/// Calculator state update which is waiting to be written with other
+/// updates.
+#[derive(Debug)]
+pub struct CalculatorStateUpdate {
+    pub s: ResultSender<()>,
+    pub account_id: AccountIdInternal,
+    pub data: CalculatorStateInternal,
+}
+
+impl From<CalculatorStateUpdate> for CalculatorWriteCommand {
+    fn from(value: CalculatorStateUpdate) -> Self {
+        CalculatorWriteCommand::UpdateCalculatorState {
+            s: value.s,
+            account_id: value.account_id,
+            data: value.data,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,16 +191,416 @@ impl CalculatorWriteCommandRunnerHandle<'_> {
             })
             .await
     }
+
+    pub async fn update_calculator_settings(
+        &self,
+        account_id: AccountIdInternal,
+        settings: CalculatorSettings,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::UpdateCalculatorSettings {
+                s,
+                account_id,
+                settings,
+            })
+            .await
+    }
+
+    /// Returns None if account has already `max_schedules` schedules.
+    pub async fn create_calculation_schedule(
+        &self,
+        account_id: AccountIdInternal,
+        schedule: NewCalculationSchedule,
+        max_schedules: i64,
+    ) -> Result<Option<ScheduleId>, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::CreateCalculationSchedule {
+                s,
+                account_id,
+                schedule,
+                max_schedules,
+            })
+            .await
+    }
+
+    /// Returns false if account does not have the schedule.
+    pub async fn update_calculation_schedule(
+        &self,
+        account_id: AccountIdInternal,
+        schedule_id: ScheduleId,
+        schedule: NewCalculationSchedule,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::UpdateCalculationSchedule {
+                s,
+                account_id,
+                schedule_id,
+                schedule,
+            })
+            .await
+    }
+
+    /// Returns false if account does not have the schedule.
+    pub async fn delete_calculation_schedule(
+        &self,
+        account_id: AccountIdInternal,
+        schedule_id: ScheduleId,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::DeleteCalculationSchedule {
+                s,
+                account_id,
+                schedule_id,
+            })
+            .await
+    }
+
+    /// Create or replace calculator slot. Returns false if the slot does
+    /// not exist and account has already `max_slots` slots.
+    pub async fn put_calculator_slot(
+        &self,
+        account_id: AccountIdInternal,
+        slot_name: CalculatorSlotName,
+        state: String,
+        max_slots: i64,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::PutCalculatorSlot {
+                s,
+                account_id,
+                slot_name,
+                state,
+                max_slots,
+            })
+            .await
+    }
+
+    /// Returns false if account does not have the slot.
+    pub async fn delete_calculator_slot(
+        &self,
+        account_id: AccountIdInternal,
+        slot_name: CalculatorSlotName,
+    ) -> Result<bool, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::DeleteCalculatorSlot {
+                s,
+                account_id,
+                slot_name,
+            })
+            .await
+    }
+
+    pub async fn complete_scheduled_calculation(
+        &self,
+        account_id: AccountIdInternal,
+        schedule: CalculationSchedule,
+        entry: NewHistoryEntry,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::CompleteScheduledCalculation {
+                s,
+                account_id,
+                schedule,
+                entry,
+            })
+            .await
+    }
+
+    pub async fn insert_history_entry(
+        &self,
+        account_id: AccountIdInternal,
+        entry: NewHistoryEntry,
+    ) -> Result<HistoryId, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::InsertHistoryEntry {
+                s,
+                account_id,
+                entry,
+            })
+            .await
+    }
+
+    /// Allow `reader` to read the calculator state of the account.
+    pub async fn share_calculator_state(
+        &self,
+        account_id: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::ShareCalculatorState {
+                s,
+                account_id,
+                reader,
+            })
+            .await
+    }
+
+    /// Does nothing if the calculator state is not shared with `reader`.
+    pub async fn remove_calculator_state_share(
+        &self,
+        account_id: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::RemoveCalculatorStateShare {
+                s,
+                account_id,
+                reader,
+            })
+            .await
+    }
+
+    /// Broadcast calculator state changes to the members of `org_id`.
+    /// None stops broadcasting.
+    pub async fn set_calculator_broadcast(
+        &self,
+        account_id: AccountIdInternal,
+        org_id: Option<OrganizationId>,
+    ) -> Result<(), DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::SetCalculatorBroadcast {
+                s,
+                account_id,
+                org_id,
+            })
+            .await
+    }
+
+    /// Returns count of deleted history entries.
+    pub async fn delete_expired_history_entries(
+        &self,
+        default_retention_days: Option<i64>,
+    ) -> Result<u64, DatabaseError> {
+        self.handle
+            .send_event(|s| CalculatorWriteCommand::DeleteExpiredHistoryEntries {
+                s,
+                default_retention_days,
+            })
+            .await
+    }
 }
 
 impl WriteCommandRunner {
+    /// Write calculator state updates in a single transaction. Only the
+    /// latest state of an account is written. If the transaction fails,
+    /// updates are handled one at a time, so that every command gets its
+    /// own result.
+    pub async fn handle_calculator_state_updates(&self, mut updates: Vec<CalculatorStateUpdate>) {
+        if updates.len() <= 1 {
+            if let Some(update) = updates.pop() {
+                self.handle_calculator_cmd(update.into()).await;
+            }
+            return;
+        }
+
+        let mut states: Vec<(AccountIdInternal, CalculatorStateInternal)> = vec![];
+        let mut indexes: HashMap<AccountIdLight, usize> = HashMap::new();
+        for update in &updates {
+            match indexes.entry(update.account_id.as_light()) {
+                Entry::Occupied(i) => states[*i.get()].1 = update.data.clone(),
+                Entry::Vacant(e) => {
+                    e.insert(states.len());
+                    states.push((update.account_id, update.data.clone()));
+                }
+            }
+        }
+
+        match self.write().update_calculator_states(&states).await {
+            Ok(()) => {
+                self.queue_counters.batch_handled(updates.len());
+                for (account_id, data) in &states {
+                    self.write_handle
+                        .event_manager()
+                        .send_connected_event(
+                            account_id.as_light(),
+                            EventToClient::CalculatorStateChanged,
+                        )
+                        .await;
+                    self.broadcast_calculator_state(*account_id, data).await;
+                }
+                for update in updates {
+                    let r: Result<(), DatabaseError> = Ok(());
+                    r.send(update.s)
+                }
+            }
+            Err(e) => {
+                error!(
+                    "Calculator state update batch failed, updating one at a time. Error: {e:?}"
+                );
+                for update in updates {
+                    self.handle_calculator_cmd(update.into()).await;
+                }
+            }
+        }
+    }
+
     pub async fn handle_calculator_cmd(&self, cmd: CalculatorWriteCommand) {
         match cmd {
             CalculatorWriteCommand::UpdateCalculatorState {
                 s,
                 account_id,
                 data,
-            } => self.write().update_data(account_id, &data).await.send(s),
+            } => {
+                let r = self.write().update_data(account_id, &data).await;
+                self.send_event_if_ok(&r, account_id, EventToClient::CalculatorStateChanged)
+                    .await;
+                if r.is_ok() {
+                    self.broadcast_calculator_state(account_id, &data).await;
+                }
+                r.send(s)
+            }
+            CalculatorWriteCommand::UpdateCalculatorSettings {
+                s,
+                account_id,
+                settings,
+            } => self
+                .write()
+                .update_data(account_id, &settings)
+                .await
+                .send(s),
+            CalculatorWriteCommand::CreateCalculationSchedule {
+                s,
+                account_id,
+                schedule,
+                max_schedules,
+            } => self
+                .write()
+                .create_calculation_schedule(account_id, schedule, max_schedules)
+                .await
+                .send(s),
+            CalculatorWriteCommand::UpdateCalculationSchedule {
+                s,
+                account_id,
+                schedule_id,
+                schedule,
+            } => self
+                .write()
+                .update_calculation_schedule(account_id, schedule_id, schedule)
+                .await
+                .send(s),
+            CalculatorWriteCommand::DeleteCalculationSchedule {
+                s,
+                account_id,
+                schedule_id,
+            } => self
+                .write()
+                .delete_calculation_schedule(account_id, schedule_id)
+                .await
+                .send(s),
+            CalculatorWriteCommand::PutCalculatorSlot {
+                s,
+                account_id,
+                slot_name,
+                state,
+                max_slots,
+            } => self
+                .write()
+                .put_calculator_slot(account_id, slot_name, state, max_slots)
+                .await
+                .send(s),
+            CalculatorWriteCommand::DeleteCalculatorSlot {
+                s,
+                account_id,
+                slot_name,
+            } => self
+                .write()
+                .delete_calculator_slot(account_id, slot_name)
+                .await
+                .send(s),
+            CalculatorWriteCommand::CompleteScheduledCalculation {
+                s,
+                account_id,
+                schedule,
+                entry,
+            } => {
+                let r = self
+                    .write()
+                    .complete_scheduled_calculation(account_id, schedule, entry)
+                    .await;
+                self.send_event_if_ok(&r, account_id, EventToClient::ScheduledCalculationCompleted)
+                    .await;
+                r.send(s)
+            }
+            CalculatorWriteCommand::InsertHistoryEntry {
+                s,
+                account_id,
+                entry,
+            } => self
+                .write()
+                .insert_history_entry(account_id, entry)
+                .await
+                .send(s),
+            CalculatorWriteCommand::ShareCalculatorState {
+                s,
+                account_id,
+                reader,
+            } => self
+                .write()
+                .share_calculator_state(account_id, reader)
+                .await
+                .send(s),
+            CalculatorWriteCommand::RemoveCalculatorStateShare {
+                s,
+                account_id,
+                reader,
+            } => self
+                .write()
+                .remove_calculator_state_share(account_id, reader)
+                .await
+                .send(s),
+            CalculatorWriteCommand::SetCalculatorBroadcast {
+                s,
+                account_id,
+                org_id,
+            } => self
+                .write()
+                .set_calculator_broadcast(account_id, org_id)
+                .await
+                .send(s),
+            CalculatorWriteCommand::DeleteExpiredHistoryEntries {
+                s,
+                default_retention_days,
+            } => self
+                .write()
+                .delete_expired_history_entries(self.config.unix_time(), default_retention_days)
+                .await
+                .send(s),
+        }
+    }
+
+    /// Send the calculator state to the organization members if the account
+    /// broadcasts its state. Broadcast errors do not fail the state update.
+    async fn broadcast_calculator_state(
+        &self,
+        account_id: AccountIdInternal,
+        data: &CalculatorStateInternal,
+    ) {
+        let recipients = match self
+            .write()
+            .calculator_broadcast_recipients(
+                account_id,
+                self.config.calculator_broadcast_min_interval(),
+            )
+            .await
+        {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                error!("Calculator state broadcast failed. Error: {e:?}");
+                return;
+            }
+        };
+
+        for recipient in recipients {
+            self.write_handle
+                .event_manager()
+                .send_connected_event(
+                    recipient,
+                    EventToClient::CalculatorStateBroadcast(CalculatorStateBroadcast {
+                        account_id: account_id.as_light(),
+                        state: data.state.clone(),
+                    }),
+                )
+                .await;
         }
     }
 }
@@ -0,0 +1,276 @@
+//! Sharded write command dispatching
+//!
+//! [WriteCommandDispatcher] receives all synchronized write commands and
+//! sends each command to a shard runner selected with the hash of the
+//! account of the command. Commands of one account always go to the same
+//! shard, so those are run in the order they were sent. Commands which are
+//! not related to a single account are run by the global runner when no
+//! shard runner is running commands.
+//!
+//! Dispatcher does not wait for free space in the queue of a runner, so one
+//! busy shard does not delay commands of other shards. Commands to a full
+//! queue fail with [DatabaseError::WriteQueueBusy].
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    sync::{atomic::Ordering, Arc},
+};
+
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        Notify, RwLock,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    api::model::AccountIdLight, config::Config, server::database::RouterDatabaseWriteHandle,
+};
+
+use super::{QueuedWriteCommand, WriteCommandRunner, WriteCommandShard, WriteQueueCounters};
+
+pub struct WriteCommandDispatcher {
+    receiver: mpsc::Receiver<QueuedWriteCommand>,
+    shards: Vec<mpsc::Sender<QueuedWriteCommand>>,
+    global: mpsc::Sender<QueuedWriteCommand>,
+    runner_handles: Vec<JoinHandle<()>>,
+    account_locks: AccountWriteLockManager,
+}
+
+impl WriteCommandDispatcher {
+    /// Starts the shard runners and the global runner.
+    pub(super) fn new(
+        receiver: mpsc::Receiver<QueuedWriteCommand>,
+        queue_counters: Arc<WriteQueueCounters>,
+        write_handle: RouterDatabaseWriteHandle,
+        config: Arc<Config>,
+    ) -> Self {
+        let account_locks = AccountWriteLockManager::default();
+        let global_lock = Arc::new(RwLock::new(()));
+        let mut runner_handles = vec![];
+
+        let mut start_runner = |shard| {
+            let (sender, receiver) = mpsc::channel(config.limits().write_command_buffer);
+            let runner = WriteCommandRunner {
+                receiver,
+                queue_counters: queue_counters.clone(),
+                write_handle: write_handle.clone(),
+                config: config.clone(),
+                shard,
+                account_locks: account_locks.clone(),
+                global_lock: global_lock.clone(),
+            };
+            runner_handles.push(tokio::spawn(runner.run()));
+            sender
+        };
+
+        let shards = (0..config.limits().write_command_shards)
+            .map(|i| start_runner(WriteCommandShard::Account(i)))
+            .collect();
+        let global = start_runner(WriteCommandShard::Global);
+
+        Self {
+            receiver,
+            shards,
+            global,
+            runner_handles,
+            account_locks,
+        }
+    }
+
+    /// Account locks which the shard runners use. Concurrent write commands
+    /// use the same locks, so those do not run at the same time with
+    /// synchronized commands of the same account.
+    pub fn account_locks(&self) -> AccountWriteLockManager {
+        self.account_locks.clone()
+    }
+
+    /// Returns handles of the dispatcher and the runners.
+    pub fn spawn(mut self) -> (JoinHandle<()>, Vec<JoinHandle<()>>) {
+        let runner_handles = std::mem::take(&mut self.runner_handles);
+        (tokio::spawn(self.run()), runner_handles)
+    }
+
+    /// Runs until web server part of the server quits. Runners quit when
+    /// the dispatcher quits.
+    async fn run(mut self) {
+        loop {
+            match self.receiver.recv().await {
+                Some(cmd) => {
+                    let sender = match cmd.cmd.shard_account() {
+                        Some(account) => &self.shards[shard_index(account, self.shards.len())],
+                        None => &self.global,
+                    };
+                    match sender.try_send(cmd) {
+                        Ok(()) => (),
+                        Err(TrySendError::Full(cmd)) => {
+                            tracing::warn!(
+                                command_id = %cmd.id,
+                                command = cmd.cmd.name(),
+                                "Write command runner queue is full"
+                            );
+                            // The result sender of the command is dropped,
+                            // so the command fails with the busy error.
+                            cmd.busy.store(true, Ordering::Relaxed);
+                        }
+                        Err(TrySendError::Closed(_)) => {
+                            // The result sender of the command is dropped, so
+                            // the command fails.
+                            tracing::error!("Write command runner is closed");
+                        }
+                    }
+                }
+                None => {
+                    tracing::info!("Write command dispatcher closed");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn shard_index(account: AccountIdLight, shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    account.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+/// Accounts which have a write command running.
+#[derive(Default, Clone)]
+pub struct AccountWriteLockManager {
+    locks: Arc<RwLock<HashSet<AccountIdLight>>>,
+    released: Arc<Notify>,
+}
+
+#[must_use]
+pub struct AccountWriteLockHandle {
+    locks: Arc<RwLock<HashSet<AccountIdLight>>>,
+    released: Arc<Notify>,
+    account: AccountIdLight,
+}
+
+impl AccountWriteLockManager {
+    /// Returns None if the account already has a running command.
+    #[must_use]
+    pub async fn set_as_running(&self, a: AccountIdLight) -> Option<AccountWriteLockHandle> {
+        if self.locks.write().await.insert(a) {
+            Some(AccountWriteLockHandle {
+                locks: self.locks.clone(),
+                released: self.released.clone(),
+                account: a,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Waits until the running command of the account is released.
+    pub async fn wait_and_set_as_running(&self, a: AccountIdLight) -> AccountWriteLockHandle {
+        loop {
+            // Create the future before checking the lock, so that a release
+            // between the check and waiting is not missed.
+            let released = self.released.notified();
+            match self.set_as_running(a).await {
+                Some(l) => return l,
+                None => released.await,
+            }
+        }
+    }
+}
+
+impl AccountWriteLockHandle {
+    pub async fn release(self) {
+        self.locks.write().await.remove(&self.account);
+        self.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::AtomicBool,
+        time::{Duration, Instant},
+    };
+
+    use error_stack::Result;
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::server::database::{
+        commands::{WriteCommand, WriteCommandId},
+        DatabaseError,
+    };
+
+    fn queued_command(
+        id: u64,
+    ) -> (
+        QueuedWriteCommand,
+        oneshot::Receiver<Result<u64, DatabaseError>>,
+    ) {
+        let (s, receiver) = oneshot::channel();
+        let cmd = QueuedWriteCommand {
+            id: WriteCommandId(id),
+            queued: Instant::now(),
+            cmd: WriteCommand::MigrateJson { s },
+            busy: Arc::new(AtomicBool::new(false)),
+        };
+        (cmd, receiver)
+    }
+
+    #[tokio::test]
+    async fn command_to_full_runner_queue_fails_as_busy() {
+        let (sender, receiver) = mpsc::channel(2);
+        let (global, mut global_receiver) = mpsc::channel(1);
+        let dispatcher = WriteCommandDispatcher {
+            receiver,
+            shards: vec![],
+            global,
+            runner_handles: vec![],
+            account_locks: AccountWriteLockManager::default(),
+        };
+
+        let (first, _first_result) = queued_command(0);
+        let (second, second_result) = queued_command(1);
+        let second_busy = second.busy.clone();
+        sender.send(first).await.unwrap();
+        sender.send(second).await.unwrap();
+        drop(sender);
+        tokio::time::timeout(Duration::from_secs(1), dispatcher.run())
+            .await
+            .unwrap();
+
+        assert!(second_busy.load(Ordering::Relaxed));
+        assert!(second_result.await.is_err());
+        assert_eq!(global_receiver.recv().await.unwrap().id, WriteCommandId(0));
+    }
+
+    #[test]
+    fn shard_index_is_same_for_same_account() {
+        let account = AccountIdLight::new(uuid::Uuid::new_v4());
+        let index = shard_index(account, 4);
+        assert!(index < 4);
+        assert_eq!(index, shard_index(account, 4));
+    }
+
+    #[tokio::test]
+    async fn waiting_lock_is_acquired_after_release() {
+        let locks = AccountWriteLockManager::default();
+        let account = AccountIdLight::new(uuid::Uuid::new_v4());
+        let lock = locks.set_as_running(account).await.unwrap();
+
+        let waiting = tokio::spawn({
+            let locks = locks.clone();
+            async move { locks.wait_and_set_as_running(account).await.release().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished());
+
+        lock.release().await;
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
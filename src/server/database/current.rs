@@ -1,14 +1,40 @@
 pub mod account;
 pub mod calculator;
+pub mod traits;
+
+use async_trait::async_trait;
+use error_stack::Result;
+use sqlx::{Postgres, Sqlite, Transaction};
 
 use self::account::read::CurrentReadAccountCommands;
-use self::account::write::CurrentWriteAccountCommands;
+use self::account::write::{CurrentWriteAccountCommands, TransactionWriteAccountCommands};
 use self::calculator::read::CurrentReadCalculatorCommands;
-use self::calculator::write::CurrentWriteCalculatorCommands;
+use self::calculator::write::{CurrentWriteCalculatorCommands, TransactionWriteCalculatorCommands};
+use self::traits::{
+    ReadAccountStorage, ReadCalculatorStorage, TransactionWriteAccountStorage,
+    TransactionWriteCalculatorStorage, WriteAccountStorage, WriteCalculatorStorage,
+};
 
-use super::sqlite::CurrentDataWriteHandle;
+use super::postgres::{
+    account::{
+        PostgresReadAccountCommands, PostgresTransactionWriteAccountCommands,
+        PostgresWriteAccountCommands,
+    },
+    calculator::{
+        PostgresReadCalculatorCommands, PostgresTransactionWriteCalculatorCommands,
+        PostgresWriteCalculatorCommands,
+    },
+    PostgresCloseHandle, PostgresHandle,
+};
+use super::read::ReadResult;
+use super::sqlite::{
+    SqliteDatabaseError, SqliteReadCloseHandle, SqliteReadHandle, SqliteWriteCloseHandle,
+    SqliteWriteHandle,
+};
+use super::write::WriteResult;
 
-use crate::server::database::sqlite::SqliteReadHandle;
+use crate::api::model::AccountIdInternal;
+use crate::utils::IntoReportExt;
 
 #[macro_export]
 macro_rules! read_json {
@@ -19,41 +45,203 @@ macro_rules! read_json {
             .await
             .into_error(SqliteDatabaseError::Execute)
             .and_then(|data| {
-                serde_json::from_str(&data.$str_field)
-                    .into_error(SqliteDatabaseError::SerdeDeserialize)
+                $crate::server::database::json_migration::from_versioned_json(&data.$str_field)
             })
     }};
 }
 
 #[macro_export]
 macro_rules! insert_or_update_json {
-    ($self:expr, $sql:literal, $data:expr, $id:expr) => {{
+    (executor = $executor:expr, $sql:literal, $data:expr, $id:expr) => {{
         let id = $id.row_id();
-        let data = serde_json::to_string($data).into_error(SqliteDatabaseError::SerdeSerialize)?;
+        let data = $crate::server::database::json_migration::to_versioned_json($data)?;
         sqlx::query!($sql, data, id)
-            .execute($self.handle.pool())
+            .execute($executor)
             .await
             .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }};
+    ($self:expr, $sql:literal, $data:expr, $id:expr) => {{
+        $crate::insert_or_update_json!(executor = $self.handle.pool(), $sql, $data, $id)
+    }};
+}
+
+/// Read handle for the current data database of the configured backend.
+#[derive(Debug, Clone)]
+pub enum CurrentDataReadHandle {
+    Sqlite(SqliteReadHandle),
+    Postgres(PostgresHandle),
+}
+
+/// Call [Self::close] before closing the server.
+pub enum CurrentDataCloseHandle {
+    Sqlite {
+        write: SqliteWriteCloseHandle,
+        read: SqliteReadCloseHandle,
+    },
+    Postgres(PostgresCloseHandle),
+}
+
+impl CurrentDataCloseHandle {
+    pub async fn close(self) {
+        match self {
+            Self::Sqlite { write, read } => {
+                read.close().await;
+                write.close().await;
+            }
+            Self::Postgres(handle) => handle.close().await,
+        }
+    }
 }
 
-pub struct SqliteReadCommands<'a> {
-    handle: &'a SqliteReadHandle,
+pub struct CurrentReadCommands<'a> {
+    handle: &'a CurrentDataReadHandle,
 }
 
-impl<'a> SqliteReadCommands<'a> {
-    pub fn new(handle: &'a SqliteReadHandle) -> Self {
+impl<'a> CurrentReadCommands<'a> {
+    pub fn new(handle: &'a CurrentDataReadHandle) -> Self {
         Self { handle }
     }
 
-    pub fn account(&self) -> CurrentReadAccountCommands<'_> {
-        CurrentReadAccountCommands::new(self.handle)
+    pub fn account(&self) -> Box<dyn ReadAccountStorage + 'a> {
+        match self.handle {
+            CurrentDataReadHandle::Sqlite(handle) => {
+                Box::new(CurrentReadAccountCommands::new(handle))
+            }
+            CurrentDataReadHandle::Postgres(handle) => {
+                Box::new(PostgresReadAccountCommands::new(handle))
+            }
+        }
+    }
+
+    pub fn calculator(&self) -> Box<dyn ReadCalculatorStorage + 'a> {
+        match self.handle {
+            CurrentDataReadHandle::Sqlite(handle) => {
+                Box::new(CurrentReadCalculatorCommands::new(handle))
+            }
+            CurrentDataReadHandle::Postgres(handle) => {
+                Box::new(PostgresReadCalculatorCommands::new(handle))
+            }
+        }
+    }
+
+    /// Run a query which does not read any table to check that the read
+    /// pool responds.
+    pub async fn check_connection(&self) -> ReadResult<(), SqliteDatabaseError> {
+        match self.handle {
+            CurrentDataReadHandle::Sqlite(handle) => sqlx::query("SELECT 1")
+                .execute(handle.pool())
+                .await
+                .map(|_| ()),
+            CurrentDataReadHandle::Postgres(handle) => sqlx::query("SELECT 1")
+                .execute(handle.pool())
+                .await
+                .map(|_| ()),
+        }
+        .into_error(SqliteDatabaseError::Fetch)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CurrentDataWriteBackend {
+    Sqlite(SqliteWriteHandle),
+    Postgres(PostgresHandle),
+}
+
+#[derive(Debug, Clone)]
+pub struct CurrentDataWriteHandle {
+    backend: CurrentDataWriteBackend,
+    read_handle: CurrentDataReadHandle,
+}
+
+impl CurrentDataWriteHandle {
+    pub fn sqlite(handle: SqliteWriteHandle) -> Self {
+        Self {
+            read_handle: CurrentDataReadHandle::Sqlite(handle.read_handle()),
+            backend: CurrentDataWriteBackend::Sqlite(handle),
+        }
+    }
+
+    pub fn postgres(handle: PostgresHandle) -> Self {
+        Self {
+            read_handle: CurrentDataReadHandle::Postgres(handle.clone()),
+            backend: CurrentDataWriteBackend::Postgres(handle),
+        }
+    }
+
+    pub fn read(&self) -> CurrentReadCommands<'_> {
+        CurrentReadCommands::new(&self.read_handle)
+    }
+
+    /// Start a transaction. The SQLite write pool has only one connection,
+    /// so reading with [Self::read] before the transaction is committed or
+    /// rollbacked waits forever.
+    pub async fn begin(&self) -> WriteResult<CurrentDataTransaction, SqliteDatabaseError> {
+        let transaction = match &self.backend {
+            CurrentDataWriteBackend::Sqlite(handle) => handle
+                .pool()
+                .begin()
+                .await
+                .map(CurrentDataTransaction::Sqlite),
+            CurrentDataWriteBackend::Postgres(handle) => handle
+                .pool()
+                .begin()
+                .await
+                .map(CurrentDataTransaction::Postgres),
+        }
+        .into_error(SqliteDatabaseError::TransactionBegin)?;
+        Ok(transaction)
+    }
+}
+
+/// Write transaction for the current data database. Transaction is
+/// rollbacked if it is dropped without calling [Self::commit].
+pub enum CurrentDataTransaction {
+    Sqlite(Transaction<'static, Sqlite>),
+    Postgres(Transaction<'static, Postgres>),
+}
+
+impl CurrentDataTransaction {
+    pub fn account(&mut self) -> Box<dyn TransactionWriteAccountStorage + '_> {
+        match self {
+            Self::Sqlite(transaction) => {
+                Box::new(TransactionWriteAccountCommands::new(transaction))
+            }
+            Self::Postgres(transaction) => {
+                Box::new(PostgresTransactionWriteAccountCommands::new(transaction))
+            }
+        }
+    }
+
+    pub fn calculator(&mut self) -> Box<dyn TransactionWriteCalculatorStorage + '_> {
+        match self {
+            Self::Sqlite(transaction) => {
+                Box::new(TransactionWriteCalculatorCommands::new(transaction))
+            }
+            Self::Postgres(transaction) => {
+                Box::new(PostgresTransactionWriteCalculatorCommands::new(transaction))
+            }
+        }
     }
 
-    pub fn calculator(&self) -> CurrentReadCalculatorCommands<'_> {
-        CurrentReadCalculatorCommands::new(self.handle)
+    pub async fn commit(self) -> WriteResult<(), SqliteDatabaseError, CurrentDataTransaction> {
+        match self {
+            Self::Sqlite(transaction) => transaction.commit().await,
+            Self::Postgres(transaction) => transaction.commit().await,
+        }
+        .into_error(SqliteDatabaseError::TransactionCommit)?;
+        Ok(())
+    }
+
+    pub async fn rollback(self) -> WriteResult<(), SqliteDatabaseError, CurrentDataTransaction> {
+        match self {
+            Self::Sqlite(transaction) => transaction.rollback().await,
+            Self::Postgres(transaction) => transaction.rollback().await,
+        }
+        .into_error(SqliteDatabaseError::TransactionRollback)?;
+        Ok(())
     }
 }
 
@@ -67,11 +255,44 @@ impl<'a> CurrentDataWriteCommands<'a> {
         Self { handle }
     }
 
-    pub fn account(self) -> CurrentWriteAccountCommands<'a> {
-        CurrentWriteAccountCommands::new(self.handle)
+    pub fn account(&self) -> Box<dyn WriteAccountStorage + 'a> {
+        let write_handle: &'a CurrentDataWriteHandle = self.handle;
+        match &write_handle.backend {
+            CurrentDataWriteBackend::Sqlite(handle) => {
+                Box::new(CurrentWriteAccountCommands::new(handle))
+            }
+            CurrentDataWriteBackend::Postgres(handle) => {
+                Box::new(PostgresWriteAccountCommands::new(handle))
+            }
+        }
     }
 
-    pub fn calculator(self) -> CurrentWriteCalculatorCommands<'a> {
-        CurrentWriteCalculatorCommands::new(self.handle)
+    pub fn calculator(&self) -> Box<dyn WriteCalculatorStorage + 'a> {
+        let write_handle: &'a CurrentDataWriteHandle = self.handle;
+        match &write_handle.backend {
+            CurrentDataWriteBackend::Sqlite(handle) => {
+                Box::new(CurrentWriteCalculatorCommands::new(handle))
+            }
+            CurrentDataWriteBackend::Postgres(handle) => {
+                Box::new(PostgresWriteCalculatorCommands::new(handle))
+            }
+        }
     }
 }
+
+#[async_trait]
+pub trait UpdateJson {
+    async fn update_json(
+        &self,
+        id: AccountIdInternal,
+        write: &CurrentDataWriteCommands,
+    ) -> Result<(), SqliteDatabaseError>;
+}
+
+#[async_trait]
+pub trait SelectJson: Sized {
+    async fn select_json(
+        id: AccountIdInternal,
+        read: &CurrentReadCommands,
+    ) -> Result<Self, SqliteDatabaseError>;
+}
@@ -1,10 +1,11 @@
 use async_trait::async_trait;
 use error_stack::Result;
-use futures::Stream;
+use futures::stream::BoxStream;
 
 use crate::api::account::data::AccountSetup;
-use crate::server::database::current::SqliteReadCommands;
-use crate::server::database::sqlite::{SqliteDatabaseError, SqliteSelectJson};
+use crate::server::database::current::traits::ReadAccountStorage;
+use crate::server::database::current::{CurrentReadCommands, SelectJson};
+use crate::server::database::sqlite::SqliteDatabaseError;
 
 use crate::api::model::*;
 
@@ -20,7 +21,12 @@ use crate::api::model::AccountIdInternal;
 use crate::server::database::read::ReadResult;
 
 use crate::read_json;
+use crate::server::database::json_migration::from_versioned_json;
 
+const ACTIVITY_PERIOD_DAY_SECONDS: i64 = 24 * 60 * 60;
+const ACTIVITY_PERIOD_WEEK_SECONDS: i64 = 7 * ACTIVITY_PERIOD_DAY_SECONDS;
+
+/// SQLite implementation of [ReadAccountStorage].
 pub struct CurrentReadAccountCommands<'a> {
     handle: &'a SqliteReadHandle,
 }
@@ -29,14 +35,17 @@ impl<'a> CurrentReadAccountCommands<'a> {
     pub fn new(handle: &'a SqliteReadHandle) -> Self {
         Self { handle }
     }
+}
 
-    pub fn account_ids_stream(
+#[async_trait]
+impl ReadAccountStorage for CurrentReadAccountCommands<'_> {
+    fn account_ids_stream(
         &self,
-    ) -> impl Stream<Item = ReadResult<AccountIdInternal, SqliteDatabaseError, NoId>> + '_ {
-        sqlx::query_as!(
+    ) -> BoxStream<'_, ReadResult<AccountIdInternal, SqliteDatabaseError, NoId>> {
+        let stream = sqlx::query_as!(
             AccountIdInternal,
             r#"
-            SELECT account_row_id, account_id as "account_id: _"
+            SELECT account_row_id as "account_row_id!", account_id as "account_id: _"
             FROM AccountId
             "#,
         )
@@ -45,17 +54,102 @@ impl<'a> CurrentReadAccountCommands<'a> {
             result
                 .into_error(SqliteDatabaseError::Fetch)
                 .map_err(|e| e.into())
-        })
+        });
+        Box::pin(stream)
+    }
+
+    async fn account_ids_page(
+        &self,
+        offset: i64,
+        limit: i64,
+        region: Option<&str>,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId> {
+        sqlx::query_as!(
+            AccountIdInternal,
+            r#"
+            SELECT account_row_id as "account_row_id!", account_id as "account_id: _"
+            FROM AccountId
+            WHERE ? IS NULL OR region = ?
+            ORDER BY account_row_id
+            LIMIT ? OFFSET ?
+            "#,
+            region,
+            region,
+            limit,
+            offset,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_times(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<AccountTimes, SqliteDatabaseError, AccountIdInternal> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            AccountTimes,
+            r#"
+            SELECT created_unix_time, last_login_unix_time
+            FROM AccountId
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_region(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<String>, SqliteDatabaseError, AccountIdInternal> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT region
+            FROM AccountId
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|r| r.region)
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_regions(
+        &self,
+    ) -> ReadResult<Vec<RegionRegistrations>, SqliteDatabaseError, NoId> {
+        sqlx::query_as!(
+            RegionRegistrations,
+            r#"
+            SELECT region, COUNT(*) as "registrations!: i64"
+            FROM AccountId
+            GROUP BY region
+            ORDER BY region
+            "#,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
     }
 
-    pub async fn access_token(
+    async fn access_token_hash(
         &self,
         id: AccountIdInternal,
-    ) -> ReadResult<Option<ApiKey>, SqliteDatabaseError, ApiKey> {
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, ApiKey> {
         let id = id.row_id();
         sqlx::query!(
             r#"
-            SELECT api_key
+            SELECT api_key_hash
             FROM ApiKey
             WHERE account_row_id = ?
             "#,
@@ -63,19 +157,61 @@ impl<'a> CurrentReadAccountCommands<'a> {
         )
         .fetch_one(self.handle.pool())
         .await
-        .map(|result| result.api_key.map(ApiKey::new))
+        .map(|result| result.api_key_hash.map(TokenHash::from_bytes))
         .into_error(SqliteDatabaseError::Fetch)
         .map_err(|e| e.into())
     }
 
-    pub async fn refresh_token(
+    async fn access_token_expiration(
         &self,
         id: AccountIdInternal,
-    ) -> ReadResult<Option<RefreshToken>, SqliteDatabaseError, RefreshToken> {
+    ) -> ReadResult<Option<i64>, SqliteDatabaseError, ApiKey> {
         let id = id.row_id();
         sqlx::query!(
             r#"
-            SELECT refresh_token
+            SELECT expires_unix_time
+            FROM ApiKey
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|result| result.expires_unix_time)
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_with_refresh_token(
+        &self,
+        token: &RefreshToken,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError, RefreshToken> {
+        let hash = token.hash();
+        let hash = hash.as_bytes();
+        sqlx::query_as!(
+            AccountIdInternal,
+            r#"
+            SELECT AccountId.account_row_id, account_id as "account_id: _"
+            FROM RefreshToken
+            INNER JOIN AccountId ON RefreshToken.account_row_id = AccountId.account_row_id
+            WHERE refresh_token_hash = ?
+            "#,
+            hash
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn refresh_token_hash(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, RefreshToken> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT refresh_token_hash
             FROM RefreshToken
             WHERE account_row_id = ?
             "#,
@@ -83,17 +219,339 @@ impl<'a> CurrentReadAccountCommands<'a> {
         )
         .fetch_one(self.handle.pool())
         .await
+        .map(|result| result.refresh_token_hash.map(TokenHash::from_bytes))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn used_refresh_token_generation(
+        &self,
+        id: AccountIdInternal,
+        token: &RefreshToken,
+    ) -> ReadResult<Option<i64>, SqliteDatabaseError, RefreshToken> {
+        let hash = token.hash();
+        let hash = hash.as_bytes();
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT generation
+            FROM UsedRefreshToken
+            WHERE account_row_id = ? AND refresh_token_hash = ?
+            "#,
+            id,
+            hash
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|result| result.map(|r| r.generation))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn service_tokens(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<ServiceTokenInfo>, SqliteDatabaseError, ServiceTokenInfo> {
+        let id = id.row_id();
+        let rows = sqlx::query!(
+            r#"
+            SELECT token_id, name, scope, created_unix_time
+            FROM ServiceToken
+            WHERE account_row_id = ?
+            ORDER BY token_id
+            "#,
+            id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let mut tokens = Vec::with_capacity(rows.len());
+        for r in rows {
+            tokens.push(ServiceTokenInfo {
+                token_id: r.token_id,
+                name: r.name,
+                scope: parse_service_token_scope(&r.scope)?,
+                created_unix_time: r.created_unix_time,
+            });
+        }
+        Ok(tokens)
+    }
+
+    async fn service_token_hashes(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<(TokenHash, ServiceTokenScope)>, SqliteDatabaseError, ApiKey> {
+        let id = id.row_id();
+        let rows = sqlx::query!(
+            r#"
+            SELECT token_hash, scope
+            FROM ServiceToken
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let mut tokens = Vec::with_capacity(rows.len());
+        for r in rows {
+            tokens.push((
+                TokenHash::from_bytes(r.token_hash),
+                parse_service_token_scope(&r.scope)?,
+            ));
+        }
+        Ok(tokens)
+    }
+
+    async fn service_token_hash(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, ApiKey> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT token_hash
+            FROM ServiceToken
+            WHERE token_id = ? AND account_row_id = ?
+            "#,
+            token_id.token_id,
+            id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|result| result.map(|r| TokenHash::from_bytes(r.token_hash)))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn service_token_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, ServiceTokenInfo> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM ServiceToken
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|result| result.count)
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn login_history(
+        &self,
+        id: AccountIdInternal,
+        limit: i64,
+    ) -> ReadResult<Vec<LoginHistoryEntry>, SqliteDatabaseError, LoginHistoryEntry> {
+        let id = id.row_id();
+        let rows = sqlx::query!(
+            r#"
+            SELECT unix_time, ip_address, method
+            FROM LoginHistory
+            WHERE account_row_id = ?
+            ORDER BY login_history_id DESC
+            LIMIT ?
+            "#,
+            id,
+            limit,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for r in rows {
+            entries.push(LoginHistoryEntry {
+                unix_time: r.unix_time,
+                ip_address: r.ip_address,
+                method: parse_login_method(&r.method)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    async fn organization(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Option<Organization>, SqliteDatabaseError, Organization> {
+        sqlx::query_as!(
+            Organization,
+            r#"
+            SELECT org_id, name, created_unix_time
+            FROM Organization
+            WHERE org_id = ?
+            "#,
+            org_id.org_id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_membership(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<OrganizationMembership>, SqliteDatabaseError, Organization> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT
+                Organization.org_id,
+                Organization.name,
+                Organization.created_unix_time,
+                OrganizationMember.is_admin as "is_admin: bool"
+            FROM OrganizationMember
+            INNER JOIN Organization ON Organization.org_id = OrganizationMember.org_id
+            WHERE OrganizationMember.account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
         .map(|result| {
-            result
-                .refresh_token
-                .as_deref()
-                .map(RefreshToken::from_bytes)
+            result.map(|r| OrganizationMembership {
+                organization: Organization {
+                    org_id: r.org_id,
+                    name: r.name,
+                    created_unix_time: r.created_unix_time,
+                },
+                is_admin: r.is_admin,
+            })
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_members(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Vec<OrganizationMember>, SqliteDatabaseError, Organization> {
+        sqlx::query!(
+            r#"
+            SELECT
+                AccountId.account_id as "account_id: uuid::Uuid",
+                OrganizationMember.is_admin as "is_admin: bool",
+                OrganizationMember.joined_unix_time,
+                AccountActivity.last_activity_unix_time as "last_activity_unix_time?: i64",
+                (
+                    SELECT COUNT(*)
+                    FROM CalculatorHistory
+                    WHERE CalculatorHistory.account_row_id = OrganizationMember.account_row_id
+                ) as "history_entries!: i64"
+            FROM OrganizationMember
+            INNER JOIN AccountId ON AccountId.account_row_id = OrganizationMember.account_row_id
+            LEFT JOIN AccountActivity
+                ON AccountActivity.account_row_id = OrganizationMember.account_row_id
+            WHERE OrganizationMember.org_id = ?
+            ORDER BY OrganizationMember.joined_unix_time, OrganizationMember.account_row_id
+            "#,
+            org_id.org_id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|r| OrganizationMember {
+                    account_id: AccountIdLight::new(r.account_id),
+                    is_admin: r.is_admin,
+                    joined_unix_time: r.joined_unix_time,
+                    last_activity_unix_time: r.last_activity_unix_time,
+                    history_entries: r.history_entries,
+                })
+                .collect()
         })
         .into_error(SqliteDatabaseError::Fetch)
         .map_err(|e| e.into())
     }
 
-    pub async fn sign_in_with_info(
+    async fn organization_member_ids(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Vec<AccountIdLight>, SqliteDatabaseError, Organization> {
+        sqlx::query_as!(
+            AccountIdLight,
+            r#"
+            SELECT AccountId.account_id as "account_id: _"
+            FROM OrganizationMember
+            INNER JOIN AccountId ON AccountId.account_row_id = OrganizationMember.account_row_id
+            WHERE OrganizationMember.org_id = ?
+            "#,
+            org_id.org_id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_settings(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Option<OrganizationSettings>, SqliteDatabaseError, Organization> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                OrganizationSettings.history_retention_days,
+                OrganizationSettings.operation_sets
+            FROM Organization
+            LEFT JOIN OrganizationSettings ON OrganizationSettings.org_id = Organization.org_id
+            WHERE Organization.org_id = ?
+            "#,
+            org_id.org_id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        match row {
+            Some(r) => Ok(Some(organization_settings(
+                r.history_retention_days,
+                r.operation_sets,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn account_organization_settings(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<OrganizationSettings, SqliteDatabaseError, Organization> {
+        let id = id.row_id();
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                OrganizationSettings.history_retention_days,
+                OrganizationSettings.operation_sets
+            FROM OrganizationMember
+            INNER JOIN OrganizationSettings ON OrganizationSettings.org_id = OrganizationMember.org_id
+            WHERE OrganizationMember.account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        match row {
+            Some(r) => Ok(organization_settings(
+                r.history_retention_days,
+                r.operation_sets,
+            )?),
+            None => Ok(OrganizationSettings::default()),
+        }
+    }
+
+    async fn sign_in_with_info(
         &self,
         id: AccountIdInternal,
     ) -> ReadResult<SignInWithInfo, SqliteDatabaseError> {
@@ -101,7 +559,9 @@ impl<'a> CurrentReadAccountCommands<'a> {
         sqlx::query_as!(
             SignInWithInfo,
             r#"
-            SELECT google_account_id as "google_account_id: _"
+            SELECT
+                google_account_id as "google_account_id: _",
+                apple_account_id as "apple_account_id: _"
             FROM SignInWithInfo
             WHERE account_row_id = ?
             "#,
@@ -113,7 +573,61 @@ impl<'a> CurrentReadAccountCommands<'a> {
         .map_err(|e| e.into())
     }
 
-    pub async fn get_account_with_google_account_id(
+    async fn account_data(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<AccountDataInternal, SqliteDatabaseError, AccountDataInternal> {
+        let id = id.row_id();
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                Account.json_text as "account_json?",
+                AccountSetup.json_text as "account_setup_json?",
+                SignInWithInfo.account_row_id IS NOT NULL as "has_sign_in_with!: bool",
+                SignInWithInfo.google_account_id as "google_account_id?",
+                SignInWithInfo.apple_account_id as "apple_account_id?",
+                CurrentState.calculation as "calculator_state?"
+            FROM AccountId
+            LEFT JOIN Account ON Account.account_row_id = AccountId.account_row_id
+            LEFT JOIN AccountSetup ON AccountSetup.account_row_id = AccountId.account_row_id
+            LEFT JOIN SignInWithInfo ON SignInWithInfo.account_row_id = AccountId.account_row_id
+            LEFT JOIN CurrentState ON CurrentState.account_row_id = AccountId.account_row_id
+            WHERE AccountId.account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let sign_in_with = if row.has_sign_in_with {
+            Some(SignInWithInfo {
+                google_account_id: row.google_account_id.map(GoogleAccountId),
+                apple_account_id: row.apple_account_id.map(AppleAccountId),
+            })
+        } else {
+            None
+        };
+
+        Ok(AccountDataInternal {
+            account: row
+                .account_json
+                .as_deref()
+                .map(from_versioned_json)
+                .transpose()?,
+            account_setup: row
+                .account_setup_json
+                .as_deref()
+                .map(from_versioned_json)
+                .transpose()?,
+            sign_in_with,
+            calculator_state: row
+                .calculator_state
+                .map(|state| CalculatorStateInternal { state }),
+        })
+    }
+
+    async fn get_account_with_google_account_id(
         &self,
         google_account_id: GoogleAccountId,
     ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError> {
@@ -137,16 +651,239 @@ impl<'a> CurrentReadAccountCommands<'a> {
             })
         })
     }
-}
 
-#[async_trait]
-impl SqliteSelectJson for Account {
-    async fn select_json(
+    async fn get_account_with_apple_account_id(
+        &self,
+        apple_account_id: AppleAccountId,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError> {
+        sqlx::query!(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id as "account_id: uuid::Uuid"
+            FROM SignInWithInfo
+            INNER JOIN AccountId on AccountId.account_row_id = SignInWithInfo.account_row_id
+            WHERE apple_account_id = ?
+            "#,
+            apple_account_id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+        .map(|r| {
+            r.map(|r| AccountIdInternal {
+                account_id: r.account_id,
+                account_row_id: r.account_row_id,
+            })
+        })
+    }
+
+    async fn get_account_with_alias(
+        &self,
+        alias: &AccountAlias,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError> {
+        let alias = alias.as_str();
+        sqlx::query!(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id as "account_id: uuid::Uuid"
+            FROM AccountAlias
+            INNER JOIN AccountId on AccountId.account_row_id = AccountAlias.account_row_id
+            WHERE alias = ?
+            "#,
+            alias
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+        .map(|r| {
+            r.map(|r| AccountIdInternal {
+                account_id: r.account_id,
+                account_row_id: r.account_row_id,
+            })
+        })
+    }
+
+    async fn account_alias(
+        &self,
         id: AccountIdInternal,
-        read: &SqliteReadCommands,
-    ) -> Result<Self, SqliteDatabaseError> {
+    ) -> ReadResult<Option<AccountAlias>, SqliteDatabaseError> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT alias as "alias: AccountAlias"
+            FROM AccountAlias
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|r| r.map(|r| r.alias))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<AccountDeletionInfo>, SqliteDatabaseError> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            AccountDeletionInfo,
+            r#"
+            SELECT requested_unix_time, delete_after_unix_time
+            FROM AccountDeletion
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn expired_account_deletions(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId> {
+        sqlx::query_as!(
+            AccountIdInternal,
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id as "account_id: _"
+            FROM AccountDeletion
+            INNER JOIN AccountId on AccountId.account_row_id = AccountDeletion.account_row_id
+            WHERE delete_after_unix_time <= ?
+            "#,
+            unix_time
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn stale_accounts(
+        &self,
+        inactive_before: i64,
+        after_row_id: i64,
+        limit: i64,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId> {
+        sqlx::query_as!(
+            AccountIdInternal,
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id as "account_id: _"
+            FROM AccountId
+            LEFT JOIN AccountActivity on AccountActivity.account_row_id = AccountId.account_row_id
+            WHERE AccountId.account_row_id > ?
+                AND COALESCE(
+                    AccountActivity.last_activity_unix_time,
+                    AccountId.last_login_unix_time,
+                    AccountId.created_unix_time
+                ) < ?
+                AND NOT EXISTS (
+                    SELECT 1 FROM AccountDeletion
+                    WHERE AccountDeletion.account_row_id = AccountId.account_row_id
+                )
+            ORDER BY AccountId.account_row_id
+            LIMIT ?
+            "#,
+            after_row_id,
+            inactive_before,
+            limit,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_report(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<AccountReport, SqliteDatabaseError, NoId> {
+        let registrations_per_provider: Vec<ProviderRegistrations> = sqlx::query!(
+            r#"
+            SELECT
+                CASE
+                    WHEN SignInWithInfo.google_account_id IS NOT NULL THEN 'google'
+                    WHEN SignInWithInfo.apple_account_id IS NOT NULL THEN 'apple'
+                    ELSE 'none'
+                END as "provider!: String",
+                COUNT(*) as "registrations!: i64"
+            FROM AccountId
+            LEFT JOIN SignInWithInfo
+                ON SignInWithInfo.account_row_id = AccountId.account_row_id
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?
+        .into_iter()
+        .map(|r| ProviderRegistrations {
+            provider: r.provider,
+            registrations: r.registrations,
+        })
+        .collect();
+
+        let setup_completed = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM Account
+            WHERE json_valid(json_text)
+                AND json_extract(json_text, '$.state') != 'InitialSetup'
+            "#,
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?
+        .count;
+
+        let day_start = unix_time - ACTIVITY_PERIOD_DAY_SECONDS;
+        let week_start = unix_time - ACTIVITY_PERIOD_WEEK_SECONDS;
+        let active = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE last_activity_unix_time >= ?) as "daily!: i64",
+                COUNT(*) FILTER (WHERE last_activity_unix_time >= ?) as "weekly!: i64"
+            FROM AccountActivity
+            "#,
+            day_start,
+            week_start,
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let registrations: i64 = registrations_per_provider
+            .iter()
+            .map(|p| p.registrations)
+            .sum();
+        let setup_completion_rate = if registrations == 0 {
+            0.0
+        } else {
+            setup_completed as f64 / registrations as f64
+        };
+
+        let registrations_per_region = self.account_regions().await?;
+
+        Ok(AccountReport {
+            generated_unix_time: unix_time,
+            registrations,
+            registrations_per_provider,
+            registrations_per_region,
+            setup_completed,
+            setup_completion_rate,
+            daily_active_accounts: active.daily,
+            weekly_active_accounts: active.weekly,
+        })
+    }
+
+    async fn account_json(&self, id: AccountIdInternal) -> Result<Account, SqliteDatabaseError> {
         read_json!(
-            read,
+            self,
             id,
             r#"
             SELECT json_text
@@ -156,16 +893,13 @@ impl SqliteSelectJson for Account {
             json_text
         )
     }
-}
 
-#[async_trait]
-impl SqliteSelectJson for AccountSetup {
-    async fn select_json(
+    async fn account_setup_json(
+        &self,
         id: AccountIdInternal,
-        read: &SqliteReadCommands,
-    ) -> Result<Self, SqliteDatabaseError> {
+    ) -> Result<AccountSetup, SqliteDatabaseError> {
         read_json!(
-            read,
+            self,
             id,
             r#"
             SELECT json_text
@@ -175,4 +909,77 @@ impl SqliteSelectJson for AccountSetup {
             json_text
         )
     }
+
+    async fn notification_settings_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<NotificationSettings, SqliteDatabaseError> {
+        read_json!(
+            self,
+            id,
+            r#"
+            SELECT json_text
+            FROM NotificationSettings
+            WHERE account_row_id = ?
+            "#,
+            json_text
+        )
+    }
+}
+
+fn parse_service_token_scope(scope: &str) -> Result<ServiceTokenScope, SqliteDatabaseError> {
+    ServiceTokenScope::parse(scope)
+        .ok_or_else(|| error_stack::Report::new(SqliteDatabaseError::DataFormatConversion))
+}
+
+fn parse_login_method(method: &str) -> Result<LoginMethod, SqliteDatabaseError> {
+    LoginMethod::parse(method)
+        .ok_or_else(|| error_stack::Report::new(SqliteDatabaseError::DataFormatConversion))
+}
+
+/// Operation sets are stored as JSON array.
+fn organization_settings(
+    history_retention_days: Option<i64>,
+    operation_sets: Option<String>,
+) -> Result<OrganizationSettings, SqliteDatabaseError> {
+    let operation_sets = match operation_sets {
+        Some(text) => {
+            Some(serde_json::from_str(&text).into_error(SqliteDatabaseError::SerdeDeserialize)?)
+        }
+        None => None,
+    };
+    Ok(OrganizationSettings {
+        history_retention_days,
+        operation_sets,
+    })
+}
+
+#[async_trait]
+impl SelectJson for Account {
+    async fn select_json(
+        id: AccountIdInternal,
+        read: &CurrentReadCommands,
+    ) -> Result<Self, SqliteDatabaseError> {
+        read.account().account_json(id).await
+    }
+}
+
+#[async_trait]
+impl SelectJson for AccountSetup {
+    async fn select_json(
+        id: AccountIdInternal,
+        read: &CurrentReadCommands,
+    ) -> Result<Self, SqliteDatabaseError> {
+        read.account().account_setup_json(id).await
+    }
+}
+
+#[async_trait]
+impl SelectJson for NotificationSettings {
+    async fn select_json(
+        id: AccountIdInternal,
+        read: &CurrentReadCommands,
+    ) -> Result<Self, SqliteDatabaseError> {
+        read.account().notification_settings_json(id).await
+    }
 }
@@ -1,9 +1,14 @@
 use async_trait::async_trait;
 use error_stack::Result;
+use sqlx::{Sqlite, Transaction};
 
 use crate::api::account::data::AccountSetup;
-use crate::server::database::current::CurrentDataWriteCommands;
-use crate::server::database::sqlite::{SqliteDatabaseError, SqliteUpdateJson};
+use crate::server::database::current::traits::{
+    TransactionWriteAccountStorage, WriteAccountStorage,
+};
+use crate::server::database::current::{CurrentDataWriteCommands, UpdateJson};
+use crate::server::database::sqlite::SqliteDatabaseError;
+use crate::server::database::utils::current_unix_time;
 
 use crate::api::model::*;
 
@@ -13,31 +18,494 @@ use crate::insert_or_update_json;
 
 use crate::{
     api::model::AccountIdInternal,
-    server::database::{sqlite::CurrentDataWriteHandle, write::WriteResult},
+    server::database::{sqlite::SqliteWriteHandle, write::WriteResult},
 };
 
+/// How long replaced refresh tokens are kept for detecting token reuse.
+pub const USED_REFRESH_TOKEN_LIFETIME_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// SQLite implementation of [WriteAccountStorage].
 pub struct CurrentWriteAccountCommands<'a> {
-    handle: &'a CurrentDataWriteHandle,
+    handle: &'a SqliteWriteHandle,
 }
 
 impl<'a> CurrentWriteAccountCommands<'a> {
-    pub fn new(handle: &'a CurrentDataWriteHandle) -> Self {
+    pub fn new(handle: &'a SqliteWriteHandle) -> Self {
         Self { handle }
     }
+}
+
+#[async_trait]
+impl WriteAccountStorage for CurrentWriteAccountCommands<'_> {
+    async fn update_sign_in_with_info(
+        &self,
+        id: AccountIdInternal,
+        sign_in_with: &SignInWithInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            UPDATE SignInWithInfo
+            SET google_account_id = ?, apple_account_id = ?
+            WHERE account_row_id = ?
+            "#,
+            sign_in_with.google_account_id,
+            sign_in_with.apple_account_id,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_account_activity(
+        &self,
+        id: AccountIdInternal,
+        last_activity_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            INSERT INTO AccountActivity (last_activity_unix_time, account_row_id)
+            VALUES (?, ?)
+            ON CONFLICT (account_row_id) DO UPDATE
+            SET last_activity_unix_time = excluded.last_activity_unix_time
+            "#,
+            last_activity_unix_time,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_last_login(
+        &self,
+        id: AccountIdInternal,
+        last_login_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            UPDATE AccountId
+            SET last_login_unix_time = ?
+            WHERE account_row_id = ?
+            "#,
+            last_login_unix_time,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
 
-    pub async fn store_account_id(
+    async fn insert_account_deletion(
         &self,
+        id: AccountIdInternal,
+        info: AccountDeletionInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountDeletionInfo> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            INSERT INTO AccountDeletion (requested_unix_time, delete_after_unix_time, account_row_id)
+            VALUES (?, ?, ?)
+            "#,
+            info.requested_unix_time,
+            info.delete_after_unix_time,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountDeletionInfo> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            DELETE FROM AccountDeletion
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_service_token(
+        &self,
+        id: AccountIdInternal,
+        token: &ApiKey,
+        new: &NewServiceToken,
+        created_unix_time: i64,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewServiceToken> {
+        let id = id.row_id();
+        let token_hash = token.hash().into_bytes();
+        let scope = new.scope.as_str();
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO ServiceToken (account_row_id, token_hash, name, scope, created_unix_time)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            id,
+            token_hash,
+            new.name,
+            scope,
+            created_unix_time,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn delete_service_token(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> WriteResult<bool, SqliteDatabaseError, ServiceTokenInfo> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM ServiceToken
+            WHERE token_id = ? AND account_row_id = ?
+            "#,
+            token_id.token_id,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_login_history(
+        &self,
+        id: AccountIdInternal,
+        method: LoginMethod,
+        ip_address: Option<&str>,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, LoginHistoryEntry> {
+        let id = id.row_id();
+        let method = method.as_str();
+        sqlx::query!(
+            r#"
+            INSERT INTO LoginHistory (account_row_id, unix_time, ip_address, method)
+            VALUES (?, ?, ?, ?)
+            "#,
+            id,
+            unix_time,
+            ip_address,
+            method,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_organization(
+        &self,
+        org: &NewOrganization,
+        created_unix_time: i64,
+    ) -> WriteResult<i64, SqliteDatabaseError, Organization> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO Organization (name, created_unix_time)
+            VALUES (?, ?)
+            "#,
+            org.name,
+            created_unix_time,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn upsert_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+        is_admin: bool,
+        joined_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, OrganizationMember> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            INSERT INTO OrganizationMember (account_row_id, org_id, is_admin, joined_unix_time)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (account_row_id) DO UPDATE
+            SET
+                joined_unix_time = CASE
+                    WHEN org_id = excluded.org_id THEN joined_unix_time
+                    ELSE excluded.joined_unix_time
+                END,
+                org_id = excluded.org_id,
+                is_admin = excluded.is_admin
+            "#,
+            id,
+            org_id.org_id,
+            is_admin,
+            joined_unix_time,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn upsert_organization_settings(
+        &self,
+        org_id: OrganizationId,
+        settings: &OrganizationSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, OrganizationSettings> {
+        // Operation sets are stored as JSON array.
+        let operation_sets = match &settings.operation_sets {
+            Some(sets) => {
+                Some(serde_json::to_string(sets).into_error(SqliteDatabaseError::SerdeSerialize)?)
+            }
+            None => None,
+        };
+        sqlx::query!(
+            r#"
+            INSERT INTO OrganizationSettings (org_id, history_retention_days, operation_sets)
+            VALUES (?, ?, ?)
+            ON CONFLICT (org_id) DO UPDATE
+            SET
+                history_retention_days = excluded.history_retention_days,
+                operation_sets = excluded.operation_sets
+            "#,
+            org_id.org_id,
+            settings.history_retention_days,
+            operation_sets,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+    ) -> WriteResult<bool, SqliteDatabaseError, OrganizationMember> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM OrganizationMember
+            WHERE org_id = ? AND account_row_id = ?
+            "#,
+            org_id.org_id,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_account(
+        &self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal> {
+        let id = id.row_id();
+        let mut transaction = self
+            .handle
+            .pool()
+            .begin()
+            .await
+            .into_error(SqliteDatabaseError::TransactionBegin)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM Account
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM AccountSetup
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM ApiKey
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM RefreshToken
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM SignInWithInfo
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM CurrentState
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM AccountId
+            WHERE account_row_id = ?
+            "#,
+            id,
+        )
+        .execute(&mut transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        transaction
+            .commit()
+            .await
+            .into_error(SqliteDatabaseError::TransactionCommit)?;
+
+        Ok(())
+    }
+
+    async fn update_account_json(
+        &self,
+        id: AccountIdInternal,
+        account: &Account,
+    ) -> Result<(), SqliteDatabaseError> {
+        insert_or_update_json!(
+            self,
+            r#"
+            UPDATE Account
+            SET json_text = ?
+            WHERE account_row_id = ?
+            "#,
+            account,
+            id
+        )
+    }
+
+    async fn update_account_setup_json(
+        &self,
+        id: AccountIdInternal,
+        account_setup: &AccountSetup,
+    ) -> Result<(), SqliteDatabaseError> {
+        insert_or_update_json!(
+            self,
+            r#"
+            UPDATE AccountSetup
+            SET json_text = ?
+            WHERE account_row_id = ?
+            "#,
+            account_setup,
+            id
+        )
+    }
+
+    async fn update_notification_settings_json(
+        &self,
+        id: AccountIdInternal,
+        settings: &NotificationSettings,
+    ) -> Result<(), SqliteDatabaseError> {
+        insert_or_update_json!(
+            self,
+            r#"
+            UPDATE NotificationSettings
+            SET json_text = ?
+            WHERE account_row_id = ?
+            "#,
+            settings,
+            id
+        )
+    }
+}
+
+/// SQLite implementation of [TransactionWriteAccountStorage].
+pub struct TransactionWriteAccountCommands<'t> {
+    transaction: &'t mut Transaction<'static, Sqlite>,
+}
+
+impl<'t> TransactionWriteAccountCommands<'t> {
+    pub fn new(transaction: &'t mut Transaction<'static, Sqlite>) -> Self {
+        Self { transaction }
+    }
+}
+
+#[async_trait]
+impl TransactionWriteAccountStorage for TransactionWriteAccountCommands<'_> {
+    async fn store_account_id(
+        &mut self,
         id: AccountIdLight,
+        region: Option<&str>,
     ) -> WriteResult<AccountIdInternal, SqliteDatabaseError, AccountIdLight> {
         let id = id.as_uuid();
+        let created_unix_time = current_unix_time();
         let insert_result = sqlx::query!(
             r#"
-            INSERT INTO AccountId (account_id)
-            VALUES (?)
+            INSERT INTO AccountId (account_id, created_unix_time, region)
+            VALUES (?, ?, ?)
             "#,
-            id
+            id,
+            created_unix_time,
+            region,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
@@ -47,64 +515,57 @@ impl<'a> CurrentWriteAccountCommands<'a> {
         })
     }
 
-    pub async fn store_api_key(
-        &self,
+    async fn store_api_key(
+        &mut self,
         id: AccountIdInternal,
         api_key: Option<ApiKey>,
     ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
-        let api_key = api_key.as_ref().map(|k| k.as_str());
+        let api_key_hash = api_key.map(|k| k.hash().into_bytes());
         let id = id.row_id();
         sqlx::query!(
             r#"
-            INSERT INTO ApiKey (api_key, account_row_id)
+            INSERT INTO ApiKey (api_key_hash, account_row_id)
             VALUES (?, ?)
             "#,
-            api_key,
+            api_key_hash,
             id,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }
 
-    pub async fn store_refresh_token(
-        &self,
+    async fn store_refresh_token(
+        &mut self,
         id: AccountIdInternal,
         refresh_token: Option<RefreshToken>,
     ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
-        let refresh_token = if let Some(t) = refresh_token {
-            Some(
-                t.bytes()
-                    .into_error(SqliteDatabaseError::DataFormatConversion)?,
-            )
-        } else {
-            None
-        };
+        let refresh_token_hash = refresh_token.map(|t| t.hash().into_bytes());
         let id = id.row_id();
         sqlx::query!(
             r#"
-            INSERT INTO RefreshToken (refresh_token, account_row_id)
+            INSERT INTO RefreshToken (refresh_token_hash, account_row_id)
             VALUES (?, ?)
             "#,
-            refresh_token,
+            refresh_token_hash,
             id,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }
 
-    pub async fn store_account(
-        &self,
+    async fn store_account(
+        &mut self,
         id: AccountIdInternal,
         account: &Account,
     ) -> WriteResult<(), SqliteDatabaseError, Account> {
         insert_or_update_json!(
-            self,
+            executor = &mut *self.transaction,
             r#"
             INSERT INTO Account (json_text, account_row_id)
             VALUES (?, ?)
@@ -114,13 +575,13 @@ impl<'a> CurrentWriteAccountCommands<'a> {
         )
     }
 
-    pub async fn store_account_setup(
-        &self,
+    async fn store_account_setup(
+        &mut self,
         id: AccountIdInternal,
         account: &AccountSetup,
     ) -> WriteResult<(), SqliteDatabaseError, AccountSetup> {
         insert_or_update_json!(
-            self,
+            executor = &mut *self.transaction,
             r#"
             INSERT INTO AccountSetup (json_text, account_row_id)
             VALUES (?, ?)
@@ -130,96 +591,144 @@ impl<'a> CurrentWriteAccountCommands<'a> {
         )
     }
 
-    pub async fn store_sign_in_with_info(
-        &self,
+    async fn store_notification_settings(
+        &mut self,
+        id: AccountIdInternal,
+        settings: &NotificationSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, NotificationSettings> {
+        insert_or_update_json!(
+            executor = &mut *self.transaction,
+            r#"
+            INSERT INTO NotificationSettings (json_text, account_row_id)
+            VALUES (?, ?)
+            "#,
+            settings,
+            id
+        )
+    }
+
+    async fn store_sign_in_with_info(
+        &mut self,
         id: AccountIdInternal,
         sign_in_with_info: &SignInWithInfo,
     ) -> WriteResult<(), SqliteDatabaseError, SignInWithInfo> {
         let id = id.row_id();
         sqlx::query!(
             r#"
-            INSERT INTO SignInWithInfo (google_account_id, account_row_id)
-            VALUES (?, ?)
+            INSERT INTO SignInWithInfo (google_account_id, apple_account_id, account_row_id)
+            VALUES (?, ?, ?)
             "#,
             sign_in_with_info.google_account_id,
+            sign_in_with_info.apple_account_id,
             id,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }
 
-    pub async fn update_api_key(
-        &self,
+    async fn store_account_alias(
+        &mut self,
+        id: AccountIdInternal,
+        alias: &AccountAlias,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountAlias> {
+        let id = id.row_id();
+        let alias = alias.as_str();
+        sqlx::query!(
+            r#"
+            INSERT INTO AccountAlias (account_row_id, alias)
+            VALUES (?, ?)
+            "#,
+            id,
+            alias,
+        )
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_api_key(
+        &mut self,
         id: AccountIdInternal,
         api_key: Option<&ApiKey>,
+        expires_unix_time: Option<i64>,
     ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
-        let api_key = api_key.as_ref().map(|k| k.as_str());
+        let api_key_hash = api_key.map(|k| k.hash().into_bytes());
         let id = id.row_id();
         sqlx::query!(
             r#"
             UPDATE ApiKey
-            SET api_key = ?
+            SET api_key_hash = ?, expires_unix_time = ?
             WHERE account_row_id = ?
             "#,
-            api_key,
+            api_key_hash,
+            expires_unix_time,
             id,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }
 
-    pub async fn update_refresh_token(
-        &self,
+    async fn save_used_refresh_token(
+        &mut self,
         id: AccountIdInternal,
-        refresh_token: Option<&RefreshToken>,
-    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
-        let refresh_token = if let Some(t) = refresh_token {
-            Some(
-                t.bytes()
-                    .into_error(SqliteDatabaseError::DataFormatConversion)?,
-            )
-        } else {
-            None
-        };
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, RefreshToken> {
         let id = id.row_id();
         sqlx::query!(
             r#"
-            UPDATE RefreshToken
-            SET refresh_token = ?
-            WHERE account_row_id = ?
+            INSERT OR IGNORE INTO UsedRefreshToken (account_row_id, refresh_token_hash, generation, used_unix_time)
+            SELECT account_row_id, refresh_token_hash, refresh_token_generation, ?
+            FROM RefreshToken
+            WHERE account_row_id = ? AND refresh_token_hash IS NOT NULL
             "#,
-            refresh_token,
+            unix_time,
             id,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        let expired = unix_time - USED_REFRESH_TOKEN_LIFETIME_SECONDS;
+        sqlx::query!(
+            r#"
+            DELETE FROM UsedRefreshToken
+            WHERE account_row_id = ? AND used_unix_time < ?
+            "#,
+            id,
+            expired,
+        )
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }
 
-    pub async fn update_sign_in_with_info(
-        &self,
+    async fn update_refresh_token(
+        &mut self,
         id: AccountIdInternal,
-        sign_in_with: &SignInWithInfo,
+        refresh_token: Option<&RefreshToken>,
     ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        let refresh_token_hash = refresh_token.map(|t| t.hash().into_bytes());
         let id = id.row_id();
         sqlx::query!(
             r#"
-            UPDATE SignInWithInfo
-            SET google_account_id = ?
+            UPDATE RefreshToken
+            SET refresh_token_hash = ?, refresh_token_generation = refresh_token_generation + 1
             WHERE account_row_id = ?
             "#,
-            sign_in_with.google_account_id,
+            refresh_token_hash,
             id,
         )
-        .execute(self.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
@@ -228,41 +737,37 @@ impl<'a> CurrentWriteAccountCommands<'a> {
 }
 
 #[async_trait]
-impl SqliteUpdateJson for Account {
+impl UpdateJson for Account {
     async fn update_json(
         &self,
         id: AccountIdInternal,
         write: &CurrentDataWriteCommands,
     ) -> Result<(), SqliteDatabaseError> {
-        insert_or_update_json!(
-            write,
-            r#"
-            UPDATE Account
-            SET json_text = ?
-            WHERE account_row_id = ?
-            "#,
-            self,
-            id
-        )
+        write.account().update_account_json(id, self).await
     }
 }
 
 #[async_trait]
-impl SqliteUpdateJson for AccountSetup {
+impl UpdateJson for AccountSetup {
     async fn update_json(
         &self,
         id: AccountIdInternal,
         write: &CurrentDataWriteCommands,
     ) -> Result<(), SqliteDatabaseError> {
-        insert_or_update_json!(
-            write,
-            r#"
-            UPDATE AccountSetup
-            SET json_text = ?
-            WHERE account_row_id = ?
-            "#,
-            self,
-            id
-        )
+        write.account().update_account_setup_json(id, self).await
+    }
+}
+
+#[async_trait]
+impl UpdateJson for NotificationSettings {
+    async fn update_json(
+        &self,
+        id: AccountIdInternal,
+        write: &CurrentDataWriteCommands,
+    ) -> Result<(), SqliteDatabaseError> {
+        write
+            .account()
+            .update_notification_settings_json(id, self)
+            .await
     }
 }
@@ -1,13 +1,17 @@
 use async_trait::async_trait;
 use error_stack::Result;
 
-use crate::server::database::current::SqliteReadCommands;
-use crate::server::database::sqlite::{SqliteDatabaseError, SqliteReadHandle, SqliteSelectJson};
+use crate::server::database::current::traits::ReadCalculatorStorage;
+use crate::server::database::current::{CurrentReadCommands, SelectJson};
+use crate::server::database::sqlite::{SqliteDatabaseError, SqliteReadHandle};
 
 use crate::api::model::*;
 
+use crate::read_json;
+use crate::server::database::{read::ReadResult, write::NoId};
 use crate::utils::IntoReportExt;
 
+/// SQLite implementation of [ReadCalculatorStorage].
 pub struct CurrentReadCalculatorCommands<'a> {
     handle: &'a SqliteReadHandle,
 }
@@ -19,11 +23,217 @@ impl<'a> CurrentReadCalculatorCommands<'a> {
 }
 
 #[async_trait]
-impl SqliteSelectJson for CalculatorStateInternal {
-    async fn select_json(
+impl ReadCalculatorStorage for CurrentReadCalculatorCommands<'_> {
+    async fn calculation_schedules(
+        &self,
         id: AccountIdInternal,
-        read: &SqliteReadCommands,
-    ) -> Result<Self, SqliteDatabaseError> {
+    ) -> ReadResult<Vec<CalculationSchedule>, SqliteDatabaseError, CalculationSchedule> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            CalculationSchedule,
+            r#"
+            SELECT schedule_id, expression, next_run_unix_time, repeat_interval_seconds
+            FROM CalculationSchedule
+            WHERE account_row_id = ?
+            ORDER BY schedule_id
+            "#,
+            id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculation_schedule_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, CalculationSchedule> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM CalculationSchedule
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|result| result.count)
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculator_slots(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<CalculatorSlot>, SqliteDatabaseError, CalculatorSlot> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            CalculatorSlot,
+            r#"
+            SELECT slot_name, state, updated_unix_time
+            FROM CalculatorSlot
+            WHERE account_row_id = ?
+            ORDER BY slot_name
+            "#,
+            id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculator_slot_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, CalculatorSlot> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM CalculatorSlot
+            WHERE account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|result| result.count)
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn history_entry(
+        &self,
+        id: AccountIdInternal,
+        history_id: HistoryId,
+    ) -> ReadResult<Option<HistoryEntryInternal>, SqliteDatabaseError, HistoryEntryInternal> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            HistoryEntryInternal,
+            r#"
+            SELECT history_id, expression, result, error, unix_time
+            FROM CalculatorHistory
+            WHERE account_row_id = ? AND history_id = ?
+            "#,
+            id,
+            history_id.history_id,
+        )
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn history_entries(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<HistoryEntryInternal>, SqliteDatabaseError, HistoryEntryInternal> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            HistoryEntryInternal,
+            r#"
+            SELECT history_id, expression, result, error, unix_time
+            FROM CalculatorHistory
+            WHERE account_row_id = ?
+            ORDER BY history_id
+            "#,
+            id,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculator_state_readers(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<AccountIdLight>, SqliteDatabaseError, CalculatorStateShare> {
+        let id = id.row_id();
+        sqlx::query_as!(
+            AccountIdLight,
+            r#"
+            SELECT AccountId.account_id as "account_id: _"
+            FROM CalculatorStateShare
+            INNER JOIN AccountId
+                ON CalculatorStateShare.reader_account_row_id = AccountId.account_row_id
+            WHERE CalculatorStateShare.owner_account_row_id = ?
+            "#,
+            id
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn due_calculation_schedules(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<Vec<(AccountIdInternal, CalculationSchedule)>, SqliteDatabaseError, NoId> {
+        let schedules = sqlx::query!(
+            r#"
+            SELECT
+                CalculationSchedule.schedule_id as "schedule_id!",
+                CalculationSchedule.expression,
+                CalculationSchedule.next_run_unix_time,
+                CalculationSchedule.repeat_interval_seconds,
+                AccountId.account_row_id,
+                AccountId.account_id as "account_id: uuid::Uuid"
+            FROM CalculationSchedule
+            INNER JOIN AccountId
+                ON CalculationSchedule.account_row_id = AccountId.account_row_id
+            WHERE CalculationSchedule.next_run_unix_time <= ?
+            "#,
+            unix_time
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?
+        .into_iter()
+        .map(|r| {
+            (
+                AccountIdInternal {
+                    account_id: r.account_id,
+                    account_row_id: r.account_row_id,
+                },
+                CalculationSchedule {
+                    schedule_id: r.schedule_id,
+                    expression: r.expression,
+                    next_run_unix_time: r.next_run_unix_time,
+                    repeat_interval_seconds: r.repeat_interval_seconds,
+                },
+            )
+        })
+        .collect();
+
+        Ok(schedules)
+    }
+
+    async fn calculator_settings_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<CalculatorSettings, SqliteDatabaseError> {
+        read_json!(
+            self,
+            id,
+            r#"
+            SELECT json_text
+            FROM CalculatorSettings
+            WHERE account_row_id = ?
+            "#,
+            json_text
+        )
+    }
+
+    async fn calculator_state(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<CalculatorStateInternal, SqliteDatabaseError> {
         let request = sqlx::query_as!(
             CalculatorStateInternal,
             r#"
@@ -34,10 +244,30 @@ impl SqliteSelectJson for CalculatorStateInternal {
             "#,
             id.account_row_id,
         )
-        .fetch_one(read.handle.pool())
+        .fetch_one(self.handle.pool())
         .await
         .into_error(SqliteDatabaseError::Fetch)?;
 
         Ok(request)
     }
 }
+
+#[async_trait]
+impl SelectJson for CalculatorSettings {
+    async fn select_json(
+        id: AccountIdInternal,
+        read: &CurrentReadCommands,
+    ) -> Result<Self, SqliteDatabaseError> {
+        read.calculator().calculator_settings_json(id).await
+    }
+}
+
+#[async_trait]
+impl SelectJson for CalculatorStateInternal {
+    async fn select_json(
+        id: AccountIdInternal,
+        read: &CurrentReadCommands,
+    ) -> Result<Self, SqliteDatabaseError> {
+        read.calculator().calculator_state(id).await
+    }
+}
@@ -1,65 +1,427 @@
 use async_trait::async_trait;
 use error_stack::Result;
+use sqlx::{Sqlite, Transaction};
 
-use crate::server::database::current::CurrentDataWriteCommands;
-use crate::server::database::sqlite::{
-    CurrentDataWriteHandle, SqliteDatabaseError, SqliteSelectJson, SqliteUpdateJson,
+use crate::server::database::current::traits::{
+    TransactionWriteCalculatorStorage, WriteCalculatorStorage,
 };
+use crate::server::database::current::{CurrentDataWriteCommands, UpdateJson};
+use crate::server::database::sqlite::{SqliteDatabaseError, SqliteWriteHandle};
 
 use crate::api::model::*;
 
+use crate::insert_or_update_json;
 use crate::server::database::write::WriteResult;
 use crate::utils::IntoReportExt;
 
+/// SQLite implementation of [WriteCalculatorStorage].
 pub struct CurrentWriteCalculatorCommands<'a> {
-    handle: &'a CurrentDataWriteHandle,
+    handle: &'a SqliteWriteHandle,
 }
 
 impl<'a> CurrentWriteCalculatorCommands<'a> {
-    pub fn new(handle: &'a CurrentDataWriteHandle) -> Self {
+    pub fn new(handle: &'a SqliteWriteHandle) -> Self {
         Self { handle }
     }
+}
+
+#[async_trait]
+impl WriteCalculatorStorage for CurrentWriteCalculatorCommands<'_> {
+    async fn insert_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule: &NewCalculationSchedule,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewCalculationSchedule> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO CalculationSchedule
+                (account_row_id, expression, next_run_unix_time, repeat_interval_seconds)
+            VALUES (?, ?, ?, ?)
+            "#,
+            id,
+            schedule.expression,
+            schedule.run_at_unix_time,
+            schedule.repeat_interval_seconds,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.last_insert_rowid())
+    }
 
-    pub async fn init_calculator_state(
+    async fn update_calculation_schedule(
         &self,
         id: AccountIdInternal,
-    ) -> WriteResult<CalculatorStateInternal, SqliteDatabaseError, CalculatorState> {
+        schedule_id: ScheduleId,
+        schedule: &NewCalculationSchedule,
+    ) -> WriteResult<bool, SqliteDatabaseError, NewCalculationSchedule> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            UPDATE CalculationSchedule
+            SET expression = ?, next_run_unix_time = ?, repeat_interval_seconds = ?
+            WHERE schedule_id = ? AND account_row_id = ?
+            "#,
+            schedule.expression,
+            schedule.run_at_unix_time,
+            schedule.repeat_interval_seconds,
+            schedule_id.schedule_id,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_calculation_schedule_next_run(
+        &self,
+        schedule_id: ScheduleId,
+        next_run_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculationSchedule> {
         sqlx::query!(
             r#"
-            INSERT INTO CurrentState (account_row_id)
-            VALUES (?)
+            UPDATE CalculationSchedule
+            SET next_run_unix_time = ?
+            WHERE schedule_id = ?
+            "#,
+            next_run_unix_time,
+            schedule_id.schedule_id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculationSchedule> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM CalculationSchedule
+            WHERE schedule_id = ? AND account_row_id = ?
+            "#,
+            schedule_id.schedule_id,
+            id,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+        state: &str,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorSlot> {
+        let id = id.row_id();
+        sqlx::query!(
+            r#"
+            INSERT INTO CalculatorSlot (account_row_id, slot_name, state, updated_unix_time)
+            VALUES (?, ?, ?, ?)
+            "#,
+            id,
+            slot_name.slot_name,
+            state,
+            unix_time,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+        state: &str,
+        unix_time: i64,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculatorSlot> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            UPDATE CalculatorSlot
+            SET state = ?, updated_unix_time = ?
+            WHERE account_row_id = ? AND slot_name = ?
+            "#,
+            state,
+            unix_time,
+            id,
+            slot_name.slot_name,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculatorSlot> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM CalculatorSlot
+            WHERE account_row_id = ? AND slot_name = ?
+            "#,
+            id,
+            slot_name.slot_name,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_calculator_state_share(
+        &self,
+        owner: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateShare> {
+        let owner = owner.row_id();
+        let reader = reader.row_id();
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO CalculatorStateShare
+                (owner_account_row_id, reader_account_row_id)
+            VALUES (?, ?)
+            "#,
+            owner,
+            reader,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_calculator_state_share(
+        &self,
+        owner: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateShare> {
+        let owner = owner.row_id();
+        let reader = reader.row_id();
+        sqlx::query!(
+            r#"
+            DELETE FROM CalculatorStateShare
+            WHERE owner_account_row_id = ? AND reader_account_row_id = ?
             "#,
+            owner,
+            reader,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_history_entry(
+        &self,
+        id: AccountIdInternal,
+        entry: &NewHistoryEntry,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewHistoryEntry> {
+        let id = id.row_id();
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO CalculatorHistory (account_row_id, expression, result, error, unix_time)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            id,
+            entry.expression,
+            entry.result,
+            entry.error,
+            entry.unix_time,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn delete_expired_history_entries(
+        &self,
+        now_unix_time: i64,
+        default_retention_days: Option<i64>,
+    ) -> WriteResult<u64, SqliteDatabaseError, NewHistoryEntry> {
+        // Comparison with NULL is never true, so entries are kept if
+        // neither retention is set.
+        let default_cutoff = default_retention_days.map(|days| now_unix_time - days * 24 * 60 * 60);
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM CalculatorHistory
+            WHERE unix_time < COALESCE(
+                (
+                    SELECT ? - OrganizationSettings.history_retention_days * 86400
+                    FROM OrganizationMember
+                    INNER JOIN OrganizationSettings
+                        ON OrganizationSettings.org_id = OrganizationMember.org_id
+                    WHERE OrganizationMember.account_row_id = CalculatorHistory.account_row_id
+                        AND OrganizationSettings.history_retention_days IS NOT NULL
+                ),
+                ?
+            )
+            "#,
+            now_unix_time,
+            default_cutoff,
+        )
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn update_calculator_settings_json(
+        &self,
+        id: AccountIdInternal,
+        settings: &CalculatorSettings,
+    ) -> Result<(), SqliteDatabaseError> {
+        insert_or_update_json!(
+            self,
+            r#"
+            UPDATE CalculatorSettings
+            SET json_text = ?
+            WHERE account_row_id = ?
+            "#,
+            settings,
+            id
+        )
+    }
+
+    async fn update_calculator_state(
+        &self,
+        id: AccountIdInternal,
+        state: &CalculatorStateInternal,
+    ) -> Result<(), SqliteDatabaseError> {
+        sqlx::query!(
+            r#"
+            UPDATE CurrentState
+            SET calculation = ?
+            WHERE account_row_id = ?
+            "#,
+            state.state,
             id.account_row_id,
         )
         .execute(self.handle.pool())
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
-        let state = CalculatorStateInternal::select_json(id, &self.handle.read()).await?;
-        Ok(state)
+        Ok(())
+    }
+}
+
+/// SQLite implementation of [TransactionWriteCalculatorStorage].
+pub struct TransactionWriteCalculatorCommands<'t> {
+    transaction: &'t mut Transaction<'static, Sqlite>,
+}
+
+impl<'t> TransactionWriteCalculatorCommands<'t> {
+    pub fn new(transaction: &'t mut Transaction<'static, Sqlite>) -> Self {
+        Self { transaction }
     }
 }
 
 #[async_trait]
-impl SqliteUpdateJson for CalculatorStateInternal {
-    async fn update_json(
-        &self,
+impl TransactionWriteCalculatorStorage for TransactionWriteCalculatorCommands<'_> {
+    async fn init_calculator_state(
+        &mut self,
         id: AccountIdInternal,
-        write: &CurrentDataWriteCommands,
-    ) -> Result<(), SqliteDatabaseError> {
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorState> {
+        sqlx::query!(
+            r#"
+            INSERT INTO CurrentState (account_row_id)
+            VALUES (?)
+            "#,
+            id.account_row_id,
+        )
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn store_calculator_settings(
+        &mut self,
+        id: AccountIdInternal,
+        settings: &CalculatorSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorSettings> {
+        insert_or_update_json!(
+            executor = &mut *self.transaction,
+            r#"
+            INSERT INTO CalculatorSettings (json_text, account_row_id)
+            VALUES (?, ?)
+            "#,
+            settings,
+            id
+        )
+    }
+
+    async fn update_calculator_state(
+        &mut self,
+        id: AccountIdInternal,
+        state: &CalculatorStateInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateInternal> {
         sqlx::query!(
             r#"
             UPDATE CurrentState
             SET calculation = ?
             WHERE account_row_id = ?
             "#,
-            self.state,
+            state.state,
             id.account_row_id,
         )
-        .execute(write.handle.pool())
+        .execute(&mut *self.transaction)
         .await
         .into_error(SqliteDatabaseError::Execute)?;
 
         Ok(())
     }
 }
+
+#[async_trait]
+impl UpdateJson for CalculatorSettings {
+    async fn update_json(
+        &self,
+        id: AccountIdInternal,
+        write: &CurrentDataWriteCommands,
+    ) -> Result<(), SqliteDatabaseError> {
+        write
+            .calculator()
+            .update_calculator_settings_json(id, self)
+            .await
+    }
+}
+
+#[async_trait]
+impl UpdateJson for CalculatorStateInternal {
+    async fn update_json(
+        &self,
+        id: AccountIdInternal,
+        write: &CurrentDataWriteCommands,
+    ) -> Result<(), SqliteDatabaseError> {
+        write.calculator().update_calculator_state(id, self).await
+    }
+}
@@ -0,0 +1,582 @@
+//! Storage traits for the current data database
+//!
+//! SQLite and PostgreSQL backends implement these traits. Use
+//! [super::CurrentReadCommands], [super::CurrentDataWriteCommands] and
+//! [super::CurrentDataTransaction] to get the implementation of the
+//! configured backend.
+
+use async_trait::async_trait;
+use error_stack::Result;
+use futures::stream::BoxStream;
+
+use crate::api::model::*;
+use crate::server::database::{
+    read::ReadResult,
+    sqlite::SqliteDatabaseError,
+    write::{NoId, WriteResult},
+};
+
+#[async_trait]
+pub trait ReadAccountStorage: Send + Sync {
+    fn account_ids_stream(
+        &self,
+    ) -> BoxStream<'_, ReadResult<AccountIdInternal, SqliteDatabaseError, NoId>>;
+
+    /// Account IDs in registration order. If `region` is set, only
+    /// accounts of the region are included.
+    async fn account_ids_page(
+        &self,
+        offset: i64,
+        limit: i64,
+        region: Option<&str>,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId>;
+
+    async fn account_times(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<AccountTimes, SqliteDatabaseError, AccountIdInternal>;
+
+    /// Data residency region. None if the account does not have a region.
+    async fn account_region(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<String>, SqliteDatabaseError, AccountIdInternal>;
+
+    /// Account counts per region.
+    async fn account_regions(
+        &self,
+    ) -> ReadResult<Vec<RegionRegistrations>, SqliteDatabaseError, NoId>;
+
+    /// Database has only the hash of the access token.
+    async fn access_token_hash(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, ApiKey>;
+
+    /// Access token expiration time. None if the token does not expire.
+    async fn access_token_expiration(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<i64>, SqliteDatabaseError, ApiKey>;
+
+    async fn account_with_refresh_token(
+        &self,
+        token: &RefreshToken,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError, RefreshToken>;
+
+    /// Database has only the hash of the refresh token.
+    async fn refresh_token_hash(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, RefreshToken>;
+
+    /// Generation of the token if the token was the refresh token of the
+    /// account before it was replaced.
+    async fn used_refresh_token_generation(
+        &self,
+        id: AccountIdInternal,
+        token: &RefreshToken,
+    ) -> ReadResult<Option<i64>, SqliteDatabaseError, RefreshToken>;
+
+    /// Service tokens of the account without the token values.
+    async fn service_tokens(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<ServiceTokenInfo>, SqliteDatabaseError, ServiceTokenInfo>;
+
+    /// Service token hashes and scopes of the account.
+    async fn service_token_hashes(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<(TokenHash, ServiceTokenScope)>, SqliteDatabaseError, ApiKey>;
+
+    /// Returns None if account does not have the service token.
+    async fn service_token_hash(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, ApiKey>;
+
+    async fn service_token_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, ServiceTokenInfo>;
+
+    /// Most recent login history entries first.
+    async fn login_history(
+        &self,
+        id: AccountIdInternal,
+        limit: i64,
+    ) -> ReadResult<Vec<LoginHistoryEntry>, SqliteDatabaseError, LoginHistoryEntry>;
+
+    async fn organization(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Option<Organization>, SqliteDatabaseError, Organization>;
+
+    /// Returns None if the account does not belong to an organization.
+    async fn organization_membership(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<OrganizationMembership>, SqliteDatabaseError, Organization>;
+
+    /// Members of one organization in joining order.
+    async fn organization_members(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Vec<OrganizationMember>, SqliteDatabaseError, Organization>;
+
+    /// Account IDs of the organization members without usage info.
+    async fn organization_member_ids(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Vec<AccountIdLight>, SqliteDatabaseError, Organization>;
+
+    /// Returns None if the organization does not exist.
+    async fn organization_settings(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Option<OrganizationSettings>, SqliteDatabaseError, Organization>;
+
+    /// Settings of the organization of the account. Default settings if the
+    /// account does not belong to an organization.
+    async fn account_organization_settings(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<OrganizationSettings, SqliteDatabaseError, Organization>;
+
+    async fn sign_in_with_info(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<SignInWithInfo, SqliteDatabaseError>;
+
+    /// Account and calculator data of the account with one query.
+    async fn account_data(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<AccountDataInternal, SqliteDatabaseError, AccountDataInternal>;
+
+    async fn get_account_with_google_account_id(
+        &self,
+        google_account_id: GoogleAccountId,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError>;
+
+    async fn get_account_with_apple_account_id(
+        &self,
+        apple_account_id: AppleAccountId,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError>;
+
+    async fn get_account_with_alias(
+        &self,
+        alias: &AccountAlias,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError>;
+
+    /// None if the account does not have an alias.
+    async fn account_alias(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<AccountAlias>, SqliteDatabaseError>;
+
+    async fn account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<AccountDeletionInfo>, SqliteDatabaseError>;
+
+    /// Accounts which deletion grace period has ended at `unix_time` or
+    /// before it.
+    async fn expired_account_deletions(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId>;
+
+    /// Accounts which latest activity, login or registration is before
+    /// `inactive_before` and which deletion is not yet requested. Returns at
+    /// most `limit` accounts which row ID is larger than `after_row_id`
+    /// ordered by row ID.
+    async fn stale_accounts(
+        &self,
+        inactive_before: i64,
+        after_row_id: i64,
+        limit: i64,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId>;
+
+    /// Registration, setup completion and activity counts. Accounts are
+    /// active if the latest saved activity time is after `unix_time` minus
+    /// the activity period.
+    async fn account_report(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<AccountReport, SqliteDatabaseError, NoId>;
+
+    async fn account_json(&self, id: AccountIdInternal) -> Result<Account, SqliteDatabaseError>;
+
+    async fn account_setup_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<AccountSetup, SqliteDatabaseError>;
+
+    async fn notification_settings_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<NotificationSettings, SqliteDatabaseError>;
+}
+
+#[async_trait]
+pub trait WriteAccountStorage: Send + Sync {
+    async fn update_sign_in_with_info(
+        &self,
+        id: AccountIdInternal,
+        sign_in_with: &SignInWithInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey>;
+
+    async fn update_account_activity(
+        &self,
+        id: AccountIdInternal,
+        last_activity_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal>;
+
+    async fn update_last_login(
+        &self,
+        id: AccountIdInternal,
+        last_login_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal>;
+
+    async fn insert_account_deletion(
+        &self,
+        id: AccountIdInternal,
+        info: AccountDeletionInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountDeletionInfo>;
+
+    async fn delete_account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountDeletionInfo>;
+
+    /// Returns token ID.
+    async fn insert_service_token(
+        &self,
+        id: AccountIdInternal,
+        token: &ApiKey,
+        new: &NewServiceToken,
+        created_unix_time: i64,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewServiceToken>;
+
+    /// Returns false if account does not have the service token.
+    async fn delete_service_token(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> WriteResult<bool, SqliteDatabaseError, ServiceTokenInfo>;
+
+    async fn insert_login_history(
+        &self,
+        id: AccountIdInternal,
+        method: LoginMethod,
+        ip_address: Option<&str>,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, LoginHistoryEntry>;
+
+    /// Returns organization ID.
+    async fn insert_organization(
+        &self,
+        org: &NewOrganization,
+        created_unix_time: i64,
+    ) -> WriteResult<i64, SqliteDatabaseError, Organization>;
+
+    /// Add the account to the organization or update the admin status. The
+    /// account is moved if it belongs to another organization.
+    async fn upsert_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+        is_admin: bool,
+        joined_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, OrganizationMember>;
+
+    async fn upsert_organization_settings(
+        &self,
+        org_id: OrganizationId,
+        settings: &OrganizationSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, OrganizationSettings>;
+
+    /// Returns false if the account is not a member of the organization.
+    async fn delete_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+    ) -> WriteResult<bool, SqliteDatabaseError, OrganizationMember>;
+
+    /// Remove all data of the account in a transaction.
+    async fn delete_account(
+        &self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal>;
+
+    async fn update_account_json(
+        &self,
+        id: AccountIdInternal,
+        account: &Account,
+    ) -> Result<(), SqliteDatabaseError>;
+
+    async fn update_account_setup_json(
+        &self,
+        id: AccountIdInternal,
+        account_setup: &AccountSetup,
+    ) -> Result<(), SqliteDatabaseError>;
+
+    async fn update_notification_settings_json(
+        &self,
+        id: AccountIdInternal,
+        settings: &NotificationSettings,
+    ) -> Result<(), SqliteDatabaseError>;
+}
+
+/// Account write commands which run in a transaction.
+#[async_trait]
+pub trait TransactionWriteAccountStorage: Send {
+    async fn store_account_id(
+        &mut self,
+        id: AccountIdLight,
+        region: Option<&str>,
+    ) -> WriteResult<AccountIdInternal, SqliteDatabaseError, AccountIdLight>;
+
+    async fn store_api_key(
+        &mut self,
+        id: AccountIdInternal,
+        api_key: Option<ApiKey>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey>;
+
+    async fn store_refresh_token(
+        &mut self,
+        id: AccountIdInternal,
+        refresh_token: Option<RefreshToken>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey>;
+
+    async fn store_account(
+        &mut self,
+        id: AccountIdInternal,
+        account: &Account,
+    ) -> WriteResult<(), SqliteDatabaseError, Account>;
+
+    async fn store_account_setup(
+        &mut self,
+        id: AccountIdInternal,
+        account: &AccountSetup,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountSetup>;
+
+    async fn store_notification_settings(
+        &mut self,
+        id: AccountIdInternal,
+        settings: &NotificationSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, NotificationSettings>;
+
+    async fn store_sign_in_with_info(
+        &mut self,
+        id: AccountIdInternal,
+        sign_in_with_info: &SignInWithInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, SignInWithInfo>;
+
+    async fn store_account_alias(
+        &mut self,
+        id: AccountIdInternal,
+        alias: &AccountAlias,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountAlias>;
+
+    /// Expiration time is None if the access token does not expire.
+    async fn update_api_key(
+        &mut self,
+        id: AccountIdInternal,
+        api_key: Option<&ApiKey>,
+        expires_unix_time: Option<i64>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey>;
+
+    /// Save hash and generation of the current refresh token before it is
+    /// replaced. Old used tokens are removed.
+    async fn save_used_refresh_token(
+        &mut self,
+        id: AccountIdInternal,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, RefreshToken>;
+
+    /// Increments the refresh token generation.
+    async fn update_refresh_token(
+        &mut self,
+        id: AccountIdInternal,
+        refresh_token: Option<&RefreshToken>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey>;
+}
+
+#[async_trait]
+pub trait ReadCalculatorStorage: Send + Sync {
+    async fn calculation_schedules(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<CalculationSchedule>, SqliteDatabaseError, CalculationSchedule>;
+
+    async fn calculation_schedule_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, CalculationSchedule>;
+
+    /// Slots ordered by name.
+    async fn calculator_slots(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<CalculatorSlot>, SqliteDatabaseError, CalculatorSlot>;
+
+    async fn calculator_slot_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, CalculatorSlot>;
+
+    async fn history_entry(
+        &self,
+        id: AccountIdInternal,
+        history_id: HistoryId,
+    ) -> ReadResult<Option<HistoryEntryInternal>, SqliteDatabaseError, HistoryEntryInternal>;
+
+    /// All history entries of the account in creation order.
+    async fn history_entries(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<HistoryEntryInternal>, SqliteDatabaseError, HistoryEntryInternal>;
+
+    /// Accounts which can read the calculator state of the account.
+    async fn calculator_state_readers(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<AccountIdLight>, SqliteDatabaseError, CalculatorStateShare>;
+
+    /// Schedules which should be evaluated at `unix_time` or before it.
+    async fn due_calculation_schedules(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<Vec<(AccountIdInternal, CalculationSchedule)>, SqliteDatabaseError, NoId>;
+
+    async fn calculator_settings_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<CalculatorSettings, SqliteDatabaseError>;
+
+    async fn calculator_state(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<CalculatorStateInternal, SqliteDatabaseError>;
+}
+
+#[async_trait]
+pub trait WriteCalculatorStorage: Send + Sync {
+    /// Returns schedule ID.
+    async fn insert_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule: &NewCalculationSchedule,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewCalculationSchedule>;
+
+    /// Returns false if account does not have the schedule.
+    async fn update_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+        schedule: &NewCalculationSchedule,
+    ) -> WriteResult<bool, SqliteDatabaseError, NewCalculationSchedule>;
+
+    async fn update_calculation_schedule_next_run(
+        &self,
+        schedule_id: ScheduleId,
+        next_run_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculationSchedule>;
+
+    /// Returns false if account does not have the schedule.
+    async fn delete_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculationSchedule>;
+
+    async fn insert_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+        state: &str,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorSlot>;
+
+    /// Returns false if account does not have the slot.
+    async fn update_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+        state: &str,
+        unix_time: i64,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculatorSlot>;
+
+    /// Returns false if account does not have the slot.
+    async fn delete_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculatorSlot>;
+
+    /// Does nothing if the share already exists.
+    async fn insert_calculator_state_share(
+        &self,
+        owner: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateShare>;
+
+    async fn delete_calculator_state_share(
+        &self,
+        owner: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateShare>;
+
+    /// Returns history entry ID.
+    async fn insert_history_entry(
+        &self,
+        id: AccountIdInternal,
+        entry: &NewHistoryEntry,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewHistoryEntry>;
+
+    /// Delete history entries older than the history retention of the
+    /// organization of the account or `default_retention_days` if the
+    /// organization does not override it. Returns count of deleted entries.
+    async fn delete_expired_history_entries(
+        &self,
+        now_unix_time: i64,
+        default_retention_days: Option<i64>,
+    ) -> WriteResult<u64, SqliteDatabaseError, NewHistoryEntry>;
+
+    async fn update_calculator_settings_json(
+        &self,
+        id: AccountIdInternal,
+        settings: &CalculatorSettings,
+    ) -> Result<(), SqliteDatabaseError>;
+
+    async fn update_calculator_state(
+        &self,
+        id: AccountIdInternal,
+        state: &CalculatorStateInternal,
+    ) -> Result<(), SqliteDatabaseError>;
+}
+
+/// Calculator write commands which run in a transaction.
+#[async_trait]
+pub trait TransactionWriteCalculatorStorage: Send {
+    async fn init_calculator_state(
+        &mut self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorState>;
+
+    async fn store_calculator_settings(
+        &mut self,
+        id: AccountIdInternal,
+        settings: &CalculatorSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorSettings>;
+
+    async fn update_calculator_state(
+        &mut self,
+        id: AccountIdInternal,
+        state: &CalculatorStateInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateInternal>;
+}
@@ -0,0 +1,80 @@
+//! Available disk space checks
+
+use std::path::Path;
+
+use error_stack::{Report, Result};
+use nix::sys::statvfs::statvfs;
+use tracing::warn;
+
+use crate::utils::IntoReportExt;
+
+const BYTES_IN_MIB: u64 = 1024 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DiskSpaceError {
+    #[error("Reading file system info failed")]
+    FileSystemInfo,
+    #[error("Available disk space is less than the configured minimum")]
+    NotEnoughSpace,
+}
+
+/// Thresholds for available disk space in the database directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpaceLimits {
+    /// Warning is logged when available space is less than this.
+    pub warning_mib: u64,
+    /// Operations which need disk space fail when available space is less
+    /// than this.
+    pub minimum_mib: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+}
+
+impl DiskSpace {
+    /// Disk space of the file system which contains `path`.
+    pub fn read(path: &Path) -> Result<Self, DiskSpaceError> {
+        let info = statvfs(path).into_error(DiskSpaceError::FileSystemInfo)?;
+        let fragment_size = info.fragment_size() as u64;
+        Ok(Self {
+            available_bytes: info.blocks_available() as u64 * fragment_size,
+            total_bytes: info.blocks() as u64 * fragment_size,
+        })
+    }
+
+    pub fn available_mib(&self) -> u64 {
+        self.available_bytes / BYTES_IN_MIB
+    }
+}
+
+/// Log warning if disk space is low. Error is returned if available space is
+/// less than the minimum.
+pub fn check_disk_space(path: &Path, limits: DiskSpaceLimits) -> Result<DiskSpace, DiskSpaceError> {
+    let space = DiskSpace::read(path)?;
+    let available = space.available_mib();
+
+    if available < limits.minimum_mib {
+        return Err(
+            Report::new(DiskSpaceError::NotEnoughSpace).attach_printable(format!(
+                "{}: available {} MiB, minimum {} MiB",
+                path.display(),
+                available,
+                limits.minimum_mib
+            )),
+        );
+    }
+
+    if available < limits.warning_mib {
+        warn!(
+            "Low disk space in {}: available {} MiB, warning threshold {} MiB",
+            path.display(),
+            available,
+            limits.warning_mib,
+        );
+    }
+
+    Ok(space)
+}
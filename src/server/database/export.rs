@@ -0,0 +1,125 @@
+//! Account data export bundles
+//!
+//! Export bundle is created when account deletion is requested, so that the
+//! account data can be downloaded during the deletion grace period. Bundles
+//! are JSON files in [Storage] and expired bundles are removed when the
+//! server starts and when a new bundle is saved.
+
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use tracing::{info, warn};
+
+use crate::{
+    api::model::{AccountExport, AccountIdLight},
+    utils::IntoReportExt,
+};
+
+use super::storage::Storage;
+
+pub const DB_EXPORTS_DIR_NAME: &str = "exports";
+
+/// How long export bundle is available after it is created.
+pub const EXPORT_BUNDLE_LIFETIME_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("Storage error")]
+    Storage,
+    #[error("Serialization error")]
+    Serialize,
+}
+
+/// Export bundle files of one server.
+pub struct ExportStorage {
+    storage: Arc<dyn Storage>,
+}
+
+impl ExportStorage {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    fn bundle_key(id: AccountIdLight) -> String {
+        format!("{}.json", id.as_uuid())
+    }
+
+    /// Replace the current bundle of the account if it exists.
+    pub async fn save(&self, export: &AccountExport) -> Result<(), ExportError> {
+        self.remove_expired(export.created_unix_time).await?;
+
+        let data = serde_json::to_vec(export).into_error(ExportError::Serialize)?;
+        self.storage
+            .put(&Self::bundle_key(export.account_id), data)
+            .await
+            .change_context(ExportError::Storage)
+    }
+
+    /// Returns None if the account does not have a bundle or the bundle is
+    /// expired.
+    pub async fn load(
+        &self,
+        id: AccountIdLight,
+        unix_time: i64,
+    ) -> Result<Option<AccountExport>, ExportError> {
+        let data = match self
+            .storage
+            .get(&Self::bundle_key(id))
+            .await
+            .change_context(ExportError::Storage)?
+        {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let export: AccountExport =
+            serde_json::from_slice(&data).into_error(ExportError::Serialize)?;
+
+        if export.expires_unix_time <= unix_time {
+            Ok(None)
+        } else {
+            Ok(Some(export))
+        }
+    }
+
+    /// Remove bundles which are expired at `unix_time`. Returns count of
+    /// removed bundles.
+    pub async fn remove_expired(&self, unix_time: i64) -> Result<u64, ExportError> {
+        let mut removed = 0;
+        let keys = self
+            .storage
+            .list()
+            .await
+            .change_context(ExportError::Storage)?;
+        for key in keys.iter().filter(|key| key.ends_with(".json")) {
+            let expired = match self.storage.get(key).await {
+                Ok(Some(data)) => match serde_json::from_slice::<AccountExport>(&data) {
+                    Ok(export) => export.expires_unix_time <= unix_time,
+                    Err(e) => {
+                        warn!("Invalid export bundle {}: {}", key, e);
+                        false
+                    }
+                },
+                Ok(None) => false,
+                Err(e) => {
+                    warn!("Reading export bundle {} failed: {:?}", key, e);
+                    false
+                }
+            };
+
+            if expired {
+                self.storage
+                    .delete(key)
+                    .await
+                    .change_context(ExportError::Storage)
+                    .attach_printable_lazy(|| key.clone())?;
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            info!("Removed {} expired export bundles", removed);
+        }
+
+        Ok(removed)
+    }
+}
@@ -0,0 +1,66 @@
+//! Append only history database
+//!
+//! Account history records are saved to a separate SQLite database, so that
+//! the current data database stays small. Queries are checked at runtime,
+//! because compile time checked queries use the current data database.
+
+use sqlx::SqlitePool;
+
+use crate::{
+    api::model::{AccountHistoryEvent, AccountIdLight},
+    utils::IntoReportExt,
+};
+
+use super::{
+    sqlite::{SqliteDatabaseError, SqliteWriteHandle},
+    write::WriteResult,
+};
+
+#[derive(Debug, Clone)]
+pub struct HistoryWriteHandle {
+    handle: SqliteWriteHandle,
+}
+
+impl HistoryWriteHandle {
+    pub fn new(handle: SqliteWriteHandle) -> Self {
+        Self { handle }
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        self.handle.pool()
+    }
+}
+
+pub struct HistoryWriteCommands<'a> {
+    handle: &'a HistoryWriteHandle,
+}
+
+impl<'a> HistoryWriteCommands<'a> {
+    pub fn new(handle: &'a HistoryWriteHandle) -> Self {
+        Self { handle }
+    }
+
+    pub async fn insert_account_event(
+        &self,
+        id: AccountIdLight,
+        event: AccountHistoryEvent,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountHistoryEvent> {
+        let event_json =
+            serde_json::to_string(&event).into_error(SqliteDatabaseError::SerdeSerialize)?;
+        sqlx::query(
+            r#"
+            INSERT INTO AccountHistory (account_id, event_json, unix_time)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(id.as_uuid())
+        .bind(event_json)
+        .bind(unix_time)
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+}
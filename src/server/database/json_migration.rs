@@ -0,0 +1,145 @@
+//! Versioned JSON data stored in SQLite
+//!
+//! Every JSON object stored with `read_json!` and `insert_or_update_json!`
+//! macros has a version field. Reading older data runs registered migrations
+//! lazily. Data is stored with the current version when it is written next
+//! time or when all data is migrated eagerly with the internal API.
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    api::model::{
+        Account, AccountSetup, CalculatorSettings, NotificationDelivery, NotificationSettings,
+    },
+    utils::IntoReportExt,
+};
+
+use super::sqlite::SqliteDatabaseError;
+
+pub const JSON_VERSION_FIELD: &str = "json_version";
+
+/// Migrate JSON object from version N to version N + 1.
+pub type JsonMigrationFn = fn(&mut Map<String, Value>) -> std::result::Result<(), String>;
+
+pub trait JsonMigrations {
+    /// Migration at index N migrates data from version N to version N + 1.
+    /// Data without version field is version 0.
+    ///
+    /// Only add new migrations to the end of the list.
+    const MIGRATIONS: &'static [JsonMigrationFn];
+
+    fn current_json_version() -> u64 {
+        Self::MIGRATIONS.len() as u64
+    }
+}
+
+/// Deserialize JSON and run migrations if stored data is older than the
+/// current version.
+pub fn from_versioned_json<T: JsonMigrations + DeserializeOwned>(
+    text: &str,
+) -> Result<T, SqliteDatabaseError> {
+    let value: Value =
+        serde_json::from_str(text).into_error(SqliteDatabaseError::SerdeDeserialize)?;
+    let mut object = match value {
+        Value::Object(object) => object,
+        _ => {
+            return Err(SqliteDatabaseError::SerdeDeserialize)
+                .into_report()
+                .attach_printable("Versioned JSON data is not an object")
+        }
+    };
+
+    let version = match object.remove(JSON_VERSION_FIELD) {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or(SqliteDatabaseError::SerdeDeserialize)
+            .into_report()
+            .attach_printable("Invalid JSON version field")?,
+    };
+
+    if version > T::current_json_version() {
+        return Err(SqliteDatabaseError::SerdeDeserialize)
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!(
+                    "JSON data version {} is newer than the current version {}",
+                    version,
+                    T::current_json_version()
+                )
+            });
+    }
+
+    for (i, migration) in T::MIGRATIONS.iter().enumerate().skip(version as usize) {
+        if let Err(e) = migration(&mut object) {
+            return Err(SqliteDatabaseError::JsonMigration)
+                .into_report()
+                .attach_printable(format!("JSON migration from version {} failed: {}", i, e));
+        }
+    }
+
+    serde_json::from_value(Value::Object(object)).into_error(SqliteDatabaseError::SerdeDeserialize)
+}
+
+/// Serialize data with the current version field.
+pub fn to_versioned_json<T: JsonMigrations + Serialize>(
+    data: &T,
+) -> Result<String, SqliteDatabaseError> {
+    let mut object =
+        match serde_json::to_value(data).into_error(SqliteDatabaseError::SerdeSerialize)? {
+            Value::Object(object) => object,
+            _ => {
+                return Err(SqliteDatabaseError::SerdeSerialize)
+                    .into_report()
+                    .attach_printable("Versioned JSON data is not an object")
+            }
+        };
+
+    object.insert(
+        JSON_VERSION_FIELD.to_string(),
+        Value::from(T::current_json_version()),
+    );
+
+    serde_json::to_string(&object).into_error(SqliteDatabaseError::SerdeSerialize)
+}
+
+// Registered migrations
+
+impl JsonMigrations for Account {
+    const MIGRATIONS: &'static [JsonMigrationFn] = &[];
+}
+
+impl JsonMigrations for AccountSetup {
+    const MIGRATIONS: &'static [JsonMigrationFn] = &[];
+}
+
+impl JsonMigrations for CalculatorSettings {
+    const MIGRATIONS: &'static [JsonMigrationFn] = &[];
+}
+
+impl JsonMigrations for NotificationSettings {
+    const MIGRATIONS: &'static [JsonMigrationFn] = &[
+        add_scheduled_calculation_completed_notification_setting,
+        add_new_session_created_notification_setting,
+    ];
+}
+
+fn add_scheduled_calculation_completed_notification_setting(
+    settings: &mut Map<String, Value>,
+) -> std::result::Result<(), String> {
+    let delivery =
+        serde_json::to_value(NotificationDelivery::default()).map_err(|e| e.to_string())?;
+    settings.insert("scheduled_calculation_completed".to_string(), delivery);
+    Ok(())
+}
+
+fn add_new_session_created_notification_setting(
+    settings: &mut Map<String, Value>,
+) -> std::result::Result<(), String> {
+    let delivery =
+        serde_json::to_value(NotificationDelivery::default()).map_err(|e| e.to_string())?;
+    settings.insert("new_session_created".to_string(), delivery);
+    Ok(())
+}
@@ -0,0 +1,299 @@
+//! Offline SQLite schema migrations
+//!
+//! Server runs migrations only when the database is created. Migrations for
+//! an existing database are run with the `migrate` command when the server
+//! is not running. Dry run applies pending migrations to a snapshot of the
+//! database, so the schema diff is the real result of the migrations.
+
+use std::path::Path;
+
+use error_stack::{Report, Result, ResultExt};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    sqlite::{self, SqliteConnectOptions, SqlitePoolOptions},
+    Row, SqlitePool,
+};
+use tracing::info;
+
+use crate::{config::Config, utils::IntoReportExt};
+
+use super::{
+    sqlite::{create_snapshot, DATABASE_FILE_NAME},
+    DatabaseDir, DatabaseRoot,
+};
+
+const DRY_RUN_TMP_FILE_NAME: &str = "migrate_dry_run_tmp.db";
+
+/// Migrations from the `migrations` directory.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// History database migrations from the `migrations_history` directory.
+/// These are not run with the `migrate` command.
+pub static HISTORY_MIGRATOR: Migrator = sqlx::migrate!("./migrations_history");
+
+/// PostgreSQL backend migrations from the `migrations_postgres` directory.
+/// These are run when the server starts and not with the `migrate` command.
+pub static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations_postgres");
+
+#[derive(thiserror::Error, Debug)]
+pub enum MigrateError {
+    #[error("Database initialization error")]
+    Init,
+    #[error("Database file does not exist")]
+    DatabaseMissing,
+    #[error("Connecting to SQLite database failed")]
+    Connect,
+    #[error("Creating database snapshot failed")]
+    Snapshot,
+    #[error("Reading applied migrations failed")]
+    AppliedMigrations,
+    #[error("Reading database schema failed")]
+    Schema,
+    #[error("Running migrations failed")]
+    Migrate,
+    #[error("Database has pending migrations")]
+    PendingMigrations,
+    #[error("File error")]
+    File,
+}
+
+/// Migration which is not yet applied to the database.
+#[derive(Debug, Clone)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Table, index, view or trigger from `sqlite_master`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaObject {
+    pub object_type: String,
+    pub name: String,
+    pub sql: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SchemaChange {
+    Added(SchemaObject),
+    Removed(SchemaObject),
+    Changed {
+        old: SchemaObject,
+        new: SchemaObject,
+    },
+}
+
+/// Open database in the current data directory. Server must not be
+/// running.
+async fn open_current(config: &Config) -> Result<(DatabaseRoot, SqlitePool), MigrateError> {
+    let root = DatabaseRoot::new(config.database_dir(), config.disk_space_limits())
+        .change_context(MigrateError::Init)?;
+    let db_path = root.current_ref().path().join(DATABASE_FILE_NAME);
+    if !db_path.exists() {
+        return Err(Report::new(MigrateError::DatabaseMissing)
+            .attach_printable(db_path.display().to_string()));
+    }
+    let pool = open(&db_path).await?;
+    Ok((root, pool))
+}
+
+async fn open(db_path: &Path) -> Result<SqlitePool, MigrateError> {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(
+            SqliteConnectOptions::new()
+                .filename(db_path)
+                .create_if_missing(false)
+                .foreign_keys(true)
+                .journal_mode(sqlite::SqliteJournalMode::Wal),
+        )
+        .await
+        .into_error(MigrateError::Connect)
+}
+
+/// Migrations which are not applied to the database. The migrations table
+/// is created if it does not exist, so do not use this with the live
+/// database in dry run mode.
+async fn pending_migrations(pool: &SqlitePool) -> Result<Vec<PendingMigration>, MigrateError> {
+    let mut connection = pool.acquire().await.into_error(MigrateError::Connect)?;
+    connection
+        .ensure_migrations_table()
+        .await
+        .into_error(MigrateError::AppliedMigrations)?;
+    let applied = connection
+        .list_applied_migrations()
+        .await
+        .into_error(MigrateError::AppliedMigrations)?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .map(|m| PendingMigration {
+            version: m.version,
+            description: m.description.to_string(),
+        })
+        .collect())
+}
+
+async fn read_schema(pool: &SqlitePool) -> Result<Vec<SchemaObject>, MigrateError> {
+    sqlx::query(
+        r#"
+        SELECT type, name, sql
+        FROM sqlite_master
+        WHERE name NOT LIKE 'sqlite_%' AND name != '_sqlx_migrations'
+        ORDER BY type, name
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .into_error(MigrateError::Schema)?
+    .into_iter()
+    .map(|row| {
+        Ok(SchemaObject {
+            object_type: row.try_get(0).into_error(MigrateError::Schema)?,
+            name: row.try_get(1).into_error(MigrateError::Schema)?,
+            sql: row.try_get(2).into_error(MigrateError::Schema)?,
+        })
+    })
+    .collect()
+}
+
+fn schema_diff(old: &[SchemaObject], new: &[SchemaObject]) -> Vec<SchemaChange> {
+    let same_object =
+        |a: &SchemaObject, b: &SchemaObject| a.object_type == b.object_type && a.name == b.name;
+
+    let mut changes = vec![];
+    for old_object in old {
+        match new.iter().find(|n| same_object(old_object, n)) {
+            Some(new_object) if new_object.sql != old_object.sql => {
+                changes.push(SchemaChange::Changed {
+                    old: old_object.clone(),
+                    new: new_object.clone(),
+                })
+            }
+            Some(_) => (),
+            None => changes.push(SchemaChange::Removed(old_object.clone())),
+        }
+    }
+    for new_object in new {
+        if !old.iter().any(|o| same_object(o, new_object)) {
+            changes.push(SchemaChange::Added(new_object.clone()));
+        }
+    }
+    changes
+}
+
+/// Print pending migrations and schema diff without modifying the current
+/// database.
+pub async fn migrate_dry_run(config: &Config) -> Result<(), MigrateError> {
+    let (root, pool) = open_current(config).await?;
+
+    let tmp_file = root.dir(DatabaseDir::Journals).join(DRY_RUN_TMP_FILE_NAME);
+    if tmp_file.exists() {
+        tokio::fs::remove_file(&tmp_file)
+            .await
+            .into_error(MigrateError::File)?;
+    }
+    create_snapshot(&pool, &tmp_file)
+        .await
+        .change_context(MigrateError::Snapshot)?;
+    pool.close().await;
+
+    let snapshot = open(&tmp_file).await?;
+    let result = dry_run_with_snapshot(&snapshot).await;
+    snapshot.close().await;
+    tokio::fs::remove_file(&tmp_file)
+        .await
+        .into_error(MigrateError::File)?;
+
+    let (pending, changes) = result?;
+
+    if pending.is_empty() {
+        info!("No pending migrations");
+        return Ok(());
+    }
+
+    info!("Pending migrations:");
+    for migration in &pending {
+        info!("  {} {}", migration.version, migration.description);
+    }
+
+    info!("Schema diff:");
+    for change in &changes {
+        match change {
+            SchemaChange::Added(object) => {
+                info!("+ {} {}\n{}", object.object_type, object.name, sql(object))
+            }
+            SchemaChange::Removed(object) => {
+                info!("- {} {}", object.object_type, object.name)
+            }
+            SchemaChange::Changed { old, new } => info!(
+                "~ {} {}\nold:\n{}\nnew:\n{}",
+                new.object_type,
+                new.name,
+                sql(old),
+                sql(new)
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn sql(object: &SchemaObject) -> &str {
+    object.sql.as_deref().unwrap_or_default()
+}
+
+async fn dry_run_with_snapshot(
+    snapshot: &SqlitePool,
+) -> Result<(Vec<PendingMigration>, Vec<SchemaChange>), MigrateError> {
+    let pending = pending_migrations(snapshot).await?;
+    let old_schema = read_schema(snapshot).await?;
+    MIGRATOR
+        .run(snapshot)
+        .await
+        .into_error(MigrateError::Migrate)?;
+    let new_schema = read_schema(snapshot).await?;
+    Ok((pending, schema_diff(&old_schema, &new_schema)))
+}
+
+/// Apply pending migrations to the current database.
+pub async fn migrate_apply(config: &Config) -> Result<(), MigrateError> {
+    let (_, pool) = open_current(config).await?;
+
+    let pending = pending_migrations(&pool).await?;
+    if pending.is_empty() {
+        info!("No pending migrations");
+    } else {
+        MIGRATOR
+            .run(&pool)
+            .await
+            .into_error(MigrateError::Migrate)?;
+        for migration in &pending {
+            info!(
+                "Migration {} {} applied",
+                migration.version, migration.description
+            );
+        }
+    }
+
+    pool.close().await;
+    Ok(())
+}
+
+/// Server does not start if there are pending migrations, because the code
+/// expects the newest schema.
+pub async fn check_no_pending_migrations(pool: &SqlitePool) -> Result<(), MigrateError> {
+    let pending = pending_migrations(pool).await?;
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        let versions: Vec<String> = pending.iter().map(|m| m.version.to_string()).collect();
+        Err(
+            Report::new(MigrateError::PendingMigrations).attach_printable(format!(
+                "Pending migrations {}. Run command `migrate --apply` before starting the server.",
+                versions.join(", ")
+            )),
+        )
+    }
+}
@@ -0,0 +1,106 @@
+//! PostgreSQL backend for the current data database
+//!
+//! Queries are checked at runtime, because compile time checked queries use
+//! the SQLite database. Migrations from the `migrations_postgres` directory
+//! run every time the server starts.
+
+pub mod account;
+pub mod calculator;
+
+use error_stack::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{postgres::PgPoolOptions, PgExecutor, PgPool};
+use tracing::info;
+
+use crate::{api::model::AccountIdInternal, utils::IntoReportExt};
+
+use super::{
+    json_migration::{from_versioned_json, to_versioned_json, JsonMigrations},
+    migrate::POSTGRES_MIGRATOR,
+    sqlite::SqliteDatabaseError,
+};
+
+const POSTGRES_MAX_CONNECTIONS: u32 = 16;
+
+pub struct PostgresCloseHandle {
+    pool: PgPool,
+}
+
+impl PostgresCloseHandle {
+    /// Call this before closing the server.
+    pub async fn close(self) {
+        self.pool.close().await
+    }
+}
+
+/// Connection pool for reading and writing. Multiple server instances can
+/// use the same database.
+#[derive(Debug, Clone)]
+pub struct PostgresHandle {
+    pool: PgPool,
+}
+
+impl PostgresHandle {
+    pub async fn new(url: &str) -> Result<(Self, PostgresCloseHandle), SqliteDatabaseError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(POSTGRES_MAX_CONNECTIONS)
+            .connect(url)
+            .await
+            .into_error(SqliteDatabaseError::Connect)?;
+
+        let version: String = sqlx::query_scalar("SHOW server_version")
+            .fetch_one(&pool)
+            .await
+            .into_error(SqliteDatabaseError::Execute)?;
+        info!("PostgreSQL version: {}", version);
+
+        POSTGRES_MIGRATOR
+            .run(&pool)
+            .await
+            .into_error(SqliteDatabaseError::Migrate)?;
+
+        let handle = PostgresHandle { pool: pool.clone() };
+
+        let close_handle = PostgresCloseHandle { pool };
+
+        Ok((handle, close_handle))
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+/// Read versioned JSON. The query has the account row ID as the only
+/// parameter and it returns one text column.
+async fn select_json<T: JsonMigrations + DeserializeOwned>(
+    pool: &PgPool,
+    sql: &'static str,
+    id: AccountIdInternal,
+) -> Result<T, SqliteDatabaseError> {
+    let text: String = sqlx::query_scalar(sql)
+        .bind(id.row_id())
+        .fetch_one(pool)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+    from_versioned_json(&text)
+}
+
+/// Write versioned JSON. The query has the JSON text and the account row ID
+/// as parameters.
+async fn write_json<'e, T: JsonMigrations + Serialize>(
+    executor: impl PgExecutor<'e>,
+    sql: &'static str,
+    data: &T,
+    id: AccountIdInternal,
+) -> Result<(), SqliteDatabaseError> {
+    let data = to_versioned_json(data)?;
+    sqlx::query(sql)
+        .bind(data)
+        .bind(id.row_id())
+        .execute(executor)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+    Ok(())
+}
@@ -0,0 +1,1589 @@
+use async_trait::async_trait;
+use error_stack::Result;
+use futures::stream::BoxStream;
+use sqlx::{Postgres, Transaction};
+use tokio_stream::StreamExt;
+
+use crate::{
+    api::{account::data::AccountSetup, model::*},
+    server::database::{
+        current::{
+            account::write::USED_REFRESH_TOKEN_LIFETIME_SECONDS,
+            traits::{ReadAccountStorage, TransactionWriteAccountStorage, WriteAccountStorage},
+        },
+        json_migration::from_versioned_json,
+        read::ReadResult,
+        sqlite::SqliteDatabaseError,
+        utils::current_unix_time,
+        write::{NoId, WriteResult},
+    },
+    utils::IntoReportExt,
+};
+
+use super::{select_json, write_json, PostgresHandle};
+
+const ACTIVITY_PERIOD_DAY_SECONDS: i64 = 24 * 60 * 60;
+const ACTIVITY_PERIOD_WEEK_SECONDS: i64 = 7 * ACTIVITY_PERIOD_DAY_SECONDS;
+
+fn to_account_id_internal((account_row_id, account_id): (i64, uuid::Uuid)) -> AccountIdInternal {
+    AccountIdInternal {
+        account_id,
+        account_row_id,
+    }
+}
+
+fn parse_service_token_scope(scope: &str) -> Result<ServiceTokenScope, SqliteDatabaseError> {
+    ServiceTokenScope::parse(scope)
+        .ok_or_else(|| error_stack::Report::new(SqliteDatabaseError::DataFormatConversion))
+}
+
+fn parse_login_method(method: &str) -> Result<LoginMethod, SqliteDatabaseError> {
+    LoginMethod::parse(method)
+        .ok_or_else(|| error_stack::Report::new(SqliteDatabaseError::DataFormatConversion))
+}
+
+/// Operation sets are stored as JSON array.
+fn organization_settings(
+    history_retention_days: Option<i64>,
+    operation_sets: Option<String>,
+) -> Result<OrganizationSettings, SqliteDatabaseError> {
+    let operation_sets = match operation_sets {
+        Some(text) => {
+            Some(serde_json::from_str(&text).into_error(SqliteDatabaseError::SerdeDeserialize)?)
+        }
+        None => None,
+    };
+    Ok(OrganizationSettings {
+        history_retention_days,
+        operation_sets,
+    })
+}
+
+/// PostgreSQL implementation of [ReadAccountStorage].
+pub struct PostgresReadAccountCommands<'a> {
+    handle: &'a PostgresHandle,
+}
+
+impl<'a> PostgresReadAccountCommands<'a> {
+    pub fn new(handle: &'a PostgresHandle) -> Self {
+        Self { handle }
+    }
+}
+
+#[async_trait]
+impl ReadAccountStorage for PostgresReadAccountCommands<'_> {
+    fn account_ids_stream(
+        &self,
+    ) -> BoxStream<'_, ReadResult<AccountIdInternal, SqliteDatabaseError, NoId>> {
+        let stream = sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT account_row_id, account_id
+            FROM AccountId
+            "#,
+        )
+        .fetch(self.handle.pool())
+        .map(|result| {
+            result
+                .map(to_account_id_internal)
+                .into_error(SqliteDatabaseError::Fetch)
+                .map_err(|e| e.into())
+        });
+        Box::pin(stream)
+    }
+
+    async fn account_ids_page(
+        &self,
+        offset: i64,
+        limit: i64,
+        region: Option<&str>,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT account_row_id, account_id
+            FROM AccountId
+            WHERE $3::TEXT IS NULL OR region = $3
+            ORDER BY account_row_id
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .bind(region)
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(to_account_id_internal).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_times(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<AccountTimes, SqliteDatabaseError, AccountIdInternal> {
+        sqlx::query_as::<_, (Option<i64>, Option<i64>)>(
+            r#"
+            SELECT created_unix_time, last_login_unix_time
+            FROM AccountId
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|(created_unix_time, last_login_unix_time)| AccountTimes {
+            created_unix_time,
+            last_login_unix_time,
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_region(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<String>, SqliteDatabaseError, AccountIdInternal> {
+        sqlx::query_scalar::<_, Option<String>>(
+            r#"
+            SELECT region
+            FROM AccountId
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_regions(
+        &self,
+    ) -> ReadResult<Vec<RegionRegistrations>, SqliteDatabaseError, NoId> {
+        sqlx::query_as::<_, (Option<String>, i64)>(
+            r#"
+            SELECT region, COUNT(*)
+            FROM AccountId
+            GROUP BY region
+            ORDER BY region
+            "#,
+        )
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|(region, registrations)| RegionRegistrations {
+                    region,
+                    registrations,
+                })
+                .collect()
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn access_token_hash(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, ApiKey> {
+        sqlx::query_scalar::<_, Option<Vec<u8>>>(
+            r#"
+            SELECT api_key_hash
+            FROM ApiKey
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|hash| hash.map(TokenHash::from_bytes))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn access_token_expiration(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<i64>, SqliteDatabaseError, ApiKey> {
+        sqlx::query_scalar::<_, Option<i64>>(
+            r#"
+            SELECT expires_unix_time
+            FROM ApiKey
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_with_refresh_token(
+        &self,
+        token: &RefreshToken,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError, RefreshToken> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id
+            FROM RefreshToken
+            INNER JOIN AccountId ON RefreshToken.account_row_id = AccountId.account_row_id
+            WHERE refresh_token_hash = $1
+            "#,
+        )
+        .bind(token.hash().into_bytes())
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| row.map(to_account_id_internal))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn refresh_token_hash(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, RefreshToken> {
+        sqlx::query_scalar::<_, Option<Vec<u8>>>(
+            r#"
+            SELECT refresh_token_hash
+            FROM RefreshToken
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|hash| hash.map(TokenHash::from_bytes))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn used_refresh_token_generation(
+        &self,
+        id: AccountIdInternal,
+        token: &RefreshToken,
+    ) -> ReadResult<Option<i64>, SqliteDatabaseError, RefreshToken> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT generation
+            FROM UsedRefreshToken
+            WHERE account_row_id = $1 AND refresh_token_hash = $2
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(token.hash().into_bytes())
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn service_tokens(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<ServiceTokenInfo>, SqliteDatabaseError, ServiceTokenInfo> {
+        let rows = sqlx::query_as::<_, (i64, String, String, i64)>(
+            r#"
+            SELECT token_id, name, scope, created_unix_time
+            FROM ServiceToken
+            WHERE account_row_id = $1
+            ORDER BY token_id
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let mut tokens = Vec::with_capacity(rows.len());
+        for (token_id, name, scope, created_unix_time) in rows {
+            tokens.push(ServiceTokenInfo {
+                token_id,
+                name,
+                scope: parse_service_token_scope(&scope)?,
+                created_unix_time,
+            });
+        }
+        Ok(tokens)
+    }
+
+    async fn service_token_hashes(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<(TokenHash, ServiceTokenScope)>, SqliteDatabaseError, ApiKey> {
+        let rows = sqlx::query_as::<_, (Vec<u8>, String)>(
+            r#"
+            SELECT token_hash, scope
+            FROM ServiceToken
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let mut tokens = Vec::with_capacity(rows.len());
+        for (hash, scope) in rows {
+            tokens.push((
+                TokenHash::from_bytes(hash),
+                parse_service_token_scope(&scope)?,
+            ));
+        }
+        Ok(tokens)
+    }
+
+    async fn service_token_hash(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> ReadResult<Option<TokenHash>, SqliteDatabaseError, ApiKey> {
+        sqlx::query_scalar::<_, Vec<u8>>(
+            r#"
+            SELECT token_hash
+            FROM ServiceToken
+            WHERE token_id = $1 AND account_row_id = $2
+            "#,
+        )
+        .bind(token_id.token_id)
+        .bind(id.row_id())
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|hash| hash.map(TokenHash::from_bytes))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn service_token_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, ServiceTokenInfo> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM ServiceToken
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn login_history(
+        &self,
+        id: AccountIdInternal,
+        limit: i64,
+    ) -> ReadResult<Vec<LoginHistoryEntry>, SqliteDatabaseError, LoginHistoryEntry> {
+        let rows = sqlx::query_as::<_, (i64, Option<String>, String)>(
+            r#"
+            SELECT unix_time, ip_address, method
+            FROM LoginHistory
+            WHERE account_row_id = $1
+            ORDER BY login_history_id DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(limit)
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for (unix_time, ip_address, method) in rows {
+            entries.push(LoginHistoryEntry {
+                unix_time,
+                ip_address,
+                method: parse_login_method(&method)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    async fn organization(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Option<Organization>, SqliteDatabaseError, Organization> {
+        sqlx::query_as::<_, (i64, String, i64)>(
+            r#"
+            SELECT org_id, name, created_unix_time
+            FROM Organization
+            WHERE org_id = $1
+            "#,
+        )
+        .bind(org_id.org_id)
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| {
+            row.map(|(org_id, name, created_unix_time)| Organization {
+                org_id,
+                name,
+                created_unix_time,
+            })
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_membership(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<OrganizationMembership>, SqliteDatabaseError, Organization> {
+        sqlx::query_as::<_, (i64, String, i64, bool)>(
+            r#"
+            SELECT
+                Organization.org_id,
+                Organization.name,
+                Organization.created_unix_time,
+                OrganizationMember.is_admin
+            FROM OrganizationMember
+            INNER JOIN Organization ON Organization.org_id = OrganizationMember.org_id
+            WHERE OrganizationMember.account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| {
+            row.map(
+                |(org_id, name, created_unix_time, is_admin)| OrganizationMembership {
+                    organization: Organization {
+                        org_id,
+                        name,
+                        created_unix_time,
+                    },
+                    is_admin,
+                },
+            )
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_members(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Vec<OrganizationMember>, SqliteDatabaseError, Organization> {
+        sqlx::query_as::<_, (uuid::Uuid, bool, i64, Option<i64>, i64)>(
+            r#"
+            SELECT
+                AccountId.account_id,
+                OrganizationMember.is_admin,
+                OrganizationMember.joined_unix_time,
+                AccountActivity.last_activity_unix_time,
+                (
+                    SELECT COUNT(*)
+                    FROM CalculatorHistory
+                    WHERE CalculatorHistory.account_row_id = OrganizationMember.account_row_id
+                )
+            FROM OrganizationMember
+            INNER JOIN AccountId ON AccountId.account_row_id = OrganizationMember.account_row_id
+            LEFT JOIN AccountActivity
+                ON AccountActivity.account_row_id = OrganizationMember.account_row_id
+            WHERE OrganizationMember.org_id = $1
+            ORDER BY OrganizationMember.joined_unix_time, OrganizationMember.account_row_id
+            "#,
+        )
+        .bind(org_id.org_id)
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(
+                    |(
+                        account_id,
+                        is_admin,
+                        joined_unix_time,
+                        last_activity_unix_time,
+                        history_entries,
+                    )| OrganizationMember {
+                        account_id: AccountIdLight::new(account_id),
+                        is_admin,
+                        joined_unix_time,
+                        last_activity_unix_time,
+                        history_entries,
+                    },
+                )
+                .collect()
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_member_ids(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Vec<AccountIdLight>, SqliteDatabaseError, Organization> {
+        sqlx::query_scalar::<_, uuid::Uuid>(
+            r#"
+            SELECT AccountId.account_id
+            FROM OrganizationMember
+            INNER JOIN AccountId ON AccountId.account_row_id = OrganizationMember.account_row_id
+            WHERE OrganizationMember.org_id = $1
+            "#,
+        )
+        .bind(org_id.org_id)
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(AccountIdLight::new).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn organization_settings(
+        &self,
+        org_id: OrganizationId,
+    ) -> ReadResult<Option<OrganizationSettings>, SqliteDatabaseError, Organization> {
+        let row = sqlx::query_as::<_, (Option<i64>, Option<String>)>(
+            r#"
+            SELECT
+                OrganizationSettings.history_retention_days,
+                OrganizationSettings.operation_sets
+            FROM Organization
+            LEFT JOIN OrganizationSettings ON OrganizationSettings.org_id = Organization.org_id
+            WHERE Organization.org_id = $1
+            "#,
+        )
+        .bind(org_id.org_id)
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        match row {
+            Some((history_retention_days, operation_sets)) => Ok(Some(organization_settings(
+                history_retention_days,
+                operation_sets,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn account_organization_settings(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<OrganizationSettings, SqliteDatabaseError, Organization> {
+        let row = sqlx::query_as::<_, (Option<i64>, Option<String>)>(
+            r#"
+            SELECT
+                OrganizationSettings.history_retention_days,
+                OrganizationSettings.operation_sets
+            FROM OrganizationMember
+            INNER JOIN OrganizationSettings ON OrganizationSettings.org_id = OrganizationMember.org_id
+            WHERE OrganizationMember.account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        match row {
+            Some((history_retention_days, operation_sets)) => Ok(organization_settings(
+                history_retention_days,
+                operation_sets,
+            )?),
+            None => Ok(OrganizationSettings::default()),
+        }
+    }
+
+    async fn sign_in_with_info(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<SignInWithInfo, SqliteDatabaseError> {
+        sqlx::query_as::<_, (Option<GoogleAccountId>, Option<AppleAccountId>)>(
+            r#"
+            SELECT google_account_id, apple_account_id
+            FROM SignInWithInfo
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|(google_account_id, apple_account_id)| SignInWithInfo {
+            google_account_id,
+            apple_account_id,
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_data(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<AccountDataInternal, SqliteDatabaseError, AccountDataInternal> {
+        let (
+            account_json,
+            account_setup_json,
+            has_sign_in_with,
+            google_account_id,
+            apple_account_id,
+            calculator_state,
+        ) = sqlx::query_as::<
+            _,
+            (
+                Option<String>,
+                Option<String>,
+                bool,
+                Option<GoogleAccountId>,
+                Option<AppleAccountId>,
+                Option<String>,
+            ),
+        >(
+            r#"
+            SELECT
+                Account.json_text,
+                AccountSetup.json_text,
+                SignInWithInfo.account_row_id IS NOT NULL,
+                SignInWithInfo.google_account_id,
+                SignInWithInfo.apple_account_id,
+                CurrentState.calculation
+            FROM AccountId
+            LEFT JOIN Account ON Account.account_row_id = AccountId.account_row_id
+            LEFT JOIN AccountSetup ON AccountSetup.account_row_id = AccountId.account_row_id
+            LEFT JOIN SignInWithInfo ON SignInWithInfo.account_row_id = AccountId.account_row_id
+            LEFT JOIN CurrentState ON CurrentState.account_row_id = AccountId.account_row_id
+            WHERE AccountId.account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let sign_in_with = if has_sign_in_with {
+            Some(SignInWithInfo {
+                google_account_id,
+                apple_account_id,
+            })
+        } else {
+            None
+        };
+
+        Ok(AccountDataInternal {
+            account: account_json
+                .as_deref()
+                .map(from_versioned_json)
+                .transpose()?,
+            account_setup: account_setup_json
+                .as_deref()
+                .map(from_versioned_json)
+                .transpose()?,
+            sign_in_with,
+            calculator_state: calculator_state.map(|state| CalculatorStateInternal { state }),
+        })
+    }
+
+    async fn get_account_with_google_account_id(
+        &self,
+        google_account_id: GoogleAccountId,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id
+            FROM SignInWithInfo
+            INNER JOIN AccountId on AccountId.account_row_id = SignInWithInfo.account_row_id
+            WHERE google_account_id = $1
+            "#,
+        )
+        .bind(google_account_id)
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| row.map(to_account_id_internal))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn get_account_with_apple_account_id(
+        &self,
+        apple_account_id: AppleAccountId,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id
+            FROM SignInWithInfo
+            INNER JOIN AccountId on AccountId.account_row_id = SignInWithInfo.account_row_id
+            WHERE apple_account_id = $1
+            "#,
+        )
+        .bind(apple_account_id)
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| row.map(to_account_id_internal))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn get_account_with_alias(
+        &self,
+        alias: &AccountAlias,
+    ) -> ReadResult<Option<AccountIdInternal>, SqliteDatabaseError> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id
+            FROM AccountAlias
+            INNER JOIN AccountId on AccountId.account_row_id = AccountAlias.account_row_id
+            WHERE alias = $1
+            "#,
+        )
+        .bind(alias.as_str())
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| row.map(to_account_id_internal))
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_alias(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<AccountAlias>, SqliteDatabaseError> {
+        sqlx::query_scalar::<_, AccountAlias>(
+            r#"
+            SELECT alias
+            FROM AccountAlias
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_optional(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Option<AccountDeletionInfo>, SqliteDatabaseError> {
+        sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT requested_unix_time, delete_after_unix_time
+            FROM AccountDeletion
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| {
+            row.map(
+                |(requested_unix_time, delete_after_unix_time)| AccountDeletionInfo {
+                    requested_unix_time,
+                    delete_after_unix_time,
+                },
+            )
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn expired_account_deletions(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id
+            FROM AccountDeletion
+            INNER JOIN AccountId on AccountId.account_row_id = AccountDeletion.account_row_id
+            WHERE delete_after_unix_time <= $1
+            "#,
+        )
+        .bind(unix_time)
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(to_account_id_internal).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn stale_accounts(
+        &self,
+        inactive_before: i64,
+        after_row_id: i64,
+        limit: i64,
+    ) -> ReadResult<Vec<AccountIdInternal>, SqliteDatabaseError, NoId> {
+        sqlx::query_as::<_, (i64, uuid::Uuid)>(
+            r#"
+            SELECT AccountId.account_row_id, AccountId.account_id
+            FROM AccountId
+            LEFT JOIN AccountActivity on AccountActivity.account_row_id = AccountId.account_row_id
+            WHERE AccountId.account_row_id > $1
+                AND COALESCE(
+                    AccountActivity.last_activity_unix_time,
+                    AccountId.last_login_unix_time,
+                    AccountId.created_unix_time
+                ) < $2
+                AND NOT EXISTS (
+                    SELECT 1 FROM AccountDeletion
+                    WHERE AccountDeletion.account_row_id = AccountId.account_row_id
+                )
+            ORDER BY AccountId.account_row_id
+            LIMIT $3
+            "#,
+        )
+        .bind(after_row_id)
+        .bind(inactive_before)
+        .bind(limit)
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(to_account_id_internal).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn account_report(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<AccountReport, SqliteDatabaseError, NoId> {
+        let registrations_per_provider: Vec<ProviderRegistrations> =
+            sqlx::query_as::<_, (String, i64)>(
+                r#"
+                SELECT
+                    CASE
+                        WHEN SignInWithInfo.google_account_id IS NOT NULL THEN 'google'
+                        WHEN SignInWithInfo.apple_account_id IS NOT NULL THEN 'apple'
+                        ELSE 'none'
+                    END,
+                    COUNT(*)
+                FROM AccountId
+                LEFT JOIN SignInWithInfo
+                    ON SignInWithInfo.account_row_id = AccountId.account_row_id
+                GROUP BY 1
+                ORDER BY 1
+                "#,
+            )
+            .fetch_all(self.handle.pool())
+            .await
+            .into_error(SqliteDatabaseError::Fetch)?
+            .into_iter()
+            .map(|(provider, registrations)| ProviderRegistrations {
+                provider,
+                registrations,
+            })
+            .collect();
+
+        // Empty JSON text is skipped like with SQLite json_valid.
+        let setup_completed = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM Account
+            WHERE CASE
+                WHEN json_text LIKE '{%' THEN json_text::jsonb ->> 'state'
+            END != 'InitialSetup'
+            "#,
+        )
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let day_start = unix_time - ACTIVITY_PERIOD_DAY_SECONDS;
+        let week_start = unix_time - ACTIVITY_PERIOD_WEEK_SECONDS;
+        let (daily, weekly) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE last_activity_unix_time >= $1),
+                COUNT(*) FILTER (WHERE last_activity_unix_time >= $2)
+            FROM AccountActivity
+            "#,
+        )
+        .bind(day_start)
+        .bind(week_start)
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?;
+
+        let registrations: i64 = registrations_per_provider
+            .iter()
+            .map(|p| p.registrations)
+            .sum();
+        let setup_completion_rate = if registrations == 0 {
+            0.0
+        } else {
+            setup_completed as f64 / registrations as f64
+        };
+
+        let registrations_per_region = self.account_regions().await?;
+
+        Ok(AccountReport {
+            generated_unix_time: unix_time,
+            registrations,
+            registrations_per_provider,
+            registrations_per_region,
+            setup_completed,
+            setup_completion_rate,
+            daily_active_accounts: daily,
+            weekly_active_accounts: weekly,
+        })
+    }
+
+    async fn account_json(&self, id: AccountIdInternal) -> Result<Account, SqliteDatabaseError> {
+        select_json(
+            self.handle.pool(),
+            r#"
+            SELECT json_text
+            FROM Account
+            WHERE account_row_id = $1
+            "#,
+            id,
+        )
+        .await
+    }
+
+    async fn account_setup_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<AccountSetup, SqliteDatabaseError> {
+        select_json(
+            self.handle.pool(),
+            r#"
+            SELECT json_text
+            FROM AccountSetup
+            WHERE account_row_id = $1
+            "#,
+            id,
+        )
+        .await
+    }
+
+    async fn notification_settings_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<NotificationSettings, SqliteDatabaseError> {
+        select_json(
+            self.handle.pool(),
+            r#"
+            SELECT json_text
+            FROM NotificationSettings
+            WHERE account_row_id = $1
+            "#,
+            id,
+        )
+        .await
+    }
+}
+
+/// PostgreSQL implementation of [WriteAccountStorage].
+pub struct PostgresWriteAccountCommands<'a> {
+    handle: &'a PostgresHandle,
+}
+
+impl<'a> PostgresWriteAccountCommands<'a> {
+    pub fn new(handle: &'a PostgresHandle) -> Self {
+        Self { handle }
+    }
+}
+
+#[async_trait]
+impl WriteAccountStorage for PostgresWriteAccountCommands<'_> {
+    async fn update_sign_in_with_info(
+        &self,
+        id: AccountIdInternal,
+        sign_in_with: &SignInWithInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        sqlx::query(
+            r#"
+            UPDATE SignInWithInfo
+            SET google_account_id = $1, apple_account_id = $2
+            WHERE account_row_id = $3
+            "#,
+        )
+        .bind(sign_in_with.google_account_id.clone())
+        .bind(sign_in_with.apple_account_id.clone())
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_account_activity(
+        &self,
+        id: AccountIdInternal,
+        last_activity_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal> {
+        sqlx::query(
+            r#"
+            INSERT INTO AccountActivity (last_activity_unix_time, account_row_id)
+            VALUES ($1, $2)
+            ON CONFLICT (account_row_id) DO UPDATE
+            SET last_activity_unix_time = excluded.last_activity_unix_time
+            "#,
+        )
+        .bind(last_activity_unix_time)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_last_login(
+        &self,
+        id: AccountIdInternal,
+        last_login_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal> {
+        sqlx::query(
+            r#"
+            UPDATE AccountId
+            SET last_login_unix_time = $1
+            WHERE account_row_id = $2
+            "#,
+        )
+        .bind(last_login_unix_time)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_account_deletion(
+        &self,
+        id: AccountIdInternal,
+        info: AccountDeletionInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountDeletionInfo> {
+        sqlx::query(
+            r#"
+            INSERT INTO AccountDeletion (requested_unix_time, delete_after_unix_time, account_row_id)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(info.requested_unix_time)
+        .bind(info.delete_after_unix_time)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountDeletionInfo> {
+        sqlx::query(
+            r#"
+            DELETE FROM AccountDeletion
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_service_token(
+        &self,
+        id: AccountIdInternal,
+        token: &ApiKey,
+        new: &NewServiceToken,
+        created_unix_time: i64,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewServiceToken> {
+        let token_id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO ServiceToken (account_row_id, token_hash, name, scope, created_unix_time)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING token_id
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(token.hash().into_bytes())
+        .bind(new.name.as_str())
+        .bind(new.scope.as_str())
+        .bind(created_unix_time)
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(token_id)
+    }
+
+    async fn delete_service_token(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> WriteResult<bool, SqliteDatabaseError, ServiceTokenInfo> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM ServiceToken
+            WHERE token_id = $1 AND account_row_id = $2
+            "#,
+        )
+        .bind(token_id.token_id)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_login_history(
+        &self,
+        id: AccountIdInternal,
+        method: LoginMethod,
+        ip_address: Option<&str>,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, LoginHistoryEntry> {
+        sqlx::query(
+            r#"
+            INSERT INTO LoginHistory (account_row_id, unix_time, ip_address, method)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(unix_time)
+        .bind(ip_address)
+        .bind(method.as_str())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_organization(
+        &self,
+        org: &NewOrganization,
+        created_unix_time: i64,
+    ) -> WriteResult<i64, SqliteDatabaseError, Organization> {
+        let org_id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO Organization (name, created_unix_time)
+            VALUES ($1, $2)
+            RETURNING org_id
+            "#,
+        )
+        .bind(org.name.as_str())
+        .bind(created_unix_time)
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(org_id)
+    }
+
+    async fn upsert_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+        is_admin: bool,
+        joined_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, OrganizationMember> {
+        sqlx::query(
+            r#"
+            INSERT INTO OrganizationMember (account_row_id, org_id, is_admin, joined_unix_time)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (account_row_id) DO UPDATE
+            SET
+                joined_unix_time = CASE
+                    WHEN OrganizationMember.org_id = excluded.org_id
+                        THEN OrganizationMember.joined_unix_time
+                    ELSE excluded.joined_unix_time
+                END,
+                org_id = excluded.org_id,
+                is_admin = excluded.is_admin
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(org_id.org_id)
+        .bind(is_admin)
+        .bind(joined_unix_time)
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn upsert_organization_settings(
+        &self,
+        org_id: OrganizationId,
+        settings: &OrganizationSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, OrganizationSettings> {
+        // Operation sets are stored as JSON array.
+        let operation_sets = match &settings.operation_sets {
+            Some(sets) => {
+                Some(serde_json::to_string(sets).into_error(SqliteDatabaseError::SerdeSerialize)?)
+            }
+            None => None,
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO OrganizationSettings (org_id, history_retention_days, operation_sets)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (org_id) DO UPDATE
+            SET
+                history_retention_days = excluded.history_retention_days,
+                operation_sets = excluded.operation_sets
+            "#,
+        )
+        .bind(org_id.org_id)
+        .bind(settings.history_retention_days)
+        .bind(operation_sets)
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+    ) -> WriteResult<bool, SqliteDatabaseError, OrganizationMember> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM OrganizationMember
+            WHERE org_id = $1 AND account_row_id = $2
+            "#,
+        )
+        .bind(org_id.org_id)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Other tables are removed with the AccountId row using the foreign
+    // key cascade.
+    async fn delete_account(
+        &self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountIdInternal> {
+        sqlx::query(
+            r#"
+            DELETE FROM AccountId
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_account_json(
+        &self,
+        id: AccountIdInternal,
+        account: &Account,
+    ) -> Result<(), SqliteDatabaseError> {
+        write_json(
+            self.handle.pool(),
+            r#"
+            UPDATE Account
+            SET json_text = $1
+            WHERE account_row_id = $2
+            "#,
+            account,
+            id,
+        )
+        .await
+    }
+
+    async fn update_account_setup_json(
+        &self,
+        id: AccountIdInternal,
+        account_setup: &AccountSetup,
+    ) -> Result<(), SqliteDatabaseError> {
+        write_json(
+            self.handle.pool(),
+            r#"
+            UPDATE AccountSetup
+            SET json_text = $1
+            WHERE account_row_id = $2
+            "#,
+            account_setup,
+            id,
+        )
+        .await
+    }
+
+    async fn update_notification_settings_json(
+        &self,
+        id: AccountIdInternal,
+        settings: &NotificationSettings,
+    ) -> Result<(), SqliteDatabaseError> {
+        write_json(
+            self.handle.pool(),
+            r#"
+            UPDATE NotificationSettings
+            SET json_text = $1
+            WHERE account_row_id = $2
+            "#,
+            settings,
+            id,
+        )
+        .await
+    }
+}
+
+/// PostgreSQL implementation of [TransactionWriteAccountStorage].
+pub struct PostgresTransactionWriteAccountCommands<'t> {
+    transaction: &'t mut Transaction<'static, Postgres>,
+}
+
+impl<'t> PostgresTransactionWriteAccountCommands<'t> {
+    pub fn new(transaction: &'t mut Transaction<'static, Postgres>) -> Self {
+        Self { transaction }
+    }
+}
+
+#[async_trait]
+impl TransactionWriteAccountStorage for PostgresTransactionWriteAccountCommands<'_> {
+    async fn store_account_id(
+        &mut self,
+        id: AccountIdLight,
+        region: Option<&str>,
+    ) -> WriteResult<AccountIdInternal, SqliteDatabaseError, AccountIdLight> {
+        let id = id.as_uuid();
+        let account_row_id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO AccountId (account_id, created_unix_time, region)
+            VALUES ($1, $2, $3)
+            RETURNING account_row_id
+            "#,
+        )
+        .bind(id)
+        .bind(current_unix_time())
+        .bind(region)
+        .fetch_one(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(AccountIdInternal {
+            account_id: id,
+            account_row_id,
+        })
+    }
+
+    async fn store_api_key(
+        &mut self,
+        id: AccountIdInternal,
+        api_key: Option<ApiKey>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        sqlx::query(
+            r#"
+            INSERT INTO ApiKey (api_key_hash, account_row_id)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(api_key.map(|k| k.hash().into_bytes()))
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn store_refresh_token(
+        &mut self,
+        id: AccountIdInternal,
+        refresh_token: Option<RefreshToken>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        sqlx::query(
+            r#"
+            INSERT INTO RefreshToken (refresh_token_hash, account_row_id)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(refresh_token.map(|t| t.hash().into_bytes()))
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn store_account(
+        &mut self,
+        id: AccountIdInternal,
+        account: &Account,
+    ) -> WriteResult<(), SqliteDatabaseError, Account> {
+        write_json(
+            &mut *self.transaction,
+            r#"
+            INSERT INTO Account (json_text, account_row_id)
+            VALUES ($1, $2)
+            "#,
+            account,
+            id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn store_account_setup(
+        &mut self,
+        id: AccountIdInternal,
+        account: &AccountSetup,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountSetup> {
+        write_json(
+            &mut *self.transaction,
+            r#"
+            INSERT INTO AccountSetup (json_text, account_row_id)
+            VALUES ($1, $2)
+            "#,
+            account,
+            id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn store_notification_settings(
+        &mut self,
+        id: AccountIdInternal,
+        settings: &NotificationSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, NotificationSettings> {
+        write_json(
+            &mut *self.transaction,
+            r#"
+            INSERT INTO NotificationSettings (json_text, account_row_id)
+            VALUES ($1, $2)
+            "#,
+            settings,
+            id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn store_sign_in_with_info(
+        &mut self,
+        id: AccountIdInternal,
+        sign_in_with_info: &SignInWithInfo,
+    ) -> WriteResult<(), SqliteDatabaseError, SignInWithInfo> {
+        sqlx::query(
+            r#"
+            INSERT INTO SignInWithInfo (google_account_id, apple_account_id, account_row_id)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(sign_in_with_info.google_account_id.clone())
+        .bind(sign_in_with_info.apple_account_id.clone())
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn store_account_alias(
+        &mut self,
+        id: AccountIdInternal,
+        alias: &AccountAlias,
+    ) -> WriteResult<(), SqliteDatabaseError, AccountAlias> {
+        sqlx::query(
+            r#"
+            INSERT INTO AccountAlias (account_row_id, alias)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(alias.as_str())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_api_key(
+        &mut self,
+        id: AccountIdInternal,
+        api_key: Option<&ApiKey>,
+        expires_unix_time: Option<i64>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        sqlx::query(
+            r#"
+            UPDATE ApiKey
+            SET api_key_hash = $1, expires_unix_time = $2
+            WHERE account_row_id = $3
+            "#,
+        )
+        .bind(api_key.map(|k| k.hash().into_bytes()))
+        .bind(expires_unix_time)
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn save_used_refresh_token(
+        &mut self,
+        id: AccountIdInternal,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, RefreshToken> {
+        sqlx::query(
+            r#"
+            INSERT INTO UsedRefreshToken (account_row_id, refresh_token_hash, generation, used_unix_time)
+            SELECT account_row_id, refresh_token_hash, refresh_token_generation, $1
+            FROM RefreshToken
+            WHERE account_row_id = $2 AND refresh_token_hash IS NOT NULL
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(unix_time)
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM UsedRefreshToken
+            WHERE account_row_id = $1 AND used_unix_time < $2
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(unix_time - USED_REFRESH_TOKEN_LIFETIME_SECONDS)
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_refresh_token(
+        &mut self,
+        id: AccountIdInternal,
+        refresh_token: Option<&RefreshToken>,
+    ) -> WriteResult<(), SqliteDatabaseError, ApiKey> {
+        sqlx::query(
+            r#"
+            UPDATE RefreshToken
+            SET refresh_token_hash = $1, refresh_token_generation = refresh_token_generation + 1
+            WHERE account_row_id = $2
+            "#,
+        )
+        .bind(refresh_token.map(|t| t.hash().into_bytes()))
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+}
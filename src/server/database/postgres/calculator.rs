@@ -0,0 +1,686 @@
+use async_trait::async_trait;
+use error_stack::Result;
+use sqlx::{Postgres, Transaction};
+
+use crate::{
+    api::model::*,
+    server::database::{
+        current::traits::{
+            ReadCalculatorStorage, TransactionWriteCalculatorStorage, WriteCalculatorStorage,
+        },
+        read::ReadResult,
+        sqlite::SqliteDatabaseError,
+        write::{NoId, WriteResult},
+    },
+    utils::IntoReportExt,
+};
+
+use super::{select_json, write_json, PostgresHandle};
+
+fn to_calculation_schedule(
+    (schedule_id, expression, next_run_unix_time, repeat_interval_seconds): (
+        i64,
+        String,
+        i64,
+        Option<i64>,
+    ),
+) -> CalculationSchedule {
+    CalculationSchedule {
+        schedule_id,
+        expression,
+        next_run_unix_time,
+        repeat_interval_seconds,
+    }
+}
+
+fn to_calculator_slot(
+    (slot_name, state, updated_unix_time): (String, String, i64),
+) -> CalculatorSlot {
+    CalculatorSlot {
+        slot_name,
+        state,
+        updated_unix_time,
+    }
+}
+
+/// PostgreSQL implementation of [ReadCalculatorStorage].
+pub struct PostgresReadCalculatorCommands<'a> {
+    handle: &'a PostgresHandle,
+}
+
+impl<'a> PostgresReadCalculatorCommands<'a> {
+    pub fn new(handle: &'a PostgresHandle) -> Self {
+        Self { handle }
+    }
+}
+
+#[async_trait]
+impl ReadCalculatorStorage for PostgresReadCalculatorCommands<'_> {
+    async fn calculation_schedules(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<CalculationSchedule>, SqliteDatabaseError, CalculationSchedule> {
+        sqlx::query_as::<_, (i64, String, i64, Option<i64>)>(
+            r#"
+            SELECT schedule_id, expression, next_run_unix_time, repeat_interval_seconds
+            FROM CalculationSchedule
+            WHERE account_row_id = $1
+            ORDER BY schedule_id
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(to_calculation_schedule).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculation_schedule_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, CalculationSchedule> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM CalculationSchedule
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculator_slots(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<CalculatorSlot>, SqliteDatabaseError, CalculatorSlot> {
+        sqlx::query_as::<_, (String, String, i64)>(
+            r#"
+            SELECT slot_name, state, updated_unix_time
+            FROM CalculatorSlot
+            WHERE account_row_id = $1
+            ORDER BY slot_name
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(to_calculator_slot).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculator_slot_count(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<i64, SqliteDatabaseError, CalculatorSlot> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM CalculatorSlot
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn history_entry(
+        &self,
+        id: AccountIdInternal,
+        history_id: HistoryId,
+    ) -> ReadResult<Option<HistoryEntryInternal>, SqliteDatabaseError, HistoryEntryInternal> {
+        sqlx::query_as::<_, (i64, String, Option<f64>, Option<String>, i64)>(
+            r#"
+            SELECT history_id, expression, result, error, unix_time
+            FROM CalculatorHistory
+            WHERE account_row_id = $1 AND history_id = $2
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(history_id.history_id)
+        .fetch_optional(self.handle.pool())
+        .await
+        .map(|row| {
+            row.map(
+                |(history_id, expression, result, error, unix_time)| HistoryEntryInternal {
+                    history_id,
+                    expression,
+                    result,
+                    error,
+                    unix_time,
+                },
+            )
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn history_entries(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<HistoryEntryInternal>, SqliteDatabaseError, HistoryEntryInternal> {
+        sqlx::query_as::<_, (i64, String, Option<f64>, Option<String>, i64)>(
+            r#"
+            SELECT history_id, expression, result, error, unix_time
+            FROM CalculatorHistory
+            WHERE account_row_id = $1
+            ORDER BY history_id
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(
+                    |(history_id, expression, result, error, unix_time)| HistoryEntryInternal {
+                        history_id,
+                        expression,
+                        result,
+                        error,
+                        unix_time,
+                    },
+                )
+                .collect()
+        })
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn calculator_state_readers(
+        &self,
+        id: AccountIdInternal,
+    ) -> ReadResult<Vec<AccountIdLight>, SqliteDatabaseError, CalculatorStateShare> {
+        sqlx::query_scalar::<_, uuid::Uuid>(
+            r#"
+            SELECT AccountId.account_id
+            FROM CalculatorStateShare
+            INNER JOIN AccountId
+                ON CalculatorStateShare.reader_account_row_id = AccountId.account_row_id
+            WHERE CalculatorStateShare.owner_account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_all(self.handle.pool())
+        .await
+        .map(|rows| rows.into_iter().map(AccountIdLight::new).collect())
+        .into_error(SqliteDatabaseError::Fetch)
+        .map_err(|e| e.into())
+    }
+
+    async fn due_calculation_schedules(
+        &self,
+        unix_time: i64,
+    ) -> ReadResult<Vec<(AccountIdInternal, CalculationSchedule)>, SqliteDatabaseError, NoId> {
+        let schedules = sqlx::query_as::<_, (i64, String, i64, Option<i64>, i64, uuid::Uuid)>(
+            r#"
+            SELECT
+                CalculationSchedule.schedule_id,
+                CalculationSchedule.expression,
+                CalculationSchedule.next_run_unix_time,
+                CalculationSchedule.repeat_interval_seconds,
+                AccountId.account_row_id,
+                AccountId.account_id
+            FROM CalculationSchedule
+            INNER JOIN AccountId
+                ON CalculationSchedule.account_row_id = AccountId.account_row_id
+            WHERE CalculationSchedule.next_run_unix_time <= $1
+            "#,
+        )
+        .bind(unix_time)
+        .fetch_all(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Fetch)?
+        .into_iter()
+        .map(
+            |(
+                schedule_id,
+                expression,
+                next_run_unix_time,
+                repeat_interval_seconds,
+                account_row_id,
+                account_id,
+            )| {
+                (
+                    AccountIdInternal {
+                        account_id,
+                        account_row_id,
+                    },
+                    CalculationSchedule {
+                        schedule_id,
+                        expression,
+                        next_run_unix_time,
+                        repeat_interval_seconds,
+                    },
+                )
+            },
+        )
+        .collect();
+
+        Ok(schedules)
+    }
+
+    async fn calculator_settings_json(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<CalculatorSettings, SqliteDatabaseError> {
+        select_json(
+            self.handle.pool(),
+            r#"
+            SELECT json_text
+            FROM CalculatorSettings
+            WHERE account_row_id = $1
+            "#,
+            id,
+        )
+        .await
+    }
+
+    async fn calculator_state(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<CalculatorStateInternal, SqliteDatabaseError> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT calculation
+            FROM CurrentState
+            WHERE account_row_id = $1
+            "#,
+        )
+        .bind(id.row_id())
+        .fetch_one(self.handle.pool())
+        .await
+        .map(|state| CalculatorStateInternal { state })
+        .into_error(SqliteDatabaseError::Fetch)
+    }
+}
+
+/// PostgreSQL implementation of [WriteCalculatorStorage].
+pub struct PostgresWriteCalculatorCommands<'a> {
+    handle: &'a PostgresHandle,
+}
+
+impl<'a> PostgresWriteCalculatorCommands<'a> {
+    pub fn new(handle: &'a PostgresHandle) -> Self {
+        Self { handle }
+    }
+}
+
+#[async_trait]
+impl WriteCalculatorStorage for PostgresWriteCalculatorCommands<'_> {
+    async fn insert_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule: &NewCalculationSchedule,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewCalculationSchedule> {
+        let schedule_id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO CalculationSchedule
+                (account_row_id, expression, next_run_unix_time, repeat_interval_seconds)
+            VALUES ($1, $2, $3, $4)
+            RETURNING schedule_id
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(schedule.expression.as_str())
+        .bind(schedule.run_at_unix_time)
+        .bind(schedule.repeat_interval_seconds)
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(schedule_id)
+    }
+
+    async fn update_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+        schedule: &NewCalculationSchedule,
+    ) -> WriteResult<bool, SqliteDatabaseError, NewCalculationSchedule> {
+        let result = sqlx::query(
+            r#"
+            UPDATE CalculationSchedule
+            SET expression = $1, next_run_unix_time = $2, repeat_interval_seconds = $3
+            WHERE schedule_id = $4 AND account_row_id = $5
+            "#,
+        )
+        .bind(schedule.expression.as_str())
+        .bind(schedule.run_at_unix_time)
+        .bind(schedule.repeat_interval_seconds)
+        .bind(schedule_id.schedule_id)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_calculation_schedule_next_run(
+        &self,
+        schedule_id: ScheduleId,
+        next_run_unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculationSchedule> {
+        sqlx::query(
+            r#"
+            UPDATE CalculationSchedule
+            SET next_run_unix_time = $1
+            WHERE schedule_id = $2
+            "#,
+        )
+        .bind(next_run_unix_time)
+        .bind(schedule_id.schedule_id)
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculationSchedule> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM CalculationSchedule
+            WHERE schedule_id = $1 AND account_row_id = $2
+            "#,
+        )
+        .bind(schedule_id.schedule_id)
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+        state: &str,
+        unix_time: i64,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorSlot> {
+        sqlx::query(
+            r#"
+            INSERT INTO CalculatorSlot (account_row_id, slot_name, state, updated_unix_time)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(slot_name.slot_name.as_str())
+        .bind(state)
+        .bind(unix_time)
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn update_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+        state: &str,
+        unix_time: i64,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculatorSlot> {
+        let result = sqlx::query(
+            r#"
+            UPDATE CalculatorSlot
+            SET state = $1, updated_unix_time = $2
+            WHERE account_row_id = $3 AND slot_name = $4
+            "#,
+        )
+        .bind(state)
+        .bind(unix_time)
+        .bind(id.row_id())
+        .bind(slot_name.slot_name.as_str())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: &CalculatorSlotName,
+    ) -> WriteResult<bool, SqliteDatabaseError, CalculatorSlot> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM CalculatorSlot
+            WHERE account_row_id = $1 AND slot_name = $2
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(slot_name.slot_name.as_str())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_calculator_state_share(
+        &self,
+        owner: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateShare> {
+        sqlx::query(
+            r#"
+            INSERT INTO CalculatorStateShare
+                (owner_account_row_id, reader_account_row_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(owner.row_id())
+        .bind(reader.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn delete_calculator_state_share(
+        &self,
+        owner: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateShare> {
+        sqlx::query(
+            r#"
+            DELETE FROM CalculatorStateShare
+            WHERE owner_account_row_id = $1 AND reader_account_row_id = $2
+            "#,
+        )
+        .bind(owner.row_id())
+        .bind(reader.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn insert_history_entry(
+        &self,
+        id: AccountIdInternal,
+        entry: &NewHistoryEntry,
+    ) -> WriteResult<i64, SqliteDatabaseError, NewHistoryEntry> {
+        let history_id = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO CalculatorHistory (account_row_id, expression, result, error, unix_time)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING history_id
+            "#,
+        )
+        .bind(id.row_id())
+        .bind(entry.expression.as_str())
+        .bind(entry.result)
+        .bind(entry.error.as_deref())
+        .bind(entry.unix_time)
+        .fetch_one(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(history_id)
+    }
+
+    async fn delete_expired_history_entries(
+        &self,
+        now_unix_time: i64,
+        default_retention_days: Option<i64>,
+    ) -> WriteResult<u64, SqliteDatabaseError, NewHistoryEntry> {
+        // Comparison with NULL is never true, so entries are kept if
+        // neither retention is set.
+        let default_cutoff = default_retention_days.map(|days| now_unix_time - days * 24 * 60 * 60);
+        let result = sqlx::query(
+            r#"
+            DELETE FROM CalculatorHistory
+            WHERE unix_time < COALESCE(
+                (
+                    SELECT $1 - OrganizationSettings.history_retention_days * 86400
+                    FROM OrganizationMember
+                    INNER JOIN OrganizationSettings
+                        ON OrganizationSettings.org_id = OrganizationMember.org_id
+                    WHERE OrganizationMember.account_row_id = CalculatorHistory.account_row_id
+                        AND OrganizationSettings.history_retention_days IS NOT NULL
+                ),
+                $2
+            )
+            "#,
+        )
+        .bind(now_unix_time)
+        .bind(default_cutoff)
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn update_calculator_settings_json(
+        &self,
+        id: AccountIdInternal,
+        settings: &CalculatorSettings,
+    ) -> Result<(), SqliteDatabaseError> {
+        write_json(
+            self.handle.pool(),
+            r#"
+            UPDATE CalculatorSettings
+            SET json_text = $1
+            WHERE account_row_id = $2
+            "#,
+            settings,
+            id,
+        )
+        .await
+    }
+
+    async fn update_calculator_state(
+        &self,
+        id: AccountIdInternal,
+        state: &CalculatorStateInternal,
+    ) -> Result<(), SqliteDatabaseError> {
+        sqlx::query(
+            r#"
+            UPDATE CurrentState
+            SET calculation = $1
+            WHERE account_row_id = $2
+            "#,
+        )
+        .bind(state.state.as_str())
+        .bind(id.row_id())
+        .execute(self.handle.pool())
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+}
+
+/// PostgreSQL implementation of [TransactionWriteCalculatorStorage].
+pub struct PostgresTransactionWriteCalculatorCommands<'t> {
+    transaction: &'t mut Transaction<'static, Postgres>,
+}
+
+impl<'t> PostgresTransactionWriteCalculatorCommands<'t> {
+    pub fn new(transaction: &'t mut Transaction<'static, Postgres>) -> Self {
+        Self { transaction }
+    }
+}
+
+#[async_trait]
+impl TransactionWriteCalculatorStorage for PostgresTransactionWriteCalculatorCommands<'_> {
+    async fn init_calculator_state(
+        &mut self,
+        id: AccountIdInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorState> {
+        sqlx::query(
+            r#"
+            INSERT INTO CurrentState (account_row_id)
+            VALUES ($1)
+            "#,
+        )
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+
+    async fn store_calculator_settings(
+        &mut self,
+        id: AccountIdInternal,
+        settings: &CalculatorSettings,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorSettings> {
+        write_json(
+            &mut *self.transaction,
+            r#"
+            INSERT INTO CalculatorSettings (json_text, account_row_id)
+            VALUES ($1, $2)
+            "#,
+            settings,
+            id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn update_calculator_state(
+        &mut self,
+        id: AccountIdInternal,
+        state: &CalculatorStateInternal,
+    ) -> WriteResult<(), SqliteDatabaseError, CalculatorStateInternal> {
+        sqlx::query(
+            r#"
+            UPDATE CurrentState
+            SET calculation = $1
+            WHERE account_row_id = $2
+            "#,
+        )
+        .bind(state.state.as_str())
+        .bind(id.row_id())
+        .execute(&mut *self.transaction)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+        Ok(())
+    }
+}
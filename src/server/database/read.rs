@@ -1,16 +1,31 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{fmt::Debug, marker::PhantomData, net::SocketAddr, sync::Arc};
 
+use tokio::sync::Notify;
 use tokio_stream::StreamExt;
+use tracing::debug;
 
 use crate::{
-    api::model::{AccountIdInternal, AccountIdLight, ApiKey, RefreshToken},
+    api::{
+        common::ClientVersion,
+        model::{
+            Account, AccountAlias, AccountDataExport, AccountDeletionInfo, AccountExport,
+            AccountIdInternal, AccountIdLight, AccountReport, AccountSetup, AccountTimes,
+            CalculationSchedule, CalculatorSettings, CalculatorSlot, CalculatorStateInternal,
+            HistoryEntry, HistoryEntryInternal, HistoryId, LoginHistoryEntry, NotificationSettings,
+            OrganizationId, OrganizationMember, OrganizationMembership, OrganizationSettings,
+            RefreshToken, ServiceTokenInfo, SessionInfo, TokenHash,
+        },
+    },
+    config::Config,
+    server::latency::{measure_phase, Phase},
     utils::{ConvertCommandError, ErrorConversion},
 };
 
 use super::{
-    cache::{CacheError, DatabaseCache, ReadCacheJson},
-    current::SqliteReadCommands,
-    sqlite::{SqliteDatabaseError, SqliteReadHandle, SqliteSelectJson},
+    cache::{CacheError, CacheRead, CacheReadStats, DatabaseCache, ReadCacheJson},
+    current::{CurrentDataReadHandle, CurrentReadCommands, SelectJson},
+    export::EXPORT_BUNDLE_LIFETIME_SECONDS,
+    sqlite::SqliteDatabaseError,
     write::NoId,
     DatabaseError,
 };
@@ -69,38 +84,425 @@ impl<Target> From<CacheError> for ReadError<error_stack::Report<CacheError>, Tar
 }
 
 pub struct ReadCommands<'a> {
-    sqlite: SqliteReadCommands<'a>,
+    current: CurrentReadCommands<'a>,
     cache: &'a DatabaseCache,
 }
 
 impl<'a> ReadCommands<'a> {
-    pub fn new(sqlite: &'a SqliteReadHandle, cache: &'a DatabaseCache) -> Self {
+    pub fn new(current: &'a CurrentDataReadHandle, cache: &'a DatabaseCache) -> Self {
         Self {
-            sqlite: SqliteReadCommands::new(sqlite),
+            current: CurrentReadCommands::new(current),
             cache,
         }
     }
 
-    pub async fn account_access_token(
+    pub async fn account_access_token_hash(
         &self,
         id: AccountIdLight,
-    ) -> Result<Option<ApiKey>, DatabaseError> {
+    ) -> Result<Option<TokenHash>, DatabaseError> {
         let id = self.cache.to_account_id_internal(id).await.convert(id)?;
-        self.sqlite.account().access_token(id).await.convert(id)
+        self.current
+            .account()
+            .access_token_hash(id)
+            .await
+            .convert(id)
     }
 
-    pub async fn account_refresh_token(
+    pub async fn account_refresh_token_hash(
         &self,
         id: AccountIdInternal,
-    ) -> Result<Option<RefreshToken>, DatabaseError> {
-        self.sqlite.account().refresh_token(id).await.convert(id)
+    ) -> Result<Option<TokenHash>, DatabaseError> {
+        self.current
+            .account()
+            .refresh_token_hash(id)
+            .await
+            .convert(id)
+    }
+
+    pub async fn account_with_refresh_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<Option<AccountIdInternal>, DatabaseError> {
+        self.current
+            .account()
+            .account_with_refresh_token(token)
+            .await
+            .convert(NoId)
+    }
+
+    /// Generation of the token if the token is a replaced refresh token of
+    /// the account.
+    pub async fn used_refresh_token_generation(
+        &self,
+        id: AccountIdInternal,
+        token: &RefreshToken,
+    ) -> Result<Option<i64>, DatabaseError> {
+        self.current
+            .account()
+            .used_refresh_token_generation(id, token)
+            .await
+            .convert(id)
+    }
+
+    /// Cached account data, current connection address and client version.
+    pub async fn cache_entry_info(
+        &self,
+        id: AccountIdLight,
+    ) -> Result<
+        (
+            Option<Account>,
+            Option<SocketAddr>,
+            Option<ClientVersion>,
+            bool,
+        ),
+        DatabaseError,
+    > {
+        let (account, connection, client_version) = self
+            .cache
+            .read_cache(id, |entry| {
+                (
+                    entry.account.as_ref().map(|a| a.as_ref().clone()),
+                    entry.current_connection,
+                    entry.client_version.clone(),
+                )
+            })
+            .await
+            .convert(id)?;
+        let access_token = self.cache.access_token_exists_for_account(id).await;
+        Ok((account, connection, client_version, access_token))
+    }
+
+    /// WebSocket connection of the account closes when the account is
+    /// deleted.
+    pub async fn connection_close_notifier(
+        &self,
+        id: AccountIdLight,
+    ) -> Result<Arc<Notify>, DatabaseError> {
+        self.cache.connection_close_notifier(id).await.convert(id)
+    }
+
+    pub async fn session_info(&self, id: AccountIdLight) -> Result<SessionInfo, DatabaseError> {
+        self.cache
+            .read_cache(id, |entry| SessionInfo {
+                connection_address: entry.current_connection.map(|a| a.to_string()),
+                last_seen_address: entry.last_seen_address.map(|a| a.to_string()),
+                last_seen_country: entry.last_seen_location.as_ref().map(|l| l.country.clone()),
+                last_activity_unix_time: entry.last_activity_unix_time,
+            })
+            .await
+            .convert(id)
+    }
+
+    pub async fn service_tokens(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Vec<ServiceTokenInfo>, DatabaseError> {
+        self.current.account().service_tokens(id).await.convert(id)
+    }
+
+    /// Returns None if the account does not have an alias.
+    pub async fn account_alias(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Option<AccountAlias>, DatabaseError> {
+        self.current.account().account_alias(id).await.convert(id)
+    }
+
+    /// Most recent login history entries first.
+    pub async fn login_history(
+        &self,
+        id: AccountIdInternal,
+        limit: i64,
+    ) -> Result<Vec<LoginHistoryEntry>, DatabaseError> {
+        self.current
+            .account()
+            .login_history(id, limit)
+            .await
+            .convert(id)
+    }
+
+    /// Returns None if the account does not belong to an organization.
+    pub async fn organization_membership(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Option<OrganizationMembership>, DatabaseError> {
+        self.current
+            .account()
+            .organization_membership(id)
+            .await
+            .convert(id)
+    }
+
+    pub async fn organization_members(
+        &self,
+        org_id: OrganizationId,
+    ) -> Result<Vec<OrganizationMember>, DatabaseError> {
+        self.current
+            .account()
+            .organization_members(org_id)
+            .await
+            .convert(NoId)
+    }
+
+    /// Returns None if the organization does not exist.
+    pub async fn organization_settings(
+        &self,
+        org_id: OrganizationId,
+    ) -> Result<Option<OrganizationSettings>, DatabaseError> {
+        self.current
+            .account()
+            .organization_settings(org_id)
+            .await
+            .convert(NoId)
+    }
+
+    /// Settings of the organization of the account. Default settings if the
+    /// account does not belong to an organization.
+    pub async fn account_organization_settings(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<OrganizationSettings, DatabaseError> {
+        self.current
+            .account()
+            .account_organization_settings(id)
+            .await
+            .convert(id)
+    }
+
+    pub async fn calculation_schedules(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Vec<CalculationSchedule>, DatabaseError> {
+        self.current
+            .calculator()
+            .calculation_schedules(id)
+            .await
+            .convert(id)
+    }
+
+    pub async fn calculator_slots(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Vec<CalculatorSlot>, DatabaseError> {
+        self.current
+            .calculator()
+            .calculator_slots(id)
+            .await
+            .convert(id)
+    }
+
+    /// True if `owner` has shared its calculator state with `reader`.
+    pub async fn calculator_state_shared_with(
+        &self,
+        owner: AccountIdLight,
+        reader: AccountIdLight,
+    ) -> Result<bool, DatabaseError> {
+        self.cache
+            .read_cache(owner, |entry| {
+                entry.calculator_state_readers.contains(&reader)
+            })
+            .await
+            .convert(owner)
+    }
+
+    /// Organization which receives the calculator state broadcast of the
+    /// account. None if the account does not broadcast.
+    pub async fn calculator_broadcast(
+        &self,
+        id: AccountIdLight,
+    ) -> Result<Option<OrganizationId>, DatabaseError> {
+        self.cache
+            .read_cache(id, |entry| {
+                entry.calculator_broadcast.map(|broadcast| broadcast.org_id)
+            })
+            .await
+            .convert(id)
+    }
+
+    /// Returns None if account does not have the history entry.
+    pub async fn history_entry(
+        &self,
+        id: AccountIdInternal,
+        history_id: HistoryId,
+    ) -> Result<Option<HistoryEntryInternal>, DatabaseError> {
+        self.current
+            .calculator()
+            .history_entry(id, history_id)
+            .await
+            .convert(id)
+    }
+
+    /// Export data of components which are enabled on this server.
+    pub async fn account_export(
+        &self,
+        id: AccountIdInternal,
+        config: &Config,
+        unix_time: i64,
+    ) -> Result<AccountExport, DatabaseError> {
+        let mut export = AccountExport {
+            account_id: id.as_light(),
+            created_unix_time: unix_time,
+            expires_unix_time: unix_time + EXPORT_BUNDLE_LIFETIME_SECONDS,
+            region: self.account_region(id).await?,
+            account: None,
+            account_setup: None,
+            notification_settings: None,
+            calculator_state: None,
+            calculator_settings: None,
+            calculation_schedules: None,
+            calculator_slots: None,
+        };
+
+        if config.components().account {
+            export.account = Some(self.read_json::<Account>(id).await?);
+            export.account_setup = Some(self.read_json::<AccountSetup>(id).await?);
+            export.notification_settings = Some(self.read_json::<NotificationSettings>(id).await?);
+        }
+
+        if config.components().calculator {
+            let state = self.read_json::<CalculatorStateInternal>(id).await?;
+            export.calculator_state = Some(state.into());
+            export.calculator_settings = Some(self.read_json::<CalculatorSettings>(id).await?);
+            export.calculation_schedules = Some(self.calculation_schedules(id).await?);
+            export.calculator_slots = Some(self.calculator_slots(id).await?);
+        }
+
+        Ok(export)
+    }
+
+    /// All data of the account from components which are enabled on this
+    /// server. Data is read from the database, so recent activity which is
+    /// only in the cache is not included.
+    pub async fn account_data_export(
+        &self,
+        id: AccountIdInternal,
+        config: &Config,
+        unix_time: i64,
+    ) -> Result<AccountDataExport, DatabaseError> {
+        let data = measure_phase(Phase::Database, self.current.account().account_data(id))
+            .await
+            .convert(id)?;
+
+        let account = match data.account {
+            Some(account) => Some(account.with_times(self.account_times(id).await?)),
+            None => None,
+        };
+
+        let mut export = AccountDataExport {
+            account_id: id.as_light(),
+            created_unix_time: unix_time,
+            account,
+            account_setup: data.account_setup,
+            sign_in_with: data.sign_in_with.map(Into::into),
+            calculator_state: None,
+            calculator_history: None,
+        };
+
+        if config.components().calculator {
+            export.calculator_state = data.calculator_state.map(Into::into);
+            let history = measure_phase(
+                Phase::Database,
+                self.current.calculator().history_entries(id),
+            )
+            .await
+            .convert(id)?;
+            if !history.is_empty() {
+                let format = self
+                    .read_json::<CalculatorSettings>(id)
+                    .await?
+                    .number_format();
+                export.calculator_history = Some(
+                    history
+                        .into_iter()
+                        .map(|entry| HistoryEntry::new(entry, &format))
+                        .collect(),
+                );
+            }
+        }
+
+        Ok(export)
+    }
+
+    /// Account report from the saved data. Recent activity which is only in
+    /// the cache is not included.
+    pub async fn account_report(&self, unix_time: i64) -> Result<AccountReport, DatabaseError> {
+        self.current
+            .account()
+            .account_report(unix_time)
+            .await
+            .convert(NoId)
+    }
+
+    pub async fn account_times(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<AccountTimes, DatabaseError> {
+        self.current.account().account_times(id).await.convert(id)
+    }
+
+    pub async fn account_region(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Option<String>, DatabaseError> {
+        self.current.account().account_region(id).await.convert(id)
+    }
+
+    /// Returns None if account deletion is not requested.
+    pub async fn account_deletion(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<Option<AccountDeletionInfo>, DatabaseError> {
+        self.current
+            .account()
+            .account_deletion(id)
+            .await
+            .convert(id)
+    }
+
+    /// Accounts which should be deleted at `unix_time` or before it.
+    pub async fn expired_account_deletions(
+        &self,
+        unix_time: i64,
+    ) -> Result<Vec<AccountIdInternal>, DatabaseError> {
+        self.current
+            .account()
+            .expired_account_deletions(unix_time)
+            .await
+            .convert(NoId)
+    }
+
+    /// Next batch of accounts which have been inactive since before
+    /// `inactive_before`. Continue from the last returned account's row ID.
+    pub async fn stale_accounts(
+        &self,
+        inactive_before: i64,
+        after_row_id: i64,
+        limit: i64,
+    ) -> Result<Vec<AccountIdInternal>, DatabaseError> {
+        self.current
+            .account()
+            .stale_accounts(inactive_before, after_row_id, limit)
+            .await
+            .convert(NoId)
+    }
+
+    /// Schedules which should be evaluated at `unix_time` or before it.
+    pub async fn due_calculation_schedules(
+        &self,
+        unix_time: i64,
+    ) -> Result<Vec<(AccountIdInternal, CalculationSchedule)>, DatabaseError> {
+        self.current
+            .calculator()
+            .due_calculation_schedules(unix_time)
+            .await
+            .convert(NoId)
     }
 
     pub async fn account_ids<T: FnMut(AccountIdInternal)>(
         &self,
         mut handler: T,
     ) -> Result<(), DatabaseError> {
-        let account = self.sqlite.account();
+        let account = self.current.account();
         let mut users = account.account_ids_stream();
         while let Some(user_id) = users.try_next().await.convert(NoId)? {
             handler(user_id)
@@ -109,20 +511,158 @@ impl<'a> ReadCommands<'a> {
         Ok(())
     }
 
-    pub async fn read_json<T: SqliteSelectJson + Debug + ReadCacheJson + Send + Sync + 'static>(
+    /// Account IDs in registration order. Page numbers start from zero.
+    pub async fn account_ids_page(
+        &self,
+        page: u32,
+        page_size: u32,
+        region: Option<&str>,
+    ) -> Result<Vec<AccountIdInternal>, DatabaseError> {
+        let offset = i64::from(page) * i64::from(page_size);
+        self.current
+            .account()
+            .account_ids_page(offset, page_size.into(), region)
+            .await
+            .convert(NoId)
+    }
+
+    /// Check that the database read pool responds.
+    pub async fn check_connection(&self) -> Result<(), DatabaseError> {
+        self.current.check_connection().await.convert(NoId)
+    }
+
+    /// Read JSON data from the database even if the data is cached.
+    pub async fn read_json_from_database<T: SelectJson + Debug + Send + Sync + 'static>(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<T, DatabaseError> {
+        measure_phase(Phase::Database, T::select_json(id, &self.current))
+            .await
+            .with_info_lazy(|| format!("Read {:?} failed, id: {:?}", PhantomData::<T>, id))
+    }
+
+    /// Cached JSON data is read from the database if it is not in the
+    /// cache. The data is then added to the cache.
+    pub async fn read_json<T: SelectJson + Debug + ReadCacheJson + Send + Sync + 'static>(
         &self,
         id: AccountIdInternal,
     ) -> Result<T, DatabaseError> {
-        if T::CACHED_JSON {
-            T::read_from_cache(id.as_light(), self.cache)
+        if !T::CACHED_JSON {
+            return measure_phase(Phase::Database, T::select_json(id, &self.current))
                 .await
-                .with_info_lazy(|| {
+                .with_info_lazy(|| format!("Read {:?} failed, id: {:?}", PhantomData::<T>, id));
+        }
+
+        let result = match T::read_from_cache(id.as_light(), self.cache).await {
+            Ok(data) => {
+                self.cache.record_read(CacheRead::Hit);
+                return Ok(data);
+            }
+            Err(e) => match e.current_context() {
+                CacheError::NotInCache => self.read_json_and_fill_cache(id).await,
+                _ => Err(e).with_info_lazy(|| {
                     format!("Cache read {:?} failed, id: {:?}", PhantomData::<T>, id)
-                })
-        } else {
-            T::select_json(id, &self.sqlite)
-                .await
-                .with_info_lazy(|| format!("Read {:?} failed, id: {:?}", PhantomData::<T>, id))
+                }),
+            },
+        };
+        if result.is_err() {
+            self.cache.record_read(CacheRead::Failure);
+        }
+        result
+    }
+
+    /// The cache entry is locked while the data is read from the database,
+    /// so a write which happens at the same time is not overwritten with
+    /// older data.
+    async fn read_json_and_fill_cache<
+        T: SelectJson + Debug + ReadCacheJson + Send + Sync + 'static,
+    >(
+        &self,
+        id: AccountIdInternal,
+    ) -> Result<T, DatabaseError> {
+        let entry = self
+            .cache
+            .account_entry(id.as_light())
+            .await
+            .convert(id.as_light())?;
+        let mut cache_entry = entry.cache.write().await;
+        if let Some(data) = T::cached(&cache_entry) {
+            // Other read added the data to the cache.
+            self.cache.record_read(CacheRead::Hit);
+            return Ok(data);
         }
+
+        let data = measure_phase(Phase::Database, T::select_json(id, &self.current))
+            .await
+            .with_info_lazy(|| format!("Read {:?} failed, id: {:?}", PhantomData::<T>, id))?;
+        data.fill_cache(&mut cache_entry);
+        self.cache.record_read(CacheRead::Fallback);
+        debug!("Cache miss for {:?}, id: {:?}", PhantomData::<T>, id);
+        Ok(data)
+    }
+
+    pub fn cache_read_stats(&self) -> CacheReadStats {
+        self.cache.read_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        api::{account::PATH_ACCOUNT_STATE, GetUsers, ReadDatabase, WriteDatabase},
+        server::test_support::TestApp,
+    };
+
+    #[tokio::test]
+    async fn evicted_cache_entry_is_read_from_database() {
+        let app = TestApp::new().await;
+        let (id, login) = app.register_and_login().await;
+        let state = app.state();
+        let id = state.users().get_internal_id(id).await.unwrap();
+        state.write_database().evict_cache_entry(id).await.unwrap();
+
+        for _ in 0..2 {
+            let response = app
+                .get(PATH_ACCOUNT_STATE, Some(&login.account.access))
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let stats = state.read_database().cache_read_stats();
+        assert_eq!(stats.fallbacks, 1);
+        assert_eq!(stats.failures, 0);
+
+        drop(state);
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn database_has_only_token_hashes() {
+        let app = TestApp::new().await;
+        let (id, login) = app.register_and_login().await;
+        let state = app.state();
+        let internal_id = state.users().get_internal_id(id).await.unwrap();
+
+        let access = state
+            .read_database()
+            .account_access_token_hash(id)
+            .await
+            .unwrap();
+        assert_eq!(access, Some(login.account.access.hash()));
+        let refresh = state
+            .read_database()
+            .account_refresh_token_hash(internal_id)
+            .await
+            .unwrap();
+        assert_eq!(refresh, Some(login.account.refresh.hash()));
+
+        let response = app
+            .get(PATH_ACCOUNT_STATE, Some(&login.account.access))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        drop(state);
+        app.close().await;
     }
 }
@@ -0,0 +1,462 @@
+//! WAL shipping for disaster recovery
+//!
+//! Automatic checkpoints of the current database are disabled when
+//! replication is enabled, so the database file changes only when the
+//! shipper checkpoints it. A generation starts with a checkpoint and a copy
+//! of the database file. After that, new WAL frames are copied as segments
+//! until the WAL file grows too large and a new generation is started.
+//!
+//! Server started with `--replay` rebuilds the current database from the
+//! snapshot and the segments of the newest generation. SQLite applies the
+//! committed WAL frames when the database is opened.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use error_stack::{Report, Result, ResultExt};
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{file::ReplicationConfig, Config},
+    server::clock::UtcTimestamp,
+    utils::{IntoReportExt, QuitReceiver, QuitSender},
+};
+
+use super::{
+    sqlite::{wal_checkpoint_truncate, SqliteWriteHandle, DATABASE_FILE_NAME},
+    storage::{LocalStorage, S3Storage, Storage},
+    utils::current_unix_time,
+    DatabaseDir, DatabaseRoot,
+};
+
+const KEY_PREFIX: &str = "wal_";
+const SNAPSHOT_KEY_SUFFIX: &str = "snapshot.db";
+const SEGMENT_KEY_SUFFIX: &str = ".wal";
+const DEFAULT_INTERVAL_SECONDS: u64 = 1;
+const DEFAULT_MAX_WAL_SIZE_MIB: u64 = 16;
+const DEFAULT_RETENTION_COUNT: usize = 2;
+
+const WAL_HEADER_SIZE: u64 = 32;
+const WAL_FRAME_HEADER_SIZE: u64 = 24;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplicationError {
+    #[error("WAL checkpoint failed")]
+    Checkpoint,
+    #[error("File error")]
+    File,
+    #[error("Replication storage error")]
+    Storage,
+    #[error("Replication is not configured")]
+    NotConfigured,
+    #[error("Not enough disk space")]
+    DiskSpace,
+    #[error("Database initialization error")]
+    Init,
+    #[error("Replicated database not found")]
+    NotFound,
+}
+
+#[derive(Debug)]
+pub struct ReplicationQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl ReplicationQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("WAL shipper task join failed: {}", e),
+        }
+    }
+}
+
+/// Generation which is currently shipped.
+struct Generation {
+    id: i64,
+    /// Index of the next segment.
+    next_segment: u32,
+    /// WAL file position until which the WAL is shipped.
+    offset: u64,
+    /// WAL salt values. None until the WAL header is written.
+    salt: Option<[u8; 8]>,
+}
+
+/// Copies WAL segments of the current database to the replication
+/// storage.
+pub struct WalShipper {
+    write: SqliteWriteHandle,
+    root: Arc<DatabaseRoot>,
+    storage: Box<dyn Storage>,
+    interval: Duration,
+    max_wal_size: u64,
+    retention_count: usize,
+    generation: Option<Generation>,
+}
+
+impl WalShipper {
+    pub fn new_task(
+        write: SqliteWriteHandle,
+        root: Arc<DatabaseRoot>,
+        config: &ReplicationConfig,
+    ) -> Result<ReplicationQuitHandle, ReplicationError> {
+        let shipper = Self {
+            write,
+            root,
+            storage: replication_storage(config)?,
+            interval: Duration::from_secs(
+                config.interval_seconds.unwrap_or(DEFAULT_INTERVAL_SECONDS),
+            ),
+            max_wal_size: config.max_wal_size_mib.unwrap_or(DEFAULT_MAX_WAL_SIZE_MIB) * 1024 * 1024,
+            retention_count: config
+                .retention_count
+                .unwrap_or(DEFAULT_RETENTION_COUNT)
+                .max(1),
+            generation: None,
+        };
+
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(shipper.run(quit_receiver));
+
+        Ok(ReplicationQuitHandle { task, quit })
+    }
+
+    async fn run(mut self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(self.interval);
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    match self.ship().await {
+                        Ok(()) => (),
+                        Err(e) => error!("WAL shipping failed: {e:?}"),
+                    }
+                }
+            }
+        }
+
+        // Ship the latest writes before the database is closed.
+        if self.generation.is_some() {
+            match self.ship_new_frames().await {
+                Ok(()) => (),
+                Err(e) => error!("WAL shipping failed: {e:?}"),
+            }
+        }
+    }
+
+    async fn ship(&mut self) -> Result<(), ReplicationError> {
+        if self.generation.is_some() {
+            self.ship_new_frames().await?;
+        }
+
+        let wal_size = wal_file_size(&self.wal_file()).await?;
+        if self.generation.is_none() || wal_size >= self.max_wal_size {
+            self.start_generation().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Checkpoint the WAL and store the database file. WAL frames after
+    /// the checkpoint belong to the new generation.
+    async fn start_generation(&mut self) -> Result<(), ReplicationError> {
+        self.generation = None;
+
+        self.root
+            .check_disk_space()
+            .change_context(ReplicationError::DiskSpace)?;
+
+        wal_checkpoint_truncate(self.write.pool())
+            .await
+            .change_context(ReplicationError::Checkpoint)?;
+
+        // Automatic checkpoints are disabled, so the database file does
+        // not change until the next checkpoint.
+        let data = tokio::fs::read(self.database_file())
+            .await
+            .into_error(ReplicationError::File)?;
+
+        let id = current_unix_time().max(self.latest_generation_id().await? + 1);
+        self.storage
+            .put(&snapshot_key(id), data)
+            .await
+            .change_context(ReplicationError::Storage)?;
+
+        self.generation = Some(Generation {
+            id,
+            next_segment: 0,
+            offset: 0,
+            salt: None,
+        });
+        info!("WAL shipping generation {} started", id);
+
+        self.remove_old_generations().await
+    }
+
+    /// Store complete WAL frames which are written after the previous
+    /// segment.
+    async fn ship_new_frames(&mut self) -> Result<(), ReplicationError> {
+        let generation = match &mut self.generation {
+            Some(generation) => generation,
+            None => return Ok(()),
+        };
+
+        let wal = match tokio::fs::read(self.root.current_ref().path().join(wal_file_name())).await
+        {
+            Ok(wal) => wal,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).into_error(ReplicationError::File),
+        };
+        if (wal.len() as u64) < WAL_HEADER_SIZE {
+            return Ok(());
+        }
+
+        let header_salt = salt(&wal[16..24]);
+        match generation.salt {
+            None => generation.salt = Some(header_salt),
+            Some(salt) if salt != header_salt => {
+                // WAL was reset without the shipper, so some frames might
+                // be missing from this generation.
+                warn!("WAL was reset outside the WAL shipper, starting new generation");
+                self.generation = None;
+                return Ok(());
+            }
+            Some(_) => (),
+        }
+
+        let page_size = u32::from_be_bytes([wal[8], wal[9], wal[10], wal[11]]) as u64;
+        let frame_size = WAL_FRAME_HEADER_SIZE + page_size;
+        let mut end = generation.offset.max(WAL_HEADER_SIZE);
+        // Frames of a previous WAL have different salt values.
+        while end + frame_size <= wal.len() as u64 {
+            let frame_salt = salt(&wal[end as usize + 8..end as usize + 16]);
+            if frame_salt != header_salt {
+                break;
+            }
+            end += frame_size;
+        }
+
+        if end <= generation.offset || end == WAL_HEADER_SIZE {
+            return Ok(());
+        }
+
+        let segment = wal[generation.offset as usize..end as usize].to_vec();
+        self.storage
+            .put(
+                &segment_key(generation.id, generation.next_segment),
+                segment,
+            )
+            .await
+            .change_context(ReplicationError::Storage)?;
+
+        generation.next_segment += 1;
+        generation.offset = end;
+
+        Ok(())
+    }
+
+    async fn latest_generation_id(&self) -> Result<i64, ReplicationError> {
+        Ok(list_generations(self.storage.as_ref())
+            .await?
+            .first()
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn remove_old_generations(&self) -> Result<(), ReplicationError> {
+        let generations = list_generations(self.storage.as_ref()).await?;
+        let keep = &generations[..generations.len().min(self.retention_count)];
+        let keys = self
+            .storage
+            .list()
+            .await
+            .change_context(ReplicationError::Storage)?;
+        for key in keys {
+            match parse_key(&key) {
+                Some((id, _)) if !keep.contains(&id) => {
+                    self.storage
+                        .delete(&key)
+                        .await
+                        .change_context(ReplicationError::Storage)
+                        .attach_printable_lazy(|| key.clone())?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn database_file(&self) -> std::path::PathBuf {
+        self.root.current_ref().path().join(DATABASE_FILE_NAME)
+    }
+
+    fn wal_file(&self) -> std::path::PathBuf {
+        self.root.current_ref().path().join(wal_file_name())
+    }
+}
+
+/// Replace the current database with the newest replicated database. Server
+/// must not be running. The replaced database is moved to the backups
+/// directory.
+pub async fn replay_wal(config: &Config) -> Result<(), ReplicationError> {
+    let replication_config = config
+        .replication()
+        .ok_or_else(|| Report::new(ReplicationError::NotConfigured))?;
+    let root = DatabaseRoot::new(config.database_dir(), config.disk_space_limits())
+        .change_context(ReplicationError::Init)?;
+    let storage = replication_storage(replication_config)?;
+
+    let keys = storage
+        .list()
+        .await
+        .change_context(ReplicationError::Storage)?;
+    let mut generation = None;
+    for id in list_generations(storage.as_ref()).await? {
+        if keys.contains(&snapshot_key(id)) {
+            generation = Some(id);
+            break;
+        }
+    }
+    let id = generation.ok_or_else(|| Report::new(ReplicationError::NotFound))?;
+
+    let snapshot = storage
+        .get(&snapshot_key(id))
+        .await
+        .change_context(ReplicationError::Storage)?
+        .ok_or_else(|| Report::new(ReplicationError::NotFound))?;
+
+    // Segments are applied in order and a missing segment ends the WAL.
+    let mut wal = vec![];
+    let mut segments = 0;
+    loop {
+        let key = segment_key(id, segments);
+        if !keys.contains(&key) {
+            break;
+        }
+        match storage
+            .get(&key)
+            .await
+            .change_context(ReplicationError::Storage)?
+        {
+            Some(segment) => wal.extend_from_slice(&segment),
+            None => break,
+        }
+        segments += 1;
+    }
+
+    root.check_disk_space()
+        .change_context(ReplicationError::DiskSpace)?;
+
+    let current_dir = root.current_ref().path();
+    let current_file = current_dir.join(DATABASE_FILE_NAME);
+    if current_file.exists() {
+        let replaced = root.dir(DatabaseDir::Backups).join(format!(
+            "before_replay_{}.db",
+            UtcTimestamp::now().unix_time()
+        ));
+        tokio::fs::rename(&current_file, &replaced)
+            .await
+            .into_error(ReplicationError::File)?;
+        info!("Current database moved to {}", replaced.display());
+    }
+    for suffix in ["-wal", "-shm"] {
+        remove_if_exists(&current_dir.join(format!("{DATABASE_FILE_NAME}{suffix}"))).await?;
+    }
+
+    let tmp_file = current_dir.join(format!("{DATABASE_FILE_NAME}.tmp"));
+    tokio::fs::write(&tmp_file, snapshot)
+        .await
+        .into_error(ReplicationError::File)?;
+    tokio::fs::rename(&tmp_file, &current_file)
+        .await
+        .into_error(ReplicationError::File)?;
+    if !wal.is_empty() {
+        tokio::fs::write(current_dir.join(wal_file_name()), wal)
+            .await
+            .into_error(ReplicationError::File)?;
+    }
+
+    info!(
+        "Database replayed from generation {} with {} WAL segments",
+        id, segments
+    );
+
+    Ok(())
+}
+
+fn replication_storage(config: &ReplicationConfig) -> Result<Box<dyn Storage>, ReplicationError> {
+    match (&config.dir, &config.s3) {
+        (Some(dir), None) => {
+            std::fs::create_dir_all(dir).into_error(ReplicationError::File)?;
+            Ok(Box::new(LocalStorage::new(dir.clone())))
+        }
+        (None, Some(s3_config)) => Ok(Box::new(
+            S3Storage::new(s3_config).change_context(ReplicationError::Storage)?,
+        )),
+        _ => Err(Report::new(ReplicationError::NotConfigured)),
+    }
+}
+
+fn wal_file_name() -> String {
+    format!("{DATABASE_FILE_NAME}-wal")
+}
+
+async fn wal_file_size(file: &Path) -> Result<u64, ReplicationError> {
+    match tokio::fs::File::open(file).await {
+        Ok(file) => Ok(file
+            .metadata()
+            .await
+            .into_error(ReplicationError::File)?
+            .len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e).into_error(ReplicationError::File),
+    }
+}
+
+fn salt(bytes: &[u8]) -> [u8; 8] {
+    let mut salt = [0; 8];
+    salt.copy_from_slice(bytes);
+    salt
+}
+
+fn snapshot_key(generation: i64) -> String {
+    format!("{KEY_PREFIX}{generation}_{SNAPSHOT_KEY_SUFFIX}")
+}
+
+fn segment_key(generation: i64, index: u32) -> String {
+    format!("{KEY_PREFIX}{generation}_{index:010}{SEGMENT_KEY_SUFFIX}")
+}
+
+/// Returns generation and the rest of the key. None if key is not a
+/// replication key.
+fn parse_key(key: &str) -> Option<(i64, &str)> {
+    let (generation, rest) = key.strip_prefix(KEY_PREFIX)?.split_once('_')?;
+    Some((generation.parse().ok()?, rest))
+}
+
+/// Generations sorted so that the newest generation is first.
+async fn list_generations(storage: &dyn Storage) -> Result<Vec<i64>, ReplicationError> {
+    let mut generations: Vec<i64> = storage
+        .list()
+        .await
+        .change_context(ReplicationError::Storage)?
+        .iter()
+        .filter_map(|key| parse_key(key).map(|(id, _)| id))
+        .collect();
+    generations.sort_by(|a, b| b.cmp(a));
+    generations.dedup();
+    Ok(generations)
+}
+
+async fn remove_if_exists(file: &Path) -> Result<(), ReplicationError> {
+    match tokio::fs::remove_file(file).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => {
+            Err(e).into_error_with_info_lazy(ReplicationError::File, || file.display().to_string())
+        }
+    }
+}
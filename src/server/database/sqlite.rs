@@ -1,17 +1,15 @@
-use crate::api::model::AccountIdInternal;
-
-use async_trait::async_trait;
 use sqlx::sqlite::SqliteRow;
 use sqlx::Row;
 use tracing::log::info;
 
-use super::current::{CurrentDataWriteCommands, SqliteReadCommands};
+use super::migrate::{HISTORY_MIGRATOR, MIGRATOR};
 
-use error_stack::Result;
+use error_stack::{Report, Result};
 
 use std::path::{Path, PathBuf};
 
 use sqlx::{
+    migrate::Migrator,
     sqlite::{self, SqliteConnectOptions, SqlitePoolOptions},
     SqlitePool,
 };
@@ -19,10 +17,12 @@ use sqlx::{
 use crate::utils::IntoReportExt;
 
 pub const DATABASE_FILE_NAME: &str = "current.db";
+pub const HISTORY_DATABASE_FILE_NAME: &str = "history.db";
 
+/// Current data database errors. PostgreSQL backend uses these also.
 #[derive(thiserror::Error, Debug)]
 pub enum SqliteDatabaseError {
-    #[error("Connecting to SQLite database failed")]
+    #[error("Connecting to database failed")]
     Connect,
     #[error("Executing SQL query failed")]
     Execute,
@@ -36,11 +36,15 @@ pub enum SqliteDatabaseError {
     TransactionRollback,
     #[error("Commiting transaction failed")]
     TransactionCommit,
+    #[error("WAL checkpoint did not complete")]
+    CheckpointBusy,
 
     #[error("Deserialization error")]
     SerdeDeserialize,
     #[error("Serialization error")]
     SerdeSerialize,
+    #[error("JSON data migration error")]
+    JsonMigration,
 
     #[error("Time parsing error")]
     TimeParsing,
@@ -85,40 +89,18 @@ impl SqliteWriteCloseHandle {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct CurrentDataWriteHandle {
-    handle: SqliteWriteHandle,
-    read_handle: SqliteReadHandle,
-}
-
-impl CurrentDataWriteHandle {
-    pub fn new(handle: SqliteWriteHandle) -> Self {
-        Self {
-            read_handle: SqliteReadHandle {
-                pool: handle.pool.clone(),
-            },
-            handle,
-        }
-    }
-
-    pub fn pool(&self) -> &SqlitePool {
-        self.handle.pool()
-    }
-
-    pub fn read(&self) -> SqliteReadCommands<'_> {
-        SqliteReadCommands::new(&self.read_handle)
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct SqliteWriteHandle {
     pool: SqlitePool,
 }
 
 impl SqliteWriteHandle {
+    /// If `auto_checkpoint` is false, WAL is copied to the database file
+    /// only with [wal_checkpoint_truncate] and when the database is closed.
     pub async fn new(
         dir: SqliteDatabasePath,
         db_type: DatabaseType,
+        auto_checkpoint: bool,
     ) -> Result<(Self, SqliteWriteCloseHandle), SqliteDatabaseError> {
         let db_path = dir.path().join(db_type.to_file_name());
 
@@ -131,13 +113,20 @@ impl SqliteWriteHandle {
                     .filename(db_path)
                     .create_if_missing(true)
                     .foreign_keys(true)
-                    .journal_mode(sqlite::SqliteJournalMode::Wal),
+                    .journal_mode(sqlite::SqliteJournalMode::Wal)
+                    .pragma(
+                        "wal_autocheckpoint",
+                        if auto_checkpoint { "1000" } else { "0" },
+                    ),
             )
             .await
             .into_error(SqliteDatabaseError::Connect)?;
 
-        if run_initial_setup {
-            sqlx::migrate!()
+        // History database does not have offline migrations, so its
+        // migrations run every time the server starts.
+        if run_initial_setup || db_type == DatabaseType::History {
+            db_type
+                .migrator()
                 .run(&pool)
                 .await
                 .into_error(SqliteDatabaseError::Migrate)?;
@@ -153,6 +142,13 @@ impl SqliteWriteHandle {
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
+
+    /// Read handle which uses the write connection.
+    pub fn read_handle(&self) -> SqliteReadHandle {
+        SqliteReadHandle {
+            pool: self.pool.clone(),
+        }
+    }
 }
 
 pub struct SqliteReadCloseHandle {
@@ -202,34 +198,27 @@ impl SqliteReadHandle {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DatabaseType {
     Current,
+    /// Append only account history.
+    History,
 }
 
 impl DatabaseType {
     pub fn to_file_name(&self) -> &str {
         match self {
             DatabaseType::Current => DATABASE_FILE_NAME,
+            DatabaseType::History => HISTORY_DATABASE_FILE_NAME,
         }
     }
-}
 
-#[async_trait]
-pub trait SqliteUpdateJson {
-    async fn update_json(
-        &self,
-        id: AccountIdInternal,
-        write: &CurrentDataWriteCommands,
-    ) -> Result<(), SqliteDatabaseError>;
-}
-
-#[async_trait]
-pub trait SqliteSelectJson: Sized {
-    async fn select_json(
-        id: AccountIdInternal,
-        read: &SqliteReadCommands,
-    ) -> Result<Self, SqliteDatabaseError>;
+    pub fn migrator(&self) -> &'static Migrator {
+        match self {
+            DatabaseType::Current => &MIGRATOR,
+            DatabaseType::History => &HISTORY_MIGRATOR,
+        }
+    }
 }
 
 pub async fn print_sqlite_version(pool: &SqlitePool) -> Result<(), SqliteDatabaseError> {
@@ -245,3 +234,30 @@ pub async fn print_sqlite_version(pool: &SqlitePool) -> Result<(), SqliteDatabas
     info!("SQLite version: {}", q);
     Ok(())
 }
+
+/// Copy all WAL frames to the database file and truncate the WAL file.
+pub async fn wal_checkpoint_truncate(pool: &SqlitePool) -> Result<(), SqliteDatabaseError> {
+    let busy: i64 = sqlx::query_scalar("PRAGMA wal_checkpoint(TRUNCATE)")
+        .fetch_one(pool)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+    if busy == 0 {
+        Ok(())
+    } else {
+        Err(Report::new(SqliteDatabaseError::CheckpointBusy))
+    }
+}
+
+/// Write consistent copy of the database to a new file. The file must not
+/// exist.
+pub async fn create_snapshot(pool: &SqlitePool, file: &Path) -> Result<(), SqliteDatabaseError> {
+    let file = file.to_string_lossy().to_string();
+    sqlx::query("VACUUM INTO ?")
+        .bind(file)
+        .execute(pool)
+        .await
+        .into_error(SqliteDatabaseError::Execute)?;
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Hot standby replication
+//!
+//! Primary server creates periodic database snapshots and sends those to
+//! the standby server's internal API. Standby server stores the latest
+//! snapshot to the standby directory. See README for promote procedure.
+
+use std::{sync::Arc, time::Duration};
+
+use error_stack::{Result, ResultExt};
+use reqwest::Url;
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    api::standby::PATH_INTERNAL_STANDBY_SNAPSHOT,
+    config::file::HotStandbyConfig,
+    utils::{IntoReportExt, QuitReceiver, QuitSender},
+};
+
+use super::{
+    sqlite::{create_snapshot, SqliteReadHandle},
+    DatabaseRoot,
+};
+
+pub const DB_STANDBY_DIR_NAME: &str = "standby";
+pub const STANDBY_SNAPSHOT_FILE_NAME: &str = "current.db";
+const SNAPSHOT_TMP_FILE_NAME: &str = "snapshot_tmp.db";
+const DEFAULT_SNAPSHOT_INTERVAL_SECONDS: u64 = 60;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HotStandbyError {
+    #[error("Creating database snapshot failed")]
+    Snapshot,
+    #[error("File error")]
+    File,
+    #[error("Sending snapshot to standby server failed")]
+    Send,
+    #[error("Standby server internal API URL is not configured")]
+    UrlMissing,
+    #[error("Not enough disk space")]
+    DiskSpace,
+}
+
+#[derive(Debug)]
+pub struct HotStandbyQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl HotStandbyQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Hot standby sender task join failed: {}", e),
+        }
+    }
+}
+
+/// Sends database snapshots from primary server to standby server.
+pub struct HotStandbySender {
+    read: SqliteReadHandle,
+    root: Arc<DatabaseRoot>,
+    client: reqwest::Client,
+    snapshot_url: Url,
+    interval: Duration,
+}
+
+impl HotStandbySender {
+    pub fn new_task(
+        read: SqliteReadHandle,
+        root: Arc<DatabaseRoot>,
+        config: &HotStandbyConfig,
+        client: reqwest::Client,
+    ) -> Result<HotStandbyQuitHandle, HotStandbyError> {
+        let snapshot_url = config
+            .standby_internal_api
+            .as_ref()
+            .ok_or(HotStandbyError::UrlMissing)?
+            .join(PATH_INTERNAL_STANDBY_SNAPSHOT)
+            .into_error(HotStandbyError::UrlMissing)?;
+
+        info!("Hot standby snapshot URL: {}", snapshot_url);
+
+        let sender = Self {
+            read,
+            root,
+            client,
+            snapshot_url,
+            interval: Duration::from_secs(
+                config
+                    .snapshot_interval_seconds
+                    .unwrap_or(DEFAULT_SNAPSHOT_INTERVAL_SECONDS),
+            ),
+        };
+
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(sender.run(quit_receiver));
+
+        Ok(HotStandbyQuitHandle { task, quit })
+    }
+
+    async fn run(self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(self.interval);
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    match self.send_snapshot().await {
+                        Ok(()) => (),
+                        Err(e) => error!("Hot standby snapshot failed: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn send_snapshot(&self) -> Result<(), HotStandbyError> {
+        let tmp_file = self.root.standby().join(SNAPSHOT_TMP_FILE_NAME);
+        if tmp_file.exists() {
+            tokio::fs::remove_file(&tmp_file)
+                .await
+                .into_error(HotStandbyError::File)?;
+        }
+
+        self.root
+            .check_disk_space()
+            .change_context(HotStandbyError::DiskSpace)?;
+
+        create_snapshot(self.read.pool(), &tmp_file)
+            .await
+            .change_context(HotStandbyError::Snapshot)?;
+
+        let data = tokio::fs::read(&tmp_file)
+            .await
+            .into_error(HotStandbyError::File)?;
+
+        let response = self
+            .client
+            .post(self.snapshot_url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .body(data)
+            .send()
+            .await
+            .into_error(HotStandbyError::Send)?;
+
+        response
+            .error_for_status()
+            .into_error(HotStandbyError::Send)?;
+
+        tokio::fs::remove_file(&tmp_file)
+            .await
+            .into_error(HotStandbyError::File)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+//! File storage
+//!
+//! Exports, backups and reports are stored using [Storage], so that the
+//! destination can be changed without changing the code which creates the
+//! files. Files are identified with keys which are plain file names.
+
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use error_stack::{Report, Result, ResultExt};
+use s3::{creds::Credentials, Bucket, Region};
+
+use crate::{config::file::S3BackupConfig, utils::IntoReportExt};
+
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    #[error("Invalid key")]
+    InvalidKey,
+    #[error("File error")]
+    File,
+    #[error("Storage configuration error")]
+    Config,
+    #[error("S3 request failed")]
+    S3Request,
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Replace file if it exists. Partially written file is never
+    /// available.
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), StorageError>;
+
+    /// Returns None if file does not exist.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Removing file which does not exist is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Keys of all files.
+    async fn list(&self) -> Result<Vec<String>, StorageError>;
+}
+
+/// Key must be a file name without a path.
+fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && key != "."
+        && key != ".."
+        && !key.contains(|c| c == '/' || c == '\\')
+        && !key.ends_with(&format!(".{TMP_FILE_EXTENSION}"));
+    if valid {
+        Ok(())
+    } else {
+        Err(Report::new(StorageError::InvalidKey).attach_printable(key.to_string()))
+    }
+}
+
+/// Files in a local directory.
+#[derive(Debug)]
+pub struct LocalStorage {
+    dir: PathBuf,
+}
+
+impl LocalStorage {
+    /// Directory must exist.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.dir.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        let tmp_path = self.dir.join(format!("{key}.{TMP_FILE_EXTENSION}"));
+        tokio::fs::write(&tmp_path, data)
+            .await
+            .into_error(StorageError::File)?;
+        tokio::fs::rename(&tmp_path, path)
+            .await
+            .into_error(StorageError::File)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).into_error(StorageError::File),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).into_error(StorageError::File),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<String>, StorageError> {
+        let mut keys = vec![];
+        let mut entries = tokio::fs::read_dir(&self.dir)
+            .await
+            .into_error(StorageError::File)?;
+        while let Some(entry) = entries.next_entry().await.into_error(StorageError::File)? {
+            let is_file = entry
+                .file_type()
+                .await
+                .into_error(StorageError::File)?
+                .is_file();
+            match entry.file_name().into_string() {
+                Ok(key) if is_file && validate_key(&key).is_ok() => keys.push(key),
+                _ => (),
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Objects in a S3-compatible bucket.
+pub struct S3Storage {
+    bucket: Bucket,
+}
+
+impl S3Storage {
+    pub fn new(config: &S3BackupConfig) -> Result<Self, StorageError> {
+        let access_key_id = read_secret(&config.access_key_id_file)?;
+        let secret_access_key = read_secret(&config.secret_access_key_file)?;
+        let credentials = Credentials::new(
+            Some(&access_key_id),
+            Some(&secret_access_key),
+            None,
+            None,
+            None,
+        )
+        .into_error(StorageError::Config)?;
+        let region = Region::Custom {
+            region: config.region.clone(),
+            endpoint: config.endpoint.as_str().trim_end_matches('/').to_string(),
+        };
+        // Path style URLs work also with self-hosted S3-compatible services.
+        let bucket = Bucket::new(&config.bucket, region, credentials)
+            .into_error(StorageError::Config)?
+            .with_path_style();
+
+        Ok(Self { bucket })
+    }
+
+    fn check_status(status: u16, key: &str) -> Result<(), StorageError> {
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(Report::new(StorageError::S3Request)
+                .attach_printable(format!("Key: {key}, HTTP status: {status}")))
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), StorageError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .put_object(key, &data)
+            .await
+            .into_error(StorageError::S3Request)?;
+        Self::check_status(response.status_code(), key)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .get_object(key)
+            .await
+            .into_error(StorageError::S3Request)?;
+        if response.status_code() == 404 {
+            return Ok(None);
+        }
+        Self::check_status(response.status_code(), key)?;
+        Ok(Some(response.bytes().to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .delete_object(key)
+            .await
+            .into_error(StorageError::S3Request)?;
+        if response.status_code() == 404 {
+            return Ok(());
+        }
+        Self::check_status(response.status_code(), key)
+    }
+
+    async fn list(&self) -> Result<Vec<String>, StorageError> {
+        let results = self
+            .bucket
+            .list(String::new(), None)
+            .await
+            .into_error(StorageError::S3Request)?;
+        Ok(results
+            .into_iter()
+            .flat_map(|result| result.contents)
+            .map(|object| object.key)
+            .filter(|key| validate_key(key).is_ok())
+            .collect())
+    }
+}
+
+fn read_secret(file: &Path) -> Result<String, StorageError> {
+    let secret = std::fs::read_to_string(file)
+        .into_error(StorageError::Config)
+        .attach_printable_lazy(|| file.display().to_string())?;
+    Ok(secret.trim().to_string())
+}
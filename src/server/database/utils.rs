@@ -3,20 +3,28 @@ use std::net::SocketAddr;
 use error_stack::Result;
 
 use crate::{
-    api::model::{AccountIdInternal, AccountIdLight, ApiKey, GoogleAccountId},
+    api::{
+        model::{
+            AccountIdInternal, AccountIdLight, ApiKey, AppleAccountId, GoogleAccountId,
+            ServiceTokenScope,
+        },
+        utils::AccountIdPath,
+    },
+    server::{clock::UtcTimestamp, geoip::GeoLocation},
     utils::ConvertCommandError,
 };
 
 use super::{
     cache::{CacheError, DatabaseCache},
-    current::SqliteReadCommands,
-    sqlite::SqliteReadHandle,
+    current::{CurrentDataReadHandle, CurrentReadCommands},
     write::DatabaseId,
     DatabaseError,
 };
 
+/// System time for timestamps of saved records. Use the clock from the
+/// config for expiration and scheduling decisions.
 pub fn current_unix_time() -> i64 {
-    time::OffsetDateTime::now_utc().unix_timestamp()
+    UtcTimestamp::now().unix_time()
 }
 
 pub struct ApiKeyManager<'a> {
@@ -28,31 +36,52 @@ impl<'a> ApiKeyManager<'a> {
         Self { cache }
     }
 
+    /// Expired access tokens do not exist.
     pub async fn api_key_exists(&self, api_key: &ApiKey) -> Option<AccountIdInternal> {
         self.cache.access_token_exists(api_key).await
     }
 
-    pub async fn api_key_and_connection_exists(
+    /// Updates last seen address, location and activity time of the access
+    /// token's account. Returns account ID and previous last seen address and
+    /// location.
+    pub async fn api_key_used(
         &self,
         api_key: &ApiKey,
-        connection: SocketAddr,
-    ) -> Option<AccountIdInternal> {
+        address: SocketAddr,
+        location: Option<GeoLocation>,
+        require_connection_ip: bool,
+    ) -> Option<(AccountIdInternal, Option<(SocketAddr, Option<GeoLocation>)>)> {
         self.cache
-            .access_token_and_connection_exists(api_key, connection)
+            .access_token_used(api_key, address, location, require_connection_ip)
             .await
     }
+
+    /// Service tokens are separate from the access tokens of interactive
+    /// sessions.
+    pub async fn service_token_used(
+        &self,
+        token: &ApiKey,
+    ) -> Option<(AccountIdInternal, ServiceTokenScope)> {
+        self.cache.service_token_used(token).await
+    }
+
+    /// Remove expired access tokens from the cache. Returns count of removed
+    /// tokens.
+    pub async fn remove_expired_api_keys(&self) -> usize {
+        self.cache.remove_expired_access_tokens().await
+    }
 }
 
 pub struct AccountIdManager<'a> {
     cache: &'a DatabaseCache,
-    read_handle: SqliteReadCommands<'a>,
+    read_handle: CurrentReadCommands<'a>,
 }
 
 impl<'a> AccountIdManager<'a> {
-    pub fn new(cache: &'a DatabaseCache, read_handle: &'a SqliteReadHandle) -> Self {
+    pub fn new(cache: &'a DatabaseCache, read_handle: &'a CurrentDataReadHandle) -> Self {
         Self {
             cache,
-            read_handle: SqliteReadCommands::new(read_handle),
+            read_handle: CurrentReadCommands::new(read_handle),
         }
     }
 
@@ -63,6 +92,23 @@ impl<'a> AccountIdManager<'a> {
         self.cache.to_account_id_internal(id).await.attach(id)
     }
 
+    /// Returns None if the account does not exist. Aliases are checked from
+    /// the database.
+    pub async fn get_internal_id_from_path(
+        &self,
+        id: AccountIdPath,
+    ) -> Result<Option<AccountIdInternal>, DatabaseError> {
+        match id {
+            AccountIdPath::Id(id) => Ok(self.get_internal_id(id).await.ok()),
+            AccountIdPath::Alias(alias) => self
+                .read_handle
+                .account()
+                .get_account_with_alias(&alias)
+                .await
+                .convert(DatabaseId::Empty),
+        }
+    }
+
     pub async fn get_account_with_google_account_id(
         &self,
         id: GoogleAccountId,
@@ -73,4 +119,15 @@ impl<'a> AccountIdManager<'a> {
             .await
             .convert(DatabaseId::Empty)
     }
+
+    pub async fn get_account_with_apple_account_id(
+        &self,
+        id: AppleAccountId,
+    ) -> Result<Option<AccountIdInternal>, DatabaseError> {
+        self.read_handle
+            .account()
+            .get_account_with_apple_account_id(id)
+            .await
+            .convert(DatabaseId::Empty)
+    }
 }
@@ -1,10 +1,25 @@
-use std::{fmt::Debug, marker::PhantomData, net::SocketAddr};
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
 
 use error_stack::Result;
+use tokio_stream::StreamExt;
+use tracing::error;
 
 use crate::{
-    api::model::{
-        Account, AccountIdInternal, AccountIdLight, AccountSetup, AuthPair, SignInWithInfo,
+    api::{
+        common::ClientVersion,
+        model::{
+            Account, AccountAlias, AccountDeletionInfo, AccountHistoryEvent, AccountIdInternal,
+            AccountIdLight, AccountSetup, AccountState, ApiKey, AuthPair, CalculationSchedule,
+            CalculatorSettings, CalculatorSlotName, CalculatorStateInternal, CreatedServiceToken,
+            HistoryId, LoginMethod, NewCalculationSchedule, NewHistoryEntry, NewOrganization,
+            NewServiceToken, NotificationSettings, OrganizationId, OrganizationSettings,
+            RefreshToken, ScheduleId, ServiceTokenId, SignInWithInfo,
+        },
     },
     config::Config,
     server::database::DatabaseError,
@@ -12,9 +27,11 @@ use crate::{
 };
 
 use super::{
-    cache::{CacheError, DatabaseCache, WriteCacheJson},
-    current::CurrentDataWriteCommands,
-    sqlite::{CurrentDataWriteHandle, SqliteDatabaseError, SqliteUpdateJson},
+    cache::{CacheError, CalculatorBroadcastState, DatabaseCache, WriteCacheJson},
+    current::{CurrentDataWriteCommands, CurrentDataWriteHandle, SelectJson, UpdateJson},
+    history::{HistoryWriteCommands, HistoryWriteHandle},
+    sqlite::SqliteDatabaseError,
+    utils::current_unix_time,
 };
 
 pub struct NoId;
@@ -84,22 +101,25 @@ impl<Target> From<CacheError> for WriteError<error_stack::Report<CacheError>, Ta
     }
 }
 
-// TODO: If one commands does multiple writes to database, move writes to happen
-// in a transaction.
-
 /// One Account can do only one write command at a time.
 pub struct AccountWriteLock;
 
 /// Globally synchronous write commands.
 pub struct WriteCommands<'a> {
     current_write: &'a CurrentDataWriteHandle,
+    history_write: &'a HistoryWriteHandle,
     cache: &'a DatabaseCache,
 }
 
 impl<'a> WriteCommands<'a> {
-    pub fn new(current_write: &'a CurrentDataWriteHandle, cache: &'a DatabaseCache) -> Self {
+    pub fn new(
+        current_write: &'a CurrentDataWriteHandle,
+        history_write: &'a HistoryWriteHandle,
+        cache: &'a DatabaseCache,
+    ) -> Self {
         Self {
             current_write,
+            history_write,
             cache,
         }
     }
@@ -107,63 +127,115 @@ impl<'a> WriteCommands<'a> {
     pub async fn register(
         id_light: AccountIdLight,
         sign_in_with_info: SignInWithInfo,
+        region: Option<String>,
         config: &Config,
         current_data_write: CurrentDataWriteHandle,
+        history_write: &HistoryWriteHandle,
         cache: &DatabaseCache,
     ) -> Result<AccountIdInternal, DatabaseError> {
-        let current = CurrentDataWriteCommands::new(&current_data_write);
-        let account_commands = current.clone().account();
-
-        let account = Account::default();
+        let account = if config.skip_initial_setup() {
+            Account::new_from(AccountState::Normal)
+        } else {
+            Account::default()
+        };
         let account_setup = AccountSetup::default();
+        let notification_settings = NotificationSettings::default();
+        let calculator_settings = CalculatorSettings::default();
+        let calculator_state = CalculatorStateInternal::default();
 
-        // TODO: Use transactions here.
+        let mut transaction = current_data_write.begin().await.convert(id_light)?;
 
-        let id = account_commands
-            .store_account_id(id_light)
+        let id = transaction
+            .account()
+            .store_account_id(id_light, region.as_deref())
             .await
             .convert(id_light)?;
 
-        cache.insert_account_if_not_exists(id).await.convert(id)?;
-
-        account_commands.store_api_key(id, None).await.convert(id)?;
-        account_commands
+        transaction
+            .account()
+            .store_api_key(id, None)
+            .await
+            .convert(id)?;
+        transaction
+            .account()
             .store_refresh_token(id, None)
             .await
             .convert(id)?;
+        // Aliases have 60 random bits, so collisions are not retried.
+        transaction
+            .account()
+            .store_account_alias(id, &AccountAlias::generate())
+            .await
+            .convert(id)?;
 
         if config.components().account {
-            account_commands
+            transaction
+                .account()
                 .store_account(id, &account)
                 .await
                 .convert(id)?;
 
-            cache
-                .write_cache(id.as_light(), |cache| {
-                    cache.account = Some(account.clone().into());
-                    Ok(())
-                })
+            transaction
+                .account()
+                .store_account_setup(id, &account_setup)
                 .await
                 .convert(id)?;
 
-            account_commands
-                .store_account_setup(id, &account_setup)
+            transaction
+                .account()
+                .store_notification_settings(id, &notification_settings)
                 .await
                 .convert(id)?;
 
-            account_commands
+            transaction
+                .account()
                 .store_sign_in_with_info(id, &sign_in_with_info)
                 .await
                 .convert(id)?;
         }
         if config.components().calculator {
-            let _ = current
+            transaction
                 .calculator()
                 .init_calculator_state(id)
                 .await
                 .convert(id)?;
+
+            transaction
+                .calculator()
+                .store_calculator_settings(id, &calculator_settings)
+                .await
+                .convert(id)?;
+        }
+
+        transaction.commit().await.convert(id)?;
+
+        // Update cache only after the account is in the database.
+
+        cache.insert_account_if_not_exists(id).await.convert(id)?;
+
+        if config.components().account {
+            cache
+                .write_cache(id.as_light(), |cache| {
+                    cache.account = Some(account.into());
+                    cache.notification_settings = Some(notification_settings.into());
+                    Ok(())
+                })
+                .await
+                .convert(id)?;
+        }
+        if config.components().calculator {
+            cache
+                .write_cache(id.as_light(), |cache| {
+                    cache.calculator_settings = Some(calculator_settings.into());
+                    cache.calculator_state = Some(calculator_state.into());
+                    Ok(())
+                })
+                .await
+                .convert(id)?;
         }
 
+        record_history(history_write, id, AccountHistoryEvent::Registered).await;
+
         Ok(id)
     }
 
@@ -171,49 +243,171 @@ impl<'a> WriteCommands<'a> {
         &self,
         id: AccountIdInternal,
         pair: AuthPair,
+        expires_unix_time: Option<i64>,
         address: Option<SocketAddr>,
+        client_version: Option<ClientVersion>,
     ) -> Result<(), DatabaseError> {
         let current_access_token = self
             .current_write
             .read()
             .account()
-            .access_token(id)
+            .access_token_hash(id)
             .await
             .convert(id)?;
 
-        self.current()
-            .account()
-            .update_api_key(id, Some(&pair.access))
+        self.store_auth_pair(id, &pair, expires_unix_time).await?;
+
+        self.cache
+            .update_access_token_and_connection(
+                id.as_light(),
+                current_access_token,
+                pair.access,
+                expires_unix_time,
+                address,
+                client_version,
+            )
             .await
-            .convert(id)?;
+            .convert(id)
+    }
+
+    /// Set new tokens without connection address and save the login time.
+    pub async fn login(
+        &self,
+        id: AccountIdInternal,
+        pair: AuthPair,
+        expires_unix_time: Option<i64>,
+    ) -> Result<(), DatabaseError> {
+        self.set_new_auth_pair(id, pair, expires_unix_time, None, None)
+            .await?;
 
         self.current()
             .account()
-            .update_refresh_token(id, Some(&pair.refresh))
+            .update_last_login(id, current_unix_time())
             .await
             .convert(id)?;
 
+        record_history(self.history_write, id, AccountHistoryEvent::Login).await;
+        Ok(())
+    }
+
+    /// Replace tokens if the current refresh token matches. Current
+    /// connection is not changed. Returns false if the refresh token did not
+    /// match.
+    /// Replace tokens if the current refresh token matches. If
+    /// `non_connected_ip` is set, the new access token is for a
+    /// non-connected session.
+    pub async fn refresh_auth_pair(
+        &self,
+        id: AccountIdInternal,
+        current_refresh_token: RefreshToken,
+        pair: AuthPair,
+        expires_unix_time: Option<i64>,
+        non_connected_ip: Option<IpAddr>,
+    ) -> Result<bool, DatabaseError> {
+        let read = self.current_write.read();
+        let account = read.account();
+        let refresh_token = account.refresh_token_hash(id).await.convert(id)?;
+        if refresh_token != Some(current_refresh_token.hash()) {
+            return Ok(false);
+        }
+        let current_access_token = account.access_token_hash(id).await.convert(id)?;
+
+        self.store_auth_pair(id, &pair, expires_unix_time).await?;
+
         self.cache
-            .update_access_token_and_connection(
+            .update_access_token(
                 id.as_light(),
                 current_access_token,
                 pair.access,
-                address,
+                expires_unix_time,
+                non_connected_ip,
             )
             .await
-            .convert(id)
+            .convert(id)?;
+
+        Ok(true)
+    }
+
+    /// Write access and refresh token in a single transaction.
+    async fn store_auth_pair(
+        &self,
+        id: AccountIdInternal,
+        pair: &AuthPair,
+        expires_unix_time: Option<i64>,
+    ) -> Result<(), DatabaseError> {
+        let mut transaction = self.current_write.begin().await.convert(id)?;
+
+        transaction
+            .account()
+            .save_used_refresh_token(id, current_unix_time())
+            .await
+            .convert(id)?;
+
+        transaction
+            .account()
+            .update_api_key(id, Some(&pair.access), expires_unix_time)
+            .await
+            .convert(id)?;
+
+        transaction
+            .account()
+            .update_refresh_token(id, Some(&pair.refresh))
+            .await
+            .convert(id)?;
+
+        transaction.commit().await.convert(id)
     }
 
     /// Remove current connection address, access and refresh tokens.
     pub async fn logout(&self, id: AccountIdInternal) -> Result<(), DatabaseError> {
-        self.current()
+        let current_access_token = self
+            .current_write
+            .read()
+            .account()
+            .access_token_hash(id)
+            .await
+            .convert(id)?;
+
+        let mut transaction = self.current_write.begin().await.convert(id)?;
+
+        transaction
             .account()
             .update_refresh_token(id, None)
             .await
             .convert(id)?;
 
-        self.end_connection_session(id, true).await?;
+        transaction
+            .account()
+            .update_api_key(id, None, None)
+            .await
+            .convert(id)?;
+
+        transaction.commit().await.convert(id)?;
+
+        self.cache
+            .delete_access_token_and_connection(id.as_light(), current_access_token)
+            .await
+            .convert(id)?;
+
+        Ok(())
+    }
+
+    /// Logout and close the WebSocket connection of the account. Used when
+    /// a replaced refresh token is used again.
+    pub async fn revoke_sessions(&self, id: AccountIdInternal) -> Result<(), DatabaseError> {
+        self.logout(id).await?;
+
+        self.cache
+            .close_connection(id.as_light())
+            .await
+            .convert(id)?;
 
+        record_history(
+            self.history_write,
+            id,
+            AccountHistoryEvent::RefreshTokenReused,
+        )
+        .await;
         Ok(())
     }
 
@@ -227,7 +421,7 @@ impl<'a> WriteCommands<'a> {
             self.current_write
                 .read()
                 .account()
-                .access_token(id)
+                .access_token_hash(id)
                 .await
                 .convert(id)?
         } else {
@@ -239,17 +433,730 @@ impl<'a> WriteCommands<'a> {
             .await
             .convert(id)?;
 
+        let mut transaction = self.current_write.begin().await.convert(id)?;
+
+        transaction
+            .account()
+            .update_api_key(id, None, None)
+            .await
+            .convert(id)?;
+
+        transaction.commit().await.convert(id)?;
+
+        Ok(())
+    }
+
+    /// Returns None if account has already `max_tokens` service tokens.
+    pub async fn create_service_token(
+        &self,
+        id: AccountIdInternal,
+        new: NewServiceToken,
+        max_tokens: i64,
+    ) -> Result<Option<CreatedServiceToken>, DatabaseError> {
+        let count = self
+            .current_write
+            .read()
+            .account()
+            .service_token_count(id)
+            .await
+            .convert(id)?;
+
+        if count >= max_tokens {
+            return Ok(None);
+        }
+
+        let token = ApiKey::generate_new();
+        let token_id = self
+            .current()
+            .account()
+            .insert_service_token(id, &token, &new, current_unix_time())
+            .await
+            .convert(id)?;
+
+        self.cache
+            .insert_service_token(id.as_light(), token.hash(), new.scope)
+            .await
+            .convert(id)?;
+
+        Ok(Some(CreatedServiceToken { token_id, token }))
+    }
+
+    /// Returns false if account does not have the service token.
+    pub async fn revoke_service_token(
+        &self,
+        id: AccountIdInternal,
+        token_id: ServiceTokenId,
+    ) -> Result<bool, DatabaseError> {
+        let token_hash = self
+            .current_write
+            .read()
+            .account()
+            .service_token_hash(id, token_id)
+            .await
+            .convert(id)?;
+
+        let token_hash = match token_hash {
+            Some(token_hash) => token_hash,
+            None => return Ok(false),
+        };
+
+        self.current()
+            .account()
+            .delete_service_token(id, token_id)
+            .await
+            .convert(id)?;
+
+        self.cache.remove_service_token(&token_hash).await;
+
+        Ok(true)
+    }
+
+    pub async fn insert_login_history(
+        &self,
+        id: AccountIdInternal,
+        method: LoginMethod,
+        address: Option<IpAddr>,
+    ) -> Result<(), DatabaseError> {
+        let address = address.map(|address| address.to_string());
+        self.current()
+            .account()
+            .insert_login_history(id, method, address.as_deref(), current_unix_time())
+            .await
+            .convert(id)
+    }
+
+    pub async fn create_organization(
+        &self,
+        org: NewOrganization,
+    ) -> Result<OrganizationId, DatabaseError> {
+        let org_id = self
+            .current()
+            .account()
+            .insert_organization(&org, current_unix_time())
+            .await
+            .convert(NoId)?;
+
+        Ok(OrganizationId { org_id })
+    }
+
+    /// Returns false if the organization does not exist.
+    pub async fn set_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+        is_admin: bool,
+    ) -> Result<bool, DatabaseError> {
+        let org = self
+            .current_write
+            .read()
+            .account()
+            .organization(org_id)
+            .await
+            .convert(NoId)?;
+
+        if org.is_none() {
+            return Ok(false);
+        }
+
         self.current()
             .account()
-            .update_api_key(id, None)
+            .upsert_organization_member(org_id, id, is_admin, current_unix_time())
             .await
             .convert(id)?;
 
+        Ok(true)
+    }
+
+    /// Returns false if the organization does not exist.
+    pub async fn set_organization_settings(
+        &self,
+        org_id: OrganizationId,
+        settings: OrganizationSettings,
+    ) -> Result<bool, DatabaseError> {
+        let org = self
+            .current_write
+            .read()
+            .account()
+            .organization(org_id)
+            .await
+            .convert(NoId)?;
+
+        if org.is_none() {
+            return Ok(false);
+        }
+
+        self.current()
+            .account()
+            .upsert_organization_settings(org_id, &settings)
+            .await
+            .convert(NoId)?;
+
+        Ok(true)
+    }
+
+    /// Returns false if the account is not a member of the organization.
+    pub async fn remove_organization_member(
+        &self,
+        org_id: OrganizationId,
+        id: AccountIdInternal,
+    ) -> Result<bool, DatabaseError> {
+        self.current()
+            .account()
+            .delete_organization_member(org_id, id)
+            .await
+            .convert(id)
+    }
+
+    /// Delete calculator history entries which are older than the history
+    /// retention at `unix_time`. Organization settings override
+    /// `default_retention_days`. Returns count of deleted entries.
+    pub async fn delete_expired_history_entries(
+        &self,
+        unix_time: i64,
+        default_retention_days: Option<i64>,
+    ) -> Result<u64, DatabaseError> {
+        self.current()
+            .calculator()
+            .delete_expired_history_entries(unix_time, default_retention_days)
+            .await
+            .convert(NoId)
+    }
+
+    /// Rewrite all versioned JSON data with the current version. Returns
+    /// count of rewritten JSON objects.
+    pub async fn migrate_json(&mut self, config: &Config) -> Result<u64, DatabaseError> {
+        let account_enabled = config.components().account;
+        let calculator_enabled = config.components().calculator;
+        if !account_enabled && !calculator_enabled {
+            return Ok(0);
+        }
+
+        let ids: Vec<AccountIdInternal> = self
+            .current_write
+            .read()
+            .account()
+            .account_ids_stream()
+            .collect::<std::result::Result<_, _>>()
+            .await
+            .convert(NoId)?;
+
+        let mut count = 0;
+        for id in ids {
+            if calculator_enabled {
+                let calculator_settings =
+                    CalculatorSettings::select_json(id, &self.current_write.read())
+                        .await
+                        .with_info_lazy(|| {
+                            format!("CalculatorSettings JSON migration failed, id: {:?}", id)
+                        })?;
+                self.update_data(id, &calculator_settings).await?;
+                count += 1;
+            }
+
+            if !account_enabled {
+                continue;
+            }
+
+            // Reading runs the migrations.
+            let account = Account::select_json(id, &self.current_write.read())
+                .await
+                .with_info_lazy(|| format!("Account JSON migration failed, id: {:?}", id))?;
+            self.update_data(id, &account).await?;
+
+            let account_setup = AccountSetup::select_json(id, &self.current_write.read())
+                .await
+                .with_info_lazy(|| format!("AccountSetup JSON migration failed, id: {:?}", id))?;
+            self.update_data(id, &account_setup).await?;
+
+            let notification_settings =
+                NotificationSettings::select_json(id, &self.current_write.read())
+                    .await
+                    .with_info_lazy(|| {
+                        format!("NotificationSettings JSON migration failed, id: {:?}", id)
+                    })?;
+            self.update_data(id, &notification_settings).await?;
+
+            count += 3;
+        }
+
+        Ok(count)
+    }
+
+    /// Replace cached data with data from SQLite.
+    pub async fn refresh_cache_entry(
+        &self,
+        id: AccountIdInternal,
+        config: &Config,
+    ) -> Result<(), DatabaseError> {
+        if config.components().account {
+            let account = Account::select_json(id, &self.current_write.read())
+                .await
+                .with_info_lazy(|| format!("Cache refresh failed, id: {:?}", id))?;
+            let settings = NotificationSettings::select_json(id, &self.current_write.read())
+                .await
+                .with_info_lazy(|| format!("Cache refresh failed, id: {:?}", id))?;
+            self.cache
+                .write_cache(id.as_light(), |entry| {
+                    entry.account = Some(account.into());
+                    entry.notification_settings = Some(settings.into());
+                    Ok(())
+                })
+                .await
+                .convert(id)?;
+        }
+
+        if config.components().calculator {
+            let settings = CalculatorSettings::select_json(id, &self.current_write.read())
+                .await
+                .with_info_lazy(|| format!("Cache refresh failed, id: {:?}", id))?;
+            let state = CalculatorStateInternal::select_json(id, &self.current_write.read())
+                .await
+                .with_info_lazy(|| format!("Cache refresh failed, id: {:?}", id))?;
+            let readers = self
+                .current_write
+                .read()
+                .calculator()
+                .calculator_state_readers(id)
+                .await
+                .convert(id)?;
+            self.cache
+                .write_cache(id.as_light(), |entry| {
+                    entry.calculator_settings = Some(settings.into());
+                    entry.calculator_state = Some(state.into());
+                    entry.calculator_state_readers = readers.into_iter().collect();
+                    Ok(())
+                })
+                .await
+                .convert(id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove cached data. Account ID mapping, access tokens and
+    /// notification settings are not removed, so event delivery does not
+    /// need the database.
+    pub async fn evict_cache_entry(&self, id: AccountIdInternal) -> Result<(), DatabaseError> {
+        self.cache
+            .write_cache(id.as_light(), |entry| {
+                entry.account = None;
+                entry.calculator_settings = None;
+                entry.calculator_state = None;
+                Ok(())
+            })
+            .await
+            .convert(id)
+    }
+
+    /// Set account state to pending deletion. Account is deleted after
+    /// `info.delete_after_unix_time`.
+    pub async fn request_account_deletion(
+        &mut self,
+        id: AccountIdInternal,
+        account: Account,
+        info: AccountDeletionInfo,
+    ) -> Result<(), DatabaseError> {
+        self.current()
+            .account()
+            .insert_account_deletion(id, info)
+            .await
+            .convert(id)?;
+
+        self.update_account(id, account).await
+    }
+
+    pub async fn cancel_account_deletion(
+        &mut self,
+        id: AccountIdInternal,
+        account: Account,
+    ) -> Result<(), DatabaseError> {
+        self.current()
+            .account()
+            .delete_account_deletion(id)
+            .await
+            .convert(id)?;
+
+        self.update_account(id, account).await
+    }
+
+    /// Save the account and record the state change to history.
+    pub async fn update_account(
+        &mut self,
+        id: AccountIdInternal,
+        account: Account,
+    ) -> Result<(), DatabaseError> {
+        let old_state = Account::select_json(id, &self.current_write.read())
+            .await
+            .with_info_lazy(|| format!("Account read failed, id: {:?}", id))?
+            .state();
+
+        self.update_data(id, &account).await?;
+
+        if old_state != account.state() {
+            let event = AccountHistoryEvent::StateChanged {
+                old: old_state,
+                new: account.state(),
+            };
+            record_history(self.history_write, id, event).await;
+        }
+
         Ok(())
     }
 
+    /// Remove all account data from the database and the cache. WebSocket
+    /// connection of the account is closed.
+    pub async fn delete_account(&self, id: AccountIdInternal) -> Result<(), DatabaseError> {
+        self.current()
+            .account()
+            .delete_account(id)
+            .await
+            .convert(id)?;
+
+        self.cache.delete_account(id.as_light()).await.convert(id)?;
+
+        record_history(self.history_write, id, AccountHistoryEvent::Purged).await;
+        Ok(())
+    }
+
+    /// Save cached account activity times which are not yet in the
+    /// database. Returns count of saved activity times.
+    pub async fn save_account_activity(&self) -> Result<u64, DatabaseError> {
+        let mut count = 0;
+        for (id, unix_time) in self.cache.unsaved_activity().await {
+            self.current()
+                .account()
+                .update_account_activity(id, unix_time)
+                .await
+                .convert(id)?;
+            self.cache
+                .activity_saved(id.as_light(), unix_time)
+                .await
+                .convert(id)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Returns None if account has already `max_schedules` schedules.
+    pub async fn create_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule: NewCalculationSchedule,
+        max_schedules: i64,
+    ) -> Result<Option<ScheduleId>, DatabaseError> {
+        let count = self
+            .current_write
+            .read()
+            .calculator()
+            .calculation_schedule_count(id)
+            .await
+            .convert(id)?;
+
+        if count >= max_schedules {
+            return Ok(None);
+        }
+
+        let schedule_id = self
+            .current()
+            .calculator()
+            .insert_calculation_schedule(id, &schedule)
+            .await
+            .convert(id)?;
+
+        Ok(Some(ScheduleId { schedule_id }))
+    }
+
+    /// Returns false if account does not have the schedule.
+    pub async fn update_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+        schedule: NewCalculationSchedule,
+    ) -> Result<bool, DatabaseError> {
+        self.current()
+            .calculator()
+            .update_calculation_schedule(id, schedule_id, &schedule)
+            .await
+            .convert(id)
+    }
+
+    /// Returns false if account does not have the schedule.
+    pub async fn delete_calculation_schedule(
+        &self,
+        id: AccountIdInternal,
+        schedule_id: ScheduleId,
+    ) -> Result<bool, DatabaseError> {
+        self.current()
+            .calculator()
+            .delete_calculation_schedule(id, schedule_id)
+            .await
+            .convert(id)
+    }
+
+    /// Create or replace calculator slot. Returns false if the slot does
+    /// not exist and account has already `max_slots` slots.
+    pub async fn put_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: CalculatorSlotName,
+        state: String,
+        max_slots: i64,
+    ) -> Result<bool, DatabaseError> {
+        let unix_time = current_unix_time();
+        let updated = self
+            .current()
+            .calculator()
+            .update_calculator_slot(id, &slot_name, &state, unix_time)
+            .await
+            .convert(id)?;
+        if updated {
+            return Ok(true);
+        }
+
+        let count = self
+            .current_write
+            .read()
+            .calculator()
+            .calculator_slot_count(id)
+            .await
+            .convert(id)?;
+
+        if count >= max_slots {
+            return Ok(false);
+        }
+
+        self.current()
+            .calculator()
+            .insert_calculator_slot(id, &slot_name, &state, unix_time)
+            .await
+            .convert(id)?;
+
+        Ok(true)
+    }
+
+    /// Returns false if account does not have the slot.
+    pub async fn delete_calculator_slot(
+        &self,
+        id: AccountIdInternal,
+        slot_name: CalculatorSlotName,
+    ) -> Result<bool, DatabaseError> {
+        self.current()
+            .calculator()
+            .delete_calculator_slot(id, &slot_name)
+            .await
+            .convert(id)
+    }
+
+    pub async fn insert_history_entry(
+        &self,
+        id: AccountIdInternal,
+        entry: NewHistoryEntry,
+    ) -> Result<HistoryId, DatabaseError> {
+        let history_id = self
+            .current()
+            .calculator()
+            .insert_history_entry(id, &entry)
+            .await
+            .convert(id)?;
+
+        Ok(HistoryId { history_id })
+    }
+
+    /// Store evaluation result to history and move the schedule to the next
+    /// run time. Non repeating schedule is removed.
+    pub async fn complete_scheduled_calculation(
+        &self,
+        id: AccountIdInternal,
+        schedule: CalculationSchedule,
+        entry: NewHistoryEntry,
+    ) -> Result<(), DatabaseError> {
+        self.current()
+            .calculator()
+            .insert_history_entry(id, &entry)
+            .await
+            .convert(id)?;
+
+        let schedule_id = ScheduleId {
+            schedule_id: schedule.schedule_id,
+        };
+
+        match schedule.repeat_interval_seconds {
+            Some(interval) => {
+                let mut next_run = schedule.next_run_unix_time + interval;
+                if next_run <= entry.unix_time {
+                    // Skip runs which were missed, for example because the
+                    // server was not running.
+                    next_run = entry.unix_time + interval;
+                }
+                self.current()
+                    .calculator()
+                    .update_calculation_schedule_next_run(schedule_id, next_run)
+                    .await
+                    .convert(id)
+            }
+            None => self
+                .current()
+                .calculator()
+                .delete_calculation_schedule(id, schedule_id)
+                .await
+                .convert(id)
+                .map(|_| ()),
+        }
+    }
+
+    /// Update calculator states of multiple accounts in a single
+    /// transaction. Cache is updated after the transaction is committed.
+    pub async fn update_calculator_states(
+        &self,
+        states: &[(AccountIdInternal, CalculatorStateInternal)],
+    ) -> Result<(), DatabaseError> {
+        let mut transaction = self.current_write.begin().await.convert(NoId)?;
+
+        for (id, state) in states {
+            transaction
+                .calculator()
+                .update_calculator_state(*id, state)
+                .await
+                .convert(*id)?;
+        }
+
+        transaction.commit().await.convert(NoId)?;
+
+        for (id, state) in states {
+            state
+                .write_to_cache(id.as_light(), self.cache)
+                .await
+                .with_info_lazy(|| {
+                    format!("Cache update CalculatorStateInternal failed, id: {:?}", id)
+                })?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn share_calculator_state(
+        &self,
+        id: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> Result<(), DatabaseError> {
+        self.current()
+            .calculator()
+            .insert_calculator_state_share(id, reader)
+            .await
+            .convert(id)?;
+
+        self.cache
+            .write_cache(id.as_light(), |entry| {
+                entry.calculator_state_readers.insert(reader.as_light());
+                Ok(())
+            })
+            .await
+            .convert(id)
+    }
+
+    pub async fn remove_calculator_state_share(
+        &self,
+        id: AccountIdInternal,
+        reader: AccountIdInternal,
+    ) -> Result<(), DatabaseError> {
+        self.current()
+            .calculator()
+            .delete_calculator_state_share(id, reader)
+            .await
+            .convert(id)?;
+
+        self.cache
+            .write_cache(id.as_light(), |entry| {
+                entry.calculator_state_readers.remove(&reader.as_light());
+                Ok(())
+            })
+            .await
+            .convert(id)
+    }
+
+    /// Start broadcasting the calculator state to the members of `org_id`
+    /// or stop broadcasting if `org_id` is None.
+    pub async fn set_calculator_broadcast(
+        &self,
+        id: AccountIdInternal,
+        org_id: Option<OrganizationId>,
+    ) -> Result<(), DatabaseError> {
+        self.cache
+            .write_cache(id.as_light(), |entry| {
+                entry.calculator_broadcast = org_id.map(|org_id| CalculatorBroadcastState {
+                    org_id,
+                    latest: None,
+                });
+                Ok(())
+            })
+            .await
+            .convert(id)
+    }
+
+    /// Organization members which receive the calculator state broadcast of
+    /// the account. Empty if the account does not broadcast or if the
+    /// previous broadcast was sent less than `min_interval` ago.
+    /// Broadcasting ends if the account is no longer an admin of the
+    /// organization.
+    pub async fn calculator_broadcast_recipients(
+        &self,
+        id: AccountIdInternal,
+        min_interval: Duration,
+    ) -> Result<Vec<AccountIdLight>, DatabaseError> {
+        let now = Instant::now();
+        let org_id = self
+            .cache
+            .write_cache(id.as_light(), |entry| {
+                match &mut entry.calculator_broadcast {
+                    Some(broadcast)
+                        if broadcast
+                            .latest
+                            .map(|latest| now.saturating_duration_since(latest) >= min_interval)
+                            .unwrap_or(true) =>
+                    {
+                        broadcast.latest = Some(now);
+                        Ok(Some(broadcast.org_id))
+                    }
+                    _ => Ok(None),
+                }
+            })
+            .await
+            .convert(id)?;
+
+        let org_id = match org_id {
+            Some(org_id) => org_id,
+            None => return Ok(vec![]),
+        };
+
+        let is_admin = self
+            .current_write
+            .read()
+            .account()
+            .organization_membership(id)
+            .await
+            .convert(id)?
+            .map(|m| m.is_admin && m.organization.org_id == org_id.org_id)
+            .unwrap_or(false);
+        if !is_admin {
+            self.set_calculator_broadcast(id, None).await?;
+            return Ok(vec![]);
+        }
+
+        let members = self
+            .current_write
+            .read()
+            .account()
+            .organization_member_ids(org_id)
+            .await
+            .convert(id)?;
+        Ok(members
+            .into_iter()
+            .filter(|member| *member != id.as_light())
+            .collect())
+    }
+
     pub async fn update_data<
-        T: Clone + Debug + Send + SqliteUpdateJson + WriteCacheJson + Sync + 'static,
+        T: Clone + Debug + Send + UpdateJson + WriteCacheJson + Sync + 'static,
     >(
         &mut self,
         id: AccountIdInternal,
@@ -270,6 +1177,22 @@ impl<'a> WriteCommands<'a> {
     }
 }
 
+/// History is not part of the current data, so history write errors are
+/// logged and the already saved current data is not changed.
+async fn record_history(
+    history_write: &HistoryWriteHandle,
+    id: AccountIdInternal,
+    event: AccountHistoryEvent,
+) {
+    let result = HistoryWriteCommands::new(history_write)
+        .insert_account_event(id.as_light(), event, current_unix_time())
+        .await
+        .convert(id);
+    if let Err(e) = result {
+        error!("Saving account history failed: {e:?}");
+    }
+}
+
 /// Commands that can run concurrently with other write commands, but which have
 /// limitation that one account can execute only one command at a time.
 /// It possible to run this and normal write command concurrently for
@@ -291,3 +1214,96 @@ impl<'a> WriteCommandsAccount<'a> {
         CurrentDataWriteCommands::new(&self.current_write)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{DatabaseId, NoId};
+    use crate::{
+        api::{
+            model::{AccountIdInternal, AccountIdLight, ApiKey, AuthPair, RefreshToken},
+            GetUsers, ReadDatabase, WriteDatabase,
+        },
+        server::test_support::TestApp,
+    };
+
+    proptest! {
+        #[test]
+        fn database_id_keeps_account_id(account_id in any::<u128>(), account_row_id in any::<i64>()) {
+            let internal = AccountIdInternal {
+                account_id: uuid::Uuid::from_u128(account_id),
+                account_row_id,
+            };
+            prop_assert!(
+                matches!(DatabaseId::from(internal), DatabaseId::Internal(id) if id == internal)
+            );
+
+            let light = internal.as_light();
+            prop_assert!(
+                matches!(DatabaseId::from(light), DatabaseId::Light(id) if id == light)
+            );
+        }
+    }
+
+    #[test]
+    fn no_id_is_empty_database_id() {
+        assert!(matches!(DatabaseId::from(NoId), DatabaseId::Empty));
+    }
+
+    #[tokio::test]
+    async fn connection_login_updates_last_seen_address() {
+        let app = TestApp::new().await;
+        let (id, _) = app.register_and_login().await;
+        let state = app.state();
+        let internal_id = state.users().get_internal_id(id).await.unwrap();
+
+        let address = "192.0.2.1:5000".parse().unwrap();
+        let pair = AuthPair::new(RefreshToken::generate_new(), ApiKey::generate_new());
+        state
+            .write_database()
+            .set_new_auth_pair(internal_id, pair, Some(address), None)
+            .await
+            .unwrap();
+
+        let info = state.read_database().session_info(id).await.unwrap();
+        assert_eq!(info.last_seen_address.as_deref(), Some("192.0.2.1:5000"));
+        assert_eq!(info.connection_address.as_deref(), Some("192.0.2.1:5000"));
+
+        drop(state);
+        app.close().await;
+    }
+
+    #[tokio::test]
+    async fn replaced_refresh_tokens_have_generations() {
+        let app = TestApp::new().await;
+        let (id, login) = app.register_and_login().await;
+        let state = app.state();
+        let id = state.users().get_internal_id(id).await.unwrap();
+
+        let mut tokens = vec![login.account.refresh];
+        for _ in 0..2 {
+            let pair = AuthPair::new(RefreshToken::generate_new(), ApiKey::generate_new());
+            tokens.push(pair.refresh.clone());
+            state
+                .write_database()
+                .set_new_auth_pair(id, pair, None, None)
+                .await
+                .unwrap();
+        }
+
+        let mut generations = vec![];
+        for token in &tokens {
+            let generation = state
+                .read_database()
+                .used_refresh_token_generation(id, token)
+                .await
+                .unwrap();
+            generations.push(generation);
+        }
+        assert_eq!(generations, [Some(1), Some(2), None]);
+
+        drop(state);
+        app.close().await;
+    }
+}
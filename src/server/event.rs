@@ -0,0 +1,137 @@
+//! Event delivery to connected WebSocket clients
+//!
+//! Every WebSocket connection creates an event channel for its account.
+//! Sender of the channel is stored in the account's cache entry, so the
+//! latest connection of the account receives the events. Events are not
+//! stored, so events are lost if the account does not have a WebSocket
+//! connection. Events which the account has disabled from its notification
+//! settings are not sent.
+
+use error_stack::Result;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::warn;
+
+use crate::{
+    api::{
+        common::EventToClient,
+        model::{AccountIdLight, NotificationDelivery},
+    },
+    server::database::cache::{CacheError, DatabaseCache},
+    utils::ConvertCommandError,
+};
+
+/// Events which are waiting to be sent to the WebSocket. If the buffer is
+/// full, new events are dropped.
+pub const EVENT_CHANNEL_BUFFER_SIZE: usize = 32;
+
+pub type EventSender = mpsc::Sender<EventToClient>;
+pub type EventReceiver = mpsc::Receiver<EventToClient>;
+
+pub struct EventManager<'a> {
+    cache: &'a DatabaseCache,
+}
+
+impl<'a> EventManager<'a> {
+    pub fn new(cache: &'a DatabaseCache) -> Self {
+        Self { cache }
+    }
+
+    /// Create event channel for a new WebSocket connection. Replaces the
+    /// channel of the previous connection.
+    pub async fn connect(&self, id: AccountIdLight) -> Result<EventReceiver, CacheError> {
+        let (sender, receiver) = mpsc::channel(EVENT_CHANNEL_BUFFER_SIZE);
+        self.cache
+            .write_cache(id, |entry| {
+                entry.event_sender = Some(sender);
+                Ok(())
+            })
+            .await
+            .attach(id)?;
+        Ok(receiver)
+    }
+
+    /// Send event to the WebSocket connection of the account if the
+    /// connection exists and the event is not disabled. This does not wait
+    /// for the event to be sent.
+    pub async fn send_connected_event(&self, id: AccountIdLight, event: EventToClient) {
+        let result = self
+            .cache
+            .write_cache(id, |entry| {
+                let delivery = entry
+                    .notification_settings
+                    .as_ref()
+                    .map(|settings| settings.delivery(&event))
+                    .unwrap_or_default();
+                if delivery == NotificationDelivery::Disabled {
+                    return Ok(());
+                }
+
+                if let Some(sender) = &entry.event_sender {
+                    match sender.try_send(event) {
+                        Ok(()) => (),
+                        Err(TrySendError::Full(event)) => {
+                            warn!("Event channel is full, event {:?} dropped", event)
+                        }
+                        // Connection is closed.
+                        Err(TrySendError::Closed(_)) => entry.event_sender = None,
+                    }
+                }
+                Ok(())
+            })
+            .await;
+
+        if let Err(e) = result {
+            warn!("Sending event failed: {:?}", e.e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        api::{
+            account::PATH_NOTIFICATION_SETTINGS,
+            common::EventToClient,
+            model::{NotificationDelivery, NotificationSettings},
+            GetEventManager,
+        },
+        server::test_support::TestApp,
+    };
+
+    #[tokio::test]
+    async fn disabled_event_is_not_sent() {
+        let app = TestApp::new().await;
+        let (id, login) = app.register_and_login().await;
+        let access = Some(&login.account.access);
+        let state = app.state();
+
+        let settings = NotificationSettings {
+            account_state_changed: NotificationDelivery::Disabled,
+            ..NotificationSettings::default()
+        };
+        let response = app
+            .put_json(PATH_NOTIFICATION_SETTINGS, access, &settings)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut events = state.event_manager().connect(id).await.unwrap();
+        let event_manager = state.event_manager();
+        event_manager
+            .send_connected_event(id, EventToClient::AccountStateChanged)
+            .await;
+        event_manager
+            .send_connected_event(id, EventToClient::CalculatorStateChanged)
+            .await;
+
+        assert!(matches!(
+            events.try_recv(),
+            Ok(EventToClient::CalculatorStateChanged)
+        ));
+        assert!(events.try_recv().is_err());
+
+        drop(state);
+        app.close().await;
+    }
+}
@@ -0,0 +1,132 @@
+//! GeoIP lookups for session info and security audit events
+//!
+//! Optional GeoIP database is a CSV file where each line is
+//! `first_ip,last_ip,country_code`. Both IPv4 and IPv6 ranges can be in the
+//! same file. For example the "IP to Country Lite" database from db-ip.com
+//! uses this format.
+
+use std::{net::IpAddr, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::utils::IntoReportExt;
+
+#[derive(thiserror::Error, Debug)]
+pub enum GeoIpError {
+    #[error("Reading GeoIP database file failed")]
+    File,
+    #[error("Parsing GeoIP database failed")]
+    Parse,
+}
+
+/// Coarse location of an IP address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeoLocation {
+    /// ISO 3166-1 alpha-2 country code.
+    pub country: String,
+}
+
+pub trait GeoIpLookup: Send + Sync {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoLocation>;
+}
+
+/// Default lookup when GeoIP database is not configured.
+pub struct NoGeoIp;
+
+impl GeoIpLookup for NoGeoIp {
+    fn lookup(&self, _ip: IpAddr) -> Option<GeoLocation> {
+        None
+    }
+}
+
+/// Load GeoIP database if it is configured.
+pub fn load_geoip(database: Option<&Path>) -> Result<Box<dyn GeoIpLookup>, GeoIpError> {
+    match database {
+        Some(file) => Ok(Box::new(CsvGeoIpDatabase::load(file)?)),
+        None => Ok(Box::new(NoGeoIp)),
+    }
+}
+
+/// GeoIP database loaded to memory from CSV file.
+pub struct CsvGeoIpDatabase {
+    /// Sorted by range start.
+    ipv4: Vec<(u32, u32, GeoLocation)>,
+    /// Sorted by range start.
+    ipv6: Vec<(u128, u128, GeoLocation)>,
+}
+
+impl CsvGeoIpDatabase {
+    pub fn load(file: &Path) -> Result<Self, GeoIpError> {
+        let text = std::fs::read_to_string(file)
+            .into_error(GeoIpError::File)
+            .attach_printable_lazy(|| file.display().to_string())?;
+
+        let mut ipv4 = vec![];
+        let mut ipv6 = vec![];
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (first, last, country) = Self::parse_line(line)
+                .attach_printable_lazy(|| format!("Line {}: {}", i + 1, line))?;
+            let location = GeoLocation { country };
+
+            match (first, last) {
+                (IpAddr::V4(first), IpAddr::V4(last)) => {
+                    ipv4.push((u32::from(first), u32::from(last), location))
+                }
+                (IpAddr::V6(first), IpAddr::V6(last)) => {
+                    ipv6.push((u128::from(first), u128::from(last), location))
+                }
+                _ => {
+                    return Err(Report::new(GeoIpError::Parse)).attach_printable(format!(
+                        "Line {}: IP address versions do not match",
+                        i + 1
+                    ))
+                }
+            }
+        }
+
+        ipv4.sort_by_key(|(first, _, _)| *first);
+        ipv6.sort_by_key(|(first, _, _)| *first);
+
+        Ok(Self { ipv4, ipv6 })
+    }
+
+    fn parse_line(line: &str) -> Result<(IpAddr, IpAddr, String), GeoIpError> {
+        let mut fields = line.split(',').map(|f| f.trim().trim_matches('"'));
+        let mut next_field = || fields.next().ok_or(Report::new(GeoIpError::Parse));
+
+        let first: IpAddr = next_field()?.parse().into_error(GeoIpError::Parse)?;
+        let last: IpAddr = next_field()?.parse().into_error(GeoIpError::Parse)?;
+        let country = next_field()?.to_string();
+
+        Ok((first, last, country))
+    }
+
+    fn find<T: Ord + Copy>(ranges: &[(T, T, GeoLocation)], ip: T) -> Option<GeoLocation> {
+        // Index of the first range which starts after the IP address.
+        let i = ranges.partition_point(|(first, _, _)| *first <= ip);
+        let (_, last, location) = ranges.get(i.checked_sub(1)?)?;
+        if ip <= *last {
+            Some(location.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl GeoIpLookup for CsvGeoIpDatabase {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoLocation> {
+        match ip {
+            IpAddr::V4(ip) => Self::find(&self.ipv4, u32::from(ip)),
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => self.lookup(IpAddr::V4(ip)),
+                None => Self::find(&self.ipv6, u128::from(ip)),
+            },
+        }
+    }
+}
@@ -1,9 +1,17 @@
 //! Routes for server to server connections
 
+pub mod api_key_cache;
+
+use std::sync::Arc;
+
 use api_client::apis::{accountinternal_api, configuration::Configuration};
-use axum::{routing::get, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post},
+    Router,
+};
 
-use error_stack::Result;
+use error_stack::{Result, ResultExt};
 
 use hyper::StatusCode;
 
@@ -15,10 +23,18 @@ use crate::{
     utils::IntoReportExt,
 };
 
-use crate::{api::model::ApiKey, config::Config};
+use crate::{
+    api::model::{
+        Account, AccountIdInternal, AccountIdLight, AccountState, ApiKey, SignInWithInfo, TokenHash,
+    },
+    config::Config,
+};
+
+use self::api_key_cache::RemoteApiKeyCache;
 
 use super::{
     app::AppState,
+    config_reload::ConfigReloader,
     database::{
         commands::WriteCommandRunnerHandle,
         read::ReadCommands,
@@ -26,9 +42,6 @@ use super::{
     },
 };
 
-// TODO: Use TLS for checking that all internal communication comes from trusted
-//       sources.
-
 #[derive(thiserror::Error, Debug)]
 pub enum InternalApiError {
     #[error("API request failed")]
@@ -67,6 +80,236 @@ impl InternalApp {
                     move |param1| api::account::internal::internal_get_account_state(param1, state)
                 }),
             )
+            .route(
+                api::account::internal::PATH_INTERNAL_MIGRATE_JSON,
+                post({
+                    let state = state.clone();
+                    move || api::account::internal::post_migrate_json(state)
+                }),
+            )
+            .route(
+                api::account::internal::PATH_INTERNAL_GET_WEBHOOK_DELIVERIES,
+                get({
+                    let state = state.clone();
+                    move || api::account::internal::internal_get_webhook_deliveries(state)
+                }),
+            )
+            .route(
+                api::account::internal::PATH_INTERNAL_GET_ACCOUNT_REPORT,
+                get({
+                    let state = state.clone();
+                    move || api::account::internal::internal_get_account_report(state)
+                }),
+            )
+    }
+
+    pub fn create_admin_router(state: AppState) -> Router {
+        Router::new()
+            .route(
+                api::admin::PATH_INTERNAL_GET_ADMIN_ACCOUNTS,
+                get({
+                    let state = state.clone();
+                    move |param1| api::admin::internal_get_admin_accounts(param1, state)
+                }),
+            )
+            .route(
+                api::admin::PATH_INTERNAL_GET_ADMIN_ACCOUNT,
+                get({
+                    let state = state.clone();
+                    move |param1| api::admin::internal_get_admin_account(param1, state)
+                }),
+            )
+            .route(
+                api::admin::PATH_INTERNAL_POST_ADMIN_LOGOUT,
+                post({
+                    let state = state.clone();
+                    move |param1| api::admin::internal_post_admin_logout(param1, state)
+                }),
+            )
+            .route(
+                api::admin::PATH_INTERNAL_POST_ADMIN_BAN,
+                post({
+                    let state = state.clone();
+                    move |param1| api::admin::internal_post_admin_ban(param1, state)
+                }),
+            )
+            .route(
+                api::admin::PATH_INTERNAL_POST_ADMIN_UNBAN,
+                post({
+                    let state = state.clone();
+                    move |param1| api::admin::internal_post_admin_unban(param1, state)
+                }),
+            )
+            .route(
+                api::admin::PATH_INTERNAL_POST_ADMIN_SERVICE_TOKEN,
+                post({
+                    let state = state.clone();
+                    move |param1, param2| {
+                        api::admin::internal_post_admin_service_token(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::admin::PATH_INTERNAL_ADMIN_STALE_ACCOUNT_CLEANUP,
+                get({
+                    let state = state.clone();
+                    move || api::admin::internal_get_stale_account_cleanup(state)
+                })
+                .post({
+                    let state = state.clone();
+                    move |param1| api::admin::internal_post_stale_account_cleanup(param1, state)
+                }),
+            )
+            .route(
+                api::organization::PATH_INTERNAL_POST_ORGANIZATION,
+                post({
+                    let state = state.clone();
+                    move |param1| api::organization::internal_post_organization(param1, state)
+                }),
+            )
+            .route(
+                api::organization::PATH_INTERNAL_ORGANIZATION_MEMBERS,
+                get({
+                    let state = state.clone();
+                    move |param1| {
+                        api::organization::internal_get_organization_members(param1, state)
+                    }
+                })
+                .put({
+                    let state = state.clone();
+                    move |param1, param2| {
+                        api::organization::internal_put_organization_member(param1, param2, state)
+                    }
+                }),
+            )
+            .route(
+                api::organization::PATH_INTERNAL_DELETE_ORGANIZATION_MEMBER,
+                delete({
+                    let state = state.clone();
+                    move |param1| {
+                        api::organization::internal_delete_organization_member(param1, state)
+                    }
+                }),
+            )
+            .route(
+                api::organization::PATH_INTERNAL_ORGANIZATION_SETTINGS,
+                get({
+                    let state = state.clone();
+                    move |param1| {
+                        api::organization::internal_get_organization_settings(param1, state)
+                    }
+                })
+                .put({
+                    let state = state.clone();
+                    move |param1, param2| {
+                        api::organization::internal_put_organization_settings(param1, param2, state)
+                    }
+                }),
+            )
+    }
+
+    pub fn create_cache_router(state: AppState) -> Router {
+        Router::new()
+            .route(
+                api::cache::PATH_INTERNAL_GET_CACHE_ENTRY,
+                get({
+                    let state = state.clone();
+                    move |param1| api::cache::internal_get_cache_entry(param1, state)
+                }),
+            )
+            .route(
+                api::cache::PATH_INTERNAL_POST_REFRESH_CACHE_ENTRY,
+                post({
+                    let state = state.clone();
+                    move |param1| api::cache::internal_post_refresh_cache_entry(param1, state)
+                }),
+            )
+            .route(
+                api::cache::PATH_INTERNAL_POST_EVICT_CACHE_ENTRY,
+                post({
+                    let state = state.clone();
+                    move |param1| api::cache::internal_post_evict_cache_entry(param1, state)
+                }),
+            )
+    }
+
+    pub fn create_metrics_router(state: AppState) -> Router {
+        Router::new().route(
+            api::metrics::PATH_INTERNAL_GET_METRICS,
+            get({
+                let state = state.clone();
+                move || api::metrics::internal_get_metrics(state)
+            }),
+        )
+    }
+
+    pub fn create_reload_router(reloader: Arc<ConfigReloader>) -> Router {
+        Router::new().route(
+            api::reload::PATH_INTERNAL_POST_RELOAD_CONFIG,
+            post(move || api::reload::internal_post_reload_config(reloader.clone())),
+        )
+    }
+
+    pub fn create_clock_router(state: AppState) -> Router {
+        Router::new().route(
+            api::clock::PATH_INTERNAL_POST_CLOCK_ADVANCE,
+            post({
+                let state = state.clone();
+                move |param1| api::clock::internal_post_clock_advance(param1, state)
+            }),
+        )
+    }
+
+    /// Health routes are also on the public router, so this is not merged
+    /// to the public router in debug mode.
+    pub fn create_health_router(state: AppState) -> Router {
+        Router::new()
+            .route(
+                api::common::PATH_GET_HEALTH_LIVE,
+                get(api::common::get_health_live),
+            )
+            .route(
+                api::common::PATH_GET_HEALTH_READY,
+                get({
+                    let state = state.clone();
+                    move || api::common::get_health_ready(state)
+                }),
+            )
+    }
+
+    /// Account server sends revoked access tokens to this router if the
+    /// account component is disabled.
+    pub fn create_api_key_cache_router(state: AppState) -> Router {
+        Router::new().route(
+            api::account::internal::PATH_INTERNAL_POST_API_KEY_REVOKED,
+            post({
+                let state = state.clone();
+                move |body| api::account::internal::internal_post_api_key_revoked(body, state)
+            }),
+        )
+    }
+
+    pub fn create_calculator_server_router(state: AppState) -> Router {
+        Router::new().route(
+            api::calculator::PATH_INTERNAL_GET_EVALUATION_CACHE_STATS,
+            get({
+                let state = state.clone();
+                move || api::calculator::internal_get_evaluation_cache_stats(state)
+            }),
+        )
+    }
+
+    pub fn create_standby_router(state: AppState) -> Router {
+        Router::new()
+            .route(
+                api::standby::PATH_INTERNAL_STANDBY_SNAPSHOT,
+                post({
+                    let state = state.clone();
+                    move |body| api::standby::post_standby_snapshot(body, state)
+                }),
+            )
+            // Snapshot is the whole database file.
+            .layer(DefaultBodyLimit::disable())
     }
 }
 
@@ -78,9 +321,9 @@ pub struct InternalApiClient {
 }
 
 impl InternalApiClient {
-    pub fn new(base_urls: InternalApiUrls) -> Self {
-        let client = reqwest::Client::new();
-
+    /// Client certificate of `client` is used for mutual TLS if the internal
+    /// API requires it.
+    pub fn new(base_urls: InternalApiUrls, client: reqwest::Client) -> Self {
         let account = base_urls.account_base_url.map(|url| {
             let url = url.as_str().trim_end_matches('/').to_string();
 
@@ -103,16 +346,12 @@ impl InternalApiClient {
     }
 }
 
-pub enum AuthResponse {
-    Ok,
-    Unauthorized,
-}
-
 /// Handle requests to internal API. If the required feature is located
 /// on the current server, then request is not made.
 pub struct InternalApiManager<'a> {
     config: &'a Config,
     api_client: &'a InternalApiClient,
+    api_key_cache: &'a RemoteApiKeyCache,
     keys: ApiKeyManager<'a>,
     read_database: ReadCommands<'a>,
     write_database: &'a WriteCommandRunnerHandle,
@@ -123,6 +362,7 @@ impl<'a> InternalApiManager<'a> {
     pub fn new(
         config: &'a Config,
         api_client: &'a InternalApiClient,
+        api_key_cache: &'a RemoteApiKeyCache,
         keys: ApiKeyManager<'a>,
         read_database: ReadCommands<'a>,
         write_database: &'a WriteCommandRunnerHandle,
@@ -131,6 +371,7 @@ impl<'a> InternalApiManager<'a> {
         Self {
             config,
             api_client,
+            api_key_cache,
             keys,
             read_database,
             write_database,
@@ -138,41 +379,137 @@ impl<'a> InternalApiManager<'a> {
         }
     }
 
-    /// Check that API key is valid. Use this only from ApiKey checker handler.
-    /// This function will cache the account ID, so it can be found using normal
-    /// database calls after this runs.
-    pub async fn check_api_key(&self, key: ApiKey) -> Result<AuthResponse, InternalApiError> {
-        if self.keys.api_key_exists(&key).await.is_some() {
-            Ok(AuthResponse::Ok)
-        } else if !self.config.components().account {
-            // Check ApiKey from external service
+    /// Check that API key is valid. Returns the account ID if the key is
+    /// valid. If the account component is on another server, the key is
+    /// checked from the account server and accepted keys are cached. The
+    /// account server notifies about logout and other revocations, and
+    /// cached keys expire after the cache TTL. The account is added to the
+    /// local database when the account is seen for the first time.
+    pub async fn check_api_key(
+        &self,
+        key: ApiKey,
+    ) -> Result<Option<AccountIdInternal>, InternalApiError> {
+        if self.config.components().account {
+            return Ok(self.keys.api_key_exists(&key).await);
+        }
+
+        if let Some(id) = self.api_key_cache.get(&key) {
+            return Ok(Some(id));
+        }
+
+        let result = accountinternal_api::check_api_key(
+            self.api_client.account()?,
+            api_client::models::ApiKey {
+                api_key: key.as_str().to_string(),
+            },
+        )
+        .await;
+
+        match result {
+            Ok(id) => {
+                let id = self
+                    .local_account_id(AccountIdLight::new(id.account_id))
+                    .await?;
+                self.api_key_cache.insert(&key, id);
+                Ok(Some(id))
+            }
+            Err(api_client::apis::Error::ResponseError(response))
+                if response.status == StatusCode::NOT_FOUND =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e).into_error(InternalApiError::ApiRequest),
+        }
+    }
+
+    /// Remove revoked API key from the cache of remotely checked keys.
+    pub fn api_key_revoked(&self, hash: &TokenHash) {
+        self.api_key_cache.remove(hash);
+    }
+
+    /// Get internal ID of an account from the account server. The account
+    /// is registered to the local database if it does not exist.
+    async fn local_account_id(
+        &self,
+        id: AccountIdLight,
+    ) -> Result<AccountIdInternal, InternalApiError> {
+        if let Ok(id) = self.account_id_manager.get_internal_id(id).await {
+            return Ok(id);
+        }
 
-            let result = accountinternal_api::check_api_key(
-                self.api_client.account()?,
-                api_client::models::ApiKey {
-                    api_key: key.into_string(),
-                },
+        info!("Adding account from account server to local database");
+        // Local data is stored in the default region of this server.
+        let result = self
+            .write_database
+            .account()
+            .register(
+                id,
+                SignInWithInfo::default(),
+                self.config.default_region().map(ToString::to_string),
             )
             .await;
+        match result {
+            Ok(id) => Ok(id),
+            // Concurrent request registered the account.
+            Err(_) => self
+                .account_id_manager
+                .get_internal_id(id)
+                .await
+                .change_context(InternalApiError::DatabaseError),
+        }
+    }
 
-            match result {
-                Ok(_res) => {
-                    // TODO: Cache this API key. Also needed for initializing
-                    // database tables.
-                    Ok(AuthResponse::Ok)
-                }
-                Err(api_client::apis::Error::ResponseError(response))
-                    if response.status == StatusCode::UNAUTHORIZED =>
-                {
-                    // TODO: NOTE: Logging every error is not good as it would spam
-                    // the log, but maybe an error counter or logging just
-                    // once for a while.
-                    Ok(AuthResponse::Unauthorized)
-                }
-                Err(e) => Err(e).into_error(InternalApiError::ApiRequest),
-            }
-        } else {
-            Ok(AuthResponse::Unauthorized)
+    /// Account data is in the cache if the account component is enabled,
+    /// so the internal API is used only when the account component is on
+    /// another server.
+    pub async fn get_account_state(
+        &self,
+        account_id: AccountIdInternal,
+    ) -> Result<Account, InternalApiError> {
+        if self.config.components().account {
+            return self
+                .read_database
+                .read_json::<Account>(account_id)
+                .await
+                .change_context(InternalApiError::DatabaseError);
         }
+
+        let account = accountinternal_api::internal_get_account_state(
+            self.api_client.account()?,
+            &account_id.as_light().to_string(),
+        )
+        .await
+        .into_error(InternalApiError::ApiRequest)?;
+
+        let state = match account.state {
+            api_client::models::AccountState::InitialSetup => AccountState::InitialSetup,
+            api_client::models::AccountState::Normal => AccountState::Normal,
+            api_client::models::AccountState::Banned => AccountState::Banned,
+            api_client::models::AccountState::PendingDeletion => AccountState::PendingDeletion,
+        };
+
+        Ok(Account::new_from(state))
+    }
+
+    /// Check that the account server internal API responds. Does nothing if
+    /// the account component is enabled.
+    pub async fn check_account_server(&self) -> Result<(), InternalApiError> {
+        if self.config.components().account {
+            return Ok(());
+        }
+
+        let account = self.api_client.account()?;
+        account
+            .client
+            .get(format!(
+                "{}{}",
+                account.base_path,
+                api::common::PATH_GET_HEALTH_LIVE
+            ))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .into_error(InternalApiError::ApiRequest)?;
+        Ok(())
     }
 }
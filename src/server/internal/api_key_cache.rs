@@ -0,0 +1,157 @@
+//! Access tokens checked from the account server
+//!
+//! If the account component is on another server, [RemoteApiKeyCache]
+//! stores hashes of the access tokens which the account server has
+//! accepted, so every request does not need an internal API request. The
+//! account server sends hashes of the revoked access tokens to the
+//! calculator servers with [ApiKeyRevocationNotifier]. Cached tokens expire
+//! after the TTL, so a lost notification does not keep a revoked token
+//! valid for long.
+
+use std::{
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use tracing::warn;
+
+use crate::{
+    api::{
+        account::internal::PATH_INTERNAL_POST_API_KEY_REVOKED,
+        model::{AccountIdInternal, ApiKey, TokenHash},
+    },
+    config::Config,
+};
+
+pub const DEFAULT_API_KEY_CACHE_CAPACITY: usize = 10_000;
+pub const DEFAULT_API_KEY_CACHE_TTL_SECONDS: u64 = 60;
+
+struct CachedApiKey {
+    id: AccountIdInternal,
+    cached: Instant,
+}
+
+/// Bounded LRU cache for access tokens which the account server accepted.
+/// Keys are access token hashes, so revocation notifications do not need
+/// the plaintext tokens.
+pub struct RemoteApiKeyCache {
+    /// None if cache is disabled.
+    cache: Option<Mutex<LruCache<TokenHash, CachedApiKey>>>,
+    ttl: Duration,
+}
+
+impl RemoteApiKeyCache {
+    /// Cache is disabled if the account component is enabled or the
+    /// configured capacity is zero.
+    pub fn new(config: &Config) -> Self {
+        let cache_config = config.api_key_cache();
+        let capacity = cache_config
+            .and_then(|c| c.capacity)
+            .unwrap_or(DEFAULT_API_KEY_CACHE_CAPACITY);
+        let ttl_seconds = cache_config
+            .and_then(|c| c.ttl_seconds)
+            .unwrap_or(DEFAULT_API_KEY_CACHE_TTL_SECONDS);
+        let cache = if config.components().account {
+            None
+        } else {
+            NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity)))
+        };
+
+        Self {
+            cache,
+            ttl: Duration::from_secs(ttl_seconds),
+        }
+    }
+
+    /// Expired tokens are removed.
+    pub fn get(&self, key: &ApiKey) -> Option<AccountIdInternal> {
+        let mut cache = Self::lock(self.cache.as_ref()?);
+        let hash = key.hash();
+        let entry = cache.get(&hash)?;
+        if entry.cached.elapsed() < self.ttl {
+            Some(entry.id)
+        } else {
+            cache.pop(&hash);
+            None
+        }
+    }
+
+    pub fn insert(&self, key: &ApiKey, id: AccountIdInternal) {
+        if let Some(cache) = &self.cache {
+            let entry = CachedApiKey {
+                id,
+                cached: Instant::now(),
+            };
+            Self::lock(cache).put(key.hash(), entry);
+        }
+    }
+
+    pub fn remove(&self, hash: &TokenHash) {
+        if let Some(cache) = &self.cache {
+            Self::lock(cache).pop(hash);
+        }
+    }
+
+    fn lock(
+        cache: &Mutex<LruCache<TokenHash, CachedApiKey>>,
+    ) -> std::sync::MutexGuard<'_, LruCache<TokenHash, CachedApiKey>> {
+        // Cache is always in valid state, so poisoning can be ignored.
+        cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Sends hashes of revoked access tokens to the internal APIs of the
+/// calculator servers. Does nothing if calculator servers are not
+/// configured.
+pub struct ApiKeyRevocationNotifier {
+    client: reqwest::Client,
+    urls: Vec<String>,
+}
+
+impl ApiKeyRevocationNotifier {
+    pub fn new(config: &Config) -> Self {
+        let urls = config
+            .external_services()
+            .calculator_internal
+            .iter()
+            .flatten()
+            .map(|url| {
+                format!(
+                    "{}{}",
+                    url.as_str().trim_end_matches('/'),
+                    PATH_INTERNAL_POST_API_KEY_REVOKED
+                )
+            })
+            .collect();
+
+        Self {
+            client: config.internal_api_http_client().clone(),
+            urls,
+        }
+    }
+
+    /// Notifications are sent in the background, so this does not wait
+    /// for the calculator servers.
+    pub fn notify(&self, hashes: Vec<TokenHash>) {
+        if self.urls.is_empty() {
+            return;
+        }
+
+        for hash in hashes {
+            for url in &self.urls {
+                let request = self.client.post(url).json(&hash);
+                tokio::spawn(async move {
+                    let result = request
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status());
+                    if let Err(e) = result {
+                        warn!("Access token revocation notification failed: {e}");
+                    }
+                });
+            }
+        }
+    }
+}
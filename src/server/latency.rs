@@ -0,0 +1,178 @@
+//! Route latency budgets
+//!
+//! [check_latency_budget] measures the whole request and logs a warning if
+//! it takes longer than the budget of the route. The warning has the time
+//! spent in each [Phase], so it is possible to see if the slow part was
+//! access token checking, cache reads or database access. Phases are
+//! recorded with [record_phase] and those are no-ops outside requests.
+//!
+//! Phase times can overlap. For example the auth phase includes the cache
+//! reads of access token checking. Database phase contains database reads
+//! and the time spent waiting for write command results.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, State},
+    middleware::Next,
+    response::Response,
+};
+use hyper::{Method, Request};
+use tracing::warn;
+
+use crate::{api::utils::route_without_base_path, config::Config};
+
+pub const DEFAULT_AUTH_BUDGET_MILLISECONDS: u64 = 5;
+pub const DEFAULT_READ_BUDGET_MILLISECONDS: u64 = 20;
+pub const DEFAULT_WRITE_BUDGET_MILLISECONDS: u64 = 100;
+
+tokio::task_local! {
+    static PHASE_TIMES: Arc<PhaseTimes>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Auth,
+    Cache,
+    Database,
+}
+
+/// Phase times of one request in microseconds.
+#[derive(Debug, Default)]
+struct PhaseTimes {
+    auth: AtomicU64,
+    cache: AtomicU64,
+    database: AtomicU64,
+}
+
+impl PhaseTimes {
+    fn add(&self, phase: Phase, elapsed: Duration) {
+        let counter = match phase {
+            Phase::Auth => &self.auth,
+            Phase::Cache => &self.cache,
+            Phase::Database => &self.database,
+        };
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        counter.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn milliseconds(&self, phase: Phase) -> f64 {
+        let counter = match phase {
+            Phase::Auth => &self.auth,
+            Phase::Cache => &self.cache,
+            Phase::Database => &self.database,
+        };
+        counter.load(Ordering::Relaxed) as f64 / 1000.0
+    }
+}
+
+/// Add time to the phase of the current request.
+pub fn record_phase(phase: Phase, elapsed: Duration) {
+    let _ = PHASE_TIMES.try_with(|times| times.add(phase, elapsed));
+}
+
+/// Run `future` and add its run time to the phase of the current request.
+pub async fn measure_phase<T>(phase: Phase, future: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let output = future.await;
+    record_phase(phase, start.elapsed());
+    output
+}
+
+#[derive(Debug)]
+pub struct LatencyBudgets {
+    auth: Duration,
+    read: Duration,
+    write: Duration,
+    /// Keys are route paths without the API base path.
+    routes: HashMap<String, Duration>,
+    api_base_path: Option<String>,
+}
+
+impl LatencyBudgets {
+    /// None if latency budgets are not configured.
+    pub fn new(config: &Config) -> Option<Self> {
+        let budgets = config.latency_budget()?;
+        Some(Self {
+            auth: Duration::from_millis(
+                budgets
+                    .auth_milliseconds
+                    .unwrap_or(DEFAULT_AUTH_BUDGET_MILLISECONDS),
+            ),
+            read: Duration::from_millis(
+                budgets
+                    .read_milliseconds
+                    .unwrap_or(DEFAULT_READ_BUDGET_MILLISECONDS),
+            ),
+            write: Duration::from_millis(
+                budgets
+                    .write_milliseconds
+                    .unwrap_or(DEFAULT_WRITE_BUDGET_MILLISECONDS),
+            ),
+            routes: budgets
+                .routes
+                .iter()
+                .map(|(route, ms)| (route.clone(), Duration::from_millis(*ms)))
+                .collect(),
+            api_base_path: config.api_base_path().map(ToString::to_string),
+        })
+    }
+
+    /// Route specific budget or the read budget for GET and HEAD requests
+    /// and the write budget for other requests.
+    fn route_budget(&self, method: &Method, route: &str) -> Duration {
+        let route = route_without_base_path(self.api_base_path.as_deref(), route);
+        match self.routes.get(route) {
+            Some(budget) => *budget,
+            None if method == Method::GET || method == Method::HEAD => self.read,
+            None => self.write,
+        }
+    }
+}
+
+/// Middleware for checking latency budgets. Add this with `route_layer`, so
+/// that only existing routes are checked.
+pub async fn check_latency_budget<B>(
+    State(budgets): State<Arc<LatencyBudgets>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let route = match req.extensions().get::<MatchedPath>() {
+        Some(route) => route.as_str().to_string(),
+        None => return next.run(req).await,
+    };
+    let method = req.method().clone();
+
+    let times = Arc::new(PhaseTimes::default());
+    let start = Instant::now();
+    let response = PHASE_TIMES.scope(times.clone(), next.run(req)).await;
+    let total = start.elapsed();
+
+    let budget = budgets.route_budget(&method, &route);
+    let auth_ms = times.milliseconds(Phase::Auth);
+    let over_auth_budget = auth_ms > budgets.auth.as_secs_f64() * 1000.0;
+    if total > budget || over_auth_budget {
+        warn!(
+            route,
+            method = method.as_str(),
+            status = response.status().as_u16(),
+            total_ms = total.as_secs_f64() * 1000.0,
+            budget_ms = budget.as_millis() as u64,
+            auth_ms,
+            auth_budget_ms = budgets.auth.as_millis() as u64,
+            cache_ms = times.milliseconds(Phase::Cache),
+            db_ms = times.milliseconds(Phase::Database),
+            "Latency budget exceeded"
+        );
+    }
+
+    response
+}
@@ -0,0 +1,89 @@
+//! Log output for the server and the test runner
+//!
+//! Logs are written to standard output as text or as JSON lines. Environment
+//! variable `RUST_LOG` overrides the level from the config file.
+
+use tracing::error;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{file::LogFormat, Config};
+
+/// Level which is used if `RUST_LOG` and the config file do not set the
+/// level.
+pub const DEFAULT_LOG_LEVEL: &str = "error";
+
+type SetFilter = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+/// Changes the level of the initialized logger when the config is reloaded.
+pub struct LogLevelHandle {
+    set_filter: SetFilter,
+}
+
+impl LogLevelHandle {
+    /// Handle for tests which do not initialize the logger.
+    #[cfg(test)]
+    pub fn disabled() -> Self {
+        Self {
+            set_filter: Box::new(|_| Ok(())),
+        }
+    }
+
+    /// Does nothing if `RUST_LOG` is set.
+    pub fn set_level(&self, config: &Config) {
+        if std::env::var(EnvFilter::DEFAULT_ENV).is_ok() {
+            return;
+        }
+
+        match (self.set_filter)(log_filter(config)) {
+            Ok(()) => (),
+            Err(e) => error!("Changing log level failed: {e}"),
+        }
+    }
+}
+
+impl std::fmt::Debug for LogLevelHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogLevelHandle").finish_non_exhaustive()
+    }
+}
+
+pub fn init_logging(config: &Config) -> LogLevelHandle {
+    let format = config
+        .logging()
+        .map(|logging| logging.format)
+        .unwrap_or_default();
+    let builder = tracing_subscriber::fmt().with_env_filter(log_filter(config));
+
+    let set_filter: SetFilter = match format {
+        LogFormat::Text => {
+            let builder = builder.with_filter_reloading();
+            let handle = builder.reload_handle();
+            builder.init();
+            Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string()))
+        }
+        // Span list contains the request ID span.
+        LogFormat::Json => {
+            let builder = builder
+                .json()
+                .with_current_span(false)
+                .with_span_list(true)
+                .with_filter_reloading();
+            let handle = builder.reload_handle();
+            builder.init();
+            Box::new(move |filter| handle.reload(filter).map_err(|e| e.to_string()))
+        }
+    };
+
+    LogLevelHandle { set_filter }
+}
+
+fn log_filter(config: &Config) -> EnvFilter {
+    let level = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(level) => level,
+        Err(_) => config
+            .logging()
+            .and_then(|logging| logging.level.clone())
+            .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string()),
+    };
+    EnvFilter::new(level)
+}
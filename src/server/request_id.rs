@@ -0,0 +1,35 @@
+//! Request IDs for correlating client errors with server logs
+//!
+//! Every request gets a random ID. Log events of the request are in a span
+//! which has the ID and the ID is returned in the `x-request-id` response
+//! header. The ID is also in request extensions, so the authentication
+//! middleware can add it to [crate::api::context::RequestContext].
+
+use axum::{middleware::Next, response::Response};
+use headers::HeaderValue;
+use hyper::{header, Request};
+use tracing::{error_span, Instrument};
+
+use crate::api::context::RequestId;
+
+pub const REQUEST_ID_HEADER_STR: &str = "x-request-id";
+pub static REQUEST_ID_HEADER: header::HeaderName =
+    header::HeaderName::from_static(REQUEST_ID_HEADER_STR);
+
+pub async fn add_request_id<B>(mut req: Request<B>, next: Next<B>) -> Response {
+    let request_id = RequestId::new();
+    req.extensions_mut().insert(request_id);
+
+    // Span level is error, so that the span is enabled with every log level
+    // and error events always have the request ID.
+    let span = error_span!("request", request_id = %request_id);
+    let mut response = next.run(req).instrument(span).await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id.to_string()) {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), value);
+    }
+
+    response
+}
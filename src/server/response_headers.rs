@@ -0,0 +1,104 @@
+//! Response headers which are the same for all routes
+//!
+//! * OPTIONS requests get an empty response with the Allow header. CORS
+//!   preflight requests are handled by the CORS layer if CORS is enabled.
+//! * HEAD requests are handled by GET routes without the response body.
+//!   This is the default behavior of axum.
+//! * Cache-Control header is added to responses which do not already have
+//!   it. Only routes in [CACHEABLE_ROUTES] can be cached because other
+//!   responses might contain account data.
+
+use std::time::Duration;
+
+use axum::{
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use headers::HeaderValue;
+use hyper::{header, HeaderMap, Method, Request, StatusCode};
+use tower_http::cors::CorsLayer;
+
+use crate::{
+    api::{self, utils::API_KEY_HEADER},
+    config::Config,
+    server::request_id::REQUEST_ID_HEADER,
+};
+
+/// Routes which are not account specific and max-age for those in seconds.
+pub const CACHEABLE_ROUTES: &[(&str, u32)] = &[(api::common::PATH_GET_VERSION, 60)];
+
+/// How long browsers can cache CORS preflight responses.
+const CORS_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+pub async fn add_response_headers<B>(req: Request<B>, next: Next<B>) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+
+    let mut response = next.run(req).await;
+
+    // Routes do not have OPTIONS handlers, so the method is not allowed
+    // for all existing routes.
+    if method == Method::OPTIONS && response.status() == StatusCode::METHOD_NOT_ALLOWED {
+        response = options_response(response.headers());
+    }
+
+    if !response.headers().contains_key(header::CACHE_CONTROL) {
+        let value = cache_control(&method, &path, response.status());
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+
+    response
+}
+
+fn options_response(method_not_allowed_headers: &HeaderMap) -> Response {
+    let allow = method_not_allowed_headers
+        .get(header::ALLOW)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| format!("{value},OPTIONS"))
+        .unwrap_or_else(|| "OPTIONS".to_string());
+
+    match HeaderValue::from_str(&allow) {
+        Ok(allow) => (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response(),
+        Err(_) => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+fn cache_control(method: &Method, path: &str, status: StatusCode) -> HeaderValue {
+    let max_age = CACHEABLE_ROUTES
+        .iter()
+        .find(|(route, _)| *route == path)
+        .map(|(_, max_age)| *max_age);
+
+    match max_age {
+        Some(max_age)
+            if (method == Method::GET || method == Method::HEAD) && status.is_success() =>
+        {
+            HeaderValue::from_str(&format!("public, max-age={max_age}"))
+                .unwrap_or_else(|_| HeaderValue::from_static("no-store"))
+        }
+        _ => HeaderValue::from_static("no-store"),
+    }
+}
+
+/// Returns None if CORS is disabled from config.
+pub fn cors_layer(config: &Config) -> Option<CorsLayer> {
+    if config.cors_allowed_origins().is_empty() {
+        return None;
+    }
+
+    let layer = CorsLayer::new()
+        .allow_origin(config.cors_allowed_origins().to_vec())
+        .allow_methods([
+            Method::GET,
+            Method::HEAD,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+        ])
+        .allow_headers([header::CONTENT_TYPE, header::ACCEPT, API_KEY_HEADER.clone()])
+        .expose_headers([REQUEST_ID_HEADER.clone()])
+        .max_age(CORS_MAX_AGE);
+
+    Some(layer)
+}
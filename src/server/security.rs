@@ -0,0 +1,113 @@
+//! Security audit events
+//!
+//! Events are logged using tracing target `security_audit`, so those can be
+//! filtered to a separate log.
+
+use std::net::{IpAddr, SocketAddr};
+
+use tracing::warn;
+
+use crate::api::model::AccountIdInternal;
+
+use super::{clock::UtcTimestamp, geoip::GeoLocation};
+
+pub const SECURITY_AUDIT_TARGET: &str = "security_audit";
+
+#[derive(Debug, Clone)]
+pub enum SecurityAuditEvent {
+    /// Access token was used from different country or IP address range
+    /// than the previous request.
+    AccessTokenUsedFromNewLocation {
+        account: AccountIdInternal,
+        previous: IpAddr,
+        new: IpAddr,
+        previous_country: Option<String>,
+        new_country: Option<String>,
+    },
+    /// Replaced refresh token was used to connect WebSocket. All sessions
+    /// of the account are revoked.
+    RefreshTokenReused {
+        account: AccountIdInternal,
+        address: IpAddr,
+        /// Generation of the replaced token.
+        generation: i64,
+    },
+}
+
+impl SecurityAuditEvent {
+    pub fn log(&self) {
+        let time = UtcTimestamp::now();
+        match self {
+            Self::AccessTokenUsedFromNewLocation {
+                account,
+                previous,
+                new,
+                previous_country,
+                new_country,
+            } => warn!(
+                target: SECURITY_AUDIT_TARGET,
+                %time,
+                account = %account.as_uuid(),
+                %previous,
+                %new,
+                previous_country = previous_country.as_deref().unwrap_or("unknown"),
+                new_country = new_country.as_deref().unwrap_or("unknown"),
+                "Access token used from new location"
+            ),
+            Self::RefreshTokenReused {
+                account,
+                address,
+                generation,
+            } => warn!(
+                target: SECURITY_AUDIT_TARGET,
+                %time,
+                account = %account.as_uuid(),
+                %address,
+                generation,
+                "Replaced refresh token used, sessions revoked"
+            ),
+        }
+    }
+}
+
+/// Log security audit event if access token is used from new location.
+///
+/// If location is known for both addresses, the countries are compared.
+/// Otherwise IP address ranges are compared.
+pub fn check_access_token_address_change(
+    account: AccountIdInternal,
+    previous: Option<(SocketAddr, Option<GeoLocation>)>,
+    new: (SocketAddr, Option<GeoLocation>),
+) {
+    let (previous, previous_location) = match previous {
+        Some(previous) => previous,
+        None => return,
+    };
+    let (new, new_location) = new;
+
+    let location_changed = match (&previous_location, &new_location) {
+        (Some(previous), Some(new)) => previous.country != new.country,
+        _ => !same_ip_range(previous.ip(), new.ip()),
+    };
+
+    if location_changed {
+        SecurityAuditEvent::AccessTokenUsedFromNewLocation {
+            account,
+            previous: previous.ip(),
+            new: new.ip(),
+            previous_country: previous_location.map(|l| l.country),
+            new_country: new_location.map(|l| l.country),
+        }
+        .log()
+    }
+}
+
+/// IPv4 addresses are in the same range if those are in the same /24 network.
+/// IPv6 addresses are in the same range if those are in the same /48 network.
+pub fn same_ip_range(a: IpAddr, b: IpAddr) -> bool {
+    match (a, b) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => a.octets()[..3] == b.octets()[..3],
+        (IpAddr::V6(a), IpAddr::V6(b)) => a.segments()[..3] == b.segments()[..3],
+        _ => false,
+    }
+}
@@ -0,0 +1,22 @@
+//! Server startup status
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Startup progress which the health route reports.
+#[derive(Debug, Default)]
+pub struct ServerStatus {
+    ready: AtomicBool,
+}
+
+impl ServerStatus {
+    /// Database cache is loaded before routers are created, so this should
+    /// be called when API servers are listening and background tasks are
+    /// started.
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,202 @@
+//! Anonymous usage telemetry
+//!
+//! Telemetry is disabled unless the telemetry endpoint is configured. When
+//! enabled, the server counts requests per route and method and posts the
+//! counts with the backend version to the endpoint at configured interval.
+//! Counts do not contain account IDs, addresses or other request data.
+//! Route paths are route templates like `/internal/cache/:account_id`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{MatchedPath, State},
+    middleware::Next,
+    response::Response,
+};
+use error_stack::Result;
+use hyper::Request;
+use serde::Serialize;
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{error, info};
+use url::Url;
+
+use crate::{
+    config::{file::TelemetryConfig, Config},
+    server::database::utils::current_unix_time,
+    utils::{IntoReportExt, QuitReceiver, QuitSender},
+};
+
+const DEFAULT_TELEMETRY_INTERVAL_SECONDS: u64 = 24 * 60 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+pub enum TelemetryError {
+    #[error("Sending telemetry report failed")]
+    Send,
+}
+
+/// Request counts since the previous successful report.
+#[derive(Debug)]
+pub struct RequestCounters {
+    api_base_path: Option<String>,
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl RequestCounters {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            api_base_path: config.api_base_path().map(ToOwned::to_owned),
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn increment(&self, method: &str, route: &str) {
+        let route = self
+            .api_base_path
+            .as_deref()
+            .and_then(|base_path| route.strip_prefix(base_path))
+            .unwrap_or(route);
+        let mut counts = self.counts.lock().unwrap();
+        *counts.entry(format!("{method} {route}")).or_default() += 1;
+    }
+
+    fn take(&self) -> HashMap<String, u64> {
+        std::mem::take(&mut *self.counts.lock().unwrap())
+    }
+
+    /// Add counts back if sending the report failed.
+    fn restore(&self, previous: HashMap<String, u64>) {
+        let mut counts = self.counts.lock().unwrap();
+        for (route, count) in previous {
+            *counts.entry(route).or_default() += count;
+        }
+    }
+}
+
+/// Middleware for counting requests. Add this with `route_layer`, so that
+/// only existing routes are counted.
+pub async fn count_request<B>(
+    State(counters): State<Arc<RequestCounters>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if let Some(route) = req.extensions().get::<MatchedPath>() {
+        counters.increment(req.method().as_str(), route.as_str());
+    }
+    next.run(req).await
+}
+
+/// Telemetry report request body.
+#[derive(Debug, Serialize)]
+struct TelemetryReport {
+    backend_version: &'static str,
+    start_unix_time: i64,
+    end_unix_time: i64,
+    /// Keys are like `GET /account_api/state`.
+    requests: HashMap<String, u64>,
+}
+
+#[derive(Debug)]
+pub struct TelemetryQuitHandle {
+    task: JoinHandle<()>,
+    quit: QuitSender,
+}
+
+impl TelemetryQuitHandle {
+    pub async fn quit(self) {
+        let _ = self.quit.send(());
+        match self.task.await {
+            Ok(()) => (),
+            Err(e) => error!("Telemetry task join failed: {}", e),
+        }
+    }
+}
+
+/// Sends telemetry reports at configured interval.
+pub struct TelemetrySender {
+    counters: Arc<RequestCounters>,
+    client: reqwest::Client,
+    endpoint: Url,
+    interval: Duration,
+    start_unix_time: i64,
+}
+
+impl TelemetrySender {
+    pub fn new_task(
+        counters: Arc<RequestCounters>,
+        config: &TelemetryConfig,
+    ) -> TelemetryQuitHandle {
+        info!("Anonymous usage telemetry is enabled");
+
+        let sender = Self {
+            counters,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            endpoint: config.endpoint.clone(),
+            interval: Duration::from_secs(
+                config
+                    .interval_seconds
+                    .unwrap_or(DEFAULT_TELEMETRY_INTERVAL_SECONDS),
+            ),
+            start_unix_time: current_unix_time(),
+        };
+
+        let (quit, quit_receiver) = oneshot::channel();
+        let task = tokio::spawn(sender.run(quit_receiver));
+
+        TelemetryQuitHandle { task, quit }
+    }
+
+    async fn run(mut self, mut quit_receiver: QuitReceiver) {
+        let mut timer = tokio::time::interval(self.interval);
+        // First tick completes immediately.
+        timer.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = &mut quit_receiver => break,
+                _ = timer.tick() => {
+                    match self.send_report().await {
+                        Ok(()) => (),
+                        Err(e) => error!("Telemetry report failed: {e:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    async fn send_report(&mut self) -> Result<(), TelemetryError> {
+        let end_unix_time = current_unix_time();
+        let report = TelemetryReport {
+            backend_version: env!("CARGO_PKG_VERSION"),
+            start_unix_time: self.start_unix_time,
+            end_unix_time,
+            requests: self.counters.take(),
+        };
+
+        let response = self
+            .client
+            .post(self.endpoint.clone())
+            .json(&report)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        match response {
+            Ok(_) => {
+                self.start_unix_time = end_unix_time;
+                Ok(())
+            }
+            Err(e) => {
+                self.counters.restore(report.requests);
+                Err(e).into_error(TelemetryError::Send)
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! In-process server for tests
+//!
+//! [TestApp] builds the public API router like the server does, but requests
+//! are handled in-process with [ServiceExt::oneshot] and data is saved to a
+//! temporary SQLite database. Debug mode is enabled, so internal routes are
+//! available. Access tokens are not bound to the WebSocket connection IP
+//! address, so private routes work without the WebSocket connection. Sign in
+//! with Google and Apple uses [MockSignInWith], so no network access is
+//! needed.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::connect_info::MockConnectInfo,
+    http::{header, Method, Request, StatusCode},
+    response::Response,
+    Router,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+use crate::{
+    api::{
+        account::{PATH_ACCOUNT_COMPLETE_SETUP, PATH_ACCOUNT_SETUP, PATH_LOGIN, PATH_REGISTER},
+        model::{AccountIdLight, ApiKey, LoginResult},
+        utils::API_KEY_HEADER_STR,
+        GetServerStatus,
+    },
+    config::{file::ConfigFile, test_config},
+};
+
+use super::{
+    app::{connection::WebSocketManager, sign_in_with::mock::MockSignInWith, App, AppState},
+    config_reload::ConfigReloader,
+    database::DatabaseManager,
+    geoip::NoGeoIp,
+    logging::LogLevelHandle,
+    CalculatorServer,
+};
+
+/// Client address of all requests.
+pub const TEST_CLIENT_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 40000);
+
+pub struct TestApp {
+    router: Router,
+    state: AppState,
+    database_manager: DatabaseManager,
+    database_dir: PathBuf,
+    /// WebSocket connections end when this is dropped.
+    _server_quit_handle: broadcast::Sender<()>,
+}
+
+impl TestApp {
+    /// Server with the default config.
+    pub async fn new() -> Self {
+        Self::with_config(|_| ()).await
+    }
+
+    /// Server with config file changes. Debug mode is always enabled and
+    /// `bind_access_token_to_ip` is disabled by default.
+    pub async fn with_config(modify: impl FnOnce(&mut ConfigFile)) -> Self {
+        Self::build(modify, MockSignInWith::default()).await
+    }
+
+    /// Server which accepts the sign in with tokens of `sign_in_with`.
+    pub async fn with_sign_in_with(sign_in_with: MockSignInWith) -> Self {
+        Self::build(|_| (), sign_in_with).await
+    }
+
+    async fn build(modify: impl FnOnce(&mut ConfigFile), sign_in_with: MockSignInWith) -> Self {
+        let database_dir =
+            std::env::temp_dir().join(format!("calculator-backend-test-{}", uuid::Uuid::new_v4()));
+        let config = Arc::new(
+            test_config(database_dir.clone(), |config| {
+                config.bind_access_token_to_ip = Some(false);
+                modify(config);
+            })
+            .expect("Creating test config failed"),
+        );
+
+        let (database_manager, router_database_handle) =
+            DatabaseManager::new(database_dir.clone(), config.clone())
+                .await
+                .expect("Database init failed");
+
+        let (server_quit_handle, server_quit_watcher) = broadcast::channel(1);
+        let (ws_manager, _) = WebSocketManager::new(server_quit_watcher);
+
+        let mut app = App::new(
+            router_database_handle,
+            config.clone(),
+            ws_manager,
+            Box::new(NoGeoIp),
+            Box::new(sign_in_with),
+        )
+        .await;
+        let config_reloader = Arc::new(ConfigReloader::new(
+            config.clone(),
+            LogLevelHandle::disabled(),
+            app.rate_limiter(),
+        ));
+
+        let server = CalculatorServer { config };
+        let router = server
+            .create_public_api_router(&mut app, &config_reloader)
+            .layer(MockConnectInfo(TEST_CLIENT_ADDRESS));
+
+        let state = app.state();
+        state.server_status().set_ready();
+
+        Self {
+            router,
+            state,
+            database_manager,
+            database_dir,
+            _server_quit_handle: server_quit_handle,
+        }
+    }
+
+    /// State for calling read and write commands directly.
+    pub fn state(&self) -> AppState {
+        self.state.clone()
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> Response {
+        match self.router.clone().oneshot(request).await {
+            Ok(response) => response,
+            Err(e) => match e {},
+        }
+    }
+
+    pub async fn get(&self, path: &str, access_token: Option<&ApiKey>) -> Response {
+        let request = request_builder(Method::GET, path, access_token)
+            .body(Body::empty())
+            .expect("Invalid request");
+        self.send(request).await
+    }
+
+    pub async fn delete(&self, path: &str, access_token: Option<&ApiKey>) -> Response {
+        let request = request_builder(Method::DELETE, path, access_token)
+            .body(Body::empty())
+            .expect("Invalid request");
+        self.send(request).await
+    }
+
+    pub async fn post_json<T: Serialize>(
+        &self,
+        path: &str,
+        access_token: Option<&ApiKey>,
+        body: &T,
+    ) -> Response {
+        self.send_json(Method::POST, path, access_token, body).await
+    }
+
+    pub async fn put_json<T: Serialize>(
+        &self,
+        path: &str,
+        access_token: Option<&ApiKey>,
+        body: &T,
+    ) -> Response {
+        self.send_json(Method::PUT, path, access_token, body).await
+    }
+
+    async fn send_json<T: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        access_token: Option<&ApiKey>,
+        body: &T,
+    ) -> Response {
+        let body = serde_json::to_vec(body).expect("Request body serialization failed");
+        let request = request_builder(method, path, access_token)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .expect("Invalid request");
+        self.send(request).await
+    }
+
+    /// Register new account, login to it and complete the account setup,
+    /// so the account is in normal state and can access all private routes.
+    pub async fn register_and_login(&self) -> (AccountIdLight, LoginResult) {
+        let (id, login) = self.register_and_login_without_setup().await;
+
+        let access = Some(&login.account.access);
+        let setup = serde_json::json!({ "email": "test@example.com" });
+        let response = self.post_json(PATH_ACCOUNT_SETUP, access, &setup).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = self
+            .post_json(PATH_ACCOUNT_COMPLETE_SETUP, access, &())
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        (id, login)
+    }
+
+    /// Register new account and login to it. Account state depends on
+    /// the account config.
+    pub async fn register_and_login_without_setup(&self) -> (AccountIdLight, LoginResult) {
+        let response = self.post_json(PATH_REGISTER, None, &()).await;
+        let id: AccountIdLight = response_json(response, StatusCode::OK).await;
+
+        let response = self.post_json(PATH_LOGIN, None, &id).await;
+        let login: LoginResult = response_json(response, StatusCode::OK).await;
+
+        (id, login)
+    }
+
+    /// Close the database and remove the database directory. Drop the
+    /// [AppState] clones from [TestApp::state] before calling this, as
+    /// closing waits that the database handles are dropped.
+    pub async fn close(self) {
+        drop(self.router);
+        drop(self.state);
+        self.database_manager.close().await;
+        let _ = std::fs::remove_dir_all(self.database_dir);
+    }
+}
+
+fn request_builder(
+    method: Method,
+    path: &str,
+    access_token: Option<&ApiKey>,
+) -> axum::http::request::Builder {
+    let builder = Request::builder().method(method).uri(path);
+    match access_token {
+        Some(token) => builder.header(API_KEY_HEADER_STR, token.as_str()),
+        None => builder,
+    }
+}
+
+/// Check the status code and deserialize the response body.
+pub async fn response_json<T: DeserializeOwned>(response: Response, expected: StatusCode) -> T {
+    assert_eq!(response.status(), expected);
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .expect("Reading response body failed");
+    serde_json::from_slice(&body).expect("Response body deserialization failed")
+}
@@ -0,0 +1,121 @@
+//! Head-based request tracing sampling
+//!
+//! The sampling decision is made when the request span is created. Events of
+//! the trace layer are skipped for requests which are not sampled. Route
+//! class is the first path segment of the request path without the API base
+//! path, like `account_api` or `internal`.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use axum::extract::ConnectInfo;
+use hyper::{Request, Response};
+use rand::Rng;
+use tower_http::trace::{
+    DefaultMakeSpan, DefaultOnEos, DefaultOnFailure, DefaultOnRequest, DefaultOnResponse, MakeSpan,
+    OnEos, OnFailure, OnRequest, OnResponse,
+};
+use tracing::Span;
+
+use crate::config::file::TracingSamplingConfig;
+
+/// Requests from trusted addresses with this header are always traced.
+pub const HEADER_DEBUG_TRACE: &str = "x-debug-trace";
+
+#[derive(Debug, Clone)]
+pub struct TraceSampler {
+    default_rate: f64,
+    route_class_rates: HashMap<String, f64>,
+    trusted_debug_addresses: Vec<IpAddr>,
+}
+
+impl TraceSampler {
+    pub fn new(config: &TracingSamplingConfig) -> Self {
+        Self {
+            default_rate: config.default_rate,
+            route_class_rates: config.route_class_rates.clone(),
+            trusted_debug_addresses: config.trusted_debug_addresses.clone(),
+        }
+    }
+
+    fn is_sampled<B>(&self, request: &Request<B>) -> bool {
+        if self.is_forced(request) {
+            return true;
+        }
+
+        let route_class = request
+            .uri()
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        let rate = self
+            .route_class_rates
+            .get(route_class)
+            .copied()
+            .unwrap_or(self.default_rate);
+
+        rate >= 1.0 || (rate > 0.0 && rand::thread_rng().gen_bool(rate))
+    }
+
+    /// Debug header is ignored if the request is not from a trusted address,
+    /// so that clients can not make tracing expensive.
+    fn is_forced<B>(&self, request: &Request<B>) -> bool {
+        if !request.headers().contains_key(HEADER_DEBUG_TRACE) {
+            return false;
+        }
+
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| self.trusted_debug_addresses.contains(&address.ip()))
+            .unwrap_or(false)
+    }
+}
+
+impl<B> MakeSpan<B> for TraceSampler {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        if self.is_sampled(request) {
+            DefaultMakeSpan::new().make_span(request)
+        } else {
+            Span::none()
+        }
+    }
+}
+
+impl<B> OnRequest<B> for TraceSampler {
+    fn on_request(&mut self, request: &Request<B>, span: &Span) {
+        if !span.is_none() {
+            DefaultOnRequest::new().on_request(request, span)
+        }
+    }
+}
+
+impl<B> OnResponse<B> for TraceSampler {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
+        if !span.is_none() {
+            DefaultOnResponse::new().on_response(response, latency, span)
+        }
+    }
+}
+
+impl<F: Display> OnFailure<F> for TraceSampler {
+    fn on_failure(&mut self, failure: F, latency: Duration, span: &Span) {
+        if !span.is_none() {
+            DefaultOnFailure::new().on_failure(failure, latency, span)
+        }
+    }
+}
+
+impl OnEos for TraceSampler {
+    fn on_eos(self, trailers: Option<&http::HeaderMap>, stream_duration: Duration, span: &Span) {
+        if !span.is_none() {
+            DefaultOnEos::new().on_eos(trailers, stream_duration, span)
+        }
+    }
+}
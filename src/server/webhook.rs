@@ -0,0 +1,239 @@
+//! Account lifecycle event webhooks
+//!
+//! Events are delivered to every configured webhook in the background, so
+//! request handlers do not wait for the external systems. Failed deliveries
+//! are retried with exponential backoff. Request body is signed with
+//! HMAC-SHA256: header `X-Webhook-Signature` is `sha256=<hex>` of
+//! `<timestamp>.<body>`, where timestamp is the `X-Webhook-Timestamp`
+//! header value.
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::model::{
+        AccountEventType, AccountIdLight, AccountWebhookEvent, WebhookDelivery,
+        WebhookDeliveryStatus,
+    },
+    config::{Config, WebhookTarget},
+    server::database::utils::current_unix_time,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+pub const HEADER_SIGNATURE: &str = "X-Webhook-Signature";
+pub const HEADER_TIMESTAMP: &str = "X-Webhook-Timestamp";
+pub const HEADER_EVENT_ID: &str = "X-Webhook-Event-Id";
+
+/// Latest deliveries for the delivery status endpoint.
+struct DeliveryHistory {
+    deliveries: Mutex<VecDeque<WebhookDelivery>>,
+    max_size: usize,
+}
+
+impl DeliveryHistory {
+    /// Replace the previous status of the delivery or add a new delivery.
+    async fn update(&self, delivery: &WebhookDelivery) {
+        let mut deliveries = self.deliveries.lock().await;
+        let existing = deliveries
+            .iter_mut()
+            .find(|d| d.event.event_id == delivery.event.event_id && d.url == delivery.url);
+        match existing {
+            Some(existing) => *existing = delivery.clone(),
+            None => {
+                if deliveries.len() >= self.max_size {
+                    deliveries.pop_front();
+                }
+                deliveries.push_back(delivery.clone());
+            }
+        }
+    }
+}
+
+pub struct WebhookManager {
+    /// None if webhooks are not configured.
+    sender: Option<mpsc::Sender<AccountWebhookEvent>>,
+    deliveries: Arc<DeliveryHistory>,
+}
+
+impl WebhookManager {
+    /// Starts delivery task if webhooks are configured. The task quits when
+    /// the manager is dropped.
+    pub fn new(config: &Config) -> Self {
+        let deliveries = Arc::new(DeliveryHistory {
+            deliveries: Mutex::new(VecDeque::new()),
+            max_size: config.limits().webhook_delivery_history,
+        });
+
+        if config.webhooks().is_empty() {
+            return Self {
+                sender: None,
+                deliveries,
+            };
+        }
+
+        let (sender, receiver) = mpsc::channel(config.limits().webhook_event_buffer);
+        tokio::spawn(run_delivery_task(
+            receiver,
+            config.webhooks().to_vec(),
+            deliveries.clone(),
+        ));
+
+        Self {
+            sender: Some(sender),
+            deliveries,
+        }
+    }
+
+    /// Queue event for delivery. This does not wait for the delivery.
+    pub fn send(&self, event: AccountEventType, account_id: AccountIdLight) {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let event = AccountWebhookEvent {
+            event_id: Uuid::new_v4(),
+            event,
+            account_id,
+            unix_time: current_unix_time(),
+        };
+
+        if let Err(e) = sender.try_send(event) {
+            warn!("Webhook event dropped: {}", e);
+        }
+    }
+
+    /// Latest deliveries. The newest delivery is first.
+    pub async fn deliveries(&self) -> Vec<WebhookDelivery> {
+        self.deliveries
+            .deliveries
+            .lock()
+            .await
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+}
+
+async fn run_delivery_task(
+    mut receiver: mpsc::Receiver<AccountWebhookEvent>,
+    targets: Vec<WebhookTarget>,
+    deliveries: Arc<DeliveryHistory>,
+) {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Webhook HTTP client creation failed: {}", e);
+            return;
+        }
+    };
+
+    while let Some(event) = receiver.recv().await {
+        for target in &targets {
+            // Retries of one webhook should not delay other deliveries.
+            tokio::spawn(deliver(
+                client.clone(),
+                target.clone(),
+                event.clone(),
+                deliveries.clone(),
+            ));
+        }
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    target: WebhookTarget,
+    event: AccountWebhookEvent,
+    deliveries: Arc<DeliveryHistory>,
+) {
+    let body = match serde_json::to_vec(&event) {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Webhook event serialization failed: {}", e);
+            return;
+        }
+    };
+
+    let mut delivery = WebhookDelivery {
+        event,
+        url: target.url.to_string(),
+        status: WebhookDeliveryStatus::Pending,
+        attempts: 0,
+        last_status_code: None,
+        updated_unix_time: current_unix_time(),
+    };
+    deliveries.update(&delivery).await;
+
+    let mut retry_delay = FIRST_RETRY_DELAY;
+    while delivery.attempts < target.max_attempts {
+        if delivery.attempts > 0 {
+            tokio::time::sleep(retry_delay).await;
+            retry_delay *= 2;
+        }
+
+        delivery.attempts += 1;
+        let timestamp = current_unix_time();
+        let response = client
+            .post(target.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(HEADER_TIMESTAMP, timestamp.to_string())
+            .header(HEADER_EVENT_ID, delivery.event.event_id.to_string())
+            .header(
+                HEADER_SIGNATURE,
+                signature(&target.secret, timestamp, &body),
+            )
+            .body(body.clone())
+            .send()
+            .await;
+
+        delivery.last_status_code = match &response {
+            Ok(response) => Some(response.status().as_u16()),
+            Err(_) => None,
+        };
+        delivery.updated_unix_time = current_unix_time();
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                delivery.status = WebhookDeliveryStatus::Delivered;
+                deliveries.update(&delivery).await;
+                return;
+            }
+            Ok(response) => warn!(
+                "Webhook {} returned {}, attempt {}",
+                target.url,
+                response.status(),
+                delivery.attempts
+            ),
+            Err(e) => warn!(
+                "Webhook {} request failed, attempt {}: {}",
+                target.url, delivery.attempts, e
+            ),
+        }
+        deliveries.update(&delivery).await;
+    }
+
+    error!(
+        "Webhook {} delivery failed, event ID {}",
+        target.url, delivery.event.event_id
+    );
+    delivery.status = WebhookDeliveryStatus::Failed;
+    deliveries.update(&delivery).await;
+}
+
+fn signature(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC key error");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
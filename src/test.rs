@@ -1,23 +1,39 @@
 //! Run test suite and benchmarks
 
 mod bot;
+pub mod bot_script;
 pub mod client;
+mod openapi;
 mod server;
 mod state;
+pub mod tls;
 
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use api_client::{apis::configuration::Configuration, manual_additions};
 use tokio::{
     io::AsyncWriteExt,
     select, signal,
     sync::{mpsc, watch},
+    time::Instant,
 };
 use tracing::{error, info};
 
 use crate::{
-    config::{args::TestMode, Config},
-    test::{bot::BotManager, client::ApiClient, server::ServerManager, state::BotPersistentState},
+    config::{
+        args::{Test, TestMode},
+        Config,
+    },
+    server::logging::init_logging,
+    test::{
+        bot::{benchmark_report, BotManager, QaReport, TaskResult},
+        client::ApiClient,
+        server::ServerManager,
+    },
 };
 
 use self::state::StateData;
@@ -36,25 +52,94 @@ impl TestRunner {
     }
 
     pub async fn run(self) {
-        tracing_subscriber::fmt::init();
+        init_logging(&self.config);
 
         info!("Testing mode");
 
+        let run_deadline = self
+            .test_config
+            .run_timeout
+            .map(|timeout| Instant::now() + timeout);
+
         let old_state = if self.test_config.save_state {
             self.load_state_data().await.map(|d| Arc::new(d))
         } else {
             None
         };
 
-        ApiClient::new(self.test_config.server.api_urls.clone()).print_to_log();
+        ApiClient::new(
+            self.test_config.server.api_urls.clone(),
+            self.test_config.server.certificate_pin.as_ref(),
+        )
+        .with_internal_api(&self.test_config.server.internal_api_url)
+        .print_to_log();
+
+        let qa_groups: Vec<Option<usize>> = match self.test_config.test {
+            Test::Qa if self.test_config.qa_isolated => {
+                (0..BotManager::qa_group_count(&self.test_config))
+                    .map(Some)
+                    .collect()
+            }
+            _ => vec![None],
+        };
+
+        let mut bot_states = vec![];
+        let mut qa_report = None;
+        for qa_group in qa_groups {
+            if let Some(group) = qa_group {
+                info!("QA test group {}. Starting new server instances.", group);
+            }
+
+            let round = self
+                .run_round(qa_group, old_state.clone(), run_deadline)
+                .await;
+            bot_states.extend(round.result.bot_states);
+            if let Some(report) = round.result.qa_report {
+                qa_report
+                    .get_or_insert_with(QaReport::default)
+                    .merge(report);
+            }
+
+            if round.stopped {
+                break;
+            }
+        }
+
+        if let Some(report) = qa_report {
+            report.print();
+        }
+
+        let new_state = StateData {
+            test_name: self.test_config.test.as_str().to_string(),
+            bot_states,
+        };
+
+        if self.test_config.save_state {
+            self.save_state_data(&new_state).await;
+        }
 
+        if let Some(file) = &self.test_config.benchmark_output {
+            if self.test_config.test.is_benchmark() {
+                self.save_benchmark_report(file).await;
+            }
+        }
+    }
+
+    /// Start server instances, run bot tasks until they complete and close
+    /// the servers.
+    async fn run_round(
+        &self,
+        qa_group: Option<usize>,
+        old_state: Option<Arc<StateData>>,
+        run_deadline: Option<Instant>,
+    ) -> RoundResult {
         let server = if !self.test_config.no_servers {
             Some(ServerManager::new(self.test_config.clone()).await)
         } else {
             None
         };
 
-        let (bot_running_handle, mut wait_all_bots) = mpsc::channel::<Vec<BotPersistentState>>(1);
+        let (bot_running_handle, mut wait_all_bots) = mpsc::channel::<TaskResult>(1);
         let (quit_handle, bot_quit_receiver) = watch::channel(());
 
         let mut task_number = 0;
@@ -62,7 +147,7 @@ impl TestRunner {
 
         info!("Waiting API availability...");
 
-        let quit_now = select! {
+        let mut stopped = select! {
             result = signal::ctrl_c() => {
                 match result {
                     Ok(()) => true,
@@ -72,12 +157,19 @@ impl TestRunner {
                     }
                 }
             }
-            _ = wait_that_servers_start(ApiClient::new(api_urls.as_ref().clone())) => {
+            _ = wait_that_servers_start(ApiClient::new(
+                api_urls.as_ref().clone(),
+                self.test_config.server.certificate_pin.as_ref(),
+            )) => {
                 false
             },
+            _ = wait_run_deadline(run_deadline) => {
+                error!("Test run timeout. API was not available.");
+                true
+            }
         };
 
-        if !quit_now {
+        if !stopped {
             info!("...API ready");
 
             info!(
@@ -89,6 +181,7 @@ impl TestRunner {
                 BotManager::spawn(
                     task_number,
                     self.test_config.clone(),
+                    qa_group,
                     old_state.clone(),
                     bot_quit_receiver.clone(),
                     bot_running_handle.clone(),
@@ -102,40 +195,55 @@ impl TestRunner {
         drop(bot_running_handle);
         drop(bot_quit_receiver);
 
+        let mut results = vec![];
         select! {
             result = signal::ctrl_c() => {
                 match result {
                     Ok(()) => (),
                     Err(e) => error!("Failed to listen CTRL+C. Error: {}", e),
                 }
+                stopped = true;
+            }
+            result = wait_all_bots.recv() => results.extend(result),
+            _ = wait_run_deadline(run_deadline) => {
+                error!("Test run timeout. Stopping bots.");
+                stopped = true;
             }
-            _ = wait_all_bots.recv() => ()
         }
 
         drop(quit_handle); // Singnal quit to bots.
 
         // Wait that all bot_running_handles are dropped.
-        let mut bot_states = vec![];
         loop {
             match wait_all_bots.recv().await {
                 None => break,
-                Some(data) => bot_states.extend(data),
+                Some(result) => results.push(result),
             }
         }
 
-        let new_state = StateData {
-            test_name: self.test_config.test.as_str().to_string(),
-            bot_states,
-        };
-
-        if self.test_config.save_state {
-            self.save_state_data(&new_state).await;
+        let mut bot_states = vec![];
+        let mut qa_report = None;
+        for result in results {
+            bot_states.extend(result.bot_states);
+            if let Some(report) = result.qa_report {
+                qa_report
+                    .get_or_insert_with(QaReport::default)
+                    .merge(report);
+            }
         }
 
         // Quit
         if let Some(server) = server {
             server.close().await;
         }
+
+        RoundResult {
+            result: TaskResult {
+                bot_states,
+                qa_report,
+            },
+            stopped,
+        }
     }
 
     async fn load_state_data(&self) -> Option<StateData> {
@@ -178,22 +286,70 @@ impl TestRunner {
         }
     }
 
+    /// Write benchmark results as CSV if the file extension is `csv` and
+    /// otherwise as JSON.
+    async fn save_benchmark_report(&self, file: &Path) {
+        let report = benchmark_report(&self.test_config.test);
+        let data = if file.extension().map(|e| e == "csv").unwrap_or(false) {
+            report.to_csv()
+        } else {
+            match serde_json::to_string_pretty(&report) {
+                Ok(d) => d,
+                Err(e) => {
+                    error!("benchmark report error: {:?}", e);
+                    return;
+                }
+            }
+        };
+
+        let path = self.test_config.server.test_database_dir.join(file);
+        if let Some(dir) = path.parent() {
+            if let Err(e) = tokio::fs::create_dir_all(dir).await {
+                error!("benchmark report saving error: {:?}", e);
+                return;
+            }
+        }
+        match tokio::fs::write(&path, data).await {
+            Ok(()) => info!("Benchmark report saved to {}", path.display()),
+            Err(e) => error!("benchmark report saving error: {:?}", e),
+        }
+    }
+
     fn state_data_file(&self) -> PathBuf {
         let data_file = format!("test_{}_state_data.json", self.test_config.test.as_str());
         self.test_config.server.test_database_dir.join(data_file)
     }
 }
 
+/// Results of bot tasks which used the same server instances.
+struct RoundResult {
+    /// Results of all tasks.
+    result: TaskResult,
+    /// Test run was stopped before the bots completed.
+    stopped: bool,
+}
+
+/// Wait until the run deadline is reached. Never completes if there is no
+/// deadline.
+async fn wait_run_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait until account and calculator servers report that they are ready,
+/// so that benchmarks do not measure partially initialized servers.
 async fn wait_that_servers_start(api: ApiClient) {
-    check_api(api.account()).await;
-    check_api(api.calculator()).await;
+    wait_server_ready(api.account()).await;
+    wait_server_ready(api.calculator()).await;
 }
 
-async fn check_api(config: &Configuration) {
+async fn wait_server_ready(config: &Configuration) {
     loop {
-        match manual_additions::api_available(config).await {
-            Ok(()) => break,
-            Err(()) => (),
+        match manual_additions::server_ready(config).await {
+            Ok(true) => break,
+            Ok(false) | Err(()) => (),
         }
 
         tokio::time::sleep(Duration::from_millis(500)).await;
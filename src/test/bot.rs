@@ -4,7 +4,13 @@ mod client_bot;
 mod qa;
 mod utils;
 
-use std::{fmt::Debug, sync::Arc, vec};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+    vec,
+};
 
 use api_client::models::AccountIdLight;
 
@@ -15,7 +21,7 @@ use tokio::{
     sync::{mpsc, watch},
 };
 
-use error_stack::{Result, ResultExt};
+use error_stack::{IntoReport, Result, ResultExt};
 
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tracing::{error, info, log::warn};
@@ -23,19 +29,54 @@ use tracing::{error, info, log::warn};
 use self::{
     actions::{BotAction, DoNothing, PreviousValue},
     benchmark::{Benchmark, BenchmarkState},
-    client_bot::ClientBot,
+    client_bot::{script_actions, ClientBot},
     qa::Qa,
 };
 
+pub use self::{benchmark::benchmark_report, qa::QaReport};
+
 use super::{
     client::{ApiClient, TestError},
+    openapi::OpenApiValidator,
     state::{BotPersistentState, StateData},
 };
 
 use crate::config::args::{Test, TestMode};
 
+/// State shared between bots of one task.
 #[derive(Debug, Default)]
-pub struct TaskState {}
+pub struct TaskState {
+    /// Accounts which multiple bots use. Key is a name which bots agree on.
+    pub shared_accounts: HashMap<&'static str, SharedAccount>,
+    /// Signals for synchronizing bots.
+    pub signals: HashSet<&'static str>,
+    /// OpenAPI document which the server serves. Downloaded when needed.
+    pub openapi: Option<Arc<OpenApiValidator>>,
+}
+
+impl TaskState {
+    pub fn signal(&mut self, name: &'static str) {
+        self.signals.insert(name);
+    }
+
+    pub fn is_signaled(&self, name: &'static str) -> bool {
+        self.signals.contains(name)
+    }
+}
+
+/// Results which a bot task sends when it quits.
+#[derive(Debug)]
+pub struct TaskResult {
+    pub bot_states: Vec<BotPersistentState>,
+    /// Set only when running QA tests.
+    pub qa_report: Option<QaReport>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SharedAccount {
+    pub id: AccountIdLight,
+    pub access_token: String,
+}
 
 pub type WsConnection = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
@@ -127,7 +168,22 @@ pub trait BotStruct: Debug + Send + 'static {
         &mut self,
         task_state: &mut TaskState,
     ) -> Result<Option<Completed>, TestError> {
-        let mut result = self.run_action_impl(task_state).await;
+        let start = Instant::now();
+        let mut result = match self.time_left() {
+            Some(time_left) => {
+                match tokio::time::timeout(time_left, self.run_action_impl(task_state)).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        let action = self.peek_action_and_state().0;
+                        Err(TestError::Timeout)
+                            .into_report()
+                            .attach_printable_lazy(|| format!("Action: {:?}", action))
+                    }
+                }
+            }
+            None => self.run_action_impl(task_state).await,
+        };
+        self.use_time(start.elapsed());
         if let Test::Qa = self.state().config.test {
             result = result.attach_printable_lazy(|| format!("{:?}", self.state().action_history))
         }
@@ -162,28 +218,50 @@ pub trait BotStruct: Debug + Send + 'static {
     fn notify_task_bot_count_decreased(&mut self, bot_count: usize) {
         let _ = bot_count;
     }
+
+    /// Execution time which actions of the bot can still use. None if
+    /// there is no time limit.
+    fn time_left(&self) -> Option<Duration> {
+        None
+    }
+
+    fn use_time(&mut self, time: Duration) {
+        let _ = time;
+    }
+
+    /// Name of the QA test which the bot runs.
+    fn qa_test_name(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 pub struct BotManager {
     bots: Vec<Box<dyn BotStruct>>,
-    _bot_running_handle: mpsc::Sender<Vec<BotPersistentState>>,
+    _bot_running_handle: mpsc::Sender<TaskResult>,
     task_id: u32,
     config: Arc<TestMode>,
+    qa_report: Option<QaReport>,
 }
 
 impl BotManager {
+    /// Spawn bot task. QA tests of all selected test groups are run if
+    /// `qa_group` is None.
     pub fn spawn(
         task_id: u32,
         config: Arc<TestMode>,
+        qa_group: Option<usize>,
         old_state: Option<Arc<StateData>>,
         bot_quit_receiver: watch::Receiver<()>,
-        _bot_running_handle: mpsc::Sender<Vec<BotPersistentState>>,
+        _bot_running_handle: mpsc::Sender<TaskResult>,
     ) {
         let bot = match config.test {
-            Test::BenchmarkGetCalculatorState | Test::Bot => {
-                Self::benchmark_or_bot(task_id, old_state, config, _bot_running_handle)
-            }
-            Test::Qa => Self::qa(task_id, config, _bot_running_handle),
+            Test::BenchmarkGetCalculatorState
+            | Test::BenchmarkCacheVsDatabase
+            | Test::BenchmarkInternalApi
+            | Test::BenchmarkUpdateCalculatorState
+            | Test::BenchmarkMixed
+            | Test::Bot => Self::benchmark_or_bot(task_id, old_state, config, _bot_running_handle),
+            Test::Qa => Self::qa(task_id, config, qa_group, _bot_running_handle),
         };
 
         tokio::spawn(bot.run(bot_quit_receiver));
@@ -193,8 +271,9 @@ impl BotManager {
         task_id: u32,
         old_state: Option<Arc<StateData>>,
         config: Arc<TestMode>,
-        _bot_running_handle: mpsc::Sender<Vec<BotPersistentState>>,
+        _bot_running_handle: mpsc::Sender<TaskResult>,
     ) -> Self {
+        let script = config.bot_script.as_ref().map(script_actions);
         let mut bots = Vec::<Box<dyn BotStruct>>::new();
         for bot_i in 0..config.bot_count {
             let state = BotState::new(
@@ -208,14 +287,32 @@ impl BotManager {
                 config.clone(),
                 task_id,
                 bot_i,
-                ApiClient::new(config.server.api_urls.clone()),
+                ApiClient::new(
+                    config.server.api_urls.clone(),
+                    config.server.certificate_pin.as_ref(),
+                )
+                .with_retries(config.request_retries)
+                .with_internal_api(&config.server.internal_api_url),
             );
 
             match config.test {
                 Test::BenchmarkGetCalculatorState => {
                     bots.push(Box::new(Benchmark::benchmark_get_calculator_state(state)))
                 }
-                Test::Bot => bots.push(Box::new(ClientBot::new(state))),
+                Test::BenchmarkCacheVsDatabase => {
+                    bots.push(Box::new(Benchmark::benchmark_cache_vs_database(state)))
+                }
+                Test::BenchmarkInternalApi => {
+                    bots.push(Box::new(Benchmark::benchmark_internal_api(state)))
+                }
+                Test::BenchmarkUpdateCalculatorState => bots.push(Box::new(
+                    Benchmark::benchmark_update_calculator_state(state),
+                )),
+                Test::BenchmarkMixed => bots.push(Box::new(Benchmark::benchmark_mixed(state))),
+                Test::Bot => match script {
+                    Some(script) => bots.push(Box::new(ClientBot::with_script(state, script))),
+                    None => bots.push(Box::new(ClientBot::new(state))),
+                },
                 _ => panic!("Invalid test {:?}", config.test),
             };
         }
@@ -225,19 +322,17 @@ impl BotManager {
             _bot_running_handle,
             task_id,
             config,
+            qa_report: None,
         }
     }
 
     pub fn qa(
         task_id: u32,
         config: Arc<TestMode>,
-        _bot_running_handle: mpsc::Sender<Vec<BotPersistentState>>,
+        group: Option<usize>,
+        _bot_running_handle: mpsc::Sender<TaskResult>,
     ) -> Self {
-        if task_id >= 1 {
-            panic!("Only task count 1 is supported for QA tests");
-        }
-
-        let required_bots = qa::test_count() + 1;
+        let required_bots = qa::bot_count(&config, task_id, group) + 1;
 
         if (config.bot_count as usize) < required_bots {
             warn!("Increasing bot count to {}", required_bots);
@@ -250,24 +345,29 @@ impl BotManager {
                 config.clone(),
                 task_id,
                 bot_i,
-                ApiClient::new(config.server.api_urls.clone()),
+                ApiClient::new(
+                    config.server.api_urls.clone(),
+                    config.server.certificate_pin.as_ref(),
+                )
+                .with_retries(config.request_retries)
+                .with_internal_api(&config.server.internal_api_url),
             )
         };
 
-        for (i, (test_name, test)) in qa::ALL_QA_TESTS
-            .into_iter()
-            .map(|tests| *tests)
-            .flatten()
-            .enumerate()
-        {
-            let state = new_bot_state(i as u32 + 1);
-            let actions = test
-                .into_iter()
-                .map(|actions| *actions)
-                .flatten()
-                .map(|action| *action);
-            let bot = Qa::user_test(state, test_name, Box::new(actions));
-            bots.push(Box::new(bot));
+        let tests = qa::tests_for_task(&config, task_id, group);
+        let mut test_count = 0;
+        let mut bot_i = 0;
+        for test in tests {
+            test_count += 1;
+            let timeout = test.timeout.unwrap_or(config.qa_test_timeout);
+            // Every action array is run by its own bot.
+            for actions in test.bots.iter() {
+                bot_i += 1;
+                let state = new_bot_state(bot_i);
+                let actions = actions.iter().copied();
+                let bot = Qa::user_test(state, test.name, Box::new(actions), timeout);
+                bots.push(Box::new(bot));
+            }
         }
 
         Self {
@@ -275,9 +375,15 @@ impl BotManager {
             _bot_running_handle,
             task_id,
             config,
+            qa_report: Some(QaReport::new(test_count)),
         }
     }
 
+    /// Count of QA test groups which the config enables.
+    pub fn qa_group_count(config: &TestMode) -> usize {
+        qa::group_count(config)
+    }
+
     pub async fn run(mut self, mut bot_quit_receiver: watch::Receiver<()>) {
         loop {
             select! {
@@ -292,8 +398,20 @@ impl BotManager {
             }
         }
 
-        let data = self.iter_persistent_state();
-        self._bot_running_handle.send(data).await.unwrap();
+        let mut qa_report = self.qa_report.take();
+        if let Some(report) = qa_report.as_mut() {
+            for bot in self.bots.iter() {
+                if let Some(test_name) = bot.qa_test_name() {
+                    report.add_not_completed(test_name);
+                }
+            }
+        }
+
+        let result = TaskResult {
+            bot_states: self.iter_persistent_state(),
+            qa_report,
+        };
+        self._bot_running_handle.send(result).await.unwrap();
     }
 
     fn iter_persistent_state(&self) -> Vec<BotPersistentState> {
@@ -341,6 +459,11 @@ impl BotManager {
                 Ok(Some(Completed)) => return Some(i),
                 Err(e) => {
                     error!("Task {}, bot returned error: {:?}", self.task_id, e);
+                    if let (Some(report), Some(test_name)) =
+                        (self.qa_report.as_mut(), b.qa_test_name())
+                    {
+                        report.add_failed(test_name);
+                    }
                     *errors = true;
                     return Some(i);
                 }
@@ -2,16 +2,25 @@ pub mod account;
 pub mod calculator;
 pub mod common;
 
-use std::{fmt::Debug, time::Duration};
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
 
 use api_client::models::AccountState;
 use async_trait::async_trait;
 
 use error_stack::{FutureExt, Result, ResultExt};
 
-use self::account::{AssertAccountState, CompleteAccountSetup, Login, Register, SetAccountSetup};
+use self::{
+    account::{
+        AssertAccountState, CompleteAccountSetup, DisconnectWebSocket, Login, ReconnectWebSocket,
+        Register, SetAccountSetup,
+    },
+    calculator::{AssertCalculatorStateUnchanged, ChangeCalculatorStateUnique},
+};
 
-use super::super::client::TestError;
+use super::super::client::{ApiErrorKind, TestError};
 
 use super::{BotState, TaskState};
 
@@ -102,6 +111,67 @@ impl<T: BotAction> BotAction for AssertFailure<T> {
     }
 }
 
+/// Assert that the action fails with a specific API error.
+#[derive(Debug)]
+pub struct AssertApiError<T: BotAction>(pub T, pub ApiErrorKind);
+
+#[async_trait]
+impl<T: BotAction> BotAction for AssertApiError<T> {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        match self.0.excecute(state, task_state).await {
+            Err(e) => match e.downcast_ref::<ApiErrorKind>().copied() {
+                Some(kind) if kind == self.1 => Ok(()),
+                Some(kind) => Err(e.change_context(TestError::AssertError(format!(
+                    "API error was {}, expected {}",
+                    kind, self.1
+                )))),
+                None => Err(e),
+            },
+            Ok(()) => Err(TestError::AssertError("API request did not fail".to_string()).into()),
+        }
+    }
+}
+
+/// Repeat the action until it fails with a specific API error. Fails if the
+/// action does not fail with the error within the time limit.
+#[derive(Debug)]
+pub struct AssertApiErrorWithin<T: BotAction> {
+    pub action: T,
+    pub error: ApiErrorKind,
+    pub time_limit: Duration,
+}
+
+#[async_trait]
+impl<T: BotAction> BotAction for AssertApiErrorWithin<T> {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        let start = Instant::now();
+        loop {
+            match self.action.excecute(state, task_state).await {
+                Err(e) => match e.downcast_ref::<ApiErrorKind>() {
+                    Some(kind) if *kind == self.error => return Ok(()),
+                    _ => return Err(e),
+                },
+                Ok(()) if start.elapsed() >= self.time_limit => {
+                    return Err(TestError::AssertError(format!(
+                        "API request did not fail with {} within {:?}",
+                        self.error, self.time_limit
+                    ))
+                    .into())
+                }
+                Ok(()) => tokio::time::sleep(Duration::from_millis(50)).await,
+            }
+        }
+    }
+}
+
 /// Sleep milliseconds
 #[derive(Debug)]
 pub struct SleepMillis(pub u64);
@@ -297,3 +367,16 @@ pub const TO_NORMAL_STATE: ActionArray = action_array![
     CompleteAccountSetup,
     AssertAccountState(AccountState::Normal),
 ];
+
+/// Time which the app spends in background in [APP_BACKGROUND_AND_FOREGROUND].
+pub const APP_BACKGROUND_MILLIS: u64 = 500;
+
+/// Mobile app moves to background and back to foreground. Calculator state
+/// which was saved before moving to background must not change.
+pub const APP_BACKGROUND_AND_FOREGROUND: ActionArray = action_array![
+    ChangeCalculatorStateUnique,
+    DisconnectWebSocket,
+    SleepMillis(APP_BACKGROUND_MILLIS),
+    ReconnectWebSocket,
+    AssertCalculatorStateUnchanged,
+];
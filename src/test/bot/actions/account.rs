@@ -1,10 +1,14 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use api_client::{
     apis::account_api::{
-        get_account_state, post_account_setup, post_complete_setup, post_login, post_register,
+        get_account_state, post_account_setup, post_cancel_delete, post_complete_setup,
+        post_delete, post_login, post_refresh, post_register, post_token_exchange,
+    },
+    models::{
+        auth_pair, AccountSetup, AccountSetupError, AccountSetupErrorCode, AccountState, ApiKey,
+        EventToClient, RefreshToken,
     },
-    models::{auth_pair, AccountSetup, AccountState},
 };
 use async_trait::async_trait;
 
@@ -13,14 +17,24 @@ use error_stack::{IntoReport, Result};
 use futures::SinkExt;
 use headers::HeaderValue;
 use tokio_stream::StreamExt;
-use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message},
+    Connector,
+};
 use url::Url;
 
-use super::{super::super::client::TestError, BotAction};
+use super::{
+    super::super::client::{IntoApiError, TestError},
+    BotAction,
+};
 
 use crate::{
-    api::{common::PATH_CONNECT, utils::API_KEY_HEADER_STR},
-    test::bot::{utils::assert::bot_assert_eq, WsConnection},
+    api::{
+        admin::{PATH_INTERNAL_POST_ADMIN_BAN, PATH_INTERNAL_POST_ADMIN_UNBAN},
+        common::{AppVersion, ClientVersion, PATH_CONNECT, WEBSOCKET_PROTOCOL_VERSION},
+        utils::API_KEY_HEADER_STR,
+    },
+    test::bot::{utils::assert::bot_assert_eq, SharedAccount, TaskState, WsConnection},
     utils::IntoReportExt,
 };
 
@@ -36,9 +50,7 @@ impl BotAction for Register {
             return Ok(());
         }
 
-        let id = post_register(state.api.account())
-            .await
-            .into_error(TestError::ApiRequest)?;
+        let id = post_register(state.api.account()).await.into_api_error()?;
         state.id = Some(id);
         Ok(())
     }
@@ -55,31 +67,19 @@ impl BotAction for Login {
         }
         let login_result = post_login(state.api.account(), state.id()?)
             .await
-            .into_error(TestError::ApiRequest)?;
+            .into_api_error()?;
 
         state
             .api
             .set_access_token(login_result.account.access.api_key.clone());
 
-        let url = state
-            .config
-            .server
-            .api_urls
-            .account_base_url
-            .join(PATH_CONNECT)
-            .into_error(TestError::WebSocket)?;
+        let url = connect_url(&state.config.server.api_urls.account_base_url)?;
         state.connections.account = connect_websocket(*login_result.account, url, state)
             .await?
             .into();
 
         if let Some(calculator) = login_result.calculator.flatten() {
-            let url = state
-                .config
-                .server
-                .api_urls
-                .calculator_base_url
-                .join(PATH_CONNECT)
-                .into_error(TestError::WebSocket)?;
+            let url = connect_url(&state.config.server.api_urls.calculator_base_url)?;
             state.connections.calculator = connect_websocket(*calculator, url, state).await?.into();
         }
 
@@ -87,6 +87,136 @@ impl BotAction for Login {
     }
 }
 
+/// Login and get tokens from HTTP token exchange without connecting the
+/// WebSocket.
+#[derive(Debug)]
+pub struct LoginWithHttpTokenExchange;
+
+#[async_trait]
+impl BotAction for LoginWithHttpTokenExchange {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let login_result = post_login(state.api.account(), state.id()?)
+            .await
+            .into_api_error()?;
+
+        let pair = post_token_exchange(state.api.account(), *login_result.account.refresh)
+            .await
+            .into_api_error()?;
+
+        state.api.set_access_token(pair.access.api_key);
+        state.refresh_token = Some(
+            base64::engine::general_purpose::STANDARD
+                .decode(pair.refresh.token)
+                .into_error(TestError::ApiRequest)?,
+        );
+
+        Ok(())
+    }
+}
+
+/// Close WebSocket connections like a mobile app which moves to
+/// background. Connections are closed with a close message, so the server
+/// ends the session but keeps the refresh token.
+#[derive(Debug)]
+pub struct DisconnectWebSocket;
+
+#[async_trait]
+impl BotAction for DisconnectWebSocket {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let connections = [
+            state.connections.account.take(),
+            state.connections.calculator.take(),
+        ];
+        for mut connection in connections.into_iter().flatten() {
+            connection
+                .close(None)
+                .await
+                .into_error(TestError::WebSocket)?;
+        }
+        Ok(())
+    }
+}
+
+/// Connect again after [DisconnectWebSocket] like a mobile app which moves
+/// to foreground. Tokens are refreshed with HTTP before connecting, so the
+/// refresh token is rotated twice. Calculator microservice connection
+/// requires tokens from login, so with the microservice the bot logs in
+/// again.
+#[derive(Debug)]
+pub struct ReconnectWebSocket;
+
+#[async_trait]
+impl BotAction for ReconnectWebSocket {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        if state.config.server.microservice_calculator {
+            return LoginAgain.excecute(state, task_state).await;
+        }
+
+        let refresh_token = state
+            .refresh_token
+            .as_ref()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        let refresh_token = base64::engine::general_purpose::STANDARD.encode(refresh_token);
+        let pair = post_refresh(state.api.account(), RefreshToken::new(refresh_token))
+            .await
+            .into_api_error()?;
+        state.api.set_access_token(pair.access.api_key.clone());
+
+        let url = connect_url(&state.config.server.api_urls.account_base_url)?;
+        state.connections.account = connect_websocket(pair, url, state).await?.into();
+        Ok(())
+    }
+}
+
+/// Login even if the bot has an access token.
+#[derive(Debug)]
+pub struct LoginAgain;
+
+#[async_trait]
+impl BotAction for LoginAgain {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        state.api.remove_access_token();
+        Login.excecute(state, task_state).await
+    }
+}
+
+/// Ban the account of the bot using the internal admin API.
+#[derive(Debug)]
+pub struct AdminBanAccount;
+
+#[async_trait]
+impl BotAction for AdminBanAccount {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let path = PATH_INTERNAL_POST_ADMIN_BAN.replace(":account_id", &state.id_string()?);
+        state.api.internal_post(&path).await
+    }
+}
+
+/// Unban the account of the bot using the internal admin API.
+#[derive(Debug)]
+pub struct AdminUnbanAccount;
+
+#[async_trait]
+impl BotAction for AdminUnbanAccount {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let path = PATH_INTERNAL_POST_ADMIN_UNBAN.replace(":account_id", &state.id_string()?);
+        state.api.internal_post(&path).await
+    }
+}
+
+fn connect_url(base_url: &Url) -> Result<Url, TestError> {
+    base_url.join(PATH_CONNECT).into_error(TestError::WebSocket)
+}
+
 async fn connect_websocket(
     auth: auth_pair::AuthPair,
     mut url: Url,
@@ -108,7 +238,26 @@ async fn connect_websocket(
         API_KEY_HEADER_STR,
         HeaderValue::from_str(&auth.access.api_key).into_error(TestError::WebSocket)?,
     );
-    let (mut stream, _) = tokio_tungstenite::connect_async(r)
+    let connector = state
+        .config
+        .server
+        .certificate_pin
+        .as_ref()
+        .map(|pin| Connector::Rustls(Arc::new(pin.client_config())));
+    let (mut stream, _) =
+        tokio_tungstenite::connect_async_tls_with_config(r, None, false, connector)
+            .await
+            .into_error(TestError::WebSocket)?;
+
+    let version = ClientVersion {
+        protocol_version: WEBSOCKET_PROTOCOL_VERSION,
+        app_version: AppVersion::try_from(env!("CARGO_PKG_VERSION").to_string())
+            .map_err(|_| TestError::WebSocket)
+            .into_report()?,
+    };
+    let version = serde_json::to_string(&version).into_error(TestError::WebSocket)?;
+    stream
+        .send(Message::Text(version))
         .await
         .into_error(TestError::WebSocket)?;
 
@@ -144,20 +293,134 @@ async fn connect_websocket(
     Ok(stream)
 }
 
+const EVENT_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read events from the account WebSocket connection until the event is
+/// received. Other events are skipped.
+#[derive(Debug)]
+pub struct AssertEventReceived(pub EventToClient);
+
+#[async_trait]
+impl BotAction for AssertEventReceived {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let connection = state
+            .connections
+            .account
+            .as_mut()
+            .ok_or(TestError::WebSocket)
+            .into_report()?;
+
+        let expected = |event: &EventToClient| *event == self.0;
+        match tokio::time::timeout(EVENT_WAIT_TIMEOUT, wait_event(connection, expected)).await {
+            Ok(result) => result,
+            Err(_) => Err(TestError::AssertError(format!(
+                "Event {:?} was not received",
+                self.0
+            )))
+            .into_report(),
+        }
+    }
+}
+
+async fn wait_event(
+    connection: &mut WsConnection,
+    expected: impl Fn(&EventToClient) -> bool,
+) -> Result<(), TestError> {
+    loop {
+        if expected(&next_event(connection).await?) {
+            return Ok(());
+        }
+    }
+}
+
+/// Login like another device without closing the current WebSocket
+/// connection. Current connection should receive the new session event.
+#[derive(Debug)]
+pub struct AssertNewSessionEventReceived;
+
+#[async_trait]
+impl BotAction for AssertNewSessionEventReceived {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let login_result = post_login(state.api.account(), state.id()?)
+            .await
+            .into_api_error()?;
+        state
+            .api
+            .set_access_token(login_result.account.access.api_key.clone());
+
+        let connection = state
+            .connections
+            .account
+            .as_mut()
+            .ok_or(TestError::WebSocket)
+            .into_report()?;
+
+        let expected = |event: &EventToClient| matches!(event, EventToClient::NewSessionCreated(info) if !info.address.is_empty());
+        match tokio::time::timeout(EVENT_WAIT_TIMEOUT, wait_event(connection, expected)).await {
+            Ok(result) => result,
+            Err(_) => Err(TestError::AssertError(
+                "New session event was not received".to_string(),
+            ))
+            .into_report(),
+        }
+    }
+}
+
+async fn next_event(connection: &mut WsConnection) -> Result<EventToClient, TestError> {
+    loop {
+        let message = connection
+            .next()
+            .await
+            .ok_or(TestError::WebSocket)
+            .into_report()?
+            .into_error(TestError::WebSocket)?;
+        match message {
+            Message::Text(event) => {
+                return serde_json::from_str(&event).into_error(TestError::WebSocketWrongValue)
+            }
+            // Ping and pong messages.
+            _ => continue,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AssertAccountState(pub AccountState);
 
 #[async_trait]
 impl BotAction for AssertAccountState {
     async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
-        let state = get_account_state(state.api.account())
-            .await
-            .into_error(TestError::ApiRequest)?;
+        let state = state
+            .api
+            .retry_idempotent(|| get_account_state(state.api.account()))
+            .await?;
 
         bot_assert_eq(state.state, self.0)
     }
 }
 
+/// Registration and login times are returned with the account state.
+#[derive(Debug)]
+pub struct AssertAccountTimesSet;
+
+#[async_trait]
+impl BotAction for AssertAccountTimesSet {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let account = state
+            .api
+            .retry_idempotent(|| get_account_state(state.api.account()))
+            .await?;
+
+        match (account.created_unix_time, account.last_login_unix_time) {
+            (Some(Some(created)), Some(Some(last_login))) if created <= last_login => Ok(()),
+            times => Err(TestError::AssertError(format!(
+                "Invalid account times (created, last login): {times:?}"
+            ))
+            .into()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SetAccountSetup {
     pub email: Option<&'static str>,
@@ -180,12 +443,60 @@ impl BotAction for SetAccountSetup {
         };
         post_account_setup(state.api.account(), setup)
             .await
-            .into_error(TestError::ApiRequest)?;
+            .into_api_error()?;
 
         Ok(())
     }
 }
 
+/// Expected reason for account setup rejection.
+#[derive(Debug)]
+pub enum SetupRejection {
+    /// Status code 400 with the error code.
+    Invalid(AccountSetupErrorCode),
+    /// Status code 403.
+    WrongState,
+}
+
+/// Post account setup and check that it is rejected with the expected
+/// reason.
+#[derive(Debug)]
+pub struct AssertAccountSetupRejected {
+    pub email: fn() -> String,
+    pub expected: SetupRejection,
+}
+
+#[async_trait]
+impl BotAction for AssertAccountSetupRejected {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let setup = AccountSetup {
+            email: (self.email)(),
+        };
+        let response = match post_account_setup(state.api.account(), setup).await {
+            Ok(()) => {
+                return Err(TestError::AssertError(
+                    "Account setup was not rejected".to_string(),
+                ))
+                .into_report()
+            }
+            Err(api_client::apis::Error::ResponseError(response)) => response,
+            Err(e) => return Err(e).into_api_error(),
+        };
+
+        match self.expected {
+            SetupRejection::Invalid(code) => {
+                bot_assert_eq(response.status, reqwest::StatusCode::BAD_REQUEST)?;
+                let error: AccountSetupError = serde_json::from_str(&response.content)
+                    .into_error(TestError::SerdeDeserialize)?;
+                bot_assert_eq(error.code, code)
+            }
+            SetupRejection::WrongState => {
+                bot_assert_eq(response.status, reqwest::StatusCode::FORBIDDEN)
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CompleteAccountSetup;
 
@@ -194,8 +505,213 @@ impl BotAction for CompleteAccountSetup {
     async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
         post_complete_setup(state.api.account())
             .await
-            .into_error(TestError::ApiRequest)?;
+            .into_api_error()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RequestAccountDeletion;
+
+#[async_trait]
+impl BotAction for RequestAccountDeletion {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        post_delete(state.api.account()).await.into_api_error()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CancelAccountDeletion;
+
+#[async_trait]
+impl BotAction for CancelAccountDeletion {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        post_cancel_delete(state.api.account())
+            .await
+            .into_api_error()?;
+
+        Ok(())
+    }
+}
+
+/// Get new tokens with the current refresh token using HTTP.
+#[derive(Debug)]
+pub struct RefreshTokens;
+
+#[async_trait]
+impl BotAction for RefreshTokens {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let refresh_token = state
+            .refresh_token
+            .as_ref()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        let refresh_token = base64::engine::general_purpose::STANDARD.encode(refresh_token);
 
+        let pair = post_refresh(state.api.account(), RefreshToken::new(refresh_token))
+            .await
+            .into_api_error()?;
+
+        state.api.set_access_token(pair.access.api_key);
+        state.refresh_token = Some(
+            base64::engine::general_purpose::STANDARD
+                .decode(pair.refresh.token)
+                .into_error(TestError::ApiRequest)?,
+        );
+
+        Ok(())
+    }
+}
+
+/// Refresh tokens and check that the previous access and refresh tokens
+/// do not work anymore.
+#[derive(Debug)]
+pub struct AssertRefreshInvalidatesPreviousTokens;
+
+#[async_trait]
+impl BotAction for AssertRefreshInvalidatesPreviousTokens {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let previous_access_token = state
+            .api
+            .api_key()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        let previous_refresh_token = state
+            .refresh_token
+            .as_ref()
+            .map(|token| base64::engine::general_purpose::STANDARD.encode(token))
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+
+        RefreshTokens.excecute_impl(state).await?;
+
+        if post_refresh(
+            state.api.account(),
+            RefreshToken::new(previous_refresh_token),
+        )
+        .await
+        .is_ok()
+        {
+            return Err(TestError::AssertError(
+                "Previous refresh token is still valid".to_string(),
+            ))
+            .into_report();
+        }
+
+        let current_access_token = state
+            .api
+            .api_key()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        state.api.set_access_token(previous_access_token);
+        let previous_access_token_works = get_account_state(state.api.account()).await.is_ok();
+        state.api.set_access_token(current_access_token);
+
+        if previous_access_token_works {
+            Err(TestError::AssertError(
+                "Previous access token is still valid".to_string(),
+            ))
+            .into_report()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Refresh tokens and connect WebSocket with the previous refresh token.
+/// Server should detect the reuse and revoke the new tokens.
+#[derive(Debug)]
+pub struct AssertRefreshTokenReuseRevokesSessions;
+
+#[async_trait]
+impl BotAction for AssertRefreshTokenReuseRevokesSessions {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let previous_refresh_token = state
+            .refresh_token
+            .as_ref()
+            .map(|token| base64::engine::general_purpose::STANDARD.encode(token))
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+
+        RefreshTokens.excecute_impl(state).await?;
+
+        let current_access_token = state
+            .api
+            .api_key()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        let auth = auth_pair::AuthPair::new(
+            ApiKey::new(current_access_token),
+            RefreshToken::new(previous_refresh_token),
+        );
+        let url = connect_url(&state.config.server.api_urls.account_base_url)?;
+        if connect_websocket(auth, url, state).await.is_ok() {
+            return Err(TestError::AssertError(
+                "WebSocket connection with previous refresh token succeeded".to_string(),
+            ))
+            .into_report();
+        }
+
+        if get_account_state(state.api.account()).await.is_ok() {
+            Err(TestError::AssertError(
+                "Access token is still valid after refresh token reuse".to_string(),
+            ))
+            .into_report()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Share the bot's account with other bots of the task.
+#[derive(Debug)]
+pub struct ShareAccount(pub &'static str);
+
+#[async_trait]
+impl BotAction for ShareAccount {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        let access_token = state
+            .api
+            .api_key()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        task_state.shared_accounts.insert(
+            self.0,
+            SharedAccount {
+                id: state.id()?,
+                access_token,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Use account which other bot shared. Bot waits until the account is
+/// shared.
+#[derive(Debug)]
+pub struct UseSharedAccount(pub &'static str);
+
+#[async_trait]
+impl BotAction for UseSharedAccount {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        let account = task_state
+            .shared_accounts
+            .get(self.0)
+            .ok_or(TestError::BotIsWaiting)
+            .into_report()?;
+        state.id = Some(account.id);
+        state.api.set_access_token(account.access_token.clone());
         Ok(())
     }
 }
@@ -1,12 +1,17 @@
 use std::fmt::Debug;
 
-use api_client::{apis::calculator_api, models::CalculatorState};
+use api_client::{
+    apis::calculator_api,
+    models::{CalculationRequest, CalculatorState},
+};
 use async_trait::async_trait;
-use error_stack::Result;
+use error_stack::{IntoReport, Result};
+use futures::future::join_all;
 
-use super::{super::super::client::TestError, BotAction, PreviousValue};
-
-use crate::utils::IntoReportExt;
+use super::{
+    super::super::client::{IntoApiError, TestError},
+    BotAction, PreviousValue,
+};
 
 use super::BotState;
 
@@ -21,7 +26,57 @@ impl BotAction for ChangeCalculatorState {
         let s = CalculatorState::new(self.state.to_string());
         api_client::apis::calculator_api::post_calculator_state(state.api.calculator(), s)
             .await
-            .into_error(TestError::ApiRequest)?;
+            .into_api_error()?;
+        Ok(())
+    }
+}
+
+/// Save calculator state which is unique for every call. The state is
+/// saved to the previous value.
+#[derive(Debug)]
+pub struct ChangeCalculatorStateUnique;
+
+#[async_trait]
+impl BotAction for ChangeCalculatorStateUnique {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let value = uuid::Uuid::new_v4().hyphenated().to_string();
+        let s = CalculatorState::new(value.clone());
+        calculator_api::post_calculator_state(state.api.calculator(), s)
+            .await
+            .into_api_error()?;
+        state.previous_value = PreviousValue::CalculatorState(value);
+        Ok(())
+    }
+
+    fn previous_value_supported(&self) -> bool {
+        true
+    }
+}
+
+/// Check that the server has the calculator state which is in the previous
+/// value.
+#[derive(Debug)]
+pub struct AssertCalculatorStateUnchanged;
+
+#[async_trait]
+impl BotAction for AssertCalculatorStateUnchanged {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let expected = state
+            .previous_value
+            .calculator_state()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        let data = state
+            .api
+            .retry_idempotent(|| calculator_api::get_calculator_state(state.api.calculator()))
+            .await?;
+        if data.state != expected {
+            return Err(TestError::AssertError(format!(
+                "Calculator state changed. was: {:?}, expected: {:?}",
+                data.state, expected
+            )))
+            .into_report();
+        }
         Ok(())
     }
 }
@@ -32,9 +87,10 @@ pub struct GetCalculatorState;
 #[async_trait]
 impl BotAction for GetCalculatorState {
     async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
-        let data = calculator_api::get_calculator_state(state.api.calculator())
-            .await
-            .into_error(TestError::ApiRequest)?;
+        let data = state
+            .api
+            .retry_idempotent(|| calculator_api::get_calculator_state(state.api.calculator()))
+            .await?;
         state.previous_value = PreviousValue::CalculatorState(data.state);
         Ok(())
     }
@@ -43,3 +99,66 @@ impl BotAction for GetCalculatorState {
         true
     }
 }
+
+/// Evaluate expression on the server. Formatted result is saved to the
+/// previous value.
+#[derive(Debug)]
+pub struct Calculate {
+    pub expression: &'static str,
+}
+
+#[async_trait]
+impl BotAction for Calculate {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let request = CalculationRequest::new(self.expression.to_string());
+        let result = calculator_api::post_calculate(state.api.calculator(), request)
+            .await
+            .into_api_error()?;
+        state.previous_value = PreviousValue::CalculatorState(result.formatted_result);
+        Ok(())
+    }
+
+    fn previous_value_supported(&self) -> bool {
+        true
+    }
+}
+
+/// Send calculator state writes and reads concurrently. Every request must
+/// succeed.
+#[derive(Debug)]
+pub struct ConcurrentCalculatorStateWrites {
+    pub writes: usize,
+}
+
+#[async_trait]
+impl BotAction for ConcurrentCalculatorStateWrites {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let configuration = state.api.calculator();
+        let writes = (0..self.writes).map(|i| {
+            let s = CalculatorState::new(format!("{}-{}", state.bot_id, i));
+            calculator_api::post_calculator_state(configuration, s)
+        });
+        let reads = (0..self.writes).map(|_| calculator_api::get_calculator_state(configuration));
+
+        let (writes, reads) = futures::join!(join_all(writes), join_all(reads));
+
+        let failed_writes = writes.iter().filter(|r| r.is_err()).count();
+        let failed_reads = reads.iter().filter(|r| r.is_err()).count();
+        if failed_writes == 0 && failed_reads == 0 {
+            Ok(())
+        } else {
+            Err(TestError::AssertError(format!(
+                "Failed writes: {}, failed reads: {}, first error: {:?}",
+                failed_writes,
+                failed_reads,
+                writes
+                    .into_iter()
+                    .find_map(|r| r.err().map(|e| e.to_string()))
+                    .or(reads
+                        .into_iter()
+                        .find_map(|r| r.err().map(|e| e.to_string()))),
+            )))
+            .into_report()
+        }
+    }
+}
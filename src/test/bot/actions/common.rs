@@ -1,9 +1,23 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
+use api_client::manual_additions;
 use async_trait::async_trait;
-use error_stack::Result;
+use error_stack::{IntoReport, Result};
+use hyper::{Method, StatusCode};
 
-use super::{super::super::client::TestError, BotAction};
+use super::{
+    super::super::{
+        client::{ApiClient, TestError},
+        openapi::OpenApiValidator,
+        tls::CertificatePin,
+    },
+    BotAction,
+};
+
+use crate::{
+    test::bot::{utils::assert::bot_assert_eq, TaskState},
+    utils::IntoReportExt,
+};
 
 use super::BotState;
 
@@ -17,3 +31,88 @@ impl BotAction for TestWebSocket {
         Ok(())
     }
 }
+
+/// Request with a wrong certificate pin must fail. Does nothing if bots do
+/// not use certificate pinning.
+#[derive(Debug)]
+pub struct AssertWrongCertificatePinFails;
+
+#[async_trait]
+impl BotAction for AssertWrongCertificatePinFails {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        if state.config.server.certificate_pin.is_none() {
+            return Ok(());
+        }
+
+        let api = ApiClient::new(
+            state.config.server.api_urls.clone(),
+            Some(&CertificatePin::wrong()),
+        );
+        match manual_additions::api_available(api.account()).await {
+            Ok(()) => Err(TestError::AssertError(
+                "Request with wrong certificate pin succeeded".to_string(),
+            )
+            .into()),
+            Err(()) => Ok(()),
+        }
+    }
+}
+
+/// Make a request and check that the response status code is the expected
+/// one and that the status code and the response body match the OpenAPI
+/// document which the server serves. The document is downloaded once per
+/// task.
+#[derive(Debug)]
+pub struct AssertOpenApiConformance {
+    pub method: Method,
+    pub path: &'static str,
+    /// JSON request body
+    pub body: Option<&'static str>,
+    pub status: StatusCode,
+}
+
+impl AssertOpenApiConformance {
+    pub const fn get(path: &'static str) -> Self {
+        Self {
+            method: Method::GET,
+            path,
+            body: None,
+            status: StatusCode::OK,
+        }
+    }
+}
+
+#[async_trait]
+impl BotAction for AssertOpenApiConformance {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        let validator = match &task_state.openapi {
+            Some(validator) => validator.clone(),
+            None => {
+                let validator =
+                    Arc::new(OpenApiValidator::new(state.api.openapi_document().await?));
+                task_state.openapi = Some(validator.clone());
+                validator
+            }
+        };
+
+        let body = self
+            .body
+            .map(serde_json::from_str::<serde_json::Value>)
+            .transpose()
+            .into_error(TestError::SerdeDeserialize)?;
+        let (status, response) = state
+            .api
+            .raw_request(self.method.clone(), self.path, body)
+            .await?;
+
+        validator
+            .validate_response(&self.method, self.path, status, &response)
+            .map_err(|problems| TestError::AssertError(problems.join("; ")))
+            .into_report()?;
+        bot_assert_eq(status, self.status)
+    }
+}
@@ -1,16 +1,33 @@
 //! Bots for benchmarking
 
+mod report;
+
 use std::{
     fmt::Debug,
     iter::Peekable,
     time::{Duration, Instant},
 };
 
-use api_client::apis::calculator_api::get_calculator_state;
+use api_client::{
+    apis::{
+        account_api::get_account_state,
+        accountinternal_api::{check_api_key, internal_get_account_state},
+        calculator_api::{get_calculator_state, post_calculator_state},
+    },
+    models::{ApiKey, CalculatorState},
+};
 use async_trait::async_trait;
+use rand::Rng;
 use tokio::time::sleep;
 
-use crate::test::client::TestError;
+use crate::{
+    config::args::Test,
+    server::database::commands::WriteQueueStats,
+    test::client::{ApiErrorKind, TestError},
+};
+
+pub use self::report::BenchmarkReport;
+use self::report::BenchmarkResults;
 
 use super::{
     actions::{
@@ -18,23 +35,149 @@ use super::{
         calculator::ChangeCalculatorState,
         BotAction,
     },
-    utils::{Counters, Timer},
+    client_bot::DoInitialSetupIfNeeded,
+    utils::{ApiErrorCounters, Counters, DurationCounter, Timer},
     BotState, BotStruct, TaskState,
 };
 
-use error_stack::Result;
+use error_stack::{IntoReport, Result};
 
 use tracing::log::info;
 
-use crate::utils::IntoReportExt;
-
 static COUNTERS: Counters = Counters::new();
+static API_ERRORS: ApiErrorCounters = ApiErrorCounters::new();
+static ACCOUNT_STATE_TIME: DurationCounter = DurationCounter::new();
+static CALCULATOR_STATE_TIME: DurationCounter = DurationCounter::new();
+static CHECK_API_KEY_TIME: DurationCounter = DurationCounter::new();
+static INTERNAL_ACCOUNT_STATE_TIME: DurationCounter = DurationCounter::new();
+static UPDATE_CALCULATOR_STATE_TIME: DurationCounter = DurationCounter::new();
+static MIXED_READ_TIME: DurationCounter = DurationCounter::new();
+static MIXED_WRITE_TIME: DurationCounter = DurationCounter::new();
+static RESULTS: BenchmarkResults = BenchmarkResults::new();
+
+/// Write queue is saturated if commands wait in the queue at least this
+/// long on average.
+const SATURATION_QUEUE_WAIT: Duration = Duration::from_millis(10);
+/// Write queue is saturated if at least this many commands are waiting.
+const SATURATION_QUEUE_DEPTH: u64 = 4;
+/// Write queue is saturated if the write command runner is handling
+/// commands at least this fraction of the time.
+const SATURATION_RUNNER_BUSY: f64 = 0.9;
+
+/// Count failed API requests, so that the benchmark continues and reports
+/// the error breakdown. Other errors stop the benchmark. The request is
+/// recorded to the benchmark results. Returns the request time.
+fn record_request<T>(
+    action: &'static str,
+    start: Instant,
+    result: Result<T, TestError>,
+) -> Result<Duration, TestError> {
+    let time = start.elapsed();
+    let error = match result {
+        Ok(_) => None,
+        Err(e) => match e.downcast_ref::<ApiErrorKind>() {
+            Some(kind) => {
+                API_ERRORS.inc(*kind);
+                Some(*kind)
+            }
+            None => return Err(e),
+        },
+    };
+    RESULTS.record(action, time, error);
+    Ok(time)
+}
+
+/// Results of all benchmark bots since the first benchmarked request.
+pub fn benchmark_report(test: &Test) -> BenchmarkReport {
+    RESULTS.report(test.as_str())
+}
+
+/// Poll server metrics and print write queue status. Write queue is flagged
+/// as the bottleneck if it is saturated, so that throughput numbers printed
+/// before this are not mistaken for API limits. Metrics are not available
+/// if the internal API is not reachable, which does not stop the benchmark.
+async fn print_write_queue_status(state: &mut BotState) {
+    let stats = match state.api.server_metrics().await {
+        Ok(metrics) => metrics.write_queue,
+        Err(e) => {
+            info!("write queue: metrics not available, error: {:?}", e);
+            return;
+        }
+    };
+    let now = Instant::now();
+    let previous = state
+        .benchmark
+        .write_queue_stats
+        .replace((now, stats.clone()));
+    let (previous_time, previous) = match previous {
+        Some(previous) => previous,
+        None => return,
+    };
+
+    let commands = stats.commands.saturating_sub(previous.commands);
+    let queue_wait = stats
+        .queue_wait_micros
+        .saturating_sub(previous.queue_wait_micros);
+    let avg_queue_wait = Duration::from_micros(if commands == 0 {
+        0
+    } else {
+        queue_wait / commands
+    });
+    let processing = stats
+        .processing_micros
+        .saturating_sub(previous.processing_micros);
+    let elapsed = now.duration_since(previous_time).as_micros().max(1);
+    let runner_busy = processing as f64 / elapsed as f64;
+    let batches = stats.batches.saturating_sub(previous.batches);
+    let batched_commands = stats
+        .batched_commands
+        .saturating_sub(previous.batched_commands);
+    let avg_batch_size = if batches == 0 {
+        0.0
+    } else {
+        batched_commands as f64 / batches as f64
+    };
+
+    let mut saturation = vec![];
+    if stats.queue_depth >= SATURATION_QUEUE_DEPTH {
+        saturation.push("queue depth");
+    }
+    if avg_queue_wait >= SATURATION_QUEUE_WAIT {
+        saturation.push("queue wait");
+    }
+    if runner_busy >= SATURATION_RUNNER_BUSY {
+        saturation.push("runner busy");
+    }
+
+    let status = if saturation.is_empty() {
+        "not saturated".to_string()
+    } else {
+        format!(
+            "SATURATED ({}), bottleneck: write command runner",
+            saturation.join(", ")
+        )
+    };
+    info!(
+        "write queue: depth: {}, commands: {}, avg queue wait: {:?}, runner busy: {:.0}%, batches: {}, avg batch size: {:.1}, {}",
+        stats.queue_depth,
+        commands,
+        avg_queue_wait,
+        runner_busy * 100.0,
+        batches,
+        avg_batch_size,
+        status,
+    );
+}
 
 #[derive(Debug)]
 pub struct BenchmarkState {
     pub update_calculator_state_timer: Timer,
     pub print_info_timer: Timer,
     pub action_duration: Instant,
+    /// Previous write queue stats for calculating differences.
+    pub write_queue_stats: Option<(Instant, WriteQueueStats)>,
+    /// Start of the current mixed benchmark throughput period.
+    pub throughput_start: Instant,
 }
 
 impl BenchmarkState {
@@ -43,6 +186,8 @@ impl BenchmarkState {
             update_calculator_state_timer: Timer::new(Duration::from_millis(1000)),
             print_info_timer: Timer::new(Duration::from_millis(1000)),
             action_duration: Instant::now(),
+            write_queue_stats: None,
+            throughput_start: Instant::now(),
         }
     }
 }
@@ -60,7 +205,7 @@ impl Debug for Benchmark {
 
 impl Benchmark {
     pub fn benchmark_get_calculator_state(state: BotState) -> Self {
-        let setup = [&Register as &dyn BotAction, &Login];
+        let setup = [&Register as &dyn BotAction, &Login, &DoInitialSetupIfNeeded];
         let benchmark = [
             &UpdateCalculatorStateBenchmark as &dyn BotAction,
             &ActionsBeforeIteration,
@@ -75,6 +220,87 @@ impl Benchmark {
                 .peekable(),
         }
     }
+
+    /// Compare get_account_state to get_calculator_state. Both are served
+    /// from the cache. Both requests are made in the same iteration and the
+    /// order alternates, so both routes have identical load.
+    pub fn benchmark_cache_vs_database(state: BotState) -> Self {
+        let setup = [&Register as &dyn BotAction, &Login, &DoInitialSetupIfNeeded];
+        let benchmark = [
+            &ActionsBeforeIteration as &dyn BotAction,
+            &TimedGetAccountState,
+            &TimedGetCalculatorState,
+            &PrintReadPathComparison,
+            &ActionsBeforeIteration,
+            &TimedGetCalculatorState,
+            &TimedGetAccountState,
+            &PrintReadPathComparison,
+        ];
+        let iter = setup.into_iter().chain(benchmark.into_iter().cycle());
+        Self {
+            state,
+            actions: (Box::new(iter)
+                as Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>)
+                .peekable(),
+        }
+    }
+
+    /// Benchmark internal API routes which microservices call on every
+    /// request. Requests go directly to the account server internal API.
+    pub fn benchmark_internal_api(state: BotState) -> Self {
+        let setup = [&Register as &dyn BotAction, &Login, &DoInitialSetupIfNeeded];
+        let benchmark = [
+            &ActionsBeforeIteration as &dyn BotAction,
+            &TimedCheckApiKey,
+            &TimedInternalGetAccountState,
+            &PrintInternalApiBenchmark,
+        ];
+        let iter = setup.into_iter().chain(benchmark.into_iter().cycle());
+        Self {
+            state,
+            actions: (Box::new(iter)
+                as Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>)
+                .peekable(),
+        }
+    }
+
+    /// Update calculator state on every iteration. Compare write
+    /// throughput with the batch size which the write queue status
+    /// reports.
+    pub fn benchmark_update_calculator_state(state: BotState) -> Self {
+        let setup = [&Register as &dyn BotAction, &Login, &DoInitialSetupIfNeeded];
+        let benchmark = [
+            &ActionsBeforeIteration as &dyn BotAction,
+            &TimedUpdateCalculatorState,
+            &PrintUpdateCalculatorStateBenchmark,
+        ];
+        let iter = setup.into_iter().chain(benchmark.into_iter().cycle());
+        Self {
+            state,
+            actions: (Box::new(iter)
+                as Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>)
+                .peekable(),
+        }
+    }
+
+    /// Read or write calculator state on every iteration. Write ratio
+    /// selects the request randomly, so reads and writes are interleaved
+    /// like in real use.
+    pub fn benchmark_mixed(state: BotState) -> Self {
+        let setup = [&Register as &dyn BotAction, &Login, &DoInitialSetupIfNeeded];
+        let benchmark = [
+            &ActionsBeforeIteration as &dyn BotAction,
+            &TimedMixedRequest,
+            &PrintMixedBenchmark,
+        ];
+        let iter = setup.into_iter().chain(benchmark.into_iter().cycle());
+        Self {
+            state,
+            actions: (Box::new(iter)
+                as Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>)
+                .peekable(),
+        }
+    }
 }
 
 #[async_trait]
@@ -96,9 +322,15 @@ pub struct GetCalculatorState;
 #[async_trait]
 impl BotAction for GetCalculatorState {
     async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
-        get_calculator_state(state.api.calculator())
-            .await
-            .into_error(TestError::ApiRequest)?;
+        let start = Instant::now();
+        record_request(
+            "get_calculator_state",
+            start,
+            state
+                .api
+                .retry_idempotent(|| get_calculator_state(state.api.calculator()))
+                .await,
+        )?;
         Ok(())
     }
 }
@@ -156,11 +388,256 @@ impl BotAction for ActionsAfterIteration {
 
         if state.print_info() {
             info!(
-                "{:?}: {:?}, total: {}",
+                "{:?}: {:?}, total: {}, errors: {}",
                 state.previous_action,
                 state.benchmark.action_duration.elapsed(),
-                COUNTERS.reset_get_calculator_state()
+                COUNTERS.reset_get_calculator_state(),
+                API_ERRORS.reset(),
+            );
+            print_write_queue_status(state).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TimedGetAccountState;
+
+#[async_trait]
+impl BotAction for TimedGetAccountState {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let start = Instant::now();
+        let time = record_request(
+            "get_account_state",
+            start,
+            state
+                .api
+                .retry_idempotent(|| get_account_state(state.api.account()))
+                .await,
+        )?;
+        ACCOUNT_STATE_TIME.add(time);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TimedGetCalculatorState;
+
+#[async_trait]
+impl BotAction for TimedGetCalculatorState {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let start = Instant::now();
+        let time = record_request(
+            "get_calculator_state",
+            start,
+            state
+                .api
+                .retry_idempotent(|| get_calculator_state(state.api.calculator()))
+                .await,
+        )?;
+        CALCULATOR_STATE_TIME.add(time);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct PrintReadPathComparison;
+
+#[async_trait]
+impl BotAction for PrintReadPathComparison {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        if state.print_info() {
+            let (account_avg, account_count) = ACCOUNT_STATE_TIME.reset();
+            let (calculator_avg, calculator_count) = CALCULATOR_STATE_TIME.reset();
+            let delta = calculator_avg.as_micros() as i128 - account_avg.as_micros() as i128;
+            info!(
+                "get_account_state: {:?}, count: {}, get_calculator_state: {:?}, count: {}, delta: {} µs",
+                account_avg, account_count, calculator_avg, calculator_count, delta,
+            );
+            info!("errors: {}", API_ERRORS.reset());
+            print_write_queue_status(state).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TimedCheckApiKey;
+
+#[async_trait]
+impl BotAction for TimedCheckApiKey {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let api_key = state
+            .api
+            .api_key()
+            .ok_or(TestError::MissingValue)
+            .into_report()?;
+        let internal = state.api.internal()?;
+        let start = Instant::now();
+        let time = record_request(
+            "check_api_key",
+            start,
+            state
+                .api
+                .retry_idempotent(|| check_api_key(internal, ApiKey::new(api_key.clone())))
+                .await,
+        )?;
+        CHECK_API_KEY_TIME.add(time);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TimedInternalGetAccountState;
+
+#[async_trait]
+impl BotAction for TimedInternalGetAccountState {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let id = state.id_string()?;
+        let internal = state.api.internal()?;
+        let start = Instant::now();
+        let time = record_request(
+            "internal_get_account_state",
+            start,
+            state
+                .api
+                .retry_idempotent(|| internal_get_account_state(internal, &id))
+                .await,
+        )?;
+        INTERNAL_ACCOUNT_STATE_TIME.add(time);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct PrintInternalApiBenchmark;
+
+#[async_trait]
+impl BotAction for PrintInternalApiBenchmark {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        if state.print_info() {
+            let (check_avg, check_count) = CHECK_API_KEY_TIME.reset();
+            let (account_avg, account_count) = INTERNAL_ACCOUNT_STATE_TIME.reset();
+            info!(
+                "check_api_key: {:?}, count: {}, get_account_state: {:?}, count: {}, errors: {}",
+                check_avg,
+                check_count,
+                account_avg,
+                account_count,
+                API_ERRORS.reset(),
+            );
+            print_write_queue_status(state).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TimedUpdateCalculatorState;
+
+#[async_trait]
+impl BotAction for TimedUpdateCalculatorState {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let start = Instant::now();
+        let time = record_request(
+            "update_calculator_state",
+            start,
+            state
+                .api
+                .retry_idempotent(|| {
+                    post_calculator_state(
+                        state.api.calculator(),
+                        CalculatorState::new("0".to_string()),
+                    )
+                })
+                .await,
+        )?;
+        UPDATE_CALCULATOR_STATE_TIME.add(time);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct PrintUpdateCalculatorStateBenchmark;
+
+#[async_trait]
+impl BotAction for PrintUpdateCalculatorStateBenchmark {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        if state.print_info() {
+            let (update_avg, update_count) = UPDATE_CALCULATOR_STATE_TIME.reset();
+            info!(
+                "update_calculator_state: {:?}, count: {}, errors: {}",
+                update_avg,
+                update_count,
+                API_ERRORS.reset(),
+            );
+            print_write_queue_status(state).await;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TimedMixedRequest;
+
+#[async_trait]
+impl BotAction for TimedMixedRequest {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        let write = rand::thread_rng().gen_range(0..100) < state.config.write_ratio;
+        let start = Instant::now();
+        if write {
+            let time = record_request(
+                "mixed_write",
+                start,
+                state
+                    .api
+                    .retry_idempotent(|| {
+                        post_calculator_state(
+                            state.api.calculator(),
+                            CalculatorState::new("0".to_string()),
+                        )
+                    })
+                    .await,
+            )?;
+            MIXED_WRITE_TIME.add(time);
+        } else {
+            let time = record_request(
+                "mixed_read",
+                start,
+                state
+                    .api
+                    .retry_idempotent(|| get_calculator_state(state.api.calculator()))
+                    .await,
+            )?;
+            MIXED_READ_TIME.add(time);
+        }
+        COUNTERS.inc_mixed_requests();
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct PrintMixedBenchmark;
+
+#[async_trait]
+impl BotAction for PrintMixedBenchmark {
+    async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
+        if state.print_info() {
+            let (read_avg, read_count) = MIXED_READ_TIME.reset();
+            let (write_avg, write_count) = MIXED_WRITE_TIME.reset();
+            let requests = COUNTERS.reset_mixed_requests();
+            let elapsed = state.benchmark.throughput_start.elapsed();
+            state.benchmark.throughput_start = Instant::now();
+            info!(
+                "read: {:?}, count: {}, write: {:?}, count: {}, throughput: {:.0} requests/s, errors: {}",
+                read_avg,
+                read_count,
+                write_avg,
+                write_count,
+                requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+                API_ERRORS.reset(),
             );
+            print_write_queue_status(state).await;
         }
         Ok(())
     }
@@ -0,0 +1,224 @@
+//! Benchmark results for the report file
+//!
+//! Bots record the latency and the result of every benchmarked request.
+//! Latencies are stored in a histogram which has 16 buckets for every power
+//! of two microseconds, so percentiles are accurate to about 6%.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::test::{bot::utils::ApiErrorCounts, client::ApiErrorKind};
+
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    buckets: Vec<u64>,
+    count: u64,
+    max_micros: u64,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, time: Duration) {
+        let micros = time.as_micros().min(u64::MAX as u128) as u64;
+        let index = bucket_index(micros);
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.max_micros = self.max_micros.max(micros);
+    }
+
+    /// Returns lower bound of the bucket which contains the percentile.
+    fn percentile_micros(&self, percentile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let target = ((percentile / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return bucket_lower_bound(index);
+            }
+        }
+        self.max_micros
+    }
+}
+
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let shift = (63 - micros.leading_zeros()) - SUB_BUCKET_BITS;
+    let sub_bucket = (micros >> shift) - SUB_BUCKETS;
+    ((shift as u64 + 1) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+fn bucket_lower_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    (SUB_BUCKETS + index % SUB_BUCKETS) << shift
+}
+
+/// Request counts of one second of the benchmark.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct ThroughputSample {
+    /// Seconds since the first benchmarked request.
+    pub second: u64,
+    pub requests: u64,
+    pub errors: u64,
+}
+
+#[derive(Debug, Default)]
+struct ActionResults {
+    latency: LatencyHistogram,
+    errors: ApiErrorCounts,
+    throughput: Vec<ThroughputSample>,
+}
+
+#[derive(Debug)]
+struct ResultsData {
+    start: Instant,
+    actions: BTreeMap<&'static str, ActionResults>,
+}
+
+/// Benchmark results of all actions which multiple bots can update.
+#[derive(Debug)]
+pub struct BenchmarkResults {
+    data: Mutex<Option<ResultsData>>,
+}
+
+impl BenchmarkResults {
+    pub const fn new() -> Self {
+        Self {
+            data: Mutex::new(None),
+        }
+    }
+
+    /// Latency is recorded also for failed requests.
+    pub fn record(&self, action: &'static str, time: Duration, error: Option<ApiErrorKind>) {
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let data = data.get_or_insert_with(|| ResultsData {
+            start: Instant::now(),
+            actions: BTreeMap::new(),
+        });
+        let second = data.start.elapsed().as_secs();
+        let results = data.actions.entry(action).or_default();
+
+        results.latency.record(time);
+        if let Some(kind) = error {
+            results.errors.inc(kind);
+        }
+
+        while results.throughput.len() as u64 <= second {
+            results.throughput.push(ThroughputSample {
+                second: results.throughput.len() as u64,
+                ..ThroughputSample::default()
+            });
+        }
+        let sample = &mut results.throughput[second as usize];
+        sample.requests += 1;
+        if error.is_some() {
+            sample.errors += 1;
+        }
+    }
+
+    pub fn report(&self, test: &str) -> BenchmarkReport {
+        let data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let (duration, actions) = match data.as_ref() {
+            Some(data) => (
+                data.start.elapsed(),
+                data.actions
+                    .iter()
+                    .map(|(action, results)| ActionReport::new(action, results))
+                    .collect(),
+            ),
+            None => (Duration::ZERO, vec![]),
+        };
+        BenchmarkReport {
+            test: test.to_string(),
+            duration_seconds: duration.as_secs_f64(),
+            actions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub test: String,
+    /// Time from the first benchmarked request to the end of the run.
+    pub duration_seconds: f64,
+    pub actions: Vec<ActionReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionReport {
+    pub action: &'static str,
+    pub requests: u64,
+    pub p50_micros: u64,
+    pub p95_micros: u64,
+    pub p99_micros: u64,
+    pub max_micros: u64,
+    pub errors: ApiErrorCounts,
+    /// Last sample is for a partial second.
+    pub throughput: Vec<ThroughputSample>,
+}
+
+impl ActionReport {
+    fn new(action: &'static str, results: &ActionResults) -> Self {
+        Self {
+            action,
+            requests: results.latency.count,
+            p50_micros: results.latency.percentile_micros(50.0),
+            p95_micros: results.latency.percentile_micros(95.0),
+            p99_micros: results.latency.percentile_micros(99.0),
+            max_micros: results.latency.max_micros,
+            errors: results.errors,
+            throughput: results.throughput.clone(),
+        }
+    }
+}
+
+impl BenchmarkReport {
+    /// Row for every second of every action and a summary row for every
+    /// action. Summary row has `total` as the second and latency columns
+    /// are empty in other rows.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "action,second,requests,errors,p50_micros,p95_micros,p99_micros,max_micros\n",
+        );
+        for action in &self.actions {
+            for sample in &action.throughput {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},,,,",
+                    action.action, sample.second, sample.requests, sample.errors
+                );
+            }
+            let _ = writeln!(
+                csv,
+                "{},total,{},{},{},{},{},{}",
+                action.action,
+                action.requests,
+                action.errors.total(),
+                action.p50_micros,
+                action.p95_micros,
+                action.p99_micros,
+                action.max_micros,
+            );
+        }
+        csv
+    }
+}
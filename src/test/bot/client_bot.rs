@@ -11,28 +11,35 @@ use api_client::{
     models::AccountState,
 };
 use async_trait::async_trait;
+use rand::Rng;
 use tokio::time::sleep;
 
 use crate::{
     action_array,
     test::{
         bot::actions::{account::CompleteAccountSetup, ActionArray},
+        bot_script::{BotScript, BotScriptAction, BotScriptStep},
         client::TestError,
     },
 };
 
 use super::{
     actions::{
-        account::{AssertAccountState, Login, Register, SetAccountSetup},
-        BotAction, RunActions,
+        account::{
+            AssertAccountState, DisconnectWebSocket, Login, ReconnectWebSocket, RefreshTokens,
+            Register, SetAccountSetup,
+        },
+        calculator::{
+            AssertCalculatorStateUnchanged, Calculate, ChangeCalculatorState,
+            ChangeCalculatorStateUnique,
+        },
+        BotAction, RunActions, SleepMillis, APP_BACKGROUND_AND_FOREGROUND,
     },
     BotState, BotStruct, TaskState,
 };
 
 use error_stack::Result;
 
-use crate::utils::IntoReportExt;
-
 pub struct ClientBot {
     state: BotState,
     actions: Peekable<Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>>,
@@ -50,6 +57,7 @@ impl ClientBot {
         let benchmark = [
             &ActionsBeforeIteration as &dyn BotAction,
             &GetCalculatorState,
+            &MaybeMoveAppToBackground,
             &ActionsAfterIteration,
         ];
         let iter = setup.into_iter().chain(benchmark.into_iter().cycle());
@@ -60,6 +68,95 @@ impl ClientBot {
                 .peekable(),
         }
     }
+
+    /// Bot which runs the setup actions once and then repeats the other
+    /// actions. Create the actions with [script_actions].
+    pub fn with_script(state: BotState, (setup, actions): (ActionArray, ActionArray)) -> Self {
+        let iter = setup.iter().copied().chain(actions.iter().copied().cycle());
+        Self {
+            state,
+            actions: (Box::new(iter)
+                as Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>)
+                .peekable(),
+        }
+    }
+}
+
+/// Convert script steps to setup and repeated actions. Bot actions are
+/// static, so the actions are leaked. Create the actions once per task.
+pub fn script_actions(script: &BotScript) -> (ActionArray, ActionArray) {
+    let leak_steps = |steps: &[BotScriptStep]| -> ActionArray {
+        let actions: Vec<&'static dyn BotAction> = steps
+            .iter()
+            .map(|step| {
+                Box::leak(Box::new(ScriptStep {
+                    action: script_action(&step.action),
+                    repeat: step.repeat,
+                    sleep: step.sleep_millis.map(Duration::from_millis),
+                })) as &'static dyn BotAction
+            })
+            .collect();
+        Box::leak(actions.into_boxed_slice())
+    };
+    (leak_steps(&script.setup), leak_steps(&script.actions))
+}
+
+fn script_action(action: &BotScriptAction) -> &'static dyn BotAction {
+    fn leak(action: impl BotAction) -> &'static dyn BotAction {
+        Box::leak(Box::new(action))
+    }
+    fn leak_str(text: &str) -> &'static str {
+        Box::leak(text.to_string().into_boxed_str())
+    }
+
+    match action {
+        BotScriptAction::Register => &Register,
+        BotScriptAction::Login => &Login,
+        BotScriptAction::InitialSetup => &DoInitialSetupIfNeeded,
+        BotScriptAction::RefreshTokens => &RefreshTokens,
+        BotScriptAction::DisconnectWebSocket => &DisconnectWebSocket,
+        BotScriptAction::ReconnectWebSocket => &ReconnectWebSocket,
+        BotScriptAction::GetCalculatorState => &GetCalculatorState,
+        BotScriptAction::ChangeCalculatorState { state: None } => &ChangeCalculatorStateUnique,
+        BotScriptAction::ChangeCalculatorState { state: Some(state) } => {
+            leak(ChangeCalculatorState {
+                state: leak_str(state),
+            })
+        }
+        BotScriptAction::AssertCalculatorStateUnchanged => &AssertCalculatorStateUnchanged,
+        BotScriptAction::Calculate { expression } => leak(Calculate {
+            expression: leak_str(expression),
+        }),
+        BotScriptAction::AppBackgroundAndForeground => &RunActions(APP_BACKGROUND_AND_FOREGROUND),
+        BotScriptAction::MaybeMoveAppToBackground => &MaybeMoveAppToBackground,
+        BotScriptAction::Sleep { millis } => leak(SleepMillis(*millis)),
+    }
+}
+
+/// Script action with repeat count and sleep. Sleeps are done also when
+/// `no_sleep` is enabled, because the script defines the load profile.
+#[derive(Debug)]
+struct ScriptStep {
+    action: &'static dyn BotAction,
+    repeat: u32,
+    sleep: Option<Duration>,
+}
+
+#[async_trait]
+impl BotAction for ScriptStep {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        for _ in 0..self.repeat {
+            self.action.excecute(state, task_state).await?;
+            if let Some(sleep_time) = self.sleep {
+                sleep(sleep_time).await;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -81,9 +178,10 @@ pub struct GetCalculatorState;
 #[async_trait]
 impl BotAction for GetCalculatorState {
     async fn excecute_impl(&self, state: &mut BotState) -> Result<(), TestError> {
-        get_calculator_state(state.api.calculator())
-            .await
-            .into_error(TestError::ApiRequest)?;
+        state
+            .api
+            .retry_idempotent(|| get_calculator_state(state.api.calculator()))
+            .await?;
         Ok(())
     }
 }
@@ -98,9 +196,10 @@ impl BotAction for DoInitialSetupIfNeeded {
         state: &mut BotState,
         task_state: &mut TaskState,
     ) -> Result<(), TestError> {
-        let account_state = get_account_state(state.api.account())
-            .await
-            .into_error(TestError::ApiRequest)?;
+        let account_state = state
+            .api
+            .retry_idempotent(|| get_account_state(state.api.account()))
+            .await?;
 
         if account_state.state == AccountState::InitialSetup {
             const ACTIONS: ActionArray = action_array!(
@@ -117,6 +216,30 @@ impl BotAction for DoInitialSetupIfNeeded {
     }
 }
 
+/// Probability that the app moves to background during one iteration.
+const APP_BACKGROUND_PROBABILITY: f64 = 0.05;
+
+/// Move the app to background and back to foreground sometimes like a real
+/// mobile app.
+#[derive(Debug)]
+struct MaybeMoveAppToBackground;
+
+#[async_trait]
+impl BotAction for MaybeMoveAppToBackground {
+    async fn excecute_impl_task_state(
+        &self,
+        state: &mut BotState,
+        task_state: &mut TaskState,
+    ) -> Result<(), TestError> {
+        if rand::thread_rng().gen_bool(APP_BACKGROUND_PROBABILITY) {
+            RunActions(APP_BACKGROUND_AND_FOREGROUND)
+                .excecute_impl_task_state(state, task_state)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 struct ActionsBeforeIteration;
 
@@ -4,34 +4,164 @@
 pub mod account;
 pub mod calculator;
 pub mod common;
+pub mod microservice;
+pub mod openapi;
 
-use std::{fmt::Debug, iter::Peekable, sync::atomic::AtomicBool};
+use std::{fmt::Debug, iter::Peekable, time::Duration};
 
 use async_trait::async_trait;
+use tracing::{error, info};
 
-use self::{account::ACCOUNT_TESTS, calculator::CALCULATOR_TESTS, common::COMMON_TESTS};
+use self::{
+    account::ACCOUNT_TESTS, calculator::CALCULATOR_TESTS, common::COMMON_TESTS,
+    microservice::MICROSERVICE_TESTS, openapi::OPENAPI_TESTS,
+};
 
 use super::{actions::BotAction, BotState, BotStruct};
 
-pub type SingleTest = (&'static str, &'static [&'static [&'static dyn BotAction]]);
+use crate::config::args::TestMode;
 
+pub struct SingleTest {
+    pub name: &'static str,
+    /// Every action array is run by its own bot.
+    pub bots: &'static [&'static [&'static dyn BotAction]],
+    /// Overrides the default test timeout.
+    pub timeout: Option<Duration>,
+}
+
+/// Create QA test. Every action array is run by its own bot, so tests with
+/// multiple action arrays can test interaction between bots. Default test
+/// timeout can be overridden with `timeout_seconds = SECONDS` before the
+/// test name.
 #[macro_export]
 macro_rules! test {
-    ($s:expr,[ $( $actions:expr, )* ] ) => {
-        (
-            $s,
-            &[
-                &[   $( &($actions) as &dyn BotAction, )*    ]
-            ]
-        )
+    (timeout_seconds = $timeout:expr, $s:expr, $( [ $( $actions:expr, )* ] ),+ ) => {
+        SingleTest {
+            name: $s,
+            bots: &[
+                $( &[   $( &($actions) as &dyn BotAction, )*    ], )+
+            ],
+            timeout: Some(std::time::Duration::from_secs($timeout)),
+        }
+    };
+    ($s:expr, $( [ $( $actions:expr, )* ] ),+ ) => {
+        SingleTest {
+            name: $s,
+            bots: &[
+                $( &[   $( &($actions) as &dyn BotAction, )*    ], )+
+            ],
+            timeout: None,
+        }
     };
 }
 
 pub const ALL_QA_TESTS: &'static [&'static [SingleTest]] =
     &[ACCOUNT_TESTS, CALCULATOR_TESTS, COMMON_TESTS];
 
-pub fn test_count() -> usize {
-    ALL_QA_TESTS.iter().map(|tests| tests.len()).sum()
+/// Tests which only run when OpenAPI conformance testing is enabled.
+pub const OPENAPI_QA_TESTS: &'static [&'static [SingleTest]] = &[OPENAPI_TESTS];
+
+/// Tests which only run when the calculator server is a microservice.
+pub const MICROSERVICE_QA_TESTS: &'static [&'static [SingleTest]] = &[MICROSERVICE_TESTS];
+
+/// QA test groups which the config enables. With isolated QA tests every
+/// group is run with its own server instances.
+pub fn selected_test_groups(config: &TestMode) -> impl Iterator<Item = &'static [SingleTest]> {
+    let openapi_tests: &'static [&'static [SingleTest]] = if config.qa_openapi {
+        OPENAPI_QA_TESTS
+    } else {
+        &[]
+    };
+    let microservice_tests: &'static [&'static [SingleTest]] =
+        if config.server.microservice_calculator {
+            MICROSERVICE_QA_TESTS
+        } else {
+            &[]
+        };
+    ALL_QA_TESTS
+        .iter()
+        .chain(openapi_tests.iter())
+        .chain(microservice_tests.iter())
+        .copied()
+}
+
+/// Count of QA test groups which the config enables.
+pub fn group_count(config: &TestMode) -> usize {
+    selected_test_groups(config).count()
+}
+
+/// QA tests which the bot task runs. Tests of all selected groups are run if
+/// the group is not set. Tests are distributed to tasks in round-robin
+/// order, so every test is run by exactly one task and the result does not
+/// depend on task scheduling.
+pub fn tests_for_task(
+    config: &TestMode,
+    task_id: u32,
+    group: Option<usize>,
+) -> impl Iterator<Item = &'static SingleTest> {
+    let task_count = config.task_count.max(1) as usize;
+    selected_test_groups(config)
+        .enumerate()
+        .filter(move |(i, _)| group.map(|group| group == *i).unwrap_or(true))
+        .flat_map(|(_, tests)| tests.iter())
+        .enumerate()
+        .filter(move |(i, _)| i % task_count == task_id as usize)
+        .map(|(_, test)| test)
+}
+
+/// Count of bots which the QA tests of the task require.
+pub fn bot_count(config: &TestMode, task_id: u32, group: Option<usize>) -> usize {
+    tests_for_task(config, task_id, group)
+        .map(|test| test.bots.len())
+        .sum()
+}
+
+/// QA test results of one or more bot tasks.
+#[derive(Debug, Default)]
+pub struct QaReport {
+    pub test_count: usize,
+    pub failed: Vec<&'static str>,
+    /// Tests which were not run to the end because the test run was
+    /// stopped.
+    pub not_completed: Vec<&'static str>,
+}
+
+impl QaReport {
+    pub fn new(test_count: usize) -> Self {
+        Self {
+            test_count,
+            ..Self::default()
+        }
+    }
+
+    pub fn add_failed(&mut self, test_name: &'static str) {
+        if !self.failed.contains(&test_name) {
+            self.failed.push(test_name);
+        }
+    }
+
+    pub fn add_not_completed(&mut self, test_name: &'static str) {
+        if !self.failed.contains(&test_name) && !self.not_completed.contains(&test_name) {
+            self.not_completed.push(test_name);
+        }
+    }
+
+    pub fn merge(&mut self, other: QaReport) {
+        self.test_count += other.test_count;
+        self.failed.extend(other.failed);
+        self.not_completed.extend(other.not_completed);
+    }
+
+    pub fn print(&self) {
+        let passed = self.test_count - self.failed.len() - self.not_completed.len();
+        info!("QA tests: {}, passed: {}", self.test_count, passed);
+        for test in self.failed.iter() {
+            error!("QA test failed: {}", test);
+        }
+        for test in self.not_completed.iter() {
+            error!("QA test was not completed: {}", test);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -47,6 +177,8 @@ pub struct Qa {
     state: BotState,
     test_name: &'static str,
     actions: Peekable<Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>>,
+    /// Execution time which the actions can still use.
+    time_left: Duration,
 }
 
 impl Debug for Qa {
@@ -60,11 +192,13 @@ impl Qa {
         state: BotState,
         test_name: &'static str,
         actions: Box<dyn Iterator<Item = &'static dyn BotAction> + Send + Sync>,
+        timeout: Duration,
     ) -> Self {
         Self {
             state,
             test_name,
             actions: actions.peekable(),
+            time_left: timeout,
         }
     }
 }
@@ -85,4 +219,16 @@ impl BotStruct for Qa {
     }
 
     fn notify_task_bot_count_decreased(&mut self, _bot_count: usize) {}
+
+    fn qa_test_name(&self) -> Option<&'static str> {
+        Some(self.test_name)
+    }
+
+    fn time_left(&self) -> Option<Duration> {
+        Some(self.time_left)
+    }
+
+    fn use_time(&mut self, time: Duration) {
+        self.time_left = self.time_left.saturating_sub(time);
+    }
 }
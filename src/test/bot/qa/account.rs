@@ -1,11 +1,20 @@
-use api_client::models::AccountState;
+use api_client::models::{AccountSetupErrorCode, AccountState, EventToClient};
+use hyper::StatusCode;
 
-use crate::test::bot::actions::BotAction;
+use crate::test::{bot::actions::BotAction, client::ApiErrorKind};
 
 use super::{
     super::actions::{
-        account::{AssertAccountState, CompleteAccountSetup, Login, Register, SetAccountSetup},
-        AssertFailure,
+        account::{
+            AdminBanAccount, AdminUnbanAccount, AssertAccountSetupRejected, AssertAccountState,
+            AssertAccountTimesSet, AssertEventReceived, AssertNewSessionEventReceived,
+            AssertRefreshInvalidatesPreviousTokens, AssertRefreshTokenReuseRevokesSessions,
+            CancelAccountDeletion, CompleteAccountSetup, Login, LoginAgain,
+            LoginWithHttpTokenExchange, RefreshTokens, Register, RequestAccountDeletion,
+            SetAccountSetup, SetupRejection,
+        },
+        calculator::GetCalculatorState,
+        AssertApiError, AssertFailure, RunActions, TO_NORMAL_STATE,
     },
     SingleTest,
 };
@@ -21,6 +30,10 @@ pub const ACCOUNT_TESTS: &[SingleTest] = &[
             AssertAccountState(AccountState::InitialSetup),
         ]
     ),
+    test!(
+        "Account times: registration and login time are set after login",
+        [Register, Login, AssertAccountTimesSet,]
+    ),
     test!(
         "Initial setup: complete setup fails if no setup info is set",
         [
@@ -40,4 +53,191 @@ pub const ACCOUNT_TESTS: &[SingleTest] = &[
             AssertAccountState(AccountState::Normal),
         ]
     ),
+    test!(
+        "Initial setup: setup can not be changed after completing setup",
+        [
+            Register,
+            Login,
+            SetAccountSetup::new(),
+            CompleteAccountSetup,
+            AssertFailure(SetAccountSetup::new()),
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
+    test!(
+        "Initial setup: empty email is rejected",
+        [
+            Register,
+            Login,
+            AssertAccountSetupRejected {
+                email: String::new,
+                expected: SetupRejection::Invalid(AccountSetupErrorCode::EmailEmpty),
+            },
+            AssertFailure(CompleteAccountSetup),
+            AssertAccountState(AccountState::InitialSetup),
+        ]
+    ),
+    test!(
+        "Initial setup: too long email is rejected",
+        [
+            Register,
+            Login,
+            AssertAccountSetupRejected {
+                email: || format!("{}@example.com", "a".repeat(250)),
+                expected: SetupRejection::Invalid(AccountSetupErrorCode::EmailTooLong),
+            },
+            AssertAccountState(AccountState::InitialSetup),
+        ]
+    ),
+    test!(
+        "Initial setup: email without @ is rejected",
+        [
+            Register,
+            Login,
+            AssertAccountSetupRejected {
+                email: || "example.com".to_string(),
+                expected: SetupRejection::Invalid(AccountSetupErrorCode::EmailInvalid),
+            },
+            AssertAccountSetupRejected {
+                email: || "test @example.com".to_string(),
+                expected: SetupRejection::Invalid(AccountSetupErrorCode::EmailInvalid),
+            },
+            AssertAccountState(AccountState::InitialSetup),
+        ]
+    ),
+    test!(
+        "Initial setup: setup is rejected in normal state",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AssertAccountSetupRejected {
+                email: || "test@example.com".to_string(),
+                expected: SetupRejection::WrongState,
+            },
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
+    test!(
+        "Account deletion: state is pending deletion after request",
+        [
+            Register,
+            Login,
+            SetAccountSetup::new(),
+            CompleteAccountSetup,
+            RequestAccountDeletion,
+            AssertAccountState(AccountState::PendingDeletion),
+        ]
+    ),
+    test!(
+        "Account deletion: request fails during initial setup",
+        [
+            Register,
+            Login,
+            AssertFailure(RequestAccountDeletion),
+            AssertAccountState(AccountState::InitialSetup),
+        ]
+    ),
+    test!(
+        "Account deletion: cancel returns to normal state",
+        [
+            Register,
+            Login,
+            SetAccountSetup::new(),
+            CompleteAccountSetup,
+            RequestAccountDeletion,
+            CancelAccountDeletion,
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
+    test!(
+        "Account deletion: cancel fails in normal state",
+        [
+            Register,
+            Login,
+            SetAccountSetup::new(),
+            CompleteAccountSetup,
+            AssertFailure(CancelAccountDeletion),
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
+    test!(
+        "Access token: refreshing tokens with HTTP works",
+        [
+            RunActions(TO_NORMAL_STATE),
+            RefreshTokens,
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
+    test!(
+        "Access token: refreshing invalidates previous tokens",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AssertRefreshInvalidatesPreviousTokens,
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
+    test!(
+        "Access token: reusing replaced refresh token revokes sessions",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AssertRefreshTokenReuseRevokesSessions,
+        ]
+    ),
+    test!(
+        "Access token: HTTP token exchange works without WebSocket",
+        [
+            Register,
+            LoginWithHttpTokenExchange,
+            AssertAccountState(AccountState::InitialSetup),
+        ]
+    ),
+    test!(
+        "Events: account state change sends event",
+        [
+            RunActions(TO_NORMAL_STATE),
+            // Completing the setup sends the first event.
+            AssertEventReceived(EventToClient::AccountStateChanged),
+            RequestAccountDeletion,
+            AssertEventReceived(EventToClient::AccountStateChanged),
+            CancelAccountDeletion,
+            AssertEventReceived(EventToClient::AccountStateChanged),
+        ]
+    ),
+    test!(
+        "Events: login from another device sends new session event",
+        [RunActions(TO_NORMAL_STATE), AssertNewSessionEventReceived,]
+    ),
+    test!(
+        "Admin: ban logs out the account",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AdminBanAccount,
+            AssertApiError(
+                AssertAccountState(AccountState::Banned),
+                ApiErrorKind::Client(StatusCode::UNAUTHORIZED)
+            ),
+        ]
+    ),
+    test!(
+        "Admin: banned account can not use private routes",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AdminBanAccount,
+            LoginAgain,
+            AssertAccountState(AccountState::Banned),
+            AssertApiError(
+                GetCalculatorState,
+                ApiErrorKind::Client(StatusCode::FORBIDDEN)
+            ),
+        ]
+    ),
+    test!(
+        "Admin: unban returns to normal state",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AdminBanAccount,
+            AdminUnbanAccount,
+            LoginAgain,
+            AssertAccountState(AccountState::Normal),
+        ]
+    ),
 ];
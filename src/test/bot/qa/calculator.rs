@@ -1,33 +1,119 @@
-use crate::test::bot::actions::{
-    calculator::{ChangeCalculatorState, GetCalculatorState},
-    AssertEqualsFn, BotAction, RunActions, TO_NORMAL_STATE,
+use api_client::models::EventToClient;
+use hyper::StatusCode;
+
+use crate::test::{
+    bot::actions::{
+        account::{AssertEventReceived, ShareAccount, UseSharedAccount},
+        calculator::{
+            Calculate, ChangeCalculatorState, ConcurrentCalculatorStateWrites, GetCalculatorState,
+        },
+        AssertApiError, AssertEqualsFn, BotAction, ModifyTaskState, RunActions, SleepUntil,
+        TO_NORMAL_STATE,
+    },
+    client::ApiErrorKind,
 };
 
 use super::SingleTest;
 
 use crate::test;
 
-pub const CALCULATOR_TESTS: &[SingleTest] = &[test!(
-    "Calculator state: saving calculator state works multiple times",
-    [
-        RunActions(TO_NORMAL_STATE),
-        ChangeCalculatorState { state: "0" },
-        AssertEqualsFn(
-            |v, _| v.calculator_state().as_deref() == Some("0"),
-            true,
-            &GetCalculatorState
-        ),
-        ChangeCalculatorState { state: "1" },
-        AssertEqualsFn(
-            |v, _| v.calculator_state().as_deref() == Some("1"),
-            true,
-            &GetCalculatorState
-        ),
-        ChangeCalculatorState { state: "2" },
-        AssertEqualsFn(
-            |v, _| v.calculator_state().as_deref() == Some("2"),
-            true,
-            &GetCalculatorState
-        ),
-    ]
-)];
+const CONCURRENT_WRITES_ACCOUNT: &str = "calculator-concurrent-writes";
+const CONCURRENT_WRITES_SECOND_BOT_DONE: &str = "calculator-concurrent-writes-second-bot-done";
+const CONCURRENT_WRITES_FINAL_WRITTEN: &str = "calculator-concurrent-writes-final-written";
+const CONCURRENT_WRITES_FINISHED: &str = "calculator-concurrent-writes-finished";
+
+pub const CALCULATOR_TESTS: &[SingleTest] = &[
+    test!(
+        "Calculator state: saving calculator state works multiple times",
+        [
+            RunActions(TO_NORMAL_STATE),
+            ChangeCalculatorState { state: "0" },
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("0"),
+                true,
+                &GetCalculatorState
+            ),
+            ChangeCalculatorState { state: "1" },
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("1"),
+                true,
+                &GetCalculatorState
+            ),
+            ChangeCalculatorState { state: "2" },
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("2"),
+                true,
+                &GetCalculatorState
+            ),
+        ]
+    ),
+    test!(
+        "Calculator state: calculate saves formatted result as calculator state",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("9"),
+                true,
+                &Calculate {
+                    expression: "(1 + 2) * 3"
+                }
+            ),
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("9"),
+                true,
+                &GetCalculatorState
+            ),
+        ]
+    ),
+    test!(
+        "Calculator state: calculate with invalid expression fails",
+        [
+            RunActions(TO_NORMAL_STATE),
+            ChangeCalculatorState { state: "0" },
+            AssertApiError(
+                Calculate { expression: "1 +" },
+                ApiErrorKind::Client(StatusCode::BAD_REQUEST)
+            ),
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("0"),
+                true,
+                &GetCalculatorState
+            ),
+        ]
+    ),
+    test!(
+        timeout_seconds = 60,
+        "Calculator state: concurrent writes from two bots to the same account",
+        [
+            RunActions(TO_NORMAL_STATE),
+            ShareAccount(CONCURRENT_WRITES_ACCOUNT),
+            ConcurrentCalculatorStateWrites { writes: 10 },
+            SleepUntil(|s| s.is_signaled(CONCURRENT_WRITES_SECOND_BOT_DONE)),
+            ChangeCalculatorState { state: "final" },
+            ModifyTaskState(|s| s.signal(CONCURRENT_WRITES_FINAL_WRITTEN)),
+            // Keep WebSocket connection and access token valid until the
+            // second bot is done.
+            SleepUntil(|s| s.is_signaled(CONCURRENT_WRITES_FINISHED)),
+        ],
+        [
+            UseSharedAccount(CONCURRENT_WRITES_ACCOUNT),
+            ConcurrentCalculatorStateWrites { writes: 10 },
+            ModifyTaskState(|s| s.signal(CONCURRENT_WRITES_SECOND_BOT_DONE)),
+            SleepUntil(|s| s.is_signaled(CONCURRENT_WRITES_FINAL_WRITTEN)),
+            AssertEqualsFn(
+                |v, _| v.calculator_state().as_deref() == Some("final"),
+                true,
+                &GetCalculatorState
+            ),
+            ModifyTaskState(|s| s.signal(CONCURRENT_WRITES_FINISHED)),
+        ]
+    ),
+    test!(
+        "Events: calculator state change sends event",
+        [
+            RunActions(TO_NORMAL_STATE),
+            ChangeCalculatorState { state: "1" },
+            AssertEventReceived(EventToClient::CalculatorStateChanged),
+        ]
+    ),
+];
@@ -1,4 +1,7 @@
-use crate::test::bot::actions::{common::TestWebSocket, BotAction};
+use crate::test::bot::actions::{
+    common::{AssertWrongCertificatePinFails, TestWebSocket},
+    BotAction, RunActions, APP_BACKGROUND_AND_FOREGROUND, TO_NORMAL_STATE,
+};
 
 use super::{
     super::actions::account::{Login, Register},
@@ -7,7 +10,21 @@ use super::{
 
 use crate::test;
 
-pub const COMMON_TESTS: &[SingleTest] = &[test!(
-    "WebSocket HTTP connection works",
-    [Register, Login, TestWebSocket,]
-)];
+pub const COMMON_TESTS: &[SingleTest] = &[
+    test!(
+        "WebSocket HTTP connection works",
+        [Register, Login, TestWebSocket,]
+    ),
+    test!(
+        "App lifecycle: reconnecting after background keeps data",
+        [
+            RunActions(TO_NORMAL_STATE),
+            RunActions(APP_BACKGROUND_AND_FOREGROUND),
+            RunActions(APP_BACKGROUND_AND_FOREGROUND),
+        ]
+    ),
+    test!(
+        "TLS: request with wrong certificate pin fails",
+        [AssertWrongCertificatePinFails,]
+    ),
+];
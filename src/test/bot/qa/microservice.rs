@@ -0,0 +1,72 @@
+//! Tests for microservice mode where the calculator server checks access
+//! tokens and account state from the account server.
+
+use std::time::Duration;
+
+use hyper::StatusCode;
+
+use crate::test::{
+    bot::actions::{
+        account::{AdminBanAccount, CancelAccountDeletion, LoginAgain, RequestAccountDeletion},
+        calculator::GetCalculatorState,
+        AssertApiErrorWithin, BotAction, RunActions, TO_NORMAL_STATE,
+    },
+    client::ApiErrorKind,
+};
+
+use super::SingleTest;
+
+use crate::test;
+
+/// Time limit for account state changes to be visible on the calculator
+/// server.
+const STATE_PROPAGATION_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+pub const MICROSERVICE_TESTS: &[SingleTest] = &[
+    test!(
+        "Microservice: calculator rejects access token after account deletion request",
+        [
+            RunActions(TO_NORMAL_STATE),
+            GetCalculatorState,
+            RequestAccountDeletion,
+            AssertApiErrorWithin {
+                action: GetCalculatorState,
+                error: ApiErrorKind::Client(StatusCode::FORBIDDEN),
+                time_limit: STATE_PROPAGATION_TIME_LIMIT,
+            },
+        ]
+    ),
+    test!(
+        "Microservice: calculator rejects access token after ban",
+        [
+            RunActions(TO_NORMAL_STATE),
+            GetCalculatorState,
+            AdminBanAccount,
+            AssertApiErrorWithin {
+                action: GetCalculatorState,
+                error: ApiErrorKind::Client(StatusCode::UNAUTHORIZED),
+                time_limit: STATE_PROPAGATION_TIME_LIMIT,
+            },
+            LoginAgain,
+            AssertApiErrorWithin {
+                action: GetCalculatorState,
+                error: ApiErrorKind::Client(StatusCode::FORBIDDEN),
+                time_limit: STATE_PROPAGATION_TIME_LIMIT,
+            },
+        ]
+    ),
+    test!(
+        "Microservice: calculator accepts access token after account deletion is cancelled",
+        [
+            RunActions(TO_NORMAL_STATE),
+            RequestAccountDeletion,
+            AssertApiErrorWithin {
+                action: GetCalculatorState,
+                error: ApiErrorKind::Client(StatusCode::FORBIDDEN),
+                time_limit: STATE_PROPAGATION_TIME_LIMIT,
+            },
+            CancelAccountDeletion,
+            GetCalculatorState,
+        ]
+    ),
+];
@@ -0,0 +1,75 @@
+use hyper::{Method, StatusCode};
+
+use crate::{
+    api::{
+        account::{PATH_ACCOUNT_STATE, PATH_NOTIFICATION_SETTINGS, PATH_SESSION},
+        calculator::{
+            PATH_CALCULATOR_SETTINGS, PATH_GET_CALCULATION_SCHEDULES, PATH_GET_CALCULATOR_SLOTS,
+            PATH_GET_CALCULATOR_STATE, PATH_POST_CALCULATE, PATH_POST_CALCULATOR_STATE,
+        },
+    },
+    test::bot::actions::{
+        common::AssertOpenApiConformance, BotAction, RunActions, TO_NORMAL_STATE,
+    },
+};
+
+use super::SingleTest;
+
+use crate::test;
+
+pub const OPENAPI_TESTS: &[SingleTest] = &[
+    test!(
+        "OpenAPI: account API responses match the document",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AssertOpenApiConformance::get(PATH_ACCOUNT_STATE),
+            AssertOpenApiConformance::get(PATH_SESSION),
+            AssertOpenApiConformance::get(PATH_NOTIFICATION_SETTINGS),
+        ]
+    ),
+    test!(
+        "OpenAPI: calculator API responses match the document",
+        [
+            RunActions(TO_NORMAL_STATE),
+            AssertOpenApiConformance {
+                method: Method::POST,
+                path: PATH_POST_CALCULATOR_STATE,
+                body: Some(r#"{"state": "1"}"#),
+                status: StatusCode::OK,
+            },
+            AssertOpenApiConformance::get(PATH_GET_CALCULATOR_STATE),
+            AssertOpenApiConformance {
+                method: Method::POST,
+                path: PATH_POST_CALCULATE,
+                body: Some(r#"{"expression": "1 + 2"}"#),
+                status: StatusCode::OK,
+            },
+            AssertOpenApiConformance {
+                method: Method::POST,
+                path: PATH_POST_CALCULATE,
+                body: Some(r#"{"expression": "1 +"}"#),
+                status: StatusCode::BAD_REQUEST,
+            },
+            AssertOpenApiConformance::get(PATH_GET_CALCULATION_SCHEDULES),
+            AssertOpenApiConformance::get(PATH_GET_CALCULATOR_SLOTS),
+            AssertOpenApiConformance::get(PATH_CALCULATOR_SETTINGS),
+        ]
+    ),
+    test!(
+        "OpenAPI: unauthorized responses match the document",
+        [
+            AssertOpenApiConformance {
+                method: Method::GET,
+                path: PATH_ACCOUNT_STATE,
+                body: None,
+                status: StatusCode::UNAUTHORIZED,
+            },
+            AssertOpenApiConformance {
+                method: Method::GET,
+                path: PATH_GET_CALCULATOR_STATE,
+                body: None,
+                status: StatusCode::UNAUTHORIZED,
+            },
+        ]
+    ),
+];
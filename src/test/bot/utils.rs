@@ -6,15 +6,21 @@ use std::{
     time::{Duration, Instant},
 };
 
+use serde::Serialize;
+
+use crate::test::client::ApiErrorKind;
+
 #[derive(Default, Debug)]
 pub struct Counters {
     get_calculator_state: AtomicU64,
+    mixed_requests: AtomicU64,
 }
 
 impl Counters {
     pub const fn new() -> Self {
         Self {
             get_calculator_state: AtomicU64::new(0),
+            mixed_requests: AtomicU64::new(0),
         }
     }
 
@@ -25,6 +31,125 @@ impl Counters {
     pub fn reset_get_calculator_state(&self) -> u64 {
         self.get_calculator_state.swap(0, Ordering::Relaxed)
     }
+
+    /// Read and write requests of the mixed benchmark.
+    pub fn inc_mixed_requests(&self) {
+        self.mixed_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn reset_mixed_requests(&self) -> u64 {
+        self.mixed_requests.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// Request count and total request time which multiple bots can update.
+#[derive(Default, Debug)]
+pub struct DurationCounter {
+    total_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl DurationCounter {
+    pub const fn new() -> Self {
+        Self {
+            total_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn add(&self, time: Duration) {
+        self.total_micros
+            .fetch_add(time.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns average time and count since previous reset.
+    pub fn reset(&self) -> (Duration, u64) {
+        let total = self.total_micros.swap(0, Ordering::Relaxed);
+        let count = self.count.swap(0, Ordering::Relaxed);
+        let avg = if count == 0 { 0 } else { total / count };
+        (Duration::from_micros(avg), count)
+    }
+}
+
+/// API error counts by error type which multiple bots can update.
+#[derive(Default, Debug)]
+pub struct ApiErrorCounters {
+    connect: AtomicU64,
+    timeout: AtomicU64,
+    client: AtomicU64,
+    server: AtomicU64,
+    other: AtomicU64,
+}
+
+impl ApiErrorCounters {
+    pub const fn new() -> Self {
+        Self {
+            connect: AtomicU64::new(0),
+            timeout: AtomicU64::new(0),
+            client: AtomicU64::new(0),
+            server: AtomicU64::new(0),
+            other: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self, kind: ApiErrorKind) {
+        let counter = match kind {
+            ApiErrorKind::Connect => &self.connect,
+            ApiErrorKind::Timeout => &self.timeout,
+            ApiErrorKind::Client(_) => &self.client,
+            ApiErrorKind::Server(_) => &self.server,
+            ApiErrorKind::Other => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns error counts since previous reset.
+    pub fn reset(&self) -> ApiErrorCounts {
+        ApiErrorCounts {
+            connect: self.connect.swap(0, Ordering::Relaxed),
+            timeout: self.timeout.swap(0, Ordering::Relaxed),
+            client: self.client.swap(0, Ordering::Relaxed),
+            server: self.server.swap(0, Ordering::Relaxed),
+            other: self.other.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct ApiErrorCounts {
+    pub connect: u64,
+    pub timeout: u64,
+    pub client: u64,
+    pub server: u64,
+    pub other: u64,
+}
+
+impl ApiErrorCounts {
+    pub fn inc(&mut self, kind: ApiErrorKind) {
+        let counter = match kind {
+            ApiErrorKind::Connect => &mut self.connect,
+            ApiErrorKind::Timeout => &mut self.timeout,
+            ApiErrorKind::Client(_) => &mut self.client,
+            ApiErrorKind::Server(_) => &mut self.server,
+            ApiErrorKind::Other => &mut self.other,
+        };
+        *counter += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.connect + self.timeout + self.client + self.server + self.other
+    }
+}
+
+impl std::fmt::Display for ApiErrorCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "connect: {}, timeout: {}, 4xx: {}, 5xx: {}, other: {}",
+            self.connect, self.timeout, self.client, self.server, self.other
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,107 @@
+//! Bot scenarios from a script file
+//!
+//! Script maps to the bot actions of the `bot` test, so load profiles can be
+//! changed without recompiling. Setup steps are run once and then the
+//! action steps are repeated until the test run ends.
+
+use std::path::Path;
+
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::Deserialize;
+
+use crate::{config::GetConfigError, utils::IntoReportExt};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotScript {
+    /// Steps which are run once when the bot starts. Default is register,
+    /// login and initial setup if needed.
+    #[serde(default = "default_setup")]
+    pub setup: Vec<BotScriptStep>,
+    /// Steps which are repeated after the setup.
+    pub actions: Vec<BotScriptStep>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BotScriptStep {
+    #[serde(flatten)]
+    pub action: BotScriptAction,
+    /// How many times the action is run. Default is 1.
+    #[serde(default = "default_repeat")]
+    pub repeat: u32,
+    /// Sleep after every run of the action.
+    pub sleep_millis: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BotScriptAction {
+    Register,
+    Login,
+    /// Complete account setup if the account is in initial setup state.
+    InitialSetup,
+    RefreshTokens,
+    DisconnectWebSocket,
+    ReconnectWebSocket,
+    GetCalculatorState,
+    /// Unique state is saved if `state` is not set.
+    ChangeCalculatorState {
+        state: Option<String>,
+    },
+    /// Check that the state saved with the previous unique state change is
+    /// unchanged.
+    AssertCalculatorStateUnchanged,
+    Calculate {
+        expression: String,
+    },
+    /// Save unique calculator state, reconnect the WebSocket and check the
+    /// state.
+    AppBackgroundAndForeground,
+    /// Same as `AppBackgroundAndForeground` with a small probability.
+    MaybeMoveAppToBackground,
+    Sleep {
+        millis: u64,
+    },
+}
+
+fn default_setup() -> Vec<BotScriptStep> {
+    [
+        BotScriptAction::Register,
+        BotScriptAction::Login,
+        BotScriptAction::InitialSetup,
+    ]
+    .into_iter()
+    .map(|action| BotScriptStep {
+        action,
+        repeat: default_repeat(),
+        sleep_millis: None,
+    })
+    .collect()
+}
+
+fn default_repeat() -> u32 {
+    1
+}
+
+impl BotScript {
+    /// Load TOML file or JSON file if the file extension is `json`.
+    pub fn load(file: &Path) -> Result<Self, GetConfigError> {
+        let text = std::fs::read_to_string(file)
+            .into_error(GetConfigError::LoadBotScript)
+            .attach_printable_lazy(|| file.display().to_string())?;
+        let script: Self = if file.extension().map(|e| e == "json").unwrap_or(false) {
+            serde_json::from_str(&text).into_error(GetConfigError::LoadBotScript)
+        } else {
+            toml::from_str(&text).into_error(GetConfigError::LoadBotScript)
+        }
+        .attach_printable_lazy(|| file.display().to_string())?;
+
+        if script.actions.is_empty() {
+            return Err(GetConfigError::LoadBotScript)
+                .into_report()
+                .attach_printable("Bot script must have at least one action");
+        }
+
+        Ok(script)
+    }
+}
@@ -1,14 +1,26 @@
 //! Access REST API from Rust
 
-use std::fmt::Debug;
+use std::{fmt::Debug, future::Future, time::Duration};
 
 use api_client::apis::configuration::Configuration;
-use error_stack::{IntoReport, Result};
+use error_stack::{IntoReport, Result, ResultExt};
 
-use hyper::StatusCode;
+use hyper::{Method, StatusCode};
 use reqwest::{Client, Url};
+use serde_json::Value;
 use tracing::info;
 
+use crate::{
+    api::{
+        doc::API_DOC_FILE_NAME,
+        metrics::{ServerMetrics, PATH_INTERNAL_GET_METRICS},
+        utils::API_KEY_HEADER_STR,
+    },
+    utils::IntoReportExt,
+};
+
+use super::tls::CertificatePin;
+
 #[derive(thiserror::Error, Debug)]
 #[error("Wrong status code: {0}")]
 pub struct StatusCodeError(StatusCode);
@@ -48,10 +60,89 @@ pub enum TestError {
     #[error("Assert error. message: {0}")]
     AssertError(String),
 
+    #[error("Test timeout")]
+    Timeout,
+
     #[error("Not an error. Just an indication that bot is waiting.")]
     BotIsWaiting,
 }
 
+/// Classified API request failure. API request errors
+/// ([TestError::ApiRequest]) have this as an attachment, so assertions can
+/// check the exact failure with `report.downcast_ref::<ApiErrorKind>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    /// Connecting to the server failed.
+    Connect,
+    /// Request timed out.
+    Timeout,
+    /// Response status code is 4xx.
+    Client(StatusCode),
+    /// Response status code is 5xx.
+    Server(StatusCode),
+    /// Invalid response or other failure.
+    Other,
+}
+
+impl ApiErrorKind {
+    pub fn from_api_error<T>(error: &api_client::apis::Error<T>) -> Self {
+        match error {
+            api_client::apis::Error::Reqwest(e) if e.is_connect() => Self::Connect,
+            api_client::apis::Error::Reqwest(e) if e.is_timeout() => Self::Timeout,
+            api_client::apis::Error::ResponseError(response) => {
+                Self::from_status(response.status.as_u16())
+            }
+            _ => Self::Other,
+        }
+    }
+
+    fn from_status(status: u16) -> Self {
+        match StatusCode::from_u16(status) {
+            Ok(status) if status.is_client_error() => Self::Client(status),
+            Ok(status) if status.is_server_error() => Self::Server(status),
+            _ => Self::Other,
+        }
+    }
+
+    /// Failure might not happen again if the request is retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Connect | Self::Timeout | Self::Server(_))
+    }
+}
+
+impl std::fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect => write!(f, "Connect error"),
+            Self::Timeout => write!(f, "Timeout"),
+            Self::Client(status) => write!(f, "Client error, status: {}", status),
+            Self::Server(status) => write!(f, "Server error, status: {}", status),
+            Self::Other => write!(f, "Other error"),
+        }
+    }
+}
+
+pub trait IntoApiError<T> {
+    /// Convert API client error to [TestError::ApiRequest] with
+    /// [ApiErrorKind] attachment.
+    fn into_api_error(self) -> Result<T, TestError>;
+}
+
+impl<T, E: Debug + Send + Sync + 'static> IntoApiError<T>
+    for std::result::Result<T, api_client::apis::Error<E>>
+{
+    #[track_caller]
+    fn into_api_error(self) -> Result<T, TestError> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                let kind = ApiErrorKind::from_api_error(&e);
+                Err(e).into_error_with_info(TestError::ApiRequest, kind)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PublicApiUrls {
     /// Account API url for register and login API
@@ -70,25 +161,59 @@ impl PublicApiUrls {
     }
 }
 
+/// Delay before the first retry. Delay doubles for every retry.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
 #[derive(Debug)]
 pub struct ApiClient {
     /// Where Account API reqister and login is available
     register: Configuration,
     account: Configuration,
     calculator: Configuration,
+    /// Account server internal API. Only benchmarks use this.
+    internal: Option<Configuration>,
+    /// Retry count for idempotent requests.
+    retries: u32,
 }
 
 impl ApiClient {
-    pub fn new(base_urls: PublicApiUrls) -> Self {
-        let client = reqwest::Client::new();
+    /// Only the server certificate matching `certificate_pin` is accepted
+    /// if the pin is set.
+    pub fn new(base_urls: PublicApiUrls, certificate_pin: Option<&CertificatePin>) -> Self {
+        let client = match certificate_pin {
+            Some(pin) => reqwest::Client::builder()
+                .use_preconfigured_tls(pin.client_config())
+                .build()
+                .expect("Creating HTTP client with pinned certificate failed"),
+            None => reqwest::Client::new(),
+        };
 
         Self {
             register: Self::create_configuration(&client, base_urls.register_base_url.as_str()),
             account: Self::create_configuration(&client, base_urls.account_base_url.as_str()),
             calculator: Self::create_configuration(&client, base_urls.calculator_base_url.as_str()),
+            internal: None,
+            retries: 0,
         }
     }
 
+    /// Enable internal API requests. Internal API uses the same HTTP client,
+    /// so the certificate pin is also used for the internal API.
+    pub fn with_internal_api(mut self, base_url: &Url) -> Self {
+        self.internal = Some(Self::create_configuration(
+            &self.account.client,
+            base_url.as_str(),
+        ));
+        self
+    }
+
+    /// Retry idempotent requests which fail with a retryable error at most
+    /// `retries` times.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
     fn create_configuration(client: &Client, base_url: &str) -> Configuration {
         let path = base_url.trim_end_matches('/').to_string();
         Configuration {
@@ -102,6 +227,9 @@ impl ApiClient {
         info!("Register API base url: {}", self.register.base_path);
         info!("Account API base url: {}", self.account.base_path);
         info!("Calculator API base url: {}", self.calculator.base_path);
+        if let Some(internal) = &self.internal {
+            info!("Internal API base url: {}", internal.base_path);
+        }
     }
 
     pub fn register(&self) -> &Configuration {
@@ -116,6 +244,13 @@ impl ApiClient {
         &self.calculator
     }
 
+    pub fn internal(&self) -> Result<&Configuration, TestError> {
+        self.internal
+            .as_ref()
+            .ok_or(TestError::ApiUrlNotConfigured)
+            .into_report()
+    }
+
     pub fn set_access_token(&mut self, token: String) {
         let token = api_client::apis::configuration::ApiKey {
             prefix: None,
@@ -125,6 +260,11 @@ impl ApiClient {
         self.calculator.api_key = Some(token.clone());
     }
 
+    pub fn remove_access_token(&mut self) {
+        self.account.api_key = None;
+        self.calculator.api_key = None;
+    }
+
     pub fn is_access_token_available(&self) -> bool {
         self.account.api_key.is_some() && self.calculator.api_key.is_some()
     }
@@ -132,6 +272,151 @@ impl ApiClient {
     pub fn api_key(&self) -> Option<String> {
         self.account.api_key.clone().map(|k| k.key)
     }
+
+    /// Download the OpenAPI document which the server serves in debug mode.
+    /// Servers document only their own routes, so if the calculator server
+    /// is a separate server, paths of both documents are combined.
+    pub async fn openapi_document(&self) -> Result<Value, TestError> {
+        let mut doc = Self::download_openapi_document(&self.account).await?;
+        if self.calculator.base_path != self.account.base_path {
+            let calculator_doc = Self::download_openapi_document(&self.calculator).await?;
+            if let (Some(paths), Some(Value::Object(calculator_paths))) = (
+                doc.get_mut("paths").and_then(Value::as_object_mut),
+                calculator_doc.get("paths"),
+            ) {
+                for (path, item) in calculator_paths {
+                    paths.entry(path.clone()).or_insert_with(|| item.clone());
+                }
+            }
+        }
+        Ok(doc)
+    }
+
+    async fn download_openapi_document(configuration: &Configuration) -> Result<Value, TestError> {
+        let url = format!("{}/api-doc/{}", configuration.base_path, API_DOC_FILE_NAME);
+        let response = configuration
+            .client
+            .get(url)
+            .send()
+            .await
+            .into_report()
+            .change_context(TestError::Reqwest)?;
+        if !response.status().is_success() {
+            return Err(StatusCodeError(response.status()))
+                .into_report()
+                .change_context(TestError::StatusCode);
+        }
+        response
+            .json()
+            .await
+            .into_report()
+            .change_context(TestError::SerdeDeserialize)
+    }
+
+    /// Get server metrics from the internal API.
+    pub async fn server_metrics(&self) -> Result<ServerMetrics, TestError> {
+        let internal = self.internal()?;
+        let url = format!("{}{}", internal.base_path, PATH_INTERNAL_GET_METRICS);
+        let response = internal
+            .client
+            .get(url)
+            .send()
+            .await
+            .into_report()
+            .change_context(TestError::Reqwest)?;
+        if !response.status().is_success() {
+            return Err(StatusCodeError(response.status()))
+                .into_report()
+                .change_context(TestError::StatusCode);
+        }
+        response
+            .json()
+            .await
+            .into_report()
+            .change_context(TestError::SerdeDeserialize)
+    }
+
+    /// POST to an internal API route without request body.
+    pub async fn internal_post(&self, path: &str) -> Result<(), TestError> {
+        let internal = self.internal()?;
+        let response = internal
+            .client
+            .post(format!("{}{}", internal.base_path, path))
+            .send()
+            .await
+            .into_report()
+            .change_context(TestError::Reqwest)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(StatusCodeError(response.status()))
+                .into_report()
+                .change_context(TestError::StatusCode)
+        }
+    }
+
+    /// Request without the generated client, so that the response status
+    /// code and body are available as is. API is selected using the path
+    /// prefix and the access token is added if it is available.
+    pub async fn raw_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<(StatusCode, bytes::Bytes), TestError> {
+        let configuration = if path.starts_with("/calculator_api") {
+            &self.calculator
+        } else {
+            &self.account
+        };
+        let mut request = configuration
+            .client
+            .request(method, format!("{}{}", configuration.base_path, path));
+        if let Some(api_key) = &configuration.api_key {
+            request = request.header(API_KEY_HEADER_STR, api_key.key.clone());
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .into_report()
+            .change_context(TestError::Reqwest)?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .into_report()
+            .change_context(TestError::Reqwest)?;
+        Ok((status, body))
+    }
+
+    /// Run idempotent request like GET and retry it if it fails with a
+    /// retryable error.
+    pub async fn retry_idempotent<T, E, F, Fut>(&self, request: F) -> Result<T, TestError>
+    where
+        E: Debug + Send + Sync + 'static,
+        F: Fn() -> Fut,
+        Fut: Future<Output = std::result::Result<T, api_client::apis::Error<E>>>,
+    {
+        let mut delay = RETRY_DELAY;
+        let mut retries_left = self.retries;
+        loop {
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    let kind = ApiErrorKind::from_api_error(&e);
+                    if retries_left == 0 || !kind.is_retryable() {
+                        return Err(e).into_api_error();
+                    }
+                }
+            }
+            retries_left -= 1;
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
 }
 
 pub fn get_api_url(url: &Option<Url>) -> Result<Url, TestError> {
@@ -0,0 +1,267 @@
+//! Validate API responses against the served OpenAPI document
+//!
+//! Only the subset of OpenAPI schemas which utoipa generates is supported:
+//! references to component schemas, `allOf`, `oneOf`, `anyOf`, `nullable`,
+//! `enum` and the basic JSON types. Object properties which the schema does
+//! not document are reported, so that fields added to handler responses
+//! without updating the annotations are noticed.
+
+use hyper::{Method, StatusCode};
+use serde_json::Value;
+
+/// Maximum `$ref` depth. Prevents infinite recursion with recursive
+/// schemas.
+const MAX_SCHEMA_DEPTH: usize = 32;
+
+static NULL: Value = Value::Null;
+
+#[derive(Debug)]
+pub struct OpenApiValidator {
+    doc: Value,
+}
+
+impl OpenApiValidator {
+    pub fn new(doc: Value) -> Self {
+        Self { doc }
+    }
+
+    /// Check that the response status code is documented for the operation
+    /// and that the body matches the documented response schema. Returns
+    /// all found problems.
+    pub fn validate_response(
+        &self,
+        method: &Method,
+        path: &str,
+        status: StatusCode,
+        body: &[u8],
+    ) -> Result<(), Vec<String>> {
+        let operation = self
+            .find_operation(method, path)
+            .ok_or_else(|| vec![format!("{method} {path} is not documented")])?;
+
+        let responses = &operation["responses"];
+        let response = responses
+            .get(status.as_str())
+            .or_else(|| responses.get("default"))
+            .ok_or_else(|| {
+                vec![format!(
+                    "{method} {path}: status {status} is not documented"
+                )]
+            })?;
+        let response = self.resolve(response);
+
+        let mut problems = vec![];
+        match response["content"].get("application/json") {
+            Some(content) => match serde_json::from_slice::<Value>(body) {
+                Ok(value) => self.validate(&content["schema"], &value, "$", 0, &mut problems),
+                Err(e) => problems.push(format!("body is not valid JSON: {e}")),
+            },
+            None if !body.is_empty() => problems.push(format!(
+                "body is not documented, but response has body {}",
+                String::from_utf8_lossy(body)
+            )),
+            None => (),
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems
+                .into_iter()
+                .map(|p| format!("{method} {path} (status {status}): {p}"))
+                .collect())
+        }
+    }
+
+    /// Find operation with path template matching. Template segment
+    /// `{name}` matches any path segment. Exact path match is preferred.
+    fn find_operation(&self, method: &Method, path: &str) -> Option<&Value> {
+        let method = method.as_str().to_lowercase();
+        let paths = self.doc["paths"].as_object()?;
+        paths
+            .get(path)
+            .or_else(|| {
+                paths
+                    .iter()
+                    .find(|(template, _)| path_matches(template, path))
+                    .map(|(_, item)| item)
+            })
+            .and_then(|item| item.get(&method))
+    }
+
+    fn resolve<'a>(&'a self, value: &'a Value) -> &'a Value {
+        match value["$ref"].as_str() {
+            Some(reference) => reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.doc.pointer(pointer))
+                .unwrap_or(&NULL),
+            None => value,
+        }
+    }
+
+    fn validate(
+        &self,
+        schema: &Value,
+        value: &Value,
+        location: &str,
+        depth: usize,
+        problems: &mut Vec<String>,
+    ) {
+        if depth > MAX_SCHEMA_DEPTH {
+            problems.push(format!("{location}: schema is too deep"));
+            return;
+        }
+
+        if let Some(reference) = schema["$ref"].as_str() {
+            let resolved = self.resolve(schema);
+            if resolved.is_null() {
+                problems.push(format!("{location}: unknown schema reference {reference}"));
+            } else {
+                self.validate(resolved, value, location, depth + 1, problems);
+            }
+            return;
+        }
+
+        if value.is_null() && schema["nullable"].as_bool() == Some(true) {
+            return;
+        }
+
+        if let Some(schemas) = schema["allOf"].as_array() {
+            for s in schemas {
+                self.validate(s, value, location, depth + 1, problems);
+            }
+        }
+
+        if let Some(schemas) = schema["oneOf"].as_array() {
+            let matching = self.matching_schemas(schemas, value, location, depth);
+            if matching != 1 {
+                problems.push(format!(
+                    "{location}: value matches {matching} oneOf schemas, expected 1"
+                ));
+            }
+        }
+
+        if let Some(schemas) = schema["anyOf"].as_array() {
+            if self.matching_schemas(schemas, value, location, depth) == 0 {
+                problems.push(format!("{location}: value does not match any anyOf schema"));
+            }
+        }
+
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                problems.push(format!("{location}: {value} is not one of {values:?}"));
+            }
+        }
+
+        match schema["type"].as_str() {
+            Some("object") => self.validate_object(schema, value, location, depth, problems),
+            Some("array") => match value.as_array() {
+                Some(items) => {
+                    for (i, item) in items.iter().enumerate() {
+                        self.validate(
+                            &schema["items"],
+                            item,
+                            &format!("{location}[{i}]"),
+                            depth + 1,
+                            problems,
+                        );
+                    }
+                }
+                None => problems.push(format!("{location}: expected array, found {value}")),
+            },
+            Some("string") if !value.is_string() => {
+                problems.push(format!("{location}: expected string, found {value}"))
+            }
+            Some("integer") if !(value.is_i64() || value.is_u64()) => {
+                problems.push(format!("{location}: expected integer, found {value}"))
+            }
+            Some("number") if !value.is_number() => {
+                problems.push(format!("{location}: expected number, found {value}"))
+            }
+            Some("boolean") if !value.is_boolean() => {
+                problems.push(format!("{location}: expected boolean, found {value}"))
+            }
+            _ => (),
+        }
+    }
+
+    fn validate_object(
+        &self,
+        schema: &Value,
+        value: &Value,
+        location: &str,
+        depth: usize,
+        problems: &mut Vec<String>,
+    ) {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => {
+                problems.push(format!("{location}: expected object, found {value}"));
+                return;
+            }
+        };
+
+        if let Some(required) = schema["required"].as_array() {
+            for name in required.iter().filter_map(|name| name.as_str()) {
+                if !object.contains_key(name) {
+                    problems.push(format!("{location}: required property {name} is missing"));
+                }
+            }
+        }
+
+        let properties = schema["properties"].as_object();
+        for (name, property_value) in object {
+            let property_location = format!("{location}.{name}");
+            match (
+                properties.and_then(|p| p.get(name)),
+                &schema["additionalProperties"],
+            ) {
+                (Some(property_schema), _) => self.validate(
+                    property_schema,
+                    property_value,
+                    &property_location,
+                    depth + 1,
+                    problems,
+                ),
+                (None, Value::Bool(true)) => (),
+                (None, additional @ Value::Object(_)) => self.validate(
+                    additional,
+                    property_value,
+                    &property_location,
+                    depth + 1,
+                    problems,
+                ),
+                (None, _) if properties.is_some() => {
+                    problems.push(format!("{property_location}: property is not documented"))
+                }
+                (None, _) => (),
+            }
+        }
+    }
+
+    fn matching_schemas(
+        &self,
+        schemas: &[Value],
+        value: &Value,
+        location: &str,
+        depth: usize,
+    ) -> usize {
+        schemas
+            .iter()
+            .filter(|s| {
+                let mut problems = vec![];
+                self.validate(s, value, location, depth + 1, &mut problems);
+                problems.is_empty()
+            })
+            .count()
+    }
+}
+
+fn path_matches(template: &str, path: &str) -> bool {
+    let template_segments = template.split('/');
+    let path_segments = path.split('/');
+    template_segments.clone().count() == path_segments.clone().count()
+        && template_segments
+            .zip(path_segments)
+            .all(|(t, p)| (t.starts_with('{') && t.ends_with('}')) || t == p)
+}
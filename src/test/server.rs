@@ -2,7 +2,10 @@ use std::{env, net::SocketAddrV4, os::unix::process::CommandExt, path::PathBuf,
 
 use crate::config::{
     args::TestMode,
-    file::{Components, ConfigFile, ExternalServices, SocketConfig, CONFIG_FILE_NAME},
+    file::{
+        AccountConfig, Components, ConfigFile, ExternalServices, Limits, SocketConfig, TlsConfig,
+        CONFIG_FILE_NAME,
+    },
 };
 
 use nix::{sys::signal::Signal, unistd::Pid};
@@ -11,6 +14,8 @@ use tokio::process::Child;
 use tracing::info;
 
 pub const SERVER_INSTANCE_DIR_START: &str = "server_instance_";
+const TEST_CERT_FILE_NAME: &str = "test_server.cert";
+const TEST_KEY_FILE_NAME: &str = "test_server.key";
 
 pub struct ServerManager {
     servers: Vec<ServerInstance>,
@@ -37,15 +42,25 @@ impl ServerManager {
             &config.server.api_urls.calculator_base_url,
             "calculator server",
         );
+        check_host(
+            &config.server.internal_api_url,
+            "account server internal API",
+        );
 
         let account_port = config.server.api_urls.account_base_url.port().unwrap();
+        let account_internal_port = config.server.internal_api_url.port().unwrap();
         let calculator_port = config.server.api_urls.calculator_base_url.port().unwrap();
 
+        let mut calculator_internal_url = config.server.internal_api_url.clone();
+        calculator_internal_url
+            .set_port(Some(calculator_port + 1))
+            .unwrap();
         let external_services = Some(ExternalServices {
-            account_internal: format!("http://127.0.0.1:{}", account_port + 1)
-                .parse::<Url>()
-                .unwrap()
-                .into(),
+            account_internal: config.server.internal_api_url.clone().into(),
+            calculator_internal: config
+                .server
+                .microservice_calculator
+                .then(|| vec![calculator_internal_url]),
         });
 
         let localhost_ip = "127.0.0.1".parse().unwrap();
@@ -53,7 +68,7 @@ impl ServerManager {
         let account_config = new_config(
             &config,
             SocketAddrV4::new(localhost_ip, account_port),
-            SocketAddrV4::new(localhost_ip, account_port + 1),
+            SocketAddrV4::new(localhost_ip, account_internal_port),
             Components {
                 account: true,
                 calculator: !config.server.microservice_calculator,
@@ -87,7 +102,7 @@ impl ServerManager {
 }
 
 fn new_config(
-    _config: &TestMode,
+    config: &TestMode,
     public_api: SocketAddrV4,
     internal_api: SocketAddrV4,
     components: Components,
@@ -96,16 +111,53 @@ fn new_config(
     ConfigFile {
         debug: Some(true),
         components,
+        // Expiration is enabled to test access token refreshing.
+        account: Some(AccountConfig {
+            access_token_ttl_seconds: Some(60 * 60),
+            http_token_exchange: Some(true),
+            ..AccountConfig::default()
+        }),
+        calculator: None,
         database: crate::config::file::DatabaseConfig {
             dir: "database_dir".into(),
+            backend: None,
+            url: None,
         },
         socket: SocketConfig {
             public_api: public_api.into(),
             internal_api: internal_api.into(),
         },
         external_services,
+        api_key_cache: None,
         sign_in_with_google: None,
-        tls: None,
+        sign_in_with_apple: None,
+        tls: config.server.tls_certificate.as_ref().map(|_| TlsConfig {
+            public_api_cert: TEST_CERT_FILE_NAME.into(),
+            public_api_key: TEST_KEY_FILE_NAME.into(),
+            internal_api_cert: TEST_CERT_FILE_NAME.into(),
+            internal_api_key: TEST_KEY_FILE_NAME.into(),
+            internal_api_ca_cert: None,
+            internal_api_client_cert: None,
+            internal_api_client_key: None,
+        }),
+        hot_standby: None,
+        backup: None,
+        replication: None,
+        webhooks: None,
+        telemetry: None,
+        analytics: None,
+        tracing_sampling: None,
+        latency_budget: None,
+        logging: None,
+        rate_limit: None,
+        websocket: None,
+        limits: Limits::default(),
+        bind_access_token_to_ip: None,
+        geoip_database: None,
+        api_base_path: None,
+        cors_allowed_origins: None,
+        // QA tests move the time forward to test expiration.
+        mock_clock: Some(true),
     }
 }
 
@@ -128,6 +180,11 @@ impl ServerInstance {
         let config = toml::to_string_pretty(&config).unwrap();
         std::fs::write(dir.join(CONFIG_FILE_NAME), config).unwrap();
 
+        if let Some(certificate) = &args_config.server.tls_certificate {
+            std::fs::write(dir.join(TEST_CERT_FILE_NAME), &certificate.cert_pem).unwrap();
+            std::fs::write(dir.join(TEST_KEY_FILE_NAME), &certificate.key_pem).unwrap();
+        }
+
         let start_cmd = env::args().next().unwrap();
         let start_cmd = std::fs::canonicalize(&start_cmd).unwrap();
 
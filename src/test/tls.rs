@@ -0,0 +1,119 @@
+//! Self-signed server certificate for test servers and certificate pinning
+//! for bots
+//!
+//! Mobile clients pin the server certificate, so bots check only the
+//! SHA-256 fingerprint of the server certificate. Server name and
+//! certificate chain are not verified.
+
+use std::{sync::Arc, time::SystemTime};
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use tokio_rustls::rustls::{
+    self,
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, ClientConfig, ServerName,
+};
+
+/// SHA-256 fingerprint of DER encoded certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificatePin(Vec<u8>);
+
+impl CertificatePin {
+    pub fn from_der(certificate: &[u8]) -> Self {
+        Self(Sha256::digest(certificate).to_vec())
+    }
+
+    /// Parse hex encoded fingerprint. Colons between bytes are allowed.
+    pub fn from_hex(text: &str) -> Option<Self> {
+        let fingerprint = hex::decode(text.replace(':', "")).ok()?;
+        if fingerprint.len() == Sha256::output_size() {
+            Some(Self(fingerprint))
+        } else {
+            None
+        }
+    }
+
+    /// Pin which does not match any certificate.
+    pub fn wrong() -> Self {
+        Self(vec![0; Sha256::output_size()])
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    /// TLS client config for reqwest and WebSocket connections.
+    pub fn client_config(&self) -> ClientConfig {
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertificateVerifier {
+                pin: self.clone(),
+            }))
+            .with_no_client_auth()
+    }
+}
+
+struct PinnedCertificateVerifier {
+    pin: CertificatePin,
+}
+
+impl ServerCertVerifier for PinnedCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if CertificatePin::from_der(&end_entity.0) == self.pin {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "Server certificate does not match the pinned certificate".to_string(),
+            ))
+        }
+    }
+}
+
+/// Self-signed certificate which test servers use.
+#[derive(Debug, Clone)]
+pub struct TestCertificate {
+    pub cert_pem: String,
+    pub key_pem: String,
+    pub pin: CertificatePin,
+}
+
+impl TestCertificate {
+    pub fn generate() -> Self {
+        let certificate = rcgen::generate_simple_self_signed(vec![
+            "localhost".to_string(),
+            "127.0.0.1".to_string(),
+        ])
+        .expect("Test certificate generation failed");
+        // Signature is different every time the certificate is serialized,
+        // so serialize only once.
+        let der = certificate
+            .serialize_der()
+            .expect("Test certificate serialization failed");
+
+        Self {
+            cert_pem: certificate_pem(&der),
+            key_pem: certificate.serialize_private_key_pem(),
+            pin: CertificatePin::from_der(&der),
+        }
+    }
+}
+
+fn certificate_pem(der: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
+}